@@ -69,6 +69,11 @@ typedef enum {
    * This format follows the extensions/conventions of the Neo4j [GraphML module](https://neo4j.com/docs/labs/apoc/current/import/graphml/).
    */
   GraphML,
+  /**
+   * [CoNLL-U](https://universaldependencies.org/format.html) dependency treebanks, either a single file or a
+   * directory with one file per document.
+   */
+  CoNLLU,
 } AnnisImportFormat;
 
 /**
@@ -99,6 +104,8 @@ typedef enum {
 
 /**
  * Defines the order of results of a `find` query.
+ *
+ * The random order with a seed is not part of this enumeration, use `annis_cs_find_seeded(...)` instead.
  */
 typedef enum {
   /**
@@ -120,6 +127,36 @@ typedef enum {
   NotSorted,
 } AnnisResultOrder;
 
+/**
+ * Defines how string arguments that are not valid UTF-8 are handled.
+ */
+typedef enum {
+  /**
+   * Invalid byte sequences are replaced with the Unicode replacement character.
+   */
+  Lossy,
+  /**
+   * Invalid byte sequences are reported as error by all functions that have an error list argument.
+   */
+  Strict,
+} AnnisUtf8Policy;
+
+/**
+ * Defines how the text between two tokens is reconstructed.
+ */
+typedef enum {
+  /**
+   * Use the `annis::tok-whitespace-after` and `annis::tok-whitespace-before` annotations of the tokens
+   * to reproduce the original spacing of the text. Tokens without any of these annotations are separated
+   * by a single space.
+   */
+  Original,
+  /**
+   * Always separate the tokens by a single space.
+   */
+  SingleSpace,
+} AnnisWhitespaceHandling;
+
 /**
  * An annotation with a qualified name and a value.
  */
@@ -141,6 +178,11 @@ typedef struct AnnisCorpusStorage AnnisCorpusStorage;
 
 typedef struct AnnisDiskMap_u64__UpdateEvent AnnisDiskMap_u64__UpdateEvent;
 
+/**
+ * Number of bytes the different parts of a corpus use on disk.
+ */
+typedef struct AnnisDiskUsage AnnisDiskUsage;
+
 /**
  * A representation of a graph including node annotations and edges.
  * Edges are partioned into components and each component is implemented by specialized graph storage implementation.
@@ -153,6 +195,15 @@ typedef struct AnnisGraph_AnnotationComponentType AnnisGraph_AnnotationComponent
 
 typedef struct AnnisIterPtr_NodeID AnnisIterPtr_NodeID;
 
+/**
+ * A handle to a loaded corpus that is not removed from the corpus cache while the handle exists.
+ *
+ * Use [CorpusStorage::pin(...)](struct.CorpusStorage.html#method.pin) to acquire it.
+ * All read-only functions of the corpus storage can be used on the pinned corpus without the risk of
+ * reloading it from disk in between. The corpus is unpinned when the handle is dropped.
+ */
+typedef struct AnnisPinnedCorpus AnnisPinnedCorpus;
+
 typedef struct AnnisVec_Annotation AnnisVec_Annotation;
 
 typedef struct AnnisVec_AnnotationComponent AnnisVec_AnnotationComponent;
@@ -165,6 +216,8 @@ typedef struct AnnisVec_Error AnnisVec_Error;
 
 typedef struct AnnisVec_FrequencyTableRow_CString AnnisVec_FrequencyTableRow_CString;
 
+typedef struct AnnisVec_Option_NodeID AnnisVec_Option_NodeID;
+
 typedef struct AnnisVec_QueryAttributeDescription AnnisVec_QueryAttributeDescription;
 
 typedef struct AnnisVec_Vec_CString AnnisVec_Vec_CString;
@@ -173,10 +226,14 @@ typedef AnnisComponent_AnnotationComponentType AnnisAnnotationComponent;
 
 /**
  * A list of changes to apply to an graph.
+ *
+ * The events are held in memory until their estimated size exceeds a limit and are then spilled to temporary
+ * files on disk, so even corpus-scale updates can be built without exhausting the main memory.
  */
 typedef struct {
   AnnisDiskMap_u64__UpdateEvent diffs;
   uint64_t event_counter;
+  uint64_t serialized_size;
 } AnnisGraphUpdate;
 
 /**
@@ -226,21 +283,59 @@ typedef struct {
   AnnisNodeID target;
 } AnnisEdge;
 
+/**
+ * A read-only view on a string that is owned by another object.
+ *
+ * The string is UTF-8 encoded and **not** necessarily terminated by a 0 byte, use the `len` field instead.
+ * The view is only valid as long as the object owning the string has not been freed or modified.
+ */
+typedef struct {
+  /**
+   * Pointer to the first byte of the string.
+   */
+  const char *data;
+  /**
+   * Number of bytes of the string.
+   */
+  size_t len;
+} AnnisStrRef;
+
 /**
  * Get the name of the given annotation object.
  */
 char *annis_annotation_name(const AnnisAnnotation *ptr);
 
+/**
+ * Get a view on the name of the given annotation object without copying it.
+ *
+ * The view is valid as long as the annotation is not freed.
+ */
+AnnisStrRef annis_annotation_name_ref(const AnnisAnnotation *ptr);
+
 /**
  * Get the namespace of the given annotation object.
  */
 char *annis_annotation_ns(const AnnisAnnotation *ptr);
 
+/**
+ * Get a view on the namespace of the given annotation object without copying it.
+ *
+ * The view is valid as long as the annotation is not freed.
+ */
+AnnisStrRef annis_annotation_ns_ref(const AnnisAnnotation *ptr);
+
 /**
  * Get the value of the given annotation object.
  */
 char *annis_annotation_val(const AnnisAnnotation *ptr);
 
+/**
+ * Get a view on the value of the given annotation object without copying it.
+ *
+ * The view is valid as long as the annotation is not freed.
+ */
+AnnisStrRef annis_annotation_val_ref(const AnnisAnnotation *ptr);
+
 /**
  * Get the layer of the given component.
  *
@@ -314,6 +409,23 @@ AnnisCountExtra annis_cs_count_extra(const AnnisCorpusStorage *ptr,
                                      AnnisQueryLanguage query_language,
                                      AnnisErrorList **err);
 
+/**
+ * Reconstruct the text covered by the given nodes.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `corpus_name` - The name of the corpus the nodes belong to.
+ * - `node_names` - The node annotation identifiers of the nodes.
+ * - `whitespace` - Defines how the text between two tokens is reconstructed.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ *
+ * Returns a vector with the same size as `node_names`. The text is empty for unknown nodes.
+ */
+AnnisVec_CString *annis_cs_covered_text(const AnnisCorpusStorage *ptr,
+                                        const char *corpus_name,
+                                        const AnnisVec_CString *node_names,
+                                        AnnisWhitespaceHandling whitespace,
+                                        AnnisErrorList **err);
+
 /**
  * Delete a corpus from this corpus storage.
  * Returns `true` if the corpus was successfully deleted and `false` if no such corpus existed.
@@ -323,6 +435,16 @@ AnnisCountExtra annis_cs_count_extra(const AnnisCorpusStorage *ptr,
  */
 bool annis_cs_delete(AnnisCorpusStorage *ptr, const char *corpus, AnnisErrorList **err);
 
+/**
+ * Returns the number of bytes the different parts of the corpus given by `corpus_name` use on disk.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+AnnisDiskUsage *annis_cs_disk_usage(const AnnisCorpusStorage *ptr,
+                                    const char *corpus_name,
+                                    AnnisErrorList **err);
+
 /**
  * Find all results for a `query` and return the match ID for each result.
  *
@@ -349,6 +471,21 @@ AnnisVec_CString *annis_cs_find(const AnnisCorpusStorage *ptr,
                                 AnnisResultOrder order,
                                 AnnisErrorList **err);
 
+/**
+ * Find all results for a `query` in a random order, which is stable for the given `seed`.
+ *
+ * Executing the same query on the same corpus with the same seed will always result in the same order.
+ * The parameters and the result are the same as for `annis_cs_find(...)`.
+ */
+AnnisVec_CString *annis_cs_find_seeded(const AnnisCorpusStorage *ptr,
+                                       const AnnisVec_CString *corpus_names,
+                                       const char *query,
+                                       AnnisQueryLanguage query_language,
+                                       size_t offset,
+                                       const size_t *limit,
+                                       uint64_t seed,
+                                       AnnisErrorList **err);
+
 /**
  * Frees the reference to the corpus storage object.
  * - `ptr` - The corpus storage object.
@@ -362,10 +499,12 @@ void annis_cs_free(AnnisCorpusStorage *ptr);
  * - `corpus_names` - The name of the corpora to execute the query on.
  * - `query` - The query as string.
  * - `query_language` The query language of the query (e.g. AQL).
- * - `frequency_query_definition` - A string representation of the list of frequency query definitions.
+ * - `frequency_query_definition` - A comma-separated list of frequency query definitions, where each definition
+ *    consists of the query node reference and the annotation name, e.g. `1:pos,2:tiger::lemma`.
  * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
  *
- * Returns a frequency table of strings.
+ * Returns a frequency table of strings, which must be freed with `annis_freqtable_str_free`.
+ * An invalid entry in the frequency query definition is reported as error.
  */
 AnnisFrequencyTable_CString *annis_cs_frequency(const AnnisCorpusStorage *ptr,
                                                 const AnnisVec_CString *corpus_names,
@@ -413,6 +552,30 @@ AnnisVec_AnnotationComponent *annis_cs_list_components_by_type(AnnisCorpusStorag
                                                                const char *corpus_name,
                                                                AnnisAnnotationComponentType ctype);
 
+/**
+ * Returns the metadata annotations (namespace, name and value) of the top-level corpus node of the corpus given by `corpus_name`.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `corpus_name` - The name of the corpus.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+AnnisMatrix_CString *annis_cs_list_corpus_metadata(const AnnisCorpusStorage *ptr,
+                                                   const char *corpus_name,
+                                                   AnnisErrorList **err);
+
+/**
+ * Returns the metadata annotations (namespace, name and value) of a document or sub-corpus.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `corpus_name` - The name of the corpus.
+ * - `document` - The path of the document (including the corpus name).
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+AnnisMatrix_CString *annis_cs_list_document_metadata(const AnnisCorpusStorage *ptr,
+                                                     const char *corpus_name,
+                                                     const char *document,
+                                                     AnnisErrorList **err);
+
 /**
  * Returns a list of all edge annotations of a corpus given by `corpus_name` and the component.
  *
@@ -458,6 +621,36 @@ AnnisVec_QueryAttributeDescription *annis_cs_node_descriptions(const AnnisCorpus
                                                                AnnisQueryLanguage query_language,
                                                                AnnisErrorList **err);
 
+/**
+ * Resolve a list of node names to the internal node IDs of a corpus.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `corpus_name` - The name of the corpus the nodes belong to.
+ * - `node_names` - The node annotation identifiers to resolve.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ *
+ * Returns a vector with the same size as `node_names`. Use `annis_vec_nodeid_get(...)` to get the node ID for each position.
+ */
+AnnisVec_Option_NodeID *annis_cs_node_ids_from_names(const AnnisCorpusStorage *ptr,
+                                                     const char *corpus_name,
+                                                     const AnnisVec_CString *node_names,
+                                                     AnnisErrorList **err);
+
+/**
+ * Pins a corpus in the cache, so it is not removed from the cache until the returned handle is released
+ * with `annis_cs_unpin(...)`.
+ *
+ * Use this for a sequence of read-only calls (e.g. find, subgraph and covered text) on the same corpus to avoid
+ * that the corpus is reloaded from disk in between, without having to preload the whole corpus.
+ *
+ * - `ptr` - The corpus storage object.
+ * - `corpus_name` - The name of the corpus to pin.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+AnnisPinnedCorpus *annis_cs_pin(const AnnisCorpusStorage *ptr,
+                                const char *corpus_name,
+                                AnnisErrorList **err);
+
 /**
  * Return the copy of a subgraph which includes all nodes that belong to any of the given list of sub-corpus/document identifiers.
  *
@@ -528,6 +721,12 @@ AnnisAnnotationGraph *annis_cs_subgraph_for_query_with_ctype(const AnnisCorpusSt
  */
 void annis_cs_unload(AnnisCorpusStorage *ptr, const char *corpus);
 
+/**
+ * Releases a pinned corpus handle, after which the corpus can be removed from the cache again.
+ * - `ptr` - The pinned corpus handle returned by `annis_cs_pin(...)`.
+ */
+void annis_cs_unpin(AnnisPinnedCorpus *ptr);
+
 /**
  * Parses a `query` and checks if it is valid.
  *
@@ -572,6 +771,46 @@ AnnisCorpusStorage *annis_cs_with_max_cache_size(const char *db_dir,
                                                  bool use_parallel_joins,
                                                  AnnisErrorList **err);
 
+/**
+ * Get the number of bytes used on disk by the backup of the corpus.
+ */
+uint64_t annis_diskusage_backup(const AnnisDiskUsage *ptr);
+
+/**
+ * Get the number of bytes used on disk by the component at position `i` of the disk usage report.
+ */
+uint64_t annis_diskusage_component_bytes(const AnnisDiskUsage *ptr, size_t i);
+
+/**
+ * Get a read-only reference to the component at position `i` of the disk usage report.
+ */
+const AnnisAnnotationComponent *annis_diskusage_component_get(const AnnisDiskUsage *ptr, size_t i);
+
+/**
+ * Returns the number of components in the disk usage report.
+ */
+size_t annis_diskusage_components_size(const AnnisDiskUsage *ptr);
+
+/**
+ * Get the number of bytes used on disk by the linked files.
+ */
+uint64_t annis_diskusage_linked_files(const AnnisDiskUsage *ptr);
+
+/**
+ * Get the number of bytes used on disk by the node annotations.
+ */
+uint64_t annis_diskusage_node_annotations(const AnnisDiskUsage *ptr);
+
+/**
+ * Get the total number of bytes used on disk.
+ */
+uint64_t annis_diskusage_total(const AnnisDiskUsage *ptr);
+
+/**
+ * Get the number of bytes used on disk by the write-ahead log of updates.
+ */
+uint64_t annis_diskusage_update_log(const AnnisDiskUsage *ptr);
+
 /**
  * Get the kind or type for the error at position `i` in the list.
  */
@@ -597,11 +836,28 @@ void annis_free(void *ptr);
  */
 size_t annis_freqtable_str_count(const AnnisFrequencyTable_CString *ptr, size_t row);
 
+/**
+ * Frees the frequency table given as `ptr` argument, including all its rows and strings.
+ *
+ * Use this function instead of `annis_free` for frequency tables, because the generic function does not know the
+ * type of the object and only releases the memory of the table itself.
+ */
+void annis_freqtable_str_free(AnnisFrequencyTable_CString *ptr);
+
 /**
  * Get a read-only reference to the string at the at position (`row`, `col`) of the frequency table.
  */
 const char *annis_freqtable_str_get(const AnnisFrequencyTable_CString *ptr, size_t row, size_t col);
 
+/**
+ * Get a view on the string at the at position (`row`, `col`) of the frequency table without copying it.
+ *
+ * The view is valid as long as the frequency table is not freed. If the position is out of range, the view is empty and its `data` pointer is `NULL`.
+ */
+AnnisStrRef annis_freqtable_str_get_ref(const AnnisFrequencyTable_CString *ptr,
+                                        size_t row,
+                                        size_t col);
+
 /**
  * Returns the number of columns of the frequency table.
  */
@@ -723,6 +979,26 @@ void annis_graphupdate_add_node_label(AnnisGraphUpdate *ptr,
                                       const char *anno_value,
                                       AnnisErrorList **err);
 
+/**
+ * Add "add node label value" action to the graph update object.
+ *
+ * In contrast to `annis_graphupdate_add_node_label`, the existing values of the node for the same
+ * annotation are kept and the value is added as an additional value.
+ *
+ * - `ptr` - The graph update object.
+ * - `node_name` - Name of the node the label is attached to.
+ * - `annos_ns` - Namespace of the annotation.
+ * - `annos_name` - Name of the annotation.
+ * - `annos_value` - Additional value of the annotation.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+void annis_graphupdate_add_node_label_value(AnnisGraphUpdate *ptr,
+                                            const char *node_name,
+                                            const char *anno_ns,
+                                            const char *anno_name,
+                                            const char *anno_value,
+                                            AnnisErrorList **err);
+
 /**
  * Add "delete edge" action to the graph update object.
  *
@@ -791,11 +1067,30 @@ void annis_graphupdate_delete_node_label(AnnisGraphUpdate *ptr,
                                          const char *anno_name,
                                          AnnisErrorList **err);
 
+/**
+ * Returns the estimated number of bytes of the events of the graph update object that are currently held in memory.
+ */
+size_t annis_graphupdate_memory_size_estimate(const AnnisGraphUpdate *ptr);
+
 /**
  * Create a new graph (empty) update instance
  */
 AnnisGraphUpdate *annis_graphupdate_new(void);
 
+/**
+ * Returns the number of bytes needed to serialize the graph update object without compression.
+ */
+uint64_t annis_graphupdate_serialized_size_estimate(const AnnisGraphUpdate *ptr);
+
+/**
+ * Create a new graph (empty) update instance, that holds at most `max_bytes` of events in memory
+ * before spilling them to disk. If `max_bytes` is 0, all events are kept in memory.
+ *
+ * - `max_bytes` - Maximum estimated size of the events in memory.
+ * - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+ */
+AnnisGraphUpdate *annis_graphupdate_with_memory_limit(size_t max_bytes, AnnisErrorList **err);
+
 /**
  * Initialize the logging of this library.
  *
@@ -826,6 +1121,14 @@ size_t annis_matrix_str_ncols(const AnnisMatrix_CString *ptr);
  */
 size_t annis_matrix_str_nrows(const AnnisMatrix_CString *ptr);
 
+/**
+ * Set the policy on how string arguments that are not valid UTF-8 are handled by the API functions.
+ *
+ * The default policy is `Lossy`.
+ * Functions without an error list argument always replace invalid byte sequences, regardless of the policy.
+ */
+void annis_set_utf8_policy(AnnisUtf8Policy policy);
+
 /**
  * Frees the string given as `s` argument.
  */
@@ -862,6 +1165,18 @@ const AnnisEdge *annis_vec_edge_get(const AnnisVec_Edge *ptr, size_t i);
  */
 size_t annis_vec_edge_size(const AnnisVec_Edge *ptr);
 
+/**
+ * Get a read-only reference to the node ID at position `i` of the vector.
+ *
+ * Returns `NULL` if there is no node ID at this position, e.g. because a node name could not be resolved.
+ */
+const AnnisNodeID *annis_vec_nodeid_get(const AnnisVec_Option_NodeID *ptr, size_t i);
+
+/**
+ * Returns the number of elements of the node ID vector.
+ */
+size_t annis_vec_nodeid_size(const AnnisVec_Option_NodeID *ptr);
+
 /**
  * Create a string representing the annotation name part of the query attribute description.
  *
@@ -899,6 +1214,13 @@ size_t annis_vec_qattdesc_size(const AnnisVec_QueryAttributeDescription *ptr);
  */
 const char *annis_vec_str_get(const AnnisVec_CString *ptr, size_t i);
 
+/**
+ * Get a view on the string at position `i` of the vector without copying it.
+ *
+ * The view is valid as long as the vector is not freed. If `i` is out of range, the view is empty and its `data` pointer is `NULL`.
+ */
+AnnisStrRef annis_vec_str_get_ref(const AnnisVec_CString *ptr, size_t i);
+
 /**
  * Create a new string vector.
  */
@@ -1,12 +1,14 @@
 use super::cerror;
 use super::cerror::ErrorList;
 use super::Matrix;
-use super::{cast_const, cast_mut, cstr, map_cerr};
+use super::{cast_const, cast_mut, cstr, cstr_checked, cstring_vec_checked, map_cerr};
 use graphannis::{
     corpusstorage::{
-        CacheStrategy, CountExtra, FrequencyDefEntry, FrequencyTable, FrequencyTableRow,
-        ImportFormat, QueryAttributeDescription, QueryLanguage, ResultOrder, SearchQuery,
+        CacheStrategy, CountExtra, DiskUsage, FrequencyDefEntry, FrequencyTable, FrequencyTableRow,
+        ImportFormat, PinnedCorpus, QueryAttributeDescription, QueryLanguage, ResultOrder,
+        SearchQuery, WhitespaceHandling,
     },
+    graph::{Annotation, NodeID},
     model::{AnnotationComponent, AnnotationComponentType},
     update::GraphUpdate,
     AnnotationGraph, CorpusStorage,
@@ -32,7 +34,10 @@ pub unsafe extern "C" fn annis_cs_with_auto_cache_size(
     use_parallel_joins: bool,
     err: *mut *mut ErrorList,
 ) -> *mut CorpusStorage {
-    let db_dir = cstr(db_dir);
+    let db_dir = match cstr_checked(db_dir, err) {
+        Some(db_dir) => db_dir,
+        None => return std::ptr::null_mut(),
+    };
 
     let db_dir_path = PathBuf::from(String::from(db_dir));
 
@@ -66,7 +71,10 @@ pub unsafe extern "C" fn annis_cs_with_max_cache_size(
     use_parallel_joins: bool,
     err: *mut *mut ErrorList,
 ) -> *mut CorpusStorage {
-    let db_dir = cstr(db_dir);
+    let db_dir = match cstr_checked(db_dir, err) {
+        Some(db_dir) => db_dir,
+        None => return std::ptr::null_mut(),
+    };
 
     let db_dir_path = PathBuf::from(String::from(db_dir));
 
@@ -120,17 +128,21 @@ pub extern "C" fn annis_cs_count(
 ) -> u64 {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
-    let corpus_names: Vec<String> = cast_const(corpus_names)
-        .iter()
-        .map(|cn| String::from(cn.to_string_lossy()))
-        .collect();
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return 0,
+    };
+    let corpus_names = match cstring_vec_checked(corpus_names, err) {
+        Some(corpus_names) => corpus_names,
+        None => return 0,
+    };
 
     let search_query = SearchQuery {
         query: &query,
         corpus_names: &corpus_names,
         query_language,
         timeout: None,
+        document_filter: Vec::new(),
     };
 
     map_cerr(cs.count(search_query), err).unwrap_or(0)
@@ -153,21 +165,53 @@ pub extern "C" fn annis_cs_count_extra(
 ) -> CountExtra {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
-    let corpus_names: Vec<String> = cast_const(corpus_names)
-        .iter()
-        .map(|cn| String::from(cn.to_string_lossy()))
-        .collect();
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return CountExtra::default(),
+    };
+    let corpus_names = match cstring_vec_checked(corpus_names, err) {
+        Some(corpus_names) => corpus_names,
+        None => return CountExtra::default(),
+    };
 
     let search_query = SearchQuery {
         query: &query,
         corpus_names: &corpus_names,
         query_language,
         timeout: None,
+        document_filter: Vec::new(),
     };
     map_cerr(cs.count_extra(search_query), err).unwrap_or_default()
 }
 
+/// Defines the order of results of a `find` query.
+///
+/// The random order with a seed is not part of this enumeration, use `annis_cs_find_seeded(...)` instead.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum CResultOrder {
+    /// Order results by their document name and the the text position of the match.
+    Normal,
+    /// Inverted the order of `Normal`.
+    Inverted,
+    /// A random ordering which is **not stable**. Each new query will result in a different order.
+    Randomized,
+    /// Results are not ordered at all, but also not actively randomized
+    /// Each new query *might* result in a different order.
+    NotSorted,
+}
+
+impl From<CResultOrder> for ResultOrder {
+    fn from(order: CResultOrder) -> Self {
+        match order {
+            CResultOrder::Normal => ResultOrder::Normal,
+            CResultOrder::Inverted => ResultOrder::Inverted,
+            CResultOrder::Randomized => ResultOrder::Randomized,
+            CResultOrder::NotSorted => ResultOrder::NotSorted,
+        }
+    }
+}
+
 /// Find all results for a `query` and return the match ID for each result.
 ///
 /// The query is paginated and an offset and limit can be specified.
@@ -189,6 +233,60 @@ pub extern "C" fn annis_cs_count_extra(
 /// This functions dereferences the `err` pointer and is therefore unsafe.
 #[no_mangle]
 pub unsafe extern "C" fn annis_cs_find(
+    ptr: *const CorpusStorage,
+    corpus_names: *const Vec<CString>,
+    query: *const libc::c_char,
+    query_language: QueryLanguage,
+    offset: libc::size_t,
+    limit: *const libc::size_t,
+    order: CResultOrder,
+    err: *mut *mut ErrorList,
+) -> *mut Vec<CString> {
+    find(
+        ptr,
+        corpus_names,
+        query,
+        query_language,
+        offset,
+        limit,
+        order.into(),
+        err,
+    )
+}
+
+/// Find all results for a `query` in a random order, which is stable for the given `seed`.
+///
+/// Executing the same query on the same corpus with the same seed will always result in the same order.
+/// The parameters and the result are the same as for `annis_cs_find(...)`.
+///
+/// # Safety
+///
+/// This functions dereferences the `err` pointer and is therefore unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn annis_cs_find_seeded(
+    ptr: *const CorpusStorage,
+    corpus_names: *const Vec<CString>,
+    query: *const libc::c_char,
+    query_language: QueryLanguage,
+    offset: libc::size_t,
+    limit: *const libc::size_t,
+    seed: u64,
+    err: *mut *mut ErrorList,
+) -> *mut Vec<CString> {
+    find(
+        ptr,
+        corpus_names,
+        query,
+        query_language,
+        offset,
+        limit,
+        ResultOrder::Seeded(seed),
+        err,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn find(
     ptr: *const CorpusStorage,
     corpus_names: *const Vec<CString>,
     query: *const libc::c_char,
@@ -200,17 +298,21 @@ pub unsafe extern "C" fn annis_cs_find(
 ) -> *mut Vec<CString> {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
-    let corpus_names: Vec<String> = cast_const(corpus_names)
-        .iter()
-        .map(|cn| String::from(cn.to_string_lossy()))
-        .collect();
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return std::ptr::null_mut(),
+    };
+    let corpus_names = match cstring_vec_checked(corpus_names, err) {
+        Some(corpus_names) => corpus_names,
+        None => return std::ptr::null_mut(),
+    };
 
     let search_query = SearchQuery {
         query: &query,
         corpus_names: &corpus_names,
         query_language,
         timeout: None,
+        document_filter: Vec::new(),
     };
 
     let limit = if limit.is_null() { None } else { Some(*limit) };
@@ -251,16 +353,22 @@ pub extern "C" fn annis_cs_subgraph(
     err: *mut *mut ErrorList,
 ) -> *mut AnnotationGraph {
     let cs: &CorpusStorage = cast_const(ptr);
-    let node_ids: Vec<String> = cast_const(node_ids)
-        .iter()
-        .map(|id| String::from(id.to_string_lossy()))
-        .collect();
-    let corpus = cstr(corpus_name);
+    let node_ids = match cstring_vec_checked(node_ids, err) {
+        Some(node_ids) => node_ids,
+        None => return std::ptr::null_mut(),
+    };
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
 
     let segmentation = if segmentation.is_null() {
         None
     } else {
-        Some(cstr(segmentation).to_string())
+        match cstr_checked(segmentation, err) {
+            Some(segmentation) => Some(segmentation.to_string()),
+            None => return std::ptr::null_mut(),
+        }
     };
 
     map_cerr(
@@ -289,11 +397,14 @@ pub extern "C" fn annis_cs_subcorpus_graph(
     err: *mut *mut ErrorList,
 ) -> *mut AnnotationGraph {
     let cs: &CorpusStorage = cast_const(ptr);
-    let corpus_ids: Vec<String> = cast_const(corpus_ids)
-        .iter()
-        .map(|id| String::from(id.to_string_lossy()))
-        .collect();
-    let corpus = cstr(corpus_name);
+    let corpus_ids = match cstring_vec_checked(corpus_ids, err) {
+        Some(corpus_ids) => corpus_ids,
+        None => return std::ptr::null_mut(),
+    };
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
 
     map_cerr(cs.subcorpus_graph(&corpus, corpus_ids), err)
         .map(|result| Box::into_raw(Box::new(result)))
@@ -311,7 +422,10 @@ pub extern "C" fn annis_cs_corpus_graph(
     err: *mut *mut ErrorList,
 ) -> *mut AnnotationGraph {
     let cs: &CorpusStorage = cast_const(ptr);
-    let corpus = cstr(corpus_name);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
 
     map_cerr(cs.corpus_graph(&corpus), err)
         .map(|result| Box::into_raw(Box::new(result)))
@@ -334,8 +448,14 @@ pub extern "C" fn annis_cs_subgraph_for_query(
     err: *mut *mut ErrorList,
 ) -> *mut AnnotationGraph {
     let cs: &CorpusStorage = cast_const(ptr);
-    let corpus = cstr(corpus_name);
-    let query = cstr(query);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return std::ptr::null_mut(),
+    };
 
     map_cerr(
         cs.subgraph_for_query(&corpus, &query, query_language, None),
@@ -363,8 +483,14 @@ pub extern "C" fn annis_cs_subgraph_for_query_with_ctype(
     err: *mut *mut ErrorList,
 ) -> *mut AnnotationGraph {
     let cs: &CorpusStorage = cast_const(ptr);
-    let corpus = cstr(corpus_name);
-    let query = cstr(query);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return std::ptr::null_mut(),
+    };
 
     map_cerr(
         cs.subgraph_for_query(&corpus, &query, query_language, Some(component_type_filter)),
@@ -380,10 +506,12 @@ pub extern "C" fn annis_cs_subgraph_for_query_with_ctype(
 /// - `corpus_names` - The name of the corpora to execute the query on.
 /// - `query` - The query as string.
 /// - `query_language` The query language of the query (e.g. AQL).
-/// - `frequency_query_definition` - A string representation of the list of frequency query definitions.
+/// - `frequency_query_definition` - A comma-separated list of frequency query definitions, where each definition
+///    consists of the query node reference and the annotation name, e.g. `1:pos,2:tiger::lemma`.
 /// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
 ///
-/// Returns a frequency table of strings.
+/// Returns a frequency table of strings, which must be freed with `annis_freqtable_str_free`.
+/// An invalid entry in the frequency query definition is reported as error.
 #[no_mangle]
 pub extern "C" fn annis_cs_frequency(
     ptr: *const CorpusStorage,
@@ -395,24 +523,35 @@ pub extern "C" fn annis_cs_frequency(
 ) -> *mut FrequencyTable<CString> {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
-    let corpus_names: Vec<String> = cast_const(corpus_names)
-        .iter()
-        .map(|cn| String::from(cn.to_string_lossy()))
-        .collect();
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return std::ptr::null_mut(),
+    };
+    let corpus_names = match cstring_vec_checked(corpus_names, err) {
+        Some(corpus_names) => corpus_names,
+        None => return std::ptr::null_mut(),
+    };
 
     let search_query = SearchQuery {
         query: &query,
         corpus_names: &corpus_names,
         query_language,
         timeout: None,
+        document_filter: Vec::new(),
     };
 
-    let frequency_query_definition = cstr(frequency_query_definition);
-    let table_def: Vec<FrequencyDefEntry> = frequency_query_definition
+    let frequency_query_definition = match cstr_checked(frequency_query_definition, err) {
+        Some(def) => def,
+        None => return std::ptr::null_mut(),
+    };
+    let table_def: Result<Vec<FrequencyDefEntry>, _> = frequency_query_definition
         .split(',')
-        .filter_map(|d| -> Option<FrequencyDefEntry> { d.parse().ok() })
+        .map(|d| d.parse())
         .collect();
+    let table_def = match map_cerr(table_def, err) {
+        Some(table_def) => table_def,
+        None => return std::ptr::null_mut(),
+    };
 
     match map_cerr(cs.frequency(search_query, table_def), err) {
         Some(orig_ft) => {
@@ -494,6 +633,70 @@ pub extern "C" fn annis_cs_list_node_annotations(
     Box::into_raw(Box::new(result))
 }
 
+fn annotations_to_matrix(annos: Vec<Annotation>) -> Matrix<CString> {
+    let mut result: Matrix<CString> = Matrix::new();
+    for anno in annos.into_iter() {
+        if let (Ok(ns), Ok(name), Ok(val)) = (
+            CString::new(anno.key.ns.as_str()),
+            CString::new(anno.key.name.as_str()),
+            CString::new(anno.val.as_str()),
+        ) {
+            result.push(vec![ns, name, val]);
+        }
+    }
+    result
+}
+
+/// Returns the metadata annotations (namespace, name and value) of the top-level corpus node of the corpus given by `corpus_name`.
+///
+/// - `ptr` - The corpus storage object.
+/// - `corpus_name` - The name of the corpus.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_cs_list_corpus_metadata(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    err: *mut *mut ErrorList,
+) -> *mut Matrix<CString> {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.list_corpus_metadata(&corpus), err)
+        .map(|annos| Box::into_raw(Box::new(annotations_to_matrix(annos))))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
+/// Returns the metadata annotations (namespace, name and value) of a document or sub-corpus.
+///
+/// - `ptr` - The corpus storage object.
+/// - `corpus_name` - The name of the corpus.
+/// - `document` - The path of the document (including the corpus name).
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_cs_list_document_metadata(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    document: *const libc::c_char,
+    err: *mut *mut ErrorList,
+) -> *mut Matrix<CString> {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+    let document = match cstr_checked(document, err) {
+        Some(document) => document,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.list_document_metadata(&corpus, &document), err)
+        .map(|annos| Box::into_raw(Box::new(annotations_to_matrix(annos))))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
 /// Returns a list of all edge annotations of a corpus given by `corpus_name` and the component.
 ///
 /// - `ptr` - The corpus storage object.
@@ -555,11 +758,14 @@ pub extern "C" fn annis_cs_validate_query(
 ) -> bool {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
-    let corpus_names: Vec<String> = cast_const(corpus_names)
-        .iter()
-        .map(|cn| String::from(cn.to_string_lossy()))
-        .collect();
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return false,
+    };
+    let corpus_names = match cstring_vec_checked(corpus_names, err) {
+        Some(corpus_names) => corpus_names,
+        None => return false,
+    };
 
     map_cerr(
         cs.validate_query(&corpus_names, &query, query_language),
@@ -583,13 +789,46 @@ pub extern "C" fn annis_cs_node_descriptions(
 ) -> *mut Vec<QueryAttributeDescription> {
     let cs: &CorpusStorage = cast_const(ptr);
 
-    let query = cstr(query);
+    let query = match cstr_checked(query, err) {
+        Some(query) => query,
+        None => return std::ptr::null_mut(),
+    };
 
     map_cerr(cs.node_descriptions(&query, query_language), err)
         .map(|result| Box::into_raw(Box::new(result)))
         .unwrap_or_else(std::ptr::null_mut)
 }
 
+/// Resolve a list of node names to the internal node IDs of a corpus.
+///
+/// - `ptr` - The corpus storage object.
+/// - `corpus_name` - The name of the corpus the nodes belong to.
+/// - `node_names` - The node annotation identifiers to resolve.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+///
+/// Returns a vector with the same size as `node_names`. Use `annis_vec_nodeid_get(...)` to get the node ID for each position.
+#[no_mangle]
+pub extern "C" fn annis_cs_node_ids_from_names(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    node_names: *const Vec<CString>,
+    err: *mut *mut ErrorList,
+) -> *mut Vec<Option<NodeID>> {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+    let node_names = match cstring_vec_checked(node_names, err) {
+        Some(node_names) => node_names,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.node_ids_from_names(&corpus, &node_names), err)
+        .map(|result| Box::into_raw(Box::new(result)))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
 /// Import a corpus from an external location on the file system into this corpus storage.
 ///
 /// - `ptr` - The corpus storage object.
@@ -616,12 +855,18 @@ pub extern "C" fn annis_cs_import_from_fs(
     let override_corpus_name: Option<String> = if corpus_name.is_null() {
         None
     } else {
-        Some(String::from(cstr(corpus_name)))
+        match cstr_checked(corpus_name, err) {
+            Some(corpus_name) => Some(String::from(corpus_name)),
+            None => return std::ptr::null_mut(),
+        }
+    };
+    let path = match cstr_checked(path, err) {
+        Some(path) => path,
+        None => return std::ptr::null_mut(),
     };
-    let path: &str = &cstr(path);
     map_cerr(
         cs.import_from_fs(
-            &PathBuf::from(path),
+            &PathBuf::from(path.as_ref()),
             format,
             override_corpus_name,
             disk_based,
@@ -654,6 +899,27 @@ pub extern "C" fn annis_cs_list_components_by_type(
     Box::into_raw(Box::new(cs.list_components(&corpus, Some(ctype), None)))
 }
 
+/// Returns the number of bytes the different parts of the corpus given by `corpus_name` use on disk.
+///
+/// - `ptr` - The corpus storage object.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_cs_disk_usage(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    err: *mut *mut ErrorList,
+) -> *mut DiskUsage {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.disk_usage(&corpus), err)
+        .map(|result| Box::into_raw(Box::new(result)))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
 /// Delete a corpus from this corpus storage.
 /// Returns `true` if the corpus was successfully deleted and `false` if no such corpus existed.
 ///
@@ -666,11 +932,93 @@ pub extern "C" fn annis_cs_delete(
     err: *mut *mut ErrorList,
 ) -> bool {
     let cs: &mut CorpusStorage = cast_mut(ptr);
-    let corpus = cstr(corpus);
+    let corpus = match cstr_checked(corpus, err) {
+        Some(corpus) => corpus,
+        None => return false,
+    };
 
     map_cerr(cs.delete(&corpus), err).unwrap_or(false)
 }
 
+/// Pins a corpus in the cache, so it is not removed from the cache until the returned handle is released
+/// with `annis_cs_unpin(...)`.
+///
+/// Use this for a sequence of read-only calls (e.g. find, subgraph and covered text) on the same corpus to avoid
+/// that the corpus is reloaded from disk in between, without having to preload the whole corpus.
+///
+/// - `ptr` - The corpus storage object.
+/// - `corpus_name` - The name of the corpus to pin.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_cs_pin(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    err: *mut *mut ErrorList,
+) -> *mut PinnedCorpus {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.pin(&corpus), err)
+        .map(|result| Box::into_raw(Box::new(result)))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
+/// Releases a pinned corpus handle, after which the corpus can be removed from the cache again.
+/// - `ptr` - The pinned corpus handle returned by `annis_cs_pin(...)`.
+///
+/// # Safety
+///
+/// This functions dereferences the pointer given as argument and is therefore unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn annis_cs_unpin(ptr: *mut PinnedCorpus) {
+    if ptr.is_null() {
+        return;
+    }
+    // take ownership and destroy the pointer
+    Box::from_raw(ptr);
+}
+
+/// Reconstruct the text covered by the given nodes.
+///
+/// - `ptr` - The corpus storage object.
+/// - `corpus_name` - The name of the corpus the nodes belong to.
+/// - `node_names` - The node annotation identifiers of the nodes.
+/// - `whitespace` - Defines how the text between two tokens is reconstructed.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+///
+/// Returns a vector with the same size as `node_names`. The text is empty for unknown nodes.
+#[no_mangle]
+pub extern "C" fn annis_cs_covered_text(
+    ptr: *const CorpusStorage,
+    corpus_name: *const libc::c_char,
+    node_names: *const Vec<CString>,
+    whitespace: WhitespaceHandling,
+    err: *mut *mut ErrorList,
+) -> *mut Vec<CString> {
+    let cs: &CorpusStorage = cast_const(ptr);
+    let corpus = match cstr_checked(corpus_name, err) {
+        Some(corpus) => corpus,
+        None => return std::ptr::null_mut(),
+    };
+    let node_names = match cstring_vec_checked(node_names, err) {
+        Some(node_names) => node_names,
+        None => return std::ptr::null_mut(),
+    };
+
+    map_cerr(cs.covered_text(&corpus, &node_names, whitespace), err)
+        .map(|result| {
+            let result: Vec<CString> = result
+                .into_iter()
+                .map(|text| CString::new(text.unwrap_or_default()).unwrap_or_default())
+                .collect();
+            Box::into_raw(Box::new(result))
+        })
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
 /// Unloads a corpus from the cache.
 #[no_mangle]
 pub extern "C" fn annis_cs_unload(ptr: *mut CorpusStorage, corpus: *const libc::c_char) {
@@ -695,7 +1043,10 @@ pub extern "C" fn annis_cs_apply_update(
 ) {
     let cs: &mut CorpusStorage = cast_mut(ptr);
     let update: &mut GraphUpdate = cast_mut(update);
-    let corpus_name = cstr(corpus_name);
+    let corpus_name = match cstr_checked(corpus_name, err) {
+        Some(corpus_name) => corpus_name,
+        None => return,
+    };
 
     map_cerr(cs.apply_update(&corpus_name, update), err);
 }
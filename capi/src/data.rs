@@ -1,12 +1,66 @@
 use super::Matrix;
-use super::{cast_const, cast_mut, cstr};
+use super::{cast_const, cast_mut, cstr, STRICT_UTF8};
 use graphannis::{
-    corpusstorage::{FrequencyTable, QueryAttributeDescription},
+    corpusstorage::{DiskUsage, FrequencyTable, QueryAttributeDescription},
     graph::{Annotation, Edge, NodeID},
     model::AnnotationComponent,
 };
 use libc::{c_char, c_void, size_t};
 use std::ffi::CString;
+use std::sync::atomic::Ordering;
+
+/// A read-only view on a string that is owned by another object.
+///
+/// The string is UTF-8 encoded and **not** necessarily terminated by a 0 byte, use the `len` field instead.
+/// The view is only valid as long as the object owning the string has not been freed or modified.
+#[repr(C)]
+pub struct StrRef {
+    /// Pointer to the first byte of the string.
+    pub data: *const c_char,
+    /// Number of bytes of the string.
+    pub len: size_t,
+}
+
+impl StrRef {
+    fn from_bytes(v: &[u8]) -> StrRef {
+        StrRef {
+            data: v.as_ptr() as *const c_char,
+            len: v.len(),
+        }
+    }
+
+    fn empty() -> StrRef {
+        StrRef {
+            data: std::ptr::null(),
+            len: 0,
+        }
+    }
+}
+
+/// Defines how string arguments that are not valid UTF-8 are handled.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum Utf8Policy {
+    /// Invalid byte sequences are replaced with the Unicode replacement character.
+    Lossy,
+    /// Invalid byte sequences are reported as error by all functions that have an error list argument.
+    Strict,
+}
+
+/// Set the policy on how string arguments that are not valid UTF-8 are handled by the API functions.
+///
+/// The default policy is `Lossy`.
+/// Functions without an error list argument always replace invalid byte sequences, regardless of the policy.
+#[no_mangle]
+pub extern "C" fn annis_set_utf8_policy(policy: Utf8Policy) {
+    STRICT_UTF8.store(
+        match policy {
+            Utf8Policy::Lossy => false,
+            Utf8Policy::Strict => true,
+        },
+        Ordering::Relaxed,
+    );
+}
 
 /// Frees the internal object given as `ptr` argument.
 ///
@@ -84,6 +138,19 @@ pub extern "C" fn annis_vec_str_get(ptr: *const Vec<CString>, i: size_t) -> *con
     }
 }
 
+/// Get a view on the string at position `i` of the vector without copying it.
+///
+/// The view is valid as long as the vector is not freed. If `i` is out of range, the view is empty and its `data` pointer is `NULL`.
+#[no_mangle]
+pub extern "C" fn annis_vec_str_get_ref(ptr: *const Vec<CString>, i: size_t) -> StrRef {
+    let strvec: &Vec<CString> = cast_const(ptr);
+    if i < strvec.len() {
+        StrRef::from_bytes(strvec[i].as_bytes())
+    } else {
+        StrRef::empty()
+    }
+}
+
 /// Create a new string vector.
 #[no_mangle]
 pub extern "C" fn annis_vec_str_new() -> *mut Vec<CString> {
@@ -128,6 +195,33 @@ pub extern "C" fn annis_annotation_val(ptr: *const Annotation) -> *mut c_char {
         .into_raw()
 }
 
+/// Get a view on the namespace of the given annotation object without copying it.
+///
+/// The view is valid as long as the annotation is not freed.
+#[no_mangle]
+pub extern "C" fn annis_annotation_ns_ref(ptr: *const Annotation) -> StrRef {
+    let anno: &Annotation = cast_const(ptr);
+    StrRef::from_bytes(anno.key.ns.as_bytes())
+}
+
+/// Get a view on the name of the given annotation object without copying it.
+///
+/// The view is valid as long as the annotation is not freed.
+#[no_mangle]
+pub extern "C" fn annis_annotation_name_ref(ptr: *const Annotation) -> StrRef {
+    let anno: &Annotation = cast_const(ptr);
+    StrRef::from_bytes(anno.key.name.as_bytes())
+}
+
+/// Get a view on the value of the given annotation object without copying it.
+///
+/// The view is valid as long as the annotation is not freed.
+#[no_mangle]
+pub extern "C" fn annis_annotation_val_ref(ptr: *const Annotation) -> StrRef {
+    let anno: &Annotation = cast_const(ptr);
+    StrRef::from_bytes(anno.val.as_bytes())
+}
+
 /// Returns the number of elements of the annotation vector.
 #[no_mangle]
 pub extern "C" fn annis_vec_annotation_size(ptr: *const Vec<Annotation>) -> size_t {
@@ -155,6 +249,27 @@ pub extern "C" fn annis_vec_edge_get(ptr: *const Vec<Edge>, i: size_t) -> *const
     vec_get(ptr, i)
 }
 
+/// Returns the number of elements of the node ID vector.
+#[no_mangle]
+pub extern "C" fn annis_vec_nodeid_size(ptr: *const Vec<Option<NodeID>>) -> size_t {
+    vec_size(ptr)
+}
+
+/// Get a read-only reference to the node ID at position `i` of the vector.
+///
+/// Returns `NULL` if there is no node ID at this position, e.g. because a node name could not be resolved.
+#[no_mangle]
+pub extern "C" fn annis_vec_nodeid_get(
+    ptr: *const Vec<Option<NodeID>>,
+    i: size_t,
+) -> *const NodeID {
+    let v: &Vec<Option<NodeID>> = cast_const(ptr);
+    if let Some(Some(id)) = v.get(i) {
+        return id as *const NodeID;
+    }
+    std::ptr::null()
+}
+
 /// Returns the number of elements of the component vector.
 #[no_mangle]
 pub extern "C" fn annis_vec_component_size(ptr: *const Vec<AnnotationComponent>) -> size_t {
@@ -280,6 +395,23 @@ pub extern "C" fn annis_freqtable_str_ncols(ptr: *const FrequencyTable<CString>)
     0
 }
 
+/// Frees the frequency table given as `ptr` argument, including all its rows and strings.
+///
+/// Use this function instead of `annis_free` for frequency tables, because the generic function does not know the
+/// type of the object and only releases the memory of the table itself.
+///
+/// # Safety
+///
+/// This functions dereferences the `ptr` pointer and is therefore unsafe.
+#[no_mangle]
+pub unsafe extern "C" fn annis_freqtable_str_free(ptr: *mut FrequencyTable<CString>) {
+    if ptr.is_null() {
+        return;
+    }
+    // take ownership and destroy the pointer
+    Box::from_raw(ptr);
+}
+
 /// Get a read-only reference to the string at the at position (`row`, `col`) of the frequency table.
 #[no_mangle]
 pub extern "C" fn annis_freqtable_str_get(
@@ -295,6 +427,23 @@ pub extern "C" fn annis_freqtable_str_get(
     std::ptr::null()
 }
 
+/// Get a view on the string at the at position (`row`, `col`) of the frequency table without copying it.
+///
+/// The view is valid as long as the frequency table is not freed. If the position is out of range, the view is empty and its `data` pointer is `NULL`.
+#[no_mangle]
+pub extern "C" fn annis_freqtable_str_get_ref(
+    ptr: *const FrequencyTable<CString>,
+    row: size_t,
+    col: size_t,
+) -> StrRef {
+    let ft: &FrequencyTable<CString> = cast_const(ptr);
+    if row < ft.len() && col < ft[row].values.len() {
+        StrRef::from_bytes(ft[row].values[col].as_bytes())
+    } else {
+        StrRef::empty()
+    }
+}
+
 /// Get the count of the `row` of the frequency table.
 #[no_mangle]
 pub extern "C" fn annis_freqtable_str_count(
@@ -307,3 +456,69 @@ pub extern "C" fn annis_freqtable_str_count(
     }
     0
 }
+
+/// Get the total number of bytes used on disk.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_total(ptr: *const DiskUsage) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.total()
+}
+
+/// Get the number of bytes used on disk by the node annotations.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_node_annotations(ptr: *const DiskUsage) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.node_annotations
+}
+
+/// Get the number of bytes used on disk by the write-ahead log of updates.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_update_log(ptr: *const DiskUsage) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.update_log
+}
+
+/// Get the number of bytes used on disk by the linked files.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_linked_files(ptr: *const DiskUsage) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.linked_files
+}
+
+/// Get the number of bytes used on disk by the backup of the corpus.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_backup(ptr: *const DiskUsage) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.backup
+}
+
+/// Returns the number of components in the disk usage report.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_components_size(ptr: *const DiskUsage) -> size_t {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage.components.len()
+}
+
+/// Get a read-only reference to the component at position `i` of the disk usage report.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_component_get(
+    ptr: *const DiskUsage,
+    i: size_t,
+) -> *const AnnotationComponent {
+    let usage: &DiskUsage = cast_const(ptr);
+    if let Some((c, _)) = usage.components.get(i) {
+        return c as *const AnnotationComponent;
+    }
+    std::ptr::null()
+}
+
+/// Get the number of bytes used on disk by the component at position `i` of the disk usage report.
+#[no_mangle]
+pub extern "C" fn annis_diskusage_component_bytes(ptr: *const DiskUsage, i: size_t) -> u64 {
+    let usage: &DiskUsage = cast_const(ptr);
+    usage
+        .components
+        .get(i)
+        .map(|(_, size)| *size)
+        .unwrap_or_default()
+}
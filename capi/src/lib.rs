@@ -2,9 +2,13 @@
 extern crate log;
 
 use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use cerror::ErrorList;
 
+/// If `true`, string arguments with invalid UTF-8 are rejected instead of being converted lossily.
+static STRICT_UTF8: AtomicBool = AtomicBool::new(false);
+
 fn cast_mut<'a, T>(x: *mut T) -> &'a mut T {
     unsafe {
         assert!(!x.is_null());
@@ -29,6 +33,41 @@ fn cstr<'a>(orig: *const libc::c_char) -> Cow<'a, str> {
     }
 }
 
+/// Convert a C string argument to a Rust string, respecting the UTF-8 policy set with `annis_set_utf8_policy`.
+///
+/// If the strict policy is active and the argument is not valid UTF-8, `None` is returned and the error is reported
+/// via `err_ptr`.
+fn cstr_checked<'a>(
+    orig: *const libc::c_char,
+    err_ptr: *mut *mut ErrorList,
+) -> Option<Cow<'a, str>> {
+    if orig.is_null() || !STRICT_UTF8.load(Ordering::Relaxed) {
+        Some(cstr(orig))
+    } else {
+        let orig = unsafe { std::ffi::CStr::from_ptr(orig) };
+        map_cerr(orig.to_str(), err_ptr).map(Cow::from)
+    }
+}
+
+/// Convert a string vector argument to Rust strings, respecting the UTF-8 policy set with `annis_set_utf8_policy`.
+fn cstring_vec_checked(
+    orig: *const Vec<std::ffi::CString>,
+    err_ptr: *mut *mut ErrorList,
+) -> Option<Vec<String>> {
+    let orig: &Vec<std::ffi::CString> = cast_const(orig);
+    if STRICT_UTF8.load(Ordering::Relaxed) {
+        let result: Result<Vec<String>, _> =
+            orig.iter().map(|s| s.to_str().map(String::from)).collect();
+        map_cerr(result, err_ptr)
+    } else {
+        Some(
+            orig.iter()
+                .map(|s| String::from(s.to_string_lossy()))
+                .collect(),
+        )
+    }
+}
+
 fn map_cerr<T, E: Into<Box<dyn std::error::Error>>>(
     x: Result<T, E>,
     err_ptr: *mut *mut ErrorList,
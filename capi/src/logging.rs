@@ -1,5 +1,5 @@
 use super::cerror::{Error, ErrorList};
-use super::cstr;
+use super::cstr_checked;
 use simplelog::{Config, LevelFilter, WriteLogger};
 use std::fs::File;
 
@@ -43,9 +43,12 @@ pub unsafe extern "C" fn annis_init_logging(
     err: *mut *mut ErrorList,
 ) {
     if !logfile.is_null() {
-        let logfile: &str = &cstr(logfile);
+        let logfile = match cstr_checked(logfile, err) {
+            Some(logfile) => logfile,
+            None => return,
+        };
 
-        match File::create(logfile) {
+        match File::create(logfile.as_ref()) {
             Ok(f) => {
                 if let Err(e) = WriteLogger::init(LevelFilter::from(level), Config::default(), f) {
                     // File was created, but logger was not.
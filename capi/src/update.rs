@@ -1,5 +1,5 @@
 use super::cerror::ErrorList;
-use super::{cast_mut, cstr, map_cerr};
+use super::{cast_const, cast_mut, cstr_checked, map_cerr};
 use graphannis::update::{GraphUpdate, UpdateEvent};
 
 /// Create a new graph (empty) update instance
@@ -9,6 +9,40 @@ pub extern "C" fn annis_graphupdate_new() -> *mut GraphUpdate {
     Box::into_raw(Box::new(gu))
 }
 
+/// Create a new graph (empty) update instance, that holds at most `max_bytes` of events in memory
+/// before spilling them to disk. If `max_bytes` is 0, all events are kept in memory.
+///
+/// - `max_bytes` - Maximum estimated size of the events in memory.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_graphupdate_with_memory_limit(
+    max_bytes: libc::size_t,
+    err: *mut *mut ErrorList,
+) -> *mut GraphUpdate {
+    let max_bytes = if max_bytes == 0 {
+        None
+    } else {
+        Some(max_bytes)
+    };
+    map_cerr(GraphUpdate::with_memory_limit(max_bytes), err)
+        .map(|gu| Box::into_raw(Box::new(gu)))
+        .unwrap_or_else(std::ptr::null_mut)
+}
+
+/// Returns the number of bytes needed to serialize the graph update object without compression.
+#[no_mangle]
+pub extern "C" fn annis_graphupdate_serialized_size_estimate(ptr: *const GraphUpdate) -> u64 {
+    let u: &GraphUpdate = cast_const(ptr);
+    u.serialized_size_estimate()
+}
+
+/// Returns the estimated number of bytes of the events of the graph update object that are currently held in memory.
+#[no_mangle]
+pub extern "C" fn annis_graphupdate_memory_size_estimate(ptr: *const GraphUpdate) -> libc::size_t {
+    let u: &GraphUpdate = cast_const(ptr);
+    u.memory_size_estimate()
+}
+
 /// Add "add node" action to the graph update object.
 ///
 /// - `ptr` - The graph update object.
@@ -23,13 +57,15 @@ pub extern "C" fn annis_graphupdate_add_node(
     err: *mut *mut ErrorList,
 ) {
     let u: &mut GraphUpdate = cast_mut(ptr);
-    map_cerr(
-        u.add_event(UpdateEvent::AddNode {
-            node_name: String::from(cstr(node_name)),
-            node_type: String::from(cstr(node_type)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::AddNode {
+            node_name: String::from(cstr_checked(node_name, err)?),
+            node_type: String::from(cstr_checked(node_type, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(u.add_event(event), err);
+    }
 }
 
 /// Add "delete node" action to the graph update object.
@@ -44,12 +80,14 @@ pub extern "C" fn annis_graphupdate_delete_node(
     err: *mut *mut ErrorList,
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
-    map_cerr(
-        cs.add_event(UpdateEvent::DeleteNode {
-            node_name: String::from(cstr(node_name)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::DeleteNode {
+            node_name: String::from(cstr_checked(node_name, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "add node label" action to the graph update object.
@@ -70,15 +108,51 @@ pub extern "C" fn annis_graphupdate_add_node_label(
     err: *mut *mut ErrorList,
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
-    map_cerr(
-        cs.add_event(UpdateEvent::AddNodeLabel {
-            node_name: String::from(cstr(node_name)),
-            anno_ns: String::from(cstr(anno_ns)),
-            anno_name: String::from(cstr(anno_name)),
-            anno_value: String::from(cstr(anno_value)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::AddNodeLabel {
+            node_name: String::from(cstr_checked(node_name, err)?),
+            anno_ns: String::from(cstr_checked(anno_ns, err)?),
+            anno_name: String::from(cstr_checked(anno_name, err)?),
+            anno_value: String::from(cstr_checked(anno_value, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
+}
+
+/// Add "add node label value" action to the graph update object.
+///
+/// In contrast to `annis_graphupdate_add_node_label`, the existing values of the node for the same
+/// annotation are kept and the value is added as an additional value.
+///
+/// - `ptr` - The graph update object.
+/// - `node_name` - Name of the node the label is attached to.
+/// - `annos_ns` - Namespace of the annotation.
+/// - `annos_name` - Name of the annotation.
+/// - `annos_value` - Additional value of the annotation.
+/// - `err` - Pointer to a list of errors. If any error occured, this list will be non-empty.
+#[no_mangle]
+pub extern "C" fn annis_graphupdate_add_node_label_value(
+    ptr: *mut GraphUpdate,
+    node_name: *const libc::c_char,
+    anno_ns: *const libc::c_char,
+    anno_name: *const libc::c_char,
+    anno_value: *const libc::c_char,
+    err: *mut *mut ErrorList,
+) {
+    let cs: &mut GraphUpdate = cast_mut(ptr);
+    let event = (|| {
+        Some(UpdateEvent::AddNodeLabelValue {
+            node_name: String::from(cstr_checked(node_name, err)?),
+            anno_ns: String::from(cstr_checked(anno_ns, err)?),
+            anno_name: String::from(cstr_checked(anno_name, err)?),
+            anno_value: String::from(cstr_checked(anno_value, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "delete node label" action to the graph update object.
@@ -97,14 +171,16 @@ pub extern "C" fn annis_graphupdate_delete_node_label(
     err: *mut *mut ErrorList,
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
-    map_cerr(
-        cs.add_event(UpdateEvent::DeleteNodeLabel {
-            node_name: String::from(cstr(node_name)),
-            anno_ns: String::from(cstr(anno_ns)),
-            anno_name: String::from(cstr(anno_name)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::DeleteNodeLabel {
+            node_name: String::from(cstr_checked(node_name, err)?),
+            anno_ns: String::from(cstr_checked(anno_ns, err)?),
+            anno_name: String::from(cstr_checked(anno_name, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "add edge" action to the graph update object.
@@ -128,16 +204,18 @@ pub extern "C" fn annis_graphupdate_add_edge(
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
 
-    map_cerr(
-        cs.add_event(UpdateEvent::AddEdge {
-            source_node: String::from(cstr(source_node)),
-            target_node: String::from(cstr(target_node)),
-            layer: String::from(cstr(layer)),
-            component_type: String::from(cstr(component_type)),
-            component_name: String::from(cstr(component_name)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::AddEdge {
+            source_node: String::from(cstr_checked(source_node, err)?),
+            target_node: String::from(cstr_checked(target_node, err)?),
+            layer: String::from(cstr_checked(layer, err)?),
+            component_type: String::from(cstr_checked(component_type, err)?),
+            component_name: String::from(cstr_checked(component_name, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "delete edge" action to the graph update object.
@@ -160,16 +238,18 @@ pub extern "C" fn annis_graphupdate_delete_edge(
     err: *mut *mut ErrorList,
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
-    map_cerr(
-        cs.add_event(UpdateEvent::DeleteEdge {
-            source_node: String::from(cstr(source_node)),
-            target_node: String::from(cstr(target_node)),
-            layer: String::from(cstr(layer)),
-            component_type: String::from(cstr(component_type)),
-            component_name: String::from(cstr(component_name)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::DeleteEdge {
+            source_node: String::from(cstr_checked(source_node, err)?),
+            target_node: String::from(cstr_checked(target_node, err)?),
+            layer: String::from(cstr_checked(layer, err)?),
+            component_type: String::from(cstr_checked(component_type, err)?),
+            component_name: String::from(cstr_checked(component_name, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "add edge label" action to the graph update object.
@@ -199,19 +279,21 @@ pub extern "C" fn annis_graphupdate_add_edge_label(
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
 
-    map_cerr(
-        cs.add_event(UpdateEvent::AddEdgeLabel {
-            source_node: String::from(cstr(source_node)),
-            target_node: String::from(cstr(target_node)),
-            layer: String::from(cstr(layer)),
-            component_type: String::from(cstr(component_type)),
-            component_name: String::from(cstr(component_name)),
-            anno_ns: String::from(cstr(anno_ns)),
-            anno_name: String::from(cstr(anno_name)),
-            anno_value: String::from(cstr(anno_value)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::AddEdgeLabel {
+            source_node: String::from(cstr_checked(source_node, err)?),
+            target_node: String::from(cstr_checked(target_node, err)?),
+            layer: String::from(cstr_checked(layer, err)?),
+            component_type: String::from(cstr_checked(component_type, err)?),
+            component_name: String::from(cstr_checked(component_name, err)?),
+            anno_ns: String::from(cstr_checked(anno_ns, err)?),
+            anno_name: String::from(cstr_checked(anno_name, err)?),
+            anno_value: String::from(cstr_checked(anno_value, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
 
 /// Add "delete edge label" action to the graph update object.
@@ -239,16 +321,18 @@ pub extern "C" fn annis_graphupdate_delete_edge_label(
 ) {
     let cs: &mut GraphUpdate = cast_mut(ptr);
 
-    map_cerr(
-        cs.add_event(UpdateEvent::DeleteEdgeLabel {
-            source_node: String::from(cstr(source_node)),
-            target_node: String::from(cstr(target_node)),
-            layer: String::from(cstr(layer)),
-            component_type: String::from(cstr(component_type)),
-            component_name: String::from(cstr(component_name)),
-            anno_ns: String::from(cstr(anno_ns)),
-            anno_name: String::from(cstr(anno_name)),
-        }),
-        err,
-    );
+    let event = (|| {
+        Some(UpdateEvent::DeleteEdgeLabel {
+            source_node: String::from(cstr_checked(source_node, err)?),
+            target_node: String::from(cstr_checked(target_node, err)?),
+            layer: String::from(cstr_checked(layer, err)?),
+            component_type: String::from(cstr_checked(component_type, err)?),
+            component_name: String::from(cstr_checked(component_name, err)?),
+            anno_ns: String::from(cstr_checked(anno_ns, err)?),
+            anno_name: String::from(cstr_checked(anno_name, err)?),
+        })
+    })();
+    if let Some(event) = event {
+        map_cerr(cs.add_event(event), err);
+    }
 }
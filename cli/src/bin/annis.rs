@@ -6,6 +6,7 @@ use graphannis::corpusstorage::FrequencyDefEntry;
 use graphannis::corpusstorage::LoadStatus;
 use graphannis::corpusstorage::QueryLanguage;
 use graphannis::corpusstorage::ResultOrder;
+use graphannis::corpusstorage::{BatchConfig, BatchMode, BatchOutputFormat, BatchQuery};
 use graphannis::corpusstorage::{CorpusInfo, SearchQuery};
 use graphannis::corpusstorage::{ExportFormat, ImportFormat};
 use graphannis::CorpusStorage;
@@ -45,6 +46,7 @@ impl ConsoleHelper {
         known_commands.insert("count".to_string());
         known_commands.insert("find".to_string());
         known_commands.insert("frequency".to_string());
+        known_commands.insert("batch".to_string());
         known_commands.insert("plan".to_string());
         known_commands.insert("re-optimize".to_string());
         known_commands.insert("set-disk-based".to_string());
@@ -210,6 +212,7 @@ impl AnnisRunner {
                 "count" => self.count(&args),
                 "find" => self.find(&args),
                 "frequency" => self.frequency(&args),
+                "batch" => self.batch(&args),
                 "set-parallel-search" => self.use_parallel(&args),
                 "set-disk-based" => self.use_disk(&args),
                 "set-quirks-mode" => self.quirks_mode(&args),
@@ -254,7 +257,7 @@ impl AnnisRunner {
                     .storage
                     .as_ref()
                     .ok_or_else(|| anyhow!("No corpus storage location set"))?
-                    .import_all_from_zip(zip_file, self.use_disk, true, |status| {
+                    .import_all_from_zip(zip_file, self.use_disk, true, None, |status| {
                         info!("{}", status)
                     })?;
                 let load_time = t_before.elapsed();
@@ -267,6 +270,8 @@ impl AnnisRunner {
 
                 if file_ext == Some("graphml") || file_ext == Some("xml") {
                     format = ImportFormat::GraphML
+                } else if file_ext == Some("conllu") {
+                    format = ImportFormat::CoNLLU
                 }
 
                 let t_before = std::time::SystemTime::now();
@@ -493,6 +498,7 @@ impl AnnisRunner {
             corpus_names: &self.current_corpus,
             query_language: self.query_language,
             timeout: self.timeout,
+            document_filter: Vec::new(),
             query,
         }
     }
@@ -608,6 +614,90 @@ impl AnnisRunner {
         Ok(())
     }
 
+    fn batch(&self, args: &str) -> Result<()> {
+        if self.current_corpus.is_empty() {
+            println!("You need to select a corpus first with the \"corpus\" command");
+            return Ok(());
+        }
+        let args: Vec<&str> = args.split_whitespace().collect();
+        if args.len() < 2 {
+            bail!("You need to give the query file and the output directory as argument, optionally followed by the mode (count or find) and the format (csv or json)");
+        }
+        let mut config = BatchConfig {
+            timeout: self.timeout,
+            parallel: self.use_parallel_joins,
+            ..Default::default()
+        };
+        for a in args.iter().skip(2) {
+            match a.to_lowercase().as_str() {
+                "count" => config.mode = BatchMode::Count,
+                "find" => config.mode = BatchMode::Find,
+                "csv" => config.format = BatchOutputFormat::CSV,
+                "json" => config.format = BatchOutputFormat::JSON,
+                _ => bail!("unknown argument \"{}\"", a),
+            }
+        }
+
+        // Each non-empty line of the query file has the form "<name><TAB><query>", lines starting with "#" are ignored
+        let mut queries = Vec::new();
+        for line in std::fs::read_to_string(args[0])?.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let splitted: Vec<&str> = line.splitn(2, '\t').collect();
+            if splitted.len() != 2 {
+                bail!(
+                    "Line \"{}\" of the query file is not of the form \"<name><TAB><query>\"",
+                    line
+                );
+            }
+            queries.push(BatchQuery {
+                name: splitted[0].trim().to_string(),
+                query: splitted[1].to_string(),
+                query_language: self.query_language,
+            });
+        }
+
+        let t_before = std::time::SystemTime::now();
+        let results = self
+            .storage
+            .as_ref()
+            .ok_or_else(|| anyhow!("No corpus storage location set"))?
+            .execute_batch(&self.current_corpus, &queries, Path::new(args[1]), &config)?;
+        let load_time = t_before.elapsed();
+        if let Ok(t) = load_time {
+            info! {"Executed batch in {} ms", (t.as_secs() * 1000 + t.subsec_nanos() as u64 / 1_000_000)};
+        }
+
+        let mut out = Table::new();
+        let mut header_row = Row::empty();
+        header_row.add_cell(Cell::from(&"name"));
+        header_row.add_cell(Cell::from(&"matches"));
+        header_row.add_cell(Cell::from(&"time (ms)"));
+        header_row.add_cell(Cell::from(&"result"));
+        out.add_row(header_row);
+        for r in results {
+            let outcome = if let Some(error) = r.error {
+                error
+            } else if let Some(output_file) = r.output_file {
+                output_file.to_string_lossy().to_string()
+            } else {
+                String::default()
+            };
+            let mut out_row = Row::empty();
+            out_row.add_cell(Cell::from(&r.name));
+            out_row.add_cell(Cell::from(
+                &r.match_count.map(|c| c.to_string()).unwrap_or_default(),
+            ));
+            out_row.add_cell(Cell::from(&r.duration.as_millis()));
+            out_row.add_cell(Cell::from(&outcome));
+            out.add_row(out_row);
+        }
+        out.printstd();
+
+        Ok(())
+    }
+
     fn use_parallel(&mut self, args: &str) -> Result<()> {
         let new_val = match args.trim().to_lowercase().as_str() {
             "on" | "true" => true,
@@ -46,6 +46,7 @@ pub fn create_query_input<M>(
                     corpus_names: &def.corpus,
                     query_language: QueryLanguage::AQL,
                     timeout: None,
+                    document_filter: Vec::new(),
                 };
                 let count = if let Ok(count) = cs.count(search_query) {
                     count
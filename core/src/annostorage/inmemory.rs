@@ -1,9 +1,9 @@
-use super::{AnnotationStorage, Match, MatchGroup};
+use super::{guess_max_count_from_samples, AnnoKeyUsage, AnnotationStorage, Match, MatchGroup};
 use crate::annostorage::ValueSearch;
 use crate::errors::Result;
 use crate::malloc_size_of::MallocSizeOf;
 use crate::types::{AnnoKey, Annotation, Edge};
-use crate::util::{self, memory_estimation};
+use crate::util::{self, encryption, memory_estimation};
 use crate::{annostorage::symboltable::SymbolTable, errors::GraphAnnisCoreError};
 use core::ops::Bound::*;
 use itertools::Itertools;
@@ -23,6 +23,16 @@ struct SparseAnnotation {
 
 type ValueItemMap<T> = FxHashMap<usize, Vec<T>>;
 
+/// Returns the range of the entries with the given annotation key symbol in the annotations of an item.
+///
+/// The annotations of an item are sorted by key and value, so if the item has multiple values for the same key,
+/// these are stored next to each other.
+fn key_range(all_annos: &[SparseAnnotation], key: usize) -> std::ops::Range<usize> {
+    let start = all_annos.partition_point(|a| a.key < key);
+    let end = all_annos.partition_point(|a| a.key <= key);
+    start..end
+}
+
 #[derive(Serialize, Deserialize, Clone, Default, MallocSizeOf)]
 pub struct AnnoStorageImpl<T: Ord + Hash + MallocSizeOf + Default> {
     by_container: FxHashMap<T, Vec<SparseAnnotation>>,
@@ -39,6 +49,10 @@ pub struct AnnoStorageImpl<T: Ord + Hash + MallocSizeOf + Default> {
     histogram_bounds: BTreeMap<usize, Vec<smartstring::alias::String>>,
     largest_item: Option<T>,
     total_number_of_annos: usize,
+    /// Number of annotation values which have been added as additional values for an existing annotation key of an item.
+    /// This is re-calculated when loading the annotation storage.
+    #[serde(skip)]
+    number_of_additional_values: usize,
 }
 
 impl<
@@ -62,6 +76,7 @@ impl<
             histogram_bounds: BTreeMap::new(),
             largest_item: None,
             total_number_of_annos: 0,
+            number_of_additional_values: 0,
         }
     }
 
@@ -73,6 +88,39 @@ impl<
         self.histogram_bounds.clear();
         self.largest_item = None;
         self.anno_values.clear();
+        self.number_of_additional_values = 0;
+    }
+
+    fn calculate_number_of_additional_values(&mut self) {
+        self.number_of_additional_values = self
+            .by_container
+            .values()
+            .map(|all_annos| all_annos.len() - all_annos.iter().map(|a| a.key).dedup().count())
+            .sum();
+    }
+
+    fn add_new_sparse_anno(&mut self, item: T, anno: SparseAnnotation, orig_anno_key: AnnoKey) {
+        // inserts a new relation between the annotation and the item
+        // if set is not existing yet it is created
+        self.by_anno
+            .entry(anno.key)
+            .or_insert_with(FxHashMap::default)
+            .entry(anno.val)
+            .or_insert_with(Vec::default)
+            .push(item.clone());
+
+        self.total_number_of_annos += 1;
+
+        if let Some(largest_item) = self.largest_item.clone() {
+            if largest_item < item {
+                self.largest_item = Some(item);
+            }
+        } else {
+            self.largest_item = Some(item);
+        }
+
+        let anno_key_entry = self.anno_key_sizes.entry(orig_anno_key).or_insert(0);
+        *anno_key_entry += 1;
     }
 
     fn create_sparse_anno(&mut self, orig: Annotation) -> SparseAnnotation {
@@ -143,12 +191,12 @@ where
         + Sync,
     (T, Arc<AnnoKey>): Into<Match>,
 {
-    fn matching_items<'a>(
+    /// Get the value maps of all annotation keys matching the namespace and name.
+    fn value_maps<'a>(
         &'a self,
         namespace: Option<&str>,
         name: &str,
-        value: Option<&str>,
-    ) -> Box<dyn Iterator<Item = (T, Arc<AnnoKey>)> + 'a> {
+    ) -> Vec<(Arc<AnnoKey>, &'a ValueItemMap<T>)> {
         let key_ranges: Vec<Arc<AnnoKey>> = if let Some(ns) = namespace {
             vec![Arc::from(AnnoKey {
                 ns: ns.into(),
@@ -157,9 +205,9 @@ where
         } else {
             self.get_qnames(name).into_iter().map(Arc::from).collect()
         };
-        // Create a vector fore each matching AnnoKey to the value map containing all items and their annotation values
+        // Create a vector for each matching AnnoKey to the value map containing all items and their annotation values
         // for this key.
-        let value_maps: Vec<(Arc<AnnoKey>, &ValueItemMap<T>)> = key_ranges
+        key_ranges
             .into_iter()
             .filter_map(|key| {
                 let key_id = self.anno_keys.get_symbol(&key)?;
@@ -169,40 +217,20 @@ where
                     None
                 }
             })
-            .collect();
-
-        if let Some(value) = value {
-            let target_value_symbol = self.anno_values.get_symbol(&value.into());
+            .collect()
+    }
 
-            if let Some(target_value_symbol) = target_value_symbol {
-                let it = value_maps
-                    .into_iter()
-                    // find the items with the correct value
-                    .filter_map(move |(key, values)| {
-                        if let Some(items) = values.get(&target_value_symbol) {
-                            Some((items, key))
-                        } else {
-                            None
-                        }
-                    })
-                    // flatten the hash set of all items, returns all items for the condition
-                    .flat_map(|(items, key)| items.iter().cloned().zip(std::iter::repeat(key)));
-                Box::new(it)
-            } else {
-                // value is not known, return empty result
-                Box::new(std::iter::empty())
-            }
+    /// Convert the items to matches and remove duplicates, which can occur if an item has multiple values for the
+    /// same annotation key.
+    fn unique_matches<'a, I>(&self, it: I) -> Box<dyn Iterator<Item = Match> + 'a>
+    where
+        I: Iterator<Item = (T, Arc<AnnoKey>)> + 'a,
+        T: 'a,
+    {
+        if self.number_of_additional_values > 0 {
+            Box::new(it.unique().map(|item| item.into()))
         } else {
-            let it = value_maps
-                .into_iter()
-                // flatten the hash set of all items of the value map
-                .flat_map(|(key, values)| {
-                    values
-                        .iter()
-                        .flat_map(|(_, items)| items.iter().cloned())
-                        .zip(std::iter::repeat(key))
-                });
-            Box::new(it)
+            Box::new(it.map(|item| item.into()))
         }
     }
 }
@@ -224,63 +252,61 @@ where
         let orig_anno_key = anno.key.clone();
         let anno = self.create_sparse_anno(anno);
 
-        let existing_anno = {
+        let existing_annos: Vec<SparseAnnotation> = {
             let existing_item_entry = self
                 .by_container
                 .entry(item.clone())
                 .or_insert_with(Vec::new);
 
-            // check if there is already an item with the same annotation key
-            let existing_entry_idx = existing_item_entry.binary_search_by_key(&anno.key, |a| a.key);
-
-            if let Ok(existing_entry_idx) = existing_entry_idx {
-                let orig_anno = existing_item_entry[existing_entry_idx];
-                // abort if the same annotation key with the same value already exist
-                if orig_anno.val == anno.val {
-                    return Ok(());
-                }
-                // insert annotation for item at existing position
-                existing_item_entry[existing_entry_idx] = anno;
-                Some(orig_anno)
-            } else if let Err(insertion_idx) = existing_entry_idx {
-                // insert at sorted position -> the result will still be a sorted vector
-                existing_item_entry.insert(insertion_idx, anno);
-                None
-            } else {
-                None
+            // check if there are already values for the same annotation key
+            let existing_range = key_range(existing_item_entry, anno.key);
+            let existing_annos = &existing_item_entry[existing_range.clone()];
+            // abort if the same annotation key with the same single value already exist
+            if existing_annos.len() == 1 && existing_annos[0].val == anno.val {
+                return Ok(());
             }
+            // replace all existing values with the new one
+            let insertion_idx = existing_range.start;
+            let existing_annos: Vec<SparseAnnotation> =
+                existing_item_entry.drain(existing_range).collect();
+            existing_item_entry.insert(insertion_idx, anno);
+            existing_annos
         };
 
-        if let Some(ref existing_anno) = existing_anno {
+        for existing_anno in existing_annos.iter() {
             // remove the relation from the original annotation to this item
             self.remove_element_from_by_anno(existing_anno, &item);
         }
+        if !existing_annos.is_empty() {
+            self.total_number_of_annos -= existing_annos.len();
+            self.number_of_additional_values -= existing_annos.len() - 1;
+            if let Some(num_of_keys) = self.anno_key_sizes.get_mut(&orig_anno_key) {
+                *num_of_keys -= existing_annos.len();
+            }
+        }
 
-        // inserts a new relation between the annotation and the item
-        // if set is not existing yet it is created
-        self.by_anno
-            .entry(anno.key)
-            .or_insert_with(FxHashMap::default)
-            .entry(anno.val)
-            .or_insert_with(Vec::default)
-            .push(item.clone());
+        self.add_new_sparse_anno(item, anno, orig_anno_key);
 
-        if existing_anno.is_none() {
-            // a new annotation entry was inserted and did not replace an existing one
-            self.total_number_of_annos += 1;
+        Ok(())
+    }
 
-            if let Some(largest_item) = self.largest_item.clone() {
-                if largest_item < item {
-                    self.largest_item = Some(item);
-                }
-            } else {
-                self.largest_item = Some(item);
-            }
+    fn add_value(&mut self, item: T, anno: Annotation) -> Result<()> {
+        let orig_anno_key = anno.key.clone();
+        let anno = self.create_sparse_anno(anno);
 
-            let anno_key_entry = self.anno_key_sizes.entry(orig_anno_key).or_insert(0);
-            *anno_key_entry += 1;
+        let existing_item_entry = self.by_container.entry(item.clone()).or_default();
+        let has_other_values = !key_range(existing_item_entry, anno.key).is_empty();
+        match existing_item_entry.binary_search_by_key(&(anno.key, anno.val), |a| (a.key, a.val)) {
+            // abort if the same annotation key with the same value already exist
+            Ok(_) => return Ok(()),
+            Err(insertion_idx) => existing_item_entry.insert(insertion_idx, anno),
+        }
+        if has_other_values {
+            self.number_of_additional_values += 1;
         }
 
+        self.add_new_sparse_anno(item, anno, orig_anno_key);
+
         Ok(())
     }
 
@@ -291,21 +317,22 @@ where
         if let Some(key) = self.anno_keys.get_symbol(key) {
             if let Some(mut all_annos) = self.by_container.remove(item) {
                 // find the specific annotation key from the sorted vector of all annotations of this item
-                let anno_idx = all_annos.binary_search_by_key(&key, |a| a.key);
+                let anno_range = key_range(&all_annos, key);
 
-                if let Ok(anno_idx) = anno_idx {
+                if !anno_range.is_empty() {
+                    // remove all values of the specific annotation key from the entry
+                    let old_annos: Vec<SparseAnnotation> = all_annos.drain(anno_range).collect();
                     // since value was found, also remove the item from the other containers
-                    self.remove_element_from_by_anno(&all_annos[anno_idx], item);
-
-                    let old_value = all_annos[anno_idx].val;
+                    for old_anno in old_annos.iter() {
+                        self.remove_element_from_by_anno(old_anno, item);
+                    }
 
-                    // remove the specific annotation key from the entry
-                    all_annos.remove(anno_idx);
+                    let old_value = old_annos[0].val;
 
                     // decrease the annotation count for this key
                     let new_key_count: usize =
                         if let Some(num_of_keys) = self.anno_key_sizes.get_mut(orig_key) {
-                            *num_of_keys -= 1;
+                            *num_of_keys -= old_annos.len();
                             *num_of_keys
                         } else {
                             0
@@ -322,8 +349,11 @@ where
                         .get_value_ref(old_value)
                         .map(|v| Cow::Owned(v.clone().into()));
 
-                    self.check_and_remove_value_symbol(old_value);
-                    self.total_number_of_annos -= 1;
+                    for old_anno in old_annos.iter() {
+                        self.check_and_remove_value_symbol(old_anno.val);
+                    }
+                    self.total_number_of_annos -= old_annos.len();
+                    self.number_of_additional_values -= old_annos.len() - 1;
                 }
                 // if there are more annotations for this item, re-insert them
                 if !all_annos.is_empty() {
@@ -384,16 +414,30 @@ where
         let key_symbol = self.anno_keys.get_symbol(key)?;
 
         if let Some(all_annos) = self.by_container.get(item) {
-            let idx = all_annos.binary_search_by_key(&key_symbol, |a| a.key);
-            if let Ok(idx) = idx {
-                if let Some(val) = self.anno_values.get_value_ref(all_annos[idx].val) {
-                    return Some(Cow::Borrowed(val));
+            if let Some(anno) = all_annos.get(key_range(all_annos, key_symbol).start) {
+                if anno.key == key_symbol {
+                    if let Some(val) = self.anno_values.get_value_ref(anno.val) {
+                        return Some(Cow::Borrowed(val));
+                    }
                 }
             }
         }
         None
     }
 
+    fn get_values_for_item(&self, item: &T, key: &AnnoKey) -> Vec<Cow<str>> {
+        if let Some(key_symbol) = self.anno_keys.get_symbol(key) {
+            if let Some(all_annos) = self.by_container.get(item) {
+                return all_annos[key_range(all_annos, key_symbol)]
+                    .iter()
+                    .filter_map(|a| self.anno_values.get_value_ref(a.val))
+                    .map(|val| Cow::Borrowed(val.as_str()))
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
     fn has_value_for_item(&self, item: &T, key: &AnnoKey) -> bool {
         if let Some(key_symbol) = self.anno_keys.get_symbol(key) {
             if let Some(all_annos) = self.by_container.get(item) {
@@ -512,77 +556,60 @@ where
         name: &str,
         value: ValueSearch<&str>,
     ) -> Box<dyn Iterator<Item = Match> + 'a> {
-        let key_ranges: Vec<Arc<AnnoKey>> = if let Some(ns) = namespace {
-            vec![Arc::from(AnnoKey {
-                ns: ns.into(),
-                name: name.into(),
-            })]
-        } else {
-            self.get_qnames(name).into_iter().map(Arc::from).collect()
+        let value_maps = self.value_maps(namespace, name);
+
+        let target_values = match &value {
+            ValueSearch::Some(value) => Some(vec![*value]),
+            ValueSearch::AnyOf(values) => Some(values.clone()),
+            ValueSearch::Any | ValueSearch::NotSome(_) => None,
         };
-        // Create a vector for each matching AnnoKey to the value map containing all items and their annotation values
-        // for this key.
-        let value_maps: Vec<(Arc<AnnoKey>, &ValueItemMap<T>)> = key_ranges
-            .into_iter()
-            .filter_map(|key| {
-                let key_id = self.anno_keys.get_symbol(&key)?;
-                if let Some(values_for_key) = self.by_anno.get(&key_id) {
-                    Some((key, values_for_key))
-                } else {
-                    None
-                }
-            })
-            .collect();
 
-        if let ValueSearch::Some(value) = value {
-            let target_value_symbol = self.anno_values.get_symbol(&value.into());
+        if let Some(target_values) = target_values {
+            // Only values that are known can match, each value symbol is only searched once
+            let target_value_symbols: Vec<usize> = target_values
+                .into_iter()
+                .filter_map(|value| self.anno_values.get_symbol(&value.into()))
+                .sorted()
+                .dedup()
+                .collect();
 
-            if let Some(target_value_symbol) = target_value_symbol {
+            if target_value_symbols.is_empty() {
+                // no value is known, return empty result
+                Box::new(std::iter::empty())
+            } else {
                 let it = value_maps
                     .into_iter()
-                    // find the items with the correct value
-                    .filter_map(move |(key, values)| {
-                        if let Some(items) = values.get(&target_value_symbol) {
-                            Some((items, key))
-                        } else {
-                            None
-                        }
+                    // find the items with the correct values
+                    .flat_map(move |(key, values)| {
+                        target_value_symbols
+                            .iter()
+                            .filter_map(|symbol| values.get(symbol))
+                            .map(|items| (items, key.clone()))
+                            .collect_vec()
                     })
                     // flatten the hash set of all items, returns all items for the condition
-                    .flat_map(|(items, key)| items.iter().cloned().zip(std::iter::repeat(key)))
-                    .map(move |item| item.into());
-                Box::new(it)
-            } else {
-                // value is not known, return empty result
-                Box::new(std::iter::empty())
+                    .flat_map(|(items, key)| items.iter().cloned().zip(std::iter::repeat(key)));
+                self.unique_matches(it)
             }
         } else {
-            // Search for all annotations having a matching qualified name, regardless of the value
+            // Items with multiple values match if any of their values is different to the excluded value
+            let excluded_value_symbol = if let ValueSearch::NotSome(value) = value {
+                self.anno_values.get_symbol(&value.into())
+            } else {
+                None
+            };
+            // Search for all annotations having a matching qualified name and a non-excluded value
             let matching_qname_annos = value_maps
                 .into_iter()
                 // flatten the hash set of all items of the value map
-                .flat_map(|(key, values)| {
+                .flat_map(move |(key, values)| {
                     values
                         .iter()
+                        .filter(move |(value, _)| Some(**value) != excluded_value_symbol)
                         .flat_map(|(_, items)| items.iter().cloned())
                         .zip(std::iter::repeat(key))
                 });
-
-            if let ValueSearch::NotSome(value) = value {
-                let value = value.to_string();
-                let it = matching_qname_annos
-                    .filter(move |(item, anno_key)| {
-                        if let Some(item_value) = self.get_value_for_item(item, anno_key) {
-                            item_value != value
-                        } else {
-                            false
-                        }
-                    })
-                    .map(move |item| item.into());
-                Box::new(it)
-            } else {
-                Box::new(matching_qname_annos.map(move |item| item.into()))
-            }
+            self.unique_matches(matching_qname_annos)
         }
     }
 
@@ -596,21 +623,26 @@ where
         let full_match_pattern = util::regex_full_match(pattern);
         let compiled_result = regex::Regex::new(&full_match_pattern);
         if let Ok(re) = compiled_result {
+            // Check the regular expression once for each distinct value. Items with multiple values match if any of
+            // their values matches.
             let it = self
-                .matching_items(namespace, name, None)
-                .filter(move |(node, anno_key)| {
-                    if let Some(val) = self.get_value_for_item(node, anno_key) {
-                        if negated {
-                            !re.is_match(&val)
-                        } else {
-                            re.is_match(&val)
-                        }
-                    } else {
-                        false
-                    }
-                })
-                .map(move |item| item.into());
-            Box::new(it)
+                .value_maps(namespace, name)
+                .into_iter()
+                .flat_map(move |(key, values)| {
+                    let re = re.clone();
+                    values
+                        .iter()
+                        .filter(move |(value, _)| {
+                            if let Some(value) = self.anno_values.get_value_ref(**value) {
+                                re.is_match(value) != negated
+                            } else {
+                                false
+                            }
+                        })
+                        .flat_map(|(_, items)| items.iter().cloned())
+                        .zip(std::iter::repeat(key))
+                });
+            self.unique_matches(it)
         } else if negated {
             // return all values
             self.exact_anno_search(namespace, name, None.into())
@@ -658,7 +690,8 @@ where
         } else if let Some(all_annos) = self.by_container.get(item) {
             // no annotation name given, return all
             let mut result: Vec<Arc<AnnoKey>> = Vec::with_capacity(all_annos.len());
-            for a in all_annos.iter() {
+            // each key is only returned once, even if there are multiple values for it
+            for a in all_annos.iter().dedup_by(|a, b| a.key == b.key) {
                 if let Some(key) = self.anno_keys.get_value(a.key) {
                     result.push(key);
                 }
@@ -738,9 +771,30 @@ where
             let val_prefix = std::str::from_utf8(prefix_set.longest_common_prefix());
 
             if let Ok(lower_val) = val_prefix {
-                let mut upper_val = String::from(lower_val);
-                upper_val.push(std::char::MAX);
-                return self.guess_max_count(ns, name, &lower_val, &upper_val);
+                if !lower_val.is_empty() {
+                    let mut upper_val = String::from(lower_val);
+                    upper_val.push(std::char::MAX);
+                    return self.guess_max_count(ns, name, &lower_val, &upper_val);
+                }
+            }
+            // There is no literal prefix that restricts the range of values, apply the regex to the samples instead
+            if let Ok(re) = regex::Regex::new(&full_match_pattern) {
+                let qualified_keys = match ns {
+                    Some(ns) => vec![AnnoKey {
+                        name: name.into(),
+                        ns: ns.into(),
+                    }],
+                    None => self.get_qnames(&name),
+                };
+                let samples = qualified_keys.into_iter().filter_map(|key| {
+                    let universe_size = *self.anno_key_sizes.get(&key)?;
+                    let histo = self
+                        .anno_keys
+                        .get_symbol(&key)
+                        .and_then(|symbol| self.histogram_bounds.get(&symbol))?;
+                    Some((universe_size, histo.as_slice()))
+                });
+                return guess_max_count_from_samples(&re, samples).unwrap_or(0);
             }
         }
 
@@ -786,6 +840,23 @@ where
         }
     }
 
+    fn get_sampled_values(&self, key: &AnnoKey) -> Vec<(Cow<str>, usize)> {
+        let mut sampled_values: HashMap<&str, usize> = HashMap::default();
+        if let Some(key) = self.anno_keys.get_symbol(key) {
+            if let Some(histo) = self.histogram_bounds.get(&key) {
+                for v in histo.iter() {
+                    let count: &mut usize = sampled_values.entry(v).or_insert(0);
+                    *count += 1;
+                }
+            }
+        }
+        sampled_values
+            .into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)))
+            .map(|(v, count)| (Cow::Borrowed(v), count))
+            .collect()
+    }
+
     fn get_all_values(&self, key: &AnnoKey, most_frequent_first: bool) -> Vec<Cow<str>> {
         if let Some(key) = self.anno_keys.get_symbol(key) {
             if let Some(values_for_key) = self.by_anno.get(&key) {
@@ -813,10 +884,62 @@ where
         return vec![];
     }
 
+    fn get_values_matching_regex(
+        &self,
+        key: &AnnoKey,
+        pattern: &str,
+    ) -> Result<Vec<(Cow<str>, usize)>> {
+        let re = regex::Regex::new(&util::regex_full_match(pattern))?;
+        let mut result = Vec::new();
+        if let Some(key) = self.anno_keys.get_symbol(key) {
+            if let Some(values_for_key) = self.by_anno.get(&key) {
+                for (val, items) in values_for_key.iter() {
+                    if let Some(val) = self.anno_values.get_value_ref(*val) {
+                        if re.is_match(val) {
+                            result.push((Cow::Borrowed(&val[..]), items.len()));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
     fn annotation_keys(&self) -> Vec<AnnoKey> {
         self.anno_key_sizes.keys().cloned().collect()
     }
 
+    fn annotation_key_usage(&self) -> Vec<AnnoKeyUsage> {
+        // Each annotation is an entry in the annotations of the item and the items of the value
+        let bytes_per_anno = std::mem::size_of::<T>() + std::mem::size_of::<SparseAnnotation>();
+        self.anno_key_sizes
+            .keys()
+            .map(|key| {
+                let mut usage = AnnoKeyUsage {
+                    key: key.clone(),
+                    count: 0,
+                    distinct_values: 0,
+                    bytes: 0,
+                };
+                let values_for_key = self
+                    .anno_keys
+                    .get_symbol(key)
+                    .and_then(|key| self.by_anno.get(&key));
+                if let Some(values_for_key) = values_for_key {
+                    for (val, items) in values_for_key {
+                        usage.count += items.len();
+                        usage.distinct_values += 1;
+                        usage.bytes += items.len() * bytes_per_anno;
+                        if let Some(val) = self.anno_values.get_value_ref(*val) {
+                            usage.bytes += std::mem::size_of::<String>() + val.len();
+                        }
+                    }
+                }
+                usage
+            })
+            .collect()
+    }
+
     fn get_largest_item(&self) -> Option<T> {
         self.largest_item.clone()
     }
@@ -900,27 +1023,23 @@ where
         self.clear_internal();
 
         let path = location.join("nodes_v1.bin");
-        let f = std::fs::File::open(path.clone()).map_err(|e| {
-            GraphAnnisCoreError::LoadingAnnotationStorage {
+        *self = encryption::deserialize_from_file(&path).map_err(|e| match e {
+            GraphAnnisCoreError::Io(e) => GraphAnnisCoreError::LoadingAnnotationStorage {
                 path: path.to_string_lossy().to_string(),
                 source: e,
-            }
+            },
+            e => e,
         })?;
-        let mut reader = std::io::BufReader::new(f);
-        *self = bincode::deserialize_from(&mut reader)?;
 
         self.anno_keys.after_deserialization();
         self.anno_values.after_deserialization();
+        self.calculate_number_of_additional_values();
 
         Ok(())
     }
 
     fn save_annotations_to(&self, location: &Path) -> Result<()> {
-        let f = std::fs::File::create(location.join("nodes_v1.bin"))?;
-        let mut writer = std::io::BufWriter::new(f);
-        bincode::serialize_into(&mut writer, self)?;
-
-        Ok(())
+        encryption::serialize_to_file(&location.join("nodes_v1.bin"), self)
     }
 }
 
@@ -928,6 +1047,7 @@ impl AnnoStorageImpl<Edge> {
     pub fn after_deserialization(&mut self) {
         self.anno_keys.after_deserialization();
         self.anno_values.after_deserialization();
+        self.calculate_number_of_additional_values();
     }
 }
 
@@ -1034,4 +1154,234 @@ mod tests {
         assert_eq!(0, a.by_anno.len());
         assert_eq!(&0, a.anno_key_sizes.get(&test_anno.key).unwrap_or(&0));
     }
+
+    #[test]
+    fn exact_anno_search_any_of() {
+        let key = AnnoKey {
+            name: "pos".into(),
+            ns: "default_ns".into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for (node, val) in [(1, "NN"), (2, "NE"), (3, "VVFIN"), (4, "NN")] {
+            a.insert(
+                node,
+                Annotation {
+                    key: key.clone(),
+                    val: val.into(),
+                },
+            )
+            .unwrap();
+        }
+
+        let mut result: Vec<NodeID> = a
+            .exact_anno_search(
+                Some("default_ns"),
+                "pos",
+                ValueSearch::AnyOf(vec!["NN", "NE", "NN", "unknown"]),
+            )
+            .map(|m| m.node)
+            .collect();
+        result.sort_unstable();
+        assert_eq!(vec![1, 2, 4], result);
+
+        assert_eq!(
+            0,
+            a.exact_anno_search(None, "pos", ValueSearch::AnyOf(vec!["unknown"]))
+                .count()
+        );
+    }
+
+    #[test]
+    fn guess_regex_without_prefix() {
+        let key = AnnoKey {
+            name: "lemma".into(),
+            ns: "default_ns".into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for node in 0..100 {
+            let val = if node % 5 == 0 {
+                format!("wort{}ung", node)
+            } else {
+                format!("wort{}", node)
+            };
+            a.insert(
+                node,
+                Annotation {
+                    key: key.clone(),
+                    val: val.into(),
+                },
+            )
+            .unwrap();
+        }
+        a.calculate_statistics();
+
+        let guess = a.guess_max_count_regex(None, "lemma", ".*ung");
+        assert!((10..=30).contains(&guess), "unexpected guess {}", guess);
+        // case-insensitive patterns have no common literal prefix and are applied to the samples
+        let guess = a.guess_max_count_regex(None, "lemma", "(?i)WORT.*UNG");
+        assert!((10..=30).contains(&guess), "unexpected guess {}", guess);
+        // values that are not sampled are still assumed to exist
+        assert!(a.guess_max_count_regex(None, "lemma", ".*xyz") >= 1);
+        assert!(a.guess_max_count_regex(None, "lemma", ".*xyz") <= 2);
+        // unknown annotations have no estimate
+        assert_eq!(0, a.guess_max_count_regex(None, "pos", ".*ung"));
+    }
+
+    #[test]
+    fn multiple_values() {
+        let key = AnnoKey {
+            name: "sense".into(),
+            ns: "wordnet".into(),
+        };
+        let anno = |val: &str| Annotation {
+            key: key.clone(),
+            val: val.into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        a.insert(1, anno("bank%1")).unwrap();
+        a.add_value(1, anno("bank%2")).unwrap();
+        a.add_value(1, anno("bank%2")).unwrap();
+        a.insert(2, anno("bank%2")).unwrap();
+
+        assert_eq!(3, a.number_of_annotations());
+        assert_eq!(vec!["bank%1", "bank%2"], a.get_values_for_item(&1, &key));
+        assert_eq!("bank%1", a.get_value_for_item(&1, &key).unwrap());
+        assert_eq!(1, a.get_all_keys_for_item(&1, None, None).len());
+
+        let search = |a: &AnnoStorageImpl<NodeID>, value: ValueSearch<&str>| -> Vec<NodeID> {
+            a.exact_anno_search(Some("wordnet"), "sense", value)
+                .map(|m| m.node)
+                .sorted()
+                .collect()
+        };
+        // any of the values can match, but each item is only returned once
+        assert_eq!(vec![1], search(&a, ValueSearch::Some("bank%1")));
+        assert_eq!(vec![1, 2], search(&a, ValueSearch::Some("bank%2")));
+        assert_eq!(vec![1, 2], search(&a, ValueSearch::Any));
+        assert_eq!(vec![1], search(&a, ValueSearch::NotSome("bank%2")));
+        assert_eq!(
+            vec![1, 2],
+            search(&a, ValueSearch::AnyOf(vec!["bank%1", "bank%2"]))
+        );
+        let regex_result: Vec<NodeID> = a
+            .regex_anno_search(Some("wordnet"), "sense", "bank.*", false)
+            .map(|m| m.node)
+            .sorted()
+            .collect();
+        assert_eq!(vec![1, 2], regex_result);
+
+        // multiple values are preserved when saving and loading the annotation storage
+        let tmp = tempfile::tempdir().unwrap();
+        a.save_annotations_to(tmp.path()).unwrap();
+        let mut loaded: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        loaded.load_annotations_from(tmp.path()).unwrap();
+        assert_eq!(
+            vec!["bank%1", "bank%2"],
+            loaded.get_values_for_item(&1, &key)
+        );
+        assert_eq!(1, loaded.number_of_additional_values);
+
+        // inserting a value replaces all existing values
+        a.insert(1, anno("bank%3")).unwrap();
+        assert_eq!(vec!["bank%3"], a.get_values_for_item(&1, &key));
+        assert_eq!(2, a.number_of_annotations());
+        assert_eq!(vec![2], search(&a, ValueSearch::Some("bank%2")));
+
+        // removing the annotation removes all values
+        a.add_value(2, anno("bank%4")).unwrap();
+        assert_eq!(
+            Some(Cow::Borrowed("bank%2")),
+            a.remove_annotation_for_item(&2, &key).unwrap()
+        );
+        assert_eq!(1, a.number_of_annotations());
+        assert_eq!(0, a.number_of_additional_values);
+        assert_eq!(vec![1], search(&a, ValueSearch::Any));
+    }
+
+    #[test]
+    fn annotation_key_usage() {
+        let pos_key = AnnoKey {
+            name: "pos".into(),
+            ns: "default_ns".into(),
+        };
+        let id_key = AnnoKey {
+            name: "id".into(),
+            ns: "default_ns".into(),
+        };
+
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for (node, pos) in ["NN", "NN", "DT", "NN"].iter().enumerate() {
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: pos_key.clone(),
+                    val: (*pos).into(),
+                },
+            )
+            .unwrap();
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: id_key.clone(),
+                    val: format!("unique-id-{}", node).into(),
+                },
+            )
+            .unwrap();
+        }
+
+        let usage = a.annotation_key_usage();
+        assert_eq!(2, usage.len());
+        assert_eq!(id_key, usage[0].key);
+        assert_eq!(4, usage[0].count);
+        assert_eq!(4, usage[0].distinct_values);
+        assert_eq!(pos_key, usage[1].key);
+        assert_eq!(4, usage[1].count);
+        assert_eq!(2, usage[1].distinct_values);
+        assert!(usage[0].bytes > usage[1].bytes);
+    }
+
+    #[test]
+    fn values_matching_regex() {
+        let key = AnnoKey {
+            name: "lemma".into(),
+            ns: "default_ns".into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for (node, lemma) in ["walk", "walk", "wall", "talk", "walker"]
+            .iter()
+            .enumerate()
+        {
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: key.clone(),
+                    val: (*lemma).into(),
+                },
+            )
+            .unwrap();
+        }
+
+        let search = |pattern: &str| {
+            let mut result: Vec<(std::string::String, usize)> = a
+                .get_values_matching_regex(&key, pattern)
+                .unwrap()
+                .into_iter()
+                .map(|(val, count)| (val.to_string(), count))
+                .collect();
+            result.sort();
+            result
+        };
+
+        assert_eq!(
+            vec![("walk".to_string(), 2), ("wall".to_string(), 1)],
+            search("wal.")
+        );
+        assert_eq!(
+            vec![("talk".to_string(), 1), ("walk".to_string(), 2)],
+            search(".alk")
+        );
+        assert_eq!(vec![("walker".to_string(), 1)], search("walk.+"));
+        assert!(search("run").is_empty());
+        assert!(a.get_values_matching_regex(&key, "wal(").is_err());
+    }
 }
@@ -5,7 +5,7 @@ pub mod symboltable;
 use smallvec::SmallVec;
 
 use crate::{
-    errors::Result,
+    errors::{GraphAnnisCoreError, Result},
     types::{AnnoKey, Annotation, Edge, NodeID},
 };
 use std::borrow::Cow;
@@ -24,6 +24,20 @@ pub struct Match {
     pub anno_key: Arc<AnnoKey>,
 }
 
+/// Describes how much space the annotations with the same annotation key use in an annotation storage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnoKeyUsage {
+    /// The qualified annotation name.
+    pub key: AnnoKey,
+    /// Number of annotations with this key.
+    pub count: usize,
+    /// Number of distinct values of the annotations with this key.
+    pub distinct_values: usize,
+    /// Estimated number of bytes used by the annotations with this key.
+    /// This is the main memory for the in-memory and the disk space for the on-disk annotation storage.
+    pub bytes: usize,
+}
+
 /// A group of single matched nodes.
 pub type MatchGroup = SmallVec<[Match; 8]>;
 
@@ -86,6 +100,8 @@ pub enum ValueSearch<T> {
     Any,
     Some(T),
     NotSome(T),
+    /// Matches if the value is equal to any of the given values.
+    AnyOf(Vec<T>),
 }
 
 impl<T> From<Option<T>> for ValueSearch<T> {
@@ -99,11 +115,12 @@ impl<T> From<Option<T>> for ValueSearch<T> {
 
 impl<T> ValueSearch<T> {
     #[inline]
-    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> ValueSearch<U> {
+    pub fn map<U, F: FnMut(T) -> U>(self, mut f: F) -> ValueSearch<U> {
         match self {
             ValueSearch::Any => ValueSearch::Any,
             ValueSearch::Some(v) => ValueSearch::Some(f(v)),
             ValueSearch::NotSome(v) => ValueSearch::NotSome(f(v)),
+            ValueSearch::AnyOf(values) => ValueSearch::AnyOf(values.into_iter().map(f).collect()),
         }
     }
 
@@ -113,6 +130,7 @@ impl<T> ValueSearch<T> {
             ValueSearch::Any => ValueSearch::Any,
             ValueSearch::Some(ref v) => ValueSearch::Some(v),
             ValueSearch::NotSome(ref v) => ValueSearch::NotSome(v),
+            ValueSearch::AnyOf(ref values) => ValueSearch::AnyOf(values.iter().collect()),
         }
     }
 }
@@ -123,8 +141,21 @@ where
     T: Send + Sync + MallocSizeOf,
 {
     /// Insert an annotation `anno` (with annotation key and value) for an item `item`.
+    /// All existing values of the item for the same annotation key are replaced.
     fn insert(&mut self, item: T, anno: Annotation) -> Result<()>;
 
+    /// Add the annotation `anno` as additional value for an item `item`, without replacing the existing values for
+    /// the same annotation key.
+    ///
+    /// Searches match an item with multiple values if any of its values matches.
+    ///
+    /// Multiple values are supported by the node annotation storages, which can be changed with the
+    /// [`UpdateEvent::AddNodeLabelValue`](crate::graph::update::UpdateEvent::AddNodeLabelValue) event.
+    /// Annotation storages without support for multiple values return an error.
+    fn add_value(&mut self, _item: T, _anno: Annotation) -> Result<()> {
+        Err(GraphAnnisCoreError::MultipleValuesNotSupported)
+    }
+
     /// Get all the annotation keys of a node, filtered by the optional namespace (`ns`) and `name`.
     fn get_all_keys_for_item(
         &self,
@@ -147,8 +178,14 @@ where
     fn get_annotations_for_item(&self, item: &T) -> Vec<Annotation>;
 
     /// Get the annotation for a given `item` and the annotation `key`.
+    /// If the item has multiple values for this key, only the first one is returned.
     fn get_value_for_item(&self, item: &T, key: &AnnoKey) -> Option<Cow<str>>;
 
+    /// Get all values of the annotation for a given `item` and the annotation `key`.
+    fn get_values_for_item(&self, item: &T, key: &AnnoKey) -> Vec<Cow<str>> {
+        self.get_value_for_item(item, key).into_iter().collect()
+    }
+
     /// Returns `true` if the given `item` has an annotation for the given `key`.
     fn has_value_for_item(&self, item: &T, key: &AnnoKey) -> bool;
 
@@ -177,6 +214,7 @@ where
     /// - `namespace`- If given, only annotations having this namespace are returned.
     /// - `name`  - Only annotations with this name are returned.
     /// - `value` - Constrain the value of the annotation.
+    ///   Use [`ValueSearch::AnyOf`] to search for several alternative values in one scan.
     ///
     /// The result is an iterator over matches.
     /// A match contains the node ID and the qualifed name of the matched annotation
@@ -235,13 +273,37 @@ where
     /// If more than one qualified annotation name matches the defnition, the more frequent value is used.
     fn guess_most_frequent_value(&self, ns: Option<&str>, name: &str) -> Option<Cow<str>>;
 
+    /// Get the values of the annotation `key` that have been sampled for the statistics, together with the number of
+    /// times each value has been sampled. The values are sorted by this number in descending order, so the most
+    /// frequent values come first.
+    ///
+    /// Returns an empty list if no statistics have been calculated for this key.
+    fn get_sampled_values(&self, key: &AnnoKey) -> Vec<(Cow<str>, usize)>;
+
     /// Return a list of all existing values for a given annotation `key`.
     /// If the `most_frequent_first` parameter is true, the results are sorted by their frequency.
     fn get_all_values(&self, key: &AnnoKey, most_frequent_first: bool) -> Vec<Cow<str>>;
 
+    /// Return all distinct values for the annotation `key` that match the regular expression `pattern`, together
+    /// with the number of annotations having this value. The values are returned in no particular order.
+    ///
+    /// Only the values are checked against the pattern, the items having the annotation are not retrieved.
+    fn get_values_matching_regex(
+        &self,
+        key: &AnnoKey,
+        pattern: &str,
+    ) -> Result<Vec<(Cow<str>, usize)>>;
+
     /// Get all the annotation keys which are part of this annotation storage
     fn annotation_keys(&self) -> Vec<AnnoKey>;
 
+    /// Return the number of annotations, distinct values and the estimated size for each annotation key,
+    /// sorted by the annotation key.
+    ///
+    /// This can be used to find the annotation keys that are responsible for most of the used space,
+    /// e.g. an annotation with a unique value for each node.
+    fn annotation_key_usage(&self) -> Vec<AnnoKeyUsage>;
+
     /// Return the item with the largest item which has an annotation value in this annotation storage.
     ///
     /// This can be used to calculate new IDs for new items.
@@ -259,3 +321,36 @@ where
     /// Save the current annotation to a `location` on the disk, but do not remember this location.
     fn save_annotations_to(&self, location: &Path) -> Result<()>;
 }
+
+/// Estimate the number of annotations with a value that matches the regular expression, by applying it to the
+/// sampled values of the histograms.
+///
+/// This is used for patterns without a literal prefix, which can't be mapped to a range of values.
+/// Each item of `samples` is the number of annotations for an annotation key and the histogram bounds for it.
+/// Returns `None` if there are no sampled values.
+pub(crate) fn guess_max_count_from_samples<'a, I, S>(
+    regex: &regex::Regex,
+    samples: I,
+) -> Option<usize>
+where
+    I: IntoIterator<Item = (usize, &'a [S])>,
+    S: AsRef<str> + 'a,
+{
+    let mut has_samples = false;
+    let mut result = 0.0;
+    for (universe_size, values) in samples {
+        if values.is_empty() {
+            continue;
+        }
+        has_samples = true;
+        let hits = values.iter().filter(|v| regex.is_match(v.as_ref())).count();
+        // A value that is not part of the samples can still exist, so never assume there is no match at all
+        let hits = if hits == 0 { 0.5 } else { hits as f64 };
+        result += hits / (values.len() as f64) * (universe_size as f64);
+    }
+    if has_samples {
+        Some(result.round() as usize)
+    } else {
+        None
+    }
+}
@@ -1,12 +1,13 @@
 use crate::annostorage::symboltable::SymbolTable;
-use crate::annostorage::AnnotationStorage;
+use crate::annostorage::{AnnoKeyUsage, AnnotationStorage};
 use crate::annostorage::{Match, ValueSearch};
 use crate::errors::Result;
 use crate::serializer::{FixedSizeKeySerializer, KeySerializer};
 use crate::types::{AnnoKey, Annotation, NodeID};
 use crate::util::disk_collections::{DiskMap, EvictionStrategy};
-use crate::util::{self, memory_estimation};
+use crate::util::{self, encryption, memory_estimation};
 use core::ops::Bound::*;
+use itertools::Itertools;
 use rand::seq::IteratorRandom;
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
@@ -15,7 +16,7 @@ use std::sync::Arc;
 
 use smartstring::alias::String as SmartString;
 
-use super::MatchGroup;
+use super::{guess_max_count_from_samples, MatchGroup};
 
 pub const SUBFOLDER_NAME: &str = "nodes_diskmap_v1";
 
@@ -46,6 +47,9 @@ where
     by_container: DiskMap<Vec<u8>, String>,
     #[ignore_malloc_size_of = "is stored on disk"]
     by_anno_qname: DiskMap<Vec<u8>, bool>,
+    /// Additional values of an item for an annotation key, which already has a (first) value in `by_container`.
+    #[ignore_malloc_size_of = "is stored on disk"]
+    additional_values: DiskMap<Vec<u8>, bool>,
     #[with_malloc_size_of_func = "memory_estimation::size_of_pathbuf"]
     location: PathBuf,
     /// A handle to a temporary directory. This must be part of the struct because the temporary directory will
@@ -62,6 +66,8 @@ where
     #[with_malloc_size_of_func = "memory_estimation::size_of_btreemap"]
     histogram_bounds: BTreeMap<AnnoKey, Vec<String>>,
     largest_item: Option<T>,
+    /// Number of entries in `additional_values`. This is re-calculated when loading the annotation storage.
+    number_of_additional_values: usize,
 
     phantom: std::marker::PhantomData<T>,
 }
@@ -103,6 +109,35 @@ fn create_by_anno_qname_key<T: FixedSizeKeySerializer>(
     result
 }
 
+/// Creates a key for the `additional_values` tree.
+///
+/// Structure:
+/// ```text
+/// [x Bits item ID][64 Bits symbol ID][Value]
+/// ```
+fn create_additional_value_key<T: FixedSizeKeySerializer>(
+    item: T,
+    anno_key_symbol: usize,
+    anno_value: &str,
+) -> Vec<u8> {
+    let mut result = create_by_container_key(item, anno_key_symbol);
+    result.extend_from_slice(anno_value.as_bytes());
+    result
+}
+
+/// Returns the longest literal prefix all values matching the (full match) regular expression have in common,
+/// or `None` if there is no such prefix.
+fn regex_literal_prefix(full_match_pattern: &str) -> Option<String> {
+    let expr = regex_syntax::Parser::new().parse(full_match_pattern).ok()?;
+    let prefix_set = regex_syntax::hir::literal::Literals::prefixes(&expr);
+    let prefix = std::str::from_utf8(prefix_set.longest_common_prefix()).ok()?;
+    if prefix.is_empty() {
+        None
+    } else {
+        Some(prefix.to_string())
+    }
+}
+
 impl<T> AnnoStorageImpl<T>
 where
     T: FixedSizeKeySerializer
@@ -119,6 +154,7 @@ where
         if let Some(path) = path {
             let path_by_container = path.join("by_container.bin");
             let path_by_anno_qname = path.join("by_anno_qname.bin");
+            let path_additional_values = path.join("additional_values.bin");
 
             let mut result = AnnoStorageImpl {
                 by_container: DiskMap::new(Some(&path_by_container), EvictionStrategy::default())?,
@@ -126,9 +162,14 @@ where
                     Some(&path_by_anno_qname),
                     EvictionStrategy::default(),
                 )?,
+                additional_values: DiskMap::new(
+                    Some(&path_additional_values),
+                    EvictionStrategy::default(),
+                )?,
                 anno_key_symbols: SymbolTable::default(),
                 anno_key_sizes: BTreeMap::new(),
                 largest_item: None,
+                number_of_additional_values: 0,
                 histogram_bounds: BTreeMap::new(),
                 location: path.clone(),
                 temp_dir: None,
@@ -144,6 +185,7 @@ where
             result.histogram_bounds = bincode::deserialize_from(&mut reader)?;
             result.anno_key_symbols = bincode::deserialize_from(&mut reader)?;
             result.anno_key_symbols.after_deserialization();
+            result.number_of_additional_values = result.additional_values.iter().count();
 
             Ok(result)
         } else {
@@ -153,9 +195,11 @@ where
             Ok(AnnoStorageImpl {
                 by_container: DiskMap::default(),
                 by_anno_qname: DiskMap::default(),
+                additional_values: DiskMap::default(),
                 anno_key_symbols: SymbolTable::default(),
                 anno_key_sizes: BTreeMap::new(),
                 largest_item: None,
+                number_of_additional_values: 0,
                 histogram_bounds: BTreeMap::new(),
                 location: tmp_dir.as_ref().to_path_buf(),
                 temp_dir: Some(tmp_dir),
@@ -271,6 +315,35 @@ where
         )
     }
 
+    /// Find all items with one of the given annotation keys and a value that starts with `prefix` and matches
+    /// the regular expression `re`.
+    /// Since the value is part of the key of the index, it is not necessary to fetch the value for each item.
+    fn regex_prefix_search<'a>(
+        &'a self,
+        qualified_keys: Vec<AnnoKey>,
+        prefix: String,
+        re: regex::Regex,
+    ) -> Box<dyn Iterator<Item = Match> + 'a> {
+        let it = qualified_keys
+            .into_iter()
+            .filter_map(move |key| {
+                let anno_key_symbol = self.anno_key_symbols.get_symbol(&key)?;
+                let mut key_prefix = Vec::from(anno_key_symbol.create_key());
+                key_prefix.extend(prefix.as_bytes());
+                Some(self.by_anno_qname.prefix(&key_prefix))
+            })
+            .flatten()
+            .filter_map(move |(data, _)| {
+                let (item, anno_key, value) = self.parse_by_anno_qname_key(data);
+                if re.is_match(&value) {
+                    Some((item, anno_key).into())
+                } else {
+                    None
+                }
+            });
+        Box::new(it)
+    }
+
     fn get_by_anno_qname_range<'a>(
         &'a self,
         anno_key: &AnnoKey,
@@ -289,6 +362,50 @@ where
             Box::from(std::iter::empty())
         }
     }
+
+    /// Get the additional values (without the first value) of an item for the annotation key.
+    fn get_additional_values(&self, item: T, anno_key_symbol: usize) -> Vec<String> {
+        if self.number_of_additional_values == 0 {
+            return Vec::new();
+        }
+        let prefix = create_by_container_key(item, anno_key_symbol);
+        self.additional_values
+            .prefix(&prefix)
+            .map(|(data, _)| String::from_utf8(data[prefix.len()..].to_vec()).expect(UTF_8_MSG))
+            .collect()
+    }
+
+    /// Remove all additional values of an item for the annotation key.
+    /// Returns the number of removed values.
+    fn remove_additional_values(&mut self, item: &T, anno_key_symbol: usize) -> Result<usize> {
+        let values = self.get_additional_values(item.clone(), anno_key_symbol);
+        for val in values.iter() {
+            self.additional_values.remove(&create_additional_value_key(
+                item.clone(),
+                anno_key_symbol,
+                val,
+            ))?;
+            self.by_anno_qname.remove(&create_by_anno_qname_key(
+                item.clone(),
+                anno_key_symbol,
+                val,
+            ))?;
+        }
+        self.number_of_additional_values -= values.len();
+        Ok(values.len())
+    }
+
+    /// Remove duplicate matches, which can occur if an item has multiple values for the same annotation key.
+    fn unique_matches<'a, I>(&self, it: I) -> Box<dyn Iterator<Item = Match> + 'a>
+    where
+        I: Iterator<Item = Match> + 'a,
+    {
+        if self.number_of_additional_values > 0 {
+            Box::new(it.unique())
+        } else {
+            Box::new(it)
+        }
+    }
 }
 
 impl<'de, T> AnnotationStorage<T> for AnnoStorageImpl<T>
@@ -311,7 +428,18 @@ where
         // insert the value into main tree
         let by_container_key = create_by_container_key(item.clone(), anno_key_symbol);
 
-        let already_existed = self.by_container.try_contains_key(&by_container_key)?;
+        let existing_value = self.by_container.try_get(&by_container_key)?;
+        let already_existed = existing_value.is_some();
+        if let Some(existing_value) = existing_value {
+            if existing_value != anno.val.as_str() {
+                // the replaced value must not be found in the index anymore
+                self.by_anno_qname.remove(&create_by_anno_qname_key(
+                    item.clone(),
+                    anno_key_symbol,
+                    &existing_value,
+                ))?;
+            }
+        }
         self.by_container
             .insert(by_container_key, anno.val.clone().into())?;
 
@@ -342,22 +470,71 @@ where
 
             let anno_key_entry = self.anno_key_sizes.entry(anno.key).or_insert(0);
             *anno_key_entry += 1;
+        } else {
+            // all existing additional values are replaced by the new value
+            let removed_values = self.remove_additional_values(&item, anno_key_symbol)?;
+            if let Some(num_of_keys) = self.anno_key_sizes.get_mut(&anno.key) {
+                *num_of_keys -= removed_values;
+            }
         }
 
         Ok(())
     }
 
+    fn add_value(&mut self, item: T, anno: Annotation) -> Result<()> {
+        let anno_key_symbol = self.anno_key_symbols.insert(anno.key.clone());
+        let by_container_key = create_by_container_key(item.clone(), anno_key_symbol);
+
+        match self.by_container.try_get(&by_container_key)? {
+            // this is the first value for the annotation key
+            None => self.insert(item, anno),
+            Some(first_value) if first_value == anno.val.as_str() => Ok(()),
+            Some(_) => {
+                let additional_value_key =
+                    create_additional_value_key(item.clone(), anno_key_symbol, &anno.val);
+                // abort if the same annotation key with the same value already exist
+                if self
+                    .additional_values
+                    .try_contains_key(&additional_value_key)?
+                {
+                    return Ok(());
+                }
+                self.additional_values.insert(additional_value_key, true)?;
+                if self.additional_values.number_of_disk_tables() > 7 {
+                    self.additional_values.compact()?;
+                }
+                self.by_anno_qname.insert(
+                    create_by_anno_qname_key(item, anno_key_symbol, &anno.val),
+                    true,
+                )?;
+                self.number_of_additional_values += 1;
+
+                let anno_key_entry = self.anno_key_sizes.entry(anno.key).or_insert(0);
+                *anno_key_entry += 1;
+
+                Ok(())
+            }
+        }
+    }
+
     fn get_annotations_for_item(&self, item: &T) -> Vec<Annotation> {
         let mut result = Vec::default();
         let start = create_by_container_key(item.clone(), usize::min_value());
         let end = create_by_container_key(item.clone(), usize::max_value());
         for (key, val) in self.by_container.range(start..=end) {
+            let anno_key_symbol = usize::parse_key(&key[T::key_size()..]);
             let parsed_key = self.parse_by_container_key(key);
-            let anno = Annotation {
-                key: parsed_key.1.as_ref().clone(),
+            let anno_key = parsed_key.1.as_ref();
+            result.push(Annotation {
+                key: anno_key.clone(),
                 val: val.into(),
-            };
-            result.push(anno);
+            });
+            for additional_value in self.get_additional_values(item.clone(), anno_key_symbol) {
+                result.push(Annotation {
+                    key: anno_key.clone(),
+                    val: additional_value.into(),
+                });
+            }
         }
 
         result
@@ -379,10 +556,11 @@ where
                     symbol_id,
                     &anno.val,
                 ))?;
+                let removed_values = 1 + self.remove_additional_values(item, symbol_id)?;
                 // decrease the annotation count for this key
                 let new_key_count: usize =
                     if let Some(num_of_keys) = self.anno_key_sizes.get_mut(key) {
-                        *num_of_keys -= 1;
+                        *num_of_keys -= removed_values;
                         *num_of_keys
                     } else {
                         0
@@ -404,8 +582,10 @@ where
     fn clear(&mut self) -> Result<()> {
         self.by_container.clear();
         self.by_anno_qname.clear();
+        self.additional_values.clear();
 
         self.largest_item = None;
+        self.number_of_additional_values = 0;
         self.anno_key_sizes.clear();
         self.histogram_bounds.clear();
 
@@ -431,7 +611,7 @@ where
     }
 
     fn number_of_annotations(&self) -> usize {
-        self.by_container.iter().count()
+        self.by_container.iter().count() + self.number_of_additional_values
     }
 
     fn is_empty(&self) -> bool {
@@ -450,6 +630,24 @@ where
         None
     }
 
+    fn get_values_for_item(&self, item: &T, key: &AnnoKey) -> Vec<Cow<str>> {
+        if let Some(symbol_id) = self.anno_key_symbols.get_symbol(key) {
+            if let Some(first_value) = self
+                .by_container
+                .get(&create_by_container_key(item.clone(), symbol_id))
+            {
+                let mut result = vec![Cow::Owned(first_value)];
+                result.extend(
+                    self.get_additional_values(item.clone(), symbol_id)
+                        .into_iter()
+                        .map(Cow::Owned),
+                );
+                return result;
+            }
+        }
+        Vec::new()
+    }
+
     fn has_value_for_item(&self, item: &T, key: &AnnoKey) -> bool {
         if let Some(symbol_id) = self.anno_key_symbols.get_symbol(key) {
             self.by_container
@@ -567,7 +765,7 @@ where
                 let it = self
                     .matching_items(namespace, name, None)
                     .map(move |item| item.into());
-                Box::new(it)
+                self.unique_matches(it)
             }
             ValueSearch::Some(value) => {
                 let it = self
@@ -575,19 +773,30 @@ where
                     .map(move |item| item.into());
                 Box::new(it)
             }
+            ValueSearch::AnyOf(values) => {
+                let mut values = values;
+                values.sort_unstable();
+                values.dedup();
+                // each value is a separate range scan on the sorted keys
+                let ranges: Vec<_> = values
+                    .into_iter()
+                    .map(|value| self.matching_items(namespace, name, Some(value)))
+                    .collect();
+                let it = ranges.into_iter().flatten().map(move |item| item.into());
+                self.unique_matches(it)
+            }
             ValueSearch::NotSome(value) => {
                 let value = value.to_string();
+                // Items with multiple values match if any of their values is different to the excluded value
                 let it = self
                     .matching_items(namespace, name, None)
                     .filter(move |(node, anno_key)| {
-                        if let Some(item_value) = self.get_value_for_item(node, anno_key) {
-                            item_value != value
-                        } else {
-                            false
-                        }
+                        self.get_values_for_item(node, anno_key)
+                            .iter()
+                            .any(|item_value| item_value != &value)
                     })
                     .map(move |item| item.into());
-                Box::new(it)
+                self.unique_matches(it)
             }
         }
     }
@@ -602,21 +811,30 @@ where
         let full_match_pattern = util::regex_full_match(pattern);
         let compiled_result = regex::Regex::new(&full_match_pattern);
         if let Ok(re) = compiled_result {
+            if !negated {
+                if let Some(prefix) = regex_literal_prefix(&full_match_pattern) {
+                    // Only scan the values that start with the literal prefix of the pattern
+                    let qualified_keys = match namespace {
+                        Some(ns) => vec![AnnoKey {
+                            name: name.into(),
+                            ns: ns.into(),
+                        }],
+                        None => self.get_qnames(name),
+                    };
+                    let it = self.regex_prefix_search(qualified_keys, prefix, re);
+                    return self.unique_matches(it);
+                }
+            }
+            // Items with multiple values match if any of their values matches
             let it = self
                 .matching_items(namespace, name, None)
                 .filter(move |(node, anno_key)| {
-                    if let Some(val) = self.get_value_for_item(node, anno_key) {
-                        if negated {
-                            !re.is_match(&val)
-                        } else {
-                            re.is_match(&val)
-                        }
-                    } else {
-                        false
-                    }
+                    self.get_values_for_item(node, anno_key)
+                        .iter()
+                        .any(|val| re.is_match(val) != negated)
                 })
                 .map(move |item| item.into());
-            Box::new(it)
+            self.unique_matches(it)
         } else if negated {
             // return all values
             self.exact_anno_search(namespace, name, None.into())
@@ -666,9 +884,11 @@ where
             }
         } else {
             // no annotation name given, return all
-            self.get_annotations_for_item(item)
-                .into_iter()
-                .map(|anno| Arc::from(anno.key))
+            let start = create_by_container_key(item.clone(), usize::min_value());
+            let end = create_by_container_key(item.clone(), usize::max_value());
+            self.by_container
+                .range(start..=end)
+                .map(|(data, _)| self.parse_by_container_key(data).1)
                 .collect()
         }
     }
@@ -732,17 +952,28 @@ where
         let full_match_pattern = util::regex_full_match(pattern);
 
         let parsed = regex_syntax::Parser::new().parse(&full_match_pattern);
-        if let Ok(parsed) = parsed {
-            let expr: regex_syntax::hir::Hir = parsed;
-
-            let prefix_set = regex_syntax::hir::literal::Literals::prefixes(&expr);
-            let val_prefix = std::str::from_utf8(prefix_set.longest_common_prefix());
-
-            if let Ok(lower_val) = val_prefix {
-                let mut upper_val = String::from(lower_val);
+        if parsed.is_ok() {
+            if let Some(lower_val) = regex_literal_prefix(&full_match_pattern) {
+                let mut upper_val = lower_val.clone();
                 upper_val.push(std::char::MAX);
                 return self.guess_max_count(ns, name, &lower_val, &upper_val);
             }
+            // There is no literal prefix that restricts the range of values, apply the regex to the samples instead
+            if let Ok(re) = regex::Regex::new(&full_match_pattern) {
+                let qualified_keys = match ns {
+                    Some(ns) => vec![AnnoKey {
+                        name: name.into(),
+                        ns: ns.into(),
+                    }],
+                    None => self.get_qnames(&name),
+                };
+                let samples = qualified_keys.into_iter().filter_map(|key| {
+                    let universe_size = *self.anno_key_sizes.get(&key)?;
+                    let histo = self.histogram_bounds.get(&key)?;
+                    Some((universe_size, histo.as_slice()))
+                });
+                return guess_max_count_from_samples(&re, samples).unwrap_or(0);
+            }
         }
 
         0
@@ -785,6 +1016,21 @@ where
         }
     }
 
+    fn get_sampled_values(&self, key: &AnnoKey) -> Vec<(Cow<str>, usize)> {
+        let mut sampled_values: HashMap<&str, usize> = HashMap::default();
+        if let Some(histo) = self.histogram_bounds.get(key) {
+            for v in histo.iter() {
+                let count: &mut usize = sampled_values.entry(v).or_insert(0);
+                *count += 1;
+            }
+        }
+        sampled_values
+            .into_iter()
+            .sorted_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)))
+            .map(|(v, count)| (Cow::Borrowed(v), count))
+            .collect()
+    }
+
     fn get_all_values(&self, key: &AnnoKey, most_frequent_first: bool) -> Vec<Cow<str>> {
         if most_frequent_first {
             let mut values_with_count: HashMap<String, usize> = HashMap::default();
@@ -801,6 +1047,7 @@ where
             values_with_count.sort();
             values_with_count
                 .into_iter()
+                .rev()
                 .map(|(_count, val)| val)
                 .collect()
         } else {
@@ -815,10 +1062,83 @@ where
         }
     }
 
+    fn get_values_matching_regex(
+        &self,
+        key: &AnnoKey,
+        pattern: &str,
+    ) -> Result<Vec<(Cow<str>, usize)>> {
+        let full_match_pattern = util::regex_full_match(pattern);
+        let re = regex::Regex::new(&full_match_pattern)?;
+        let anno_key_symbol = if let Some(symbol) = self.anno_key_symbols.get_symbol(key) {
+            symbol
+        } else {
+            return Ok(Vec::new());
+        };
+
+        // Only scan the values that start with the literal prefix of the pattern
+        let mut key_prefix = Vec::from(anno_key_symbol.create_key());
+        if let Some(prefix) = regex_literal_prefix(&full_match_pattern) {
+            key_prefix.extend(prefix.as_bytes());
+        }
+
+        // The entries are sorted by value, so all entries with the same value are next to each other
+        let mut result: Vec<(Cow<str>, usize)> = Vec::new();
+        let mut last_value: Option<(String, bool)> = None;
+        for (data, _) in self.by_anno_qname.prefix(&key_prefix) {
+            let (_, _, val) = self.parse_by_anno_qname_key(data);
+            match &last_value {
+                Some((last, is_match)) if *last == val => {
+                    if *is_match {
+                        if let Some((_, count)) = result.last_mut() {
+                            *count += 1;
+                        }
+                    }
+                }
+                _ => {
+                    let is_match = re.is_match(&val);
+                    if is_match {
+                        result.push((Cow::Owned(val.clone()), 1));
+                    }
+                    last_value = Some((val, is_match));
+                }
+            }
+        }
+        Ok(result)
+    }
+
     fn annotation_keys(&self) -> Vec<AnnoKey> {
         self.anno_key_sizes.keys().cloned().collect()
     }
 
+    fn annotation_key_usage(&self) -> Vec<AnnoKeyUsage> {
+        self.anno_key_sizes
+            .keys()
+            .map(|key| {
+                let mut usage = AnnoKeyUsage {
+                    key: key.clone(),
+                    count: 0,
+                    distinct_values: 0,
+                    bytes: 0,
+                };
+                // The entries are sorted by value, so all entries with the same value are next to each other
+                let mut last_value: Option<String> = None;
+                for (data, _) in self.get_by_anno_qname_range(key) {
+                    // Each annotation has an entry in both trees: the item and the annotation key symbol are the
+                    // key of the `by_container` entry and the value its value
+                    let qname_key_size = data.len();
+                    let (_, _, val) = self.parse_by_anno_qname_key(data);
+                    usage.count += 1;
+                    usage.bytes += 2 * qname_key_size;
+                    if last_value.as_ref() != Some(&val) {
+                        usage.distinct_values += 1;
+                        last_value = Some(val);
+                    }
+                }
+                usage
+            })
+            .collect()
+    }
+
     fn get_largest_item(&self) -> Option<T> {
         self.largest_item.clone()
     }
@@ -894,6 +1214,11 @@ where
                 Some(&location.join("by_anno_qname.bin")),
                 EvictionStrategy::default(),
             )?;
+            // Older versions did not write the additional values, which is the same as having none
+            self.additional_values = DiskMap::new(
+                Some(&location.join("additional_values.bin")),
+                EvictionStrategy::default(),
+            )?;
         }
 
         // load internal helper fields
@@ -904,11 +1229,13 @@ where
         self.histogram_bounds = bincode::deserialize_from(&mut reader)?;
         self.anno_key_symbols = bincode::deserialize_from(&mut reader)?;
         self.anno_key_symbols.after_deserialization();
+        self.number_of_additional_values = self.additional_values.iter().count();
 
         Ok(())
     }
 
     fn save_annotations_to(&self, location: &Path) -> Result<()> {
+        encryption::check_no_key("disk-based annotation storage")?;
         let location = location.join(SUBFOLDER_NAME);
 
         // write out the disk maps to a single sorted string table
@@ -916,6 +1243,8 @@ where
             .write_to(&location.join("by_container.bin"))?;
         self.by_anno_qname
             .write_to(&location.join("by_anno_qname.bin"))?;
+        self.additional_values
+            .write_to(&location.join("additional_values.bin"))?;
 
         // save the other custom fields
         let f = std::fs::File::create(location.join("custom.bin"))?;
@@ -1041,4 +1370,204 @@ mod tests {
         assert_eq!(0, a.number_of_annotations());
         assert_eq!(&0, a.anno_key_sizes.get(&test_anno.key).unwrap_or(&0));
     }
+
+    #[test]
+    fn regex_search_with_prefix() {
+        LOGGER_INIT.call_once(|| env_logger::init());
+
+        let mut a = AnnoStorageImpl::new(None).unwrap();
+        for (node, ns, val) in [
+            (1, "ns1", "root/doc1#tok1"),
+            (2, "ns1", "root/doc1#tok2"),
+            (3, "ns1", "root/doc2#tok1"),
+            (4, "ns2", "root/doc1#tok3"),
+        ] {
+            let anno = Annotation {
+                key: AnnoKey {
+                    name: "node_name".into(),
+                    ns: ns.into(),
+                },
+                val: val.into(),
+            };
+            a.insert(node, anno).unwrap();
+        }
+
+        let search = |ns: Option<&str>, pattern: &str, negated: bool| -> Vec<NodeID> {
+            let mut result: Vec<NodeID> = a
+                .regex_anno_search(ns, "node_name", pattern, negated)
+                .map(|m| m.node)
+                .collect();
+            result.sort_unstable();
+            result
+        };
+
+        assert_eq!(vec![1, 2, 4], search(None, "root/doc1#.*", false));
+        assert_eq!(vec![1, 2], search(Some("ns1"), "root/doc1#.*", false));
+        assert_eq!(vec![1, 3], search(None, "root/doc.#tok1", false));
+        assert_eq!(vec![2, 4], search(None, "root/doc1#tok[2-9]", false));
+        assert!(search(None, "other.*", false).is_empty());
+        assert_eq!(vec![3], search(None, "root/doc1#.*", true));
+    }
+
+    #[test]
+    fn annotation_key_usage() {
+        LOGGER_INIT.call_once(|| env_logger::init());
+        let pos_key = AnnoKey {
+            name: "pos".into(),
+            ns: "default_ns".into(),
+        };
+        let id_key = AnnoKey {
+            name: "id".into(),
+            ns: "default_ns".into(),
+        };
+
+        let mut a = AnnoStorageImpl::new(None).unwrap();
+        for (node, pos) in ["NN", "NN", "DT", "NN"].iter().enumerate() {
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: pos_key.clone(),
+                    val: (*pos).into(),
+                },
+            )
+            .unwrap();
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: id_key.clone(),
+                    val: format!("unique-id-{}", node).into(),
+                },
+            )
+            .unwrap();
+        }
+
+        let usage = a.annotation_key_usage();
+        assert_eq!(2, usage.len());
+        assert_eq!(id_key, usage[0].key);
+        assert_eq!(4, usage[0].count);
+        assert_eq!(4, usage[0].distinct_values);
+        assert_eq!(pos_key, usage[1].key);
+        assert_eq!(4, usage[1].count);
+        assert_eq!(2, usage[1].distinct_values);
+        assert!(usage[0].bytes > usage[1].bytes);
+    }
+
+    #[test]
+    fn values_matching_regex() {
+        LOGGER_INIT.call_once(|| env_logger::init());
+
+        let key = AnnoKey {
+            name: "lemma".into(),
+            ns: "default_ns".into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new(None).unwrap();
+        for (node, lemma) in ["walk", "walk", "wall", "talk", "walker"]
+            .iter()
+            .enumerate()
+        {
+            a.insert(
+                node as NodeID,
+                Annotation {
+                    key: key.clone(),
+                    val: (*lemma).into(),
+                },
+            )
+            .unwrap();
+        }
+
+        let search = |pattern: &str| {
+            let mut result: Vec<(std::string::String, usize)> = a
+                .get_values_matching_regex(&key, pattern)
+                .unwrap()
+                .into_iter()
+                .map(|(val, count)| (val.to_string(), count))
+                .collect();
+            result.sort();
+            result
+        };
+
+        assert_eq!(
+            vec![("walk".to_string(), 2), ("wall".to_string(), 1)],
+            search("wal.")
+        );
+        assert_eq!(
+            vec![("talk".to_string(), 1), ("walk".to_string(), 2)],
+            search(".alk")
+        );
+        assert_eq!(vec![("walker".to_string(), 1)], search("walk.+"));
+        assert!(search("run").is_empty());
+        assert!(a.get_values_matching_regex(&key, "wal(").is_err());
+    }
+
+    #[test]
+    fn multiple_values() {
+        let key = AnnoKey {
+            name: "sense".into(),
+            ns: "wordnet".into(),
+        };
+        let anno = |val: &str| Annotation {
+            key: key.clone(),
+            val: val.into(),
+        };
+        let mut a: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new(None).unwrap();
+        a.insert(1, anno("bank%1")).unwrap();
+        a.add_value(1, anno("bank%2")).unwrap();
+        a.add_value(1, anno("bank%2")).unwrap();
+        a.insert(2, anno("bank%2")).unwrap();
+
+        assert_eq!(3, a.number_of_annotations());
+        assert_eq!(vec!["bank%1", "bank%2"], a.get_values_for_item(&1, &key));
+        assert_eq!("bank%1", a.get_value_for_item(&1, &key).unwrap());
+        assert_eq!(2, a.get_annotations_for_item(&1).len());
+        assert_eq!(1, a.get_all_keys_for_item(&1, None, None).len());
+
+        let search = |a: &AnnoStorageImpl<NodeID>, value: ValueSearch<&str>| -> Vec<NodeID> {
+            a.exact_anno_search(Some("wordnet"), "sense", value)
+                .map(|m| m.node)
+                .sorted()
+                .collect()
+        };
+        // any of the values can match, but each item is only returned once
+        assert_eq!(vec![1], search(&a, ValueSearch::Some("bank%1")));
+        assert_eq!(vec![1, 2], search(&a, ValueSearch::Some("bank%2")));
+        assert_eq!(vec![1, 2], search(&a, ValueSearch::Any));
+        assert_eq!(vec![1], search(&a, ValueSearch::NotSome("bank%2")));
+        assert_eq!(
+            vec![1, 2],
+            search(&a, ValueSearch::AnyOf(vec!["bank%1", "bank%2"]))
+        );
+        let regex_result: Vec<NodeID> = a
+            .regex_anno_search(Some("wordnet"), "sense", "bank.*", false)
+            .map(|m| m.node)
+            .sorted()
+            .collect();
+        assert_eq!(vec![1, 2], regex_result);
+
+        // multiple values are preserved when saving and loading the annotation storage
+        let tmp = tempfile::tempdir().unwrap();
+        a.save_annotations_to(tmp.path()).unwrap();
+        let mut loaded: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new(None).unwrap();
+        loaded.load_annotations_from(tmp.path()).unwrap();
+        assert_eq!(
+            vec!["bank%1", "bank%2"],
+            loaded.get_values_for_item(&1, &key)
+        );
+        assert_eq!(1, loaded.number_of_additional_values);
+
+        // inserting a value replaces all existing values
+        a.insert(1, anno("bank%3")).unwrap();
+        assert_eq!(vec!["bank%3"], a.get_values_for_item(&1, &key));
+        assert_eq!(2, a.number_of_annotations());
+        assert_eq!(vec![2], search(&a, ValueSearch::Some("bank%2")));
+
+        // removing the annotation removes all values
+        a.add_value(2, anno("bank%4")).unwrap();
+        assert_eq!(
+            Some(Cow::Owned("bank%2".to_string())),
+            a.remove_annotation_for_item(&2, &key).unwrap()
+        );
+        assert_eq!(1, a.number_of_annotations());
+        assert_eq!(0, a.number_of_additional_values);
+        assert_eq!(vec![1], search(&a, ValueSearch::Any));
+    }
 }
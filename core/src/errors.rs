@@ -16,6 +16,12 @@ pub enum GraphAnnisCoreError {
     },
     #[error("could not find implementation for graph storage with name '{0}'")]
     UnknownGraphStorageImpl(String),
+    #[error("graph storage implementation {impl_name} can not be used for component {component}: {reason}")]
+    GraphStorageImplNotApplicable {
+        impl_name: String,
+        component: String,
+        reason: String,
+    },
     #[error("can't load component with empty path")]
     EmptyComponentPath,
     #[error("could not find annotation key ID for {0:?} when mapping to GraphML")]
@@ -26,8 +32,45 @@ pub enum GraphAnnisCoreError {
     MissingComponent(String),
     #[error("component {0} was not loaded")]
     ComponentNotLoaded(String),
+    #[error("could not load component {component}: {source}")]
+    LoadingComponent {
+        component: String,
+        source: Box<GraphAnnisCoreError>,
+    },
+    #[error(
+        "component {component} is corrupted, checksum is {actual} but {expected} was expected"
+    )]
+    ComponentChecksumMismatch {
+        component: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("not enough free disk space at {path}: {required} bytes are required, but only {available} bytes are available")]
+    InsufficientDiskSpace {
+        path: String,
+        required: u64,
+        available: u64,
+    },
     #[error("component {0} is read-only")]
     ReadOnlyComponent(String),
+    #[error(
+        "this annotation storage does not support multiple values for the same annotation key"
+    )]
+    MultipleValuesNotSupported,
+    #[error("encryption key must have 32 bytes in hexadecimal representation")]
+    InvalidEncryptionKey,
+    #[error("could not encrypt data")]
+    Encryption,
+    #[error(
+        "could not decrypt file {0}, the encryption key is wrong or the file has been modified"
+    )]
+    Decryption(String),
+    #[error("file {0} is encrypted, but no encryption key has been given")]
+    EncryptionKeyMissing(String),
+    #[error("file {0} is not encrypted, but an encryption key has been given that does not allow unencrypted files")]
+    UnencryptedFile(String),
+    #[error("the {0} can't be encrypted, use a main memory implementation instead")]
+    EncryptionNotSupported(String),
     #[error(transparent)]
     ModelError(#[from] ComponentTypeError),
     #[error(transparent)]
@@ -40,6 +83,8 @@ pub enum GraphAnnisCoreError {
     SortedStringTable(#[from] sstable::error::Status),
     #[error(transparent)]
     Xml(#[from] quick_xml::Error),
+    #[error(transparent)]
+    Regex(#[from] regex::Error),
 }
 
 #[derive(Error, Debug)]
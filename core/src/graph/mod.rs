@@ -1,12 +1,17 @@
 pub mod serialization;
 pub mod storage;
 pub mod update;
+mod update_log;
 
 use crate::{
     annostorage::{AnnotationStorage, ValueSearch},
     errors::Result,
     graph::storage::{registry, GraphStorage, WriteableGraphStorage},
-    util::disk_collections::{DiskMap, EvictionStrategy},
+    util::{
+        disk_collections::{DiskMap, EvictionStrategy},
+        encryption::{self, EncryptionKey},
+        memory_estimation, UnicodeNormalization,
+    },
 };
 use crate::{
     errors::GraphAnnisCoreError,
@@ -15,22 +20,37 @@ use crate::{
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use rayon::prelude::*;
 use smartstring::alias::String as SmartString;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::prelude::*;
 use std::ops::Bound::Included;
 use std::path::{Path, PathBuf};
 use std::string::ToString;
+use std::time::Instant;
 use std::{
     borrow::Cow,
     sync::{Arc, Mutex},
 };
-use update::{GraphUpdate, UpdateEvent};
+use update::{GraphUpdate, UpdateEvent, UpdateStatistics};
+use update_log::{Segment, SegmentHeader};
 
 pub const ANNIS_NS: &str = "annis";
 pub const DEFAULT_NS: &str = "default_ns";
 pub const NODE_NAME: &str = "node_name";
 pub const NODE_TYPE: &str = "node_type";
 
+/// File in a saved graph folder that contains the change ID of the saved state.
+/// It is written last and marks the folder as completely written.
+const CHANGE_ID_FILE: &str = "change_id";
+/// Write-ahead log with the updates that have not been persisted yet, as written by older versions.
+/// Newer versions write the update log as segments to the `update_log` directory.
+const UPDATE_LOG_FILE: &str = "update_log.bin";
+/// Change ID of the state the write-ahead log of older versions has been applied to.
+const UPDATE_LOG_BASE_FILE: &str = "update_log.base";
+/// Serialized data of a graph storage, if the implementation uses the default serialization.
+const COMPONENT_DATA_FILE: &str = "component.bin";
+/// CRC32 checksum of the component data file.
+const COMPONENT_CHECKSUM_FILE: &str = "component.crc32";
+
 lazy_static! {
     pub static ref DEFAULT_ANNO_KEY: Arc<AnnoKey> = Arc::from(AnnoKey::default());
     pub static ref NODE_NAME_KEY: Arc<AnnoKey> = Arc::from(AnnoKey {
@@ -44,6 +64,132 @@ lazy_static! {
     });
 }
 
+/// The folder from which the persisted state of a graph was loaded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadedState {
+    /// The regular `current` folder.
+    Current,
+    /// The `backup` folder, because saving the `current` folder was not finished.
+    Backup,
+}
+
+/// Describes how a write-ahead update log was handled when loading a graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateLogRecovery {
+    /// There was no update log.
+    NotFound,
+    /// The update log was applied to the loaded state and the result has been persisted.
+    Replayed { events: usize },
+    /// The changes of the update log were already part of the loaded state and the log was removed.
+    AlreadyPersisted,
+    /// The update log does not record on which state it was based and has been ignored.
+    UnknownBase,
+    /// The update log was based on a newer state than the one that could be loaded.
+    /// The log is not applied and kept on disk.
+    MissingChanges { log_base: u64 },
+    /// A segment of the update log did not match its checksum. The segments before it have been replayed,
+    /// the corrupted segment has been renamed and is not replayed.
+    CorruptedSegment {
+        sequence: u64,
+        replayed_events: usize,
+    },
+}
+
+/// Describes which persisted state was chosen when loading a graph from disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadReport {
+    /// The folder the graph was loaded from.
+    pub loaded_from: LoadedState,
+    /// True if an incompletely written `current` folder was discarded in favor of the backup.
+    pub discarded_incomplete_current: bool,
+    /// True if a backup folder was discarded because the `current` folder was completely written.
+    pub discarded_outdated_backup: bool,
+    /// Change ID of the graph after loading and applying the update log.
+    pub change_id: u64,
+    /// What happened to the write-ahead update log.
+    pub update_log: UpdateLogRecovery,
+}
+
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.loaded_from {
+            LoadedState::Current => write!(f, "loaded from current")?,
+            LoadedState::Backup => write!(f, "loaded from backup")?,
+        }
+        if self.discarded_incomplete_current {
+            write!(f, ", discarded incomplete current")?;
+        }
+        if self.discarded_outdated_backup {
+            write!(f, ", discarded outdated backup")?;
+        }
+        write!(f, ", change ID {}", self.change_id)?;
+        match &self.update_log {
+            UpdateLogRecovery::NotFound => Ok(()),
+            UpdateLogRecovery::Replayed { events } => {
+                write!(f, ", replayed {} events from update log", events)
+            }
+            UpdateLogRecovery::AlreadyPersisted => write!(f, ", removed persisted update log"),
+            UpdateLogRecovery::UnknownBase => write!(f, ", ignored update log of unknown state"),
+            UpdateLogRecovery::MissingChanges { log_base } => write!(
+                f,
+                ", ignored update log based on newer change ID {}",
+                log_base
+            ),
+            UpdateLogRecovery::CorruptedSegment {
+                sequence,
+                replayed_events,
+            } => write!(
+                f,
+                ", replayed {} events from update log until corrupted segment {}",
+                replayed_events, sequence
+            ),
+        }
+    }
+}
+
+fn read_change_id(path: &Path) -> Result<Option<u64>> {
+    if path.is_file() {
+        let content = std::fs::read_to_string(path)?;
+        Ok(content.trim().parse::<u64>().ok())
+    } else {
+        Ok(None)
+    }
+}
+
+/// Check that the file system of the given `location` has at least `required` bytes of free space.
+/// Writing to a file system that is running out of space could otherwise fail in the middle of the operation.
+fn check_free_disk_space(location: &Path, required: u64) -> Result<()> {
+    let available = fs2::available_space(location)?;
+    if available < required {
+        return Err(GraphAnnisCoreError::InsufficientDiskSpace {
+            path: location.to_string_lossy().to_string(),
+            required,
+            available,
+        });
+    } else if available / 2 < required {
+        warn!(
+            "Low disk space at {}: {} bytes available, {} bytes will be written",
+            location.to_string_lossy(),
+            available,
+            required
+        );
+    }
+    Ok(())
+}
+
+/// Rename the given folder and delete it afterwards.
+/// Renaming is atomic while deleting could leave an incomplete folder on disk.
+fn remove_dir_atomically(dir: &Path, location: &Path) -> Result<()> {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("temporary-graphannis-backup")
+        .tempdir_in(location)?;
+    // the target directory is created and can cause issues on windows: delete it first
+    std::fs::remove_dir(tmp_dir.path())?;
+    std::fs::rename(dir, tmp_dir.path())?;
+    tmp_dir.close()?;
+    Ok(())
+}
+
 /// A representation of a graph including node annotations and edges.
 /// Edges are partioned into components and each component is implemented by specialized graph storage implementation.
 ///
@@ -58,11 +204,19 @@ pub struct Graph<CT: ComponentType> {
     components: BTreeMap<Component<CT>, Option<Arc<dyn GraphStorage>>>,
     current_change_id: u64,
 
+    load_report: Option<LoadReport>,
+
     background_persistance: Arc<Mutex<()>>,
 
     cached_size: Mutex<Option<usize>>,
 
     disk_based: bool,
+
+    verify_checksums: bool,
+
+    encryption_key: Option<Arc<EncryptionKey>>,
+
+    impl_overrides: BTreeMap<Component<CT>, String>,
 }
 
 impl<CT: ComponentType> MallocSizeOf for Graph<CT> {
@@ -85,18 +239,104 @@ impl<CT: ComponentType> MallocSizeOf for Graph<CT> {
     }
 }
 
-fn load_component_from_disk(component_path: &Path) -> Result<Arc<dyn GraphStorage>> {
+fn component_to_relative_path<CT: ComponentType>(c: &Component<CT>) -> PathBuf {
+    let mut p = PathBuf::new();
+    p.push("gs");
+    p.push(c.get_type().to_string());
+    p.push(if c.layer.is_empty() {
+        "default_layer"
+    } else {
+        &c.layer
+    });
+    p.push(c.name.as_str());
+    p
+}
+
+fn load_component_from_disk<CT: ComponentType>(
+    c: &Component<CT>,
+    component_path: &Path,
+    verify_checksum: bool,
+    encryption_key: Option<&Arc<EncryptionKey>>,
+) -> Result<Arc<dyn GraphStorage>> {
+    if verify_checksum {
+        check_component_checksum(c, component_path)?;
+    }
+
     // load component into memory
     let impl_path = PathBuf::from(component_path).join("impl.cfg");
     let mut f_impl = std::fs::File::open(impl_path)?;
     let mut impl_name = String::new();
     f_impl.read_to_string(&mut impl_name)?;
 
-    let gs = registry::deserialize(&impl_name, component_path)?;
+    // the files of the component are identified by their path relative to the directory of the graph
+    let relative_path = component_to_relative_path(c);
+    let graph_dir = component_path
+        .ancestors()
+        .find(|dir| dir.join(&relative_path) == component_path)
+        .unwrap_or(component_path);
+    let gs = encryption::with_key(encryption_key, graph_dir, || {
+        registry::deserialize(&impl_name, component_path)
+    })
+    .map_err(|e| GraphAnnisCoreError::LoadingComponent {
+        component: c.to_string(),
+        source: Box::new(e),
+    })?;
 
     Ok(gs)
 }
 
+/// Estimate the cost of loading a component by the size of all its files on disk.
+fn component_size_on_disk(path: &Path) -> u64 {
+    let mut result = 0;
+    if let Ok(entries) = path.read_dir() {
+        for entry in entries.flatten() {
+            let entry_path = entry.path();
+            if entry_path.is_dir() {
+                result += component_size_on_disk(&entry_path);
+            } else if let Ok(metadata) = entry.metadata() {
+                result += metadata.len();
+            }
+        }
+    }
+    result
+}
+
+fn calculate_checksum(path: &Path) -> Result<u32> {
+    let mut f = std::fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let bytes_read = f.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[0..bytes_read]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Compare the data file of the component with its stored checksum.
+/// Components without a data file or without a stored checksum (e.g. saved by older versions) are not checked.
+fn check_component_checksum<CT: ComponentType>(
+    c: &Component<CT>,
+    component_path: &Path,
+) -> Result<()> {
+    let data_path = component_path.join(COMPONENT_DATA_FILE);
+    let checksum_path = component_path.join(COMPONENT_CHECKSUM_FILE);
+    if data_path.is_file() && checksum_path.is_file() {
+        let expected = std::fs::read_to_string(&checksum_path)?.trim().to_string();
+        let actual = format!("{:08x}", calculate_checksum(&data_path)?);
+        if expected != actual {
+            return Err(GraphAnnisCoreError::ComponentChecksumMismatch {
+                component: c.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
 impl<CT: ComponentType> Graph<CT> {
     /// Create a new and empty instance without any location on the disk.
     pub fn new(disk_based: bool) -> Result<Self> {
@@ -114,10 +354,18 @@ impl<CT: ComponentType> Graph<CT> {
 
             current_change_id: 0,
 
+            load_report: None,
+
             background_persistance: Arc::new(Mutex::new(())),
             cached_size: Mutex::new(None),
 
             disk_based,
+
+            verify_checksums: false,
+
+            encryption_key: None,
+
+            impl_overrides: BTreeMap::new(),
         })
     }
 
@@ -147,6 +395,10 @@ impl<CT: ComponentType> Graph<CT> {
     /// Load the graph from an external location.
     /// This sets the location of this instance to the given location.
     ///
+    /// If a previous save operation was interrupted, the most recent consistent state is chosen
+    /// and any outstanding write-ahead update log that belongs to this state is applied.
+    /// The chosen recovery path is available via [load_report()](#method.load_report).
+    ///
     /// * `location` - The path on the disk
     /// * `preload` - If `true`, all components are loaded from disk into main memory.
     pub fn load_from(&mut self, location: &Path, preload: bool) -> Result<()> {
@@ -157,13 +409,39 @@ impl<CT: ComponentType> Graph<CT> {
 
         self.set_location(location.as_path())?;
         let backup = location.join("backup");
+        let current = location.join("current");
+
+        // The "current" folder is complete if the change ID has been written at the end of saving it.
+        // Older versions did not write a change ID, so its absence only matters if there is a backup.
+        let current_change_id = read_change_id(&current.join(CHANGE_ID_FILE))?;
+        let backup_exists = backup.is_dir();
+
+        let mut report = LoadReport {
+            loaded_from: LoadedState::Current,
+            discarded_incomplete_current: false,
+            discarded_outdated_backup: false,
+            change_id: 0,
+            update_log: UpdateLogRecovery::NotFound,
+        };
 
-        let mut backup_was_loaded = false;
-        let dir2load = if backup.exists() && backup.is_dir() {
-            backup_was_loaded = true;
+        let dir2load = if backup_exists && current_change_id.is_none() {
+            info!(
+                "Loading backup of corpus {} because saving it was not finished",
+                location.to_string_lossy()
+            );
+            report.loaded_from = LoadedState::Backup;
+            report.discarded_incomplete_current = current.exists();
             backup.clone()
         } else {
-            location.join("current")
+            if backup_exists {
+                info!(
+                    "Removing outdated backup of corpus {}",
+                    location.to_string_lossy()
+                );
+                remove_dir_atomically(&backup, &location)?;
+                report.discarded_outdated_backup = true;
+            }
+            current.clone()
         };
 
         let ondisk_subdirectory = dir2load.join(crate::annostorage::ondisk::SUBFOLDER_NAME);
@@ -177,58 +455,263 @@ impl<CT: ComponentType> Graph<CT> {
             // assume a main memory implementation
             self.disk_based = false;
             let mut node_annos_tmp = crate::annostorage::inmemory::AnnoStorageImpl::new();
-            node_annos_tmp.load_annotations_from(&dir2load)?;
+            encryption::with_key(self.encryption_key.as_ref(), &dir2load, || {
+                node_annos_tmp.load_annotations_from(&dir2load)
+            })?;
             self.node_annos = Box::new(node_annos_tmp);
         }
+        self.current_change_id = read_change_id(&dir2load.join(CHANGE_ID_FILE))?.unwrap_or(0);
 
-        let log_path = dir2load.join("update_log.bin");
-
-        let logfile_exists = log_path.exists() && log_path.is_file();
+        let log_path = location.join(UPDATE_LOG_FILE);
+        let log_base_path = location.join(UPDATE_LOG_BASE_FILE);
+        let logfile_exists = log_path.is_file();
+        let log_base = if logfile_exists {
+            read_change_id(&log_base_path)?
+        } else {
+            None
+        };
+        let mut segments: Vec<(Segment, Option<SegmentHeader>)> = Vec::new();
+        for segment in update_log::list_segments(&location)? {
+            let header = update_log::read_header(&segment)?;
+            segments.push((segment, header));
+        }
+        let replay_log = (logfile_exists && log_base == Some(self.current_change_id))
+            || segments.iter().any(|(_, header)| {
+                header
+                    .map(|h| h.end_change_id > self.current_change_id)
+                    .unwrap_or(false)
+            });
 
         self.find_components_from_disk(&dir2load)?;
 
-        // If backup is active or a write log exists, always  a pre-load to get the complete corpus.
-        if preload | logfile_exists | backup_was_loaded {
+        // If backup is active or a write log needs to be applied, always  a pre-load to get the complete corpus.
+        if preload | replay_log | (report.loaded_from == LoadedState::Backup) {
             self.ensure_loaded_all()?;
         }
 
+        if report.loaded_from == LoadedState::Backup {
+            // save the backup under the actual location, replacing any incomplete files
+            if current.exists() {
+                remove_dir_atomically(&current, &location)?;
+            }
+            self.internal_save(&current)?;
+            remove_dir_atomically(&backup, &location)?;
+        }
+
         if logfile_exists {
-            // apply any outstanding log file updates
-            let log_reader = std::fs::File::open(&log_path)?;
-            let mut update = bincode::deserialize_from(log_reader)?;
-            self.apply_update_in_memory(&mut update, |_| {})?;
-        } else {
-            self.current_change_id = 0;
+            report.update_log = match log_base {
+                Some(log_base) if log_base == self.current_change_id => {
+                    // apply the outstanding updates of the log file and persist them
+                    let log_reader = std::fs::File::open(&log_path)?;
+                    let mut update: GraphUpdate = bincode::deserialize_from(log_reader)?;
+                    let events = self
+                        .apply_update_in_memory(&mut update, |_| {})?
+                        .number_of_events();
+                    self.internal_save_with_backup(&location)?;
+                    std::fs::remove_file(&log_path)?;
+                    std::fs::remove_file(&log_base_path)?;
+                    UpdateLogRecovery::Replayed { events }
+                }
+                Some(log_base) if log_base < self.current_change_id => {
+                    std::fs::remove_file(&log_path)?;
+                    std::fs::remove_file(&log_base_path)?;
+                    UpdateLogRecovery::AlreadyPersisted
+                }
+                Some(log_base) => {
+                    warn!(
+                        "Update log of corpus {} is based on change ID {}, but the newest state on disk has change ID {}",
+                        location.to_string_lossy(),
+                        log_base,
+                        self.current_change_id
+                    );
+                    UpdateLogRecovery::MissingChanges { log_base }
+                }
+                None => {
+                    warn!(
+                        "Ignoring update log of corpus {} because it is unknown on which state it is based",
+                        location.to_string_lossy()
+                    );
+                    UpdateLogRecovery::UnknownBase
+                }
+            };
+        } else if log_base_path.is_file() {
+            // writing the update log itself was not finished
+            std::fs::remove_file(&log_base_path)?;
+        }
+        if !segments.is_empty() {
+            report.update_log = self.replay_update_log_segments(&location, segments)?;
+        }
+
+        report.change_id = self.current_change_id;
+        self.load_report = Some(report);
+
+        Ok(())
+    }
+
+    /// Apply the segments of the update log in the order of their sequence number and persist the result.
+    ///
+    /// Segments that are already part of the loaded state are removed. Replaying stops at the first segment
+    /// that is corrupted or not based on the current state.
+    fn replay_update_log_segments(
+        &mut self,
+        location: &Path,
+        segments: Vec<(Segment, Option<SegmentHeader>)>,
+    ) -> Result<UpdateLogRecovery> {
+        let mut events = 0;
+        let mut already_persisted = false;
+        let mut stopped_at = None;
+        for (segment, header) in segments {
+            let update = match header {
+                Some(header) if header.end_change_id <= self.current_change_id => {
+                    std::fs::remove_file(&segment.path)?;
+                    already_persisted = true;
+                    continue;
+                }
+                Some(header) if header.base_change_id == self.current_change_id => {
+                    update_log::read_update(&segment, &header, self.encryption_key.as_deref())?
+                }
+                Some(header) => {
+                    warn!(
+                        "Update log segment {} of corpus {} is based on change ID {}, but the current state has change ID {}",
+                        segment.sequence,
+                        location.to_string_lossy(),
+                        header.base_change_id,
+                        self.current_change_id
+                    );
+                    stopped_at = Some(UpdateLogRecovery::MissingChanges {
+                        log_base: header.base_change_id,
+                    });
+                    break;
+                }
+                None => None,
+            };
+            if let Some(mut update) = update {
+                events += self
+                    .apply_update_in_memory(&mut update, |_| {})?
+                    .number_of_events();
+            } else {
+                warn!(
+                    "Update log segment {} of corpus {} is corrupted and is not replayed",
+                    segment.sequence,
+                    location.to_string_lossy()
+                );
+                update_log::mark_corrupted(&segment)?;
+                stopped_at = Some(UpdateLogRecovery::CorruptedSegment {
+                    sequence: segment.sequence,
+                    replayed_events: events,
+                });
+                break;
+            }
         }
 
-        if backup_was_loaded {
-            // save the current corpus under the actual location
-            self.save_to(&location.join("current"))?;
-            // rename backup folder (renaming is atomic and deleting could leave an incomplete backup folder on disk)
-            let tmp_dir = tempfile::Builder::new()
-                .prefix("temporary-graphannis-backup")
-                .tempdir_in(location)?;
-            // the target directory is created and can cause issues on windows: delete it first
-            std::fs::remove_dir(tmp_dir.path())?;
-            std::fs::rename(&backup, tmp_dir.path())?;
-            // remove it after renaming it
-            tmp_dir.close()?;
+        if events > 0 {
+            self.internal_save_with_backup(location)?;
+            update_log::remove_persisted_segments(location, self.current_change_id)?;
         }
 
+        let recovery = if let Some(stopped_at) = stopped_at {
+            stopped_at
+        } else if events > 0 {
+            UpdateLogRecovery::Replayed { events }
+        } else if already_persisted {
+            UpdateLogRecovery::AlreadyPersisted
+        } else {
+            UpdateLogRecovery::NotFound
+        };
+        Ok(recovery)
+    }
+
+    /// Returns a report on which persisted state was chosen when this graph was loaded from disk.
+    /// This is `None` if the graph was not loaded from disk.
+    pub fn load_report(&self) -> Option<&LoadReport> {
+        self.load_report.as_ref()
+    }
+
+    /// Returns the ID of the last change applied to this graph, which is increased by each applied update.
+    pub fn change_id(&self) -> u64 {
+        self.current_change_id
+    }
+
+    /// If `true`, the data of each component is compared with the checksum that was stored when saving the
+    /// component, before the component is loaded from disk.
+    /// A mismatch results in an error that names the corrupted component. Defaults to `false`.
+    pub fn set_verify_checksums(&mut self, verify_checksums: bool) {
+        self.verify_checksums = verify_checksums;
+    }
+
+    /// Set the key which is used to encrypt the components, the annotation storage and the update log when they are
+    /// written to disk, and to decrypt them when they are loaded.
+    ///
+    /// Only the main memory implementations of the storages can be encrypted, saving a graph with disk-based
+    /// storages fails if a key is set. Files that are not encrypted can still be loaded. Defaults to `None`.
+    pub fn set_encryption_key(&mut self, key: Option<Arc<EncryptionKey>>) {
+        self.encryption_key = key;
+    }
+
+    /// Always use the graph storage implementation with the given name (e.g. `AdjacencyListV1`) for a component
+    /// instead of choosing it by heuristic when the component is optimized.
+    /// Use `None` to remove an existing override.
+    ///
+    /// Returns an error if there is no graph storage implementation with the given name or if the component is
+    /// loaded and the implementation can not represent it (e.g. a pre/post order for a cyclic component).
+    pub fn set_impl_override(&mut self, c: Component<CT>, impl_name: Option<String>) -> Result<()> {
+        if let Some(impl_name) = impl_name {
+            // check that the implementation exists and is able to represent the component
+            let info = registry::get_info(&impl_name)?;
+            if let Some(gs) = self.get_graphstorage(&c) {
+                if let Some(stats) = gs.get_statistics() {
+                    registry::check_applicable(&info, gs.as_ref(), stats).map_err(|reason| {
+                        GraphAnnisCoreError::GraphStorageImplNotApplicable {
+                            impl_name: impl_name.clone(),
+                            component: c.to_string(),
+                            reason,
+                        }
+                    })?;
+                }
+            }
+            self.impl_overrides.insert(c, impl_name);
+        } else {
+            self.impl_overrides.remove(&c);
+        }
         Ok(())
     }
 
+    /// Remove the graph storage implementation overrides of all components.
+    pub fn clear_impl_overrides(&mut self) {
+        self.impl_overrides.clear();
+    }
+
+    /// Returns the name of the graph storage implementation that has been set for the component with
+    /// [`set_impl_override`](Graph::set_impl_override), if any.
+    pub fn get_impl_override(&self, c: &Component<CT>) -> Option<&str> {
+        self.impl_overrides
+            .get(c)
+            .map(|impl_name| impl_name.as_str())
+    }
+
+    /// Compare the data of all components that are not loaded yet with their stored checksums,
+    /// without loading them, and return the components with a mismatching checksum.
+    pub fn find_corrupted_components(&self) -> Result<Vec<Component<CT>>> {
+        let mut result = Vec::new();
+        for (c, gs) in &self.components {
+            if gs.is_some() {
+                continue;
+            }
+            if let Some(component_path) = self.component_path(c) {
+                match check_component_checksum(c, &component_path) {
+                    Ok(()) => {}
+                    Err(GraphAnnisCoreError::ComponentChecksumMismatch { .. }) => {
+                        result.push(c.clone())
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(result)
+    }
+
     fn component_to_relative_path(&self, c: &Component<CT>) -> PathBuf {
-        let mut p = PathBuf::new();
-        p.push("gs");
-        p.push(c.get_type().to_string());
-        p.push(if c.layer.is_empty() {
-            "default_layer"
-        } else {
-            &c.layer
-        });
-        p.push(c.name.as_str());
-        p
+        component_to_relative_path(c)
     }
 
     fn find_components_from_disk(&mut self, location: &Path) -> Result<()> {
@@ -283,7 +766,39 @@ impl<CT: ComponentType> Graph<CT> {
         Ok(())
     }
 
+    /// Save the graph to the given `location`, replacing any existing folder at this location.
+    ///
+    /// All files are written to a temporary folder next to the `location` first, which is only renamed to the
+    /// `location` when it is complete. A failing save (e.g. because the disk is full) never leaves a half-written
+    /// folder behind.
     fn internal_save(&self, location: &Path) -> Result<()> {
+        let parent_dir = location.parent().unwrap_or_else(|| Path::new("."));
+        std::fs::create_dir_all(parent_dir)?;
+
+        // The size of the graph in memory is a good estimation for the size of the files
+        let mut ops = MallocSizeOfOps::new(memory_estimation::platform::usable_size, None, None);
+        check_free_disk_space(parent_dir, self.size_of_cached(&mut ops) as u64)?;
+
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("temporary-graphannis-save")
+            .tempdir_in(parent_dir)?;
+        self.save_files_to(tmp_dir.path())?;
+
+        if location.exists() {
+            remove_dir_atomically(location, parent_dir)?;
+        }
+        // After renaming, the temporary folder does not exist anymore and dropping it will not delete anything
+        std::fs::rename(tmp_dir.path(), location)?;
+        Ok(())
+    }
+
+    fn save_files_to(&self, location: &Path) -> Result<()> {
+        encryption::with_key(self.encryption_key.as_ref(), location, || {
+            self.save_files_to_with_current_key(location)
+        })
+    }
+
+    fn save_files_to_with_current_key(&self, location: &Path) -> Result<()> {
         let location = PathBuf::from(location);
 
         std::fs::create_dir_all(&location)?;
@@ -298,11 +813,24 @@ impl<CT: ComponentType> Graph<CT> {
                 let impl_name = data.serialization_id();
                 data.save_to(&dir)?;
 
+                let data_path = dir.join(COMPONENT_DATA_FILE);
+                if data_path.is_file() {
+                    let checksum = calculate_checksum(&data_path)?;
+                    std::fs::write(
+                        dir.join(COMPONENT_CHECKSUM_FILE),
+                        format!("{:08x}", checksum),
+                    )?;
+                }
+
                 let cfg_path = PathBuf::from(&dir).join("impl.cfg");
                 let mut f_cfg = std::fs::File::create(cfg_path)?;
                 f_cfg.write_all(impl_name.as_bytes())?;
             }
         }
+        // The change ID is written last and marks the folder as complete
+        let mut f_change_id = std::fs::File::create(location.join(CHANGE_ID_FILE))?;
+        f_change_id.write_all(self.current_change_id.to_string().as_bytes())?;
+        f_change_id.sync_all()?;
         Ok(())
     }
 
@@ -334,12 +862,20 @@ impl<CT: ComponentType> Graph<CT> {
     }
 
     #[allow(clippy::cognitive_complexity)]
-    fn apply_update_in_memory<F>(&mut self, u: &mut GraphUpdate, progress_callback: F) -> Result<()>
+    fn apply_update_in_memory<F>(
+        &mut self,
+        u: &mut GraphUpdate,
+        progress_callback: F,
+    ) -> Result<UpdateStatistics>
     where
         F: Fn(&str),
     {
         self.reset_cached_size();
 
+        let mut statistics = UpdateStatistics::default();
+        let events_start = Instant::now();
+        let base_change_id = self.current_change_id;
+
         let all_components = self.get_all_components(None, None);
 
         let mut update_graph_index = ComponentType::init_update_graph_index(self)?;
@@ -349,6 +885,10 @@ impl<CT: ComponentType> Graph<CT> {
         // Iterate once over all changes in the same order as the updates have been added
         for (nr_updates, (id, change)) in u.iter()?.enumerate() {
             trace!("applying event {:?}", &change);
+            *statistics
+                .events_per_type
+                .entry(change.event_type())
+                .or_default() += 1;
             ComponentType::before_update_event(&change, self, &mut update_graph_index)?;
             match &change {
                 UpdateEvent::AddNode {
@@ -421,6 +961,25 @@ impl<CT: ComponentType> Graph<CT> {
                         self.node_annos.insert(existing_node_id, anno)?;
                     }
                 }
+                UpdateEvent::AddNodeLabelValue {
+                    node_name,
+                    anno_ns,
+                    anno_name,
+                    anno_value,
+                } => {
+                    if let Some(existing_node_id) =
+                        self.get_cached_node_id_from_name(Cow::Borrowed(node_name), &mut node_ids)?
+                    {
+                        let anno = Annotation {
+                            key: AnnoKey {
+                                ns: anno_ns.into(),
+                                name: anno_name.into(),
+                            },
+                            val: anno_value.into(),
+                        };
+                        self.node_annos.add_value(existing_node_id, anno)?;
+                    }
+                }
                 UpdateEvent::DeleteNodeLabel {
                     node_name,
                     anno_ns,
@@ -541,22 +1100,33 @@ impl<CT: ComponentType> Graph<CT> {
                 }
             } // end match update entry type
             ComponentType::after_update_event(change, self, &mut update_graph_index)?;
-            self.current_change_id = id;
+            self.current_change_id = base_change_id + id;
 
             if nr_updates % 100_000 == 0 {
                 progress_callback(&format!("applied {} atomic updates", nr_updates));
             }
         } // end for each consistent update entry
 
+        statistics.events_duration = events_start.elapsed();
+
         progress_callback("extending graph with model-specific index");
+        let index_start = Instant::now();
+        statistics.reindexed_nodes = CT::number_of_reindexed_nodes(&update_graph_index);
         ComponentType::apply_update_graph_index(update_graph_index, self)?;
+        statistics.index_duration = index_start.elapsed();
 
-        Ok(())
+        Ok(statistics)
     }
 
     /// Apply a sequence of updates (`u` parameter) to this graph.
     /// If the graph has a location on the disk, the changes are persisted.
-    pub fn apply_update<F>(&mut self, u: &mut GraphUpdate, progress_callback: F) -> Result<()>
+    ///
+    /// Returns statistics about the applied events and the time needed for each phase of the update.
+    pub fn apply_update<F>(
+        &mut self,
+        u: &mut GraphUpdate,
+        progress_callback: F,
+    ) -> Result<UpdateStatistics>
     where
         F: Fn(&str),
     {
@@ -565,32 +1135,31 @@ impl<CT: ComponentType> Graph<CT> {
         // we have to make sure that the corpus is fully loaded (with all components) before we can apply the update.
         self.ensure_loaded_all()?;
 
-        let result = self.apply_update_in_memory(u, &progress_callback);
+        let base_change_id = self.current_change_id;
+        let mut result = self.apply_update_in_memory(u, &progress_callback);
 
         progress_callback("memory updates completed, persisting updates to disk");
 
         if let Some(location) = self.location.clone() {
             trace!("output location for persisting updates is {:?}", location);
-            if result.is_ok() {
-                let current_path = location.join("current");
+            if let Ok(statistics) = &mut result {
+                let persist_start = Instant::now();
                 // make sure the output path exits
-                std::fs::create_dir_all(&current_path)?;
-
-                // If successfull write log
-                let log_path = location.join("update_log.bin");
-
-                // Create a temporary directory in the same file system as the output
-                let temporary_dir = tempfile::tempdir_in(&current_path)?;
-                let mut temporary_disk_file = tempfile::NamedTempFile::new_in(&temporary_dir)?;
-
-                debug!("writing WAL update log to {:?}", temporary_disk_file.path());
-                bincode::serialize_into(temporary_disk_file.as_file(), &u)?;
-                temporary_disk_file.flush()?;
-                debug!("moving finished WAL update log to {:?}", &log_path);
-                // Since the temporary file should be on the same file system, persisting/moving it should be an atomic operation
-                temporary_disk_file.persist(&log_path)?;
+                std::fs::create_dir_all(&location)?;
+                check_free_disk_space(&location, u.serialized_size_estimate())?;
+
+                // If successfull, append the update to the log, together with the state it is based on,
+                // so it is only replayed on this state
+                update_log::append_segment(
+                    &location,
+                    base_change_id,
+                    self.current_change_id,
+                    u,
+                    self.encryption_key.as_deref(),
+                )?;
 
                 progress_callback("finished writing WAL update log");
+                statistics.persist_duration = persist_start.elapsed();
             } else {
                 trace!("error occured while applying updates: {:?}", &result);
                 // load corpus from disk again
@@ -599,7 +1168,9 @@ impl<CT: ComponentType> Graph<CT> {
             }
         }
 
-        Ok(())
+        let statistics = result?;
+        info!("{}", statistics);
+        Ok(statistics)
     }
 
     /// A function to persist the changes of a write-ahead-log update on the disk. Should be run in a background thread.
@@ -611,6 +1182,19 @@ impl<CT: ComponentType> Graph<CT> {
             let _lock = self.background_persistance.lock().unwrap();
 
             self.internal_save_with_backup(location)?;
+
+            // The update log is not needed anymore if its changes are part of the saved state
+            update_log::remove_persisted_segments(location, self.current_change_id)?;
+            let log_base_path = location.join(UPDATE_LOG_BASE_FILE);
+            if let Some(log_base) = read_change_id(&log_base_path)? {
+                if log_base < self.current_change_id {
+                    let log_path = location.join(UPDATE_LOG_FILE);
+                    if log_path.is_file() {
+                        std::fs::remove_file(&log_path)?;
+                    }
+                    std::fs::remove_file(&log_base_path)?;
+                }
+            }
         }
 
         Ok(())
@@ -630,20 +1214,20 @@ impl<CT: ComponentType> Graph<CT> {
         let current_location = location.join("current");
         if !backup_location.exists() {
             std::fs::rename(&current_location, &backup_location)?;
+        } else if current_location.join(CHANGE_ID_FILE).is_file() {
+            // The existing backup is outdated, because the current version was completely written
+            remove_dir_atomically(&backup_location, location)?;
+            std::fs::rename(&current_location, &backup_location)?;
+        } else if current_location.exists() {
+            // Remove the incompletely written current version, so no stale files remain
+            remove_dir_atomically(&current_location, location)?;
         }
 
         // Save the complete corpus without the write log to the target location
         self.internal_save(&current_location)?;
 
-        // rename backup folder (renaming is atomic and deleting could leave an incomplete backup folder on disk)
-        let tmp_dir = tempfile::Builder::new()
-            .prefix("temporary-graphannis-backup")
-            .tempdir_in(location)?;
-        // the target directory is created and can cause issues on windows: delete it first
-        std::fs::remove_dir(tmp_dir.path())?;
-        std::fs::rename(&backup_location, tmp_dir.path())?;
-        // remove it after renaming it, (since the new "current" folder was completely written)
-        tmp_dir.close()?;
+        // remove it, since the new "current" folder was completely written
+        remove_dir_atomically(&backup_location, location)?;
         Ok(())
     }
 
@@ -673,7 +1257,12 @@ impl<CT: ComponentType> Graph<CT> {
                 let component_path = self
                     .component_path(c)
                     .ok_or(GraphAnnisCoreError::EmptyComponentPath)?;
-                load_component_from_disk(&component_path)?
+                load_component_from_disk(
+                    c,
+                    &component_path,
+                    self.verify_checksums,
+                    self.encryption_key.as_ref(),
+                )?
             };
 
             // copy to writable implementation if needed
@@ -770,26 +1359,50 @@ impl<CT: ComponentType> Graph<CT> {
 
     /// Ensure that the graph storages for all component are loaded and ready to use.
     pub fn ensure_loaded_all(&mut self) -> Result<()> {
-        let mut components_to_load: Vec<_> = Vec::with_capacity(self.components.len());
+        let components: Vec<_> = self.components.keys().cloned().collect();
+        self.ensure_loaded_parallel(&components)
+    }
 
-        // colllect all missing components
-        for (c, gs) in &self.components {
-            if gs.is_none() {
-                components_to_load.push(c.clone());
+    /// Ensure that the graph storages for all given components are loaded and ready to use.
+    ///
+    /// The missing components are loaded in parallel. Loading the smallest components (by the size of their
+    /// files on disk) is started first, so reading the files of large components overlaps with deserializing the
+    /// small ones instead of blocking them.
+    pub fn ensure_loaded_parallel(&mut self, components: &[Component<CT>]) -> Result<()> {
+        // collect all missing components
+        let mut components_to_load: Vec<(Component<CT>, PathBuf, u64)> = Vec::new();
+        for c in components {
+            if let Some(None) = self.components.get(c) {
+                let cpath = self
+                    .component_path(c)
+                    .ok_or(GraphAnnisCoreError::EmptyComponentPath)?;
+                let size = component_size_on_disk(&cpath);
+                components_to_load.push((c.clone(), cpath, size));
             }
         }
+        if components_to_load.is_empty() {
+            return Ok(());
+        }
+        components_to_load.sort_by_key(|(_, _, size)| *size);
 
         self.reset_cached_size();
 
-        // load missing components in parallel
+        // load missing components in parallel, the threads take the components in the sorted order
+        let verify_checksums = self.verify_checksums;
+        let encryption_key = self.encryption_key.clone();
         let loaded_components: Vec<(_, Result<Arc<dyn GraphStorage>>)> = components_to_load
-            .into_par_iter()
-            .map(|c| match self.component_path(&c) {
-                Some(cpath) => {
-                    debug!("loading component {} from {}", c, &cpath.to_string_lossy());
-                    (c, load_component_from_disk(&cpath))
-                }
-                None => (c, Err(GraphAnnisCoreError::EmptyComponentPath)),
+            .into_iter()
+            .par_bridge()
+            .map(|(c, cpath, size)| {
+                debug!(
+                    "loading component {} ({} bytes) from {}",
+                    c,
+                    size,
+                    &cpath.to_string_lossy()
+                );
+                let gs =
+                    load_component_from_disk(&c, &cpath, verify_checksums, encryption_key.as_ref());
+                (c, gs)
             })
             .collect();
 
@@ -818,7 +1431,12 @@ impl<CT: ComponentType> Graph<CT> {
                     c,
                     &component_path.to_string_lossy()
                 );
-                load_component_from_disk(&component_path)?
+                load_component_from_disk(
+                    c,
+                    &component_path,
+                    self.verify_checksums,
+                    self.encryption_key.as_ref(),
+                )?
             };
 
             self.components.insert(c.clone(), Some(loaded));
@@ -845,8 +1463,15 @@ impl<CT: ComponentType> Graph<CT> {
                 .node_annos
                 .exact_anno_search(Some(ANNIS_NS), NODE_TYPE, ValueSearch::Any)
             {
+                let mut copied_keys = HashSet::new();
                 for anno in self.node_annos.get_annotations_for_item(&m.node) {
-                    new_node_annos.insert(m.node, anno)?;
+                    if copied_keys.contains(&anno.key) {
+                        // Fails if the new implementation does not support multiple values
+                        new_node_annos.add_value(m.node, anno)?;
+                    } else {
+                        copied_keys.insert(anno.key.clone());
+                        new_node_annos.insert(m.node, anno)?;
+                    }
                 }
             }
             info!("re-calculating node annotation statistics");
@@ -871,9 +1496,30 @@ impl<CT: ComponentType> Graph<CT> {
 
     pub fn optimize_gs_impl(&mut self, c: &Component<CT>) -> Result<()> {
         if let Some(gs) = self.get_graphstorage(c) {
-            if let Some(stats) = gs.get_statistics() {
-                let opt_info = registry::get_optimal_impl_heuristic(self, stats);
-
+            let override_info = if let Some(impl_name) = self.impl_overrides.get(c) {
+                let info = registry::get_info(impl_name)?;
+                match gs.get_statistics() {
+                    Some(stats) => match registry::check_applicable(&info, gs.as_ref(), stats) {
+                        Ok(()) => Some(info),
+                        Err(reason) => {
+                            // the component might have changed since the override was set
+                            warn!(
+                                "ignoring graph storage implementation {} for component {}: {}",
+                                impl_name, c, reason
+                            );
+                            None
+                        }
+                    },
+                    None => Some(info),
+                }
+            } else {
+                None
+            };
+            let opt_info = override_info.or_else(|| {
+                gs.get_statistics()
+                    .map(|stats| registry::get_optimal_impl_heuristic(self, gs.as_ref(), stats))
+            });
+            if let Some(opt_info) = opt_info {
                 // convert if necessary
                 if opt_info.id != gs.serialization_id() {
                     let mut new_gs = registry::create_from_info(&opt_info)?;
@@ -899,6 +1545,103 @@ impl<CT: ComponentType> Graph<CT> {
         Ok(())
     }
 
+    /// Normalize all node and edge annotation values to the given Unicode normal form.
+    ///
+    /// This can be used to migrate graphs which have been created without normalization.
+    /// Only components which contain non-normalized values are converted to a writable implementation.
+    /// The changes are not persisted automatically.
+    pub fn normalize_unicode(&mut self, normalization: UnicodeNormalization) -> Result<()> {
+        if normalization == UnicodeNormalization::None {
+            return Ok(());
+        }
+        self.ensure_loaded_all()?;
+        self.reset_cached_size();
+
+        // collect and replace node annotations that are not normalized yet
+        let mut changed_node_annos = Vec::new();
+        for key in self.node_annos.annotation_keys() {
+            // nodes with multiple values for the key are found once for each value
+            let mut nodes: Vec<NodeID> = self
+                .node_annos
+                .exact_anno_search(Some(&key.ns), &key.name, ValueSearch::Any)
+                .map(|m| m.node)
+                .collect();
+            nodes.sort_unstable();
+            nodes.dedup();
+            for node in nodes {
+                let values = self.node_annos.get_values_for_item(&node, &key);
+                if values
+                    .iter()
+                    .any(|val| matches!(normalization.normalize(val), Cow::Owned(_)))
+                {
+                    let normalized: Vec<String> = values
+                        .iter()
+                        .map(|val| normalization.normalize(val).into_owned())
+                        .collect();
+                    changed_node_annos.push((node, key.clone(), normalized));
+                }
+            }
+        }
+        if !changed_node_annos.is_empty() {
+            debug!(
+                "normalizing the annotation values of {} nodes",
+                changed_node_annos.len()
+            );
+            for (node, key, values) in changed_node_annos {
+                // each value of a multi-valued annotation is normalized separately
+                for (i, val) in values.into_iter().enumerate() {
+                    let anno = Annotation {
+                        key: key.clone(),
+                        val: val.into(),
+                    };
+                    if i == 0 {
+                        self.node_annos.insert(node, anno)?;
+                    } else {
+                        self.node_annos.add_value(node, anno)?;
+                    }
+                }
+            }
+            self.node_annos.calculate_statistics();
+        }
+
+        // do the same for all edge annotations of all components
+        for c in self.get_all_components(None, None) {
+            let mut changed_edge_annos = Vec::new();
+            if let Some(gs) = self.get_graphstorage_as_ref(&c) {
+                for source in gs.source_nodes() {
+                    for target in gs.get_outgoing_edges(source) {
+                        let edge = Edge { source, target };
+                        for anno in gs.get_anno_storage().get_annotations_for_item(&edge) {
+                            if let Cow::Owned(normalized) = normalization.normalize(&anno.val) {
+                                changed_edge_annos.push((
+                                    edge.clone(),
+                                    Annotation {
+                                        key: anno.key,
+                                        val: normalized.into(),
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            if !changed_edge_annos.is_empty() {
+                debug!(
+                    "normalizing {} edge annotation values in component {}",
+                    changed_edge_annos.len(),
+                    c
+                );
+                let gs = self.get_or_create_writable(&c)?;
+                for (edge, anno) in changed_edge_annos {
+                    gs.add_edge_annotation(edge, anno)?;
+                }
+                gs.calculate_statistics();
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn get_node_id_from_name(&self, node_name: &str) -> Option<NodeID> {
         let mut all_nodes_with_anno = self.node_annos.exact_anno_search(
             Some(&ANNIS_NS.to_owned()),
@@ -911,6 +1654,42 @@ impl<CT: ComponentType> Graph<CT> {
         None
     }
 
+    /// Resolve a list of node names to their node IDs.
+    ///
+    /// The result has the same order as the given node names and contains `None` for names that do not exist.
+    /// All distinct names are resolved with a single search on the node name index, which disk-based annotation
+    /// storages can execute as sequential scans over the sorted names.
+    pub fn node_ids_from_names<I, S>(&self, node_names: I) -> Vec<Option<NodeID>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let node_names: Vec<S> = node_names.into_iter().collect();
+        if node_names.is_empty() {
+            return Vec::new();
+        }
+        let mut distinct_names: Vec<&str> = node_names.iter().map(|n| n.as_ref()).collect();
+        distinct_names.sort_unstable();
+        distinct_names.dedup();
+
+        // map the found nodes back to their names
+        let mut node_ids: HashMap<String, NodeID> = HashMap::with_capacity(distinct_names.len());
+        for m in self.node_annos.exact_anno_search(
+            Some(ANNIS_NS),
+            NODE_NAME,
+            ValueSearch::AnyOf(distinct_names),
+        ) {
+            if let Some(node_name) = self.node_annos.get_value_for_item(&m.node, &NODE_NAME_KEY) {
+                node_ids.entry(node_name.into_owned()).or_insert(m.node);
+            }
+        }
+
+        node_names
+            .iter()
+            .map(|n| node_ids.get(n.as_ref()).copied())
+            .collect()
+    }
+
     /// Get a read-only graph storage copy for the given component `c`.
     pub fn get_graphstorage(&self, c: &Component<CT>) -> Option<Arc<dyn GraphStorage>> {
         // get and return the reference to the entry if loaded
@@ -1057,4 +1836,452 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn resolve_node_names_in_bulk() {
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        let mut u = GraphUpdate::new();
+        for name in &["n1", "n2", "n3"] {
+            u.add_event(UpdateEvent::AddNode {
+                node_name: name.to_string(),
+                node_type: "node".to_string(),
+            })
+            .unwrap();
+        }
+        db.apply_update(&mut u, |_| {}).unwrap();
+
+        let n1 = db.get_node_id_from_name("n1");
+        let n3 = db.get_node_id_from_name("n3");
+        assert!(n1.is_some());
+        assert!(n3.is_some());
+
+        assert_eq!(
+            vec![n3, None, n1, n3],
+            db.node_ids_from_names(["n3", "unknown", "n1", "n3"])
+        );
+        assert!(db.node_ids_from_names(Vec::<String>::new()).is_empty());
+
+        // the disk-based node name index gives the same result
+        db.optimize_impl(true).unwrap();
+        assert_eq!(
+            vec![n3, None, n1, n3],
+            db.node_ids_from_names(["n3", "unknown", "n1", "n3"])
+        );
+    }
+
+    #[test]
+    fn normalize_each_value_of_multi_valued_annotations() {
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        let n1 = db.get_node_id_from_name("n1").unwrap();
+        let key = AnnoKey {
+            ns: "test".into(),
+            name: "word".into(),
+        };
+        for val in &["Cafe\u{0301}", "Tea", "Fiance\u{0301}"] {
+            db.get_node_annos_mut()
+                .add_value(
+                    n1,
+                    Annotation {
+                        key: key.clone(),
+                        val: (*val).into(),
+                    },
+                )
+                .unwrap();
+        }
+
+        db.normalize_unicode(UnicodeNormalization::NFC).unwrap();
+
+        let mut values: Vec<String> = db
+            .get_node_annos()
+            .get_values_for_item(&n1, &key)
+            .into_iter()
+            .map(|val| val.to_string())
+            .collect();
+        values.sort();
+        assert_eq!(vec!["Caf\u{00E9}", "Fianc\u{00E9}", "Tea"], values);
+    }
+
+    fn add_nodes_update(names: &[&str]) -> GraphUpdate {
+        let mut u = GraphUpdate::new();
+        for name in names {
+            u.add_event(UpdateEvent::AddNode {
+                node_name: name.to_string(),
+                node_type: "node".to_string(),
+            })
+            .unwrap();
+        }
+        u
+    }
+
+    #[test]
+    fn detect_corrupted_component() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        let mut u = add_nodes_update(&["n1", "n2"]);
+        u.add_event(UpdateEvent::AddEdge {
+            source_node: "n1".to_string(),
+            target_node: "n2".to_string(),
+            layer: "test".to_string(),
+            component_type: "Edge".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+        db.apply_update(&mut u, |_| {}).unwrap();
+        db.persist_to(tmp.path()).unwrap();
+
+        let component = Component::new(DefaultComponentType::Edge, "test".into(), "dep".into());
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.set_verify_checksums(true);
+        loaded.load_from(tmp.path(), false).unwrap();
+        assert!(loaded.find_corrupted_components().unwrap().is_empty());
+
+        // Change a single byte of the component data
+        let data_path = loaded
+            .component_path(&component)
+            .unwrap()
+            .join(COMPONENT_DATA_FILE);
+        let mut data = std::fs::read(&data_path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        std::fs::write(&data_path, data).unwrap();
+
+        assert_eq!(
+            vec![component.clone()],
+            loaded.find_corrupted_components().unwrap()
+        );
+        match loaded.ensure_loaded(&component) {
+            Err(GraphAnnisCoreError::ComponentChecksumMismatch {
+                component: name, ..
+            }) => assert_eq!(component.to_string(), name),
+            other => panic!("expected checksum mismatch, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn load_components_in_parallel() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        let mut u = add_nodes_update(&["n1", "n2", "n3"]);
+        for (source, target, name) in &[
+            ("n1", "n2", "dep"),
+            ("n2", "n3", "dep"),
+            ("n1", "n3", "coref"),
+        ] {
+            u.add_event(UpdateEvent::AddEdge {
+                source_node: source.to_string(),
+                target_node: target.to_string(),
+                layer: "test".to_string(),
+                component_type: "Edge".to_string(),
+                component_name: name.to_string(),
+            })
+            .unwrap();
+        }
+        db.apply_update(&mut u, |_| {}).unwrap();
+        db.persist_to(tmp.path()).unwrap();
+
+        let dep = Component::new(DefaultComponentType::Edge, "test".into(), "dep".into());
+        let coref = Component::new(DefaultComponentType::Edge, "test".into(), "coref".into());
+        let unknown = Component::new(DefaultComponentType::Edge, "test".into(), "unknown".into());
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        assert!(!loaded.is_loaded(&dep));
+        assert!(!loaded.is_loaded(&coref));
+
+        // components that do not exist are ignored
+        loaded
+            .ensure_loaded_parallel(&[dep.clone(), unknown.clone()])
+            .unwrap();
+        assert!(loaded.is_loaded(&dep));
+        assert!(!loaded.is_loaded(&coref));
+        assert!(!loaded.is_loaded(&unknown));
+        let n2 = loaded.get_node_id_from_name("n2").unwrap();
+        assert_eq!(
+            1,
+            loaded
+                .get_graphstorage(&dep)
+                .unwrap()
+                .get_outgoing_edges(n2)
+                .count()
+        );
+
+        loaded
+            .ensure_loaded_parallel(&[dep.clone(), coref.clone()])
+            .unwrap();
+        assert!(loaded.is_loaded(&coref));
+        assert!(loaded
+            .get_graphstorage(&coref)
+            .unwrap()
+            .get_outgoing_edges(loaded.get_node_id_from_name("n1").unwrap())
+            .next()
+            .is_some());
+    }
+
+    #[test]
+    fn recover_from_backup_and_update_log() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1", "n2"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+
+        // Apply an update whose write-ahead log is not synchronized yet
+        db.apply_update(&mut add_nodes_update(&["n3"]), |_| {})
+            .unwrap();
+        assert_eq!(1, update_log::list_segments(tmp.path()).unwrap().len());
+
+        // Simulate an aborted save: the backup is complete, but "current" is not
+        std::fs::rename(tmp.path().join("current"), tmp.path().join("backup")).unwrap();
+        std::fs::create_dir_all(tmp.path().join("current")).unwrap();
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(LoadedState::Backup, report.loaded_from);
+        assert!(report.discarded_incomplete_current);
+        assert!(!report.discarded_outdated_backup);
+        assert_eq!(UpdateLogRecovery::Replayed { events: 1 }, report.update_log);
+        assert_eq!(3, report.change_id);
+        assert!(loaded.get_node_id_from_name("n3").is_some());
+        assert!(!tmp.path().join("backup").exists());
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+
+        // Loading again must use the saved state without any recovery
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(LoadedState::Current, report.loaded_from);
+        assert_eq!(UpdateLogRecovery::NotFound, report.update_log);
+        assert_eq!(3, report.change_id);
+        assert!(loaded.get_node_id_from_name("n3").is_some());
+    }
+
+    #[test]
+    fn skip_outdated_backup_and_update_log() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n2"]), |_| {})
+            .unwrap();
+
+        // Keep the update log and an old state as backup, although saving was successful
+        let segments = update_log::list_segments(tmp.path()).unwrap();
+        assert_eq!(1, segments.len());
+        let log_copy = tmp.path().join("log.copy");
+        std::fs::copy(&segments[0].path, &log_copy).unwrap();
+        db.background_sync_wal_updates().unwrap();
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+        let mut old_state = Graph::<DefaultComponentType>::new(false).unwrap();
+        old_state
+            .apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        old_state.internal_save(&tmp.path().join("backup")).unwrap();
+        std::fs::rename(&log_copy, &segments[0].path).unwrap();
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(LoadedState::Current, report.loaded_from);
+        assert!(report.discarded_outdated_backup);
+        assert_eq!(UpdateLogRecovery::AlreadyPersisted, report.update_log);
+        assert_eq!(2, report.change_id);
+        assert!(loaded.get_node_id_from_name("n2").is_some());
+        assert!(!tmp.path().join("backup").exists());
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn replay_multiple_update_log_segments() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+
+        // Several updates without synchronizing them in between
+        db.apply_update(&mut add_nodes_update(&["n2", "n3"]), |_| {})
+            .unwrap();
+        db.apply_update(&mut add_nodes_update(&["n4"]), |_| {})
+            .unwrap();
+        let segments = update_log::list_segments(tmp.path()).unwrap();
+        assert_eq!(
+            vec![1, 2],
+            segments.iter().map(|s| s.sequence).collect::<Vec<_>>()
+        );
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(UpdateLogRecovery::Replayed { events: 3 }, report.update_log);
+        assert_eq!(4, report.change_id);
+        assert!(loaded.get_node_id_from_name("n4").is_some());
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+
+        // Synchronizing truncates the log
+        db.background_sync_wal_updates().unwrap();
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn skip_corrupted_update_log_segment() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n2"]), |_| {})
+            .unwrap();
+        db.apply_update(&mut add_nodes_update(&["n3"]), |_| {})
+            .unwrap();
+
+        // Change the last byte of the second segment
+        let segments = update_log::list_segments(tmp.path()).unwrap();
+        let mut data = std::fs::read(&segments[1].path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        std::fs::write(&segments[1].path, data).unwrap();
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(
+            UpdateLogRecovery::CorruptedSegment {
+                sequence: 2,
+                replayed_events: 1
+            },
+            report.update_log
+        );
+        assert!(loaded.get_node_id_from_name("n2").is_some());
+        assert!(loaded.get_node_id_from_name("n3").is_none());
+        assert!(update_log::list_segments(tmp.path()).unwrap().is_empty());
+        assert!(segments[1].path.with_extension("corrupted").is_file());
+    }
+
+    #[test]
+    fn replay_legacy_update_log() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+
+        // Older versions wrote a single update log file
+        let f = std::fs::File::create(tmp.path().join(UPDATE_LOG_FILE)).unwrap();
+        bincode::serialize_into(f, &add_nodes_update(&["n2"])).unwrap();
+        std::fs::write(tmp.path().join(UPDATE_LOG_BASE_FILE), "1").unwrap();
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        let report = loaded.load_report().unwrap();
+        assert_eq!(UpdateLogRecovery::Replayed { events: 1 }, report.update_log);
+        assert!(loaded.get_node_id_from_name("n2").is_some());
+        assert!(!tmp.path().join(UPDATE_LOG_FILE).exists());
+    }
+
+    #[test]
+    fn save_replaces_folder_atomically() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n1"]), |_| {})
+            .unwrap();
+        db.persist_to(tmp.path()).unwrap();
+        db.apply_update(&mut add_nodes_update(&["n2"]), |_| {})
+            .unwrap();
+        db.save_to(tmp.path()).unwrap();
+
+        // Only the complete "current" folder remains, no temporary folders are left
+        let entries: Vec<_> = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|f| f.starts_with("temporary-graphannis"))
+            .collect();
+        assert!(entries.is_empty());
+
+        let mut loaded = Graph::<DefaultComponentType>::new(false).unwrap();
+        loaded.load_from(tmp.path(), false).unwrap();
+        assert_eq!(2, loaded.load_report().unwrap().change_id);
+        assert!(loaded.get_node_id_from_name("n2").is_some());
+    }
+
+    #[test]
+    fn add_multiple_values_with_update_event() {
+        let mut db = Graph::<DefaultComponentType>::new(false).unwrap();
+        let mut u = add_nodes_update(&["n1"]);
+        u.add_event(UpdateEvent::AddNodeLabel {
+            node_name: "n1".to_string(),
+            anno_ns: "wordnet".to_string(),
+            anno_name: "sense".to_string(),
+            anno_value: "a".to_string(),
+        })
+        .unwrap();
+        u.add_event(UpdateEvent::AddNodeLabelValue {
+            node_name: "n1".to_string(),
+            anno_ns: "wordnet".to_string(),
+            anno_name: "sense".to_string(),
+            anno_value: "b".to_string(),
+        })
+        .unwrap();
+        db.apply_update(&mut u, |_| {}).unwrap();
+
+        let n1 = db.get_node_id_from_name("n1").unwrap();
+        let key = AnnoKey {
+            ns: "wordnet".into(),
+            name: "sense".into(),
+        };
+        assert_eq!(
+            vec!["a", "b"],
+            db.get_node_annos().get_values_for_item(&n1, &key)
+        );
+
+        // converting to the disk-based implementation keeps all values
+        db.optimize_impl(true).unwrap();
+        assert_eq!(
+            vec!["a", "b"],
+            db.get_node_annos().get_values_for_item(&n1, &key)
+        );
+        let found: Vec<NodeID> = db
+            .get_node_annos()
+            .exact_anno_search(Some("wordnet"), "sense", ValueSearch::Any)
+            .map(|m| m.node)
+            .collect();
+        assert_eq!(vec![n1], found);
+
+        // adding a label replaces all values
+        let mut u = GraphUpdate::new();
+        u.add_event(UpdateEvent::AddNodeLabel {
+            node_name: "n1".to_string(),
+            anno_ns: "wordnet".to_string(),
+            anno_name: "sense".to_string(),
+            anno_value: "c".to_string(),
+        })
+        .unwrap();
+        db.apply_update(&mut u, |_| {}).unwrap();
+        assert_eq!(
+            vec!["c"],
+            db.get_node_annos().get_values_for_item(&n1, &key)
+        );
+        assert_eq!(
+            0,
+            db.get_node_annos()
+                .exact_anno_search(Some("wordnet"), "sense", ValueSearch::Some("b"))
+                .count()
+        );
+    }
+
+    #[test]
+    fn fail_on_insufficient_disk_space() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(check_free_disk_space(tmp.path(), 0).is_ok());
+        match check_free_disk_space(tmp.path(), u64::MAX) {
+            Err(GraphAnnisCoreError::InsufficientDiskSpace { required, .. }) => {
+                assert_eq!(u64::MAX, required)
+            }
+            other => panic!("expected insufficient disk space, got {:?}", other),
+        }
+    }
 }
@@ -3,9 +3,9 @@ use crate::{
     errors::{GraphAnnisCoreError, Result},
     graph::{
         update::{GraphUpdate, UpdateEvent},
-        Graph, ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE, NODE_TYPE_KEY,
+        Graph, ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE_KEY,
     },
-    types::{AnnoKey, Annotation, Component, ComponentType, Edge},
+    types::{AnnoKey, Annotation, Component, ComponentType, Edge, NodeID},
     util::{join_qname, split_qname},
 };
 use quick_xml::{
@@ -113,88 +113,114 @@ fn write_data<W: std::io::Write>(
     Ok(())
 }
 
-fn write_nodes<CT: ComponentType, W: std::io::Write>(
+/// Iterate over all nodes that have a name, grouped by the document they belong to.
+///
+/// The disk-based annotation storage already returns the nodes sorted by their name, so they can be
+/// streamed without holding any additional state in memory. For graphs in main memory, the node IDs are
+/// sorted by their name first.
+fn nodes_in_document_order<'a, CT: ComponentType>(
+    graph: &'a Graph<CT>,
+) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+    let node_annos = graph.get_node_annos();
+    let nodes = node_annos
+        .exact_anno_search(Some(ANNIS_NS), NODE_NAME, ValueSearch::Any)
+        .map(|m| m.node);
+    if graph.disk_based {
+        Box::new(nodes)
+    } else {
+        let mut nodes: Vec<NodeID> = nodes.collect();
+        nodes.sort_unstable_by(|a, b| {
+            let name_a = node_annos.get_value_for_item(a, &NODE_NAME_KEY);
+            let name_b = node_annos.get_value_for_item(b, &NODE_NAME_KEY);
+            name_a.cmp(&name_b)
+        });
+        Box::new(nodes.into_iter())
+    }
+}
+
+fn write_node<CT: ComponentType, W: std::io::Write>(
     graph: &Graph<CT>,
+    node: NodeID,
+    node_id: &str,
     writer: &mut Writer<W>,
     key_id_mapping: &BTreeMap<AnnoKey, String>,
 ) -> Result<()> {
-    for m in graph
-        .get_node_annos()
-        .exact_anno_search(Some(ANNIS_NS), NODE_TYPE, ValueSearch::Any)
-    {
-        let mut node_start = BytesStart::borrowed_name(b"node");
-
-        if let Some(id) = graph
-            .get_node_annos()
-            .get_value_for_item(&m.node, &NODE_NAME_KEY)
-        {
-            node_start.push_attribute(("id", id.as_ref()));
-            let node_annotations = graph.get_node_annos().get_annotations_for_item(&m.node);
-            if node_annotations.is_empty() {
-                // Write an empty XML element without child nodes
-                writer.write_event(Event::Empty(node_start))?;
-            } else {
-                writer.write_event(Event::Start(node_start))?;
-                // Write all annotations of the node as "data" element
-                for anno in node_annotations {
-                    if anno.key.ns != ANNIS_NS || anno.key.name != NODE_NAME {
-                        write_data(anno, writer, key_id_mapping)?;
-                    }
-                }
-                writer.write_event(Event::End(BytesEnd::borrowed(b"node")))?;
+    let mut node_start = BytesStart::borrowed_name(b"node");
+    node_start.push_attribute(("id", node_id));
+    let node_annotations = graph.get_node_annos().get_annotations_for_item(&node);
+    if node_annotations.is_empty() {
+        // Write an empty XML element without child nodes
+        writer.write_event(Event::Empty(node_start))?;
+    } else {
+        writer.write_event(Event::Start(node_start))?;
+        // Write all annotations of the node as "data" element
+        for anno in node_annotations {
+            if anno.key.ns != ANNIS_NS || anno.key.name != NODE_NAME {
+                write_data(anno, writer, key_id_mapping)?;
             }
         }
+        writer.write_event(Event::End(BytesEnd::borrowed(b"node")))?;
     }
     Ok(())
 }
 
-fn write_edges<CT: ComponentType, W: std::io::Write>(
+/// Write all nodes in document order, each one directly followed by its outgoing edges.
+///
+/// This only needs a single pass over the nodes and no state besides the list of components.
+fn write_nodes_and_edges<CT: ComponentType, W: std::io::Write>(
     graph: &Graph<CT>,
     writer: &mut Writer<W>,
     key_id_mapping: &BTreeMap<AnnoKey, String>,
 ) -> Result<()> {
+    // Collect all components, but skip auto-generated ones
+    let autogenerated_components: BTreeSet<Component<CT>> =
+        CT::update_graph_index_components(graph)
+            .into_iter()
+            .collect();
+    let components: Vec<_> = graph
+        .get_all_components(None, None)
+        .into_iter()
+        .filter(|c| !autogenerated_components.contains(c))
+        .filter_map(|c| graph.get_graphstorage(&c).map(|gs| (c.to_string(), gs)))
+        .collect();
+
     let mut edge_counter = 0;
-    for c in graph.get_all_components(None, None) {
-        // Create edge annotation keys for all components, but skip auto-generated ones
-        let autogenerated_components: BTreeSet<Component<CT>> =
-            CT::update_graph_index_components(graph)
-                .into_iter()
-                .collect();
-        if !autogenerated_components.contains(&c) {
-            if let Some(gs) = graph.get_graphstorage(&c) {
-                for source in gs.source_nodes() {
-                    if let Some(source_id) = graph
-                        .get_node_annos()
-                        .get_value_for_item(&source, &NODE_NAME_KEY)
-                    {
-                        for target in gs.get_outgoing_edges(source) {
-                            if let Some(target_id) = graph
-                                .get_node_annos()
-                                .get_value_for_item(&target, &NODE_NAME_KEY)
-                            {
-                                let edge = Edge { source, target };
-
-                                let mut edge_id = edge_counter.to_string();
-                                edge_counter += 1;
-                                edge_id.insert(0, 'e');
-
-                                let mut edge_start = BytesStart::borrowed_name(b"edge");
-                                edge_start.push_attribute(("id", edge_id.as_str()));
-                                edge_start.push_attribute(("source", source_id.as_ref()));
-                                edge_start.push_attribute(("target", target_id.as_ref()));
-                                // Use the "label" attribute as component type. This is consistent with how Neo4j interprets this non-standard attribute
-                                edge_start.push_attribute(("label", c.to_string().as_ref()));
-
-                                writer.write_event(Event::Start(edge_start))?;
-
-                                // Write all annotations of the edge as "data" element
-                                for anno in gs.get_anno_storage().get_annotations_for_item(&edge) {
-                                    write_data(anno, writer, key_id_mapping)?;
-                                }
-                                writer.write_event(Event::End(BytesEnd::borrowed(b"edge")))?;
-                            }
-                        }
+    for source in nodes_in_document_order(graph) {
+        let source_id = match graph
+            .get_node_annos()
+            .get_value_for_item(&source, &NODE_NAME_KEY)
+        {
+            Some(source_id) => source_id,
+            None => continue,
+        };
+        write_node(graph, source, &source_id, writer, key_id_mapping)?;
+
+        for (component_label, gs) in &components {
+            for target in gs.get_outgoing_edges(source) {
+                if let Some(target_id) = graph
+                    .get_node_annos()
+                    .get_value_for_item(&target, &NODE_NAME_KEY)
+                {
+                    let edge = Edge { source, target };
+
+                    let mut edge_id = edge_counter.to_string();
+                    edge_counter += 1;
+                    edge_id.insert(0, 'e');
+
+                    let mut edge_start = BytesStart::borrowed_name(b"edge");
+                    edge_start.push_attribute(("id", edge_id.as_str()));
+                    edge_start.push_attribute(("source", source_id.as_ref()));
+                    edge_start.push_attribute(("target", target_id.as_ref()));
+                    // Use the "label" attribute as component type. This is consistent with how Neo4j interprets this non-standard attribute
+                    edge_start.push_attribute(("label", component_label.as_str()));
+
+                    writer.write_event(Event::Start(edge_start))?;
+
+                    // Write all annotations of the edge as "data" element
+                    for anno in gs.get_anno_storage().get_annotations_for_item(&edge) {
+                        write_data(anno, writer, key_id_mapping)?;
                     }
+                    writer.write_event(Event::End(BytesEnd::borrowed(b"edge")))?;
                 }
             }
         }
@@ -230,7 +256,7 @@ where
     let mut graph_start = BytesStart::borrowed_name(b"graph");
     graph_start.push_attribute(("edgedefault", "directed"));
     // Add parse helper information to allow more efficient parsing
-    graph_start.push_attribute(("parse.order", "nodesfirst"));
+    graph_start.push_attribute(("parse.order", "adjacencylist"));
     graph_start.push_attribute(("parse.nodeids", "free"));
     graph_start.push_attribute(("parse.edgeids", "canonical"));
 
@@ -247,13 +273,9 @@ where
         writer.write_event(Event::End(BytesEnd::borrowed(b"data")))?;
     }
 
-    // Write out all nodes
-    progress_callback("exporting nodes");
-    write_nodes(graph, &mut writer, &key_id_mapping)?;
-
-    // Write out all edges
-    progress_callback("exporting edges");
-    write_edges(graph, &mut writer, &key_id_mapping)?;
+    // Write out all nodes and edges
+    progress_callback("exporting nodes and edges");
+    write_nodes_and_edges(graph, &mut writer, &key_id_mapping)?;
 
     writer.write_event(Event::End(BytesEnd::borrowed(b"graph")))?;
     writer.write_event(Event::End(BytesEnd::borrowed(b"graphml")))?;
@@ -298,25 +320,39 @@ fn add_annotation_key(keys: &mut BTreeMap<String, AnnoKey>, attributes: Attribut
 fn add_node(
     node_updates: &mut GraphUpdate,
     current_node_id: &Option<String>,
-    data: &mut HashMap<AnnoKey, String>,
+    data: &mut HashMap<AnnoKey, Vec<String>>,
 ) -> Result<()> {
     if let Some(node_name) = current_node_id {
         // Insert graph update for node
         let node_type = data
             .remove(&NODE_TYPE_KEY)
+            .and_then(|mut values| values.pop())
             .unwrap_or_else(|| "node".to_string());
         node_updates.add_event(UpdateEvent::AddNode {
             node_name: node_name.clone(),
             node_type,
         })?;
         // Add all remaining data entries as annotations
-        for (key, value) in data.drain() {
-            node_updates.add_event(UpdateEvent::AddNodeLabel {
-                node_name: node_name.clone(),
-                anno_ns: key.ns.into(),
-                anno_name: key.name.into(),
-                anno_value: value,
-            })?;
+        for (key, values) in data.drain() {
+            for (i, value) in values.into_iter().enumerate() {
+                let event = if i == 0 {
+                    UpdateEvent::AddNodeLabel {
+                        node_name: node_name.clone(),
+                        anno_ns: key.ns.to_string(),
+                        anno_name: key.name.to_string(),
+                        anno_value: value,
+                    }
+                } else {
+                    // repeated data elements for the same key are additional values of the annotation
+                    UpdateEvent::AddNodeLabelValue {
+                        node_name: node_name.clone(),
+                        anno_ns: key.ns.to_string(),
+                        anno_name: key.name.to_string(),
+                        anno_value: value,
+                    }
+                };
+                node_updates.add_event(event)?;
+            }
         }
     }
     Ok(())
@@ -327,7 +363,7 @@ fn add_edge<CT: ComponentType>(
     current_source_id: &Option<String>,
     current_target_id: &Option<String>,
     current_component: &Option<String>,
-    data: &mut HashMap<AnnoKey, String>,
+    data: &mut HashMap<AnnoKey, Vec<String>>,
 ) -> Result<()> {
     if let (Some(source), Some(target), Some(component)) =
         (current_source_id, current_target_id, current_component)
@@ -342,8 +378,11 @@ fn add_edge<CT: ComponentType>(
                 component_name: component.name.clone().into(),
             })?;
 
-            // Add all remaining data entries as annotations
-            for (key, value) in data.drain() {
+            // Add all remaining data entries as annotations, edge annotations only have a single value
+            for (key, value) in data
+                .drain()
+                .filter_map(|(key, mut values)| Some((key, values.pop()?)))
+            {
                 edge_updates.add_event(UpdateEvent::AddEdgeLabel {
                     source_node: source.clone(),
                     target_node: target.clone(),
@@ -379,7 +418,7 @@ fn read_graphml<CT: ComponentType, R: std::io::BufRead>(
     let mut current_source_id: Option<String> = None;
     let mut current_target_id: Option<String> = None;
     let mut current_component: Option<String> = None;
-    let mut data: HashMap<AnnoKey, String> = HashMap::new();
+    let mut data: HashMap<AnnoKey, Vec<String>> = HashMap::new();
 
     let mut config = None;
 
@@ -437,6 +476,17 @@ fn read_graphml<CT: ComponentType, R: std::io::BufRead>(
                                     Some(String::from_utf8_lossy(&att.value).to_string());
                             }
                         }
+                        // Elements without content (e.g. an empty `annis::tok-whitespace-after`) have no text
+                        // event, but are still annotations with an empty value.
+                        if let Some(current_data_key) = &current_data_key {
+                            if in_graph && level == 4 {
+                                if let Some(anno_key) = keys.get(current_data_key) {
+                                    data.entry(anno_key.clone())
+                                        .or_default()
+                                        .push(String::default());
+                                }
+                            }
+                        }
                     }
                     _ => {}
                 }
@@ -444,9 +494,13 @@ fn read_graphml<CT: ComponentType, R: std::io::BufRead>(
             Event::Text(t) => {
                 if let Some(current_data_key) = &current_data_key {
                     if in_graph && level == 4 {
-                        if let Some(anno_key) = keys.get(current_data_key) {
+                        if let Some(value) = keys
+                            .get(current_data_key)
+                            .and_then(|anno_key| data.get_mut(anno_key))
+                            .and_then(|values| values.last_mut())
+                        {
                             // Copy all data attributes into our own map
-                            data.insert(anno_key.clone(), t.unescape_and_decode(&reader)?);
+                            *value = t.unescape_and_decode(&reader)?;
                         }
                     }
                 }
@@ -586,6 +640,63 @@ value = "test""#;
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn export_graphml_document_order() {
+        for disk_based in [false, true].iter() {
+            let mut u = GraphUpdate::new();
+            for node_name in [
+                "corpus/doc2#n1",
+                "corpus/doc1#n2",
+                "corpus/doc1",
+                "corpus/doc2",
+            ]
+            .iter()
+            {
+                u.add_event(UpdateEvent::AddNode {
+                    node_name: node_name.to_string(),
+                    node_type: "node".to_string(),
+                })
+                .unwrap();
+            }
+            for (source, target) in [
+                ("corpus/doc2#n1", "corpus/doc2"),
+                ("corpus/doc1#n2", "corpus/doc1"),
+            ]
+            .iter()
+            {
+                u.add_event(UpdateEvent::AddEdge {
+                    source_node: source.to_string(),
+                    target_node: target.to_string(),
+                    component_type: "Edge".to_string(),
+                    layer: "".to_string(),
+                    component_name: "".to_string(),
+                })
+                .unwrap();
+            }
+            let mut g: Graph<DefaultComponentType> = Graph::new(*disk_based).unwrap();
+            g.apply_update(&mut u, |_| {}).unwrap();
+
+            let mut xml_data: Vec<u8> = Vec::default();
+            export(&g, None, &mut xml_data, |_| {}).unwrap();
+            let actual = String::from_utf8(xml_data).unwrap();
+
+            let positions: Vec<usize> = [
+                "<node id=\"corpus/doc1\"",
+                "<node id=\"corpus/doc1#n2\"",
+                "source=\"corpus/doc1#n2\"",
+                "<node id=\"corpus/doc2\"",
+                "<node id=\"corpus/doc2#n1\"",
+                "source=\"corpus/doc2#n1\"",
+            ]
+            .iter()
+            .map(|pattern| actual.find(pattern).unwrap())
+            .collect();
+            let mut sorted_positions = positions.clone();
+            sorted_positions.sort_unstable();
+            assert_eq!(sorted_positions, positions);
+        }
+    }
+
     #[test]
     fn import_graphml() {
         let input_xml = std::io::Cursor::new(
@@ -629,4 +740,94 @@ value = "test""#;
 
         assert_eq!(Some(TEST_CONFIG), config_str.as_deref());
     }
+
+    #[test]
+    fn import_graphml_whitespace_values() {
+        let mut u = GraphUpdate::new();
+        u.add_event(UpdateEvent::AddNode {
+            node_name: "tok".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        for (name, value) in [
+            ("tok-whitespace-before", ""),
+            ("tok-whitespace-after", " \n "),
+        ]
+        .iter()
+        {
+            u.add_event(UpdateEvent::AddNodeLabel {
+                node_name: "tok".to_string(),
+                anno_ns: "annis".to_string(),
+                anno_name: name.to_string(),
+                anno_value: value.to_string(),
+            })
+            .unwrap();
+        }
+        let mut g: Graph<DefaultComponentType> = Graph::new(false).unwrap();
+        g.apply_update(&mut u, |_| {}).unwrap();
+
+        let mut xml_data: Vec<u8> = Vec::default();
+        export(&g, None, &mut xml_data, |_| {}).unwrap();
+        let (g, _) =
+            import::<DefaultComponentType, _, _>(std::io::Cursor::new(xml_data), false, |_| {})
+                .unwrap();
+
+        let tok = g.get_node_id_from_name("tok").unwrap();
+        let value = |name: &str| {
+            g.get_node_annos()
+                .get_value_for_item(
+                    &tok,
+                    &AnnoKey {
+                        ns: "annis".into(),
+                        name: name.into(),
+                    },
+                )
+                .map(|v| v.to_string())
+        };
+        assert_eq!(Some("".to_string()), value("tok-whitespace-before"));
+        assert_eq!(Some(" \n ".to_string()), value("tok-whitespace-after"));
+    }
+
+    #[test]
+    fn import_graphml_multiple_values() {
+        let mut u = GraphUpdate::new();
+        u.add_event(UpdateEvent::AddNode {
+            node_name: "tok".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        for value in ["bank%1", "bank%2"].iter() {
+            u.add_event(UpdateEvent::AddNodeLabelValue {
+                node_name: "tok".to_string(),
+                anno_ns: "wordnet".to_string(),
+                anno_name: "sense".to_string(),
+                anno_value: value.to_string(),
+            })
+            .unwrap();
+        }
+        let mut g: Graph<DefaultComponentType> = Graph::new(false).unwrap();
+        g.apply_update(&mut u, |_| {}).unwrap();
+
+        let mut xml_data: Vec<u8> = Vec::default();
+        export(&g, None, &mut xml_data, |_| {}).unwrap();
+        let (g, _) =
+            import::<DefaultComponentType, _, _>(std::io::Cursor::new(xml_data), false, |_| {})
+                .unwrap();
+
+        let tok = g.get_node_id_from_name("tok").unwrap();
+        let mut values: Vec<String> = g
+            .get_node_annos()
+            .get_values_for_item(
+                &tok,
+                &AnnoKey {
+                    ns: "wordnet".into(),
+                    name: "sense".into(),
+                },
+            )
+            .into_iter()
+            .map(|v| v.to_string())
+            .collect();
+        values.sort();
+        assert_eq!(vec!["bank%1", "bank%2"], values);
+    }
 }
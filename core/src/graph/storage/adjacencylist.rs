@@ -5,7 +5,9 @@ use crate::{
     types::{AnnoKey, Annotation, Edge, NodeID},
 };
 
-use super::{EdgeContainer, GraphStatistic, GraphStorage, WriteableGraphStorage};
+use super::{
+    EdgeContainer, EstimatedStatistics, GraphStatistic, GraphStorage, WriteableGraphStorage,
+};
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::Deserialize;
 use std::collections::BTreeSet;
@@ -17,6 +19,9 @@ pub struct AdjacencyListStorage {
     inverse_edges: FxHashMap<NodeID, Vec<NodeID>>,
     annos: AnnoStorageImpl<Edge>,
     stats: Option<GraphStatistic>,
+    #[serde(skip)]
+    #[ignore_malloc_size_of = "is only a small cache"]
+    estimated_stats: EstimatedStatistics,
 }
 
 fn get_fan_outs(edges: &FxHashMap<NodeID, Vec<NodeID>>) -> Vec<usize> {
@@ -45,6 +50,7 @@ impl AdjacencyListStorage {
             inverse_edges: FxHashMap::default(),
             annos: AnnoStorageImpl::new(),
             stats: None,
+            estimated_stats: EstimatedStatistics::default(),
         }
     }
 
@@ -53,6 +59,7 @@ impl AdjacencyListStorage {
         self.inverse_edges.clear();
         self.annos.clear()?;
         self.stats = None;
+        self.estimated_stats.clear();
         Ok(())
     }
 }
@@ -91,6 +98,14 @@ impl EdgeContainer for AdjacencyListStorage {
     fn get_statistics(&self) -> Option<&GraphStatistic> {
         self.stats.as_ref()
     }
+
+    fn get_statistics_or_estimate(&self) -> Option<GraphStatistic> {
+        if let Some(stats) = &self.stats {
+            Some(stats.clone())
+        } else {
+            Some(self.estimated_stats.get_or_estimate(self))
+        }
+    }
 }
 
 impl GraphStorage for AdjacencyListStorage {
@@ -231,6 +246,7 @@ impl WriteableGraphStorage for AdjacencyListStorage {
                 regular_entry.insert(insertion_idx, edge.target);
             }
             self.stats = None;
+            self.estimated_stats.clear();
         }
         Ok(())
     }
@@ -261,6 +277,8 @@ impl WriteableGraphStorage for AdjacencyListStorage {
             self.annos.remove_annotation_for_item(edge, &a.key)?;
         }
 
+        self.estimated_stats.clear();
+
         Ok(())
     }
     fn delete_edge_annotation(&mut self, edge: &Edge, anno_key: &AnnoKey) -> Result<()> {
@@ -842,4 +860,40 @@ mod tests {
         let stats = gs.get_statistics().unwrap();
         assert_eq!(true, stats.cyclic);
     }
+
+    #[test]
+    fn estimate_missing_statistics() {
+        let mut gs = AdjacencyListStorage::new();
+        // a small tree: 1 -> 2, 1 -> 3, 3 -> 4
+        for (source, target) in [(1, 2), (1, 3), (3, 4)].iter() {
+            gs.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+        assert!(gs.get_statistics().is_none());
+
+        let estimation = gs.get_statistics_or_estimate().unwrap();
+        assert_eq!(4, estimation.nodes);
+        assert_eq!(2, estimation.max_fan_out);
+        assert_eq!(2, estimation.max_depth);
+        assert_eq!(false, estimation.cyclic);
+        assert!(gs.get_statistics().is_none());
+
+        // changing the graph storage must invalidate the cached estimation
+        gs.add_edge(Edge {
+            source: 4,
+            target: 1,
+        })
+        .unwrap();
+        let estimation = gs.get_statistics_or_estimate().unwrap();
+        assert_eq!(true, estimation.cyclic);
+
+        // calculated statistics are preferred
+        gs.calculate_statistics();
+        let stats = gs.get_statistics_or_estimate().unwrap();
+        assert_eq!(true, stats.cyclic);
+        assert_eq!(0, stats.max_depth);
+    }
 }
@@ -0,0 +1,263 @@
+//! Micro-benchmarks for comparing the graph storage implementations on the components of real corpora.
+//!
+//! The benchmark copies a component into every implementation that is able to represent it and measures
+//! the reachability functions with a sample of the nodes of the component.
+//! This module is only available when the `benchmark` feature is enabled.
+
+use super::adjacencylist::AdjacencyListStorage;
+use super::{registry, EdgeContainer, GraphStatistic, GraphStorage, WriteableGraphStorage};
+use crate::{
+    errors::{GraphAnnisCoreError, Result},
+    graph::Graph,
+    types::{Component, ComponentType, NodeID},
+};
+use rand::{rngs::SmallRng, seq::IteratorRandom, SeedableRng};
+use std::{
+    fmt,
+    ops::Bound,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Parameters of a benchmark run.
+#[derive(Clone, Debug)]
+pub struct BenchmarkConfig {
+    /// Number of source nodes that are sampled from the component.
+    pub sample_size: usize,
+    /// How often the operations are repeated for all sampled nodes.
+    pub iterations: usize,
+    /// Seed for sampling the nodes, so different implementations and runs use the same nodes.
+    pub seed: u64,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> Self {
+        BenchmarkConfig {
+            sample_size: 100,
+            iterations: 10,
+            seed: 0,
+        }
+    }
+}
+
+/// The graph storage function that is measured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    FindConnected,
+    Distance,
+    IsConnected,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::FindConnected => write!(f, "find_connected"),
+            Operation::Distance => write!(f, "distance"),
+            Operation::IsConnected => write!(f, "is_connected"),
+        }
+    }
+}
+
+/// Measured times of a single operation for one graph storage implementation.
+#[derive(Clone, Debug)]
+pub struct BenchmarkResult {
+    /// Description of the component the benchmark was executed on.
+    pub component: String,
+    /// Serialization ID of the graph storage implementation.
+    pub implementation: String,
+    pub operation: Operation,
+    /// Average time of a single call for each iteration.
+    pub times: Vec<Duration>,
+}
+
+impl BenchmarkResult {
+    pub fn min(&self) -> Duration {
+        self.times.iter().min().cloned().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.times.iter().max().cloned().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.times.is_empty() {
+            Duration::default()
+        } else {
+            self.times.iter().sum::<Duration>() / (self.times.len() as u32)
+        }
+    }
+}
+
+/// Formats the result in the same way as criterion.rs does, so existing tooling can parse it.
+impl fmt::Display for BenchmarkResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let id = format!(
+            "{}/{}/{}",
+            self.component, self.implementation, self.operation
+        );
+        writeln!(f, "{}", id)?;
+        write!(
+            f,
+            "{:24}time:   [{} {} {}]",
+            "",
+            format_duration(self.min()),
+            format_duration(self.mean()),
+            format_duration(self.max())
+        )
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    let ns = d.as_secs_f64() * 1e9;
+    if ns < 1e3 {
+        format!("{:.4} ns", ns)
+    } else if ns < 1e6 {
+        format!("{:.4} us", ns / 1e3)
+    } else if ns < 1e9 {
+        format!("{:.4} ms", ns / 1e6)
+    } else {
+        format!("{:.4} s", ns / 1e9)
+    }
+}
+
+/// Measure `find_connected`, `distance` and `is_connected` for all graph storage implementations
+/// which are able to represent the given component of the graph.
+pub fn benchmark_component<CT: ComponentType>(
+    graph: &Graph<CT>,
+    component: &Component<CT>,
+    config: &BenchmarkConfig,
+) -> Result<Vec<BenchmarkResult>> {
+    let orig = graph
+        .get_graphstorage_as_ref(component)
+        .ok_or_else(|| GraphAnnisCoreError::ComponentNotLoaded(component.to_string()))?;
+
+    // The adjacency list can represent any component and is used to calculate the statistics
+    let mut adjacency = AdjacencyListStorage::new();
+    adjacency.copy(graph.get_node_annos(), orig)?;
+    adjacency.calculate_statistics();
+    let stats: GraphStatistic = adjacency
+        .get_statistics()
+        .cloned()
+        .unwrap_or_else(|| unreachable!("statistics have been calculated"));
+
+    let candidates = registry::get_applicable_impls(&adjacency, &stats, graph.disk_based);
+
+    // Sample the source nodes and pair them with a reachable node if there is one
+    let mut rng = SmallRng::seed_from_u64(config.seed);
+    let sources = adjacency
+        .source_nodes()
+        .choose_multiple(&mut rng, config.sample_size);
+    let pairs: Vec<(NodeID, NodeID)> = sources
+        .iter()
+        .enumerate()
+        .map(|(i, source)| {
+            let target = adjacency
+                .find_connected(*source, 1, Bound::Unbounded)
+                .next()
+                .unwrap_or_else(|| sources[(i + 1) % sources.len()]);
+            (*source, target)
+        })
+        .collect();
+
+    let mut result = Vec::new();
+    for info in candidates {
+        let mut gs = registry::create_from_info(&info)?;
+        Arc::get_mut(&mut gs)
+            .ok_or_else(|| GraphAnnisCoreError::NonExclusiveComponentReference(info.id.clone()))?
+            .copy(graph.get_node_annos(), orig)?;
+
+        for operation in [
+            Operation::FindConnected,
+            Operation::Distance,
+            Operation::IsConnected,
+        ]
+        .iter()
+        {
+            let mut times = Vec::with_capacity(config.iterations);
+            for _ in 0..config.iterations {
+                let start = Instant::now();
+                for (source, target) in &pairs {
+                    match operation {
+                        Operation::FindConnected => {
+                            gs.find_connected(*source, 1, Bound::Unbounded).count();
+                        }
+                        Operation::Distance => {
+                            gs.distance(*source, *target);
+                        }
+                        Operation::IsConnected => {
+                            gs.is_connected(*source, *target, 1, Bound::Unbounded);
+                        }
+                    }
+                }
+                times.push(start.elapsed() / (pairs.len().max(1) as u32));
+            }
+            result.push(BenchmarkResult {
+                component: component.to_string(),
+                implementation: info.id.clone(),
+                operation: *operation,
+                times,
+            });
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::storage::symmetric::SymmetricAdjacencyListStorage;
+    use crate::graph::update::{GraphUpdate, UpdateEvent};
+    use crate::types::DefaultComponentType;
+
+    #[test]
+    fn benchmark_chain() {
+        let mut u = GraphUpdate::new();
+        for i in 0..10 {
+            u.add_event(UpdateEvent::AddNode {
+                node_name: format!("n{}", i),
+                node_type: "node".to_string(),
+            })
+            .unwrap();
+        }
+        for i in 0..9 {
+            u.add_event(UpdateEvent::AddEdge {
+                source_node: format!("n{}", i),
+                target_node: format!("n{}", i + 1),
+                layer: "test".to_string(),
+                component_type: "Edge".to_string(),
+                component_name: "chain".to_string(),
+            })
+            .unwrap();
+        }
+        let mut g: Graph<DefaultComponentType> = Graph::new(false).unwrap();
+        g.apply_update(&mut u, |_| {}).unwrap();
+
+        let component = Component::new(DefaultComponentType::Edge, "test".into(), "chain".into());
+        let config = BenchmarkConfig {
+            sample_size: 5,
+            iterations: 2,
+            seed: 42,
+        };
+        let result = benchmark_component(&g, &component, &config).unwrap();
+        g.calculate_component_statistics(&component).unwrap();
+
+        // a chain can be represented by all implementations besides the symmetric one
+        let gs = g.get_graphstorage_as_ref(&component).unwrap();
+        let applicable: std::collections::BTreeSet<_> =
+            registry::get_applicable_impls(gs, gs.get_statistics().unwrap(), false)
+                .into_iter()
+                .map(|info| info.id)
+                .collect();
+        let implementations: std::collections::BTreeSet<_> =
+            result.iter().map(|r| r.implementation.clone()).collect();
+        assert_eq!(applicable, implementations);
+        assert!(
+            !implementations.contains(&SymmetricAdjacencyListStorage::default().serialization_id())
+        );
+        assert_eq!(3 * implementations.len(), result.len());
+        for r in result {
+            assert_eq!(2, r.times.len());
+            assert!(r.min() <= r.mean() && r.mean() <= r.max());
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use super::{EdgeContainer, GraphStatistic, GraphStorage};
+use crate::{
+    annostorage::{inmemory::AnnoStorageImpl, AnnotationStorage},
+    errors::Result,
+    types::{Edge, NodeID},
+};
+use num_traits::{FromPrimitive, ToPrimitive};
+use serde::Deserialize;
+use std::{ops::Bound, path::Path};
+
+#[derive(Serialize, Deserialize, Clone, Copy, MallocSizeOf)]
+struct ChainPosition {
+    chain: u32,
+    pos: u32,
+}
+
+/// A graph storage for components that consist of linear chains (like the token ordering) and which contain most of
+/// the nodes of the graph.
+///
+/// Each chain is stored as a vector of node IDs and the position of a node is looked up in a vector indexed by the
+/// node ID, so getting the distance between two nodes or checking if they are connected needs constant time.
+#[derive(Serialize, Deserialize, Clone, MallocSizeOf)]
+pub struct DenseOrderingStorage {
+    node_to_pos: Vec<Option<ChainPosition>>,
+    chains: Vec<Vec<NodeID>>,
+    annos: AnnoStorageImpl<Edge>,
+    stats: Option<GraphStatistic>,
+}
+
+impl Default for DenseOrderingStorage {
+    fn default() -> Self {
+        DenseOrderingStorage::new()
+    }
+}
+
+impl DenseOrderingStorage {
+    pub fn new() -> DenseOrderingStorage {
+        DenseOrderingStorage {
+            node_to_pos: Vec::default(),
+            chains: Vec::default(),
+            annos: AnnoStorageImpl::new(),
+            stats: None,
+        }
+    }
+
+    fn get_pos(&self, node: NodeID) -> Option<ChainPosition> {
+        let idx = node.to_usize()?;
+        self.node_to_pos.get(idx).cloned().flatten()
+    }
+
+    fn get_chain(&self, pos: ChainPosition) -> &[NodeID] {
+        &self.chains[pos.chain as usize]
+    }
+
+    fn is_connected_by_pos(
+        source_pos: ChainPosition,
+        target_pos: ChainPosition,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> bool {
+        if source_pos.chain == target_pos.chain && source_pos.pos <= target_pos.pos {
+            let diff = (target_pos.pos - source_pos.pos) as usize;
+            match max_distance {
+                Bound::Unbounded => diff >= min_distance,
+                Bound::Included(max_distance) => diff >= min_distance && diff <= max_distance,
+                Bound::Excluded(max_distance) => diff >= min_distance && diff < max_distance,
+            }
+        } else {
+            false
+        }
+    }
+}
+
+impl EdgeContainer for DenseOrderingStorage {
+    fn get_outgoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        if let Some(pos) = self.get_pos(node) {
+            if let Some(next) = self.get_chain(pos).get(pos.pos as usize + 1) {
+                return Box::new(std::iter::once(*next));
+            }
+        }
+        Box::new(std::iter::empty())
+    }
+
+    fn get_ingoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        if let Some(pos) = self.get_pos(node) {
+            if let Some(previous_pos) = (pos.pos as usize).checked_sub(1) {
+                return Box::new(std::iter::once(self.get_chain(pos)[previous_pos]));
+            }
+        }
+        Box::new(std::iter::empty())
+    }
+
+    fn source_nodes<'a>(&'a self) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        // the last element of a chain is only a target node, not a source node
+        let it = self
+            .chains
+            .iter()
+            .flat_map(|chain| chain.iter().rev().skip(1))
+            .cloned();
+        Box::new(it)
+    }
+
+    fn get_statistics(&self) -> Option<&GraphStatistic> {
+        self.stats.as_ref()
+    }
+}
+
+impl GraphStorage for DenseOrderingStorage {
+    fn find_connected<'a>(
+        &'a self,
+        node: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        if let Some(pos) = self.get_pos(node) {
+            let chain = self.get_chain(pos);
+            let offset = pos.pos as usize;
+            let start = offset.saturating_add(min_distance);
+            let end = match max_distance {
+                Bound::Unbounded => chain.len(),
+                Bound::Included(max_distance) => offset.saturating_add(max_distance) + 1,
+                Bound::Excluded(max_distance) => offset.saturating_add(max_distance),
+            };
+            let end = std::cmp::min(chain.len(), end);
+            if start < end {
+                return Box::new(chain[start..end].iter().cloned());
+            }
+        }
+        Box::new(std::iter::empty())
+    }
+
+    fn find_connected_inverse<'a>(
+        &'a self,
+        node: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        if let Some(pos) = self.get_pos(node) {
+            let chain = self.get_chain(pos);
+            let offset = pos.pos as usize;
+            if let Some(end) = offset.checked_sub(min_distance) {
+                let start = match max_distance {
+                    Bound::Unbounded => 0,
+                    Bound::Included(max_distance) => offset.saturating_sub(max_distance),
+                    Bound::Excluded(max_distance) => {
+                        if max_distance == 0 {
+                            return Box::new(std::iter::empty());
+                        }
+                        offset.saturating_sub(max_distance - 1)
+                    }
+                };
+                if start <= end {
+                    // return the nearest nodes first
+                    return Box::new(chain[start..=end].iter().rev().cloned());
+                }
+            }
+        }
+        Box::new(std::iter::empty())
+    }
+
+    fn distance(&self, source: NodeID, target: NodeID) -> Option<usize> {
+        if source == target {
+            return Some(0);
+        }
+        let source_pos = self.get_pos(source)?;
+        let target_pos = self.get_pos(target)?;
+        if source_pos.chain == target_pos.chain && source_pos.pos <= target_pos.pos {
+            Some((target_pos.pos - source_pos.pos) as usize)
+        } else {
+            None
+        }
+    }
+
+    fn is_connected(
+        &self,
+        source: NodeID,
+        target: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> bool {
+        if let (Some(source_pos), Some(target_pos)) = (self.get_pos(source), self.get_pos(target)) {
+            Self::is_connected_by_pos(source_pos, target_pos, min_distance, max_distance)
+        } else {
+            false
+        }
+    }
+
+    fn are_connected(
+        &self,
+        source: NodeID,
+        targets: &[NodeID],
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Vec<bool> {
+        if let Some(source_pos) = self.get_pos(source) {
+            targets
+                .iter()
+                .map(|target| {
+                    self.get_pos(*target)
+                        .map(|target_pos| {
+                            Self::is_connected_by_pos(
+                                source_pos,
+                                target_pos,
+                                min_distance,
+                                max_distance,
+                            )
+                        })
+                        .unwrap_or(false)
+                })
+                .collect()
+        } else {
+            vec![false; targets.len()]
+        }
+    }
+
+    fn get_anno_storage(&self) -> &dyn AnnotationStorage<Edge> {
+        &self.annos
+    }
+
+    fn copy(
+        &mut self,
+        node_annos: &dyn AnnotationStorage<NodeID>,
+        orig: &dyn GraphStorage,
+    ) -> Result<()> {
+        self.annos.clear()?;
+        self.node_to_pos.clear();
+        self.chains.clear();
+        self.stats = None;
+
+        if let Some(largest_idx) = node_annos.get_largest_item().and_then(|idx| idx.to_usize()) {
+            debug!("Resizing dense ordering to size {}", largest_idx + 1);
+            self.node_to_pos.resize(largest_idx + 1, None);
+
+            // each chain starts at a source node without an incoming edge
+            let mut roots: Vec<NodeID> = orig
+                .source_nodes()
+                .filter(|n| orig.get_ingoing_edges(*n).next().is_none())
+                .collect();
+            roots.sort_unstable();
+
+            for root in roots {
+                let chain_idx = if let Some(chain_idx) = u32::from_usize(self.chains.len()) {
+                    chain_idx
+                } else {
+                    break;
+                };
+                let mut chain = Vec::new();
+                let mut current = Some(root);
+                while let Some(source) = current {
+                    let pos = if let Some(pos) = u32::from_usize(chain.len()) {
+                        pos
+                    } else {
+                        break;
+                    };
+                    match source
+                        .to_usize()
+                        .and_then(|idx| self.node_to_pos.get_mut(idx))
+                    {
+                        // stop at nodes that have already been visited, e.g. because of a cycle
+                        Some(entry) if entry.is_none() => {
+                            *entry = Some(ChainPosition {
+                                chain: chain_idx,
+                                pos,
+                            });
+                        }
+                        _ => break,
+                    }
+                    chain.push(source);
+
+                    current = orig.get_outgoing_edges(source).next();
+                    if let Some(target) = current {
+                        let e = Edge { source, target };
+                        for a in orig.get_anno_storage().get_annotations_for_item(&e) {
+                            self.annos.insert(e.clone(), a)?;
+                        }
+                    }
+                }
+                chain.shrink_to_fit();
+                self.chains.push(chain);
+            }
+            self.chains.shrink_to_fit();
+
+            self.stats = orig.get_statistics().cloned();
+            self.annos.calculate_statistics();
+        }
+        Ok(())
+    }
+
+    fn as_edgecontainer(&self) -> &dyn EdgeContainer {
+        self
+    }
+
+    fn inverse_has_same_cost(&self) -> bool {
+        true
+    }
+
+    fn serialization_id(&self) -> String {
+        "DenseOrderingV1".to_owned()
+    }
+
+    fn load_from(location: &Path) -> Result<Self>
+    where
+        for<'de> Self: std::marker::Sized + Deserialize<'de>,
+    {
+        let mut result: Self = super::default_deserialize_gs(location)?;
+        result.annos.after_deserialization();
+        Ok(result)
+    }
+
+    fn save_to(&self, location: &Path) -> Result<()> {
+        super::default_serialize_gs(self, location)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::storage::{adjacencylist::AdjacencyListStorage, WriteableGraphStorage};
+    use crate::graph::NODE_NAME_KEY;
+    use crate::types::Annotation;
+
+    #[test]
+    fn same_result_as_adjacency_list() {
+        let mut node_annos: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for n in 0..=8 {
+            node_annos
+                .insert(
+                    n,
+                    Annotation {
+                        key: NODE_NAME_KEY.as_ref().clone(),
+                        val: format!("n{}", n).into(),
+                    },
+                )
+                .unwrap();
+        }
+        // two texts and a node (8) which is not part of the component
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in [(0, 1), (1, 2), (2, 3), (3, 4), (5, 6), (6, 7)].iter() {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+        orig.calculate_statistics();
+
+        let mut gs = DenseOrderingStorage::new();
+        gs.copy(&node_annos, &orig).unwrap();
+
+        let sorted = |it: Box<dyn Iterator<Item = NodeID>>| {
+            let mut result: Vec<NodeID> = it.collect();
+            result.sort_unstable();
+            result
+        };
+
+        for source in 0..=7 {
+            assert_eq!(
+                sorted(orig.get_outgoing_edges(source)),
+                sorted(gs.get_outgoing_edges(source))
+            );
+            assert_eq!(
+                sorted(orig.get_ingoing_edges(source)),
+                sorted(gs.get_ingoing_edges(source))
+            );
+            for (min_distance, max_distance) in &[
+                (0, Bound::Included(0)),
+                (1, Bound::Included(1)),
+                (1, Bound::Unbounded),
+                (2, Bound::Included(3)),
+                (0, Bound::Included(2)),
+            ] {
+                assert_eq!(
+                    sorted(orig.find_connected(source, *min_distance, *max_distance)),
+                    sorted(gs.find_connected(source, *min_distance, *max_distance))
+                );
+                assert_eq!(
+                    sorted(orig.find_connected_inverse(source, *min_distance, *max_distance)),
+                    sorted(gs.find_connected_inverse(source, *min_distance, *max_distance))
+                );
+                for target in 0..=9 {
+                    assert_eq!(
+                        orig.is_connected(source, target, *min_distance, *max_distance),
+                        gs.is_connected(source, target, *min_distance, *max_distance)
+                    );
+                }
+            }
+            for target in 0..=8 {
+                assert_eq!(orig.distance(source, target), gs.distance(source, target));
+            }
+        }
+        assert_eq!(sorted(orig.source_nodes()), sorted(gs.source_nodes()));
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     annostorage::ondisk::AnnoStorageImpl,
     dfs::CycleSafeDFS,
     errors::Result,
+    serializer::KeySerializer,
     util::disk_collections::{DiskMap, EvictionStrategy},
 };
 use itertools::Itertools;
@@ -21,6 +22,8 @@ pub struct DiskAdjacencyListStorage {
     inverse_edges: DiskMap<Edge, bool>,
     annos: AnnoStorageImpl<Edge>,
     stats: Option<GraphStatistic>,
+    #[ignore_malloc_size_of = "is only a small cache"]
+    estimated_stats: EstimatedStatistics,
 }
 
 fn get_fan_outs(edges: &DiskMap<Edge, bool>) -> Vec<usize> {
@@ -43,6 +46,7 @@ impl DiskAdjacencyListStorage {
             inverse_edges: DiskMap::default(),
             annos: AnnoStorageImpl::new(None)?,
             stats: None,
+            estimated_stats: EstimatedStatistics::default(),
         })
     }
 
@@ -51,39 +55,20 @@ impl DiskAdjacencyListStorage {
         self.inverse_edges.clear();
         self.annos.clear()?;
         self.stats = None;
+        self.estimated_stats.clear();
         Ok(())
     }
 }
 
 impl EdgeContainer for DiskAdjacencyListStorage {
     fn get_outgoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
-        let lower_bound = Edge {
-            source: node,
-            target: NodeID::min_value(),
-        };
-        let upper_bound = Edge {
-            source: node,
-            target: NodeID::max_value(),
-        };
-        Box::new(
-            self.edges
-                .range(lower_bound..upper_bound)
-                .map(|(e, _)| e.target),
-        )
+        Box::new(self.edges.prefix(&node.create_key()).map(|(e, _)| e.target))
     }
 
     fn get_ingoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
-        let lower_bound = Edge {
-            source: node,
-            target: NodeID::min_value(),
-        };
-        let upper_bound = Edge {
-            source: node,
-            target: NodeID::max_value(),
-        };
         Box::new(
             self.inverse_edges
-                .range(lower_bound..upper_bound)
+                .prefix(&node.create_key())
                 .map(|(e, _)| e.target),
         )
     }
@@ -96,6 +81,14 @@ impl EdgeContainer for DiskAdjacencyListStorage {
     fn get_statistics(&self) -> Option<&GraphStatistic> {
         self.stats.as_ref()
     }
+
+    fn get_statistics_or_estimate(&self) -> Option<GraphStatistic> {
+        if let Some(stats) = &self.stats {
+            Some(stats.clone())
+        } else {
+            Some(self.estimated_stats.get_or_estimate(self))
+        }
+    }
 }
 
 impl GraphStorage for DiskAdjacencyListStorage {
@@ -130,11 +123,13 @@ impl GraphStorage for DiskAdjacencyListStorage {
                 location.join(crate::annostorage::ondisk::SUBFOLDER_NAME),
             ))?,
             stats,
+            estimated_stats: EstimatedStatistics::default(),
         };
         Ok(result)
     }
 
     fn save_to(&self, location: &Path) -> Result<()> {
+        encryption::check_no_key("disk-based graph storage")?;
         self.edges.write_to(&location.join("edges.bin"))?;
         self.inverse_edges
             .write_to(&location.join("inverse_edges.bin"))?;
@@ -251,6 +246,7 @@ impl WriteableGraphStorage for DiskAdjacencyListStorage {
             self.inverse_edges.insert(edge.inverse(), true)?;
             self.edges.insert(edge, true)?;
             self.stats = None;
+            self.estimated_stats.clear();
         }
         Ok(())
     }
@@ -271,6 +267,8 @@ impl WriteableGraphStorage for DiskAdjacencyListStorage {
             self.annos.remove_annotation_for_item(edge, &a.key)?;
         }
 
+        self.estimated_stats.clear();
+
         Ok(())
     }
     fn delete_edge_annotation(&mut self, edge: &Edge, anno_key: &AnnoKey) -> Result<()> {
@@ -45,6 +45,31 @@ where
         self.stats = None;
         Ok(())
     }
+
+    fn is_connected_by_pos(
+        source_pos: &RelativePosition<PosT>,
+        target_pos: &RelativePosition<PosT>,
+        min_distance: usize,
+        max_distance: std::ops::Bound<usize>,
+    ) -> bool {
+        if source_pos.root == target_pos.root && source_pos.pos <= target_pos.pos {
+            let diff = target_pos.pos.clone() - source_pos.pos.clone();
+            if let Some(diff) = diff.to_usize() {
+                match max_distance {
+                    std::ops::Bound::Unbounded => {
+                        return diff >= min_distance;
+                    }
+                    std::ops::Bound::Included(max_distance) => {
+                        return diff >= min_distance && diff <= max_distance;
+                    }
+                    std::ops::Bound::Excluded(max_distance) => {
+                        return diff >= min_distance && diff < max_distance;
+                    }
+                }
+            }
+        }
+        false
+    }
 }
 
 impl<PosT> Default for LinearGraphStorage<PosT>
@@ -227,25 +252,38 @@ where
         if let (Some(source_pos), Some(target_pos)) =
             (self.node_to_pos.get(&source), self.node_to_pos.get(&target))
         {
-            if source_pos.root == target_pos.root && source_pos.pos <= target_pos.pos {
-                let diff = target_pos.pos.clone() - source_pos.pos.clone();
-                if let Some(diff) = diff.to_usize() {
-                    match max_distance {
-                        std::ops::Bound::Unbounded => {
-                            return diff >= min_distance;
-                        }
-                        std::ops::Bound::Included(max_distance) => {
-                            return diff >= min_distance && diff <= max_distance;
-                        }
-                        std::ops::Bound::Excluded(max_distance) => {
-                            return diff >= min_distance && diff < max_distance;
-                        }
-                    }
-                }
-            }
+            Self::is_connected_by_pos(source_pos, target_pos, min_distance, max_distance)
+        } else {
+            false
         }
+    }
 
-        false
+    fn are_connected(
+        &self,
+        source: NodeID,
+        targets: &[NodeID],
+        min_distance: usize,
+        max_distance: std::ops::Bound<usize>,
+    ) -> Vec<bool> {
+        if let Some(source_pos) = self.node_to_pos.get(&source) {
+            targets
+                .iter()
+                .map(|target| {
+                    if let Some(target_pos) = self.node_to_pos.get(target) {
+                        Self::is_connected_by_pos(
+                            source_pos,
+                            target_pos,
+                            min_distance,
+                            max_distance,
+                        )
+                    } else {
+                        false
+                    }
+                })
+                .collect()
+        } else {
+            vec![false; targets.len()]
+        }
     }
 
     fn copy(
@@ -334,3 +372,60 @@ where
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::storage::{adjacencylist::AdjacencyListStorage, WriteableGraphStorage};
+    use crate::types::Annotation;
+    use std::ops::Bound;
+
+    #[test]
+    fn are_connected_same_as_is_connected() {
+        let mut node_annos: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for n in 1..=6 {
+            node_annos
+                .insert(
+                    n,
+                    Annotation {
+                        key: NODE_NAME_KEY.as_ref().clone(),
+                        val: format!("n{}", n).into(),
+                    },
+                )
+                .unwrap();
+        }
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in [(1, 2), (2, 3), (3, 4), (5, 6)].iter() {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+
+        let mut gs = LinearGraphStorage::<u16>::new();
+        gs.copy(&node_annos, &orig).unwrap();
+
+        let targets: Vec<NodeID> = (1..=7).collect();
+        for source in 1..=7 {
+            for (min_distance, max_distance) in &[
+                (1, Bound::Included(1)),
+                (1, Bound::Unbounded),
+                (2, Bound::Excluded(3)),
+            ] {
+                let expected: Vec<bool> = targets
+                    .iter()
+                    .map(|t| gs.is_connected(source, *t, *min_distance, *max_distance))
+                    .collect();
+                assert_eq!(
+                    expected,
+                    gs.are_connected(source, &targets, *min_distance, *max_distance)
+                );
+            }
+        }
+        assert_eq!(
+            vec![false, true, true, true],
+            gs.are_connected(1, &[1, 2, 3, 4], 1, Bound::Unbounded)
+        );
+    }
+}
@@ -1,22 +1,34 @@
 pub mod adjacencylist;
+#[cfg(feature = "benchmark")]
+pub mod benchmark;
 pub mod dense_adjacency;
+pub mod dense_ordering;
 pub mod disk_adjacency;
 pub mod linear;
 pub mod prepost;
 pub mod registry;
+pub mod symmetric;
 pub mod union;
 
 use crate::malloc_size_of::MallocSizeOf;
 use crate::{
     annostorage::AnnotationStorage,
+    dfs::CycleSafeDFS,
     errors::Result,
     types::{AnnoKey, Annotation, Edge, NodeID},
+    util::encryption,
 };
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use std::{self, path::Path};
+use std::{
+    self,
+    path::Path,
+    sync::RwLock,
+    time::{Duration, Instant},
+};
 
 /// Some general statistical numbers specific to a graph component
-#[derive(Serialize, Deserialize, Clone, MallocSizeOf)]
+#[derive(Serialize, Deserialize, Clone, Debug, MallocSizeOf)]
 pub struct GraphStatistic {
     /// True if the component contains any cycle.
     pub cyclic: bool,
@@ -61,6 +73,134 @@ impl std::fmt::Display for GraphStatistic {
     }
 }
 
+/// Maximum number of source nodes that are sampled when estimating the statistics.
+const ESTIMATION_SAMPLE_SIZE: usize = 1_000;
+/// Maximum time that is spent on estimating the statistics of a single edge container.
+const ESTIMATION_TIME_BUDGET: Duration = Duration::from_millis(5);
+
+/// Estimate the statistics of an edge container by sampling the first source nodes and their fan-out.
+///
+/// The estimation stops when the sample is complete or the time budget is exceeded. The depth and whether the graph
+/// is cyclic are only determined for the paths starting at the sampled nodes, so these are lower bounds.
+pub fn estimate_statistics(container: &dyn EdgeContainer) -> GraphStatistic {
+    let start = Instant::now();
+
+    let mut stats = GraphStatistic {
+        max_depth: 1,
+        max_fan_out: 0,
+        avg_fan_out: 0.0,
+        fan_out_99_percentile: 0,
+        inverse_fan_out_99_percentile: 0,
+        cyclic: false,
+        // without a complete traversal we can't know if this is a tree
+        rooted_tree: false,
+        nodes: 0,
+        dfs_visit_ratio: 1.0,
+    };
+
+    let mut sampled_sources = Vec::new();
+    let mut all_nodes = FxHashSet::default();
+    let mut fan_outs = Vec::new();
+    let mut inverse_fan_outs = Vec::new();
+    for source in container.source_nodes() {
+        if sampled_sources.len() >= ESTIMATION_SAMPLE_SIZE
+            || start.elapsed() > ESTIMATION_TIME_BUDGET
+        {
+            break;
+        }
+        all_nodes.insert(source);
+        let mut fan_out = 0;
+        for target in container.get_outgoing_edges(source) {
+            fan_out += 1;
+            if all_nodes.insert(target) {
+                inverse_fan_outs.push(container.get_ingoing_edges(target).count());
+            }
+        }
+        fan_outs.push(fan_out);
+        sampled_sources.push(source);
+    }
+    stats.nodes = all_nodes.len();
+
+    fan_outs.sort_unstable();
+    inverse_fan_outs.sort_unstable();
+    // use the same percentile calculation as the graph storages
+    if let Some(last) = fan_outs.last() {
+        stats.max_fan_out = *last;
+        stats.fan_out_99_percentile = *last;
+    }
+    if let Some(last) = inverse_fan_outs.last() {
+        stats.inverse_fan_out_99_percentile = *last;
+    }
+    if fan_outs.len() >= 100 {
+        stats.fan_out_99_percentile = fan_outs[fan_outs.len() / 100];
+    }
+    if inverse_fan_outs.len() >= 100 {
+        stats.inverse_fan_out_99_percentile = inverse_fan_outs[inverse_fan_outs.len() / 100];
+    }
+    let sum_fan_out: usize = fan_outs.iter().sum();
+    if sum_fan_out > 0 && stats.nodes > 0 {
+        stats.avg_fan_out = (sum_fan_out as f64) / (stats.nodes as f64);
+    }
+
+    // Follow the paths from the sampled nodes as long as there is time left
+    'sources: for source in sampled_sources {
+        let mut dfs = CycleSafeDFS::new(container, source, 0, usize::max_value());
+        while let Some(step) = dfs.next() {
+            stats.max_depth = std::cmp::max(stats.max_depth, step.distance);
+            if start.elapsed() > ESTIMATION_TIME_BUDGET {
+                break 'sources;
+            }
+        }
+        if dfs.is_cyclic() {
+            stats.cyclic = true;
+            stats.max_depth = 0;
+            stats.dfs_visit_ratio = 0.0;
+            break;
+        }
+    }
+
+    stats
+}
+
+/// Caches the estimated statistics of a graph storage that has no calculated statistics.
+///
+/// Graph storages have to [clear](#method.clear) the cache whenever their edges are changed.
+#[derive(Default)]
+pub struct EstimatedStatistics {
+    estimation: RwLock<Option<GraphStatistic>>,
+}
+
+impl Clone for EstimatedStatistics {
+    fn clone(&self) -> Self {
+        let estimation = self.estimation.read().map(|e| e.clone()).unwrap_or(None);
+        EstimatedStatistics {
+            estimation: RwLock::new(estimation),
+        }
+    }
+}
+
+impl EstimatedStatistics {
+    /// Get the cached estimation or estimate the statistics of the given container.
+    pub fn get_or_estimate(&self, container: &dyn EdgeContainer) -> GraphStatistic {
+        if let Ok(estimation) = self.estimation.read() {
+            if let Some(estimation) = estimation.as_ref() {
+                return estimation.clone();
+            }
+        }
+        let result = estimate_statistics(container);
+        if let Ok(mut estimation) = self.estimation.write() {
+            *estimation = Some(result.clone());
+        }
+        result
+    }
+
+    pub fn clear(&mut self) {
+        if let Ok(estimation) = self.estimation.get_mut() {
+            *estimation = None;
+        }
+    }
+}
+
 /// Basic trait for accessing edges of a graph for a specific component.
 pub trait EdgeContainer: Sync + Send + MallocSizeOf {
     /// Get all outgoing edges for a given `node`.
@@ -78,6 +218,14 @@ pub trait EdgeContainer: Sync + Send + MallocSizeOf {
         None
     }
 
+    /// Get the statistics of this edge container or, if they have not been calculated yet (e.g. because the
+    /// component has just been updated), a cheap estimation based on a sample of the edges.
+    ///
+    /// This should be used for query planning, where missing statistics would result in bad execution plans.
+    fn get_statistics_or_estimate(&self) -> Option<GraphStatistic> {
+        self.get_statistics().cloned()
+    }
+
     /// Provides an iterator over all nodes of this edge container that are the source of an edge
     fn source_nodes<'a>(&'a self) -> Box<dyn Iterator<Item = NodeID> + 'a>;
 }
@@ -113,6 +261,25 @@ pub trait GraphStorage: EdgeContainer {
         max_distance: std::ops::Bound<usize>,
     ) -> bool;
 
+    /// Check for each of the `targets` if it is connected with the `source` node in this component given a minimum
+    /// (`min_distance`) and maximum (`max_distance`) path length.
+    ///
+    /// The result has the same order as the `targets`.
+    /// Graph storages can override the default implementation, which calls [is_connected(...)](#tymethod.is_connected)
+    /// for each target, to only look up the source node once.
+    fn are_connected(
+        &self,
+        source: NodeID,
+        targets: &[NodeID],
+        min_distance: usize,
+        max_distance: std::ops::Bound<usize>,
+    ) -> Vec<bool> {
+        targets
+            .iter()
+            .map(|target| self.is_connected(source, *target, min_distance, max_distance))
+            .collect()
+    }
+
     /// Get the annotation storage for the edges of this graph storage.
     fn get_anno_storage(&self) -> &dyn AnnotationStorage<Edge>;
 
@@ -155,10 +322,7 @@ where
     GS: Serialize,
 {
     let data_path = location.join("component.bin");
-    let f_data = std::fs::File::create(&data_path)?;
-    let mut writer = std::io::BufWriter::new(f_data);
-    bincode::serialize_into(&mut writer, gs)?;
-    Ok(())
+    encryption::serialize_to_file(&data_path, gs)
 }
 
 pub fn default_deserialize_gs<GS>(location: &Path) -> Result<GS>
@@ -166,12 +330,7 @@ where
     for<'de> GS: std::marker::Sized + Deserialize<'de>,
 {
     let data_path = location.join("component.bin");
-    let f_data = std::fs::File::open(data_path)?;
-    let input = std::io::BufReader::new(f_data);
-
-    let result = bincode::deserialize_from(input)?;
-
-    Ok(result)
+    encryption::deserialize_from_file(&data_path)
 }
 
 /// Trait for accessing graph storages which can be written to.
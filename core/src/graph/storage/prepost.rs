@@ -78,6 +78,30 @@ where
         Ok(())
     }
 
+    fn is_connected_by_order(
+        order_source: &[PrePost<OrderT, LevelT>],
+        order_target: &[PrePost<OrderT, LevelT>],
+        min_distance: usize,
+        max_distance: usize,
+    ) -> bool {
+        for order_source in order_source.iter() {
+            for order_target in order_target.iter() {
+                if order_source.pre <= order_target.pre && order_target.post <= order_source.post {
+                    // check the level
+                    if let (Some(source_level), Some(target_level)) =
+                        (order_source.level.to_usize(), order_target.level.to_usize())
+                    {
+                        if source_level <= target_level {
+                            let diff_level = target_level - source_level;
+                            return min_distance <= diff_level && diff_level <= max_distance;
+                        }
+                    }
+                }
+            }
+        }
+        false
+    }
+
     fn enter_node(
         current_order: &mut OrderT,
         node_id: NodeID,
@@ -385,27 +409,43 @@ where
                 Included(max_distance) => max_distance,
                 Excluded(max_distance) => max_distance - 1,
             };
+            Self::is_connected_by_order(order_source, order_target, min_distance, max_distance)
+        } else {
+            false
+        }
+    }
 
-            for order_source in order_source.iter() {
-                for order_target in order_target.iter() {
-                    if order_source.pre <= order_target.pre
-                        && order_target.post <= order_source.post
-                    {
-                        // check the level
-                        if let (Some(source_level), Some(target_level)) =
-                            (order_source.level.to_usize(), order_target.level.to_usize())
-                        {
-                            if source_level <= target_level {
-                                let diff_level = target_level - source_level;
-                                return min_distance <= diff_level && diff_level <= max_distance;
-                            }
-                        }
+    fn are_connected(
+        &self,
+        source: NodeID,
+        targets: &[NodeID],
+        min_distance: usize,
+        max_distance: std::ops::Bound<usize>,
+    ) -> Vec<bool> {
+        if let Some(order_source) = self.node_to_order.get(&source) {
+            let max_distance = match max_distance {
+                Unbounded => usize::max_value(),
+                Included(max_distance) => max_distance,
+                Excluded(max_distance) => max_distance - 1,
+            };
+            targets
+                .iter()
+                .map(|target| {
+                    if let Some(order_target) = self.node_to_order.get(target) {
+                        Self::is_connected_by_order(
+                            order_source,
+                            order_target,
+                            min_distance,
+                            max_distance,
+                        )
+                    } else {
+                        false
                     }
-                }
-            }
+                })
+                .collect()
+        } else {
+            vec![false; targets.len()]
         }
-
-        false
     }
 
     fn copy(
@@ -540,3 +580,60 @@ where
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::storage::{adjacencylist::AdjacencyListStorage, WriteableGraphStorage};
+    use crate::types::Annotation;
+    use std::ops::Bound;
+
+    #[test]
+    fn are_connected_same_as_is_connected() {
+        let mut node_annos: AnnoStorageImpl<NodeID> = AnnoStorageImpl::new();
+        for n in 1..=6 {
+            node_annos
+                .insert(
+                    n,
+                    Annotation {
+                        key: NODE_NAME_KEY.as_ref().clone(),
+                        val: format!("n{}", n).into(),
+                    },
+                )
+                .unwrap();
+        }
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in [(1, 2), (1, 3), (2, 4), (2, 5)].iter() {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+
+        let mut gs = PrePostOrderStorage::<u16, u16>::new();
+        gs.copy(&node_annos, &orig).unwrap();
+
+        let targets: Vec<NodeID> = (1..=7).collect();
+        for source in 1..=7 {
+            for (min_distance, max_distance) in &[
+                (1, Bound::Included(1)),
+                (1, Bound::Unbounded),
+                (2, Bound::Excluded(3)),
+            ] {
+                let expected: Vec<bool> = targets
+                    .iter()
+                    .map(|t| gs.is_connected(source, *t, *min_distance, *max_distance))
+                    .collect();
+                assert_eq!(
+                    expected,
+                    gs.are_connected(source, &targets, *min_distance, *max_distance)
+                );
+            }
+        }
+        assert_eq!(
+            vec![false, true, true, true],
+            gs.are_connected(1, &[1, 2, 3, 4], 1, Bound::Unbounded)
+        );
+    }
+}
@@ -1,8 +1,10 @@
 use super::adjacencylist::AdjacencyListStorage;
 use super::dense_adjacency::DenseAdjacencyListStorage;
+use super::dense_ordering::DenseOrderingStorage;
 use super::disk_adjacency;
 use super::disk_adjacency::DiskAdjacencyListStorage;
 use super::linear::LinearGraphStorage;
+use super::symmetric::{self, SymmetricAdjacencyListStorage};
 use super::{prepost::PrePostOrderStorage, GraphStatistic, GraphStorage};
 use crate::{
     errors::{GraphAnnisCoreError, Result},
@@ -13,6 +15,7 @@ use serde::Deserialize;
 use std::collections::HashMap;
 use std::{path::Path, sync::Arc};
 
+#[derive(Clone)]
 pub struct GSInfo {
     pub id: String,
     constructor: fn() -> Result<Arc<dyn GraphStorage>>,
@@ -29,6 +32,8 @@ lazy_static! {
             create_info_diskadjacency(),
         );
         insert_info::<DenseAdjacencyListStorage>(&mut m);
+        insert_info::<DenseOrderingStorage>(&mut m);
+        insert_info::<SymmetricAdjacencyListStorage>(&mut m);
 
         insert_info::<PrePostOrderStorage<u64, u64>>(&mut m);
         insert_info::<PrePostOrderStorage<u64, u32>>(&mut m);
@@ -67,13 +72,20 @@ pub fn create_writeable<CT: ComponentType>(
 
 pub fn get_optimal_impl_heuristic<CT: ComponentType>(
     db: &Graph<CT>,
+    gs: &dyn GraphStorage,
     stats: &GraphStatistic,
 ) -> GSInfo {
-    if stats.max_depth <= 1 {
+    if stats.cyclic && !db.disk_based && symmetric::is_symmetric(gs) {
+        // each edge has an inverse edge, so we only need to store the neighbors once
+        return create_info::<SymmetricAdjacencyListStorage>();
+    } else if stats.max_depth <= 1 {
         // if we don't have any deep graph structures an adjencency list is always fasted (and has no overhead)
         return get_adjacencylist_impl(db, stats);
     } else if stats.rooted_tree {
         if stats.max_fan_out <= 1 {
+            if is_dense_ordering(db, stats) {
+                return create_info::<DenseOrderingStorage>();
+            }
             return get_linear_by_size(stats);
         } else {
             return get_prepostorder_by_size(stats);
@@ -90,7 +102,143 @@ pub fn get_optimal_impl_heuristic<CT: ComponentType>(
     get_adjacencylist_impl(db, stats)
 }
 
-fn get_adjacencylist_impl<CT: ComponentType>(db: &Graph<CT>, stats: &GraphStatistic) -> GSInfo {
+/// Get all implementations which are able to represent a component with the given statistics.
+///
+/// For implementations with different integer sizes, only the smallest sufficient one is returned.
+#[cfg(feature = "benchmark")]
+pub(crate) fn get_applicable_impls(
+    gs: &dyn GraphStorage,
+    stats: &GraphStatistic,
+    disk_based: bool,
+) -> Vec<GSInfo> {
+    let mut result = vec![
+        create_info::<AdjacencyListStorage>(),
+        create_info_diskadjacency(),
+    ];
+    if stats.max_fan_out <= 1 {
+        result.push(create_info::<DenseAdjacencyListStorage>());
+    }
+    if !stats.cyclic {
+        result.push(get_prepostorder_by_size(stats));
+    }
+    if stats.rooted_tree && stats.max_fan_out <= 1 {
+        result.push(get_linear_by_size(stats));
+        if !disk_based {
+            result.push(create_info::<DenseOrderingStorage>());
+        }
+    }
+    if stats.cyclic && symmetric::is_symmetric(gs) {
+        result.push(create_info::<SymmetricAdjacencyListStorage>());
+    }
+    result
+}
+
+/// Check if the graph storage implementation with the given name is able to represent a component with the
+/// given statistics.
+///
+/// Returns the reason why the implementation is not applicable as error.
+pub(crate) fn check_applicable(
+    info: &GSInfo,
+    gs: &dyn GraphStorage,
+    stats: &GraphStatistic,
+) -> std::result::Result<(), String> {
+    let is_impl = |other: GSInfo| other.id == info.id;
+
+    if is_impl(create_info::<DenseAdjacencyListStorage>()) && stats.max_fan_out > 1 {
+        return Err(format!(
+            "the maximal fan-out is {}, but only one outgoing edge per node is supported",
+            stats.max_fan_out
+        ));
+    }
+    if is_impl(create_info::<SymmetricAdjacencyListStorage>()) && !symmetric::is_symmetric(gs) {
+        return Err("the component has edges without an inverse edge".to_string());
+    }
+
+    let linear: [(GSInfo, u64); 5] = [
+        (create_info::<DenseOrderingStorage>(), u32::MAX as u64),
+        (create_info::<LinearGraphStorage<u8>>(), u8::MAX as u64),
+        (create_info::<LinearGraphStorage<u16>>(), u16::MAX as u64),
+        (create_info::<LinearGraphStorage<u32>>(), u32::MAX as u64),
+        (create_info::<LinearGraphStorage<u64>>(), u64::MAX),
+    ];
+    if let Some((_, max_pos)) = linear.iter().find(|(other, _)| other.id == info.id) {
+        if !stats.rooted_tree || stats.max_fan_out > 1 {
+            return Err("only components consisting of chains are supported".to_string());
+        }
+        if stats.max_depth as u64 >= *max_pos {
+            return Err(format!(
+                "the maximal depth of {} exceeds the supported position {}",
+                stats.max_depth, max_pos
+            ));
+        }
+    }
+
+    let prepost: [(GSInfo, u64, u64); 7] = [
+        (
+            create_info::<PrePostOrderStorage<u16, u8>>(),
+            u16::MAX as u64,
+            u8::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u16, u32>>(),
+            u16::MAX as u64,
+            u32::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u32, u8>>(),
+            u32::MAX as u64,
+            u8::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u32, u32>>(),
+            u32::MAX as u64,
+            u32::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u64, u8>>(),
+            u64::MAX,
+            u8::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u64, u32>>(),
+            u64::MAX,
+            u32::MAX as u64,
+        ),
+        (
+            create_info::<PrePostOrderStorage<u64, u64>>(),
+            u64::MAX,
+            u64::MAX,
+        ),
+    ];
+    if let Some((_, max_order, max_level)) =
+        prepost.iter().find(|(other, _, _)| other.id == info.id)
+    {
+        if stats.cyclic {
+            return Err("cyclic components are not supported".to_string());
+        }
+        // Each visit of a node in the depth first search needs a pre- and a post-order value
+        let order_values = 2.0 * stats.nodes as f64 * stats.dfs_visit_ratio.max(1.0);
+        if order_values >= *max_order as f64 {
+            return Err(format!(
+                "{} order values are needed, but at most {} are supported",
+                order_values.ceil(),
+                max_order
+            ));
+        }
+        if stats.max_depth as u64 >= *max_level {
+            return Err(format!(
+                "the maximal depth of {} exceeds the supported level {}",
+                stats.max_depth, max_level
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn get_adjacencylist_impl<CT: ComponentType>(
+    db: &Graph<CT>,
+    stats: &GraphStatistic,
+) -> GSInfo {
     if db.disk_based {
         create_info_diskadjacency()
     } else {
@@ -105,7 +253,20 @@ fn get_adjacencylist_impl<CT: ComponentType>(db: &Graph<CT>, stats: &GraphStatis
     }
 }
 
-fn get_prepostorder_by_size(stats: &GraphStatistic) -> GSInfo {
+/// True if the component consists of chains (like the token ordering) which contain most of the nodes of the graph,
+/// so that a position vector indexed by the node ID has little overhead.
+pub(crate) fn is_dense_ordering<CT: ComponentType>(db: &Graph<CT>, stats: &GraphStatistic) -> bool {
+    if db.disk_based || stats.max_depth >= u32::max_value() as usize {
+        return false;
+    }
+    if let Some(largest_node_id) = db.node_annos.get_largest_item() {
+        (stats.nodes as f64 / largest_node_id as f64) >= 0.5
+    } else {
+        false
+    }
+}
+
+pub(crate) fn get_prepostorder_by_size(stats: &GraphStatistic) -> GSInfo {
     if stats.rooted_tree {
         // There are exactly two order values per node and there can be only one order value per node
         // in a tree.
@@ -132,7 +293,7 @@ fn get_prepostorder_by_size(stats: &GraphStatistic) -> GSInfo {
     create_info::<PrePostOrderStorage<u64, u64>>()
 }
 
-fn get_linear_by_size(stats: &GraphStatistic) -> GSInfo {
+pub(crate) fn get_linear_by_size(stats: &GraphStatistic) -> GSInfo {
     if stats.max_depth < u8::max_value() as usize {
         create_info::<LinearGraphStorage<u8>>()
     } else if stats.max_depth < u16::max_value() as usize {
@@ -152,7 +313,7 @@ where
     registry.insert(info.id.clone(), info);
 }
 
-fn create_info<GS: 'static>() -> GSInfo
+pub(crate) fn create_info<GS: 'static>() -> GSInfo
 where
     for<'de> GS: GraphStorage + Default + Deserialize<'de>,
 {
@@ -166,7 +327,7 @@ where
     }
 }
 
-fn create_info_diskadjacency() -> GSInfo {
+pub(crate) fn create_info_diskadjacency() -> GSInfo {
     GSInfo {
         id: disk_adjacency::SERIALIZATION_ID.to_owned(),
         constructor: || Ok(Arc::from(DiskAdjacencyListStorage::new()?)),
@@ -177,6 +338,14 @@ fn create_info_diskadjacency() -> GSInfo {
     }
 }
 
+/// Get the information about the graph storage implementation with the given name.
+pub fn get_info(impl_name: &str) -> Result<GSInfo> {
+    REGISTRY
+        .get(impl_name)
+        .cloned()
+        .ok_or_else(|| GraphAnnisCoreError::UnknownGraphStorageImpl(impl_name.to_string()))
+}
+
 pub fn create_from_info(info: &GSInfo) -> Result<Arc<dyn GraphStorage>> {
     (info.constructor)()
 }
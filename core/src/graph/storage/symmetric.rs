@@ -0,0 +1,288 @@
+use crate::{
+    annostorage::{inmemory::AnnoStorageImpl, AnnotationStorage},
+    dfs::CycleSafeDFS,
+    errors::Result,
+    types::{Edge, NodeID},
+};
+
+use super::{EdgeContainer, GraphStatistic, GraphStorage};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+use std::{ops::Bound, path::Path};
+
+/// A read-only graph storage for symmetric relations, where for each edge from `a` to `b` there
+/// is also an edge from `b` to `a` (e.g. coreference or alignment).
+///
+/// Instead of storing the outgoing and ingoing edges separately, only the list of neighbors is
+/// stored for each node.
+#[derive(Serialize, Deserialize, Clone, MallocSizeOf)]
+pub struct SymmetricAdjacencyListStorage {
+    neighbors: FxHashMap<NodeID, Vec<NodeID>>,
+    annos: AnnoStorageImpl<Edge>,
+    stats: Option<GraphStatistic>,
+}
+
+impl Default for SymmetricAdjacencyListStorage {
+    fn default() -> Self {
+        SymmetricAdjacencyListStorage::new()
+    }
+}
+
+impl SymmetricAdjacencyListStorage {
+    pub fn new() -> SymmetricAdjacencyListStorage {
+        SymmetricAdjacencyListStorage {
+            neighbors: FxHashMap::default(),
+            annos: AnnoStorageImpl::new(),
+            stats: None,
+        }
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.neighbors.clear();
+        self.annos.clear()?;
+        self.stats = None;
+        Ok(())
+    }
+
+    fn add_neighbor(&mut self, node: NodeID, neighbor: NodeID) {
+        let entry = self.neighbors.entry(node).or_default();
+        if let Err(insertion_idx) = entry.binary_search(&neighbor) {
+            entry.insert(insertion_idx, neighbor);
+        }
+    }
+}
+
+/// Returns true if for each edge of the graph storage, the inverse edge also exists.
+pub fn is_symmetric(gs: &dyn GraphStorage) -> bool {
+    for source in gs.source_nodes() {
+        for target in gs.get_outgoing_edges(source) {
+            if !gs.get_outgoing_edges(target).any(|n| n == source) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+impl EdgeContainer for SymmetricAdjacencyListStorage {
+    fn get_outgoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        if let Some(neighbors) = self.neighbors.get(&node) {
+            return match neighbors.len() {
+                0 => Box::new(std::iter::empty()),
+                1 => Box::new(std::iter::once(neighbors[0])),
+                _ => Box::new(neighbors.iter().cloned()),
+            };
+        }
+        Box::new(std::iter::empty())
+    }
+
+    fn get_ingoing_edges<'a>(&'a self, node: NodeID) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        // Each outgoing edge has an ingoing edge in the other direction
+        self.get_outgoing_edges(node)
+    }
+
+    fn source_nodes<'a>(&'a self) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        let it = self
+            .neighbors
+            .iter()
+            .filter(|(_, neighbors)| !neighbors.is_empty())
+            .map(|(key, _)| *key);
+        Box::new(it)
+    }
+
+    fn get_statistics(&self) -> Option<&GraphStatistic> {
+        self.stats.as_ref()
+    }
+}
+
+impl GraphStorage for SymmetricAdjacencyListStorage {
+    fn get_anno_storage(&self) -> &dyn AnnotationStorage<Edge> {
+        &self.annos
+    }
+
+    fn serialization_id(&self) -> String {
+        "SymmetricAdjacencyListV1".to_owned()
+    }
+
+    fn load_from(location: &Path) -> Result<Self>
+    where
+        for<'de> Self: std::marker::Sized + Deserialize<'de>,
+    {
+        let mut result: Self = super::default_deserialize_gs(location)?;
+        result.annos.after_deserialization();
+        Ok(result)
+    }
+
+    fn save_to(&self, location: &Path) -> Result<()> {
+        super::default_serialize_gs(self, location)?;
+        Ok(())
+    }
+
+    fn find_connected<'a>(
+        &'a self,
+        node: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        let mut visited = FxHashSet::<NodeID>::default();
+        let max_distance = match max_distance {
+            Bound::Unbounded => usize::MAX,
+            Bound::Included(max_distance) => max_distance,
+            Bound::Excluded(max_distance) => max_distance + 1,
+        };
+        let it = CycleSafeDFS::<'a>::new(self, node, min_distance, max_distance)
+            .map(|x| x.node)
+            .filter(move |n| visited.insert(*n));
+        Box::new(it)
+    }
+
+    fn find_connected_inverse<'a>(
+        &'a self,
+        node: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Box<dyn Iterator<Item = NodeID> + 'a> {
+        // The inverse graph is the same as the original one
+        self.find_connected(node, min_distance, max_distance)
+    }
+
+    fn distance(&self, source: NodeID, target: NodeID) -> Option<usize> {
+        let mut it = CycleSafeDFS::new(self, source, usize::MIN, usize::MAX)
+            .filter(|x| target == x.node)
+            .map(|x| x.distance);
+
+        it.next()
+    }
+
+    fn is_connected(
+        &self,
+        source: NodeID,
+        target: NodeID,
+        min_distance: usize,
+        max_distance: std::ops::Bound<usize>,
+    ) -> bool {
+        let max_distance = match max_distance {
+            Bound::Unbounded => usize::MAX,
+            Bound::Included(max_distance) => max_distance,
+            Bound::Excluded(max_distance) => max_distance + 1,
+        };
+        let mut it = CycleSafeDFS::new(self, source, min_distance, max_distance)
+            .filter(|x| target == x.node);
+
+        it.next().is_some()
+    }
+
+    fn copy(
+        &mut self,
+        _node_annos: &dyn AnnotationStorage<NodeID>,
+        orig: &dyn GraphStorage,
+    ) -> Result<()> {
+        self.clear()?;
+
+        for source in orig.source_nodes() {
+            for target in orig.get_outgoing_edges(source) {
+                // self-loops are not supported by the other graph storages either, so neither the edge
+                // nor its annotations are copied
+                if source == target {
+                    continue;
+                }
+                self.add_neighbor(source, target);
+                self.add_neighbor(target, source);
+                let e = Edge { source, target };
+                for a in orig.get_anno_storage().get_annotations_for_item(&e) {
+                    self.annos.insert(e.clone(), a)?;
+                }
+            }
+        }
+
+        self.stats = orig.get_statistics().cloned();
+        self.annos.calculate_statistics();
+        Ok(())
+    }
+
+    fn as_edgecontainer(&self) -> &dyn EdgeContainer {
+        self
+    }
+
+    fn inverse_has_same_cost(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::graph::storage::{adjacencylist::AdjacencyListStorage, WriteableGraphStorage};
+    use itertools::Itertools;
+
+    #[test]
+    fn copy_symmetric_graph() {
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in &[(1, 2), (2, 1), (2, 3), (3, 2), (4, 5), (5, 4)] {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+        orig.calculate_statistics();
+        assert!(is_symmetric(&orig));
+
+        let node_annos = crate::annostorage::inmemory::AnnoStorageImpl::<NodeID>::new();
+        let mut gs = SymmetricAdjacencyListStorage::new();
+        gs.copy(&node_annos, &orig).unwrap();
+
+        assert_eq!(
+            vec![1, 3],
+            gs.get_outgoing_edges(2).sorted().collect::<Vec<NodeID>>()
+        );
+        assert_eq!(
+            vec![1, 3],
+            gs.get_ingoing_edges(2).sorted().collect::<Vec<NodeID>>()
+        );
+        assert_eq!(
+            vec![2, 3],
+            gs.find_connected(1, 1, Bound::Unbounded)
+                .sorted()
+                .collect::<Vec<NodeID>>()
+        );
+        assert_eq!(Some(2), gs.distance(1, 3));
+        assert!(gs.is_connected(3, 1, 1, Bound::Included(2)));
+        assert!(!gs.is_connected(1, 4, 1, Bound::Unbounded));
+        assert_eq!(5, gs.source_nodes().count());
+    }
+
+    #[test]
+    fn heuristic_chooses_symmetric_storage() {
+        // a single symmetric edge has a depth of 1, which must not prevent the symmetric storage
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in &[(1, 2), (2, 1)] {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+        orig.calculate_statistics();
+        let stats = orig.get_statistics().unwrap();
+        assert!(stats.cyclic);
+
+        let db = crate::graph::Graph::<crate::types::DefaultComponentType>::new(false).unwrap();
+        let info = crate::graph::storage::registry::get_optimal_impl_heuristic(&db, &orig, stats);
+        assert_eq!("SymmetricAdjacencyListV1", info.id);
+    }
+
+    #[test]
+    fn detect_asymmetric_graph() {
+        let mut orig = AdjacencyListStorage::new();
+        for (source, target) in &[(1, 2), (2, 1), (2, 3)] {
+            orig.add_edge(Edge {
+                source: *source,
+                target: *target,
+            })
+            .unwrap();
+        }
+        assert!(!is_symmetric(&orig));
+    }
+}
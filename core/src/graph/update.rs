@@ -1,10 +1,23 @@
 //! Types used to describe updates on graphs.
 
-use crate::{errors::Result, util::disk_collections::DiskMap};
+use crate::{
+    errors::Result,
+    util::{
+        disk_collections::{DiskMap, EvictionStrategy},
+        UnicodeNormalization,
+    },
+};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
 use serde::de::Error as DeserializeError;
 use serde::de::{MapAccess, Visitor};
 use serde::ser::Error as SerializeError;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufReader, Read, Write};
+use std::time::Duration;
 
 /// Describes a single update on the graph.
 #[derive(Serialize, Deserialize, Clone, Debug, MallocSizeOf)]
@@ -66,29 +79,68 @@ pub enum UpdateEvent {
         anno_ns: String,
         anno_name: String,
     },
+    /// Add an additional value for a label of the node given by the name.
+    /// In contrast to `AddNodeLabel`, the existing values of the node for the same label are kept.
+    AddNodeLabelValue {
+        node_name: String,
+        anno_ns: String,
+        anno_name: String,
+        anno_value: String,
+    },
 }
 
 /// A list of changes to apply to an graph.
-#[derive(Default)]
+///
+/// The events are held in memory until their estimated size exceeds a limit and are then spilled to temporary
+/// files on disk, so even corpus-scale updates can be built without exhausting the main memory.
+/// Events spilled while an [encryption key](crate::util::encryption::with_key) is active are written to encrypted
+/// temporary files.
 #[repr(C)]
 pub struct GraphUpdate {
     diffs: DiskMap<u64, UpdateEvent>,
     event_counter: u64,
+    serialized_size: u64,
 }
 
+impl Default for GraphUpdate {
+    fn default() -> Self {
+        GraphUpdate::new()
+    }
+}
+
+/// Size of the length prefix of the serialized event list.
+const SERIALIZED_MAP_HEADER_SIZE: u64 = 8;
+
 impl GraphUpdate {
     /// Create a new empty list of updates.
     pub fn new() -> GraphUpdate {
         GraphUpdate {
             diffs: DiskMap::default(),
             event_counter: 0,
+            serialized_size: SERIALIZED_MAP_HEADER_SIZE,
         }
     }
 
+    /// Create a new empty list of updates, that holds at most `max_bytes` of events in memory before spilling
+    /// them to disk. If `max_bytes` is `None`, all events are kept in memory.
+    pub fn with_memory_limit(max_bytes: Option<usize>) -> Result<GraphUpdate> {
+        let eviction_strategy = EvictionStrategy::MaximumBytes(max_bytes.unwrap_or(usize::MAX));
+        Ok(GraphUpdate {
+            diffs: DiskMap::new(None, eviction_strategy)?,
+            event_counter: 0,
+            serialized_size: SERIALIZED_MAP_HEADER_SIZE,
+        })
+    }
+
     /// Add the given event to the update list.
     pub fn add_event(&mut self, event: UpdateEvent) -> Result<()> {
         self.event_counter += 1;
-        self.diffs.insert(self.event_counter, event)?;
+        self.insert_event(self.event_counter, event)
+    }
+
+    fn insert_event(&mut self, id: u64, event: UpdateEvent) -> Result<()> {
+        self.serialized_size += bincode::serialized_size(&id)? + bincode::serialized_size(&event)?;
+        self.diffs.insert(id, event)?;
         Ok(())
     }
 
@@ -102,6 +154,221 @@ impl GraphUpdate {
     pub fn is_empty(&self) -> Result<bool> {
         self.diffs.try_is_empty()
     }
+
+    /// Returns the number of bytes needed to serialize this update list without compression.
+    ///
+    /// The size is tracked when adding events and does not require to iterate over the (possibly spilled)
+    /// events. The size of the compressed representation is usually much smaller.
+    pub fn serialized_size_estimate(&self) -> u64 {
+        self.serialized_size
+    }
+
+    /// Returns the estimated number of bytes of the events that are currently held in memory.
+    ///
+    /// Events that have been spilled to disk are not included.
+    pub fn memory_size_estimate(&self) -> usize {
+        self.diffs.estimated_memory_size()
+    }
+
+    /// Serialize this update list in a compressed form to the `writer`.
+    ///
+    /// The events are compressed while they are serialized, so the uncompressed representation
+    /// is never held in memory as a whole.
+    pub fn serialize_compressed<W: Write>(&self, writer: W) -> Result<()> {
+        let mut encoder = ZlibEncoder::new(writer, Compression::fast());
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Deserialize an update list that has been written by [`GraphUpdate::serialize_compressed`].
+    pub fn deserialize_compressed<R: Read>(reader: R) -> Result<GraphUpdate> {
+        let decoder = ZlibDecoder::new(reader);
+        let result = bincode::deserialize_from(BufReader::new(decoder))?;
+        Ok(result)
+    }
+
+    /// Create a copy of this update list, where all node names and annotation values are
+    /// normalized to the given Unicode normal form.
+    pub fn normalize_unicode(&self, normalization: UnicodeNormalization) -> Result<GraphUpdate> {
+        let mut result = GraphUpdate::new();
+        for (_, event) in self.iter()? {
+            result.add_event(event.normalize_unicode(normalization))?;
+        }
+        Ok(result)
+    }
+}
+
+impl UpdateEvent {
+    /// Get the name of the type of this event, e.g. `AddNode`.
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            UpdateEvent::AddNode { .. } => "AddNode",
+            UpdateEvent::DeleteNode { .. } => "DeleteNode",
+            UpdateEvent::AddNodeLabel { .. } => "AddNodeLabel",
+            UpdateEvent::DeleteNodeLabel { .. } => "DeleteNodeLabel",
+            UpdateEvent::AddEdge { .. } => "AddEdge",
+            UpdateEvent::DeleteEdge { .. } => "DeleteEdge",
+            UpdateEvent::AddEdgeLabel { .. } => "AddEdgeLabel",
+            UpdateEvent::DeleteEdgeLabel { .. } => "DeleteEdgeLabel",
+            UpdateEvent::AddNodeLabelValue { .. } => "AddNodeLabelValue",
+        }
+    }
+
+    /// Normalize all node names and annotation values of this event to the given Unicode normal form.
+    pub fn normalize_unicode(self, normalization: UnicodeNormalization) -> UpdateEvent {
+        let n = |v: String| match normalization.normalize(&v) {
+            std::borrow::Cow::Borrowed(_) => v,
+            std::borrow::Cow::Owned(normalized) => normalized,
+        };
+        match self {
+            UpdateEvent::AddNode {
+                node_name,
+                node_type,
+            } => UpdateEvent::AddNode {
+                node_name: n(node_name),
+                node_type: n(node_type),
+            },
+            UpdateEvent::DeleteNode { node_name } => UpdateEvent::DeleteNode {
+                node_name: n(node_name),
+            },
+            UpdateEvent::AddNodeLabel {
+                node_name,
+                anno_ns,
+                anno_name,
+                anno_value,
+            } => UpdateEvent::AddNodeLabel {
+                node_name: n(node_name),
+                anno_ns,
+                anno_name,
+                anno_value: n(anno_value),
+            },
+            UpdateEvent::AddNodeLabelValue {
+                node_name,
+                anno_ns,
+                anno_name,
+                anno_value,
+            } => UpdateEvent::AddNodeLabelValue {
+                node_name: n(node_name),
+                anno_ns,
+                anno_name,
+                anno_value: n(anno_value),
+            },
+            UpdateEvent::DeleteNodeLabel {
+                node_name,
+                anno_ns,
+                anno_name,
+            } => UpdateEvent::DeleteNodeLabel {
+                node_name: n(node_name),
+                anno_ns,
+                anno_name,
+            },
+            UpdateEvent::AddEdge {
+                source_node,
+                target_node,
+                layer,
+                component_type,
+                component_name,
+            } => UpdateEvent::AddEdge {
+                source_node: n(source_node),
+                target_node: n(target_node),
+                layer,
+                component_type,
+                component_name,
+            },
+            UpdateEvent::DeleteEdge {
+                source_node,
+                target_node,
+                layer,
+                component_type,
+                component_name,
+            } => UpdateEvent::DeleteEdge {
+                source_node: n(source_node),
+                target_node: n(target_node),
+                layer,
+                component_type,
+                component_name,
+            },
+            UpdateEvent::AddEdgeLabel {
+                source_node,
+                target_node,
+                layer,
+                component_type,
+                component_name,
+                anno_ns,
+                anno_name,
+                anno_value,
+            } => UpdateEvent::AddEdgeLabel {
+                source_node: n(source_node),
+                target_node: n(target_node),
+                layer,
+                component_type,
+                component_name,
+                anno_ns,
+                anno_name,
+                anno_value: n(anno_value),
+            },
+            UpdateEvent::DeleteEdgeLabel {
+                source_node,
+                target_node,
+                layer,
+                component_type,
+                component_name,
+                anno_ns,
+                anno_name,
+            } => UpdateEvent::DeleteEdgeLabel {
+                source_node: n(source_node),
+                target_node: n(target_node),
+                layer,
+                component_type,
+                component_name,
+                anno_ns,
+                anno_name,
+            },
+        }
+    }
+}
+
+/// Performance counters collected while applying a [`GraphUpdate`] to a graph.
+#[derive(Clone, Debug, Default)]
+pub struct UpdateStatistics {
+    /// Number of applied events for each type of event (see [`UpdateEvent::event_type`]).
+    pub events_per_type: BTreeMap<&'static str, usize>,
+    /// Number of nodes for which the model-specific index (e.g. the inherited coverage) has been recalculated.
+    pub reindexed_nodes: usize,
+    /// Time needed to apply the events.
+    pub events_duration: Duration,
+    /// Time needed to recalculate the model-specific index.
+    pub index_duration: Duration,
+    /// Time needed to persist the update log on disk.
+    pub persist_duration: Duration,
+}
+
+impl UpdateStatistics {
+    /// Total number of applied events.
+    pub fn number_of_events(&self) -> usize {
+        self.events_per_type.values().sum()
+    }
+}
+
+impl fmt::Display for UpdateStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let events: Vec<String> = self
+            .events_per_type
+            .iter()
+            .map(|(event_type, count)| format!("{}: {}", event_type, count))
+            .collect();
+        write!(
+            f,
+            "applied {} events ({}) in {} ms, re-indexed {} nodes in {} ms, persisted in {} ms",
+            self.number_of_events(),
+            events.join(", "),
+            self.events_duration.as_millis(),
+            self.reindexed_nodes,
+            self.index_duration.as_millis(),
+            self.persist_duration.as_millis()
+        )
+    }
 }
 
 pub struct GraphUpdateIterator<'a> {
@@ -153,10 +420,10 @@ impl<'de> Visitor<'de> for GraphUpdateVisitor {
     where
         M: MapAccess<'de>,
     {
-        let mut g = GraphUpdate::default();
+        let mut g = GraphUpdate::new();
 
         while let Some((key, value)) = access.next_entry().map_err(M::Error::custom)? {
-            g.diffs.insert(key, value).map_err(M::Error::custom)?;
+            g.insert_event(key, value).map_err(M::Error::custom)?;
             g.event_counter = key;
         }
 
@@ -172,3 +439,81 @@ impl<'de> Deserialize<'de> for GraphUpdate {
         deserializer.deserialize_map(GraphUpdateVisitor {})
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example_update(update: &mut GraphUpdate, number_of_nodes: usize) {
+        for i in 0..number_of_nodes {
+            update
+                .add_event(UpdateEvent::AddNode {
+                    node_name: format!("root/doc1#n{}", i),
+                    node_type: "node".to_string(),
+                })
+                .unwrap();
+            update
+                .add_event(UpdateEvent::AddNodeLabel {
+                    node_name: format!("root/doc1#n{}", i),
+                    anno_ns: "annis".to_string(),
+                    anno_name: "tok".to_string(),
+                    anno_value: "example".to_string(),
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn serialized_size_estimate() {
+        let mut u = GraphUpdate::new();
+        assert_eq!(
+            bincode::serialized_size(&u).unwrap(),
+            u.serialized_size_estimate()
+        );
+        example_update(&mut u, 100);
+        assert_eq!(
+            bincode::serialized_size(&u).unwrap(),
+            u.serialized_size_estimate()
+        );
+
+        let deserialized: GraphUpdate =
+            bincode::deserialize(&bincode::serialize(&u).unwrap()).unwrap();
+        assert_eq!(
+            u.serialized_size_estimate(),
+            deserialized.serialized_size_estimate()
+        );
+    }
+
+    #[test]
+    fn compressed_roundtrip() {
+        let mut u = GraphUpdate::new();
+        example_update(&mut u, 1000);
+
+        let mut compressed = Vec::new();
+        u.serialize_compressed(&mut compressed).unwrap();
+        assert!((compressed.len() as u64) < u.serialized_size_estimate());
+
+        let deserialized = GraphUpdate::deserialize_compressed(&compressed[..]).unwrap();
+        let original: Vec<_> = u.iter().unwrap().map(|(id, _)| id).collect();
+        let events: Vec<_> = deserialized.iter().unwrap().collect();
+        assert_eq!(original.len(), events.len());
+        assert_eq!(
+            original,
+            events.iter().map(|(id, _)| *id).collect::<Vec<_>>()
+        );
+        assert_eq!("AddNodeLabel", events[1].1.event_type());
+    }
+
+    #[test]
+    fn spill_events_to_disk() {
+        let mut u = GraphUpdate::with_memory_limit(Some(1024)).unwrap();
+        example_update(&mut u, 1000);
+        assert!(u.memory_size_estimate() <= 1024);
+        assert_eq!(2000, u.iter().unwrap().count());
+
+        let mut in_memory = GraphUpdate::with_memory_limit(None).unwrap();
+        example_update(&mut in_memory, 1000);
+        assert!(in_memory.memory_size_estimate() > 1024);
+        assert_eq!(2000, in_memory.iter().unwrap().count());
+    }
+}
@@ -0,0 +1,279 @@
+//! Segmented write-ahead log for the updates of a graph that have not been persisted yet.
+//!
+//! Each applied update is appended as a new segment file to the `update_log` directory of the graph location.
+//! The segment files are named by their sequence number and contain a header with the change IDs before and
+//! after the update and a checksum of the serialized update. The update is stored in a compressed form. If the
+//! graph has an encryption key, the compressed update is encrypted.
+
+use super::update::GraphUpdate;
+use crate::errors::{GraphAnnisCoreError, Result};
+use crate::util::encryption::EncryptionKey;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory of the graph location that contains the segments of the update log.
+pub(crate) const UPDATE_LOG_DIR: &str = "update_log";
+const SEGMENT_EXTENSION: &str = "wal";
+const CORRUPTED_EXTENSION: &str = "corrupted";
+const SEGMENT_MAGIC: &[u8; 8] = b"GANNWAL2";
+/// Segments with this magic number contain a compressed and encrypted update.
+const ENCRYPTED_SEGMENT_MAGIC: &[u8; 8] = b"GANNWAL3";
+const HEADER_SIZE: u64 = 8 + 8 + 8 + 8 + 4;
+
+/// The header of a segment file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SegmentHeader {
+    /// Change ID of the graph the update has been applied to.
+    pub base_change_id: u64,
+    /// Change ID of the graph after the update has been applied.
+    pub end_change_id: u64,
+    payload_size: u64,
+    checksum: u32,
+    encrypted: bool,
+}
+
+/// A segment file of the update log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Segment {
+    pub sequence: u64,
+    pub path: PathBuf,
+}
+
+/// Counts the written bytes and calculates their checksum.
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    hasher: crc32fast::Hasher,
+    written: u64,
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[0..written]);
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn write_header(f: &mut File, header: &SegmentHeader) -> std::io::Result<()> {
+    if header.encrypted {
+        f.write_all(ENCRYPTED_SEGMENT_MAGIC)?;
+    } else {
+        f.write_all(SEGMENT_MAGIC)?;
+    }
+    f.write_all(&header.base_change_id.to_le_bytes())?;
+    f.write_all(&header.end_change_id.to_le_bytes())?;
+    f.write_all(&header.payload_size.to_le_bytes())?;
+    f.write_all(&header.checksum.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u64<R: Read>(r: &mut R) -> std::io::Result<u64> {
+    let mut buffer = [0; 8];
+    r.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+/// The path of the segment with the given `sequence` number relative to the location of the graph.
+fn segment_name(sequence: u64) -> String {
+    format!("{}/{:020}.{}", UPDATE_LOG_DIR, sequence, SEGMENT_EXTENSION)
+}
+
+/// Return all segments of the update log at the `location`, sorted by their sequence number.
+pub(crate) fn list_segments(location: &Path) -> Result<Vec<Segment>> {
+    let log_dir = location.join(UPDATE_LOG_DIR);
+    let mut result = Vec::new();
+    if log_dir.is_dir() {
+        for entry in log_dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(SEGMENT_EXTENSION) {
+                continue;
+            }
+            let sequence = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<u64>().ok());
+            if let Some(sequence) = sequence {
+                result.push(Segment { sequence, path });
+            }
+        }
+    }
+    result.sort_by_key(|s| s.sequence);
+    Ok(result)
+}
+
+/// Return the sequence number for the next segment, which is larger than the one of all existing
+/// (including corrupted) segments.
+fn next_sequence(log_dir: &Path) -> Result<u64> {
+    let mut next = 1;
+    for entry in log_dir.read_dir()? {
+        let path = entry?.path();
+        let sequence = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.split('.').next())
+            .and_then(|s| s.parse::<u64>().ok());
+        if let Some(sequence) = sequence {
+            next = next.max(sequence + 1);
+        }
+    }
+    Ok(next)
+}
+
+/// Append the `update`, which changed the graph from the `base_change_id` to the `end_change_id`, as a new
+/// segment to the update log at the `location`.
+///
+/// The segment is written to a temporary file first and then moved to its final name, so a segment file is
+/// either complete or does not exist. The update is encrypted with the `key`, if one is given.
+pub(crate) fn append_segment(
+    location: &Path,
+    base_change_id: u64,
+    end_change_id: u64,
+    update: &GraphUpdate,
+    key: Option<&EncryptionKey>,
+) -> Result<Segment> {
+    let log_dir = location.join(UPDATE_LOG_DIR);
+    std::fs::create_dir_all(&log_dir)?;
+    let sequence = next_sequence(&log_dir)?;
+
+    let mut tmp_file = tempfile::NamedTempFile::new_in(&log_dir)?;
+    let mut header = SegmentHeader {
+        base_change_id,
+        end_change_id,
+        payload_size: 0,
+        checksum: 0,
+        encrypted: key.is_some(),
+    };
+    // Write a placeholder for the header, which is replaced when the size and checksum are known
+    write_header(tmp_file.as_file_mut(), &header)?;
+    {
+        let mut writer = ChecksumWriter {
+            inner: BufWriter::new(tmp_file.as_file_mut()),
+            hasher: crc32fast::Hasher::new(),
+            written: 0,
+        };
+        if let Some(key) = key {
+            // the update can only be encrypted as a whole
+            let mut compressed = Vec::new();
+            update.serialize_compressed(&mut compressed)?;
+            writer.write_all(&key.encrypt(&compressed, &segment_name(sequence))?)?;
+        } else {
+            update.serialize_compressed(&mut writer)?;
+        }
+        writer.flush()?;
+        header.payload_size = writer.written;
+        header.checksum = writer.hasher.finalize();
+    }
+    tmp_file.as_file_mut().seek(SeekFrom::Start(0))?;
+    write_header(tmp_file.as_file_mut(), &header)?;
+    tmp_file.as_file().sync_all()?;
+
+    let path = location.join(segment_name(sequence));
+    debug!("writing update log segment {:?}", &path);
+    tmp_file.persist(&path)?;
+    Ok(Segment { sequence, path })
+}
+
+/// Read the header of the segment or return `None` if it is not a valid segment file.
+pub(crate) fn read_header(segment: &Segment) -> Result<Option<SegmentHeader>> {
+    let mut f = File::open(&segment.path)?;
+    let file_size = f.metadata()?.len();
+    let mut magic = [0; 8];
+    if file_size < HEADER_SIZE || f.read_exact(&mut magic).is_err() {
+        return Ok(None);
+    }
+    let encrypted = if &magic == SEGMENT_MAGIC {
+        false
+    } else if &magic == ENCRYPTED_SEGMENT_MAGIC {
+        true
+    } else {
+        return Ok(None);
+    };
+    let base_change_id = read_u64(&mut f)?;
+    let end_change_id = read_u64(&mut f)?;
+    let payload_size = read_u64(&mut f)?;
+    let mut checksum = [0; 4];
+    f.read_exact(&mut checksum)?;
+    if file_size != HEADER_SIZE + payload_size {
+        return Ok(None);
+    }
+    Ok(Some(SegmentHeader {
+        base_change_id,
+        end_change_id,
+        payload_size,
+        checksum: u32::from_le_bytes(checksum),
+        encrypted,
+    }))
+}
+
+/// Read the update of the segment or return `None` if the update does not match the checksum of the header.
+///
+/// Encrypted segments are decrypted with the `key`.
+pub(crate) fn read_update(
+    segment: &Segment,
+    header: &SegmentHeader,
+    key: Option<&EncryptionKey>,
+) -> Result<Option<GraphUpdate>> {
+    let mut f = File::open(&segment.path)?;
+
+    // check the checksum before deserializing the update
+    f.seek(SeekFrom::Start(HEADER_SIZE))?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let bytes_read = f.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[0..bytes_read]);
+    }
+    if hasher.finalize() != header.checksum {
+        return Ok(None);
+    }
+
+    if !header.encrypted && key.map(|key| !key.allows_unencrypted()).unwrap_or(false) {
+        return Err(GraphAnnisCoreError::UnencryptedFile(
+            segment.path.to_string_lossy().to_string(),
+        ));
+    }
+
+    f.seek(SeekFrom::Start(HEADER_SIZE))?;
+    let update = if header.encrypted {
+        let key = key.ok_or_else(|| {
+            GraphAnnisCoreError::EncryptionKeyMissing(segment.path.to_string_lossy().to_string())
+        })?;
+        let mut encrypted = Vec::new();
+        f.read_to_end(&mut encrypted)?;
+        let compressed = key.decrypt(&encrypted, &segment_name(segment.sequence), &segment.path)?;
+        GraphUpdate::deserialize_compressed(&compressed[..])?
+    } else {
+        GraphUpdate::deserialize_compressed(f)?
+    };
+    Ok(Some(update))
+}
+
+/// Rename a corrupted segment, so it is not replayed anymore but kept for inspection.
+pub(crate) fn mark_corrupted(segment: &Segment) -> Result<()> {
+    std::fs::rename(
+        &segment.path,
+        segment.path.with_extension(CORRUPTED_EXTENSION),
+    )?;
+    Ok(())
+}
+
+/// Remove all segments whose changes are part of the state with the given `change_id`.
+pub(crate) fn remove_persisted_segments(location: &Path, change_id: u64) -> Result<()> {
+    for segment in list_segments(location)? {
+        if let Some(header) = read_header(&segment)? {
+            if header.end_change_id <= change_id {
+                std::fs::remove_file(&segment.path)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -133,6 +133,12 @@ pub trait ComponentType:
     ) -> StdResult<(), ComponentTypeError> {
         Ok(())
     }
+    /// Returns the number of nodes for which the model-specific index needs to be recalculated when
+    /// `apply_update_graph_index` is called.
+    fn number_of_reindexed_nodes(_index: &Self::UpdateGraphIndex) -> usize {
+        0
+    }
+
     fn apply_update_graph_index(
         _index: Self::UpdateGraphIndex,
         _graph: &mut Graph<Self>,
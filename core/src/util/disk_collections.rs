@@ -1,11 +1,14 @@
+use super::encryption::{self, TemporaryFileKey};
 use super::memory_estimation;
 use bincode::config::Options;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use serde::{Deserialize, Serialize};
-use sstable::{SSIterator, Table, TableBuilder, TableIterator};
+use sstable::{RandomAccess, SSIterator, Table, TableBuilder, TableIterator};
 
 use crate::{errors::Result, serializer::KeySerializer};
 use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
 use std::iter::Peekable;
 use std::ops::{Bound, RangeBounds};
 use std::path::{Path, PathBuf};
@@ -13,6 +16,16 @@ use std::path::{Path, PathBuf};
 const DEFAULT_MSG : &str = "Accessing the disk-database failed. This is a non-recoverable error since it means something serious is wrong with the disk or file system.";
 const MAX_TRIES: usize = 5;
 const MAX_NUMBER_OF_TABLES: usize = 128;
+/// Number of deleted entries in the disk tables after which the tables are compacted on the next eviction to get rid
+/// of the tombstone entries.
+const MAX_NUMBER_OF_TOMBSTONES: usize = 100_000;
+
+/// Number of bits per key used by the bloom filters for the disk tables.
+/// 10 bits per key result in a false positive rate of approximately 1%.
+const KEY_FILTER_BITS_PER_KEY: usize = 10;
+const KEY_FILTER_NUMBER_OF_HASHES: u64 = 7;
+const KEY_FILTER_INITIAL_CAPACITY: usize = 1024;
+const KEY_FILTER_FILE_EXTENSION: &str = "bloom";
 
 #[derive(Serialize, Deserialize)]
 struct Entry<K, V>
@@ -42,6 +55,12 @@ where
     eviction_strategy: EvictionStrategy,
     c0: BTreeMap<Vec<u8>, Option<V>>,
     disk_tables: Vec<Table>,
+    /// A bloom filter for all keys (including the deleted ones) of the disk tables.
+    /// If this is `None`, it is unknown which keys are contained in the disk tables and they need to be queried.
+    key_filter: Option<KeyFilter>,
+    /// Number of tombstone entries that have been written to the disk tables.
+    /// Tables loaded from a persisted file never contain tombstones, because they are omitted by `write_to`.
+    tombstones_in_disk_tables: usize,
 
     /// Marks if all items have been inserted in sorted order and if there has not been any delete operation yet.
     insertion_was_sorted: bool,
@@ -67,12 +86,16 @@ where
         eviction_strategy: EvictionStrategy,
     ) -> Result<DiskMap<K, V>> {
         let mut disk_tables = Vec::default();
+        let mut key_filter = Some(KeyFilter::default());
 
         if let Some(persisted_file) = persisted_file {
             if persisted_file.is_file() {
                 // Use existing file as read-only table which contains the whole map
                 let table = Table::new_from_file(sstable::Options::default(), persisted_file)?;
                 disk_tables.push(table);
+                // Older versions did not write a key filter next to the table, in this case
+                // we have to query the disk table for each key
+                key_filter = KeyFilter::load_from(persisted_file)?;
             }
         }
 
@@ -80,6 +103,8 @@ where
             eviction_strategy,
             c0: BTreeMap::default(),
             disk_tables,
+            key_filter,
+            tombstones_in_disk_tables: 0,
             insertion_was_sorted: true,
             unchanged_from_disk: persisted_file.is_some(),
             last_inserted_key: None,
@@ -134,53 +159,41 @@ where
         match self.eviction_strategy {
             EvictionStrategy::MaximumItems(n) => {
                 if self.c0.len() > n {
-                    self.evict_c0(write_deleted, None)?;
+                    self.evict_c0(write_deleted)?;
                 }
             }
             EvictionStrategy::MaximumBytes(b) => {
                 if self.est_sum_memory > b {
-                    self.evict_c0(write_deleted, None)?;
+                    self.evict_c0(write_deleted)?;
                 }
             }
         }
         Ok(())
     }
 
-    fn evict_c0(&mut self, write_deleted: bool, output_file: Option<&PathBuf>) -> Result<()> {
-        let out_file = if let Some(output_file) = output_file {
-            debug!("Evicting DiskMap C0 to {:?}", output_file.as_path());
-            if let Some(parent) = output_file.parent() {
-                std::fs::create_dir_all(parent)?
-            }
-            std::fs::OpenOptions::new()
-                .write(true)
-                .read(true)
-                .create(true)
-                .open(output_file)?
-        } else {
-            debug!("Evicting DiskMap C0 to temporary file");
-            tempfile::tempfile()?
-        };
-
-        {
-            let mut builder = TableBuilder::new(sstable::Options::default(), &out_file);
+    fn evict_c0(&mut self, write_deleted: bool) -> Result<()> {
+        debug!("Evicting DiskMap C0 to temporary file");
+        let mut out_file = TemporaryTableFile::new()?;
+        let size = {
+            let mut builder = TableBuilder::new(sstable::Options::default(), &mut out_file);
 
             for (key, value) in self.c0.iter() {
                 let key = key.create_key();
                 if write_deleted || value.is_some() {
                     builder.add(&key, &self.serialization.serialize(value)?)?;
+                    if let Some(key_filter) = &mut self.key_filter {
+                        key_filter.insert(&key);
+                    }
+                    if value.is_none() {
+                        self.tombstones_in_disk_tables += 1;
+                    }
                 }
             }
-            builder.finish()?;
-        }
+            builder.finish()?
+        };
 
         self.est_sum_memory = 0;
-        let size = out_file.metadata()?.len();
-        let table = Table::new(
-            sstable::Options::default(),
-            Box::new(out_file),
-            size as usize,
-        )?;
+        let table = Table::new(sstable::Options::default(), Box::new(out_file), size)?;
         self.disk_tables.push(table);
 
         self.c0.clear();
@@ -188,6 +201,9 @@ where
         if self.disk_tables.len() > MAX_NUMBER_OF_TABLES {
             debug!("Compacting disk tables after eviction");
             self.compact()?;
+        } else if self.tombstones_in_disk_tables > MAX_NUMBER_OF_TOMBSTONES {
+            debug!("Compacting disk tables after eviction because of too many deleted entries");
+            self.compact()?;
         }
 
         debug!("Finished evicting DiskMap C0 ");
@@ -225,6 +241,8 @@ where
     pub fn clear(&mut self) {
         self.c0.clear();
         self.disk_tables.clear();
+        self.key_filter = Some(KeyFilter::default());
+        self.tombstones_in_disk_tables = 0;
         self.est_sum_memory = 0;
         self.insertion_was_sorted = true;
         self.unchanged_from_disk = false;
@@ -265,6 +283,9 @@ where
                 return Ok(None);
             }
         }
+        if !self.disk_tables_may_contain(key) {
+            return Ok(None);
+        }
         // Iterate over all disk-tables to find the entry
         for table in self.disk_tables.iter().rev() {
             if let Some(value) = table.get(key)? {
@@ -281,9 +302,22 @@ where
         Ok(None)
     }
 
+    /// Returns `false` if the key is definitely not part of any disk table.
+    fn disk_tables_may_contain(&self, key: &[u8]) -> bool {
+        if let Some(key_filter) = &self.key_filter {
+            key_filter.may_contain(key)
+        } else {
+            !self.disk_tables.is_empty()
+        }
+    }
+
     pub fn try_contains_key(&self, key: &K) -> Result<bool> {
         let key = K::create_key(key);
 
+        if !self.disk_tables_may_contain(&key) && !self.c0.contains_key(key.as_ref()) {
+            return Ok(false);
+        }
+
         if self.unchanged_from_disk {
             // Use a iterator on the single disk to check if there is an entry with this, without getting the value.
             // Since we don't serialize tombstone entries when compacting or writing the disk table to an output file,
@@ -432,17 +466,34 @@ where
             Bound::Unbounded => Bound::Unbounded,
         };
 
+        self.raw_range(mapped_start_bound, mapped_end_bound)
+    }
+
+    /// Returns an iterator over all entries where the serialized key (as created by
+    /// [`KeySerializer::create_key`]) starts with the given prefix.
+    ///
+    /// In contrast to [`range`](DiskMap::range), no complete keys need to be constructed for the bounds,
+    /// e.g. all edges of a source node can be found by using the serialized source node ID as prefix.
+    pub fn prefix<'b>(&'b self, prefix: &[u8]) -> Box<dyn Iterator<Item = (K, V)> + 'b> {
+        self.raw_range(Bound::Included(prefix.to_vec()), prefix_upper_bound(prefix))
+    }
+
+    fn raw_range<'b>(
+        &'b self,
+        start: Bound<Vec<u8>>,
+        end: Bound<Vec<u8>>,
+    ) -> Box<dyn Iterator<Item = (K, V)> + 'b> {
         if self.c0.is_empty() && self.disk_tables.len() == 1 {
             Box::new(SimplifiedRange::new(
-                mapped_start_bound,
-                mapped_end_bound,
+                start,
+                end,
                 &self.disk_tables[0],
                 self.serialization,
             ))
         } else {
             Box::new(Range::new(
-                mapped_start_bound,
-                mapped_end_bound,
+                start,
+                end,
                 self.disk_tables.as_slice(),
                 &self.c0,
                 self.serialization,
@@ -460,17 +511,21 @@ where
         }
 
         // Create single temporary sorted string file by iterating over all entries
-        let out_file = tempfile::tempfile()?;
-        let mut builder = TableBuilder::new(sstable::Options::default(), &out_file);
+        let mut out_file = TemporaryTableFile::new()?;
+        let mut builder = TableBuilder::new(sstable::Options::default(), &mut out_file);
+        let mut key_filter = KeyFilter::default();
         for (key, value) in self.try_iter()? {
             let key = key.create_key();
             builder.add(&key, &self.serialization.serialize(&Some(value))?)?;
+            key_filter.insert(&key);
         }
         let size = builder.finish()?;
 
         // Re-open sorted string table and set it as the only table
         let table = Table::new(sstable::Options::default(), Box::new(out_file), size)?;
         self.disk_tables = vec![table];
+        self.key_filter = Some(key_filter);
+        self.tombstones_in_disk_tables = 0;
         self.c0.clear();
 
         self.unchanged_from_disk = true;
@@ -484,6 +539,20 @@ where
         self.disk_tables.len()
     }
 
+    /// Returns the estimated size in bytes of the entries that are held in memory and have not been evicted to
+    /// disk yet. This is only tracked when the eviction strategy is [`EvictionStrategy::MaximumBytes`].
+    pub fn estimated_memory_size(&self) -> usize {
+        self.est_sum_memory
+    }
+
+    /// Returns the number of deleted entries which are still stored as tombstones in C0 or the disk tables.
+    pub fn number_of_tombstones(&self) -> usize {
+        self.tombstones_in_disk_tables + self.c0.values().filter(|v| v.is_none()).count()
+    }
+
+    /// Write all entries to a single sorted string table at the given `location`.
+    ///
+    /// The written table is compacted: deleted entries are omitted and it contains no tombstones.
     pub fn write_to(&self, location: &Path) -> Result<()> {
         // Make sure the parent directory exist
         if let Some(parent) = location.parent() {
@@ -496,12 +565,17 @@ where
             .create(true)
             .open(&location)?;
         let mut builder = TableBuilder::new(sstable::Options::default(), out_file);
+        let mut key_filter = KeyFilter::default();
         for (key, value) in self.try_iter()? {
             let key = key.create_key();
             builder.add(&key, &self.serialization.serialize(&Some(value))?)?;
+            key_filter.insert(&key);
         }
         builder.finish()?;
 
+        // Store the key filter next to the table, so it does not need to be re-created when loading the table
+        key_filter.save_to(location)?;
+
         Ok(())
     }
 }
@@ -517,6 +591,69 @@ where
     }
 }
 
+/// A temporary file for an evicted or compacted disk table.
+///
+/// If the current thread has an encryption key, the file is encrypted with a [TemporaryFileKey], so no data of an
+/// encrypted graph is written to disk as plain text, even temporarily.
+struct TemporaryTableFile {
+    file: File,
+    key: Option<TemporaryFileKey>,
+    /// Number of bytes that have been written to the file.
+    written: u64,
+}
+
+impl TemporaryTableFile {
+    fn new() -> Result<TemporaryTableFile> {
+        Ok(TemporaryTableFile {
+            file: tempfile::tempfile()?,
+            key: encryption::current_key().map(|_| TemporaryFileKey::generate()),
+            written: 0,
+        })
+    }
+}
+
+impl Write for TemporaryTableFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = if let Some(key) = &self.key {
+            let mut encrypted = buf.to_vec();
+            key.apply_keystream(self.written, &mut encrypted);
+            self.file.write_all(&encrypted)?;
+            buf.len()
+        } else {
+            self.file.write(buf)?
+        };
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl RandomAccess for TemporaryTableFile {
+    fn read_at(&self, off: usize, dst: &mut [u8]) -> sstable::Result<usize> {
+        let bytes_read = self.file.read_at(off, dst)?;
+        if let Some(key) = &self.key {
+            key.apply_keystream(off as u64, &mut dst[0..bytes_read]);
+        }
+        Ok(bytes_read)
+    }
+}
+
+/// Returns the smallest key that is larger than all keys with the given prefix, or an unbounded end
+/// if there is no such key (e.g. because the prefix only consists of `0xFF` bytes).
+fn prefix_upper_bound(prefix: &[u8]) -> Bound<Vec<u8>> {
+    let mut upper = prefix.to_vec();
+    while let Some(last) = upper.pop() {
+        if last < u8::MAX {
+            upper.push(last + 1);
+            return Bound::Excluded(upper);
+        }
+    }
+    Bound::Unbounded
+}
+
 pub struct Range<'a, K, V> {
     range_start: Bound<Vec<u8>>,
     range_end: Bound<Vec<u8>>,
@@ -728,14 +865,14 @@ where
 
     fn next(&mut self) -> Option<(K, V)> {
         loop {
-            // Find the smallest key in all tables.
-            let mut smallest_key: Option<(Vec<u8>, Option<V>)> = None;
+            // Find the smallest key in all tables. Values from the disk tables are only
+            // decoded once it is clear which entry is returned.
+            let mut smallest_key: Option<(Vec<u8>, SmallestValue<'a, V>)> = None;
 
             // Try C0 first
-            if let Some(c0_item) = self.c0_range.peek() {
-                let key: &Vec<u8> = c0_item.0;
-                let value: &Option<V> = c0_item.1;
-                smallest_key = Some((key.clone(), value.clone()));
+            if let Some((key, value)) = self.c0_range.peek() {
+                let value: &'a Option<V> = value;
+                smallest_key = Some((key.to_vec(), SmallestValue::C0(value)));
             }
 
             // Iterate over all disk tables
@@ -752,11 +889,10 @@ where
                             true
                         };
                         if key_is_smaller {
-                            let value: Option<V> = self
-                                .serialization
-                                .deserialize(&self.current_value)
-                                .expect("Could not decode previously written data from disk.");
-                            smallest_key = Some((self.current_key.clone(), value));
+                            smallest_key = Some((
+                                self.current_key.clone(),
+                                SmallestValue::Disk(self.current_value.clone()),
+                            ));
                         }
                     } else {
                         self.exhausted[i] = true;
@@ -764,12 +900,19 @@ where
                 }
             }
 
-            if let Some(smallest_key) = smallest_key {
+            if let Some((smallest_key, value)) = smallest_key {
                 // Set all iterators to the next element
-                self.advance_all(&smallest_key.0);
+                self.advance_all(&smallest_key);
+                let value: Option<V> = match value {
+                    SmallestValue::C0(value) => value.clone(),
+                    SmallestValue::Disk(raw) => self
+                        .serialization
+                        .deserialize(&raw)
+                        .expect("Could not decode previously written data from disk."),
+                };
                 // Return any non-deleted entry
-                if let Some(value) = smallest_key.1 {
-                    let key = K::parse_key(&smallest_key.0);
+                if let Some(value) = value {
+                    let key = K::parse_key(&smallest_key);
                     return Some((key, value));
                 }
             } else {
@@ -780,6 +923,12 @@ where
     }
 }
 
+/// The value of the currently smallest key found by the [`Range`] iterator.
+enum SmallestValue<'a, V> {
+    C0(&'a Option<V>),
+    Disk(Vec<u8>),
+}
+
 /// An iterator implementation for the case that there is only a single disk-table and no C0
 pub struct SimplifiedRange<K, V> {
     range_start: Bound<Vec<u8>>,
@@ -1017,5 +1166,122 @@ where
     }
 }
 
+/// A scalable bloom filter for the (serialized) keys of the disk tables.
+///
+/// It consists of several bloom filters with increasing capacity.
+/// New keys are always added to the last filter, and a new filter is
+/// created when the last one is full.
+#[derive(Serialize, Deserialize)]
+struct KeyFilter {
+    filters: Vec<KeyFilterSegment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct KeyFilterSegment {
+    bits: Vec<u64>,
+    capacity: usize,
+    len: usize,
+}
+
+impl Default for KeyFilter {
+    fn default() -> Self {
+        KeyFilter {
+            filters: vec![KeyFilterSegment::with_capacity(KEY_FILTER_INITIAL_CAPACITY)],
+        }
+    }
+}
+
+impl KeyFilter {
+    fn insert(&mut self, key: &[u8]) {
+        let hash = KeyFilter::hash(key);
+        let needs_new_segment = match self.filters.last() {
+            Some(f) => f.len >= f.capacity,
+            None => true,
+        };
+        if needs_new_segment {
+            let capacity = self
+                .filters
+                .last()
+                .map_or(KEY_FILTER_INITIAL_CAPACITY, |f| f.capacity * 2);
+            self.filters.push(KeyFilterSegment::with_capacity(capacity));
+        }
+        if let Some(f) = self.filters.last_mut() {
+            f.insert(hash);
+        }
+    }
+
+    fn may_contain(&self, key: &[u8]) -> bool {
+        let hash = KeyFilter::hash(key);
+        self.filters.iter().any(|f| f.may_contain(hash))
+    }
+
+    /// Calculates a 64 bit FNV-1a hash, which is stable across platforms and Rust versions.
+    fn hash(key: &[u8]) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for b in key {
+            hash ^= u64::from(*b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+
+    fn location_for_table(table_location: &Path) -> PathBuf {
+        table_location.with_extension(KEY_FILTER_FILE_EXTENSION)
+    }
+
+    fn load_from(table_location: &Path) -> Result<Option<KeyFilter>> {
+        let location = KeyFilter::location_for_table(table_location);
+        if location.is_file() {
+            let f = std::fs::File::open(location)?;
+            let reader = std::io::BufReader::new(f);
+            let key_filter = bincode::deserialize_from(reader)?;
+            Ok(Some(key_filter))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn save_to(&self, table_location: &Path) -> Result<()> {
+        let f = std::fs::File::create(KeyFilter::location_for_table(table_location))?;
+        let writer = std::io::BufWriter::new(f);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+}
+
+impl KeyFilterSegment {
+    fn with_capacity(capacity: usize) -> KeyFilterSegment {
+        KeyFilterSegment {
+            bits: vec![0; (capacity * KEY_FILTER_BITS_PER_KEY) / 64 + 1],
+            capacity,
+            len: 0,
+        }
+    }
+
+    /// Returns the bit positions for the given hash using the double hashing scheme
+    /// by Kirsch and Mitzenmacher.
+    fn bit_positions(&self, hash: u64) -> impl Iterator<Item = usize> {
+        let number_of_bits = (self.bits.len() * 64) as u64;
+        let h1 = hash & 0xffff_ffff;
+        let h2 = (hash >> 32) | 1;
+        (0..KEY_FILTER_NUMBER_OF_HASHES)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % number_of_bits) as usize)
+    }
+
+    fn insert(&mut self, hash: u64) {
+        let number_of_bits = self.bits.len() * 64;
+        for pos in self.bit_positions(hash) {
+            debug_assert!(pos < number_of_bits);
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+        self.len += 1;
+    }
+
+    fn may_contain(&self, hash: u64) -> bool {
+        self.bit_positions(hash)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -158,3 +158,128 @@ fn unknown_key() {
     );
     assert_eq!(false, table.try_contains_key(&test_key).unwrap());
 }
+
+#[test]
+fn compact_after_many_deletes() {
+    let max_items = 1000;
+    let mut table = DiskMap::new(None, EvictionStrategy::MaximumItems(max_items)).unwrap();
+    let number_of_entries = (MAX_NUMBER_OF_TOMBSTONES + 3 * max_items) as u64;
+    for i in 0..number_of_entries {
+        table.insert(i, true).unwrap();
+    }
+    table.compact().unwrap();
+
+    // Deleting entries only adds tombstones to C0 and does not compact the tables
+    for i in 0..(max_items as u64) {
+        table.remove(&i).unwrap();
+    }
+    assert_eq!(max_items, table.number_of_tombstones());
+    assert_eq!(1, table.number_of_disk_tables());
+
+    // The tables are compacted after the eviction which exceeds the maximum number of tombstones
+    for i in (max_items as u64)..(number_of_entries - 10) {
+        table.remove(&i).unwrap();
+    }
+    assert!(table.number_of_tombstones() < 3 * (max_items + 1));
+
+    let result: Vec<(u64, bool)> = table.iter().collect();
+    assert_eq!(10, result.len());
+    assert_eq!(None, table.try_get(&0).unwrap());
+    assert_eq!(Some(true), table.try_get(&(number_of_entries - 1)).unwrap());
+
+    // The saved table does not contain any tombstones
+    let tmp = tempfile::tempdir().unwrap();
+    let location = tmp.path().join("table.bin");
+    table.write_to(&location).unwrap();
+    let loaded: DiskMap<u64, bool> =
+        DiskMap::new(Some(&location), EvictionStrategy::default()).unwrap();
+    assert_eq!(0, loaded.number_of_tombstones());
+    let result: Vec<(u64, bool)> = loaded.iter().collect();
+    assert_eq!(10, result.len());
+}
+
+#[test]
+fn key_filter_persisted() {
+    let tmp = tempfile::tempdir().unwrap();
+    let location = tmp.path().join("table.bin");
+
+    let mut table = DiskMap::new(None, EvictionStrategy::MaximumItems(5)).unwrap();
+    for i in 0..100_u64 {
+        table.insert(i * 2, true).unwrap();
+    }
+    table.write_to(&location).unwrap();
+    assert!(tmp.path().join("table.bloom").is_file());
+
+    let loaded: DiskMap<u64, bool> =
+        DiskMap::new(Some(&location), EvictionStrategy::default()).unwrap();
+    assert!(loaded.key_filter.is_some());
+    for i in 0..100 {
+        assert!(loaded.try_contains_key(&(i * 2)).unwrap());
+        assert!(!loaded.try_contains_key(&(i * 2 + 1)).unwrap());
+        assert_eq!(Some(true), loaded.try_get(&(i * 2)).unwrap());
+        assert_eq!(None, loaded.try_get(&(i * 2 + 1)).unwrap());
+    }
+}
+
+#[test]
+fn prefix() {
+    let mut table = DiskMap::new(None, EvictionStrategy::MaximumItems(3)).unwrap();
+    for key in ["a", "ab", "abc", "abd", "b", "\u{FF}", "\u{FF}a"] {
+        table.insert(key.to_string(), true).unwrap();
+    }
+    table.remove(&"abd".to_string()).unwrap();
+
+    let keys = |table: &DiskMap<String, bool>, prefix: &str| -> Vec<String> {
+        table.prefix(prefix.as_bytes()).map(|(k, _)| k).collect()
+    };
+
+    // Several disk tables and C0
+    assert_eq!(vec!["ab", "abc"], keys(&table, "ab"));
+    assert_eq!(vec!["a", "ab", "abc"], keys(&table, "a"));
+    assert_eq!(vec!["\u{FF}", "\u{FF}a"], keys(&table, "\u{FF}"));
+    assert!(keys(&table, "c").is_empty());
+    assert_eq!(6, keys(&table, "").len());
+
+    // Single disk table
+    table.compact().unwrap();
+    assert_eq!(vec!["ab", "abc"], keys(&table, "ab"));
+    assert_eq!(vec!["\u{FF}", "\u{FF}a"], keys(&table, "\u{FF}"));
+    assert!(keys(&table, "c").is_empty());
+}
+
+#[test]
+fn prefix_upper_bound_of_max_bytes() {
+    assert_eq!(Bound::Excluded(vec![1, 3]), prefix_upper_bound(&[1, 2]));
+    assert_eq!(Bound::Excluded(vec![2]), prefix_upper_bound(&[1, 255, 255]));
+    assert_eq!(Bound::Unbounded, prefix_upper_bound(&[255, 255]));
+    assert_eq!(Bound::Unbounded, prefix_upper_bound(&[]));
+}
+
+#[test]
+fn evict_with_encryption_key() {
+    let tmp = tempfile::tempdir().unwrap();
+    let key = std::sync::Arc::new(encryption::EncryptionKey::new([5; encryption::KEY_LENGTH]));
+    encryption::with_key(Some(&key), tmp.path(), || {
+        // temporary files are encrypted
+        let mut file = TemporaryTableFile::new().unwrap();
+        file.write_all(b"secret annotation value").unwrap();
+        let mut raw = vec![0; 23];
+        file.file.read_at(0, &mut raw).unwrap();
+        assert_ne!(b"secret annotation value".to_vec(), raw);
+        let mut decrypted = vec![0; 10];
+        RandomAccess::read_at(&file, 7, &mut decrypted).unwrap();
+        assert_eq!(b"annotation".to_vec(), decrypted);
+
+        // the disk map still evicts its entries to disk
+        let mut table = DiskMap::new(None, EvictionStrategy::MaximumItems(5)).unwrap();
+        for i in 0..100_u64 {
+            table.insert(i, format!("value{}", i)).unwrap();
+        }
+        assert!(table.number_of_disk_tables() > 0);
+        assert_eq!(Some("value42".to_string()), table.try_get(&42).unwrap());
+        table.compact().unwrap();
+        let result: Vec<(u64, String)> = table.iter().collect();
+        assert_eq!(100, result.len());
+        assert_eq!((99, "value99".to_string()), result[99]);
+    });
+}
@@ -0,0 +1,420 @@
+//! Optional encryption of the files of a graph at rest.
+//!
+//! Files are encrypted as a whole with ChaCha20-Poly1305, which also detects files that have been modified or are
+//! read with the wrong key. The path of a file relative to the directory it belongs to is authenticated too, so an
+//! encrypted file can't be replaced by another encrypted file of the same corpus. Graph storages and annotation
+//! storages are saved and loaded by their trait methods, which only get the location on disk. The key of a graph is
+//! therefore made available to them for the current thread with [with_key] while the graph is saved or loaded.
+
+use crate::errors::{GraphAnnisCoreError, Result};
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Length of an encryption key in bytes.
+pub const KEY_LENGTH: usize = 32;
+/// Encrypted files start with this magic number, followed by the nonce and the encrypted content.
+const ENCRYPTED_FILE_MAGIC: &[u8; 8] = b"GANNENC2";
+const NONCE_LENGTH: usize = 12;
+
+/// The key of the current thread and the directory the paths of the encrypted files are relative to.
+struct KeyContext {
+    key: Arc<EncryptionKey>,
+    base: PathBuf,
+}
+
+thread_local! {
+    static CURRENT_KEY: RefCell<Option<KeyContext>> = const { RefCell::new(None) };
+}
+
+/// A secret key which is used to encrypt the files of a graph.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    key: Key,
+    allow_unencrypted: bool,
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // never print the actual key, e.g. in log messages
+        write!(f, "EncryptionKey(..)")
+    }
+}
+
+impl EncryptionKey {
+    pub fn new(key: [u8; KEY_LENGTH]) -> EncryptionKey {
+        EncryptionKey {
+            key: key.into(),
+            allow_unencrypted: false,
+        }
+    }
+
+    /// If `allow` is true, unencrypted files can be read with this key.
+    ///
+    /// By default, these files are rejected. Allowing them is needed to migrate an existing corpus, which is
+    /// encrypted by saving it again.
+    pub fn allow_unencrypted(mut self, allow: bool) -> EncryptionKey {
+        self.allow_unencrypted = allow;
+        self
+    }
+
+    /// Returns `true` if unencrypted files can be read with this key.
+    pub fn allows_unencrypted(&self) -> bool {
+        self.allow_unencrypted
+    }
+
+    /// Create the key from its hexadecimal representation with 64 characters, e.g. from a configuration file.
+    pub fn from_hex(hex: &str) -> Result<EncryptionKey> {
+        let hex = hex.trim();
+        if hex.len() != KEY_LENGTH * 2 || !hex.is_ascii() {
+            return Err(GraphAnnisCoreError::InvalidEncryptionKey);
+        }
+        let mut key = [0; KEY_LENGTH];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| GraphAnnisCoreError::InvalidEncryptionKey)?;
+        }
+        Ok(EncryptionKey::new(key))
+    }
+
+    /// Encrypt the `plaintext` and return the content of an encrypted file.
+    ///
+    /// The `name` identifies the file, e.g. by its path relative to the corpus, and must be the same when
+    /// decrypting it.
+    pub fn encrypt(&self, plaintext: &[u8], name: &str) -> Result<Vec<u8>> {
+        let cipher = ChaCha20Poly1305::new(&self.key);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = associated_data(name);
+        let payload = Payload {
+            msg: plaintext,
+            aad: &aad,
+        };
+        let ciphertext = cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| GraphAnnisCoreError::Encryption)?;
+
+        let mut result =
+            Vec::with_capacity(ENCRYPTED_FILE_MAGIC.len() + NONCE_LENGTH + ciphertext.len());
+        result.extend_from_slice(ENCRYPTED_FILE_MAGIC);
+        result.extend_from_slice(&nonce);
+        result.extend_from_slice(&ciphertext);
+        Ok(result)
+    }
+
+    /// Decrypt the content of an encrypted file with the `name` it has been encrypted with.
+    /// The `path` is only used for the error message.
+    pub fn decrypt(&self, data: &[u8], name: &str, path: &Path) -> Result<Vec<u8>> {
+        let decryption_error =
+            || GraphAnnisCoreError::Decryption(path.to_string_lossy().to_string());
+        if !is_encrypted(data) || data.len() < ENCRYPTED_FILE_MAGIC.len() + NONCE_LENGTH {
+            return Err(decryption_error());
+        }
+        let aad = associated_data(name);
+        let (nonce, ciphertext) = data[ENCRYPTED_FILE_MAGIC.len()..].split_at(NONCE_LENGTH);
+        let cipher = ChaCha20Poly1305::new(&self.key);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &aad,
+        };
+        cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| decryption_error())
+    }
+}
+
+/// A random key for temporary files, which only exists in main memory.
+///
+/// Temporary files (e.g. the evicted tables of a disk map) are only read by the process that wrote them. They are
+/// encrypted with a stream cipher, which allows to decrypt any range of the file, and their content can't be read
+/// anymore after the process has ended.
+pub(crate) struct TemporaryFileKey {
+    key: chacha20::Key,
+    nonce: chacha20::Nonce,
+}
+
+impl TemporaryFileKey {
+    pub(crate) fn generate() -> TemporaryFileKey {
+        TemporaryFileKey {
+            key: rand::random::<[u8; KEY_LENGTH]>().into(),
+            nonce: rand::random::<[u8; NONCE_LENGTH]>().into(),
+        }
+    }
+
+    /// Encrypt or decrypt the `data` which is located at the given `offset` of the temporary file.
+    pub(crate) fn apply_keystream(&self, offset: u64, data: &mut [u8]) {
+        let mut cipher = ChaCha20::new(&self.key, &self.nonce);
+        cipher.seek(offset);
+        cipher.apply_keystream(data);
+    }
+}
+
+/// The additional authenticated data of an encrypted file with the given `name`.
+fn associated_data(name: &str) -> Vec<u8> {
+    let mut result = ENCRYPTED_FILE_MAGIC.to_vec();
+    result.extend_from_slice(name.as_bytes());
+    result
+}
+
+/// Execute `f` with the given `key` as the encryption key of the current thread.
+///
+/// The files are identified by their path relative to the `base` directory, e.g. the directory of the saved graph.
+/// The previous key of the thread is restored afterwards, so calls can be nested.
+pub fn with_key<F, R>(key: Option<&Arc<EncryptionKey>>, base: &Path, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct RestoreKey(Option<KeyContext>);
+
+    impl Drop for RestoreKey {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT_KEY.with(|k| *k.borrow_mut() = previous);
+        }
+    }
+
+    let context = key.map(|key| KeyContext {
+        key: key.clone(),
+        base: base.to_path_buf(),
+    });
+    let previous = CURRENT_KEY.with(|k| k.replace(context));
+    let _restore = RestoreKey(previous);
+    f()
+}
+
+/// Get the encryption key of the current thread, if there is one.
+pub fn current_key() -> Option<Arc<EncryptionKey>> {
+    CURRENT_KEY.with(|k| k.borrow().as_ref().map(|c| c.key.clone()))
+}
+
+/// Get the encryption key of the current thread and the name of the file at `path`, which is its path relative to
+/// the base directory of the key.
+fn current_key_and_name(path: &Path) -> Option<(Arc<EncryptionKey>, String)> {
+    CURRENT_KEY.with(|k| {
+        k.borrow().as_ref().map(|c| {
+            let relative = path.strip_prefix(&c.base).unwrap_or(path);
+            let name: Vec<_> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect();
+            (c.key.clone(), name.join("/"))
+        })
+    })
+}
+
+/// Return an error if the current thread has an encryption key.
+///
+/// This is used by the disk-based storages, which access their files directly and can't encrypt them.
+pub(crate) fn check_no_key(storage: &str) -> Result<()> {
+    if current_key().is_some() {
+        Err(GraphAnnisCoreError::EncryptionNotSupported(
+            storage.to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `true` if the file content starts with the magic number of encrypted files.
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(ENCRYPTED_FILE_MAGIC)
+}
+
+/// Encrypt the `data` for the file at `path` with the key of the current thread, if there is one.
+///
+/// Use this when the content is first written to a temporary file and then moved to `path`.
+pub fn encode(path: &Path, data: &[u8]) -> Result<Vec<u8>> {
+    if let Some((key, name)) = current_key_and_name(path) {
+        key.encrypt(data, &name)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// Write the `data` to the file at `path` and encrypt it with the key of the current thread, if there is one.
+pub fn write(path: &Path, data: &[u8]) -> Result<()> {
+    std::fs::write(path, encode(path, data)?)?;
+    Ok(())
+}
+
+/// Read the file at `path` and decrypt it if it is encrypted.
+///
+/// Unencrypted files are rejected if the current thread has a key, unless the key
+/// [allows unencrypted files](EncryptionKey::allow_unencrypted).
+pub fn read(path: &Path) -> Result<Vec<u8>> {
+    let data = std::fs::read(path)?;
+    match current_key_and_name(path) {
+        Some((key, name)) if is_encrypted(&data) => key.decrypt(&data, &name, path),
+        Some((key, _)) if !key.allows_unencrypted() => Err(GraphAnnisCoreError::UnencryptedFile(
+            path.to_string_lossy().to_string(),
+        )),
+        None if is_encrypted(&data) => Err(GraphAnnisCoreError::EncryptionKeyMissing(
+            path.to_string_lossy().to_string(),
+        )),
+        _ => Ok(data),
+    }
+}
+
+/// Serialize the `value` into the file at `path` and encrypt it with the key of the current thread, if there is
+/// one.
+pub(crate) fn serialize_to_file<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some((key, name)) = current_key_and_name(path) {
+        let data = bincode::serialize(value)?;
+        std::fs::write(path, key.encrypt(&data, &name)?)?;
+    } else {
+        let f = std::fs::File::create(path)?;
+        let mut writer = std::io::BufWriter::new(f);
+        bincode::serialize_into(&mut writer, value)?;
+    }
+    Ok(())
+}
+
+/// Deserialize the content of the file at `path`, which is decrypted first if it is encrypted.
+pub(crate) fn deserialize_from_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    let mut magic = [0; 8];
+    let magic_len = read_prefix(&mut reader, &mut magic)?;
+    if is_encrypted(&magic[0..magic_len]) {
+        // encrypted files can only be decrypted as a whole
+        let data = read(path)?;
+        Ok(bincode::deserialize(&data)?)
+    } else if current_key()
+        .map(|key| !key.allows_unencrypted())
+        .unwrap_or(false)
+    {
+        Err(GraphAnnisCoreError::UnencryptedFile(
+            path.to_string_lossy().to_string(),
+        ))
+    } else {
+        let reader = std::io::Cursor::new(&magic[0..magic_len]).chain(reader);
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+/// Fill the `buffer` with the first bytes of the `reader` and return the number of read bytes, which is smaller
+/// than the buffer for short files.
+fn read_prefix<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        let bytes_read = reader.read(&mut buffer[len..])?;
+        if bytes_read == 0 {
+            break;
+        }
+        len += bytes_read;
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_and_decrypt_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("data.bin");
+        let key = Arc::new(EncryptionKey::new([7; KEY_LENGTH]));
+        let value: Vec<String> = vec!["secret".to_string(), "annotation".to_string()];
+
+        with_key(Some(&key), tmp.path(), || serialize_to_file(&path, &value)).unwrap();
+        let raw = std::fs::read(&path).unwrap();
+        assert!(is_encrypted(&raw));
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+
+        let loaded: Vec<String> =
+            with_key(Some(&key), tmp.path(), || deserialize_from_file(&path)).unwrap();
+        assert_eq!(value, loaded);
+
+        // reading fails without the key or with the wrong one
+        assert!(matches!(
+            deserialize_from_file::<Vec<String>>(&path),
+            Err(GraphAnnisCoreError::EncryptionKeyMissing(_))
+        ));
+        let other_key = Arc::new(EncryptionKey::new([8; KEY_LENGTH]));
+        assert!(matches!(
+            with_key(Some(&other_key), tmp.path(), || deserialize_from_file::<
+                Vec<String>,
+            >(&path)),
+            Err(GraphAnnisCoreError::Decryption(_))
+        ));
+
+        // an encrypted file can't be moved to another path
+        let moved_path = tmp.path().join("other.bin");
+        std::fs::copy(&path, &moved_path).unwrap();
+        assert!(matches!(
+            with_key(Some(&key), tmp.path(), || deserialize_from_file::<
+                Vec<String>,
+            >(&moved_path)),
+            Err(GraphAnnisCoreError::Decryption(_))
+        ));
+        // but the directory the paths are relative to can be renamed
+        let renamed_dir = tempfile::tempdir().unwrap();
+        let renamed_path = renamed_dir.path().join("data.bin");
+        std::fs::copy(&path, &renamed_path).unwrap();
+        let loaded: Vec<String> = with_key(Some(&key), renamed_dir.path(), || {
+            deserialize_from_file(&renamed_path)
+        })
+        .unwrap();
+        assert_eq!(value, loaded);
+
+        // unencrypted files are rejected with a key, unless they are explicitly allowed
+        serialize_to_file(&path, &value).unwrap();
+        assert!(matches!(
+            with_key(Some(&key), tmp.path(), || deserialize_from_file::<
+                Vec<String>,
+            >(&path)),
+            Err(GraphAnnisCoreError::UnencryptedFile(_))
+        ));
+        assert!(matches!(
+            with_key(Some(&key), tmp.path(), || read(&path)),
+            Err(GraphAnnisCoreError::UnencryptedFile(_))
+        ));
+        let migration_key = Arc::new(EncryptionKey::new([7; KEY_LENGTH]).allow_unencrypted(true));
+        let loaded: Vec<String> = with_key(Some(&migration_key), tmp.path(), || {
+            deserialize_from_file(&path)
+        })
+        .unwrap();
+        assert_eq!(value, loaded);
+        // the key of the thread is reset after the call
+        assert!(current_key().is_none());
+    }
+
+    #[test]
+    fn temporary_file_key() {
+        let key = TemporaryFileKey::generate();
+        let plaintext: Vec<u8> = (0..200).collect();
+        let mut data = plaintext.clone();
+        key.apply_keystream(0, &mut data);
+        assert_ne!(plaintext, data);
+        // each range can be decrypted on its own
+        let mut range = data[70..150].to_vec();
+        key.apply_keystream(70, &mut range);
+        assert_eq!(&plaintext[70..150], &range[..]);
+    }
+
+    #[test]
+    fn key_from_hex() {
+        let key = EncryptionKey::from_hex(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        )
+        .unwrap();
+        let encrypted = key.encrypt(b"text", "text").unwrap();
+        assert_eq!(
+            b"text".to_vec(),
+            EncryptionKey::new([
+                0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+                23, 24, 25, 26, 27, 28, 29, 30, 31
+            ])
+            .decrypt(&encrypted, "text", Path::new("text"))
+            .unwrap()
+        );
+        assert!(EncryptionKey::from_hex("0001").is_err());
+        assert!(EncryptionKey::from_hex(&"x".repeat(64)).is_err());
+    }
+}
@@ -2,7 +2,9 @@ use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use std::borrow::Cow;
 
 pub mod disk_collections;
+pub mod encryption;
 pub mod memory_estimation;
+pub mod progress;
 
 const QNAME_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ').add(b':').add(b'%');
 
@@ -27,6 +29,10 @@ pub fn split_qname(qname: &str) -> (Option<&str>, &str) {
     }
 }
 
+/// Wrap the regular expression `pattern` so it only matches complete values and not a substring of them.
+///
+/// Inline flags of the pattern like `(?i)` for case-insensitive matching are allowed, because the pattern is wrapped
+/// into its own group.
 pub fn regex_full_match(pattern: &str) -> String {
     let mut full_match_pattern = String::new();
     full_match_pattern.push_str(r"\A(");
@@ -35,3 +41,52 @@ pub fn regex_full_match(pattern: &str) -> String {
 
     full_match_pattern
 }
+
+/// Unicode normalization form applied to annotation values and query literals.
+///
+/// Values that are canonically equivalent but use a different normal form (e.g. a precomposed
+/// "é" and "e" followed by a combining accent) only match each other if both are normalized
+/// to the same form.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnicodeNormalization {
+    /// Do not normalize values.
+    None,
+    /// Canonical decomposition, followed by canonical composition.
+    #[default]
+    NFC,
+    /// Canonical decomposition.
+    NFD,
+    /// Compatibility decomposition, followed by canonical composition.
+    NFKC,
+    /// Compatibility decomposition.
+    NFKD,
+}
+
+impl UnicodeNormalization {
+    /// Returns the normalized version of the value.
+    ///
+    /// No new string is allocated if the value is already in the normal form.
+    pub fn normalize<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        use unicode_normalization::{
+            is_nfc_quick, is_nfd_quick, is_nfkc_quick, is_nfkd_quick, IsNormalized,
+            UnicodeNormalization as _,
+        };
+
+        let (quick_check, normalized): (IsNormalized, fn(&str) -> String) = match self {
+            UnicodeNormalization::None => return Cow::Borrowed(value),
+            UnicodeNormalization::NFC => (is_nfc_quick(value.chars()), |v| v.nfc().collect()),
+            UnicodeNormalization::NFD => (is_nfd_quick(value.chars()), |v| v.nfd().collect()),
+            UnicodeNormalization::NFKC => (is_nfkc_quick(value.chars()), |v| v.nfkc().collect()),
+            UnicodeNormalization::NFKD => (is_nfkd_quick(value.chars()), |v| v.nfkd().collect()),
+        };
+        if quick_check == IsNormalized::Yes {
+            return Cow::Borrowed(value);
+        }
+        let result = normalized(value);
+        if result == value {
+            Cow::Borrowed(value)
+        } else {
+            Cow::Owned(result)
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct ThrottleState {
+    last_reported: Option<Instant>,
+    pending: Option<String>,
+}
+
+/// Wraps a progress callback and limits how often it is called.
+///
+/// Status messages that arrive less than the configured interval after the last reported one are not forwarded
+/// immediately. Only the latest of these messages is kept and reported either with the next message after the
+/// interval has passed, or when the throttle is flushed or dropped. This way the consumer always gets the final
+/// status message. An interval of zero forwards all messages.
+pub struct ProgressThrottle<F>
+where
+    F: Fn(&str),
+{
+    callback: F,
+    interval: Duration,
+    state: Mutex<ThrottleState>,
+}
+
+impl<F> ProgressThrottle<F>
+where
+    F: Fn(&str),
+{
+    pub fn new(callback: F, interval: Duration) -> ProgressThrottle<F> {
+        ProgressThrottle {
+            callback,
+            interval,
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    /// Report a status message to the wrapped callback, unless the last message was reported recently.
+    pub fn report(&self, status: &str) {
+        if self.interval.is_zero() {
+            (self.callback)(status);
+            return;
+        }
+        let due = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            let due = state
+                .last_reported
+                .map(|last| now.duration_since(last) >= self.interval)
+                .unwrap_or(true);
+            if due {
+                state.last_reported = Some(now);
+                state.pending = None;
+            } else {
+                state.pending = Some(status.to_string());
+            }
+            due
+        };
+        // Don't hold the lock while calling the callback, which might report progress itself
+        if due {
+            (self.callback)(status);
+        }
+    }
+
+    /// Report the last status message that has been held back, if there is any.
+    pub fn flush(&self) {
+        let pending = {
+            let mut state = self.state.lock().unwrap();
+            let pending = state.pending.take();
+            if pending.is_some() {
+                state.last_reported = Some(Instant::now());
+            }
+            pending
+        };
+        if let Some(pending) = pending {
+            (self.callback)(&pending);
+        }
+    }
+}
+
+impl<F> Drop for ProgressThrottle<F>
+where
+    F: Fn(&str),
+{
+    fn drop(&mut self) {
+        let pending = self
+            .state
+            .get_mut()
+            .map(|state| state.pending.take())
+            .unwrap_or(None);
+        if let Some(pending) = pending {
+            (self.callback)(&pending);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn coalesce_messages() {
+        let reported = RefCell::new(Vec::new());
+        {
+            let throttle = ProgressThrottle::new(
+                |status: &str| reported.borrow_mut().push(status.to_string()),
+                Duration::from_secs(3600),
+            );
+            for i in 0..10 {
+                throttle.report(&format!("message {}", i));
+            }
+            assert_eq!(vec!["message 0"], *reported.borrow());
+        }
+        // the last message is reported when the throttle is dropped
+        assert_eq!(vec!["message 0", "message 9"], *reported.borrow());
+    }
+
+    #[test]
+    fn zero_interval_reports_all() {
+        let reported = RefCell::new(Vec::new());
+        let throttle = ProgressThrottle::new(
+            |status: &str| reported.borrow_mut().push(status.to_string()),
+            Duration::from_secs(0),
+        );
+        throttle.report("a");
+        throttle.report("b");
+        throttle.flush();
+        assert_eq!(vec!["a", "b"], *reported.borrow());
+    }
+}
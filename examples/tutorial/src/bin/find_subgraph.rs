@@ -10,6 +10,7 @@ fn main() {
         query: "tok . tok",
         query_language: QueryLanguage::AQL,
         timeout: None,
+        document_filter: Vec::new(),
     };
 
     let matches = cs
@@ -57,6 +57,7 @@ fn find_all_nouns_gum(bench: &mut Criterion) {
                 query: "pos=\"NN\"",
                 query_language: QueryLanguage::AQL,
                 timeout: None,
+                document_filter: Vec::new(),
             };
             let f = cs.find(query, usize::min_value(), None, ResultOrder::Normal);
             assert!(f.is_ok());
@@ -60,6 +60,7 @@ fn search_{corpus_escaped}_{name_escaped}() {{
                 corpus_names: &[\"{corpus}\"],
                 query_language: QueryLanguage::AQL,
                 timeout: None,
+                document_filter: Vec::new(),
             }};
             cs.count(search_query).unwrap_or(0)
         }};
@@ -1,9 +1,10 @@
 use std::rc::Rc;
 
 use crate::annis::db::aql::operators::{
-    AritySpec, DominanceSpec, IdenticalCoverageSpec, IdenticalNodeSpec, InclusionSpec,
-    LeftAlignmentSpec, NearSpec, OverlapSpec, PartOfSubCorpusSpec, PointingSpec, PrecedenceSpec,
-    RightAlignmentSpec,
+    AritySpec, DifferentDocumentSpec, DominanceSpec, IdenticalCoverageSpec, IdenticalNodeSpec,
+    InclusionSpec, LeftAlignmentSpec, LengthSpec, NearSpec, OutDegreeSpec, OverlapSpec,
+    PartOfSubCorpusSpec, PointingSpec, PrecedenceSpec, RightAlignmentSpec, SameSegmentSpec,
+    SameTextSpec,
 };
 use crate::annis::db::exec::nodesearch::NodeSearchSpec;
 
@@ -36,6 +37,7 @@ pub enum Literal {
         spec: NodeSearchSpec,
         pos: Option<Pos>,
         variable: Option<String>,
+        optional: bool,
     },
     BinaryOp {
         lhs: Operand,
@@ -52,6 +54,10 @@ pub enum Literal {
         spec: NodeSearchSpec,
         pos: Pos,
     },
+    NegatedMetaSearch {
+        spec: NodeSearchSpec,
+        pos: Pos,
+    },
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, Hash, PartialEq, Eq)]
@@ -61,6 +67,7 @@ pub enum Operand {
         spec: Rc<NodeSearchSpec>,
         pos: Pos,
         variable: Option<String>,
+        optional: bool,
     },
 }
 
@@ -101,12 +108,19 @@ pub enum BinaryOpSpec {
     LeftAlignment(LeftAlignmentSpec),
     RightAlignment(RightAlignmentSpec),
     IdenticalNode(IdenticalNodeSpec),
+    SameSegment(SameSegmentSpec),
+    SameText(SameTextSpec),
+    DifferentDocument(DifferentDocumentSpec),
     ValueComparison(ComparisonOperator),
+    Negated(Box<BinaryOpSpec>),
+    Derived(String),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum UnaryOpSpec {
     Arity(AritySpec),
+    OutDegree(OutDegreeSpec),
+    Length(LengthSpec),
 }
 
 pub use crate::annis::db::aql::operators::RangeSpec;
@@ -1,6 +1,7 @@
 mod ast;
 pub mod model;
 pub mod operators;
+pub mod syntax;
 use boolean_expression::Expr;
 lalrpop_mod!(
     #[allow(clippy::all)]
@@ -9,34 +10,59 @@ lalrpop_mod!(
 );
 
 use crate::annis::db::aql::operators::{
-    EqualValueSpec, IdenticalNodeSpec, PartOfSubCorpusSpec, RangeSpec,
+    EqualValueSpec, IdenticalNodeSpec, NegatedMetaSpec, NegatedOpSpec, PartOfSubCorpusSpec,
+    RangeSpec,
 };
 use crate::annis::db::exec::nodesearch::NodeSearchSpec;
 use crate::annis::db::query::conjunction::Conjunction;
 use crate::annis::db::query::disjunction::Disjunction;
 use crate::annis::errors::*;
 use crate::annis::operator::{BinaryOperatorSpec, UnaryOperatorSpec};
-use crate::annis::types::{LineColumn, LineColumnRange};
+use crate::annis::types::{DerivedOperatorDefinition, LineColumn, LineColumnRange, QuirksRule};
+use graphannis_core::graph::{ANNIS_NS, NODE_NAME};
+use graphannis_core::util::UnicodeNormalization;
 use lalrpop_util::ParseError;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 
 thread_local! {
     static AQL_PARSER: parser::DisjunctionParser = parser::DisjunctionParser::new();
+    static DERIVED_OPERATOR_PARSER: parser::DerivedOperatorDefinitionParser =
+        parser::DerivedOperatorDefinitionParser::new();
 }
 
+/// Maps the name of a derived operator to the operators it is composed of.
+type DerivedOperatorMap = HashMap<String, Vec<ast::BinaryOpSpec>>;
+
 fn map_conjunction<'a>(
     c: Vec<ast::Literal>,
     offsets: &BTreeMap<usize, usize>,
     var_idx_offset: usize,
     quirks_mode: bool,
+    quirks: &mut Vec<QuirksRule>,
+    derived_operators: Option<&DerivedOperatorMap>,
 ) -> Result<Conjunction<'a>> {
     let mut q = Conjunction::with_offset(var_idx_offset);
     // collect and sort all node searches according to their start position in the text
     let (pos_to_node, pos_to_endpos) = calculate_node_positions(&c, offsets, quirks_mode)?;
 
+    if quirks_mode {
+        if let Some((start_pos, _)) = pos_to_node.iter().find(|(_, (_, _, optional))| *optional) {
+            return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                desc: "Optional nodes are not supported in the ANNIS3 compatibility mode.".into(),
+                location: Some(LineColumnRange {
+                    start: get_line_and_column_for_pos(*start_pos, offsets),
+                    end: pos_to_endpos
+                        .get(start_pos)
+                        .map(|end_pos| get_line_and_column_for_pos(*end_pos, offsets)),
+                }),
+            }));
+        }
+    }
+
     // add all nodes specs in order of their start position
-    let mut pos_to_node_id = add_node_specs_by_start(&mut q, pos_to_node, pos_to_endpos, offsets)?;
+    let mut pos_to_node_id =
+        add_node_specs_by_start(&mut q, pos_to_node, pos_to_endpos, offsets, var_idx_offset)?;
 
     // add all unary operators as filter(s) to the referenced nodes
     for literal in c.iter() {
@@ -59,17 +85,34 @@ fn map_conjunction<'a>(
         }
     }
 
+    // negated meta data constraints filter the first node of the conjunction
+    for literal in c.iter() {
+        if let ast::Literal::NegatedMetaSearch { spec, pos } = literal {
+            let op_pos = Some(LineColumnRange {
+                start: get_line_and_column_for_pos(pos.start, &offsets),
+                end: Some(get_line_and_column_for_pos(pos.end, &offsets)),
+            });
+            if let Some(first_node) = q.get_variable_by_pos(0) {
+                q.add_unary_operator_from_query(
+                    Box::new(NegatedMetaSpec { spec: spec.clone() }),
+                    &first_node,
+                    op_pos,
+                )?;
+            } else {
+                return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                    desc: "A negated meta data constraint needs at least one node in the query."
+                        .into(),
+                    location: op_pos,
+                }));
+            }
+        }
+    }
+
     let mut num_pointing_or_dominance_joins: HashMap<String, usize> = HashMap::default();
 
     // finally add all binary operators
     for literal in c {
-        if let ast::Literal::BinaryOp {
-            lhs,
-            mut op,
-            rhs,
-            pos,
-        } = literal
-        {
+        if let ast::Literal::BinaryOp { lhs, op, rhs, pos } = literal {
             let var_left = match lhs {
                 ast::Operand::Literal { spec, pos, .. } => pos_to_node_id
                     .entry(pos.start)
@@ -104,45 +147,94 @@ fn map_conjunction<'a>(
             let spec_left = q.resolve_variable(&var_left, op_pos.clone())?;
             let spec_right = q.resolve_variable(&var_right, op_pos.clone())?;
 
-            if quirks_mode {
-                match op {
-                    ast::BinaryOpSpec::Dominance(_) | ast::BinaryOpSpec::Pointing(_) => {
-                        let entry_lhs = num_pointing_or_dominance_joins
-                            .entry(var_left.clone())
-                            .or_insert(0);
-                        *entry_lhs += 1;
-                        let entry_rhs = num_pointing_or_dominance_joins
-                            .entry(var_right.clone())
-                            .or_insert(0);
-                        *entry_rhs += 1;
-                    }
-                    ast::BinaryOpSpec::Precedence(ref mut spec) => {
-                        // limit unspecified .* precedence to 50
-                        spec.dist = if let RangeSpec::Unbound = spec.dist {
-                            RangeSpec::Bound {
-                                min_dist: 1,
-                                max_dist: 50,
-                            }
+            // a derived operator is replaced by all the operators it is composed of
+            let ops = match op {
+                ast::BinaryOpSpec::Derived(name) => {
+                    if let Some(derived_operators) = derived_operators {
+                        if let Some(ops) = derived_operators.get(&name) {
+                            ops.clone()
                         } else {
-                            spec.dist.clone()
-                        };
+                            return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                                desc: format!(
+                                    "Unknown operator ~{}, it is not defined in the corpus configuration.",
+                                    name
+                                ),
+                                location: op_pos,
+                            }));
+                        }
+                    } else {
+                        // the definition is unknown, so the operator is left out
+                        Vec::new()
                     }
-                    ast::BinaryOpSpec::Near(ref mut spec) => {
-                        // limit unspecified ^* near-by operator to 50
-                        spec.dist = if let RangeSpec::Unbound = spec.dist {
-                            RangeSpec::Bound {
-                                min_dist: 1,
-                                max_dist: 50,
-                            }
-                        } else {
-                            spec.dist.clone()
-                        };
+                }
+                op => vec![op],
+            };
+
+            for mut op in ops {
+                if q.is_optional_node(&var_left) || q.is_optional_node(&var_right) {
+                    // the non-negated operator is used to search for the optional node, which must not exist
+                    op = match op {
+                        ast::BinaryOpSpec::Negated(op) => *op,
+                        _ => return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                            desc: "Optional nodes can only be used with negated operators like \"!>\"."
+                                .into(),
+                            location: op_pos.clone(),
+                        })),
+                    };
+                }
+
+                if quirks_mode {
+                    match op {
+                        ast::BinaryOpSpec::Dominance(_) | ast::BinaryOpSpec::Pointing(_) => {
+                            let entry_lhs = num_pointing_or_dominance_joins
+                                .entry(var_left.clone())
+                                .or_insert(0);
+                            *entry_lhs += 1;
+                            let entry_rhs = num_pointing_or_dominance_joins
+                                .entry(var_right.clone())
+                                .or_insert(0);
+                            *entry_rhs += 1;
+                        }
+                        ast::BinaryOpSpec::Precedence(ref mut spec) => {
+                            // limit unspecified .* precedence to 50
+                            spec.dist = if let RangeSpec::Unbound = spec.dist {
+                                quirks.push(QuirksRule::PrecedenceLimited {
+                                    location: op_pos.clone(),
+                                });
+                                RangeSpec::Bound {
+                                    min_dist: 1,
+                                    max_dist: 50,
+                                }
+                            } else {
+                                spec.dist.clone()
+                            };
+                        }
+                        ast::BinaryOpSpec::Near(ref mut spec) => {
+                            // limit unspecified ^* near-by operator to 50
+                            spec.dist = if let RangeSpec::Unbound = spec.dist {
+                                quirks.push(QuirksRule::NearLimited {
+                                    location: op_pos.clone(),
+                                });
+                                RangeSpec::Bound {
+                                    min_dist: 1,
+                                    max_dist: 50,
+                                }
+                            } else {
+                                spec.dist.clone()
+                            };
+                        }
+                        _ => {}
                     }
-                    _ => {}
                 }
+                let op_spec = make_binary_operator_spec(op, spec_left.clone(), spec_right.clone())?;
+                q.add_operator_from_query(
+                    op_spec,
+                    &var_left,
+                    &var_right,
+                    op_pos.clone(),
+                    !quirks_mode,
+                )?;
             }
-            let op_spec = make_binary_operator_spec(op, spec_left, spec_right)?;
-            q.add_operator_from_query(op_spec, &var_left, &var_right, op_pos, !quirks_mode)?;
         }
     }
 
@@ -152,6 +244,12 @@ fn map_conjunction<'a>(
         // Iterate over the query nodes in their order as given by the query.
         for (_, orig_var) in pos_to_node_id.iter() {
             let num_joins = num_pointing_or_dominance_joins.get(orig_var).unwrap_or(&0);
+            if *num_joins > 1 {
+                quirks.push(QuirksRule::DistributedJoins {
+                    variable: orig_var.clone(),
+                    joins: *num_joins,
+                });
+            }
             // add an additional node for each extra join and join this artificial node with identity relation
             for _ in 1..*num_joins {
                 if let Ok(node_spec) = q.resolve_variable(&orig_var, None) {
@@ -165,7 +263,7 @@ fn map_conjunction<'a>(
     Ok(q)
 }
 
-type PosToNodeMap = BTreeMap<usize, (NodeSearchSpec, Option<String>)>;
+type PosToNodeMap = BTreeMap<usize, (NodeSearchSpec, Option<String>, bool)>;
 type PosToEndPosMap = BTreeMap<usize, usize>;
 
 fn calculate_node_positions(
@@ -182,9 +280,10 @@ fn calculate_node_positions(
                 spec,
                 pos,
                 variable,
+                optional,
             } => {
                 if let Some(pos) = pos {
-                    pos_to_node.insert(pos.start, (spec.clone(), variable.clone()));
+                    pos_to_node.insert(pos.start, (spec.clone(), variable.clone(), *optional));
                     pos_to_endpos.insert(pos.start, pos.end);
                 }
             }
@@ -193,28 +292,33 @@ fn calculate_node_positions(
                     spec,
                     pos,
                     variable,
+                    optional,
                 } = lhs
                 {
                     pos_to_node
                         .entry(pos.start)
-                        .or_insert_with(|| (spec.as_ref().clone(), variable.clone()));
+                        .or_insert_with(|| (spec.as_ref().clone(), variable.clone(), *optional));
                     pos_to_endpos.entry(pos.start).or_insert_with(|| pos.end);
                 }
                 if let ast::Operand::Literal {
                     spec,
                     pos,
                     variable,
+                    optional,
                 } = rhs
                 {
                     pos_to_node
                         .entry(pos.start)
-                        .or_insert_with(|| (spec.as_ref().clone(), variable.clone()));
+                        .or_insert_with(|| (spec.as_ref().clone(), variable.clone(), *optional));
                     pos_to_endpos.entry(pos.start).or_insert_with(|| pos.end);
                 }
             }
             ast::Literal::UnaryOp { .. } => {
                 // can only have node reference, not a literal
             }
+            ast::Literal::NegatedMetaSearch { .. } => {
+                // refers to the document of the first node and does not define a node itself
+            }
             ast::Literal::LegacyMetaSearch { pos, .. } => {
                 if !quirks_mode {
                     let start = get_line_and_column_for_pos(pos.start, &offsets);
@@ -236,26 +340,37 @@ fn calculate_node_positions(
 
 fn add_node_specs_by_start<'a>(
     q: &mut Conjunction<'a>,
-    pos_to_node: BTreeMap<usize, (NodeSearchSpec, Option<String>)>,
-    pos_to_endpos: BTreeMap<usize, usize>,
+    pos_to_node: PosToNodeMap,
+    pos_to_endpos: PosToEndPosMap,
     offsets: &BTreeMap<usize, usize>,
+    var_idx_offset: usize,
 ) -> Result<BTreeMap<usize, String>> {
     let mut pos_to_node_id: BTreeMap<usize, String> = BTreeMap::default();
-    for (start_pos, (node_spec, variable)) in pos_to_node {
+    // Optional nodes are added after all other nodes, because they are not part of the match.
+    // They are still referenced by their position in the query.
+    let mut optional_nodes = Vec::new();
+    for (node_nr, (start_pos, (node_spec, variable, optional))) in
+        pos_to_node.into_iter().enumerate()
+    {
         let start = get_line_and_column_for_pos(start_pos, &offsets);
         let end = if let Some(end_pos) = pos_to_endpos.get(&start_pos) {
             Some(get_line_and_column_for_pos(*end_pos, &offsets))
         } else {
             None
         };
+        let location = Some(LineColumnRange { start, end });
+        let variable = variable.unwrap_or_else(|| (var_idx_offset + node_nr + 1).to_string());
 
-        let idx = q.add_node_from_query(
-            node_spec,
-            variable.as_deref(),
-            Some(LineColumnRange { start, end }),
-            true,
-        );
-        pos_to_node_id.insert(start_pos, idx.clone());
+        if optional {
+            optional_nodes.push((start_pos, node_spec, variable, location));
+        } else {
+            let idx = q.add_node_from_query(node_spec, Some(&variable), location, true);
+            pos_to_node_id.insert(start_pos, idx);
+        }
+    }
+    for (start_pos, node_spec, variable, location) in optional_nodes {
+        let idx = q.add_optional_node_from_query(node_spec, Some(&variable), location);
+        pos_to_node_id.insert(start_pos, idx);
     }
 
     Ok(pos_to_node_id)
@@ -286,6 +401,7 @@ fn add_legacy_metadata_constraints(
                 q.add_operator(
                     Box::new(PartOfSubCorpusSpec {
                         dist: RangeSpec::Unbound,
+                        inverse: false,
                     }),
                     &first_node_pos,
                     &meta_node_idx,
@@ -316,6 +432,81 @@ fn add_legacy_metadata_constraints(
     Ok(())
 }
 
+/// Variable name of the artificial node that is added by [`add_document_filter`].
+///
+/// The variable name can't be used in an AQL query and will therefore not conflict with other nodes.
+const DOCUMENT_FILTER_VARIABLE: &str = "annis:document_filter";
+
+fn document_filter_spec(documents: &[String]) -> NodeSearchSpec {
+    if documents.len() == 1 {
+        NodeSearchSpec::ExactValue {
+            ns: Some(ANNIS_NS.to_string()),
+            name: NODE_NAME.to_string(),
+            val: Some(documents[0].clone()),
+            is_meta: false,
+        }
+    } else {
+        NodeSearchSpec::ExactValueSet {
+            ns: Some(ANNIS_NS.to_string()),
+            name: NODE_NAME.to_string(),
+            vals: documents.to_vec(),
+            is_meta: false,
+        }
+    }
+}
+
+/// Restrict all alternatives of the query to matches that are part of one of the given documents.
+///
+/// An artificial node for the document is added to each alternative, which is joined with each node of the
+/// alternative that is part of the output using the `@*` operator. The document itself is also accepted, e.g. for
+/// queries like `tok @* doc="example"`. Nodes that are not part of the output (e.g. the metadata nodes of quirks mode)
+/// and optional nodes are not restricted. The artificial node is not included in the output.
+pub fn add_document_filter(q: &mut Disjunction, documents: &[String]) -> Result<()> {
+    let node_spec = document_filter_spec(documents);
+
+    for alt in q.alternatives.iter_mut() {
+        let filtered_nodes: Vec<String> = (0..alt.num_of_nodes())
+            .filter_map(|pos| alt.get_variable_by_pos(pos))
+            .filter(|var| alt.is_included_in_output(var) && !alt.is_optional_node(var))
+            .collect();
+        if filtered_nodes.is_empty() {
+            continue;
+        }
+        let doc_node_idx = alt.add_node_from_query(
+            node_spec.clone(),
+            Some(DOCUMENT_FILTER_VARIABLE),
+            None,
+            false,
+        );
+        for var in filtered_nodes {
+            alt.add_operator(
+                Box::new(PartOfSubCorpusSpec {
+                    dist: RangeSpec::Bound {
+                        min_dist: 0,
+                        max_dist: usize::MAX,
+                    },
+                    inverse: false,
+                }),
+                &var,
+                &doc_node_idx,
+                true,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Replace the documents of a filter that has been added with [`add_document_filter`] before.
+///
+/// This allows to execute the same query for different documents without parsing it again.
+pub fn replace_document_filter(q: &mut Disjunction, documents: &[String]) -> Result<()> {
+    let node_spec = document_filter_spec(documents);
+    for alt in q.alternatives.iter_mut() {
+        alt.replace_node(DOCUMENT_FILTER_VARIABLE, node_spec.clone())?;
+    }
+    Ok(())
+}
+
 fn find_all_children_for_and(expr: &ast::Expr, followers: &mut Vec<ast::Literal>) {
     match expr {
         Expr::Terminal(l) => {
@@ -368,8 +559,107 @@ fn get_alternatives_from_dnf(expr: ast::Expr) -> Vec<Vec<ast::Literal>> {
     vec![]
 }
 
-pub fn parse<'a>(query_as_aql: &str, quirks_mode: bool) -> Result<Disjunction<'a>> {
-    let ast = AQL_PARSER.with(|p| p.parse(query_as_aql));
+/// Parse the AQL query into a disjunction.
+///
+/// The literals of the query are normalized to the given Unicode normal form, so that they match
+/// annotation values which have been normalized in the same way. The rest of the query is unchanged.
+pub fn parse<'a>(
+    query_as_aql: &str,
+    quirks_mode: bool,
+    normalization: UnicodeNormalization,
+) -> Result<Disjunction<'a>> {
+    parse_with_derived_operators(query_as_aql, quirks_mode, normalization, &[])
+}
+
+/// Parse the AQL query into a disjunction and allow to use the given derived operators.
+///
+/// A derived operator `~name` in the query is replaced by all the operators of its definition.
+pub fn parse_with_derived_operators<'a>(
+    query_as_aql: &str,
+    quirks_mode: bool,
+    normalization: UnicodeNormalization,
+    derived_operators: &[DerivedOperatorDefinition],
+) -> Result<Disjunction<'a>> {
+    let derived_operators = parse_derived_operators(derived_operators, normalization)?;
+    parse_with_quirks(
+        query_as_aql,
+        quirks_mode,
+        normalization,
+        &mut Vec::new(),
+        Some(&derived_operators),
+    )
+}
+
+/// Parse the AQL query into a disjunction and accept any derived operator without knowing its definition.
+///
+/// The derived operators are left out of the result, which is only useful to analyze the nodes of the query.
+pub fn parse_ignoring_derived_operators<'a>(
+    query_as_aql: &str,
+    quirks_mode: bool,
+    normalization: UnicodeNormalization,
+) -> Result<Disjunction<'a>> {
+    parse_with_quirks(
+        query_as_aql,
+        quirks_mode,
+        normalization,
+        &mut Vec::new(),
+        None,
+    )
+}
+
+fn parse_derived_operators(
+    definitions: &[DerivedOperatorDefinition],
+    normalization: UnicodeNormalization,
+) -> Result<DerivedOperatorMap> {
+    let mut result = DerivedOperatorMap::default();
+    for d in definitions {
+        let ops = DERIVED_OPERATOR_PARSER
+            .with(|p| p.parse(normalization, &d.definition))
+            .map_err(|e| {
+                let e = map_parse_error(e, &d.definition);
+                GraphAnnisError::AQLSemanticError(AQLError {
+                    desc: format!("Invalid definition of derived operator ~{}: {}", d.name, e),
+                    location: None,
+                })
+            })?;
+        if ops
+            .iter()
+            .any(|op| matches!(op, ast::BinaryOpSpec::Derived(_)))
+        {
+            return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                desc: format!(
+                    "Invalid definition of derived operator ~{}: derived operators can't be used in the definition.",
+                    d.name
+                ),
+                location: None,
+            }));
+        }
+        result.insert(d.name.clone(), ops);
+    }
+    Ok(result)
+}
+
+/// Parse the AQL query in quirks mode and return which rules of the quirks mode change the semantics of the query.
+pub fn quirks_report(
+    query_as_aql: &str,
+    normalization: UnicodeNormalization,
+) -> Result<Vec<QuirksRule>> {
+    let mut quirks = Vec::new();
+    parse_with_quirks(query_as_aql, true, normalization, &mut quirks, None)?;
+    // these rules are applied to the results of every query
+    quirks.push(QuirksRule::DocumentNameOrder);
+    quirks.push(QuirksRule::MatchIdEscaping);
+    Ok(quirks)
+}
+
+fn parse_with_quirks<'a>(
+    query_as_aql: &str,
+    quirks_mode: bool,
+    normalization: UnicodeNormalization,
+    quirks: &mut Vec<QuirksRule>,
+    derived_operators: Option<&DerivedOperatorMap>,
+) -> Result<Disjunction<'a>> {
+    let ast = AQL_PARSER.with(|p| p.parse(normalization, query_as_aql));
     match ast {
         Ok(ast) => {
             let offsets = get_line_offsets(query_as_aql);
@@ -383,6 +673,12 @@ pub fn parse<'a>(query_as_aql: &str, quirks_mode: bool) -> Result<Disjunction<'a
                 for conjunction in &ast {
                     for literal in conjunction {
                         if let ast::Literal::LegacyMetaSearch { spec, pos } = literal {
+                            quirks.push(QuirksRule::LegacyMetaSearch {
+                                location: Some(LineColumnRange {
+                                    start: get_line_and_column_for_pos(pos.start, &offsets),
+                                    end: Some(get_line_and_column_for_pos(pos.end, &offsets)),
+                                }),
+                            });
                             legacy_meta_search.push((spec.clone(), pos.clone()));
                         }
                     }
@@ -392,9 +688,23 @@ pub fn parse<'a>(query_as_aql: &str, quirks_mode: bool) -> Result<Disjunction<'a
             // map all conjunctions and its literals
             let mut alternatives: Vec<Conjunction> = Vec::new();
             let mut var_idx_offset = 0;
-            for c in ast {
+            for (alt_idx, c) in ast.into_iter().enumerate() {
+                let has_binary_operator =
+                    c.iter().any(|l| matches!(l, ast::Literal::BinaryOp { .. }));
                 // add the conjunction to the disjunction
-                let mut mapped = map_conjunction(c, &offsets, var_idx_offset, quirks_mode)?;
+                let mut mapped = map_conjunction(
+                    c,
+                    &offsets,
+                    var_idx_offset,
+                    quirks_mode,
+                    quirks,
+                    derived_operators,
+                )?;
+                if quirks_mode && has_binary_operator && mapped.num_of_nodes() > 2 {
+                    quirks.push(QuirksRule::LocalReflexivity {
+                        alternative: alt_idx + 1,
+                    });
+                }
 
                 if quirks_mode {
                     // apply the meta constraints from all conjunctions to conjunctions
@@ -412,27 +722,33 @@ pub fn parse<'a>(query_as_aql: &str, quirks_mode: bool) -> Result<Disjunction<'a
 
             Ok(Disjunction::new(alternatives))
         }
-        Err(e) => {
-            let mut desc = match e {
-                ParseError::InvalidToken { .. } => "Invalid token detected.",
-                ParseError::ExtraToken { .. } => "Extra token at end of query.",
-                ParseError::UnrecognizedToken { .. } => "Unexpected token in query.",
-                ParseError::UnrecognizedEOF { .. } => "Unexpected end of query.",
-                ParseError::User { error } => error,
-            }
-            .to_string();
-            let location = extract_location(&e, query_as_aql);
-            if let ParseError::UnrecognizedToken { expected, .. } = e {
-                if !expected.is_empty() {
-                    //TODO: map token regular expressions and IDs (like IDENT_NODE) to human readable descriptions
-                    desc.push_str(" Expected one of: ");
-                    desc.push_str(&expected.join(","));
-                }
-            }
-            Err(GraphAnnisError::AQLSyntaxError(AQLError { desc, location }))
+        Err(e) => Err(map_parse_error(e, query_as_aql)),
+    }
+}
+
+fn map_parse_error(
+    e: ParseError<usize, parser::Token<'_>, &'static str>,
+    query_as_aql: &str,
+) -> GraphAnnisError {
+    let mut desc = match e {
+        ParseError::InvalidToken { .. } => "Invalid token detected.",
+        ParseError::ExtraToken { .. } => "Extra token at end of query.",
+        ParseError::UnrecognizedToken { .. } => "Unexpected token in query.",
+        ParseError::UnrecognizedEOF { .. } => "Unexpected end of query.",
+        ParseError::User { error } => error,
+    }
+    .to_string();
+    let location = extract_location(&e, query_as_aql);
+    if let ParseError::UnrecognizedToken { expected, .. } = e {
+        if !expected.is_empty() {
+            //TODO: map token regular expressions and IDs (like IDENT_NODE) to human readable descriptions
+            desc.push_str(" Expected one of: ");
+            desc.push_str(&expected.join(","));
         }
     }
+    GraphAnnisError::AQLSyntaxError(AQLError { desc, location })
 }
+
 fn make_binary_operator_spec(
     op: ast::BinaryOpSpec,
     spec_left: NodeSearchSpec,
@@ -450,6 +766,18 @@ fn make_binary_operator_spec(
         ast::BinaryOpSpec::LeftAlignment(spec) => Box::new(spec),
         ast::BinaryOpSpec::RightAlignment(spec) => Box::new(spec),
         ast::BinaryOpSpec::IdenticalNode(spec) => Box::new(spec),
+        ast::BinaryOpSpec::SameSegment(spec) => Box::new(spec),
+        ast::BinaryOpSpec::SameText(spec) => Box::new(spec),
+        ast::BinaryOpSpec::DifferentDocument(spec) => Box::new(spec),
+        ast::BinaryOpSpec::Negated(op) => Box::new(NegatedOpSpec {
+            spec: make_binary_operator_spec(*op, spec_left, spec_right)?,
+        }),
+        ast::BinaryOpSpec::Derived(name) => {
+            return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                desc: format!("Derived operator ~{} has not been expanded.", name),
+                location: None,
+            }))
+        }
         ast::BinaryOpSpec::ValueComparison(cmp) => match cmp {
             ast::ComparisonOperator::Equal => Box::new(EqualValueSpec {
                 spec_left,
@@ -469,6 +797,8 @@ fn make_binary_operator_spec(
 fn make_unary_operator_spec(op: ast::UnaryOpSpec) -> Box<dyn UnaryOperatorSpec> {
     match op {
         ast::UnaryOpSpec::Arity(spec) => Box::new(spec),
+        ast::UnaryOpSpec::OutDegree(spec) => Box::new(spec),
+        ast::UnaryOpSpec::Length(spec) => Box::new(spec),
     }
 }
 
@@ -4,8 +4,8 @@ use crate::{
 };
 use graphannis_core::{
     dfs::CycleSafeDFS,
-    errors::ComponentTypeError,
-    graph::{storage::union::UnionEdgeContainer, ANNIS_NS},
+    errors::{ComponentTypeError, GraphAnnisCoreError},
+    graph::{storage::union::UnionEdgeContainer, ANNIS_NS, NODE_NAME_KEY},
     types::ComponentType,
     util::disk_collections::{DiskMap, EvictionStrategy},
 };
@@ -34,6 +34,14 @@ lazy_static! {
         ns: ANNIS_NS.into(),
         name: TOK.into(),
     });
+    pub static ref TOK_WHITESPACE_BEFORE_KEY: Arc<AnnoKey> = Arc::from(AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: TOK_WHITESPACE_BEFORE.into(),
+    });
+    pub static ref TOK_WHITESPACE_AFTER_KEY: Arc<AnnoKey> = Arc::from(AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: TOK_WHITESPACE_AFTER.into(),
+    });
 }
 
 /// Specifies the type of component of the annotation graph. The types of this enum carray certain semantics about the edges of the graph components their are used in.
@@ -530,27 +538,39 @@ impl ComponentType for AnnotationComponentType {
         Ok(())
     }
 
+    fn number_of_reindexed_nodes(index: &Self::UpdateGraphIndex) -> usize {
+        index.invalid_nodes.iter().count()
+    }
+
     fn apply_update_graph_index(
         mut index: Self::UpdateGraphIndex,
         graph: &mut AnnotationGraph,
     ) -> std::result::Result<(), ComponentTypeError> {
         index.invalid_nodes.compact()?;
 
+        // Optimize all order components, including the ones for segmentations. Ordering chains are converted to a
+        // linear graph storage, which stores the position of each node in its chain, so the distance between two
+        // nodes can be checked without traversing the chain.
+        // This also makes re-indexing the inherited coverage component fast.
+        for order_component in
+            graph.get_all_components(Some(AnnotationComponentType::Ordering), None)
+        {
+            let order_stats_exist = graph
+                .get_graphstorage(&order_component)
+                .map(|gs_order| gs_order.get_statistics().is_some())
+                .unwrap_or(false);
+            if !order_stats_exist {
+                graph.calculate_component_statistics(&order_component)?;
+            }
+            graph.optimize_gs_impl(&order_component)?;
+        }
+
         // Re-index the inherited coverage component.
-        // To make this operation fast, we need to optimize the order component first
         let order_component = AnnotationComponent::new(
             AnnotationComponentType::Ordering,
             ANNIS_NS.into(),
             "".into(),
         );
-        let order_stats_exist = graph
-            .get_graphstorage(&order_component)
-            .map(|gs_order| gs_order.get_statistics().is_some())
-            .unwrap_or(false);
-        if !order_stats_exist {
-            graph.calculate_component_statistics(&order_component)?;
-        }
-        graph.optimize_gs_impl(&order_component)?;
         if let Some(gs_order) = graph.get_graphstorage(&order_component) {
             index.reindex_inherited_coverage(graph, gs_order)?;
         }
@@ -579,6 +599,39 @@ impl ComponentType for AnnotationComponentType {
     }
 }
 
+/// Calculate the components which are derived from the other components of the `graph` again for all nodes.
+///
+/// These are the `LeftToken` and `RightToken` components and the inherited coverage. They are normally
+/// updated incrementally when applying an update, but this allows to create them for a graph that only
+/// has the minimal components or to repair them.
+/// Returns the number of nodes for which the derived components have been calculated.
+pub fn rebuild_derived_components(graph: &mut AnnotationGraph) -> crate::errors::Result<usize> {
+    graph.ensure_loaded_all()?;
+
+    let mut index = AnnotationComponentType::init_update_graph_index(graph)
+        .map_err(GraphAnnisCoreError::from)?;
+    for m in graph.get_node_annos().exact_anno_search(
+        Some(&NODE_NAME_KEY.ns),
+        &NODE_NAME_KEY.name,
+        None.into(),
+    ) {
+        index
+            .invalid_nodes
+            .insert(m.node, true)
+            .map_err(GraphAnnisCoreError::from)?;
+    }
+    let reindexed_nodes = AnnotationComponentType::number_of_reindexed_nodes(&index);
+    AnnotationComponentType::apply_update_graph_index(index, graph)
+        .map_err(GraphAnnisCoreError::from)?;
+
+    for c in AnnotationComponentType::update_graph_index_components(graph) {
+        graph.calculate_component_statistics(&c)?;
+        graph.optimize_gs_impl(&c)?;
+    }
+
+    Ok(reindexed_nodes)
+}
+
 impl fmt::Display for AnnotationComponentType {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         fmt::Debug::fmt(self, f)
@@ -30,7 +30,7 @@ impl UnaryOperatorSpec for AritySpec {
         result
     }
 
-    fn create_operator(&self, db: &AnnotationGraph) -> Option<Box<dyn UnaryOperator>> {
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn UnaryOperator + 'a>> {
         // collect all relevant graph storages
         let mut graphstorages = Vec::default();
 
@@ -90,7 +90,7 @@ impl UnaryOperator for ArityOperator {
             let mut max_sel: f64 = 0.0;
 
             for gs in self.graphstorages.iter() {
-                if let Some(stats) = gs.get_statistics() {
+                if let Some(stats) = gs.get_statistics_or_estimate() {
                     if min_dist <= stats.max_fan_out {
                         min_matches_any = true;
                     }
@@ -0,0 +1,119 @@
+use crate::annis::db::aql::model::AnnotationComponentType;
+use crate::annis::operator::{BinaryOperator, BinaryOperatorSpec, EstimationType};
+use crate::{
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    graph::ANNIS_NS,
+    types::{Component, NodeID},
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Specification for the `_diffdoc_` operator, which is true if two nodes belong to different documents.
+///
+/// The documents of a node are the (sub-) corpus nodes it is directly part of. Nodes without a document never match.
+#[derive(Clone, Debug, PartialOrd, Ord, Hash, PartialEq, Eq)]
+pub struct DifferentDocumentSpec;
+
+lazy_static! {
+    static ref COMPONENT_PART_OF: Component<AnnotationComponentType> =
+        Component::new(AnnotationComponentType::PartOf, ANNIS_NS.into(), "".into());
+}
+
+impl BinaryOperatorSpec for DifferentDocumentSpec {
+    fn necessary_components(
+        &self,
+        _db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        let mut v = HashSet::new();
+        v.insert(COMPONENT_PART_OF.clone());
+        v
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
+        Some(Box::new(DifferentDocument {
+            gs_part_of: db.get_graphstorage(&COMPONENT_PART_OF)?,
+            db,
+        }))
+    }
+
+    fn is_filter_only(&self) -> bool {
+        // nearly all nodes of the corpus are part of another document, retrieving them is as expensive as
+        // comparing all combinations of both operands
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct DifferentDocument<'a> {
+    gs_part_of: Arc<dyn GraphStorage>,
+    db: &'a AnnotationGraph,
+}
+
+impl<'a> DifferentDocument<'a> {
+    fn documents(&self, node: NodeID) -> Vec<NodeID> {
+        let mut documents: Vec<NodeID> = self.gs_part_of.get_outgoing_edges(node).collect();
+        documents.sort_unstable();
+        documents
+    }
+
+    fn is_different(&self, lhs_documents: &[NodeID], rhs: NodeID) -> bool {
+        let rhs_documents = self.documents(rhs);
+        !lhs_documents.is_empty()
+            && !rhs_documents.is_empty()
+            && !rhs_documents
+                .iter()
+                .any(|d| lhs_documents.binary_search(d).is_ok())
+    }
+}
+
+impl<'a> std::fmt::Display for DifferentDocument<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "_diffdoc_")
+    }
+}
+
+impl<'a> BinaryOperator for DifferentDocument<'a> {
+    fn retrieve_matches(&self, _lhs: &Match) -> Box<dyn Iterator<Item = Match>> {
+        // the planner only uses this operator as filter
+        Box::new(std::iter::empty())
+    }
+
+    fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
+        self.is_different(&self.documents(lhs.node), rhs.node)
+    }
+
+    fn is_reflexive(&self) -> bool {
+        false
+    }
+
+    fn get_inverse_operator<'b>(
+        &self,
+        graph: &'b AnnotationGraph,
+    ) -> Option<Box<dyn BinaryOperator + 'b>> {
+        // the operator is symmetric
+        Some(Box::new(DifferentDocument {
+            gs_part_of: self.gs_part_of.clone(),
+            db: graph,
+        }))
+    }
+
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        let number_of_documents = self
+            .db
+            .get_node_annos()
+            .number_of_annotations_by_name(Some(ANNIS_NS), "doc");
+        if number_of_documents > 0 {
+            // assume that the nodes are distributed equally over the documents
+            EstimationType::SELECTIVITY(1.0 - (1.0 / number_of_documents as f64))
+        } else {
+            EstimationType::SELECTIVITY(1.0)
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::annis::db::aql::{model::AnnotationComponentType, operators::RangeSpec};
 use crate::annis::operator::{
-    BinaryOperator, BinaryOperatorSpec, EdgeAnnoSearchSpec, EstimationType,
+    BinaryOperator, BinaryOperatorSpec, EdgeAnnoSearchSpec, EstimationType, ReachabilityCache,
 };
 use crate::graph::{GraphStatistic, GraphStorage, Match};
 use crate::AnnotationGraph;
@@ -9,7 +9,7 @@ use graphannis_core::{
     graph::{ANNIS_NS, DEFAULT_ANNO_KEY, NODE_TYPE_KEY},
     types::{Component, Edge, NodeID},
 };
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeSet, HashSet, VecDeque};
 use std::iter::FromIterator;
 use std::sync::Arc;
 
@@ -20,6 +20,8 @@ struct BaseEdgeOpSpec {
     pub edge_anno: Option<EdgeAnnoSearchSpec>,
     pub is_reflexive: bool,
     pub op_str: Option<String>,
+    /// Create the operator for the inverse direction of the edges.
+    pub inverse: bool,
 }
 
 struct BaseEdgeOp {
@@ -27,16 +29,22 @@ struct BaseEdgeOp {
     spec: BaseEdgeOpSpec,
     max_nodes_estimate: usize,
     inverse: bool,
+    cache: Option<Arc<ReachabilityCache>>,
 }
 
 impl BaseEdgeOp {
-    pub fn new(db: &AnnotationGraph, spec: BaseEdgeOpSpec) -> Option<BaseEdgeOp> {
+    pub fn new(
+        db: &AnnotationGraph,
+        spec: BaseEdgeOpSpec,
+        cache: Option<Arc<ReachabilityCache>>,
+    ) -> Option<BaseEdgeOp> {
         let mut gs: Vec<Arc<dyn GraphStorage>> = Vec::new();
         for c in &spec.components {
             gs.push(db.get_graphstorage(c)?);
         }
         Some(BaseEdgeOp {
             gs,
+            inverse: spec.inverse,
             spec,
             max_nodes_estimate: db.get_node_annos().guess_max_count(
                 Some(&NODE_TYPE_KEY.ns),
@@ -44,9 +52,34 @@ impl BaseEdgeOp {
                 "node",
                 "node",
             ),
-            inverse: false,
+            cache,
         })
     }
+
+    fn are_connected(
+        &self,
+        gs: &Arc<dyn GraphStorage>,
+        source: NodeID,
+        targets: &[NodeID],
+    ) -> Vec<bool> {
+        let min_dist = self.spec.dist.min_dist();
+        let max_dist = self.spec.dist.max_dist();
+        if let Some(cache) = &self.cache {
+            cache.are_connected(gs, source, targets, min_dist, max_dist)
+        } else {
+            gs.are_connected(source, targets, min_dist, max_dist)
+        }
+    }
+
+    fn is_connected(&self, gs: &Arc<dyn GraphStorage>, source: NodeID, target: NodeID) -> bool {
+        let min_dist = self.spec.dist.min_dist();
+        let max_dist = self.spec.dist.max_dist();
+        if let Some(cache) = &self.cache {
+            cache.is_connected(gs, source, target, min_dist, max_dist)
+        } else {
+            gs.is_connected(source, target, min_dist, max_dist)
+        }
+    }
 }
 
 impl BinaryOperatorSpec for BaseEdgeOpSpec {
@@ -58,7 +91,7 @@ impl BinaryOperatorSpec for BaseEdgeOpSpec {
     }
 
     fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
-        let optional_op = BaseEdgeOp::new(db, self.clone());
+        let optional_op = BaseEdgeOp::new(db, self.clone(), None);
         if let Some(op) = optional_op {
             Some(Box::new(op))
         } else {
@@ -66,6 +99,15 @@ impl BinaryOperatorSpec for BaseEdgeOpSpec {
         }
     }
 
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        let op = BaseEdgeOp::new(db, self.clone(), Some(cache.clone()))?;
+        Some(Box::new(op))
+    }
+
     fn get_edge_anno_spec(&self) -> Option<EdgeAnnoSearchSpec> {
         self.edge_anno.clone()
     }
@@ -177,11 +219,37 @@ fn check_edge_annotation(
             }
             false
         }
+        Some(EdgeAnnoSearchSpec::ExactValueAnyName { val }) => gs
+            .get_anno_storage()
+            .get_annotations_for_item(&Edge { source, target })
+            .iter()
+            .any(|a| val.as_str() == a.val.as_str()),
+        Some(EdgeAnnoSearchSpec::RegexValueAnyName { val }) => {
+            let full_match_pattern = graphannis_core::util::regex_full_match(&val);
+            if let Ok(re) = regex::Regex::new(&full_match_pattern) {
+                gs.get_anno_storage()
+                    .get_annotations_for_item(&Edge { source, target })
+                    .iter()
+                    .any(|a| re.is_match(&a.val))
+            } else {
+                false
+            }
+        }
         None => true,
     }
 }
 
-impl BaseEdgeOp {}
+/// Check that the regular expression of an edge annotation search is valid.
+/// Invalid patterns would otherwise silently never match any edge.
+fn validate_edge_anno(edge_anno: &Option<EdgeAnnoSearchSpec>) -> std::result::Result<(), String> {
+    if let Some(pattern) = edge_anno.as_ref().and_then(|a| a.regex_pattern()) {
+        let full_match_pattern = graphannis_core::util::regex_full_match(pattern);
+        if let Err(e) = regex::Regex::new(&full_match_pattern) {
+            return Err(format!("/{}/ -> {}", pattern, e));
+        }
+    }
+    Ok(())
+}
 
 impl std::fmt::Display for BaseEdgeOp {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -308,32 +376,41 @@ impl BinaryOperator for BaseEdgeOp {
     fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
         for e in &self.gs {
             if self.inverse {
-                if e.is_connected(
-                    rhs.node,
-                    lhs.node,
-                    self.spec.dist.min_dist(),
-                    self.spec.dist.max_dist(),
-                ) && check_edge_annotation(&self.spec.edge_anno, e.as_ref(), rhs.node, lhs.node)
+                if self.is_connected(e, rhs.node, lhs.node)
+                    && check_edge_annotation(&self.spec.edge_anno, e.as_ref(), rhs.node, lhs.node)
                 {
                     return true;
                 }
-            } else if e.is_connected(
-                lhs.node,
-                rhs.node,
-                self.spec.dist.min_dist(),
-                self.spec.dist.max_dist(),
-            ) && check_edge_annotation(
-                &self.spec.edge_anno,
-                e.as_ref(),
-                lhs.node,
-                rhs.node,
-            ) {
+            } else if self.is_connected(e, lhs.node, rhs.node)
+                && check_edge_annotation(&self.spec.edge_anno, e.as_ref(), lhs.node, rhs.node)
+            {
                 return true;
             }
         }
         false
     }
 
+    fn filter_matches(&self, lhs: &Match, rhs: &[Match]) -> Vec<bool> {
+        if self.inverse {
+            // each candidate is the source of the path, which can't be checked in a batch
+            return rhs.iter().map(|m| self.filter_match(lhs, m)).collect();
+        }
+        let targets: Vec<NodeID> = rhs.iter().map(|m| m.node).collect();
+        let mut result = vec![false; targets.len()];
+        for e in &self.gs {
+            let connected = self.are_connected(e, lhs.node, &targets);
+            for (i, is_connected) in connected.into_iter().enumerate() {
+                if is_connected
+                    && !result[i]
+                    && check_edge_annotation(&self.spec.edge_anno, e.as_ref(), lhs.node, targets[i])
+                {
+                    result[i] = true;
+                }
+            }
+        }
+        result
+    }
+
     fn is_reflexive(&self) -> bool {
         self.spec.is_reflexive
     }
@@ -345,9 +422,20 @@ impl BinaryOperator for BaseEdgeOp {
             if !g.inverse_has_same_cost() {
                 return None;
             }
-            if let Some(stat) = g.get_statistics() {
+            if let Some(stat) = g.get_statistics_or_estimate() {
                 // If input and output estimations are too different, also don't provide a more costly inverse operator
-                if stat.inverse_fan_out_99_percentile > stat.fan_out_99_percentile {
+                let (current_fan_out, inverse_fan_out) = if self.inverse {
+                    (
+                        stat.inverse_fan_out_99_percentile,
+                        stat.fan_out_99_percentile,
+                    )
+                } else {
+                    (
+                        stat.fan_out_99_percentile,
+                        stat.inverse_fan_out_99_percentile,
+                    )
+                };
+                if inverse_fan_out > current_fan_out {
                     return None;
                 }
             }
@@ -357,6 +445,7 @@ impl BinaryOperator for BaseEdgeOp {
             spec: self.spec.clone(),
             max_nodes_estimate: self.max_nodes_estimate,
             inverse: !self.inverse,
+            cache: self.cache.clone(),
         };
         Some(Box::new(edge_op))
     }
@@ -367,7 +456,9 @@ impl BinaryOperator for BaseEdgeOp {
             return EstimationType::SELECTIVITY(0.0);
         }
 
-        let max_nodes: f64 = self.max_nodes_estimate as f64;
+        // The number of nodes is unknown if the annotation statistics have not been calculated yet,
+        // make sure not to divide by zero in this case.
+        let max_nodes: f64 = std::cmp::max(1, self.max_nodes_estimate) as f64;
 
         let mut worst_sel: f64 = 0.0;
 
@@ -376,8 +467,8 @@ impl BinaryOperator for BaseEdgeOp {
 
             let mut gs_selectivity = 0.01;
 
-            if let Some(stats) = g.get_statistics() {
-                let stats: &GraphStatistic = stats;
+            if let Some(stats) = g.get_statistics_or_estimate() {
+                let stats: GraphStatistic = stats;
                 if stats.cyclic {
                     // can get all other nodes
                     return EstimationType::SELECTIVITY(1.0);
@@ -478,6 +569,10 @@ impl BinaryOperator for BaseEdgeOp {
                                     val,
                                 )
                         }
+                        EdgeAnnoSearchSpec::ExactValueAnyName { .. }
+                        | EdgeAnnoSearchSpec::RegexValueAnyName { .. } => {
+                            edge_anno.guess_max_count(anno_storage)
+                        }
                     };
                     let g_sel: f64 = (guessed_count as f64) / (num_of_annos as f64);
                     if g_sel > worst_sel {
@@ -492,8 +587,87 @@ impl BinaryOperator for BaseEdgeOp {
     }
 }
 
+/// Split an operator component name like `syntax::edge` into the optional layer and the name.
+pub(crate) fn split_layer_qualified_name(qualified_name: &str) -> (Option<String>, String) {
+    if let Some(pos) = qualified_name.find("::") {
+        (
+            Some(qualified_name[..pos].to_string()),
+            qualified_name[pos + "::".len()..].to_string(),
+        )
+    } else {
+        (None, qualified_name.to_string())
+    }
+}
+
+/// Get all components of the given type and name, restricted to a layer if it is given.
+pub(super) fn components_in_layer(
+    db: &AnnotationGraph,
+    ctype: AnnotationComponentType,
+    layer: &Option<String>,
+    name: &str,
+) -> Vec<Component<AnnotationComponentType>> {
+    let mut components = db.get_all_components(Some(ctype), Some(name));
+    if let Some(layer) = layer {
+        components.retain(|c| c.layer == layer.as_str());
+    }
+    components
+}
+
+/// Return the qualified name of the component if there is no component with the given type, layer and name.
+fn missing_component(
+    db: &AnnotationGraph,
+    ctype: AnnotationComponentType,
+    layer: &Option<String>,
+    name: &str,
+) -> Vec<String> {
+    if components_in_layer(db, ctype.clone(), layer, name).is_empty() {
+        vec![format!("{}/{}", ctype, qualified_name(layer, name))]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Check that a layer given for the operator is the layer of at least one component with the given type and name.
+fn validate_layer(
+    db: &AnnotationGraph,
+    ctype: AnnotationComponentType,
+    layer: &Option<String>,
+    name: &str,
+) -> std::result::Result<(), String> {
+    if let Some(layer) = layer {
+        let components = db.get_all_components(Some(ctype.clone()), Some(name));
+        if !components.iter().any(|c| c.layer == layer.as_str()) {
+            let available: BTreeSet<&str> = components.iter().map(|c| c.layer.as_str()).collect();
+            let available: Vec<&str> = available.into_iter().collect();
+            return Err(format!(
+                "There is no {} component \"{}\" in layer \"{}\", available layers: {}",
+                ctype,
+                name,
+                layer,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Format the component name of an operator, including its layer if it is given.
+fn qualified_name(layer: &Option<String>, name: &str) -> String {
+    if let Some(layer) = layer {
+        format!("{}::{}", layer, name)
+    } else {
+        name.to_string()
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct DominanceSpec {
+    /// If given, only components of this layer are used.
+    pub layer: Option<String>,
     pub name: String,
     pub dist: RangeSpec,
     pub edge_anno: Option<EdgeAnnoSearchSpec>,
@@ -504,32 +678,83 @@ impl BinaryOperatorSpec for DominanceSpec {
         &self,
         db: &AnnotationGraph,
     ) -> HashSet<Component<AnnotationComponentType>> {
-        HashSet::from_iter(
-            db.get_all_components(Some(AnnotationComponentType::Dominance), Some(&self.name)),
-        )
+        HashSet::from_iter(components_in_layer(
+            db,
+            AnnotationComponentType::Dominance,
+            &self.layer,
+            &self.name,
+        ))
     }
 
     fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
-        let components =
-            db.get_all_components(Some(AnnotationComponentType::Dominance), Some(&self.name));
-        let op_str = if self.name.is_empty() {
+        self.base_spec(db).create_operator(db)
+    }
+
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        self.base_spec(db).create_operator_with_cache(db, cache)
+    }
+
+    fn missing_components(&self, db: &AnnotationGraph) -> Vec<String> {
+        missing_component(
+            db,
+            AnnotationComponentType::Dominance,
+            &self.layer,
+            &self.name,
+        )
+    }
+
+    fn validate(&self, db: &AnnotationGraph) -> std::result::Result<(), String> {
+        validate_layer(
+            db,
+            AnnotationComponentType::Dominance,
+            &self.layer,
+            &self.name,
+        )?;
+        validate_edge_anno(&self.edge_anno)
+    }
+
+    fn contradiction(&self) -> Option<String> {
+        self.dist.contradiction()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.dist == RangeSpec::Unbound
+    }
+}
+
+impl DominanceSpec {
+    fn base_spec(&self, db: &AnnotationGraph) -> BaseEdgeOpSpec {
+        let components = components_in_layer(
+            db,
+            AnnotationComponentType::Dominance,
+            &self.layer,
+            &self.name,
+        );
+        let name = qualified_name(&self.layer, &self.name);
+        let op_str = if name.is_empty() {
             String::from(">")
         } else {
-            format!(">{} ", &self.name)
+            format!(">{} ", &name)
         };
-        let base = BaseEdgeOpSpec {
+        BaseEdgeOpSpec {
             op_str: Some(op_str),
             components,
             dist: self.dist.clone(),
             edge_anno: self.edge_anno.clone(),
             is_reflexive: true,
-        };
-        base.create_operator(db)
+            inverse: false,
+        }
     }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PointingSpec {
+    /// If given, only components of this layer are used.
+    pub layer: Option<String>,
     pub name: String,
     pub dist: RangeSpec,
     pub edge_anno: Option<EdgeAnnoSearchSpec>,
@@ -540,34 +765,85 @@ impl BinaryOperatorSpec for PointingSpec {
         &self,
         db: &AnnotationGraph,
     ) -> HashSet<Component<AnnotationComponentType>> {
-        HashSet::from_iter(
-            db.get_all_components(Some(AnnotationComponentType::Pointing), Some(&self.name)),
-        )
+        HashSet::from_iter(components_in_layer(
+            db,
+            AnnotationComponentType::Pointing,
+            &self.layer,
+            &self.name,
+        ))
     }
 
     fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
-        let components =
-            db.get_all_components(Some(AnnotationComponentType::Pointing), Some(&self.name));
-        let op_str = if self.name.is_empty() {
+        self.base_spec(db).create_operator(db)
+    }
+
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        self.base_spec(db).create_operator_with_cache(db, cache)
+    }
+
+    fn missing_components(&self, db: &AnnotationGraph) -> Vec<String> {
+        missing_component(
+            db,
+            AnnotationComponentType::Pointing,
+            &self.layer,
+            &self.name,
+        )
+    }
+
+    fn validate(&self, db: &AnnotationGraph) -> std::result::Result<(), String> {
+        validate_layer(
+            db,
+            AnnotationComponentType::Pointing,
+            &self.layer,
+            &self.name,
+        )?;
+        validate_edge_anno(&self.edge_anno)
+    }
+
+    fn contradiction(&self) -> Option<String> {
+        self.dist.contradiction()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.dist == RangeSpec::Unbound
+    }
+}
+
+impl PointingSpec {
+    fn base_spec(&self, db: &AnnotationGraph) -> BaseEdgeOpSpec {
+        let components = components_in_layer(
+            db,
+            AnnotationComponentType::Pointing,
+            &self.layer,
+            &self.name,
+        );
+        let name = qualified_name(&self.layer, &self.name);
+        let op_str = if name.is_empty() {
             String::from("->")
         } else {
-            format!("->{} ", self.name)
+            format!("->{} ", name)
         };
 
-        let base = BaseEdgeOpSpec {
+        BaseEdgeOpSpec {
             components,
             dist: self.dist.clone(),
             edge_anno: self.edge_anno.clone(),
             is_reflexive: true,
             op_str: Some(op_str),
-        };
-        base.create_operator(db)
+            inverse: false,
+        }
     }
 }
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct PartOfSubCorpusSpec {
     pub dist: RangeSpec,
+    /// If `true`, the left operand is the (sub-) corpus and the right operand the node that is part of it (`@^`).
+    pub inverse: bool,
 }
 
 impl BinaryOperatorSpec for PartOfSubCorpusSpec {
@@ -585,19 +861,40 @@ impl BinaryOperatorSpec for PartOfSubCorpusSpec {
     }
 
     fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
+        self.base_spec().create_operator(db)
+    }
+
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        self.base_spec().create_operator_with_cache(db, cache)
+    }
+
+    fn contradiction(&self) -> Option<String> {
+        self.dist.contradiction()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.dist == RangeSpec::Unbound
+    }
+}
+
+impl PartOfSubCorpusSpec {
+    fn base_spec(&self) -> BaseEdgeOpSpec {
         let components = vec![Component::new(
             AnnotationComponentType::PartOf,
             ANNIS_NS.into(),
             "".into(),
         )];
-        let base = BaseEdgeOpSpec {
+        BaseEdgeOpSpec {
             op_str: Some(String::from("@")),
             components,
             dist: self.dist.clone(),
             edge_anno: None,
             is_reflexive: false,
-        };
-
-        base.create_operator(db)
+            inverse: self.inverse,
+        }
     }
 }
@@ -64,22 +64,25 @@ impl<'a> std::fmt::Display for EqualValue<'a> {
 }
 
 impl<'a> EqualValue<'a> {
-    fn value_for_match(&self, m: &Match, spec: &NodeSearchSpec) -> Option<Cow<str>> {
+    /// Get all values of the match. Nodes can have multiple values for the same annotation key.
+    fn values_for_match(&self, m: &Match, spec: &NodeSearchSpec) -> Vec<Cow<str>> {
         match spec {
             NodeSearchSpec::ExactValue { .. }
             | NodeSearchSpec::NotExactValue { .. }
+            | NodeSearchSpec::ExactValueSet { .. }
             | NodeSearchSpec::RegexValue { .. }
             | NodeSearchSpec::NotRegexValue { .. } => {
-                self.node_annos.get_value_for_item(&m.node, &m.anno_key)
+                self.node_annos.get_values_for_item(&m.node, &m.anno_key)
             }
             NodeSearchSpec::AnyToken
             | NodeSearchSpec::ExactTokenValue { .. }
             | NodeSearchSpec::NotExactTokenValue { .. }
             | NodeSearchSpec::RegexTokenValue { .. }
             | NodeSearchSpec::NotRegexTokenValue { .. } => {
-                self.node_annos.get_value_for_item(&m.node, &TOKEN_KEY)
+                self.node_annos.get_values_for_item(&m.node, &TOKEN_KEY)
             }
-            NodeSearchSpec::AnyNode => None,
+            NodeSearchSpec::AnyNode => Vec::new(),
+            NodeSearchSpec::InComponent { base, .. } => self.values_for_match(m, base),
         }
     }
 
@@ -87,6 +90,7 @@ impl<'a> EqualValue<'a> {
         match spec {
             NodeSearchSpec::ExactValue { ns, name, .. }
             | NodeSearchSpec::NotExactValue { ns, name, .. }
+            | NodeSearchSpec::ExactValueSet { ns, name, .. }
             | NodeSearchSpec::RegexValue { ns, name, .. }
             | NodeSearchSpec::NotRegexValue { ns, name, .. } => {
                 Some((ns.as_ref().map(String::as_str), &name))
@@ -101,6 +105,7 @@ impl<'a> EqualValue<'a> {
                 Some((ns, name))
             }
             NodeSearchSpec::AnyNode => None,
+            NodeSearchSpec::InComponent { base, .. } => EqualValue::anno_def_for_spec(base),
         }
     }
 }
@@ -108,11 +113,19 @@ impl<'a> EqualValue<'a> {
 impl<'a> BinaryOperator for EqualValue<'a> {
     fn retrieve_matches(&self, lhs: &Match) -> Box<dyn Iterator<Item = Match>> {
         let lhs = lhs.clone();
-        if let Some(lhs_val) = self.value_for_match(&lhs, &self.spec_left) {
+        let lhs_vals = self.values_for_match(&lhs, &self.spec_left);
+        if !lhs_vals.is_empty() {
             let val_search: ValueSearch<&str> = if self.negated {
-                ValueSearch::NotSome(&lhs_val)
+                if lhs_vals.len() == 1 {
+                    ValueSearch::NotSome(&lhs_vals[0])
+                } else {
+                    // any value is different to at least one of the multiple values of the LHS
+                    ValueSearch::Any
+                }
+            } else if lhs_vals.len() == 1 {
+                ValueSearch::Some(&lhs_vals[0])
             } else {
-                ValueSearch::Some(&lhs_val)
+                ValueSearch::AnyOf(lhs_vals.iter().map(|v| v.as_ref()).collect())
             };
 
             if let Some((ns, name)) = EqualValue::anno_def_for_spec(&self.spec_right) {
@@ -127,18 +140,19 @@ impl<'a> BinaryOperator for EqualValue<'a> {
     }
 
     fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
-        let lhs_val = self.value_for_match(lhs, &self.spec_left);
-        let rhs_val = self.value_for_match(rhs, &self.spec_right);
+        let lhs_vals = self.values_for_match(lhs, &self.spec_left);
+        let rhs_vals = self.values_for_match(rhs, &self.spec_right);
 
-        if let (Some(lhs_val), Some(rhs_val)) = (lhs_val, rhs_val) {
-            if self.negated {
-                lhs_val != rhs_val
-            } else {
-                lhs_val == rhs_val
-            }
-        } else {
-            false
-        }
+        // true if any combination of the values fulfills the condition
+        lhs_vals.iter().any(|lhs_val| {
+            rhs_vals.iter().any(|rhs_val| {
+                if self.negated {
+                    lhs_val != rhs_val
+                } else {
+                    lhs_val == rhs_val
+                }
+            })
+        })
     }
 
     fn estimation_type(&self) -> EstimationType {
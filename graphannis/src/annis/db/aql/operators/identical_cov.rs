@@ -147,8 +147,16 @@ impl<'a> BinaryOperator for IdenticalCoverage<'a> {
         }))
     }
 
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(order_stats) = self.gs_order.get_statistics() {
+        if let Some(order_stats) = self.gs_order.get_statistics_or_estimate() {
             let num_of_token = order_stats.nodes as f64;
 
             // Assume two nodes have same identical coverage if they have the same
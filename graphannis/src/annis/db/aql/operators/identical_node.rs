@@ -23,6 +23,10 @@ impl BinaryOperatorSpec for IdenticalNodeSpec {
     ) -> Option<Box<dyn BinaryOperator + 'a>> {
         Some(Box::new(IdenticalNode {}))
     }
+
+    fn is_identity(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -46,6 +50,10 @@ impl BinaryOperator for IdenticalNode {
         lhs.node == rhs.node
     }
 
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
         EstimationType::MIN
     }
@@ -56,4 +64,8 @@ impl BinaryOperator for IdenticalNode {
     ) -> Option<Box<dyn BinaryOperator + 'a>> {
         Some(Box::new(self.clone()))
     }
+
+    fn is_commutative(&self) -> bool {
+        true
+    }
 }
@@ -90,7 +90,7 @@ impl<'a> BinaryOperator for Inclusion<'a> {
                             .filter(move |n| {
                                 // right-aligned token of candidate
                                 let mut end_n =
-                                    self.tok_helper.get_gs_right_token_().get_outgoing_edges(*n);
+                                    self.tok_helper.get_gs_right_token().get_outgoing_edges(*n);
                                 if let Some(end_n) = end_n.next() {
                                     // path between right-most tokens exists in ORDERING component
                                     // and has maximum length l
@@ -147,17 +147,23 @@ impl<'a> BinaryOperator for Inclusion<'a> {
         false
     }
 
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
         if let (Some(stats_order), Some(stats_left)) = (
-            self.gs_order.get_statistics(),
-            self.tok_helper.get_gs_left_token().get_statistics(),
+            self.gs_order.get_statistics_or_estimate(),
+            self.tok_helper
+                .get_gs_left_token()
+                .get_statistics_or_estimate(),
         ) {
             let mut sum_cov_nodes = 0;
             let mut sum_included = 0;
 
             let num_of_token = stats_order.nodes as f64;
             for gs_cov in self.tok_helper.get_gs_coverage().iter() {
-                if let Some(stats_cov) = gs_cov.get_statistics() {
+                if let Some(stats_cov) = gs_cov.get_statistics_or_estimate() {
                     sum_cov_nodes += stats_cov.nodes;
 
                     let covered_token_per_node = stats_cov.fan_out_99_percentile;
@@ -96,8 +96,20 @@ impl<'a> BinaryOperator for LeftAlignment<'a> {
         Some(Box::new(LeftAlignment { tok_helper }))
     }
 
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_left) = self.tok_helper.get_gs_left_token().get_statistics() {
+        if let Some(stats_left) = self
+            .tok_helper
+            .get_gs_left_token()
+            .get_statistics_or_estimate()
+        {
             let aligned_nodes_per_token: f64 = stats_left.inverse_fan_out_99_percentile as f64;
             return EstimationType::SELECTIVITY(
                 aligned_nodes_per_token / (stats_left.nodes as f64),
@@ -0,0 +1,128 @@
+use crate::annis::db::aql::model::TOKEN_KEY;
+use crate::annis::db::token_helper::{self, TokenHelper};
+use crate::annis::operator::EstimationType;
+use crate::annis::{
+    db::aql::model::AnnotationComponentType,
+    operator::{UnaryOperator, UnaryOperatorSpec},
+};
+use crate::{
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    graph::ANNIS_NS,
+    types::{Component, NodeID},
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// The unit in which the length of the covered text is measured.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LengthUnit {
+    /// Number of characters of the covered token values, without the whitespace between the tokens.
+    Characters,
+    /// Number of covered tokens.
+    Tokens,
+}
+
+/// Specification for the `:len` and `:tokenlen` operators, which are true if the length of the text covered by
+/// a node is in the allowed range, e.g. `#1:len>=10` or `#1:tokenlen=2,4`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LengthSpec {
+    pub unit: LengthUnit,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+lazy_static! {
+    static ref COMPONENT_ORDER: Component<AnnotationComponentType> = {
+        Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        )
+    };
+}
+
+impl std::fmt::Display for LengthSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.unit {
+            LengthUnit::Characters => write!(f, ":len")?,
+            LengthUnit::Tokens => write!(f, ":tokenlen")?,
+        }
+        match self.max {
+            Some(max) if max == self.min => write!(f, "={}", max),
+            Some(max) if self.min == 0 => write!(f, "<={}", max),
+            Some(max) => write!(f, "={},{}", self.min, max),
+            None => write!(f, ">={}", self.min),
+        }
+    }
+}
+
+impl UnaryOperatorSpec for LengthSpec {
+    fn necessary_components(
+        &self,
+        db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        let mut result = token_helper::necessary_components(db);
+        result.insert(COMPONENT_ORDER.clone());
+        result
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn UnaryOperator + 'a>> {
+        Some(Box::new(LengthOperator {
+            tok_helper: TokenHelper::new(db)?,
+            gs_order: db.get_graphstorage(&COMPONENT_ORDER)?,
+            db,
+            spec: self.clone(),
+        }))
+    }
+}
+
+struct LengthOperator<'a> {
+    tok_helper: TokenHelper<'a>,
+    gs_order: Arc<dyn GraphStorage>,
+    db: &'a AnnotationGraph,
+    spec: LengthSpec,
+}
+
+impl<'a> LengthOperator<'a> {
+    fn length(&self, covered_token: &[NodeID]) -> usize {
+        match self.spec.unit {
+            LengthUnit::Tokens => covered_token.len(),
+            LengthUnit::Characters => covered_token
+                .iter()
+                .filter_map(|t| self.db.get_node_annos().get_value_for_item(t, &TOKEN_KEY))
+                .map(|val| val.chars().count())
+                .sum(),
+        }
+    }
+}
+
+impl<'a> std::fmt::Display for LengthOperator<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+impl<'a> UnaryOperator for LengthOperator<'a> {
+    fn filter_match(&self, m: &Match) -> bool {
+        let covered_token = self
+            .tok_helper
+            .covered_token(m.node, self.gs_order.as_ref());
+        let length = self.length(&covered_token);
+        match self.spec.max {
+            Some(max) => length >= self.spec.min && length <= max,
+            None => length >= self.spec.min,
+        }
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        if self.spec.min == 0 && self.spec.max.is_none() {
+            EstimationType::SELECTIVITY(1.0)
+        } else {
+            // there are no statistics about the length of the covered text
+            EstimationType::SELECTIVITY(0.1)
+        }
+    }
+}
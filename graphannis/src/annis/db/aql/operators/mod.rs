@@ -20,6 +20,17 @@ impl RangeSpec {
             RangeSpec::Unbound => 1,
         }
     }
+
+    /// Describe why no distance can be part of this range, e.g. because the minimum is larger than the maximum.
+    pub fn contradiction(&self) -> Option<String> {
+        match &self {
+            RangeSpec::Bound { min_dist, max_dist } if min_dist > max_dist => Some(format!(
+                "the minimal distance {} is larger than the maximal distance {}",
+                min_dist, max_dist
+            )),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for RangeSpec {
@@ -38,25 +49,40 @@ impl fmt::Display for RangeSpec {
 }
 
 mod arity;
+mod different_document;
 mod edge_op;
 mod equal_value;
 mod identical_cov;
 mod identical_node;
 mod inclusion;
 mod leftalignment;
+mod length;
 mod near;
+mod negated;
+mod negated_meta;
+mod outdegree;
 mod overlap;
 mod precedence;
 mod rightalignment;
+mod same_segment;
+mod same_text;
 
 pub use self::arity::AritySpec;
+pub use self::different_document::DifferentDocumentSpec;
+pub(crate) use self::edge_op::split_layer_qualified_name;
 pub use self::edge_op::{DominanceSpec, PartOfSubCorpusSpec, PointingSpec};
 pub use self::equal_value::EqualValueSpec;
 pub use self::identical_cov::IdenticalCoverageSpec;
 pub use self::identical_node::IdenticalNodeSpec;
 pub use self::inclusion::InclusionSpec;
 pub use self::leftalignment::LeftAlignmentSpec;
+pub use self::length::{LengthSpec, LengthUnit};
 pub use self::near::NearSpec;
+pub use self::negated::NegatedOpSpec;
+pub use self::negated_meta::NegatedMetaSpec;
+pub use self::outdegree::OutDegreeSpec;
 pub use self::overlap::OverlapSpec;
 pub use self::precedence::PrecedenceSpec;
 pub use self::rightalignment::RightAlignmentSpec;
+pub use self::same_segment::SameSegmentSpec;
+pub use self::same_text::SameTextSpec;
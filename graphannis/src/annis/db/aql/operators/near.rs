@@ -56,6 +56,14 @@ impl BinaryOperatorSpec for NearSpec {
             None
         }
     }
+
+    fn contradiction(&self) -> Option<String> {
+        self.dist.contradiction()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.dist == RangeSpec::Unbound
+    }
 }
 
 impl std::fmt::Display for NearSpec {
@@ -132,7 +140,7 @@ impl<'a> BinaryOperator for Near<'a> {
                 .fuse()
                 // find all right aligned nodes for this token and add it together with the token itself
                 .flat_map(move |t| {
-                    let it_aligned = self.tok_helper.get_gs_right_token_().get_ingoing_edges(t);
+                    let it_aligned = self.tok_helper.get_gs_right_token().get_ingoing_edges(t);
                     std::iter::once(t).chain(it_aligned)
                 });
             Box::new(it)
@@ -188,8 +196,12 @@ impl<'a> BinaryOperator for Near<'a> {
         )
     }
 
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_order) = self.gs_order.get_statistics() {
+        if let Some(stats_order) = self.gs_order.get_statistics_or_estimate() {
             let max_dist = match self.spec.dist.max_dist() {
                 std::ops::Bound::Unbounded => usize::max_value(),
                 std::ops::Bound::Included(max_dist) => max_dist,
@@ -216,4 +228,8 @@ impl<'a> BinaryOperator for Near<'a> {
             spec: self.spec.clone(),
         }))
     }
+
+    fn is_commutative(&self) -> bool {
+        true
+    }
 }
@@ -0,0 +1,97 @@
+use crate::AnnotationGraph;
+use crate::{
+    annis::{
+        db::aql::model::AnnotationComponentType,
+        operator::{BinaryOperator, BinaryOperatorSpec, EstimationType, ReachabilityCache},
+    },
+    graph::Match,
+};
+use graphannis_core::types::Component;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Negation of a binary operator, e.g. `!>` for nodes that are not dominated by the other node.
+///
+/// The negated operator can't enumerate the matches of its right-hand side, it can only filter
+/// matches of both operands that have been bound by other operators of the query.
+#[derive(Debug)]
+pub struct NegatedOpSpec {
+    pub spec: Box<dyn BinaryOperatorSpec>,
+}
+
+impl BinaryOperatorSpec for NegatedOpSpec {
+    fn necessary_components(
+        &self,
+        db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        self.spec.necessary_components(db)
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
+        let op = self.spec.create_operator(db)?;
+        Some(Box::new(NegatedOp { op }))
+    }
+
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        let op = self.spec.create_operator_with_cache(db, cache)?;
+        Some(Box::new(NegatedOp { op }))
+    }
+
+    fn is_binding(&self) -> bool {
+        false
+    }
+
+    fn is_filter_only(&self) -> bool {
+        true
+    }
+
+    fn validate(&self, db: &AnnotationGraph) -> std::result::Result<(), String> {
+        self.spec.validate(db)
+    }
+}
+
+pub struct NegatedOp<'a> {
+    op: Box<dyn BinaryOperator + 'a>,
+}
+
+impl<'a> std::fmt::Display for NegatedOp<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "!{}", self.op)
+    }
+}
+
+impl<'a> BinaryOperator for NegatedOp<'a> {
+    fn retrieve_matches(&self, _lhs: &Match) -> Box<dyn Iterator<Item = Match>> {
+        // the planner only uses this operator as filter
+        Box::new(std::iter::empty())
+    }
+
+    fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
+        !self.op.filter_match(lhs, rhs)
+    }
+
+    fn filter_matches(&self, lhs: &Match, rhs: &[Match]) -> Vec<bool> {
+        self.op
+            .filter_matches(lhs, rhs)
+            .into_iter()
+            .map(|result| !result)
+            .collect()
+    }
+
+    fn is_reflexive(&self) -> bool {
+        self.op.is_reflexive()
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        match self.op.estimation_type() {
+            EstimationType::SELECTIVITY(selectivity) => {
+                EstimationType::SELECTIVITY(1.0 - selectivity)
+            }
+            EstimationType::MIN => EstimationType::SELECTIVITY(0.9),
+        }
+    }
+}
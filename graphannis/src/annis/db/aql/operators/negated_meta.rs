@@ -0,0 +1,128 @@
+use crate::annis::db::aql::model::AnnotationComponentType;
+use crate::annis::db::exec::nodesearch::NodeSearchSpec;
+use crate::annis::operator::{EstimationType, UnaryOperator, UnaryOperatorSpec};
+use crate::{
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::ANNIS_NS,
+    types::{AnnoKey, Component, NodeID},
+};
+use rustc_hash::FxHashSet;
+use std::collections::HashSet;
+use std::ops::Bound;
+use std::sync::Arc;
+
+/// Specification for a negated meta data constraint like `!meta::genre="fiction"`, which is true if the
+/// document of the matched node does not have a matching annotation.
+///
+/// In contrast to `meta::genre!="fiction"`, documents that don't have a `genre` annotation at all
+/// fulfill the constraint.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NegatedMetaSpec {
+    pub spec: NodeSearchSpec,
+}
+
+impl NegatedMetaSpec {
+    fn part_of_component() -> Component<AnnotationComponentType> {
+        Component::new(AnnotationComponentType::PartOf, ANNIS_NS.into(), "".into())
+    }
+
+    /// Find all documents which have an annotation that matches the specification.
+    fn matching_documents(&self, db: &AnnotationGraph) -> FxHashSet<NodeID> {
+        let node_annos = db.get_node_annos();
+        let it = match &self.spec {
+            NodeSearchSpec::ExactValue { ns, name, val, .. } => {
+                node_annos.exact_anno_search(ns.as_deref(), name, val.as_deref().into())
+            }
+            NodeSearchSpec::NotExactValue { ns, name, val, .. } => {
+                node_annos.exact_anno_search(ns.as_deref(), name, ValueSearch::NotSome(val))
+            }
+            NodeSearchSpec::RegexValue { ns, name, val, .. } => {
+                node_annos.regex_anno_search(ns.as_deref(), name, val, false)
+            }
+            NodeSearchSpec::NotRegexValue { ns, name, val, .. } => {
+                node_annos.regex_anno_search(ns.as_deref(), name, val, true)
+            }
+            _ => return FxHashSet::default(),
+        };
+        let doc_key = doc_key();
+        it.map(|m| m.node)
+            .filter(|n| node_annos.get_value_for_item(n, &doc_key).is_some())
+            .collect()
+    }
+}
+
+fn doc_key() -> AnnoKey {
+    AnnoKey {
+        ns: ANNIS_NS.into(),
+        name: "doc".into(),
+    }
+}
+
+impl std::fmt::Display for NegatedMetaSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "!meta::{}", self.spec)
+    }
+}
+
+impl UnaryOperatorSpec for NegatedMetaSpec {
+    fn necessary_components(
+        &self,
+        _db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        let mut components = HashSet::default();
+        components.insert(NegatedMetaSpec::part_of_component());
+        components
+    }
+
+    fn create_operator(&self, db: &AnnotationGraph) -> Option<Box<dyn UnaryOperator>> {
+        let gs_part_of = db.get_graphstorage(&NegatedMetaSpec::part_of_component())?;
+        let excluded_documents = self.matching_documents(db);
+        let number_of_documents = db
+            .get_node_annos()
+            .number_of_annotations_by_name(Some(ANNIS_NS), "doc");
+        Some(Box::new(NegatedMetaOperator {
+            gs_part_of,
+            excluded_documents,
+            number_of_documents,
+            spec: self.clone(),
+        }))
+    }
+}
+
+struct NegatedMetaOperator {
+    gs_part_of: Arc<dyn GraphStorage>,
+    excluded_documents: FxHashSet<NodeID>,
+    number_of_documents: usize,
+    spec: NegatedMetaSpec,
+}
+
+impl std::fmt::Display for NegatedMetaOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+impl UnaryOperator for NegatedMetaOperator {
+    fn filter_match(&self, m: &Match) -> bool {
+        if self.excluded_documents.is_empty() {
+            return true;
+        }
+        !self
+            .gs_part_of
+            .find_connected(m.node, 1, Bound::Unbounded)
+            .any(|parent| self.excluded_documents.contains(&parent))
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        if self.number_of_documents == 0 {
+            return EstimationType::SELECTIVITY(1.0);
+        }
+        // assume the matches are distributed equally over all documents
+        let excluded = self.excluded_documents.len() as f64 / self.number_of_documents as f64;
+        EstimationType::SELECTIVITY((1.0 - excluded).max(0.0))
+    }
+}
@@ -0,0 +1,138 @@
+use super::edge_op::components_in_layer;
+use crate::annis::operator::EstimationType;
+use crate::annis::{
+    db::aql::model::AnnotationComponentType,
+    operator::{UnaryOperator, UnaryOperatorSpec},
+};
+use crate::{
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::types::{Component, NodeID};
+use rustc_hash::FxHashSet;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Specification for the `:outdegree(name)` operator, which is true if the number of nodes that are connected
+/// with a direct outgoing dominance or pointing relation of the components with the given name is in the
+/// allowed range, e.g. `#1:outdegree(dep)>=3`.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct OutDegreeSpec {
+    pub layer: Option<String>,
+    pub name: String,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl OutDegreeSpec {
+    fn components(&self, db: &AnnotationGraph) -> Vec<Component<AnnotationComponentType>> {
+        let mut result = components_in_layer(
+            db,
+            AnnotationComponentType::Dominance,
+            &self.layer,
+            &self.name,
+        );
+        result.extend(components_in_layer(
+            db,
+            AnnotationComponentType::Pointing,
+            &self.layer,
+            &self.name,
+        ));
+        result
+    }
+}
+
+impl std::fmt::Display for OutDegreeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, ":outdegree(")?;
+        if let Some(layer) = &self.layer {
+            write!(f, "{}::", layer)?;
+        }
+        write!(f, "{})", self.name)?;
+        match self.max {
+            Some(max) if max == self.min => write!(f, "={}", max),
+            Some(max) if self.min == 0 => write!(f, "<={}", max),
+            Some(max) => write!(f, "={},{}", self.min, max),
+            None => write!(f, ">={}", self.min),
+        }
+    }
+}
+
+impl UnaryOperatorSpec for OutDegreeSpec {
+    fn necessary_components(
+        &self,
+        db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        self.components(db).into_iter().collect()
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn UnaryOperator + 'a>> {
+        let graphstorages = self
+            .components(db)
+            .into_iter()
+            .filter_map(|c| db.get_graphstorage(&c))
+            .collect();
+
+        Some(Box::new(OutDegreeOperator {
+            graphstorages,
+            spec: self.clone(),
+        }))
+    }
+}
+
+struct OutDegreeOperator {
+    graphstorages: Vec<Arc<dyn GraphStorage>>,
+    spec: OutDegreeSpec,
+}
+
+impl std::fmt::Display for OutDegreeOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+impl UnaryOperator for OutDegreeOperator {
+    fn filter_match(&self, m: &Match) -> bool {
+        let mut targets: FxHashSet<NodeID> = FxHashSet::default();
+        for gs in self.graphstorages.iter() {
+            for out in gs.get_outgoing_edges(m.node) {
+                if targets.insert(out) {
+                    // stop counting as soon as the result is known
+                    if let Some(max) = self.spec.max {
+                        if targets.len() > max {
+                            return false;
+                        }
+                    } else if targets.len() >= self.spec.min {
+                        return true;
+                    }
+                }
+            }
+        }
+        targets.len() >= self.spec.min
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        if self.spec.min == 0 && self.spec.max.is_none() {
+            return EstimationType::SELECTIVITY(1.0);
+        }
+        // the maximal number of outgoing edges over all components
+        let mut max_fan_out = 0;
+        for gs in self.graphstorages.iter() {
+            if let Some(stats) = gs.get_statistics_or_estimate() {
+                max_fan_out += stats.max_fan_out;
+            } else {
+                return EstimationType::SELECTIVITY(0.1);
+            }
+        }
+        if self.spec.min > max_fan_out {
+            // no node has the minimum required amount of outgoing edges
+            return EstimationType::SELECTIVITY(0.0);
+        }
+        // Without a histogram of the number of outgoing edges, assume all values up to the maximum are equally
+        // likely, which is similar to the estimation of the arity operator.
+        let max = std::cmp::min(self.spec.max.unwrap_or(max_fan_out), max_fan_out);
+        let range_len = max.saturating_sub(self.spec.min) + 1;
+        let sel = range_len as f64 / ((max_fan_out + 1) as f64);
+        EstimationType::SELECTIVITY(sel.min(1.0))
+    }
+}
@@ -166,15 +166,23 @@ impl<'a> BinaryOperator for Overlap<'a> {
         }))
     }
 
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_order) = self.gs_order.get_statistics() {
+        if let Some(stats_order) = self.gs_order.get_statistics_or_estimate() {
             let mut sum_included = 0;
             let mut sum_cov_nodes = 0;
 
             let num_of_token = stats_order.nodes as f64;
 
             for gs_cov in self.tok_helper.get_gs_coverage().iter() {
-                if let Some(stats_cov) = gs_cov.get_statistics() {
+                if let Some(stats_cov) = gs_cov.get_statistics_or_estimate() {
                     sum_cov_nodes += stats_cov.nodes;
                     let covered_token_per_node = stats_cov.fan_out_99_percentile;
                     // for each covered token get the number of inverse covered non-token nodes
@@ -73,6 +73,14 @@ impl BinaryOperatorSpec for PrecedenceSpec {
             None
         }
     }
+
+    fn contradiction(&self) -> Option<String> {
+        self.dist.contradiction()
+    }
+
+    fn is_unbounded(&self) -> bool {
+        self.dist == RangeSpec::Unbound
+    }
 }
 
 impl std::fmt::Display for PrecedenceSpec {
@@ -175,8 +183,12 @@ impl<'a> BinaryOperator for Precedence<'a> {
         )
     }
 
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_order) = self.gs_order.get_statistics() {
+        if let Some(stats_order) = self.gs_order.get_statistics_or_estimate() {
             let max_dist = match self.spec.dist.max_dist() {
                 std::ops::Bound::Unbounded => usize::max_value(),
                 std::ops::Bound::Included(max_dist) => max_dist,
@@ -297,8 +309,12 @@ impl<'a> BinaryOperator for InversePrecedence<'a> {
         Some(Box::new(prec))
     }
 
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_order) = self.gs_order.get_statistics() {
+        if let Some(stats_order) = self.gs_order.get_statistics_or_estimate() {
             let max_dist = match self.spec.dist.max_dist() {
                 std::ops::Bound::Unbounded => usize::max_value(),
                 std::ops::Bound::Included(max_dist) => max_dist,
@@ -57,7 +57,7 @@ impl<'a> BinaryOperator for RightAlignment<'a> {
             });
             aligned.extend(
                 self.tok_helper
-                    .get_gs_right_token_()
+                    .get_gs_right_token()
                     .get_ingoing_edges(lhs_token)
                     .map(|n| Match {
                         node: n,
@@ -93,8 +93,20 @@ impl<'a> BinaryOperator for RightAlignment<'a> {
         Some(Box::new(RightAlignment { tok_helper }))
     }
 
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
     fn estimation_type(&self) -> EstimationType {
-        if let Some(stats_right) = self.tok_helper.get_gs_right_token_().get_statistics() {
+        if let Some(stats_right) = self
+            .tok_helper
+            .get_gs_right_token()
+            .get_statistics_or_estimate()
+        {
             let aligned_nodes_per_token: f64 = stats_right.inverse_fan_out_99_percentile as f64;
             return EstimationType::SELECTIVITY(
                 aligned_nodes_per_token / (stats_right.nodes as f64),
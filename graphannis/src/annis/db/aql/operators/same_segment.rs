@@ -0,0 +1,190 @@
+use super::inclusion::{Inclusion, InclusionSpec};
+use crate::annis::db::token_helper::TokenHelper;
+use crate::annis::db::AnnotationStorage;
+use crate::annis::operator::EstimationType;
+use crate::AnnotationGraph;
+use crate::{
+    annis::operator::{BinaryOperator, BinaryOperatorSpec},
+    graph::Match,
+    model::AnnotationComponentType,
+};
+use graphannis_core::{
+    graph::DEFAULT_ANNO_KEY,
+    types::{AnnoKey, Component, NodeID},
+};
+
+use std::collections::{BTreeSet, HashSet};
+use std::sync::Arc;
+
+/// Specification for the `_=seg_` operator, which is true if both nodes are covered by the same node that has an
+/// annotation with the name of the segmentation (e.g. the same sentence span).
+#[derive(Clone, Debug, PartialOrd, Ord, Hash, PartialEq, Eq)]
+pub struct SameSegmentSpec {
+    pub segmentation: String,
+}
+
+pub struct SameSegment<'a> {
+    inclusion: Inclusion<'a>,
+    tok_helper: TokenHelper<'a>,
+    node_annos: &'a dyn AnnotationStorage<NodeID>,
+    segment_keys: Vec<Arc<AnnoKey>>,
+    spec: SameSegmentSpec,
+}
+
+impl BinaryOperatorSpec for SameSegmentSpec {
+    fn necessary_components(
+        &self,
+        db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        InclusionSpec.necessary_components(db)
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
+        let optional_op = SameSegment::new(db, self.clone());
+        if let Some(op) = optional_op {
+            Some(Box::new(op))
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for SameSegmentSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "_={}_", self.segmentation)
+    }
+}
+
+impl<'a> SameSegment<'a> {
+    pub fn new(db: &'a AnnotationGraph, spec: SameSegmentSpec) -> Option<SameSegment<'a>> {
+        let segment_keys = db
+            .get_node_annos()
+            .get_qnames(&spec.segmentation)
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+
+        Some(SameSegment {
+            inclusion: Inclusion::new(db)?,
+            tok_helper: TokenHelper::new(db)?,
+            node_annos: db.get_node_annos(),
+            segment_keys,
+            spec,
+        })
+    }
+
+    /// Get all segment nodes that include the given node.
+    fn segments_for(&self, node: NodeID) -> Vec<NodeID> {
+        let left_token = if self.tok_helper.is_token(node) {
+            Some(node)
+        } else {
+            self.tok_helper.left_token_for(node)
+        };
+        let left_token = if let Some(left_token) = left_token {
+            left_token
+        } else {
+            return Vec::default();
+        };
+
+        // the token itself or any node covering the left-most token of the node can be a segment
+        let candidates: BTreeSet<NodeID> = std::iter::once(left_token)
+            .chain(
+                self.tok_helper
+                    .get_gs_coverage()
+                    .iter()
+                    .flat_map(|gs| gs.get_ingoing_edges(left_token)),
+            )
+            .collect();
+
+        candidates
+            .into_iter()
+            .filter(|candidate| {
+                self.segment_keys
+                    .iter()
+                    .any(|key| self.node_annos.get_value_for_item(candidate, key).is_some())
+            })
+            .filter(|segment| {
+                *segment == node
+                    || self
+                        .inclusion
+                        .filter_match(&segment_match(*segment), &segment_match(node))
+            })
+            .collect()
+    }
+}
+
+fn segment_match(node: NodeID) -> Match {
+    Match {
+        node,
+        anno_key: DEFAULT_ANNO_KEY.clone(),
+    }
+}
+
+impl<'a> std::fmt::Display for SameSegment<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+impl<'a> BinaryOperator for SameSegment<'a> {
+    fn retrieve_matches(&self, lhs: &Match) -> Box<dyn Iterator<Item = Match>> {
+        // all nodes that are included in any of the segments of the LHS
+        let result: BTreeSet<NodeID> = self
+            .segments_for(lhs.node)
+            .into_iter()
+            .flat_map(|segment| {
+                let segment_match = segment_match(segment);
+                let included: Vec<Match> =
+                    self.inclusion.retrieve_matches(&segment_match).collect();
+                std::iter::once(segment).chain(included.into_iter().map(|m| m.node))
+            })
+            .collect();
+
+        Box::new(result.into_iter().map(segment_match))
+    }
+
+    fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
+        let rhs_segments = self.segments_for(rhs.node);
+        self.segments_for(lhs.node)
+            .iter()
+            .any(|segment| rhs_segments.contains(segment))
+    }
+
+    fn is_reflexive(&self) -> bool {
+        false
+    }
+
+    fn is_document_local(&self) -> bool {
+        true
+    }
+
+    fn get_inverse_operator<'b>(
+        &self,
+        graph: &'b AnnotationGraph,
+    ) -> Option<Box<dyn BinaryOperator + 'b>> {
+        // the operator is symmetric
+        let inverse = SameSegment::new(graph, self.spec.clone())?;
+        Some(Box::new(inverse))
+    }
+
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        let number_of_segments: usize = self
+            .segment_keys
+            .iter()
+            .map(|key| {
+                self.node_annos
+                    .number_of_annotations_by_name(Some(&key.ns), &key.name)
+            })
+            .sum();
+        if number_of_segments > 0 {
+            // two random nodes are in the same segment with a probability of 1/(number of segments)
+            EstimationType::SELECTIVITY(1.0 / (number_of_segments as f64))
+        } else {
+            EstimationType::SELECTIVITY(0.1)
+        }
+    }
+}
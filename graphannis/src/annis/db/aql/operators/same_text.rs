@@ -0,0 +1,197 @@
+use crate::annis::db::aql::model::{AnnotationComponentType, TOK, TOKEN_KEY};
+use crate::annis::db::token_helper::{self, TokenHelper};
+use crate::annis::operator::{BinaryOperator, BinaryOperatorSpec, EstimationType};
+use crate::{
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::{ANNIS_NS, DEFAULT_ANNO_KEY},
+    types::{Component, NodeID},
+};
+use rustc_hash::{FxHashMap, FxHasher};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// Specification for the `_sametext_` operator, which is true if two nodes cover the same token values
+/// (e.g. `"in" "the" "end"`) at different positions of the same or of different documents.
+#[derive(Clone, Debug, PartialOrd, Ord, Hash, PartialEq, Eq)]
+pub struct SameTextSpec;
+
+lazy_static! {
+    static ref COMPONENT_ORDER: Component<AnnotationComponentType> = {
+        Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        )
+    };
+}
+
+/// Maps the hash of the covered text to all nodes covering a text with this hash.
+type TextIndex = FxHashMap<u64, Vec<NodeID>>;
+
+impl BinaryOperatorSpec for SameTextSpec {
+    fn necessary_components(
+        &self,
+        db: &AnnotationGraph,
+    ) -> HashSet<Component<AnnotationComponentType>> {
+        let mut v = HashSet::new();
+        v.insert(COMPONENT_ORDER.clone());
+        v.extend(token_helper::necessary_components(db));
+        v
+    }
+
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>> {
+        Some(Box::new(SameText {
+            gs_order: db.get_graphstorage(&COMPONENT_ORDER)?,
+            tok_helper: TokenHelper::new(db)?,
+            db,
+            index: Arc::new(Mutex::new(None)),
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct SameText<'a> {
+    gs_order: Arc<dyn GraphStorage>,
+    tok_helper: TokenHelper<'a>,
+    db: &'a AnnotationGraph,
+    /// Index of the covered text of all nodes, which is only built when it is needed by the query and is
+    /// shared with the inverse operator.
+    index: Arc<Mutex<Option<TextIndex>>>,
+}
+
+fn text_hash(text: &str) -> u64 {
+    let mut hasher = FxHasher::default();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<'a> SameText<'a> {
+    /// Get the values of the covered tokens, separated by a single space, or `None` if the node does not
+    /// cover any token.
+    fn covered_text(&self, node: NodeID) -> Option<String> {
+        let covered_token = self.tok_helper.covered_token(node, self.gs_order.as_ref());
+        if covered_token.is_empty() {
+            return None;
+        }
+        let values: Vec<_> = covered_token
+            .iter()
+            .map(|t| {
+                self.db
+                    .get_node_annos()
+                    .get_value_for_item(t, &TOKEN_KEY)
+                    .unwrap_or_default()
+            })
+            .collect();
+        Some(values.join(" "))
+    }
+
+    fn build_index(&self) -> TextIndex {
+        let tokens = self
+            .db
+            .get_node_annos()
+            .exact_anno_search(Some(ANNIS_NS), TOK, ValueSearch::Any)
+            .map(|m| m.node);
+        let spans = self.tok_helper.get_gs_left_token().source_nodes();
+
+        let mut index = TextIndex::default();
+        for n in tokens.chain(spans) {
+            if let Some(text) = self.covered_text(n) {
+                index.entry(text_hash(&text)).or_default().push(n);
+            }
+        }
+        for nodes in index.values_mut() {
+            nodes.sort_unstable();
+            nodes.dedup();
+        }
+        index
+    }
+
+    fn same_position(&self, lhs: NodeID, rhs: NodeID) -> bool {
+        self.tok_helper.left_right_token_for(lhs) == self.tok_helper.left_right_token_for(rhs)
+    }
+}
+
+impl<'a> std::fmt::Display for SameText<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "_sametext_")
+    }
+}
+
+impl<'a> BinaryOperator for SameText<'a> {
+    fn retrieve_matches(&self, lhs: &Match) -> Box<dyn Iterator<Item = Match>> {
+        let lhs_text = if let Some(text) = self.covered_text(lhs.node) {
+            text
+        } else {
+            return Box::new(std::iter::empty());
+        };
+
+        let candidates = {
+            let mut index = self.index.lock().unwrap();
+            if index.is_none() {
+                *index = Some(self.build_index());
+            }
+            index
+                .as_ref()
+                .and_then(|index| index.get(&text_hash(&lhs_text)))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        // the hash could collide, so the text of each candidate must be compared
+        let result: Vec<Match> = candidates
+            .into_iter()
+            .filter(|n| !self.same_position(lhs.node, *n))
+            .filter(|n| self.covered_text(*n).as_ref() == Some(&lhs_text))
+            .map(|node| Match {
+                node,
+                anno_key: DEFAULT_ANNO_KEY.clone(),
+            })
+            .collect();
+        Box::new(result.into_iter())
+    }
+
+    fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool {
+        if self.same_position(lhs.node, rhs.node) {
+            return false;
+        }
+        match (self.covered_text(lhs.node), self.covered_text(rhs.node)) {
+            (Some(lhs_text), Some(rhs_text)) => lhs_text == rhs_text,
+            _ => false,
+        }
+    }
+
+    fn is_reflexive(&self) -> bool {
+        false
+    }
+
+    fn get_inverse_operator<'b>(
+        &self,
+        graph: &'b AnnotationGraph,
+    ) -> Option<Box<dyn BinaryOperator + 'b>> {
+        Some(Box::new(SameText {
+            gs_order: self.gs_order.clone(),
+            tok_helper: TokenHelper::new(graph)?,
+            db: graph,
+            index: self.index.clone(),
+        }))
+    }
+
+    fn is_commutative(&self) -> bool {
+        true
+    }
+
+    fn estimation_type(&self) -> EstimationType {
+        if let Some(order_stats) = self.gs_order.get_statistics_or_estimate() {
+            // There are no statistics about repeated texts, assume that each text occurs about ten times
+            let num_of_token = order_stats.nodes as f64;
+            EstimationType::SELECTIVITY((10.0 / num_of_token).min(1.0))
+        } else {
+            EstimationType::SELECTIVITY(0.1)
+        }
+    }
+}
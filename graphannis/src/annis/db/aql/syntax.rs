@@ -0,0 +1,678 @@
+//! A serializable representation of the syntax tree of an AQL query, which can be used by tooling like editors,
+//! formatters or linters without re-implementing the AQL parser.
+
+use super::ast;
+use super::operators::{LengthUnit, RangeSpec};
+use super::{get_line_and_column_for_pos, get_line_offsets, map_parse_error, AQL_PARSER};
+use crate::annis::db::exec::nodesearch::NodeSearchSpec;
+use crate::annis::errors::*;
+use crate::annis::operator::EdgeAnnoSearchSpec;
+use crate::annis::types::LineColumnRange;
+use boolean_expression::Expr;
+use graphannis_core::util::UnicodeNormalization;
+use std::collections::BTreeMap;
+
+/// The annotation name of edge annotation conditions that match annotations with any name, e.g. `>[*="subj"]`.
+pub const ANY_NAME: &str = "*";
+
+/// The location of a fragment in the query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Span {
+    /// Byte offset of the first character.
+    pub start: usize,
+    /// Byte offset after the last character.
+    pub end: usize,
+    /// Line and column of the start and end offset.
+    pub location: LineColumnRange,
+}
+
+/// A boolean expression of the query. Nested conjunctions or disjunctions of the same kind are merged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Expression {
+    /// All child expressions must be true (`&`).
+    And(Vec<Expression>),
+    /// At least one of the child expressions must be true (`|`).
+    Or(Vec<Expression>),
+    Literal(Literal),
+}
+
+/// A single node search or operator of the query.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Literal {
+    /// A query node, e.g. `pos="NN"` or `n#cat="NP"`.
+    NodeSearch {
+        search: NodeSearch,
+        variable: Option<String>,
+        /// True for optional nodes like `pos="ADJ"?`, which must not exist.
+        optional: bool,
+        span: Option<Span>,
+    },
+    /// A binary operator like `#1 . #2` or `cat="S" > pos="NN"`.
+    BinaryOperator {
+        lhs: Box<Operand>,
+        operator: BinaryOperator,
+        rhs: Box<Operand>,
+        span: Option<Span>,
+    },
+    /// A unary operator like `#1:arity=2`.
+    UnaryOperator {
+        node: NodeReference,
+        operator: UnaryOperator,
+        span: Option<Span>,
+    },
+    /// A deprecated metadata search with the `meta::` prefix, which applies to all alternatives of the query.
+    LegacyMetaSearch { search: NodeSearch, span: Span },
+    /// A metadata search which requires that the document does not have the annotation, e.g. `!meta::genre="news"`.
+    NegatedMetaSearch { search: NodeSearch, span: Span },
+}
+
+/// An operand of a binary operator, either a reference to a query node or a node search which is given inline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Operand {
+    Reference(NodeReference),
+    NodeSearch {
+        search: NodeSearch,
+        variable: Option<String>,
+        optional: bool,
+        span: Span,
+    },
+}
+
+/// A reference to a query node, either by its position (`#1`) or by its variable name (`#n`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum NodeReference {
+    Position(usize),
+    Variable(String),
+}
+
+/// A condition on an annotation value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ValueCondition {
+    /// The value is (or is not, if `negated`) equal to the given value.
+    Exact { value: String, negated: bool },
+    /// The value matches (or does not match, if `negated`) the regular expression.
+    Regex { value: String, negated: bool },
+    /// The value is one of the given values.
+    OneOf { values: Vec<String> },
+}
+
+/// A condition on an annotation with an optional namespace, e.g. `tiger:pos="NN"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnnotationCondition {
+    pub ns: Option<String>,
+    /// The annotation name, edge annotation conditions use [`ANY_NAME`] to match annotations with any name.
+    pub name: String,
+    /// The condition on the value, `None` if any value is allowed.
+    pub value: Option<ValueCondition>,
+}
+
+/// The search for a query node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum NodeSearch {
+    /// Nodes with an annotation, e.g. `pos="NN"`, or documents with a metadata annotation if `is_meta` is true.
+    Annotation {
+        annotation: AnnotationCondition,
+        is_meta: bool,
+    },
+    /// Tokens, optionally with a condition on their value, e.g. `tok` or `"house"`.
+    Token {
+        value: Option<ValueCondition>,
+        leafs_only: bool,
+    },
+    /// Any node (`node`).
+    AnyNode,
+    /// Nodes of the `base` search that are part of an edge of the given component.
+    InComponent {
+        base: Box<NodeSearch>,
+        component: String,
+    },
+}
+
+/// The allowed distance of an operator, `max` is `None` for unbounded distances.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Distance {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+/// A binary operator and its parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum BinaryOperator {
+    Dominance {
+        layer: Option<String>,
+        name: String,
+        distance: Distance,
+        edge_annotations: Vec<AnnotationCondition>,
+    },
+    Pointing {
+        layer: Option<String>,
+        name: String,
+        distance: Distance,
+        edge_annotations: Vec<AnnotationCondition>,
+    },
+    Precedence {
+        segmentation: Option<String>,
+        distance: Distance,
+    },
+    Near {
+        segmentation: Option<String>,
+        distance: Distance,
+    },
+    Overlap {
+        reflexive: bool,
+    },
+    IdenticalCoverage,
+    /// `@` (or `@^` if `inverse` is true).
+    PartOfSubCorpus {
+        distance: Distance,
+        inverse: bool,
+    },
+    Inclusion,
+    LeftAlignment,
+    RightAlignment,
+    IdenticalNode,
+    SameSegment {
+        segmentation: String,
+    },
+    SameText,
+    DifferentDocument,
+    /// `==` (or `!=` if `negated` is true).
+    ValueComparison {
+        negated: bool,
+    },
+    /// Negated dominance, pointing relation or precedence, e.g. `!>`.
+    Negated {
+        operator: Box<BinaryOperator>,
+    },
+    /// Operator defined in the corpus configuration, e.g. `~governs`.
+    Derived {
+        name: String,
+    },
+}
+
+/// A unary operator and its parameters.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum UnaryOperator {
+    Arity {
+        children: Distance,
+    },
+    OutDegree {
+        layer: Option<String>,
+        name: String,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// `:len` if `tokens` is false, `:tokenlen` otherwise.
+    Length {
+        tokens: bool,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// Parse an AQL query and return its syntax tree.
+///
+/// In contrast to the execution of a query, the syntax tree is not normalized: the spans refer to the given query
+/// string and the structure of the boolean expression is kept as written.
+pub fn parse(query_as_aql: &str) -> Result<Expression> {
+    let expr = AQL_PARSER
+        .with(|p| p.parse(UnicodeNormalization::None, query_as_aql))
+        .map_err(|e| map_parse_error(e, query_as_aql))?;
+    let offsets = get_line_offsets(query_as_aql);
+    map_expr(expr, &offsets)
+}
+
+fn map_expr(expr: ast::Expr, offsets: &BTreeMap<usize, usize>) -> Result<Expression> {
+    match expr {
+        Expr::Terminal(literal) => Ok(Expression::Literal(map_literal(literal, offsets))),
+        Expr::And(lhs, rhs) => {
+            let mut children = Vec::new();
+            for child in [*lhs, *rhs] {
+                match map_expr(child, offsets)? {
+                    Expression::And(nested) => children.extend(nested),
+                    other => children.push(other),
+                }
+            }
+            Ok(Expression::And(children))
+        }
+        Expr::Or(lhs, rhs) => {
+            let mut children = Vec::new();
+            for child in [*lhs, *rhs] {
+                match map_expr(child, offsets)? {
+                    Expression::Or(nested) => children.extend(nested),
+                    other => children.push(other),
+                }
+            }
+            Ok(Expression::Or(children))
+        }
+        Expr::Not(_) | Expr::Const(_) => Err(GraphAnnisError::AQLSemanticError(AQLError {
+            desc: "Negated or constant expressions are not part of AQL".to_string(),
+            location: None,
+        })),
+    }
+}
+
+fn map_span(pos: ast::Pos, offsets: &BTreeMap<usize, usize>) -> Span {
+    Span {
+        location: LineColumnRange {
+            start: get_line_and_column_for_pos(pos.start, offsets),
+            end: Some(get_line_and_column_for_pos(pos.end, offsets)),
+        },
+        start: pos.start,
+        end: pos.end,
+    }
+}
+
+fn map_literal(literal: ast::Literal, offsets: &BTreeMap<usize, usize>) -> Literal {
+    match literal {
+        ast::Literal::NodeSearch {
+            spec,
+            pos,
+            variable,
+            optional,
+        } => Literal::NodeSearch {
+            search: map_node_search(&spec),
+            variable,
+            optional,
+            span: pos.map(|pos| map_span(pos, offsets)),
+        },
+        ast::Literal::BinaryOp { lhs, op, rhs, pos } => Literal::BinaryOperator {
+            lhs: Box::new(map_operand(lhs, offsets)),
+            operator: map_binary_operator(op),
+            rhs: Box::new(map_operand(rhs, offsets)),
+            span: pos.map(|pos| map_span(pos, offsets)),
+        },
+        ast::Literal::UnaryOp { node_ref, op, pos } => Literal::UnaryOperator {
+            node: map_node_ref(node_ref),
+            operator: map_unary_operator(op),
+            span: pos.map(|pos| map_span(pos, offsets)),
+        },
+        ast::Literal::LegacyMetaSearch { spec, pos } => Literal::LegacyMetaSearch {
+            search: map_node_search(&spec),
+            span: map_span(pos, offsets),
+        },
+        ast::Literal::NegatedMetaSearch { spec, pos } => Literal::NegatedMetaSearch {
+            search: map_node_search(&spec),
+            span: map_span(pos, offsets),
+        },
+    }
+}
+
+fn map_operand(operand: ast::Operand, offsets: &BTreeMap<usize, usize>) -> Operand {
+    match operand {
+        ast::Operand::NodeRef(node_ref) => Operand::Reference(map_node_ref(node_ref)),
+        ast::Operand::Literal {
+            spec,
+            pos,
+            variable,
+            optional,
+        } => Operand::NodeSearch {
+            search: map_node_search(&spec),
+            variable,
+            optional,
+            span: map_span(pos, offsets),
+        },
+    }
+}
+
+fn map_node_ref(node_ref: ast::NodeRef) -> NodeReference {
+    match node_ref {
+        ast::NodeRef::ID(id) => NodeReference::Position(id),
+        ast::NodeRef::Name(name) => NodeReference::Variable(name),
+    }
+}
+
+fn map_node_search(spec: &NodeSearchSpec) -> NodeSearch {
+    let annotation =
+        |ns: &Option<String>, name: &str, value, is_meta: bool| NodeSearch::Annotation {
+            annotation: AnnotationCondition {
+                ns: ns.clone(),
+                name: name.to_string(),
+                value,
+            },
+            is_meta,
+        };
+    match spec {
+        NodeSearchSpec::ExactValue {
+            ns,
+            name,
+            val,
+            is_meta,
+        } => annotation(
+            ns,
+            name,
+            val.as_ref().map(|val| ValueCondition::Exact {
+                value: val.clone(),
+                negated: false,
+            }),
+            *is_meta,
+        ),
+        NodeSearchSpec::NotExactValue {
+            ns,
+            name,
+            val,
+            is_meta,
+        } => annotation(
+            ns,
+            name,
+            Some(ValueCondition::Exact {
+                value: val.clone(),
+                negated: true,
+            }),
+            *is_meta,
+        ),
+        NodeSearchSpec::ExactValueSet {
+            ns,
+            name,
+            vals,
+            is_meta,
+        } => annotation(
+            ns,
+            name,
+            Some(ValueCondition::OneOf {
+                values: vals.clone(),
+            }),
+            *is_meta,
+        ),
+        NodeSearchSpec::RegexValue {
+            ns,
+            name,
+            val,
+            is_meta,
+        } => annotation(
+            ns,
+            name,
+            Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: false,
+            }),
+            *is_meta,
+        ),
+        NodeSearchSpec::NotRegexValue {
+            ns,
+            name,
+            val,
+            is_meta,
+        } => annotation(
+            ns,
+            name,
+            Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: true,
+            }),
+            *is_meta,
+        ),
+        NodeSearchSpec::ExactTokenValue { val, leafs_only } => NodeSearch::Token {
+            value: Some(ValueCondition::Exact {
+                value: val.clone(),
+                negated: false,
+            }),
+            leafs_only: *leafs_only,
+        },
+        NodeSearchSpec::NotExactTokenValue { val } => NodeSearch::Token {
+            value: Some(ValueCondition::Exact {
+                value: val.clone(),
+                negated: true,
+            }),
+            leafs_only: false,
+        },
+        NodeSearchSpec::RegexTokenValue { val, leafs_only } => NodeSearch::Token {
+            value: Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: false,
+            }),
+            leafs_only: *leafs_only,
+        },
+        NodeSearchSpec::NotRegexTokenValue { val } => NodeSearch::Token {
+            value: Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: true,
+            }),
+            leafs_only: false,
+        },
+        NodeSearchSpec::AnyToken => NodeSearch::Token {
+            value: None,
+            leafs_only: false,
+        },
+        NodeSearchSpec::AnyNode => NodeSearch::AnyNode,
+        NodeSearchSpec::InComponent { base, component } => NodeSearch::InComponent {
+            base: Box::new(map_node_search(base)),
+            component: component.clone(),
+        },
+    }
+}
+
+fn map_edge_anno(edge_anno: &Option<EdgeAnnoSearchSpec>) -> Vec<AnnotationCondition> {
+    let condition = |ns: &Option<String>, name: &str, value| AnnotationCondition {
+        ns: ns.clone(),
+        name: name.to_string(),
+        value,
+    };
+    match edge_anno {
+        None => Vec::new(),
+        Some(EdgeAnnoSearchSpec::ExactValue { ns, name, val }) => vec![condition(
+            ns,
+            name,
+            val.as_ref().map(|val| ValueCondition::Exact {
+                value: val.clone(),
+                negated: false,
+            }),
+        )],
+        Some(EdgeAnnoSearchSpec::NotExactValue { ns, name, val }) => vec![condition(
+            ns,
+            name,
+            Some(ValueCondition::Exact {
+                value: val.clone(),
+                negated: true,
+            }),
+        )],
+        Some(EdgeAnnoSearchSpec::RegexValue { ns, name, val }) => vec![condition(
+            ns,
+            name,
+            Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: false,
+            }),
+        )],
+        Some(EdgeAnnoSearchSpec::NotRegexValue { ns, name, val }) => vec![condition(
+            ns,
+            name,
+            Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: true,
+            }),
+        )],
+        Some(EdgeAnnoSearchSpec::ExactValueAnyName { val }) => vec![condition(
+            &None,
+            ANY_NAME,
+            Some(ValueCondition::Exact {
+                value: val.clone(),
+                negated: false,
+            }),
+        )],
+        Some(EdgeAnnoSearchSpec::RegexValueAnyName { val }) => vec![condition(
+            &None,
+            ANY_NAME,
+            Some(ValueCondition::Regex {
+                value: val.clone(),
+                negated: false,
+            }),
+        )],
+    }
+}
+
+fn map_distance(dist: &RangeSpec) -> Distance {
+    match dist {
+        RangeSpec::Bound { min_dist, max_dist } => Distance {
+            min: *min_dist,
+            max: Some(*max_dist),
+        },
+        RangeSpec::Unbound => Distance { min: 1, max: None },
+    }
+}
+
+fn map_binary_operator(op: ast::BinaryOpSpec) -> BinaryOperator {
+    match op {
+        ast::BinaryOpSpec::Dominance(spec) => BinaryOperator::Dominance {
+            distance: map_distance(&spec.dist),
+            edge_annotations: map_edge_anno(&spec.edge_anno),
+            layer: spec.layer,
+            name: spec.name,
+        },
+        ast::BinaryOpSpec::Pointing(spec) => BinaryOperator::Pointing {
+            distance: map_distance(&spec.dist),
+            edge_annotations: map_edge_anno(&spec.edge_anno),
+            layer: spec.layer,
+            name: spec.name,
+        },
+        ast::BinaryOpSpec::Precedence(spec) => BinaryOperator::Precedence {
+            distance: map_distance(&spec.dist),
+            segmentation: spec.segmentation,
+        },
+        ast::BinaryOpSpec::Near(spec) => BinaryOperator::Near {
+            distance: map_distance(&spec.dist),
+            segmentation: spec.segmentation,
+        },
+        ast::BinaryOpSpec::Overlap(spec) => BinaryOperator::Overlap {
+            reflexive: spec.reflexive,
+        },
+        ast::BinaryOpSpec::IdenticalCoverage(_) => BinaryOperator::IdenticalCoverage,
+        ast::BinaryOpSpec::PartOfSubCorpus(spec) => BinaryOperator::PartOfSubCorpus {
+            distance: map_distance(&spec.dist),
+            inverse: spec.inverse,
+        },
+        ast::BinaryOpSpec::Inclusion(_) => BinaryOperator::Inclusion,
+        ast::BinaryOpSpec::LeftAlignment(_) => BinaryOperator::LeftAlignment,
+        ast::BinaryOpSpec::RightAlignment(_) => BinaryOperator::RightAlignment,
+        ast::BinaryOpSpec::IdenticalNode(_) => BinaryOperator::IdenticalNode,
+        ast::BinaryOpSpec::SameSegment(spec) => BinaryOperator::SameSegment {
+            segmentation: spec.segmentation,
+        },
+        ast::BinaryOpSpec::SameText(_) => BinaryOperator::SameText,
+        ast::BinaryOpSpec::DifferentDocument(_) => BinaryOperator::DifferentDocument,
+        ast::BinaryOpSpec::ValueComparison(cmp) => BinaryOperator::ValueComparison {
+            negated: cmp == ast::ComparisonOperator::NotEqual,
+        },
+        ast::BinaryOpSpec::Negated(op) => BinaryOperator::Negated {
+            operator: Box::new(map_binary_operator(*op)),
+        },
+        ast::BinaryOpSpec::Derived(name) => BinaryOperator::Derived { name },
+    }
+}
+
+fn map_unary_operator(op: ast::UnaryOpSpec) -> UnaryOperator {
+    match op {
+        ast::UnaryOpSpec::Arity(spec) => UnaryOperator::Arity {
+            children: map_distance(&spec.children),
+        },
+        ast::UnaryOpSpec::OutDegree(spec) => UnaryOperator::OutDegree {
+            layer: spec.layer,
+            name: spec.name,
+            min: spec.min,
+            max: spec.max,
+        },
+        ast::UnaryOpSpec::Length(spec) => UnaryOperator::Length {
+            tokens: spec.unit == LengthUnit::Tokens,
+            min: spec.min,
+            max: spec.max,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annis::types::LineColumn;
+
+    #[test]
+    fn syntax_tree_with_spans() {
+        let expr = parse("pos=\"NN\" &\n#1 ->dep[func=\"subj\"] tok").unwrap();
+        let children = if let Expression::And(children) = expr {
+            children
+        } else {
+            panic!("expected conjunction");
+        };
+        assert_eq!(2, children.len());
+        assert_eq!(
+            Expression::Literal(Literal::NodeSearch {
+                search: NodeSearch::Annotation {
+                    annotation: AnnotationCondition {
+                        ns: None,
+                        name: "pos".to_string(),
+                        value: Some(ValueCondition::Exact {
+                            value: "NN".to_string(),
+                            negated: false,
+                        }),
+                    },
+                    is_meta: false,
+                },
+                variable: None,
+                optional: false,
+                span: Some(Span {
+                    start: 0,
+                    end: 8,
+                    location: LineColumnRange {
+                        start: LineColumn { line: 1, column: 1 },
+                        end: Some(LineColumn { line: 1, column: 9 }),
+                    },
+                }),
+            }),
+            children[0]
+        );
+        if let Expression::Literal(Literal::BinaryOperator {
+            lhs, operator, rhs, ..
+        }) = &children[1]
+        {
+            assert_eq!(
+                &Operand::Reference(NodeReference::Position(1)),
+                lhs.as_ref()
+            );
+            assert_eq!(
+                &BinaryOperator::Pointing {
+                    layer: None,
+                    name: "dep".to_string(),
+                    distance: Distance {
+                        min: 1,
+                        max: Some(1)
+                    },
+                    edge_annotations: vec![AnnotationCondition {
+                        ns: None,
+                        name: "func".to_string(),
+                        value: Some(ValueCondition::Exact {
+                            value: "subj".to_string(),
+                            negated: false
+                        }),
+                    }],
+                },
+                operator
+            );
+            if let Operand::NodeSearch { search, span, .. } = rhs.as_ref() {
+                assert_eq!(
+                    &NodeSearch::Token {
+                        value: None,
+                        leafs_only: false
+                    },
+                    search
+                );
+                assert_eq!(2, span.location.start.line);
+            } else {
+                panic!("expected inline node search");
+            }
+        } else {
+            panic!("expected binary operator");
+        }
+    }
+
+    #[test]
+    fn nested_alternatives_are_merged() {
+        let expr = parse("(tok | node | cat=/S.*/) & #1 . #2").unwrap();
+        if let Expression::And(children) = expr {
+            assert!(
+                matches!(&children[0], Expression::Or(alternatives) if alternatives.len() == 3)
+            );
+        } else {
+            panic!("expected conjunction");
+        }
+        assert!(parse("tok &").is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::annis::db::aql::model::TOKEN_KEY;
+use crate::annis::errors::Result;
+use crate::annis::types::FrequencyTable;
+use crate::{model::AnnotationComponentType, AnnotationGraph};
+use arrow_array::{ArrayRef, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use graphannis_core::{
+    graph::{ANNIS_NS, NODE_NAME_KEY},
+    types::Component,
+};
+use parquet::arrow::ArrowWriter;
+use std::io::Write;
+use std::sync::Arc;
+
+/// File format of the exported tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrowFormat {
+    /// The Apache Arrow IPC file format (also known as Feather V2).
+    Ipc,
+    /// The Apache Parquet file format.
+    Parquet,
+}
+
+fn write_batch<W: Write + Send>(batch: &RecordBatch, format: ArrowFormat, output: W) -> Result<()> {
+    match format {
+        ArrowFormat::Ipc => {
+            let mut writer = FileWriter::try_new(output, &batch.schema())?;
+            writer.write(batch)?;
+            writer.finish()?;
+        }
+        ArrowFormat::Parquet => {
+            let mut writer = ArrowWriter::try_new(output, batch.schema(), None)?;
+            writer.write(batch)?;
+            writer.close()?;
+        }
+    }
+    Ok(())
+}
+
+/// Write the match IDs returned by [`CorpusStorage::find`](crate::CorpusStorage::find) as table to the `output`.
+///
+/// Each matched node is a row with the columns
+/// - `match` - the position of the match in the given list,
+/// - `node` - the position of the node in the match,
+/// - `node_name` - the name of the matched node,
+/// - `anno_ns` and `anno_name` - the qualified name of the matched annotation or null if the node itself was matched.
+pub fn write_matches<W: Write + Send>(
+    matches: &[String],
+    format: ArrowFormat,
+    output: W,
+) -> Result<()> {
+    let mut match_nr = Vec::new();
+    let mut node_nr = Vec::new();
+    let mut node_name = Vec::new();
+    let mut anno_ns = Vec::new();
+    let mut anno_name = Vec::new();
+
+    for (i, m) in matches.iter().enumerate() {
+        for (j, node) in m.split_whitespace().enumerate() {
+            match_nr.push(i as u64);
+            node_nr.push(j as u64);
+            let elements: Vec<&str> = node.splitn(3, "::").collect();
+            match elements.as_slice() {
+                [ns, name, node] => {
+                    anno_ns.push(Some(*ns));
+                    anno_name.push(Some(*name));
+                    node_name.push(*node);
+                }
+                [name, node] => {
+                    anno_ns.push(None);
+                    anno_name.push(Some(*name));
+                    node_name.push(*node);
+                }
+                _ => {
+                    anno_ns.push(None);
+                    anno_name.push(None);
+                    node_name.push(node);
+                }
+            }
+        }
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("match", DataType::UInt64, false),
+        Field::new("node", DataType::UInt64, false),
+        Field::new("node_name", DataType::Utf8, false),
+        Field::new("anno_ns", DataType::Utf8, true),
+        Field::new("anno_name", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from(match_nr)),
+        Arc::new(UInt64Array::from(node_nr)),
+        Arc::new(StringArray::from(node_name)),
+        Arc::new(StringArray::from(anno_ns)),
+        Arc::new(StringArray::from(anno_name)),
+    ];
+    let batch = RecordBatch::try_new(Arc::new(schema), columns)?;
+    write_batch(&batch, format, output)
+}
+
+/// Write the result of [`CorpusStorage::frequency`](crate::CorpusStorage::frequency) as table to the `output`.
+///
+/// There is a column for each of the `column_names`, which must have the same length as the frequency
+/// definition, and an additional `count` column.
+pub fn write_frequency_table<W: Write + Send>(
+    table: &FrequencyTable<String>,
+    column_names: &[&str],
+    format: ArrowFormat,
+    output: W,
+) -> Result<()> {
+    let mut fields = Vec::with_capacity(column_names.len() + 1);
+    let mut columns: Vec<ArrayRef> = Vec::with_capacity(column_names.len() + 1);
+    for (i, name) in column_names.iter().enumerate() {
+        fields.push(Field::new(*name, DataType::Utf8, true));
+        let values: StringArray = table
+            .iter()
+            .map(|row| row.values.get(i).map(String::as_str))
+            .collect();
+        columns.push(Arc::new(values));
+    }
+    fields.push(Field::new("count", DataType::UInt64, false));
+    let counts: UInt64Array = table.iter().map(|row| Some(row.count as u64)).collect();
+    columns.push(Arc::new(counts));
+
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)?;
+    write_batch(&batch, format, output)
+}
+
+/// Write the token stream of a `graph`, e.g. as returned by
+/// [`CorpusStorage::corpus_graph`](crate::CorpusStorage::corpus_graph), as table to the `output`.
+///
+/// Each token is a row with the columns
+/// - `document` - the path of the document the token belongs to,
+/// - `position` - the position of the token in its text, starting with 0,
+/// - `node_name` - the name of the token node,
+/// - `token` - the value of the token or null if the token has no value.
+pub fn write_tokens<W: Write + Send>(
+    graph: &AnnotationGraph,
+    format: ArrowFormat,
+    output: W,
+) -> Result<()> {
+    let mut document = Vec::new();
+    let mut position = Vec::new();
+    let mut node_name = Vec::new();
+    let mut token = Vec::new();
+
+    let gs_order = graph.get_graphstorage_as_ref(&Component::new(
+        AnnotationComponentType::Ordering,
+        ANNIS_NS.into(),
+        "".into(),
+    ));
+    if let Some(gs_order) = gs_order {
+        // each text starts with a token without an incoming edge in the ordering component
+        let mut first_tokens: Vec<(String, u64)> = gs_order
+            .source_nodes()
+            .filter(|n| gs_order.get_ingoing_edges(*n).next().is_none())
+            .filter_map(|n| {
+                graph
+                    .get_node_annos()
+                    .get_value_for_item(&n, &NODE_NAME_KEY)
+                    .map(|name| (name.to_string(), n))
+            })
+            .collect();
+        first_tokens.sort();
+
+        for (_, first_token) in first_tokens {
+            let mut current = Some(first_token);
+            let mut pos = 0;
+            while let Some(t) = current {
+                let name = graph
+                    .get_node_annos()
+                    .get_value_for_item(&t, &NODE_NAME_KEY)
+                    .unwrap_or_default()
+                    .to_string();
+                document.push(name[0..name.rfind('#').unwrap_or(name.len())].to_string());
+                position.push(pos);
+                token.push(
+                    graph
+                        .get_node_annos()
+                        .get_value_for_item(&t, &TOKEN_KEY)
+                        .map(|v| v.to_string()),
+                );
+                node_name.push(name);
+
+                pos += 1;
+                current = gs_order.get_outgoing_edges(t).next();
+            }
+        }
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("document", DataType::Utf8, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new("node_name", DataType::Utf8, false),
+        Field::new("token", DataType::Utf8, true),
+    ]);
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from(document)),
+        Arc::new(UInt64Array::from(position)),
+        Arc::new(StringArray::from(node_name)),
+        Arc::new(StringArray::from(token)),
+    ];
+    let batch = RecordBatch::try_new(Arc::new(schema), columns)?;
+    write_batch(&batch, format, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annis::db::example_generator;
+    use crate::annis::types::FrequencyTableRow;
+    use arrow_array::cast::AsArray;
+    use arrow_array::Array;
+    use arrow_ipc::reader::FileReader;
+    use graphannis_core::graph::update::GraphUpdate;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use std::io::Cursor;
+
+    fn read_ipc(data: Vec<u8>) -> RecordBatch {
+        let mut reader = FileReader::try_new(Cursor::new(data), None).unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn export_matches() {
+        let matches = vec![
+            "root/doc1#tok1 default_ns::pos::root/doc1#tok2".to_string(),
+            "tiger::cat::root/doc1#n1".to_string(),
+        ];
+        let mut output = Vec::new();
+        write_matches(&matches, ArrowFormat::Ipc, &mut output).unwrap();
+
+        let batch = read_ipc(output);
+        assert_eq!(3, batch.num_rows());
+        let node_name = batch.column(2).as_string::<i32>();
+        assert_eq!("root/doc1#tok1", node_name.value(0));
+        assert_eq!("root/doc1#tok2", node_name.value(1));
+        assert_eq!("root/doc1#n1", node_name.value(2));
+        let anno_name = batch.column(4).as_string::<i32>();
+        assert!(anno_name.is_null(0));
+        assert_eq!("pos", anno_name.value(1));
+        assert_eq!("cat", anno_name.value(2));
+    }
+
+    #[test]
+    fn export_frequency_table_as_parquet() {
+        let table = vec![
+            FrequencyTableRow {
+                values: vec!["NN".to_string()],
+                count: 3,
+            },
+            FrequencyTableRow {
+                values: vec!["DT".to_string()],
+                count: 1,
+            },
+        ];
+        let mut output = tempfile::tempfile().unwrap();
+        write_frequency_table(&table, &["1:pos"], ArrowFormat::Parquet, &mut output).unwrap();
+
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(output)
+            .unwrap()
+            .build()
+            .unwrap();
+        let batch = reader.next().unwrap().unwrap();
+        assert_eq!("1:pos", batch.schema().field(0).name());
+        assert_eq!("DT", batch.column(0).as_string::<i32>().value(1));
+        let counts = batch
+            .column(1)
+            .as_primitive::<arrow_array::types::UInt64Type>();
+        assert_eq!(3, counts.value(0));
+        assert_eq!(1, counts.value(1));
+    }
+
+    #[test]
+    fn export_tokens() {
+        let mut u = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut u);
+        example_generator::create_tokens(&mut u, Some("root/doc1"));
+        let mut graph = AnnotationGraph::with_default_graphstorages(false).unwrap();
+        graph.apply_update(&mut u, |_| {}).unwrap();
+
+        let mut output = Vec::new();
+        write_tokens(&graph, ArrowFormat::Ipc, &mut output).unwrap();
+
+        let batch = read_ipc(output);
+        assert_eq!(11, batch.num_rows());
+        assert_eq!("root/doc1", batch.column(0).as_string::<i32>().value(0));
+        let token = batch.column(3).as_string::<i32>();
+        assert_eq!("Is", token.value(0));
+        assert_eq!("this", token.value(1));
+        assert_eq!("?", token.value(10));
+    }
+}
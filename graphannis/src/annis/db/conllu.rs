@@ -0,0 +1,341 @@
+//! Import of dependency treebanks in the [CoNLL-U format](https://universaldependencies.org/format.html).
+//!
+//! Each file is a document of the corpus. The words of a sentence are tokens with the `FORM` column as value,
+//! the other columns are mapped to node annotations in the `conll` namespace (each feature of the `FEATS` column
+//! is a separate annotation) and the `HEAD` and `DEPREL` columns to edges of the `Pointing/dep/dep` component
+//! from the head to the dependent.
+//! Each sentence is a span with the `sent_id` and `text` comments as annotations.
+
+use super::aql::model::{AnnotationComponentType, TOK, TOK_WHITESPACE_AFTER};
+use crate::annis::errors::*;
+use crate::annis::types::CorpusConfiguration;
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::AnnotationGraph;
+use graphannis_core::graph::ANNIS_NS;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+const CONLL_NS: &str = "conll";
+const DEP_LAYER: &str = "dep";
+
+/// A single word line of a sentence.
+struct Word {
+    id: String,
+    form: String,
+    lemma: Option<String>,
+    upos: Option<String>,
+    xpos: Option<String>,
+    feats: Vec<(String, String)>,
+    head: Option<String>,
+    deprel: Option<String>,
+    space_after: bool,
+}
+
+#[derive(Default)]
+struct Sentence {
+    sent_id: Option<String>,
+    text: Option<String>,
+    words: Vec<Word>,
+}
+
+/// The value of a column, or `None` if the column is empty (`_`).
+fn column_value(value: &str) -> Option<String> {
+    if value == "_" || value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn parse_word(columns: &[&str], file: &str, line: usize) -> Result<Word> {
+    if columns.len() != 10 {
+        return Err(ConllUError::InvalidLine {
+            file: file.to_string(),
+            line,
+            reason: format!("expected 10 columns, but found {}", columns.len()),
+        }
+        .into());
+    }
+    let feats = column_value(columns[5])
+        .map(|feats| {
+            feats
+                .split('|')
+                .filter_map(|f| f.split_once('='))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    let space_after = !columns[9].split('|').any(|m| m == "SpaceAfter=No");
+    Ok(Word {
+        id: columns[0].to_string(),
+        form: columns[1].to_string(),
+        lemma: column_value(columns[2]),
+        upos: column_value(columns[3]),
+        xpos: column_value(columns[4]),
+        feats,
+        head: column_value(columns[6]),
+        deprel: column_value(columns[7]),
+        space_after,
+    })
+}
+
+fn read_sentences(path: &Path) -> Result<Vec<Sentence>> {
+    let file_name = path.to_string_lossy().to_string();
+    let reader = BufReader::new(File::open(path)?);
+
+    let mut sentences = Vec::new();
+    let mut current = Sentence::default();
+    for (line_idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            if !current.words.is_empty() {
+                sentences.push(std::mem::take(&mut current));
+            }
+        } else if let Some(comment) = line.strip_prefix('#') {
+            if let Some((key, value)) = comment.split_once('=') {
+                match key.trim() {
+                    "sent_id" => current.sent_id = Some(value.trim().to_string()),
+                    "text" => current.text = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+        } else {
+            let columns: Vec<&str> = line.split('\t').collect();
+            let id = columns[0];
+            if id.contains('-') || id.contains('.') {
+                // multiword tokens and empty nodes are not part of the token layer
+                continue;
+            }
+            current
+                .words
+                .push(parse_word(&columns, &file_name, line_idx + 1)?);
+        }
+    }
+    if !current.words.is_empty() {
+        sentences.push(current);
+    }
+    Ok(sentences)
+}
+
+fn add_label(
+    updates: &mut GraphUpdate,
+    node_name: &str,
+    ns: &str,
+    name: &str,
+    value: &str,
+) -> Result<()> {
+    updates.add_event(UpdateEvent::AddNodeLabel {
+        node_name: node_name.to_string(),
+        anno_ns: ns.to_string(),
+        anno_name: name.to_string(),
+        anno_value: value.to_string(),
+    })?;
+    Ok(())
+}
+
+fn add_document(
+    updates: &mut GraphUpdate,
+    corpus_name: &str,
+    document_name: &str,
+    sentences: &[Sentence],
+) -> Result<()> {
+    let document_path = format!("{}/{}", corpus_name, document_name);
+    updates.add_event(UpdateEvent::AddNode {
+        node_name: document_path.clone(),
+        node_type: "corpus".to_string(),
+    })?;
+    add_label(updates, &document_path, ANNIS_NS, "doc", document_name)?;
+    updates.add_event(UpdateEvent::AddEdge {
+        source_node: document_path.clone(),
+        target_node: corpus_name.to_string(),
+        layer: ANNIS_NS.to_string(),
+        component_type: AnnotationComponentType::PartOf.to_string(),
+        component_name: "".to_string(),
+    })?;
+
+    let mut token_counter = 0;
+    let mut previous_token: Option<String> = None;
+    for (sentence_idx, sentence) in sentences.iter().enumerate() {
+        // map the word IDs, which are only unique for the sentence, to the node names
+        let mut word_nodes: HashMap<&str, String> = HashMap::new();
+        for w in &sentence.words {
+            token_counter += 1;
+            let node_name = format!("{}#t{}", document_path, token_counter);
+            updates.add_event(UpdateEvent::AddNode {
+                node_name: node_name.clone(),
+                node_type: "node".to_string(),
+            })?;
+            add_label(updates, &node_name, ANNIS_NS, TOK, &w.form)?;
+            if w.space_after {
+                add_label(updates, &node_name, ANNIS_NS, TOK_WHITESPACE_AFTER, " ")?;
+            }
+            for (name, value) in [("lemma", &w.lemma), ("upos", &w.upos), ("xpos", &w.xpos)] {
+                if let Some(value) = value {
+                    add_label(updates, &node_name, CONLL_NS, name, value)?;
+                }
+            }
+            for (name, value) in &w.feats {
+                add_label(updates, &node_name, CONLL_NS, name, value)?;
+            }
+            updates.add_event(UpdateEvent::AddEdge {
+                source_node: node_name.clone(),
+                target_node: document_path.clone(),
+                layer: ANNIS_NS.to_string(),
+                component_type: AnnotationComponentType::PartOf.to_string(),
+                component_name: "".to_string(),
+            })?;
+            if let Some(previous_token) = previous_token {
+                updates.add_event(UpdateEvent::AddEdge {
+                    source_node: previous_token,
+                    target_node: node_name.clone(),
+                    layer: ANNIS_NS.to_string(),
+                    component_type: AnnotationComponentType::Ordering.to_string(),
+                    component_name: "".to_string(),
+                })?;
+            }
+            previous_token = Some(node_name.clone());
+            word_nodes.insert(&w.id, node_name);
+        }
+
+        // the sentence is a span over all its tokens
+        let sentence_node = format!("{}#s{}", document_path, sentence_idx + 1);
+        updates.add_event(UpdateEvent::AddNode {
+            node_name: sentence_node.clone(),
+            node_type: "node".to_string(),
+        })?;
+        let sent_id = sentence
+            .sent_id
+            .clone()
+            .unwrap_or_else(|| (sentence_idx + 1).to_string());
+        add_label(updates, &sentence_node, CONLL_NS, "sent_id", &sent_id)?;
+        if let Some(text) = &sentence.text {
+            add_label(updates, &sentence_node, CONLL_NS, "text", text)?;
+        }
+        updates.add_event(UpdateEvent::AddEdge {
+            source_node: sentence_node.clone(),
+            target_node: document_path.clone(),
+            layer: ANNIS_NS.to_string(),
+            component_type: AnnotationComponentType::PartOf.to_string(),
+            component_name: "".to_string(),
+        })?;
+        for w in &sentence.words {
+            updates.add_event(UpdateEvent::AddEdge {
+                source_node: sentence_node.clone(),
+                target_node: word_nodes[w.id.as_str()].clone(),
+                layer: ANNIS_NS.to_string(),
+                component_type: AnnotationComponentType::Coverage.to_string(),
+                component_name: "".to_string(),
+            })?;
+        }
+
+        // add the dependency edges from the head to the dependent, the root has no incoming edge
+        for w in &sentence.words {
+            let head_node = match w.head.as_deref() {
+                None | Some("0") => continue,
+                Some(head) => word_nodes.get(head),
+            };
+            if let Some(head_node) = head_node {
+                let dependent = word_nodes[w.id.as_str()].clone();
+                updates.add_event(UpdateEvent::AddEdge {
+                    source_node: head_node.clone(),
+                    target_node: dependent.clone(),
+                    layer: DEP_LAYER.to_string(),
+                    component_type: AnnotationComponentType::Pointing.to_string(),
+                    component_name: DEP_LAYER.to_string(),
+                })?;
+                if let Some(deprel) = &w.deprel {
+                    updates.add_event(UpdateEvent::AddEdgeLabel {
+                        source_node: head_node.clone(),
+                        target_node: dependent,
+                        layer: DEP_LAYER.to_string(),
+                        component_type: AnnotationComponentType::Pointing.to_string(),
+                        component_name: DEP_LAYER.to_string(),
+                        anno_ns: CONLL_NS.to_string(),
+                        anno_name: "deprel".to_string(),
+                        anno_value: deprel.clone(),
+                    })?;
+                }
+            } else {
+                warn!(
+                    "Ignoring unknown head {} of word {} in sentence {}",
+                    w.head.as_deref().unwrap_or_default(),
+                    w.id,
+                    sent_id
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load a corpus from a single CoNLL-U file or a directory with CoNLL-U files (with the extension `conllu`).
+///
+/// The name of the corpus is the name of the file or directory without the extension.
+pub fn load<F>(
+    path: &Path,
+    disk_based: bool,
+    progress_callback: F,
+) -> Result<(String, AnnotationGraph, CorpusConfiguration)>
+where
+    F: Fn(&str),
+{
+    let corpus_name = path
+        .file_stem()
+        .map(|f| f.to_string_lossy().to_string())
+        .unwrap_or_else(|| "UnknownCorpus".to_string());
+
+    let files: Vec<PathBuf> = if path.is_dir() {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            if file.is_file()
+                && file
+                    .extension()
+                    .map(|ext| ext.to_string_lossy().to_ascii_lowercase() == "conllu")
+                    .unwrap_or(false)
+            {
+                files.push(file);
+            }
+        }
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut updates = GraphUpdate::new();
+    updates.add_event(UpdateEvent::AddNode {
+        node_name: corpus_name.clone(),
+        node_type: "corpus".to_string(),
+    })?;
+    for file in files {
+        progress_callback(&format!("reading CoNLL-U file {}", file.to_string_lossy()));
+        let document_name = file
+            .file_stem()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let sentences = read_sentences(&file)?;
+        add_document(&mut updates, &corpus_name, &document_name, &sentences)?;
+    }
+
+    let mut db = AnnotationGraph::with_default_graphstorages(disk_based)?;
+    db.apply_update(&mut updates, &progress_callback)?;
+
+    progress_callback("calculating node statistics");
+    db.get_node_annos_mut().calculate_statistics();
+    for c in db.get_all_components(None, None) {
+        progress_callback(&format!("calculating statistics for component {}", c));
+        db.calculate_component_statistics(&c)?;
+        db.optimize_gs_impl(&c)?;
+    }
+
+    progress_callback(&format!(
+        "finished loading CoNLL-U from {}",
+        path.to_string_lossy()
+    ));
+
+    Ok((corpus_name, db, CorpusConfiguration::default()))
+}
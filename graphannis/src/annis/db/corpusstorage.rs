@@ -1,19 +1,27 @@
 use crate::annis::db::aql;
 use crate::annis::db::aql::operators;
 use crate::annis::db::aql::operators::RangeSpec;
-use crate::annis::db::exec::nodesearch::NodeSearchSpec;
+use crate::annis::db::conllu;
+use crate::annis::db::exec::nodesearch::{self, NodeSearchSpec};
+use crate::annis::db::exec::parallel;
+use crate::annis::db::node_name_scheme::{apply_node_name_scheme, NodeNameScheme};
 use crate::annis::db::plan::ExecutionPlan;
 use crate::annis::db::query;
 use crate::annis::db::query::conjunction::Conjunction;
 use crate::annis::db::query::disjunction::Disjunction;
+use crate::annis::db::query::{JoinThresholds, QueryLimits};
 use crate::annis::db::relannis;
 use crate::annis::db::sort_matches::CollationType;
 use crate::annis::db::token_helper;
-use crate::annis::db::token_helper::TokenHelper;
+use crate::annis::db::token_helper::{TokenHelper, WhitespaceHandling};
 use crate::annis::errors::*;
-use crate::annis::types::CountExtra;
 use crate::annis::types::{
-    CorpusConfiguration, FrequencyTable, FrequencyTableRow, QueryAttributeDescription,
+    AnnotationValueCount, CountExtra, DocumentCounts, DocumentStatistics, DocumentTermCount,
+    QueryPlan, QueryWarning, QuirksRule, Suggestion,
+};
+use crate::annis::types::{
+    CorpusConfiguration, DerivedOperatorDefinition, FrequencyTable, FrequencyTableRow,
+    MatchWithOffsets, MatchedNode, QueryAttributeDescription, SegmentationDefinition, TextOffset,
 };
 use crate::annis::util::quicksort;
 use crate::annis::{db, util::TimeoutCheck};
@@ -23,45 +31,72 @@ use crate::{
     AnnotationGraph,
 };
 use fmt::Display;
+#[cfg(feature = "corpus-storage")]
 use fs2::FileExt;
 use graphannis_core::{
-    annostorage::{MatchGroup, ValueSearch},
+    annostorage::{AnnoKeyUsage, MatchGroup, ValueSearch},
     graph::{
-        storage::GraphStatistic, update::GraphUpdate, ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE,
+        storage::{GraphStatistic, GraphStorage},
+        update::{GraphUpdate, UpdateEvent, UpdateStatistics},
+        LoadReport, ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE, NODE_TYPE_KEY,
+    },
+    types::{AnnoKey, Annotation, Component, ComponentType, Edge, NodeID},
+    util::{
+        encryption::{self, EncryptionKey},
+        memory_estimation,
+        progress::ProgressThrottle,
+        UnicodeNormalization,
     },
-    types::{AnnoKey, Annotation, Component, Edge, NodeID},
-    util::memory_estimation,
 };
 use linked_hash_map::LinkedHashMap;
 use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 use smartstring::alias::String as SmartString;
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{
+    atomic::{AtomicBool, AtomicUsize, Ordering},
+    Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
 use std::thread;
 use std::{borrow::Cow, time::Duration};
 
 use rustc_hash::FxHashMap;
 
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::{
     ffi::CString,
     io::{BufReader, Write},
 };
 
-use aql::model::AnnotationComponentType;
+use aql::model::{rebuild_derived_components, AnnotationComponentType, TOKEN_KEY};
 use db::AnnotationStorage;
 
-#[cfg(test)]
+#[cfg(feature = "corpus-storage")]
+mod docstats;
+#[cfg(feature = "corpus-storage")]
+mod eviction;
+#[cfg(feature = "corpus-storage")]
+mod lease;
+#[cfg(feature = "corpus-storage")]
+mod plancache;
+#[cfg(feature = "corpus-storage")]
+mod sharding;
+#[cfg(all(test, feature = "corpus-storage"))]
 mod tests;
 
 const MAX_VECTOR_RESERVATION: usize = 10_000_000;
 
+/// Default number of corpora that are imported in parallel from a ZIP file.
+const DEFAULT_MAX_CONCURRENT_IMPORTS: usize = 4;
+
+#[cfg(feature = "corpus-storage")]
 enum CacheEntry {
     Loaded(AnnotationGraph),
     NotLoaded,
@@ -141,6 +176,21 @@ pub struct CorpusInfo {
     /// This information is stored in the "corpus-config.toml` file in the data directory
     /// and loaded on demand.
     pub config: CorpusConfiguration,
+    /// Number of nodes in this corpus (including corpus and document nodes).
+    /// For corpora that are not loaded, this information is taken from the persisted corpus summary if available.
+    pub number_of_nodes: Option<usize>,
+    /// Estimated amount of main memory in bytes that the fully loaded corpus uses.
+    /// For corpora that are not fully loaded, this information is taken from the persisted corpus summary if available.
+    pub estimated_memory_size: Option<usize>,
+    /// Number of bytes the corpus used on disk when it was saved the last time, taken from the persisted corpus summary.
+    /// Use [`CorpusStorage::disk_usage`] to inspect the current state of the corpus directory.
+    pub disk_usage: Option<DiskUsage>,
+    /// Describes which persisted state was chosen when the corpus was loaded
+    /// and how an interrupted update was recovered. Only available for loaded corpora.
+    pub load_report: Option<LoadReport>,
+    /// Number of annotations, distinct values and used bytes (memory or disk, depending on the annotation storage)
+    /// for each node annotation key. Only available for loaded corpora.
+    pub node_annotation_usage: Option<Vec<AnnoKeyUsage>>,
 }
 
 impl fmt::Display for CorpusInfo {
@@ -171,6 +221,38 @@ impl fmt::Display for CorpusInfo {
                 memory_size as f64 / f64::from(1024 * 1024)
             )?;
         }
+        if let Some(number_of_nodes) = self.number_of_nodes {
+            writeln!(f, "Nodes: {}", number_of_nodes)?;
+        }
+        if let Some(memory_size) = self.estimated_memory_size {
+            writeln!(
+                f,
+                "Memory when fully loaded: {:.2} MB",
+                memory_size as f64 / f64::from(1024 * 1024)
+            )?;
+        }
+        if let Some(disk_usage) = &self.disk_usage {
+            write!(f, "{}", disk_usage)?;
+        }
+        if let Some(load_report) = &self.load_report {
+            writeln!(f, "Recovery: {}", load_report)?;
+        }
+        if let Some(node_annotation_usage) = &self.node_annotation_usage {
+            // only show the largest annotation keys
+            let mut largest: Vec<&AnnoKeyUsage> = node_annotation_usage.iter().collect();
+            largest.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+            for usage in largest.into_iter().take(10) {
+                writeln!(
+                    f,
+                    "Node annotation {}::{}: {} annotations, {} distinct values, {:.2} MB",
+                    usage.key.ns,
+                    usage.key.name,
+                    usage.count,
+                    usage.distinct_values,
+                    usage.bytes as f64 / f64::from(1024 * 1024)
+                )?;
+            }
+        }
         if !self.graphstorages.is_empty() {
             writeln!(f, "------------")?;
             for gs in &self.graphstorages {
@@ -182,6 +264,384 @@ impl fmt::Display for CorpusInfo {
     }
 }
 
+/// Number of bytes the different parts of a corpus use on disk.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiskUsage {
+    /// Size of the node annotation storage.
+    pub node_annotations: u64,
+    /// Size of each component, sorted by the component.
+    pub components: Vec<(Component<AnnotationComponentType>, u64)>,
+    /// Size of the write-ahead log of updates that have not been persisted yet.
+    pub update_log: u64,
+    /// Size of the files linked to the corpus (e.g. media files).
+    pub linked_files: u64,
+    /// Size of the backup of the previous version of the corpus, which only exists while saving the corpus.
+    pub backup: u64,
+}
+
+impl DiskUsage {
+    /// Inspect the given corpus directory and collect the size of its parts.
+    fn from_corpus_directory(corpus_dir: &Path) -> std::io::Result<DiskUsage> {
+        let current_dir = corpus_dir.join("current");
+        let gs_dir = current_dir.join("gs");
+
+        let mut components = Vec::new();
+        for ctype in AnnotationComponentType::all_component_types() {
+            let ctype_dir = gs_dir.join(ctype.to_string());
+            if !ctype_dir.is_dir() {
+                continue;
+            }
+            for layer in ctype_dir.read_dir()? {
+                let layer = layer?;
+                if !layer.path().is_dir() {
+                    continue;
+                }
+                let layer_name: String = layer.file_name().to_string_lossy().into();
+                // The component with the empty name is stored directly in the layer directory
+                // and all other components of the layer in sub-directories.
+                if layer.path().join("impl.cfg").is_file() {
+                    components.push((
+                        Component::new(ctype.clone(), layer_name.clone().into(), "".into()),
+                        directory_size(&layer.path(), false)?,
+                    ));
+                }
+                for name in layer.path().read_dir()? {
+                    let name = name?;
+                    if name.path().join("impl.cfg").is_file() {
+                        components.push((
+                            Component::new(
+                                ctype.clone(),
+                                layer_name.clone().into(),
+                                name.file_name().to_string_lossy().into(),
+                            ),
+                            directory_size(&name.path(), true)?,
+                        ));
+                    }
+                }
+            }
+        }
+        components.sort();
+
+        // The segments of the update log are located in the corpus directory. Older versions used a single
+        // file, which might be located in the corpus directory or in the current version of the corpus.
+        let update_log = directory_size(&corpus_dir.join("update_log"), false)?
+            + file_size(&corpus_dir.join("update_log.bin"))?
+            + file_size(&current_dir.join("update_log.bin"))?;
+        // Everything else in the current version of the corpus belongs to the node annotations
+        let mut node_annotations = 0;
+        if current_dir.is_dir() {
+            for entry in current_dir.read_dir()? {
+                let entry = entry?;
+                let entry_path = entry.path();
+                if entry_path == gs_dir || entry.file_name() == "update_log.bin" {
+                    continue;
+                } else if entry_path.is_dir() {
+                    node_annotations += directory_size(&entry_path, true)?;
+                } else {
+                    node_annotations += file_size(&entry_path)?;
+                }
+            }
+        }
+
+        Ok(DiskUsage {
+            node_annotations,
+            components,
+            update_log,
+            linked_files: directory_size(&corpus_dir.join("files"), true)?,
+            backup: directory_size(&corpus_dir.join("backup"), true)?,
+        })
+    }
+
+    /// Sum of the sizes of all parts of the corpus.
+    pub fn total(&self) -> u64 {
+        self.node_annotations
+            + self.components.iter().map(|(_, size)| size).sum::<u64>()
+            + self.update_log
+            + self.linked_files
+            + self.backup
+    }
+}
+
+impl fmt::Display for DiskUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let to_mb = |size: u64| size as f64 / f64::from(1024 * 1024);
+        writeln!(f, "Disk usage: {:.2} MB", to_mb(self.total()))?;
+        writeln!(
+            f,
+            "Disk usage of node annotations: {:.2} MB",
+            to_mb(self.node_annotations)
+        )?;
+        for (c, size) in &self.components {
+            writeln!(f, "Disk usage of {}: {:.2} MB", c, to_mb(*size))?;
+        }
+        if self.update_log > 0 {
+            writeln!(
+                f,
+                "Disk usage of update log: {:.2} MB",
+                to_mb(self.update_log)
+            )?;
+        }
+        if self.linked_files > 0 {
+            writeln!(
+                f,
+                "Disk usage of linked files: {:.2} MB",
+                to_mb(self.linked_files)
+            )?;
+        }
+        if self.backup > 0 {
+            writeln!(f, "Disk usage of backup: {:.2} MB", to_mb(self.backup))?;
+        }
+        Ok(())
+    }
+}
+
+/// Machine-readable description of the annotations and components of a corpus.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CorpusSchema {
+    /// Name of the corpus.
+    pub corpus_name: String,
+    /// All node annotation keys, sorted by namespace and name.
+    pub node_annotations: Vec<AnnotationSchema>,
+    /// All components of the corpus with their edge annotations.
+    pub components: Vec<ComponentSchema>,
+    /// Names of the segmentations (ordering components besides the default token ordering).
+    pub segmentations: Vec<String>,
+}
+
+/// Description of a single annotation key as part of a [`CorpusSchema`](struct.CorpusSchema.html).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AnnotationSchema {
+    /// Namespace of the annotation.
+    pub ns: String,
+    /// Name of the annotation.
+    pub name: String,
+    /// Number of nodes or edges that have this annotation.
+    pub count: usize,
+    /// Number of distinct values of this annotation.
+    /// For annotations with a large number of values, this is estimated from the statistics.
+    pub distinct_values: usize,
+    /// The most frequent values of this annotation, sorted by their frequency.
+    /// For annotations with a large number of values, these are taken from the values sampled for the statistics.
+    pub example_values: Vec<String>,
+}
+
+/// Description of a single component as part of a [`CorpusSchema`](struct.CorpusSchema.html).
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ComponentSchema {
+    /// Type of the component.
+    pub component_type: AnnotationComponentType,
+    /// Layer of the component.
+    pub layer: String,
+    /// Name of the component.
+    pub name: String,
+    /// Name of the graph storage implementation.
+    pub implementation: String,
+    /// Graph statistics, if available.
+    pub statistics: Option<GraphStatistic>,
+    /// All edge annotation keys of this component, sorted by namespace and name.
+    pub edge_annotations: Vec<AnnotationSchema>,
+}
+
+/// Maximal number of example values for each annotation in a corpus schema.
+const MAX_SCHEMA_EXAMPLE_VALUES: usize = 5;
+
+/// Annotation keys with at most this number of annotations get their exact number of distinct values and most
+/// frequent values in a corpus schema. For larger keys, both are estimated from the values sampled for the statistics,
+/// so keys with a different value for each item (like `annis::node_name`) don't need to retrieve all their values.
+const MAX_EXACT_SCHEMA_VALUES: usize = 1000;
+
+fn create_annotation_schema<T>(annos: &dyn AnnotationStorage<T>) -> Vec<AnnotationSchema>
+where
+    T: Send + Sync + MallocSizeOf,
+{
+    let mut keys = annos.annotation_keys();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| {
+            let count = annos.number_of_annotations_by_name(Some(&key.ns), &key.name);
+            let sampled_values = if count > MAX_EXACT_SCHEMA_VALUES {
+                annos.get_sampled_values(&key)
+            } else {
+                Vec::new()
+            };
+            let (distinct_values, example_values) = if sampled_values.is_empty() {
+                let values = annos.get_all_values(&key, true);
+                (values.len(), values)
+            } else {
+                (
+                    estimate_distinct_values(&sampled_values, count),
+                    sampled_values.into_iter().map(|(val, _)| val).collect(),
+                )
+            };
+            AnnotationSchema {
+                count,
+                distinct_values,
+                example_values: example_values
+                    .into_iter()
+                    .take(MAX_SCHEMA_EXAMPLE_VALUES)
+                    .map(|v| v.to_string())
+                    .collect(),
+                ns: key.ns.to_string(),
+                name: key.name.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Estimate the number of distinct values of an annotation with `count` annotations from its sampled values and the
+/// number of times each value has been sampled.
+///
+/// This uses the estimator by Haas and Stokes (also used by PostgreSQL), which returns the number of annotations
+/// if every sampled value is unique and the number of sampled values if every value has been sampled several times.
+fn estimate_distinct_values(sampled_values: &[(Cow<str>, usize)], count: usize) -> usize {
+    let sample_size = sampled_values.iter().map(|(_, n)| n).sum::<usize>() as f64;
+    let sampled_distinct = sampled_values.len() as f64;
+    let sampled_once = sampled_values.iter().filter(|(_, n)| *n == 1).count() as f64;
+    let estimate = sample_size * sampled_distinct
+        / (sample_size - sampled_once + sampled_once * sample_size / count as f64);
+    (estimate.round() as usize)
+        .max(sampled_values.len())
+        .min(count)
+}
+
+/// Summary of a corpus, which is persisted in the corpus directory so that information about
+/// a corpus is available without loading it.
+///
+/// The summary is calculated each time the corpus is saved, so reading it is cheap.
+#[derive(Serialize, Deserialize)]
+struct CorpusSummary {
+    number_of_nodes: usize,
+    memory_size: usize,
+    graphstorages: Vec<GraphStorageSummary>,
+    /// Missing in summaries of older versions.
+    #[serde(default)]
+    disk_usage: Option<DiskUsageSummary>,
+}
+
+impl CorpusSummary {
+    /// Create the summary for a fully loaded corpus and its directory.
+    ///
+    /// Returns `None` if not all components of the corpus are loaded.
+    fn calculate(
+        db: &AnnotationGraph,
+        corpus_dir: &Path,
+        mem_ops: &mut MallocSizeOfOps,
+    ) -> Option<CorpusSummary> {
+        let mut graphstorages = Vec::new();
+        for c in db.get_all_components(None, None) {
+            let gs = db.get_graphstorage_as_ref(&c)?;
+            graphstorages.push(GraphStorageSummary {
+                component_type: c.get_type(),
+                component_layer: c.layer.to_string(),
+                component_name: c.name.to_string(),
+                implementation: gs.serialization_id(),
+                number_of_annotations: gs.get_anno_storage().number_of_annotations(),
+                statistics: gs.get_statistics().cloned(),
+            });
+        }
+        let disk_usage = match DiskUsage::from_corpus_directory(corpus_dir) {
+            Ok(disk_usage) => Some(DiskUsageSummary::from(disk_usage)),
+            Err(e) => {
+                warn!(
+                    "Could not determine disk usage of corpus directory {}: {:?}",
+                    corpus_dir.to_string_lossy(),
+                    e
+                );
+                None
+            }
+        };
+        Some(CorpusSummary {
+            number_of_nodes: db
+                .get_node_annos()
+                .number_of_annotations_by_name(Some(ANNIS_NS), NODE_NAME),
+            memory_size: db.size_of(mem_ops),
+            graphstorages,
+            disk_usage,
+        })
+    }
+}
+
+/// Same as [`DiskUsage`], but with a representation of the components that can be serialized as TOML.
+#[derive(Serialize, Deserialize, Clone)]
+struct DiskUsageSummary {
+    node_annotations: u64,
+    update_log: u64,
+    linked_files: u64,
+    backup: u64,
+    // TOML requires the arrays of tables to be written after the plain values
+    components: Vec<ComponentDiskUsageSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct ComponentDiskUsageSummary {
+    component_type: AnnotationComponentType,
+    component_layer: String,
+    component_name: String,
+    size: u64,
+}
+
+impl From<DiskUsage> for DiskUsageSummary {
+    fn from(usage: DiskUsage) -> Self {
+        DiskUsageSummary {
+            node_annotations: usage.node_annotations,
+            components: usage
+                .components
+                .into_iter()
+                .map(|(c, size)| ComponentDiskUsageSummary {
+                    component_type: c.get_type(),
+                    component_layer: c.layer.to_string(),
+                    component_name: c.name.to_string(),
+                    size,
+                })
+                .collect(),
+            update_log: usage.update_log,
+            linked_files: usage.linked_files,
+            backup: usage.backup,
+        }
+    }
+}
+
+impl From<DiskUsageSummary> for DiskUsage {
+    fn from(summary: DiskUsageSummary) -> Self {
+        DiskUsage {
+            node_annotations: summary.node_annotations,
+            components: summary
+                .components
+                .into_iter()
+                .map(|c| {
+                    (
+                        Component::new(
+                            c.component_type,
+                            c.component_layer.into(),
+                            c.component_name.into(),
+                        ),
+                        c.size,
+                    )
+                })
+                .collect(),
+            update_log: summary.update_log,
+            linked_files: summary.linked_files,
+            backup: summary.backup,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphStorageSummary {
+    component_type: AnnotationComponentType,
+    component_layer: String,
+    component_name: String,
+    implementation: String,
+    number_of_annotations: usize,
+    statistics: Option<GraphStatistic>,
+}
+
+const CORPUS_SUMMARY_FILE: &str = "corpus-summary.toml";
+const NODE_ID_GENERATION_FILE: &str = "node-id-generation";
+const CORPUS_CONFIG_FILE: &str = "corpus-config.toml";
+const CORPUS_GROUPS_FILE: &str = "corpus-groups.toml";
+const CORPUS_SHARDS_FILE: &str = "corpus-shards.toml";
+
 /// Defines the order of results of a `find` query.
 #[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 #[repr(C)]
@@ -195,6 +655,9 @@ pub enum ResultOrder {
     /// Results are not ordered at all, but also not actively randomized
     /// Each new query *might* result in a different order.
     NotSorted,
+    /// A random ordering which is stable for the given seed.
+    /// Executing the same query on the same corpus with the same seed will always result in the same order.
+    Seeded(u64),
 }
 
 impl Default for ResultOrder {
@@ -203,9 +666,51 @@ impl Default for ResultOrder {
     }
 }
 
+/// Defines how the nodes of a match are described in the match IDs returned by a `find` query.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub enum MatchIdFormat {
+    /// Only use the node name (including the matched annotation key), e.g. `tiger::pos::root/doc1#tok1`.
+    NodeName,
+    /// Append the internal node ID to the node name, separated by an `@`, e.g. `tiger::pos::root/doc1#tok1@42`.
+    /// The IDs are only valid for the current [node ID generation](struct.CorpusStorage.html#method.node_id_generation)
+    /// of the corpus.
+    NodeNameAndId,
+}
+
+impl Default for MatchIdFormat {
+    fn default() -> Self {
+        MatchIdFormat::NodeName
+    }
+}
+
+/// Options for [`CorpusStorage::find_with_options`] which define which matches are returned and how they are described.
+///
+/// The default options return all matches in the normal order with all query nodes, the node names as match IDs and
+/// without text offsets, like [`CorpusStorage::find`].
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FindOptions {
+    /// Skip the `n` first results, where `n` is the offset.
+    pub offset: usize,
+    /// Return at most `n` matches, where `n` is the limit. Use `None` to allow unlimited result sizes.
+    pub limit: Option<usize>,
+    /// The order of the matches.
+    pub order: ResultOrder,
+    /// The variables of the query nodes (e.g. `1` or `name` for a node named `#name`) to include in the output.
+    /// Use `None` to include all query nodes.
+    pub projection: Option<Vec<String>>,
+    /// How the nodes of a match are described in the match ID.
+    pub format: MatchIdFormat,
+    /// If `true`, the index of the left-most and right-most token covered by each included node is returned.
+    pub with_offsets: bool,
+}
+
+#[cfg(feature = "corpus-storage")]
 struct PreparationResult<'a> {
     query: Disjunction<'a>,
     db_entry: Arc<RwLock<CacheEntry>>,
+    /// The derived operators of the corpus, which are needed when the query is parsed again.
+    derived_operators: Vec<DerivedOperatorDefinition>,
 }
 
 /// Definition of a single attribute of a frequency query.
@@ -243,7 +748,7 @@ impl FromStr for FrequencyDefEntry {
 /// Currently, only the ANNIS Query Language (AQL) and its variants are supported, but this enum allows us to add a support for older query language versions
 /// or completely new query languages.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum QueryLanguage {
     AQL,
     /// Emulates the (sometimes problematic) behavior of AQL used in ANNIS 3
@@ -265,6 +770,39 @@ pub enum ImportFormat {
     /// [GraphML](http://graphml.graphdrawing.org/) based export-format, suitable to be imported from other graph databases.
     /// This format follows the extensions/conventions of the Neo4j [GraphML module](https://neo4j.com/docs/labs/apoc/current/import/graphml/).
     GraphML,
+    /// [CoNLL-U](https://universaldependencies.org/format.html) dependency treebanks, either a single file or a
+    /// directory with one file per document.
+    CoNLLU,
+}
+
+/// Summary of a corpus that has been loaded from an external location, but is not yet imported into the corpus storage.
+#[derive(Debug, Clone)]
+pub struct ImportAnalysis {
+    /// The name of the corpus as given by the imported data.
+    pub corpus_name: String,
+    /// Estimated number of nodes (including corpus and document nodes).
+    pub estimated_node_count: usize,
+    /// Estimated number of edges over all components.
+    pub estimated_edge_count: usize,
+    /// Sorted list of all namespaces used by node annotations, except the internal `annis` namespace.
+    pub annotation_layers: Vec<String>,
+    /// True if a corpus with the same name already exists in the corpus storage.
+    pub conflicts_with_existing: bool,
+    /// Node names that are used by more than one node and how they have been resolved.
+    pub duplicate_node_names: Vec<DuplicateNodeName>,
+}
+
+/// A corpus that has been loaded and analyzed by [CorpusStorage::analyze_import_from_fs](struct.CorpusStorage.html#method.analyze_import_from_fs),
+/// but not yet written to the data directory.
+///
+/// Use [CorpusStorage::commit_import](struct.CorpusStorage.html#method.commit_import) to finish the import.
+/// Dropping this object discards the loaded corpus.
+pub struct PendingImport {
+    /// The analysis of the loaded corpus.
+    pub analysis: ImportAnalysis,
+    graph: AnnotationGraph,
+    config: CorpusConfiguration,
+    source_dir: PathBuf,
 }
 
 /// An enum of all supported output formats of graphANNIS.
@@ -280,6 +818,74 @@ pub enum ExportFormat {
     GraphMLDirectory,
 }
 
+/// Defines how a corpus storage makes sure that no other instance uses the same directory at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockingStrategy {
+    /// Use an exclusive file lock on the `db.lock` file in the directory.
+    FileLock,
+    /// Use a lease file `db.lease` with the ID of the instance, which is renewed periodically by a background
+    /// thread. A lease that has not been renewed for longer than the `timeout` is regarded as abandoned and is
+    /// taken over by a new instance. Creating an instance fails if another live instance holds the lease.
+    /// If the lease of an instance has been taken over, e.g. because the heartbeat was delayed, all operations
+    /// that change the corpora fail with an error.
+    ///
+    /// Use this strategy for directories on network filesystems where file locks are unreliable, e.g. some NFS
+    /// setups. The clocks of all hosts accessing the directory need to be synchronized.
+    Lease {
+        /// Time after which a lease that has not been renewed is regarded as abandoned, which must be at least
+        /// three seconds.
+        timeout: Duration,
+    },
+}
+
+impl Default for LockingStrategy {
+    fn default() -> Self {
+        LockingStrategy::FileLock
+    }
+}
+
+/// The lock that is held on the corpus storage directory.
+#[cfg(feature = "corpus-storage")]
+enum DirectoryLock {
+    File(File),
+    Lease(lease::Lease),
+}
+
+#[cfg(feature = "corpus-storage")]
+impl DirectoryLock {
+    fn acquire(db_dir: &Path, strategy: LockingStrategy) -> Result<DirectoryLock> {
+        match strategy {
+            LockingStrategy::FileLock => {
+                Ok(DirectoryLock::File(create_lockfile_for_directory(db_dir)?))
+            }
+            LockingStrategy::Lease { timeout } => Ok(DirectoryLock::Lease(lease::Lease::acquire(
+                db_dir, timeout,
+            )?)),
+        }
+    }
+
+    fn release(&mut self) {
+        match self {
+            DirectoryLock::File(lock_file) => {
+                if let Err(e) = lock_file.unlock() {
+                    warn!("Could not unlock CorpusStorage lock file: {:?}", e);
+                } else {
+                    trace!("Unlocked CorpusStorage lock file");
+                }
+            }
+            DirectoryLock::Lease(lease) => lease.release(),
+        }
+    }
+
+    /// Return an error if the lock is not held anymore and the directory must not be changed.
+    fn check_valid(&self) -> Result<()> {
+        match self {
+            DirectoryLock::File(_) => Ok(()),
+            DirectoryLock::Lease(lease) => lease.check_valid(),
+        }
+    }
+}
+
 /// Different strategies how it is decided when corpora need to be removed from the cache.
 #[derive(Debug, Deserialize, Clone)]
 pub enum CacheStrategy {
@@ -309,6 +915,296 @@ impl Default for CacheStrategy {
     }
 }
 
+/// Decides which corpora are removed first when the corpus cache is larger than allowed by the [`CacheStrategy`].
+///
+/// Corpora that are pinned or needed by the current operation are never removed.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Remove the corpus that has not been accessed for the longest time first.
+    LeastRecentlyUsed,
+    /// Remove the largest corpus first, which frees the most memory with the fewest reloads.
+    /// Corpora with the same size are removed in least recently used order.
+    SizeWeighted,
+    /// Greedy-Dual-Size-Frequency: remove the corpus with the lowest ratio of access frequency multiplied by the
+    /// time it took to load the corpus to its size first.
+    /// Corpora that are cheap to load again or rarely accessed are removed before large but frequently used ones.
+    LoadCostWeighted,
+}
+
+impl Default for EvictionPolicy {
+    fn default() -> Self {
+        EvictionPolicy::LeastRecentlyUsed
+    }
+}
+
+/// Metrics of the corpus cache for a single corpus.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CorpusCacheMetrics {
+    /// Number of times the corpus has been accessed while it was already loaded.
+    pub hits: u64,
+    /// Number of times the corpus has been loaded into the cache.
+    pub loads: u64,
+    /// Number of times the corpus has been removed because the cache was too large.
+    pub evictions: u64,
+    /// Time it took to load the corpus the last time.
+    pub last_load_time: Duration,
+}
+
+/// Metrics of the corpus cache, collected since the corpus storage has been created or the metrics have been
+/// reset with [reset_cache_metrics()](struct.CorpusStorage.html#method.reset_cache_metrics).
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CacheMetrics {
+    /// Number of accesses to a corpus which was already loaded.
+    pub hits: u64,
+    /// Number of accesses to a corpus which had to be loaded first.
+    pub misses: u64,
+    /// Number of corpora that have been removed because the cache was too large.
+    pub evictions: u64,
+    /// Estimated sum of the memory of all removed corpora in bytes.
+    pub evicted_bytes: u64,
+    /// Overall time spent loading corpora into the cache.
+    pub load_time: Duration,
+    /// The metrics for each corpus that has been accessed.
+    pub corpora: BTreeMap<String, CorpusCacheMetrics>,
+}
+
+/// Configuration of the background job that re-calculates the statistics used for query planning
+/// after a corpus has been changed by updates.
+#[derive(Debug, Clone)]
+pub struct StatisticsRefresh {
+    /// Fraction of changed items, relative to the number of nodes of the corpus, after which the statistics are
+    /// re-calculated (e.g. `0.1` for 10%).
+    pub threshold: f64,
+    /// Minimal amount of time between two re-calculations for the same corpus.
+    pub min_interval: Duration,
+}
+
+impl Default for StatisticsRefresh {
+    fn default() -> Self {
+        StatisticsRefresh {
+            threshold: 0.1,
+            min_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+/// An opaque position in the result stream of a `find` query, which is used to continue the query after the last
+/// returned match with [CorpusStorage::find_with_cursor(...)](struct.CorpusStorage.html#method.find_with_cursor).
+///
+/// The cursor can be converted to a string and parsed again, e.g. to pass it to a client of a web service.
+/// It is only valid for the same query, corpora and result order it has been created for and becomes invalid
+/// when one of the corpora is changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindCursor {
+    fingerprint: u32,
+    corpus: String,
+    /// The document of the last returned match if the query was executed document by document.
+    document: Option<String>,
+    /// Number of matches of the corpus (or document, if given) that have already been returned.
+    position: usize,
+}
+
+impl FindCursor {
+    /// Stable checksum of the query, the result order and the corpora with their change ID, so a cursor can be
+    /// used with another instance of the corpus storage, but not after one of the corpora has been changed.
+    fn fingerprint<S: AsRef<str>>(
+        query: &SearchQuery<S>,
+        corpora: &[(SmartString, u64)],
+        order: ResultOrder,
+    ) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(query.query.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(format!("{:?}", query.query_language).as_bytes());
+        hasher.update(&[0]);
+        hasher.update(format!("{:?}", order).as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(query.document_filter.len() as u64).to_le_bytes());
+        for d in &query.document_filter {
+            hasher.update(d.as_bytes());
+            hasher.update(&[0]);
+        }
+        for (corpus_name, change_id) in corpora {
+            hasher.update(corpus_name.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(&change_id.to_le_bytes());
+        }
+        hasher.finalize()
+    }
+}
+
+impl fmt::Display for FindCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let document: Cow<str> = if let Some(document) = &self.document {
+            utf8_percent_encode(document, SALT_URI_ENCODE_SET).into()
+        } else {
+            Cow::Borrowed("")
+        };
+        write!(
+            f,
+            "{:x}:{}:{}:{}",
+            self.fingerprint,
+            self.position,
+            utf8_percent_encode(&self.corpus, SALT_URI_ENCODE_SET),
+            document
+        )
+    }
+}
+
+impl FromStr for FindCursor {
+    type Err = GraphAnnisError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 4 {
+            return Err(CorpusStorageError::InvalidFindCursor(s.to_string()).into());
+        }
+        let invalid = || CorpusStorageError::InvalidFindCursor(s.to_string());
+        let fingerprint = u32::from_str_radix(parts[0], 16).map_err(|_| invalid())?;
+        let position = parts[1].parse::<usize>().map_err(|_| invalid())?;
+        let corpus = percent_decode_str(parts[2])
+            .decode_utf8()
+            .map_err(|_| invalid())?
+            .to_string();
+        let document = if parts[3].is_empty() {
+            None
+        } else {
+            Some(
+                percent_decode_str(parts[3])
+                    .decode_utf8()
+                    .map_err(|_| invalid())?
+                    .to_string(),
+            )
+        };
+        Ok(FindCursor {
+            fingerprint,
+            corpus,
+            document,
+            position,
+        })
+    }
+}
+
+/// A handle to a loaded corpus that is not removed from the corpus cache while the handle exists.
+///
+/// Use [CorpusStorage::pin(...)](struct.CorpusStorage.html#method.pin) to acquire it.
+/// All read-only functions of the corpus storage can be used on the pinned corpus without the risk of
+/// reloading it from disk in between. The corpus is unpinned when the handle is dropped.
+#[cfg(feature = "corpus-storage")]
+pub struct PinnedCorpus {
+    corpus_name: String,
+    pinned_corpora: Arc<Mutex<HashMap<String, usize>>>,
+    _entry: Arc<RwLock<CacheEntry>>,
+}
+
+#[cfg(feature = "corpus-storage")]
+impl PinnedCorpus {
+    /// Name of the pinned corpus.
+    pub fn corpus_name(&self) -> &str {
+        &self.corpus_name
+    }
+}
+
+#[cfg(feature = "corpus-storage")]
+impl Drop for PinnedCorpus {
+    fn drop(&mut self) {
+        let mut pinned_corpora = self.pinned_corpora.lock().unwrap();
+        if let Some(count) = pinned_corpora.get_mut(&self.corpus_name) {
+            *count -= 1;
+            if *count == 0 {
+                pinned_corpora.remove(&self.corpus_name);
+            }
+        }
+    }
+}
+
+/// A named query that is executed as part of a batch by [CorpusStorage::execute_batch(...)](struct.CorpusStorage.html#method.execute_batch).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchQuery {
+    /// Name of the query, which is used as file name for its results.
+    pub name: String,
+    /// The query as string.
+    pub query: String,
+    /// The query language of the query (e.g. AQL).
+    #[serde(default)]
+    pub query_language: QueryLanguage,
+}
+
+/// Defines which results are written for each query of a batch.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BatchMode {
+    /// Write the number of matches and the number of documents with a match.
+    Count,
+    /// Write the match IDs of all matches.
+    Find,
+}
+
+/// File format of the results of a batch.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BatchOutputFormat {
+    /// A CSV file with a header row.
+    CSV,
+    /// A JSON file.
+    JSON,
+}
+
+impl BatchOutputFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            BatchOutputFormat::CSV => "csv",
+            BatchOutputFormat::JSON => "json",
+        }
+    }
+}
+
+/// Configuration of a batch execution.
+#[derive(Clone, Debug)]
+pub struct BatchConfig {
+    /// Which results are written for each query.
+    pub mode: BatchMode,
+    /// The file format of the results.
+    pub format: BatchOutputFormat,
+    /// If `true`, several queries are executed in parallel.
+    pub parallel: bool,
+    /// Timeout for each query of the batch.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            mode: BatchMode::Count,
+            format: BatchOutputFormat::CSV,
+            parallel: false,
+            timeout: None,
+        }
+    }
+}
+
+/// Outcome of a single query of a batch.
+#[derive(Clone, Debug)]
+pub struct BatchQueryResult {
+    /// Name of the query.
+    pub name: String,
+    /// Number of matches, `None` if the query failed.
+    pub match_count: Option<u64>,
+    /// The file the results have been written to, `None` if the query failed.
+    pub output_file: Option<PathBuf>,
+    /// Description of the error if the query failed.
+    pub error: Option<String>,
+    /// Time needed to execute the query and write its results.
+    pub duration: Duration,
+}
+
+/// Number of items changed by updates since the statistics of a corpus have been re-calculated.
+#[derive(Debug, Default)]
+#[cfg(feature = "corpus-storage")]
+struct StatisticsDrift {
+    changed_items: usize,
+    last_refresh: Option<std::time::Instant>,
+    refresh_running: bool,
+}
+
 pub const SALT_URI_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ').add(b':').add(b'%');
 const QUIRKS_SALT_URI_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ').add(b'%');
 pub const PATH_SEGMENT_ENCODE_SET: &AsciiSet = &CONTROLS
@@ -335,6 +1231,53 @@ pub struct SearchQuery<'a, S: AsRef<str>> {
     pub query_language: QueryLanguage,
     /// If not `None`, the query will be aborted after running for the given amount of time.
     pub timeout: Option<Duration>,
+    /// If not empty, only search in the documents with the given node names (e.g. `rootCorpus/doc1`).
+    /// Only nodes that are part of a document can be found when this filter is active.
+    pub document_filter: Vec<String>,
+}
+
+/// Defines how the relANNIS importer handles different nodes with the same node name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateNodeNamePolicy {
+    /// Abort the import with an error.
+    Error,
+    /// Add a numeric suffix to the name of each duplicate node, e.g. `corpus/doc#node_2`.
+    Rename,
+    /// Merge all nodes with the same name into a single node.
+    Merge,
+}
+
+impl Default for DuplicateNodeNamePolicy {
+    fn default() -> Self {
+        DuplicateNodeNamePolicy::Merge
+    }
+}
+
+/// Defines how the components which are derived from other components (the `LeftToken` and `RightToken`
+/// components and the inherited coverage) are created when importing a corpus.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DerivedComponentsPolicy {
+    /// Calculate the derived components while the imported graph is created.
+    Incremental,
+    /// Additionally discard the derived components after the import and calculate them again for all nodes.
+    /// This is useful if the imported data already contains derived components that might be incomplete,
+    /// e.g. a GraphML file that has been created by another tool.
+    Rebuild,
+}
+
+impl Default for DerivedComponentsPolicy {
+    fn default() -> Self {
+        DerivedComponentsPolicy::Incremental
+    }
+}
+
+/// A node name that has been used by more than one node of an imported corpus.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateNodeName {
+    /// The duplicated node name.
+    pub node_name: String,
+    /// The new name of the duplicate node if it has been renamed, `None` if it has been merged.
+    pub renamed_to: Option<String>,
 }
 
 /// A thread-safe API for managing corpora stored in a common location on the file system.
@@ -342,12 +1285,28 @@ pub struct SearchQuery<'a, S: AsRef<str>> {
 /// Multiple corpora can be part of a corpus storage and they are identified by their unique name.
 /// Corpora are loaded from disk into main memory on demand:
 /// An internal main memory cache is used to avoid re-loading a recently queried corpus from disk again.
+#[cfg(feature = "corpus-storage")]
 pub struct CorpusStorage {
     db_dir: PathBuf,
-    lock_file: File,
+    lock: DirectoryLock,
     cache_strategy: CacheStrategy,
     corpus_cache: RwLock<LinkedHashMap<String, Arc<RwLock<CacheEntry>>>>,
+    cache_usage: Mutex<eviction::CacheUsage>,
     query_config: query::Config,
+    unicode_normalization: UnicodeNormalization,
+    statistics_refresh: Option<StatisticsRefresh>,
+    node_name_scheme: Option<Arc<dyn NodeNameScheme>>,
+    duplicate_node_name_policy: DuplicateNodeNamePolicy,
+    progress_interval: Duration,
+    verify_component_checksums: bool,
+    encryption_key: Option<Arc<EncryptionKey>>,
+    derived_components_policy: DerivedComponentsPolicy,
+    corpus_groups_lock: Mutex<()>,
+    corpus_shards_lock: Mutex<()>,
+    plan_cache: Arc<plancache::PlanCache>,
+    statistics_drift: Arc<Mutex<HashMap<String, StatisticsDrift>>>,
+    pinned_corpora: Arc<Mutex<HashMap<String, usize>>>,
+    abort_background_workers: Arc<AtomicBool>,
     active_background_workers: Arc<(Mutex<usize>, Condvar)>,
 }
 
@@ -460,8 +1419,89 @@ fn new_vector_with_memory_aligned_capacity<T>(expected_len: usize) -> Vec<T> {
     Vec::with_capacity(aligned_memory_size / std::mem::size_of::<T>())
 }
 
-type FindIterator<'a> = Box<dyn Iterator<Item = MatchGroup> + 'a>;
+type FindIterator<'a> = Box<dyn Iterator<Item = Result<MatchGroup>> + 'a>;
 
+/// Executes a document-local query for each document separately and yields the sorted matches document by
+/// document.
+///
+/// Since all nodes of a match belong to the same document and the documents are iterated in the order of
+/// their path, the output has the same order as sorting all matches at once, but only the matches of a single
+/// document have to be held in memory. An error while executing the query for a document is returned as item
+/// and ends the iteration.
+struct DocumentOrderIterator<'a> {
+    db: &'a AnnotationGraph,
+    query: Disjunction<'a>,
+    query_config: query::Config,
+    documents: std::vec::IntoIter<String>,
+    output_size: usize,
+    token_helper: Option<TokenHelper<'a>>,
+    gs_order: Option<&'a dyn GraphStorage>,
+    current_document: std::vec::IntoIter<MatchGroup>,
+    /// Name of the document the last returned match belongs to.
+    current_document_name: Option<String>,
+    /// Skip the given number of matches when executing the query for this document.
+    skip_in_document: Option<(String, usize)>,
+}
+
+impl<'a> DocumentOrderIterator<'a> {
+    /// Execute the query for the given document and return its matches.
+    fn matches_of_document(&mut self, document: &str) -> Result<Vec<MatchGroup>> {
+        aql::replace_document_filter(&mut self.query, &[document.to_string()])?;
+        let output_size = self.output_size;
+        let plan = ExecutionPlan::from_disjunction(&self.query, self.db, &self.query_config)?;
+        Ok(plan
+            .map(|mut m| {
+                // remove the artificial document node
+                m.truncate(output_size);
+                m
+            })
+            .collect())
+    }
+}
+
+impl<'a> Iterator for DocumentOrderIterator<'a> {
+    type Item = Result<MatchGroup>;
+
+    fn next(&mut self) -> Option<Result<MatchGroup>> {
+        loop {
+            if let Some(m) = self.current_document.next() {
+                return Some(Ok(m));
+            }
+            let document = self.documents.next()?;
+            let mut matches = match self.matches_of_document(&document) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    // do not continue with the next document after an error
+                    self.documents = Vec::new().into_iter();
+                    return Some(Err(e));
+                }
+            };
+            let node_annos = self.db.get_node_annos();
+            let token_helper = self.token_helper.as_ref();
+            let gs_order = self.gs_order;
+            matches.sort_by(|m1, m2| {
+                db::sort_matches::compare_matchgroup_by_text_pos(
+                    m1,
+                    m2,
+                    node_annos,
+                    token_helper,
+                    gs_order,
+                    CollationType::Default,
+                    false,
+                )
+            });
+            self.current_document = matches.into_iter();
+            if let Some((skip_document, n)) = &self.skip_in_document {
+                if skip_document == &document && *n > 0 {
+                    self.current_document.nth(n - 1);
+                }
+            }
+            self.current_document_name = Some(document);
+        }
+    }
+}
+
+#[cfg(feature = "corpus-storage")]
 impl CorpusStorage {
     /// Create a new instance with a maximum size for the internal corpus cache.
     ///
@@ -472,19 +1512,58 @@ impl CorpusStorage {
         db_dir: &Path,
         cache_strategy: CacheStrategy,
         use_parallel_joins: bool,
+    ) -> Result<CorpusStorage> {
+        CorpusStorage::with_locking_strategy(
+            db_dir,
+            cache_strategy,
+            use_parallel_joins,
+            LockingStrategy::default(),
+        )
+    }
+
+    /// Create a new instance with a maximum size for the internal corpus cache and the given strategy to lock
+    /// the directory against concurrent use by other instances.
+    ///
+    /// - `db_dir` - The path on the filesystem where the corpus storage content is located. Must be an existing directory.
+    /// - `cache_strategy`: A strategy for clearing the cache.
+    /// - `use_parallel_joins` - If `true` parallel joins are used by the system, using all available cores.
+    /// - `locking_strategy` - How the directory is locked, e.g. with a lease for directories on network filesystems.
+    pub fn with_locking_strategy(
+        db_dir: &Path,
+        cache_strategy: CacheStrategy,
+        use_parallel_joins: bool,
+        locking_strategy: LockingStrategy,
     ) -> Result<CorpusStorage> {
         init_locale();
 
-        let query_config = query::Config { use_parallel_joins };
+        let query_config = query::Config {
+            use_parallel_joins,
+            ..Default::default()
+        };
 
         #[allow(clippy::mutex_atomic)]
         let active_background_workers = Arc::new((Mutex::new(0), Condvar::new()));
         let cs = CorpusStorage {
             db_dir: PathBuf::from(db_dir),
-            lock_file: create_lockfile_for_directory(db_dir)?,
+            lock: DirectoryLock::acquire(db_dir, locking_strategy)?,
             cache_strategy,
             corpus_cache: RwLock::new(LinkedHashMap::new()),
+            cache_usage: Mutex::new(eviction::CacheUsage::default()),
             query_config,
+            unicode_normalization: UnicodeNormalization::default(),
+            statistics_refresh: None,
+            node_name_scheme: None,
+            duplicate_node_name_policy: DuplicateNodeNamePolicy::default(),
+            progress_interval: Duration::from_secs(0),
+            verify_component_checksums: false,
+            encryption_key: None,
+            derived_components_policy: DerivedComponentsPolicy::default(),
+            corpus_groups_lock: Mutex::new(()),
+            corpus_shards_lock: Mutex::new(()),
+            plan_cache: Arc::new(plancache::PlanCache::default()),
+            statistics_drift: Arc::new(Mutex::new(HashMap::default())),
+            pinned_corpora: Arc::new(Mutex::new(HashMap::default())),
+            abort_background_workers: Arc::new(AtomicBool::new(false)),
             active_background_workers,
         };
 
@@ -501,26 +1580,193 @@ impl CorpusStorage {
     pub fn with_auto_cache_size(db_dir: &Path, use_parallel_joins: bool) -> Result<CorpusStorage> {
         init_locale();
 
-        let query_config = query::Config { use_parallel_joins };
+        let query_config = query::Config {
+            use_parallel_joins,
+            ..Default::default()
+        };
 
         // get the amount of available memory, use a quarter of it per default
         let cache_strategy: CacheStrategy = CacheStrategy::PercentOfFreeMemory(25.0);
+        let locking_strategy = LockingStrategy::default();
 
         #[allow(clippy::mutex_atomic)]
         let active_background_workers = Arc::new((Mutex::new(0), Condvar::new()));
 
         let cs = CorpusStorage {
             db_dir: PathBuf::from(db_dir),
-            lock_file: create_lockfile_for_directory(db_dir)?,
+            lock: DirectoryLock::acquire(db_dir, locking_strategy)?,
             cache_strategy,
             corpus_cache: RwLock::new(LinkedHashMap::new()),
+            cache_usage: Mutex::new(eviction::CacheUsage::default()),
             query_config,
+            unicode_normalization: UnicodeNormalization::default(),
+            statistics_refresh: None,
+            node_name_scheme: None,
+            duplicate_node_name_policy: DuplicateNodeNamePolicy::default(),
+            progress_interval: Duration::from_secs(0),
+            verify_component_checksums: false,
+            encryption_key: None,
+            derived_components_policy: DerivedComponentsPolicy::default(),
+            corpus_groups_lock: Mutex::new(()),
+            corpus_shards_lock: Mutex::new(()),
+            plan_cache: Arc::new(plancache::PlanCache::default()),
+            statistics_drift: Arc::new(Mutex::new(HashMap::default())),
+            pinned_corpora: Arc::new(Mutex::new(HashMap::default())),
+            abort_background_workers: Arc::new(AtomicBool::new(false)),
             active_background_workers,
         };
 
         Ok(cs)
     }
 
+    /// Set the Unicode normal form which is applied to annotation values of imports and updates and
+    /// to the literals of queries.
+    ///
+    /// The default is NFC. Corpora which have been imported with a different setting can be
+    /// migrated with [`renormalize_unicode`](#method.renormalize_unicode).
+    pub fn set_unicode_normalization(&mut self, normalization: UnicodeNormalization) {
+        self.unicode_normalization = normalization;
+    }
+
+    /// Enable or disable the background job that re-calculates the annotation and component statistics
+    /// of a corpus when too many items have been changed by updates.
+    ///
+    /// The job is disabled per default. It runs in a background thread, processes one component at a time
+    /// so queries are only blocked for a short time, and is aborted when the corpus storage is dropped.
+    pub fn set_statistics_refresh(&mut self, refresh: Option<StatisticsRefresh>) {
+        self.statistics_refresh = refresh;
+    }
+
+    /// Set the scheme that is used to create the names of annotation nodes when importing a corpus.
+    ///
+    /// Per default, the node names created by the importer are kept.
+    /// The import fails if the scheme creates the same name for different nodes.
+    pub fn set_node_name_scheme(&mut self, scheme: Option<Arc<dyn NodeNameScheme>>) {
+        self.node_name_scheme = scheme;
+    }
+
+    /// Set how different nodes with the same name are handled when importing a relANNIS corpus.
+    ///
+    /// Per default, nodes with the same name are merged. All duplicate node names are reported in the
+    /// [`ImportAnalysis`] of [analyze_import_from_fs(...)](#method.analyze_import_from_fs).
+    pub fn set_duplicate_node_name_policy(&mut self, policy: DuplicateNodeNamePolicy) {
+        self.duplicate_node_name_policy = policy;
+    }
+
+    /// Set the minimal time between two calls of the progress callback of imports and exports.
+    ///
+    /// Status messages that are reported earlier are coalesced: only the latest one is reported after the interval
+    /// has passed or when the import or export is finished. This protects slow consumers (e.g. clients which receive
+    /// the status over the network) from being flooded with messages.
+    /// Per default, the interval is zero and all status messages are reported.
+    pub fn set_progress_interval(&mut self, interval: Duration) {
+        self.progress_interval = interval;
+    }
+
+    /// Set the thread pool which is used for parallel query execution, imports and the background jobs
+    /// (e.g. writing the changes of an update to disk).
+    ///
+    /// Per default, the global [rayon](https://docs.rs/rayon) thread pool is used for parallel execution and a new
+    /// thread is spawned for each background job. Applications which embed the corpus storage can provide their own
+    /// pool to control the number and names of the threads. Call [shutdown()](#method.shutdown) before shutting down
+    /// the pool, so no background job is lost.
+    pub fn set_thread_pool(&mut self, thread_pool: Option<Arc<ThreadPool>>) {
+        self.query_config.thread_pool = thread_pool;
+    }
+
+    /// If `true`, the checksum of each component is verified when it is loaded from disk.
+    ///
+    /// Loading a corrupted component fails with an error that names the component.
+    /// Verifying the checksums needs to read each component file twice, so this is disabled per default.
+    pub fn set_verify_component_checksums(&mut self, verify: bool) {
+        self.verify_component_checksums = verify;
+    }
+
+    /// Set the key which is used to encrypt the corpora of this corpus storage on disk.
+    ///
+    /// If a key is set, all files of the corpora that are imported or changed afterwards are encrypted, including
+    /// the corpus configuration, the statistics and the linked files, as well as the corpus groups and shards of the
+    /// data directory. Only the lock file and the files without corpus data (the implementation name and checksum of
+    /// each component and the change ID) are not encrypted. Unencrypted files are rejected, unless the key
+    /// [allows unencrypted files](EncryptionKey::allow_unencrypted) to migrate existing corpora, which are encrypted
+    /// when they are saved again. Temporary files, e.g. of large imports, are encrypted with a random key that only
+    /// exists in main memory.
+    ///
+    /// Only main memory storages can be encrypted. Disk-based corpora, which are typically the large ones, can't be
+    /// encrypted at all: they can't be imported and existing disk-based corpora fail to load while a key is set.
+    /// The key must be set before any corpus is accessed.
+    pub fn set_encryption_key(&mut self, key: Option<EncryptionKey>) {
+        self.encryption_key = key.map(Arc::new);
+    }
+
+    /// Set how the derived components (`LeftToken`, `RightToken` and the inherited coverage) of imported corpora
+    /// are created.
+    ///
+    /// Per default, they are calculated incrementally while the imported graph is created.
+    /// Corpora which have already been imported can be repaired with
+    /// [rebuild_derived_components(...)](#method.rebuild_derived_components).
+    pub fn set_derived_components_policy(&mut self, policy: DerivedComponentsPolicy) {
+        self.derived_components_policy = policy;
+    }
+
+    /// Set the thresholds that are used by the query planner to select the join implementations.
+    ///
+    /// See [`JoinThresholds`] for the available thresholds and their default values.
+    /// Batch sizes of `0` are replaced by `1`, since the parallel joins can't make progress with empty batches.
+    pub fn set_join_thresholds(&mut self, thresholds: JoinThresholds) {
+        self.query_config.join_thresholds = JoinThresholds {
+            parallel_index_join_batch_size: thresholds.parallel_index_join_batch_size.max(1),
+            parallel_nested_loop_batch_size: thresholds.parallel_nested_loop_batch_size.max(1),
+            ..thresholds
+        };
+    }
+
+    /// Get the thresholds that are currently used by the query planner.
+    pub fn join_thresholds(&self) -> &JoinThresholds {
+        &self.query_config.join_thresholds
+    }
+
+    /// Set which corpora are removed first when the corpus cache is too large.
+    ///
+    /// The default is [`EvictionPolicy::LeastRecentlyUsed`].
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.cache_usage.get_mut().unwrap().set_policy(policy);
+    }
+
+    /// Get the policy that is currently used to remove corpora from the cache.
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        self.cache_usage.lock().unwrap().policy()
+    }
+
+    /// Get the number of cache hits, misses and evictions as well as the time spent loading corpora.
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.cache_usage.lock().unwrap().metrics()
+    }
+
+    /// Reset all counters of the [cache metrics](#method.cache_metrics).
+    pub fn reset_cache_metrics(&self) {
+        self.cache_usage.lock().unwrap().reset_metrics();
+    }
+
+    /// Finish all background work, e.g. writing the changes of previous updates to disk, and wait until it is done.
+    ///
+    /// Optional maintenance jobs like the [statistics refresh](#method.set_statistics_refresh) are aborted and
+    /// not started again. Call this before shutting down a [thread pool](#method.set_thread_pool) provided by the
+    /// application. Dropping the corpus storage calls this method automatically.
+    pub fn shutdown(&self) {
+        self.abort_background_workers.store(true, Ordering::SeqCst);
+        self.wait_for_background_workers();
+        self.plan_cache.persist(self.encryption_key.as_ref());
+    }
+
+    /// Set the limits for the complexity of queries.
+    ///
+    /// Queries exceeding one of the limits are rejected with a [`GraphAnnisError::QueryTooComplex`] error
+    /// before they are executed. See [`QueryLimits`] for the available limits.
+    pub fn set_query_limits(&mut self, limits: QueryLimits) {
+        self.query_config.limits = limits;
+    }
+
     /// List  all available corpora in the corpus storage.
     pub fn list(&self) -> Result<Vec<CorpusInfo>> {
         let names: Vec<String> = self.list_from_disk().unwrap_or_default();
@@ -533,6 +1779,9 @@ impl CorpusStorage {
             let corpus_info = self.create_corpus_info(&n, &mut mem_ops)?;
             result.push(corpus_info);
         }
+        for sharded_corpus in self.list_sharded_corpora()? {
+            result.push(self.create_sharded_corpus_info(&sharded_corpus, &mut mem_ops)?);
+        }
 
         Ok(result)
     }
@@ -568,10 +1817,32 @@ impl CorpusStorage {
         Ok(corpora)
     }
 
+    fn corpus_directory(&self, corpus_name: &str) -> PathBuf {
+        let escaped_corpus_name: Cow<str> =
+            utf8_percent_encode(corpus_name, PATH_SEGMENT_ENCODE_SET).into();
+        self.db_dir.join(escaped_corpus_name.as_ref())
+    }
+
+    fn corpus_exists(&self, corpus_name: &str) -> Result<bool> {
+        if self.corpus_cache.read().unwrap().contains_key(corpus_name) {
+            return Ok(true);
+        }
+        let on_disk = self.list_from_disk()?;
+        Ok(on_disk.iter().any(|c| c == corpus_name))
+    }
+
     fn get_corpus_config(&self, corpus_name: &str) -> Result<Option<CorpusConfiguration>> {
-        let corpus_config_path = self.db_dir.join(corpus_name).join("corpus-config.toml");
-        if corpus_config_path.is_file() {
-            let file_content = std::fs::read_to_string(corpus_config_path)?;
+        // All shards share the configuration of the original corpus
+        if let Some(first_shard) = self.shards_of_corpus(corpus_name)?.first() {
+            return self.get_corpus_config(first_shard);
+        }
+        let corpus_dir = self.corpus_directory(corpus_name);
+        let corpus_config_path = corpus_dir.join(CORPUS_CONFIG_FILE);
+        if let Some(file_content) = read_corpus_file(
+            &corpus_dir,
+            &corpus_config_path,
+            self.encryption_key.as_ref(),
+        )? {
             let config = toml::from_str(&file_content)?;
             Ok(Some(config))
         } else {
@@ -579,6 +1850,16 @@ impl CorpusStorage {
         }
     }
 
+    fn write_corpus_config(&self, corpus_name: &str, config: &CorpusConfiguration) -> Result<()> {
+        let corpus_dir = self.corpus_directory(corpus_name);
+        write_corpus_file(
+            &corpus_dir,
+            &corpus_dir.join(CORPUS_CONFIG_FILE),
+            &toml::to_string(config)?,
+            self.encryption_key.as_ref(),
+        )
+    }
+
     fn create_corpus_info(
         &self,
         corpus_name: &str,
@@ -596,6 +1877,13 @@ impl CorpusStorage {
             })?
             .unwrap_or_default();
 
+        let corpus_dir = self.corpus_directory(corpus_name);
+        let summary = read_corpus_summary(&corpus_dir, self.encryption_key.as_ref());
+        let disk_usage = summary
+            .as_ref()
+            .and_then(|summary| summary.disk_usage.as_ref())
+            .map(|usage| DiskUsage::from(usage.clone()));
+
         let corpus_info: CorpusInfo = match &*lock {
             CacheEntry::Loaded(ref db) => {
                 // check if all components are loaded
@@ -625,21 +1913,74 @@ impl CorpusStorage {
                     }
                 }
 
+                let number_of_nodes = db
+                    .get_node_annos()
+                    .number_of_annotations_by_name(Some(ANNIS_NS), NODE_NAME);
+
+                let estimated_memory_size =
+                    if let LoadStatus::FullyLoaded(memory_size) = load_status {
+                        Some(memory_size)
+                    } else {
+                        summary.map(|summary| summary.memory_size)
+                    };
+
                 CorpusInfo {
                     name: corpus_name.to_owned(),
                     load_status,
                     graphstorages,
                     node_annos_load_size,
                     config,
+                    number_of_nodes: Some(number_of_nodes),
+                    estimated_memory_size,
+                    disk_usage,
+                    load_report: db.load_report().cloned(),
+                    node_annotation_usage: Some(db.get_node_annos().annotation_key_usage()),
+                }
+            }
+            &CacheEntry::NotLoaded => {
+                if let Some(summary) = summary {
+                    let graphstorages = summary
+                        .graphstorages
+                        .into_iter()
+                        .map(|gs| GraphStorageInfo {
+                            component: Component::new(
+                                gs.component_type,
+                                gs.component_layer.into(),
+                                gs.component_name.into(),
+                            ),
+                            load_status: LoadStatus::NotLoaded,
+                            number_of_annotations: gs.number_of_annotations,
+                            implementation: gs.implementation,
+                            statistics: gs.statistics,
+                        })
+                        .collect();
+                    CorpusInfo {
+                        name: corpus_name.to_owned(),
+                        load_status: LoadStatus::NotLoaded,
+                        graphstorages,
+                        node_annos_load_size: None,
+                        config,
+                        number_of_nodes: Some(summary.number_of_nodes),
+                        estimated_memory_size: Some(summary.memory_size),
+                        disk_usage,
+                        load_report: None,
+                        node_annotation_usage: None,
+                    }
+                } else {
+                    CorpusInfo {
+                        name: corpus_name.to_owned(),
+                        load_status: LoadStatus::NotLoaded,
+                        graphstorages: vec![],
+                        node_annos_load_size: None,
+                        config,
+                        number_of_nodes: None,
+                        estimated_memory_size: None,
+                        disk_usage,
+                        load_report: None,
+                        node_annotation_usage: None,
+                    }
                 }
             }
-            &CacheEntry::NotLoaded => CorpusInfo {
-                name: corpus_name.to_owned(),
-                load_status: LoadStatus::NotLoaded,
-                graphstorages: vec![],
-                node_annos_load_size: None,
-                config,
-            },
         };
         Ok(corpus_info)
     }
@@ -648,7 +1989,442 @@ impl CorpusStorage {
     pub fn info(&self, corpus_name: &str) -> Result<CorpusInfo> {
         let mut mem_ops =
             MallocSizeOfOps::new(memory_estimation::platform::usable_size, None, None);
-        self.create_corpus_info(corpus_name, &mut mem_ops)
+        if self.shards_of_corpus(corpus_name)?.is_empty() {
+            self.create_corpus_info(corpus_name, &mut mem_ops)
+        } else {
+            self.create_sharded_corpus_info(corpus_name, &mut mem_ops)
+        }
+    }
+
+    fn create_sharded_corpus_info(
+        &self,
+        corpus_name: &str,
+        mem_ops: &mut MallocSizeOfOps,
+    ) -> Result<CorpusInfo> {
+        let shard_infos = self
+            .shards_of_corpus(corpus_name)?
+            .iter()
+            .map(|shard| self.create_corpus_info(shard, mem_ops))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(sharding::combine_corpus_info(corpus_name, shard_infos))
+    }
+
+    /// Add the corpus to the named group. The group is created if it does not exist yet.
+    ///
+    /// Corpus groups are stored in the corpus storage directory, so all users of the same directory
+    /// (e.g. the CLI and the web service) share them.
+    pub fn add_to_corpus_group(&self, group: &str, corpus_name: &str) -> Result<()> {
+        if !self.corpus_exists(corpus_name)? {
+            return Err(GraphAnnisError::NoSuchCorpus(corpus_name.to_string()));
+        }
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        let mut groups = self.read_corpus_groups()?;
+        if groups
+            .entry(group.to_string())
+            .or_default()
+            .insert(corpus_name.to_string())
+        {
+            self.write_corpus_groups(&groups)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the corpus from the named group and return `true` if it was a member of the group.
+    /// Groups without any corpus are removed.
+    pub fn remove_from_corpus_group(&self, group: &str, corpus_name: &str) -> Result<bool> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        let mut groups = self.read_corpus_groups()?;
+        let removed = if let Some(corpora) = groups.get_mut(group) {
+            let removed = corpora.remove(corpus_name);
+            if corpora.is_empty() {
+                groups.remove(group);
+            }
+            removed
+        } else {
+            false
+        };
+        if removed {
+            self.write_corpus_groups(&groups)?;
+        }
+        Ok(removed)
+    }
+
+    /// Delete the named group and return `true` if it existed. The corpora of the group are not deleted.
+    pub fn delete_corpus_group(&self, group: &str) -> Result<bool> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        let mut groups = self.read_corpus_groups()?;
+        let existed = groups.remove(group).is_some();
+        if existed {
+            self.write_corpus_groups(&groups)?;
+        }
+        Ok(existed)
+    }
+
+    /// List the names of all corpus groups in alphabetical order.
+    pub fn list_corpus_groups(&self) -> Result<Vec<String>> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        Ok(self
+            .read_corpus_groups()?
+            .into_iter()
+            .map(|(g, _)| g)
+            .collect())
+    }
+
+    /// Get the names of all corpora of the named group in alphabetical order.
+    ///
+    /// The result can be used as the `corpus_names` of a [`SearchQuery`] to query all corpora of the group.
+    /// An unknown group has no corpora.
+    pub fn corpora_in_group(&self, group: &str) -> Result<Vec<String>> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        let mut groups = self.read_corpus_groups()?;
+        Ok(groups
+            .remove(group)
+            .map(|corpora| corpora.into_iter().collect())
+            .unwrap_or_default())
+    }
+
+    /// Get the names of all groups the corpus is a member of in alphabetical order.
+    pub fn groups_of_corpus(&self, corpus_name: &str) -> Result<Vec<String>> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        Ok(self
+            .read_corpus_groups()?
+            .into_iter()
+            .filter(|(_, corpora)| corpora.contains(corpus_name))
+            .map(|(g, _)| g)
+            .collect())
+    }
+
+    fn read_corpus_groups(&self) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let path = self.db_dir.join(CORPUS_GROUPS_FILE);
+        match read_corpus_file(&self.db_dir, &path, self.encryption_key.as_ref())? {
+            Some(content) => Ok(toml::from_str(&content)?),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn write_corpus_groups(&self, groups: &BTreeMap<String, BTreeSet<String>>) -> Result<()> {
+        write_corpus_file(
+            &self.db_dir,
+            &self.db_dir.join(CORPUS_GROUPS_FILE),
+            &toml::to_string(groups)?,
+            self.encryption_key.as_ref(),
+        )
+    }
+
+    /// Split the corpus into `num_shards` corpora with about the same number of documents each.
+    ///
+    /// The documents are distributed in the order of their path, so each shard contains a consecutive range of
+    /// documents. The shards are named `<corpus_name>-shard<n>` and the original corpus is replaced by a sharded
+    /// corpus with the same name. Queries on the sharded corpus (e.g. [count(...)](#method.count),
+    /// [find(...)](#method.find) or [frequency(...)](#method.frequency)) are executed on each shard and the results
+    /// are merged as if the shards were queried as multiple corpora. Functions that return information about a
+    /// corpus or a subgraph (e.g. [info(...)](#method.info) or [subgraph(...)](#method.subgraph)) combine the
+    /// results of the shards that contain the requested documents.
+    /// If creating one of the shards fails, the already created shards are deleted and the original corpus is kept.
+    ///
+    /// Returns the names of the shards.
+    pub fn split_into_shards(&self, corpus_name: &str, num_shards: usize) -> Result<Vec<String>> {
+        self.lock.check_valid()?;
+        if num_shards == 0 {
+            return Err(CorpusStorageError::InvalidSharding {
+                corpus: corpus_name.to_string(),
+                reason: "the number of shards must be at least 1".to_string(),
+            }
+            .into());
+        }
+        if !self.corpus_exists(corpus_name)? {
+            return Err(GraphAnnisError::NoSuchCorpus(corpus_name.to_string()));
+        }
+        let documents: Vec<String> = self
+            .document_statistics(corpus_name)?
+            .into_iter()
+            .map(|d| d.document)
+            .collect();
+        if documents.is_empty() {
+            return Err(CorpusStorageError::InvalidSharding {
+                corpus: corpus_name.to_string(),
+                reason: "the corpus has no documents".to_string(),
+            }
+            .into());
+        }
+        let num_shards = num_shards.min(documents.len());
+        let documents_per_shard = (documents.len() + num_shards - 1) / num_shards;
+
+        let mut shards = Vec::with_capacity(num_shards);
+        let result = self.create_shards(
+            corpus_name,
+            &documents,
+            num_shards,
+            documents_per_shard,
+            &mut shards,
+        );
+        if let Err(e) = result {
+            // Do not leave incomplete shards behind, the original corpus still exists
+            for shard in shards {
+                if let Err(delete_error) = self.delete(&shard) {
+                    warn!(
+                        "could not delete incomplete shard {}: {}",
+                        shard, delete_error
+                    );
+                }
+            }
+            return Err(e);
+        }
+
+        // The groups of the original corpus now contain the sharded corpus, which has the same name
+        let groups = self.groups_of_corpus(corpus_name)?;
+        self.delete(corpus_name)?;
+        {
+            let _lock = self.corpus_shards_lock.lock().unwrap();
+            let mut all_shards = self.read_corpus_shards()?;
+            all_shards.insert(corpus_name.to_string(), shards.clone());
+            self.write_corpus_shards(&all_shards)?;
+        }
+        if !groups.is_empty() {
+            let _lock = self.corpus_groups_lock.lock().unwrap();
+            let mut all_groups = self.read_corpus_groups()?;
+            for g in groups {
+                all_groups
+                    .entry(g)
+                    .or_default()
+                    .insert(corpus_name.to_string());
+            }
+            self.write_corpus_groups(&all_groups)?;
+        }
+
+        Ok(shards)
+    }
+
+    /// Import the shards of a corpus and add the name of each imported shard to `shards`.
+    fn create_shards(
+        &self,
+        corpus_name: &str,
+        documents: &[String],
+        num_shards: usize,
+        documents_per_shard: usize,
+        shards: &mut Vec<String>,
+    ) -> Result<()> {
+        let config = self.get_corpus_config(corpus_name)?.unwrap_or_default();
+        let files_dir = self.corpus_directory(corpus_name).join("files");
+        for (shard_idx, shard_documents) in documents.chunks(documents_per_shard).enumerate() {
+            let shard_name = format!(
+                "{}-shard{:0width$}",
+                corpus_name,
+                shard_idx + 1,
+                width = num_shards.to_string().len()
+            );
+            info!(
+                "creating shard {} with {} documents",
+                shard_name,
+                shard_documents.len()
+            );
+            let graph = self.shard_graph(corpus_name, shard_documents)?;
+            let pending = PendingImport {
+                analysis: ImportAnalysis {
+                    corpus_name: shard_name.clone(),
+                    estimated_node_count: 0,
+                    estimated_edge_count: 0,
+                    annotation_layers: Vec::new(),
+                    conflicts_with_existing: false,
+                    duplicate_node_names: Vec::new(),
+                },
+                graph,
+                config: config.clone(),
+                source_dir: files_dir.clone(),
+            };
+            shards.push(self.commit_import(pending, None, false)?);
+        }
+        Ok(())
+    }
+
+    /// Get the subgraph of the given documents together with the corpus structure they belong to.
+    fn shard_graph(&self, corpus_name: &str, documents: &[String]) -> Result<AnnotationGraph> {
+        let db_entry = self.get_fully_loaded_entry(corpus_name)?;
+
+        let mut query = subcorpus_query(documents.to_vec())?;
+        // also include the documents and the (sub-) corpora they are part of
+        for doc in documents {
+            let mut q = Conjunction::new();
+            let doc_idx = q.add_node(
+                NodeSearchSpec::new_exact(Some(ANNIS_NS), NODE_NAME, Some(doc), false),
+                None,
+            );
+            let corpus_idx = q.add_node(
+                NodeSearchSpec::new_exact(Some(ANNIS_NS), NODE_TYPE, Some("corpus"), false),
+                None,
+            );
+            q.add_operator(
+                Box::new(operators::PartOfSubCorpusSpec {
+                    dist: RangeSpec::Unbound,
+                    inverse: false,
+                }),
+                &doc_idx,
+                &corpus_idx,
+                true,
+            )?;
+            query.alternatives.push(q);
+        }
+        let mut graph =
+            extract_subgraph_by_query(&db_entry, &query, &[0, 1], &self.query_config, None)?;
+
+        // the derived components and the statistics are not part of the extracted subgraph
+        rebuild_derived_components(&mut graph)?;
+        graph.get_node_annos_mut().calculate_statistics();
+        for c in graph.get_all_components(None, None) {
+            graph.calculate_component_statistics(&c)?;
+            graph.optimize_gs_impl(&c)?;
+        }
+        Ok(graph)
+    }
+
+    /// Combine existing corpora, which must have been split by document, to a sharded corpus with the given name.
+    ///
+    /// This allows to import the shards of a corpus separately. See
+    /// [split_into_shards(...)](#method.split_into_shards) for how queries on a sharded corpus are executed.
+    pub fn create_sharded_corpus<S: AsRef<str>>(
+        &self,
+        corpus_name: &str,
+        shards: &[S],
+    ) -> Result<()> {
+        if self.corpus_exists(corpus_name)? {
+            return Err(GraphAnnisError::CorpusExists(corpus_name.to_string()));
+        }
+        if shards.is_empty() {
+            return Err(CorpusStorageError::InvalidSharding {
+                corpus: corpus_name.to_string(),
+                reason: "a sharded corpus needs at least one shard".to_string(),
+            }
+            .into());
+        }
+        for s in shards {
+            if !self.corpus_exists(s.as_ref())? {
+                return Err(GraphAnnisError::NoSuchCorpus(s.as_ref().to_string()));
+            }
+        }
+
+        let _lock = self.corpus_shards_lock.lock().unwrap();
+        let mut all_shards = self.read_corpus_shards()?;
+        if all_shards.contains_key(corpus_name) {
+            return Err(GraphAnnisError::CorpusExists(corpus_name.to_string()));
+        }
+        if let Some(s) = shards.iter().find(|s| all_shards.contains_key(s.as_ref())) {
+            return Err(CorpusStorageError::InvalidSharding {
+                corpus: corpus_name.to_string(),
+                reason: format!("the shard {} is a sharded corpus itself", s.as_ref()),
+            }
+            .into());
+        }
+        all_shards.insert(
+            corpus_name.to_string(),
+            shards.iter().map(|s| s.as_ref().to_string()).collect(),
+        );
+        self.write_corpus_shards(&all_shards)
+    }
+
+    /// List the names of all sharded corpora in alphabetical order.
+    pub fn list_sharded_corpora(&self) -> Result<Vec<String>> {
+        let _lock = self.corpus_shards_lock.lock().unwrap();
+        Ok(self
+            .read_corpus_shards()?
+            .into_iter()
+            .map(|(c, _)| c)
+            .collect())
+    }
+
+    /// Get the names of the shards of a sharded corpus. A corpus that is not sharded has no shards.
+    pub fn shards_of_corpus(&self, corpus_name: &str) -> Result<Vec<String>> {
+        let _lock = self.corpus_shards_lock.lock().unwrap();
+        Ok(self
+            .read_corpus_shards()?
+            .remove(corpus_name)
+            .unwrap_or_default())
+    }
+
+    /// Replace the names of sharded corpora with the names of their shards.
+    fn resolve_shards<S: AsRef<str>>(&self, corpus_names: &[S]) -> Result<Vec<String>> {
+        let all_shards = {
+            let _lock = self.corpus_shards_lock.lock().unwrap();
+            self.read_corpus_shards()?
+        };
+        let mut result = Vec::with_capacity(corpus_names.len());
+        for cn in corpus_names {
+            if let Some(shards) = all_shards.get(cn.as_ref()) {
+                result.extend(shards.iter().cloned());
+            } else {
+                result.push(cn.as_ref().to_string());
+            }
+        }
+        Ok(result)
+    }
+
+    /// Group the node names by the shards of a sharded corpus that contain the document of the node.
+    ///
+    /// The document of a node is the part of the node name before the `#`. Nodes of documents or corpora that
+    /// are part of several shards, like the top-level corpus, are assigned to all of these shards.
+    /// Nodes of unknown documents are not assigned to any shard.
+    fn route_to_shards(
+        &self,
+        shards: &[String],
+        node_names: Vec<String>,
+    ) -> Result<Vec<(String, Vec<String>)>> {
+        let mut by_document: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for n in node_names {
+            by_document
+                .entry(sharding::document_of_node_name(&n).to_string())
+                .or_default()
+                .push(n);
+        }
+        let mut result = Vec::new();
+        for shard in shards {
+            let db_entry = self.get_loaded_entry(shard, false)?;
+            let lock = db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            let shard_nodes: Vec<String> = by_document
+                .iter()
+                .filter(|(document, _)| db.get_node_id_from_name(document).is_some())
+                .flat_map(|(_, nodes)| nodes.iter().cloned())
+                .collect();
+            if !shard_nodes.is_empty() {
+                result.push((shard.clone(), shard_nodes));
+            }
+        }
+        Ok(result)
+    }
+
+    fn read_corpus_shards(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let path = self.db_dir.join(CORPUS_SHARDS_FILE);
+        match read_corpus_file(&self.db_dir, &path, self.encryption_key.as_ref())? {
+            Some(content) => Ok(toml::from_str(&content)?),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    fn write_corpus_shards(&self, shards: &BTreeMap<String, Vec<String>>) -> Result<()> {
+        write_corpus_file(
+            &self.db_dir,
+            &self.db_dir.join(CORPUS_SHARDS_FILE),
+            &toml::to_string(shards)?,
+            self.encryption_key.as_ref(),
+        )
+    }
+
+    /// Return the number of bytes used on disk by the node annotations, each component, the
+    /// write-ahead log, the linked files and the backup of the corpus with the given name.
+    pub fn disk_usage(&self, corpus_name: &str) -> Result<DiskUsage> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let shard_usage = shards
+                .iter()
+                .map(|shard| self.disk_usage(shard))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(sharding::combine_disk_usage(shard_usage));
+        }
+        let corpus_dir = self.corpus_directory(corpus_name);
+        if !corpus_dir.is_dir() {
+            return Err(GraphAnnisError::NoSuchCorpus(corpus_name.to_string()));
+        }
+        let disk_usage = DiskUsage::from_corpus_directory(&corpus_dir)?;
+        Ok(disk_usage)
     }
 
     fn get_entry(&self, corpus_name: &str) -> Result<Arc<RwLock<CacheEntry>>> {
@@ -698,11 +2474,20 @@ impl CorpusStorage {
         };
 
         // make sure the cache is not too large before adding the new corpus
-        check_cache_size_and_remove_with_cache(cache, &self.cache_strategy, vec![], false);
+        check_cache_size_and_remove_with_cache(
+            cache,
+            &self.cache_strategy,
+            &self.cache_usage,
+            &self.pinned_corpora,
+            vec![],
+            false,
+        );
 
+        let load_start = std::time::Instant::now();
         let db = if create_corpus {
             // create the default graph storages that are assumed to exist in every corpus
             let mut db = AnnotationGraph::with_default_graphstorages(false)?;
+            db.set_encryption_key(self.encryption_key.clone());
 
             // save corpus to the path where it should be stored
             db.persist_to(&db_path)
@@ -713,7 +2498,10 @@ impl CorpusStorage {
             db
         } else {
             let mut db = AnnotationGraph::new(false)?;
+            db.set_verify_checksums(self.verify_component_checksums);
+            db.set_encryption_key(self.encryption_key.clone());
             db.load_from(&db_path, false)?;
+            self.preload_configured_components(corpus_name, &mut db)?;
             db
         };
 
@@ -721,10 +2509,16 @@ impl CorpusStorage {
         // first remove entry, than add it: this ensures it is at the end of the linked hash map
         cache.remove(corpus_name);
         cache.insert(String::from(corpus_name), entry.clone());
+        self.cache_usage
+            .lock()
+            .unwrap()
+            .record_load(corpus_name, load_start.elapsed());
         info!("Loaded corpus {}", corpus_name,);
         check_cache_size_and_remove_with_cache(
             cache,
             &self.cache_strategy,
+            &self.cache_usage,
+            &self.pinned_corpora,
             vec![corpus_name],
             true,
         );
@@ -732,11 +2526,52 @@ impl CorpusStorage {
         Ok(entry)
     }
 
-    fn get_loaded_entry(
+    /// Load all components that are listed in the `preload_components` of the corpus configuration and
+    /// register the graph storage implementations that are configured for specific components.
+    fn preload_configured_components(
         &self,
         corpus_name: &str,
-        create_if_missing: bool,
-    ) -> Result<Arc<RwLock<CacheEntry>>> {
+        db: &mut AnnotationGraph,
+    ) -> Result<()> {
+        let config = match self.get_corpus_config(corpus_name) {
+            Ok(Some(config)) => config,
+            Ok(None) => return Ok(()),
+            Err(e) => {
+                warn!(
+                    "Could not read configuration of corpus {} to preload components: {:?}",
+                    corpus_name, e
+                );
+                return Ok(());
+            }
+        };
+        for component in &config.preload_components {
+            match nodesearch::matching_components(db, component) {
+                Ok(components) => {
+                    for c in components {
+                        debug!("Preloading component {} of corpus {}", c, corpus_name);
+                        db.ensure_loaded(&c)?;
+                    }
+                }
+                Err(e) => warn!(
+                    "Ignoring component \"{}\" in the preload list of corpus {}: {}",
+                    component, corpus_name, e
+                ),
+            }
+        }
+        if let Err(e) = apply_graph_storage_config(db, &config, false) {
+            warn!(
+                "Ignoring graph storage configuration of corpus {}: {}",
+                corpus_name, e
+            );
+        }
+        Ok(())
+    }
+
+    fn get_loaded_entry(
+        &self,
+        corpus_name: &str,
+        create_if_missing: bool,
+    ) -> Result<Arc<RwLock<CacheEntry>>> {
         let cache_entry = self.get_entry(corpus_name)?;
 
         // check if basics (node annotation, strings) of the database are loaded
@@ -746,6 +2581,7 @@ impl CorpusStorage {
         };
 
         if loaded {
+            self.cache_usage.lock().unwrap().record_hit(corpus_name);
             Ok(cache_entry)
         } else {
             let mut cache_lock = self.corpus_cache.write().unwrap();
@@ -775,9 +2611,8 @@ impl CorpusStorage {
             // load the needed components
             let mut lock = db_entry.write().unwrap();
             let db = get_write_or_error(&mut lock)?;
-            for c in missing_components {
-                db.ensure_loaded(&c)?;
-            }
+            let missing_components: Vec<_> = missing_components.into_iter().collect();
+            db.ensure_loaded_parallel(&missing_components)?;
         };
 
         Ok(db_entry)
@@ -801,9 +2636,8 @@ impl CorpusStorage {
             // load the needed components
             let mut lock = db_entry.write().unwrap();
             let db = get_write_or_error(&mut lock)?;
-            for c in missing_components {
-                db.ensure_loaded(&c)?;
-            }
+            let missing_components: Vec<_> = missing_components.into_iter().collect();
+            db.ensure_loaded_parallel(&missing_components)?;
         };
 
         Ok(db_entry)
@@ -813,10 +2647,15 @@ impl CorpusStorage {
     ///
     /// This function will unzip the file to a temporary location and find all relANNIS and GraphML files in the ZIP file.
     /// The formats of the corpora can be relANNIS or GraphML.
+    /// Multiple corpora are imported in parallel.
     /// - `zip_file` - The content of the ZIP file.
     /// - `disk_based` - If `true`, prefer disk-based annotation and graph storages instead of memory-only ones.
     /// - `overwrite_existing` - If `true`, overwrite existing corpora. Otherwise ignore.
-    /// - `progress_callback` - A callback function to which the import progress is reported to.
+    /// - `max_concurrent_imports` - The maximal number of corpora that are imported in parallel. Each import holds its
+    ///   corpus in main memory and competes for the disk bandwidth, so `None` uses a small default number of parallel
+    ///   imports. Use `Some(1)` to import the corpora sequentially.
+    /// - `progress_callback` - A callback function to which the import progress is reported to. It is called from
+    ///   the parallel imports and the messages are prefixed with the location of the imported corpus.
     ///
     /// Returns the names of the imported corpora.
     pub fn import_all_from_zip<R, F>(
@@ -824,12 +2663,15 @@ impl CorpusStorage {
         zip_file: R,
         disk_based: bool,
         overwrite_existing: bool,
+        max_concurrent_imports: Option<usize>,
         progress_callback: F,
     ) -> Result<Vec<String>>
     where
         R: Read + Seek,
-        F: Fn(&str),
+        F: Fn(&str) + Sync,
     {
+        let progress = ProgressThrottle::new(progress_callback, self.progress_interval);
+
         // Unzip all files to a temporary directory
         let tmp_dir = tempfile::tempdir()?;
         debug!(
@@ -870,34 +2712,62 @@ impl CorpusStorage {
             }
         }
 
-        let mut corpus_names = Vec::new();
-
-        // Import all relANNIS files
-        for p in relannis_files {
-            info!("importing relANNIS corpus from {}", p.to_string_lossy());
-            let name = self.import_from_fs(
-                &p,
-                ImportFormat::RelANNIS,
-                None,
-                disk_based,
-                overwrite_existing,
-                &progress_callback,
-            )?;
-            corpus_names.push(name);
-        }
-        // Import all GraphML files
-        for p in graphannis_files {
-            info!("importing corpus from {}", p.to_string_lossy());
-            let name = self.import_from_fs(
-                &p,
-                ImportFormat::GraphML,
-                None,
-                disk_based,
-                overwrite_existing,
-                &progress_callback,
-            )?;
-            corpus_names.push(name);
-        }
+        // Import all relANNIS files first and then all GraphML files, using a bounded number of parallel imports
+        let all_files: Vec<(PathBuf, ImportFormat)> = relannis_files
+            .into_iter()
+            .map(|p| (p, ImportFormat::RelANNIS))
+            .chain(
+                graphannis_files
+                    .into_iter()
+                    .map(|p| (p, ImportFormat::GraphML)),
+            )
+            .collect();
+        let number_of_files = all_files.len();
+        let finished_imports = AtomicUsize::new(0);
+
+        // a configured thread pool might have more threads than the allowed concurrent imports, so make sure
+        // each parallel task imports enough corpora
+        let max_concurrent_imports = std::cmp::max(
+            1,
+            max_concurrent_imports.unwrap_or(DEFAULT_MAX_CONCURRENT_IMPORTS),
+        );
+        let min_imports_per_task = std::cmp::max(
+            1,
+            (number_of_files + max_concurrent_imports - 1) / max_concurrent_imports,
+        );
+        let import_all = || {
+            all_files
+                .par_iter()
+                .with_min_len(min_imports_per_task)
+                .map(|(p, format)| {
+                    let location = p.to_string_lossy();
+                    info!("importing corpus from {}", location);
+                    let name = self.import_from_fs(
+                        p,
+                        *format,
+                        None,
+                        disk_based,
+                        overwrite_existing,
+                        |status| progress.report(&format!("{}: {}", location, status)),
+                    )?;
+                    let finished = finished_imports.fetch_add(1, Ordering::SeqCst) + 1;
+                    progress.report(&format!(
+                        "imported corpus {} ({} of {})",
+                        name, finished, number_of_files
+                    ));
+                    Ok(name)
+                })
+                .collect()
+        };
+        let corpus_names: Result<Vec<String>> = if let Some(pool) = &self.query_config.thread_pool {
+            pool.install(import_all)
+        } else {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(max_concurrent_imports)
+                .build()?
+                .install(import_all)
+        };
+        let corpus_names = corpus_names?;
 
         // Delete temporary directory
         debug!(
@@ -931,36 +2801,125 @@ impl CorpusStorage {
     where
         F: Fn(&str),
     {
-        let (orig_name, mut graph, config) = match format {
-            ImportFormat::RelANNIS => relannis::load(path, disk_based, |status| {
-                progress_callback(status);
-                // loading the file from relANNIS consumes memory, update the corpus cache regularly to allow it to adapt
-                self.check_cache_size_and_remove(vec![], false);
-            })?,
-            ImportFormat::GraphML => {
-                let orig_corpus_name = if let Some(file_name) = path.file_stem() {
-                    file_name.to_string_lossy().to_string()
-                } else {
-                    "UnknownCorpus".to_string()
-                };
-                let input_file = File::open(path)?;
-                let (g, config_str) = graphannis_core::graph::serialization::graphml::import(
-                    input_file,
-                    disk_based,
-                    |status| {
-                        progress_callback(status);
-                        // loading the file from relANNIS consumes memory, update the corpus cache regularly to allow it to adapt
-                        self.check_cache_size_and_remove(vec![], false);
-                    },
-                )?;
-                let config = if let Some(config_str) = config_str {
-                    toml::from_str(&config_str)?
-                } else {
-                    CorpusConfiguration::default()
-                };
-                (orig_corpus_name.into(), g, config)
-            }
+        let pending = self.analyze_import_from_fs(path, format, disk_based, progress_callback)?;
+        self.commit_import(pending, corpus_name, overwrite_existing)
+    }
+
+    /// Load a corpus from an external location on the file system and analyze it, without adding it to this corpus storage.
+    ///
+    /// This is the first phase of a two-phase import, which allows to check the corpus (e.g. for conflicts
+    /// with existing corpora) before anything is written to the data directory.
+    /// Use [commit_import(...)](#method.commit_import) with the returned object to finish the import.
+    ///
+    /// - `path` - The location on the file system where the corpus data is located.
+    /// - `format` - The format in which this corpus data is stored.
+    /// - `disk_based` - If `true`, prefer disk-based annotation and graph storages instead of memory-only ones.
+    /// - `progress_callback` - A callback function to which the import progress is reported to.
+    pub fn analyze_import_from_fs<F>(
+        &self,
+        path: &Path,
+        format: ImportFormat,
+        disk_based: bool,
+        progress_callback: F,
+    ) -> Result<PendingImport>
+    where
+        F: Fn(&str),
+    {
+        self.analyze_import_from_fs_cancellable(
+            path,
+            format,
+            disk_based,
+            &AtomicBool::new(false),
+            progress_callback,
+        )
+    }
+
+    /// Load and analyze a corpus like [analyze_import_from_fs(...)](#method.analyze_import_from_fs), but abort
+    /// with [GraphAnnisError::ImportCancelled] when the `cancel_flag` is set, e.g. by another thread.
+    ///
+    /// Imports of the relANNIS format check the flag for each loaded row, other formats when the corpus has
+    /// been loaded.
+    pub fn analyze_import_from_fs_cancellable<F>(
+        &self,
+        path: &Path,
+        format: ImportFormat,
+        disk_based: bool,
+        cancel_flag: &AtomicBool,
+        progress_callback: F,
+    ) -> Result<PendingImport>
+    where
+        F: Fn(&str),
+    {
+        if disk_based && self.encryption_key.is_some() {
+            return Err(
+                graphannis_core::errors::GraphAnnisCoreError::EncryptionNotSupported(
+                    "disk-based annotation storage".to_string(),
+                )
+                .into(),
+            );
+        }
+        let progress = ProgressThrottle::new(progress_callback, self.progress_interval);
+        let report_status = |status: &str| {
+            progress.report(status);
+            // loading the file from relANNIS consumes memory, update the corpus cache regularly to allow it to adapt
+            self.check_cache_size_and_remove(vec![], false);
         };
+        // Temporary files of the import (e.g. spilled updates) are encrypted while the key is active.
+        let (orig_name, mut graph, config, duplicate_node_names) = encryption::with_key(
+            self.encryption_key.as_ref(),
+            &self.db_dir,
+            || -> Result<_> {
+                Ok(match format {
+                    ImportFormat::RelANNIS => {
+                        let ctx = relannis::ImportContext::new(&report_status)
+                            .with_cancel_flag(cancel_flag);
+                        let result = relannis::load(
+                            path,
+                            disk_based,
+                            self.duplicate_node_name_policy,
+                            &ctx,
+                        )?;
+                        info!("loaded {} rows from relANNIS tables", ctx.total_rows());
+                        result
+                    }
+                    ImportFormat::GraphML => {
+                        let orig_corpus_name = if let Some(file_name) = path.file_stem() {
+                            file_name.to_string_lossy().to_string()
+                        } else {
+                            "UnknownCorpus".to_string()
+                        };
+                        let input_file = File::open(path)?;
+                        let (g, config_str) =
+                            graphannis_core::graph::serialization::graphml::import(
+                                input_file,
+                                disk_based,
+                                |status| {
+                                    progress.report(status);
+                                    // loading the file from relANNIS consumes memory, update the corpus cache regularly to allow it to adapt
+                                    self.check_cache_size_and_remove(vec![], false);
+                                },
+                            )?;
+                        let config = if let Some(config_str) = config_str {
+                            toml::from_str(&config_str)?
+                        } else {
+                            CorpusConfiguration::default()
+                        };
+                        (orig_corpus_name.into(), g, config, Vec::new())
+                    }
+                    ImportFormat::CoNLLU => {
+                        let (orig_corpus_name, g, config) =
+                            conllu::load(path, disk_based, |status| {
+                                progress.report(status);
+                                self.check_cache_size_and_remove(vec![], false);
+                            })?;
+                        (orig_corpus_name.into(), g, config, Vec::new())
+                    }
+                })
+            },
+        )?;
+        if cancel_flag.load(Ordering::Relaxed) {
+            return Err(GraphAnnisError::ImportCancelled);
+        }
 
         let r = graph.ensure_loaded_all();
         if let Err(e) = r {
@@ -970,7 +2929,95 @@ impl CorpusStorage {
             );
         }
 
-        let corpus_name = corpus_name.unwrap_or_else(|| orig_name.into());
+        encryption::with_key(
+            self.encryption_key.as_ref(),
+            &self.db_dir,
+            || -> Result<()> {
+                progress.report("normalizing annotation values");
+                graph.normalize_unicode(self.unicode_normalization)?;
+
+                if self.derived_components_policy == DerivedComponentsPolicy::Rebuild {
+                    progress.report("rebuilding derived components");
+                    let nodes = rebuild_derived_components(&mut graph)?;
+                    info!("rebuilt derived components for {} nodes", nodes);
+                }
+
+                if !config.graph_storage.is_empty() {
+                    progress.report("applying configured graph storage implementations");
+                    apply_graph_storage_config(&mut graph, &config, true)?;
+                }
+
+                if let Some(scheme) = &self.node_name_scheme {
+                    progress.report("applying node name scheme");
+                    let renamed = apply_node_name_scheme(&mut graph, scheme.as_ref())?;
+                    info!("renamed {} nodes with the node name scheme", renamed);
+                }
+                Ok(())
+            },
+        )?;
+
+        let corpus_name: String = orig_name.into();
+
+        let estimated_node_count = graph
+            .get_node_annos()
+            .number_of_annotations_by_name(Some(ANNIS_NS), NODE_NAME);
+        let mut estimated_edge_count = 0;
+        for c in graph.get_all_components(None, None) {
+            if let Some(stats) = graph
+                .get_graphstorage(&c)
+                .and_then(|gs| gs.get_statistics().cloned())
+            {
+                estimated_edge_count += (stats.nodes as f64 * stats.avg_fan_out).round() as usize;
+            }
+        }
+        let annotation_layers: BTreeSet<String> = graph
+            .get_node_annos()
+            .annotation_keys()
+            .into_iter()
+            .filter(|k| k.ns != ANNIS_NS)
+            .map(|k| k.ns.to_string())
+            .collect();
+
+        let conflicts_with_existing = self.corpus_exists(&corpus_name)?;
+
+        let current_dir = PathBuf::from(".");
+        Ok(PendingImport {
+            analysis: ImportAnalysis {
+                corpus_name,
+                estimated_node_count,
+                estimated_edge_count,
+                annotation_layers: annotation_layers.into_iter().collect(),
+                conflicts_with_existing,
+                duplicate_node_names,
+            },
+            graph,
+            config,
+            source_dir: path.parent().unwrap_or(&current_dir).to_path_buf(),
+        })
+    }
+
+    /// Add a corpus that has been loaded with [analyze_import_from_fs(...)](#method.analyze_import_from_fs) to this corpus storage.
+    ///
+    /// - `pending` - The loaded corpus.
+    /// - `corpus_name` - Optionally override the name of the new corpus.
+    /// - `overwrite_existing` - If `true`, overwrite existing corpora. Otherwise return an error if the corpus already exists.
+    ///
+    /// Returns the name of the imported corpus.
+    pub fn commit_import(
+        &self,
+        pending: PendingImport,
+        corpus_name: Option<String>,
+        overwrite_existing: bool,
+    ) -> Result<String> {
+        self.lock.check_valid()?;
+        let PendingImport {
+            analysis,
+            mut graph,
+            config,
+            source_dir,
+        } = pending;
+
+        let corpus_name = corpus_name.unwrap_or(analysis.corpus_name);
         let escaped_corpus_name: Cow<str> =
             utf8_percent_encode(&corpus_name, PATH_SEGMENT_ENCODE_SET).into();
 
@@ -981,7 +3028,14 @@ impl CorpusStorage {
         let cache = &mut *cache_lock;
 
         // make sure the cache is not too large before adding the new corpus
-        check_cache_size_and_remove_with_cache(cache, &self.cache_strategy, vec![], false);
+        check_cache_size_and_remove_with_cache(
+            cache,
+            &self.cache_strategy,
+            &self.cache_usage,
+            &self.pinned_corpora,
+            vec![],
+            false,
+        );
 
         // remove any possible old corpus
         if cache.contains_key(&corpus_name) {
@@ -1006,17 +3060,15 @@ impl CorpusStorage {
         }
 
         info!("copying linked files for corpus {}", corpus_name);
-        let current_dir = PathBuf::from(".");
         let files_dir = db_path.join("files");
         std::fs::create_dir_all(&files_dir)?;
-        self.copy_linked_files_and_update_references(
-            path.parent().unwrap_or(&current_dir),
-            &files_dir,
-            &mut graph,
-        )?;
+        self.copy_linked_files_and_update_references(&source_dir, &files_dir, &mut graph)?;
 
         // save to its location
         info!("saving corpus {} to disk", corpus_name);
+        remove_corpus_summary(&db_path);
+        self.plan_cache.invalidate(&corpus_name, &db_path);
+        graph.set_encryption_key(self.encryption_key.clone());
         let save_result = graph.save_to(&db_path);
         if let Err(e) = save_result {
             error!(
@@ -1024,25 +3076,48 @@ impl CorpusStorage {
                 db_path.to_string_lossy(),
                 e
             );
+        } else {
+            save_corpus_summary(&db_path, &graph, self.encryption_key.as_ref());
+        }
+        info!("calculating document statistics for corpus {}", corpus_name);
+        if let Err(e) = docstats::write(
+            &db_path,
+            &docstats::calculate(&graph),
+            self.encryption_key.as_ref(),
+        ) {
+            warn!(
+                "Could not write document statistics for corpus {}: {:?}",
+                corpus_name, e
+            );
         }
+        // node IDs of a previously imported corpus with the same name are not valid anymore
+        renew_node_id_generation(&db_path, self.encryption_key.as_ref())?;
 
         // Use the imported/generated/default corpus configuration and store it in our graph directory
-        let corpus_config_path = db_path.join("corpus-config.toml");
+        let corpus_config_path = db_path.join(CORPUS_CONFIG_FILE);
         info!(
             "saving corpus configuration file for corpus {} to {}",
             corpus_name,
             &corpus_config_path.to_string_lossy()
         );
-        std::fs::write(corpus_config_path, toml::to_string(&config)?)?;
+        write_corpus_file(
+            &db_path,
+            &corpus_config_path,
+            &toml::to_string(&config)?,
+            self.encryption_key.as_ref(),
+        )?;
 
         // make it known to the cache
         cache.insert(
             corpus_name.clone(),
             Arc::new(RwLock::new(CacheEntry::Loaded(graph))),
         );
+        self.cache_usage.lock().unwrap().record_insert(&corpus_name);
         check_cache_size_and_remove_with_cache(
             cache,
             &self.cache_strategy,
+            &self.cache_usage,
+            &self.pinned_corpora,
             vec![&corpus_name],
             true,
         );
@@ -1069,9 +3144,8 @@ impl CorpusStorage {
         for node in file_nodes {
             // Get the linked file for this node
             if let Some(original_path) = node_annos.get_value_for_item(&node, &linked_file_key) {
-                let original_path = old_base_path
-                    .canonicalize()?
-                    .join(&PathBuf::from(original_path.as_ref()));
+                let old_base_path = old_base_path.canonicalize()?;
+                let original_path = old_base_path.join(PathBuf::from(original_path.as_ref()));
                 if original_path.is_file() {
                     if let Some(node_name) = node_annos.get_value_for_item(&node, &NODE_NAME_KEY) {
                         // Create a new file name based on the node name and copy the file
@@ -1079,7 +3153,12 @@ impl CorpusStorage {
                         if let Some(parent) = new_path.parent() {
                             std::fs::create_dir_all(parent)?;
                         }
-                        std::fs::copy(&original_path, &new_path)?;
+                        self.copy_linked_file(
+                            &old_base_path,
+                            &original_path,
+                            new_base_path,
+                            &new_path,
+                        )?;
                         // Update the annotation to link to the new file with a relative path.
                         // Use the corpus directory as base path for this relative path.
                         let relative_path = new_path.strip_prefix(&new_base_path)?;
@@ -1109,7 +3188,7 @@ impl CorpusStorage {
             name: "file".into(),
         };
 
-        let base_path = self.db_dir.join(corpus_name).join("files").canonicalize()?;
+        let base_path = self.linked_files_directory(corpus_name)?;
 
         // Find all nodes of the type "file"
         let node_annos: &dyn AnnotationStorage<NodeID> = graph.get_node_annos();
@@ -1132,6 +3211,17 @@ impl CorpusStorage {
         Ok(it)
     }
 
+    /// Get the directory of the linked files of a corpus.
+    fn linked_files_directory(&self, corpus_name: &str) -> Result<PathBuf> {
+        let base_path = self.db_dir.join(corpus_name).join("files");
+        // Corpora that have been created by updates don't have a directory for linked files
+        if base_path.is_dir() {
+            Ok(base_path.canonicalize()?)
+        } else {
+            Ok(base_path)
+        }
+    }
+
     fn copy_linked_files_to_disk(
         &self,
         corpus_name: &str,
@@ -1146,12 +3236,75 @@ impl CorpusStorage {
                 if let Some(parent) = new_path.parent() {
                     std::fs::create_dir_all(parent)?;
                 }
-                std::fs::copy(&original_path, &new_path)?;
+                if self.encryption_key.is_some() {
+                    std::fs::write(
+                        &new_path,
+                        self.read_linked_file(
+                            &self.linked_files_directory(corpus_name)?,
+                            &original_path,
+                        )?,
+                    )?;
+                } else {
+                    std::fs::copy(&original_path, &new_path)?;
+                }
             }
         }
         Ok(())
     }
 
+    /// Copy a linked file into the directory of the linked files of a corpus and encrypt it if an encryption key is
+    /// set.
+    ///
+    /// The source file can be an imported file or the encrypted file of another corpus. Encrypted files are identified
+    /// by their path relative to the directory of the linked files.
+    fn copy_linked_file(
+        &self,
+        source_dir: &Path,
+        source: &Path,
+        target_dir: &Path,
+        target: &Path,
+    ) -> Result<()> {
+        if let Some(key) = &self.encryption_key {
+            let content = std::fs::read(source)?;
+            let content = if encryption::is_encrypted(&content) {
+                encryption::with_key(Some(key), source_dir, || encryption::read(source))?
+            } else {
+                content
+            };
+            encryption::with_key(Some(key), target_dir, || {
+                encryption::write(target, &content)
+            })?;
+        } else {
+            std::fs::copy(source, target)?;
+        }
+        Ok(())
+    }
+
+    /// Read the content of a linked file in the directory `files_dir` of a corpus, which is decrypted if it is
+    /// encrypted.
+    fn read_linked_file(&self, files_dir: &Path, path: &Path) -> Result<Vec<u8>> {
+        let content = encryption::with_key(self.encryption_key.as_ref(), files_dir, || {
+            encryption::read(path)
+        })?;
+        Ok(content)
+    }
+
+    /// Get the content of the file which is linked by a node of the corpus.
+    ///
+    /// The `file_name` is the path relative to the directory of the linked files of the corpus, e.g. as returned
+    /// by the webservice. Encrypted files are decrypted with the [encryption key](#method.set_encryption_key).
+    pub fn linked_file_content(&self, corpus_name: &str, file_name: &str) -> Result<Vec<u8>> {
+        let file_name = Path::new(file_name.trim());
+        if !is_relative_path_without_parent(file_name) {
+            return Err(CorpusStorageError::InvalidLinkedFileName(
+                file_name.to_string_lossy().to_string(),
+            )
+            .into());
+        }
+        let files_dir = self.corpus_directory(corpus_name).join("files");
+        self.read_linked_file(&files_dir, &files_dir.join(file_name))
+    }
+
     fn export_corpus_graphml(&self, corpus_name: &str, path: &Path) -> Result<()> {
         let output_file = File::create(path)?;
         let entry = self.get_loaded_entry(corpus_name, false)?;
@@ -1229,21 +3382,30 @@ impl CorpusStorage {
         };
 
         let config_as_str: Option<&str> = config_as_str.as_deref();
+        let progress = ProgressThrottle::new(progress_callback, self.progress_interval);
         graphannis_core::graph::serialization::graphml::export(
             graph,
             config_as_str,
             &mut zip,
-            progress_callback,
+            |status| progress.report(status),
         )?;
+        progress.flush();
 
         // Insert all linked files into the ZIP file
         for (node_name, original_path) in self.get_linked_files(corpus_name.as_ref(), graph)? {
             let node_name: String = node_name;
 
             zip.start_file_from_path(&base_path.join(&node_name), options)?;
-            let file_to_copy = File::open(original_path)?;
-            let mut reader = BufReader::new(file_to_copy);
-            std::io::copy(&mut reader, zip)?;
+            if self.encryption_key.is_some() {
+                zip.write_all(&self.read_linked_file(
+                    &self.linked_files_directory(corpus_name.as_ref())?,
+                    &original_path,
+                )?)?;
+            } else {
+                let file_to_copy = File::open(original_path)?;
+                let mut reader = BufReader::new(file_to_copy);
+                std::io::copy(&mut reader, zip)?;
+            }
         }
 
         Ok(())
@@ -1255,6 +3417,7 @@ impl CorpusStorage {
         path: &Path,
         format: ExportFormat,
     ) -> Result<()> {
+        let corpora = self.resolve_shards(corpora)?;
         match format {
             ExportFormat::GraphML => {
                 if corpora.len() == 1 {
@@ -1273,11 +3436,11 @@ impl CorpusStorage {
                     if use_corpus_subdirectory {
                         // Use a sub-directory with the corpus name to avoid conflicts with the
                         // linked files
-                        path.push(corpus_name.as_ref());
+                        path.push(corpus_name.as_str());
                     } else {
                     };
                     std::fs::create_dir_all(&path)?;
-                    path.push(format!("{}.graphml", corpus_name.as_ref()));
+                    path.push(format!("{}.graphml", corpus_name.as_str()));
                     self.export_corpus_graphml(corpus_name.as_ref(), &path)?;
                 }
             }
@@ -1306,9 +3469,127 @@ impl CorpusStorage {
         Ok(())
     }
 
+    /// Export only the documents that contain at least one match of the `query`.
+    ///
+    /// Each document is exported as a separate GraphML file to the directory given by `path`. The directory
+    /// structure follows the document path, e.g. the document `corpus/doc1` is exported to
+    /// `<path>/corpus/doc1.graphml`. Documents whose path would be written outside of `path` (e.g. because it
+    /// contains `..`) are rejected with an error.
+    /// Returns the names of all exported documents, which include the corpus name (e.g. `corpus/doc1`).
+    pub fn export_matched_documents<S: AsRef<str>>(
+        &self,
+        query: SearchQuery<S>,
+        path: &Path,
+    ) -> Result<Vec<String>> {
+        let timeout = TimeoutCheck::new(query.timeout);
+        let mut result = Vec::new();
+
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let corpus_name: &str = cn.as_ref();
+            let documents = {
+                let prep = self.prepare_query(
+                    corpus_name,
+                    query.query,
+                    query.query_language,
+                    &query.document_filter,
+                    |_| vec![],
+                )?;
+
+                // acquire read-only lock and execute query
+                let lock = prep.db_entry.read().unwrap();
+                let db: &AnnotationGraph = get_read_or_error(&lock)?;
+                let plan = ExecutionPlan::from_disjunction(
+                    &prep.query,
+                    &db,
+                    &self.query_config_with_timeout(timeout),
+                )?;
+
+                let mut documents: BTreeSet<String> = BTreeSet::new();
+                for (match_nr, m) in plan.enumerate() {
+                    for singlematch in m.iter() {
+                        if let Some(node_name) = db
+                            .get_node_annos()
+                            .get_value_for_item(&singlematch.node, &NODE_NAME_KEY)
+                        {
+                            // extract the document path from the node name
+                            let doc_path = &node_name
+                                [0..node_name.rfind('#').unwrap_or_else(|| node_name.len())];
+                            documents.insert(doc_path.to_string());
+                        }
+                    }
+                    if match_nr % 1_000 == 0 {
+                        timeout.check()?;
+                    }
+                }
+                timeout.check()?;
+                documents
+            };
+
+            let config_as_str = if let Some(config) = self.get_corpus_config(corpus_name)? {
+                Some(toml::to_string_pretty(&config)?)
+            } else {
+                None
+            };
+
+            for doc_path in documents {
+                timeout.check()?;
+                let graph = self.subcorpus_graph(corpus_name, vec![doc_path.clone()])?;
+
+                let relative_path = format!("{}.graphml", doc_path);
+                if !is_relative_path_without_parent(Path::new(&relative_path)) {
+                    return Err(CorpusStorageError::InvalidDocumentExportPath(doc_path).into());
+                }
+                let output_path = path.join(relative_path);
+                if let Some(parent_dir) = output_path.parent() {
+                    std::fs::create_dir_all(parent_dir)?;
+                }
+                let output_file = File::create(&output_path)?;
+                graphannis_core::graph::serialization::graphml::export(
+                    &graph,
+                    config_as_str.as_deref(),
+                    output_file,
+                    |status| {
+                        info!("{}", status);
+                    },
+                )?;
+                result.push(doc_path);
+            }
+        }
+
+        Ok(result)
+    }
+
     /// Delete a corpus from this corpus storage.
     /// Returns `true` if the corpus was successfully deleted and `false` if no such corpus existed.
+    ///
+    /// Deleting a sharded corpus also deletes all its shards.
     pub fn delete(&self, corpus_name: &str) -> Result<bool> {
+        self.lock.check_valid()?;
+        let shards = {
+            let _lock = self.corpus_shards_lock.lock().unwrap();
+            let mut all_shards = self.read_corpus_shards()?;
+            let shards = all_shards.remove(corpus_name);
+            // a shard that is deleted on its own is not part of its sharded corpus anymore
+            let mut changed = shards.is_some();
+            for other_shards in all_shards.values_mut() {
+                let len_before = other_shards.len();
+                other_shards.retain(|s| s != corpus_name);
+                changed |= other_shards.len() != len_before;
+            }
+            if changed {
+                all_shards.retain(|_, other_shards| !other_shards.is_empty());
+                self.write_corpus_shards(&all_shards)?;
+            }
+            shards
+        };
+        if let Some(shards) = shards {
+            for s in shards {
+                self.delete(&s)?;
+            }
+            self.remove_from_all_corpus_groups(corpus_name)?;
+            return Ok(true);
+        }
+
         let mut db_path = PathBuf::from(&self.db_dir);
         db_path.push(corpus_name);
 
@@ -1331,32 +3612,195 @@ impl CorpusStorage {
                 })?
             }
 
+            drop(_lock);
+            drop(cache_lock);
+            self.plan_cache
+                .invalidate(corpus_name, &self.corpus_directory(corpus_name));
+            self.remove_from_all_corpus_groups(corpus_name)?;
+
             Ok(true)
         } else {
             Ok(false)
         }
     }
 
+    fn remove_from_all_corpus_groups(&self, corpus_name: &str) -> Result<()> {
+        let _lock = self.corpus_groups_lock.lock().unwrap();
+        let mut groups = self.read_corpus_groups()?;
+        let mut changed = false;
+        for corpora in groups.values_mut() {
+            changed |= corpora.remove(corpus_name);
+        }
+        if changed {
+            groups.retain(|_, corpora| !corpora.is_empty());
+            self.write_corpus_groups(&groups)?;
+        }
+        Ok(())
+    }
+
+    /// Apply an update that only changes the meta data of corpus and document nodes.
+    ///
+    /// In contrast to [apply_update(...)](#method.apply_update), all events are validated before the update is applied:
+    /// - only adding and deleting node labels is allowed,
+    /// - the labeled node must be an existing corpus or document node,
+    /// - annotations of the `annis` namespace can't be changed and
+    /// - if the corpus configuration declares a meta data schema, the annotation must be declared and its value
+    ///   must be valid.
+    ///
+    /// If any event is invalid, an error is returned and the corpus is not changed.
+    pub fn apply_metadata_update(
+        &self,
+        corpus_name: &str,
+        update: &mut GraphUpdate,
+    ) -> Result<UpdateStatistics> {
+        let schema = self
+            .get_corpus_config(corpus_name)?
+            .map(|config| config.metadata)
+            .unwrap_or_default();
+        {
+            let db_entry = self.get_loaded_entry(corpus_name, false)?;
+            let lock = db_entry.read().unwrap();
+            let db: &AnnotationGraph = get_read_or_error(&lock)?;
+
+            for (_, event) in update.iter()? {
+                let (node_name, anno_ns, anno_name, anno_value) = match &event {
+                    UpdateEvent::AddNodeLabel {
+                        node_name,
+                        anno_ns,
+                        anno_name,
+                        anno_value,
+                    }
+                    | UpdateEvent::AddNodeLabelValue {
+                        node_name,
+                        anno_ns,
+                        anno_name,
+                        anno_value,
+                    } => (node_name, anno_ns, anno_name, Some(anno_value)),
+                    UpdateEvent::DeleteNodeLabel {
+                        node_name,
+                        anno_ns,
+                        anno_name,
+                    } => (node_name, anno_ns, anno_name, None),
+                    _ => {
+                        return Err(CorpusStorageError::InvalidMetadataUpdate {
+                            node_name: String::default(),
+                            reason: format!(
+                                "event type {} is not allowed, only node labels can be changed",
+                                event.event_type()
+                            ),
+                        }
+                        .into())
+                    }
+                };
+                let invalid = |reason: String| -> GraphAnnisError {
+                    CorpusStorageError::InvalidMetadataUpdate {
+                        node_name: node_name.clone(),
+                        reason,
+                    }
+                    .into()
+                };
+
+                let node_id = db
+                    .get_node_id_from_name(node_name)
+                    .ok_or_else(|| invalid("node does not exist".to_string()))?;
+                let is_corpus_node = db
+                    .get_node_annos()
+                    .get_value_for_item(&node_id, &NODE_TYPE_KEY)
+                    .map(|node_type| node_type == "corpus")
+                    .unwrap_or(false);
+                if !is_corpus_node {
+                    return Err(invalid("not a corpus or document node".to_string()));
+                }
+                if anno_ns == ANNIS_NS {
+                    return Err(invalid(format!(
+                        "annotations of the namespace {} can not be changed",
+                        ANNIS_NS
+                    )));
+                }
+                if let (Some(anno_value), false) = (anno_value, schema.is_empty()) {
+                    let key = AnnoKey {
+                        ns: anno_ns.into(),
+                        name: anno_name.into(),
+                    };
+                    let definition =
+                        schema
+                            .iter()
+                            .find(|def| def.matches_key(&key))
+                            .ok_or_else(|| {
+                                invalid(format!(
+                                    "annotation {}::{} is not declared in the meta data schema",
+                                    anno_ns, anno_name
+                                ))
+                            })?;
+                    if !definition.is_valid_value(anno_value) {
+                        return Err(invalid(format!(
+                            "invalid value \"{}\" for annotation {}::{}",
+                            anno_value, anno_ns, anno_name
+                        )));
+                    }
+                }
+            }
+        }
+
+        self.apply_update(corpus_name, update)
+    }
+
     /// Apply a sequence of updates (`update` parameter) to this graph for a corpus given by the `corpus_name` parameter.
     ///
     /// It is ensured that the update process is atomic and that the changes are persisted to disk if the result is `Ok`.
-    pub fn apply_update(&self, corpus_name: &str, update: &mut GraphUpdate) -> Result<()> {
+    /// The returned statistics contain the number of applied events and the time needed for each phase of the update.
+    pub fn apply_update(
+        &self,
+        corpus_name: &str,
+        update: &mut GraphUpdate,
+    ) -> Result<UpdateStatistics> {
+        self.lock.check_valid()?;
         let db_entry = self.get_loaded_entry(corpus_name, true)?;
-        {
+        let corpus_dir = self.corpus_directory(corpus_name);
+        let (statistics, change_id) = {
             let mut lock = db_entry.write().unwrap();
             let db: &mut AnnotationGraph = get_write_or_error(&mut lock)?;
 
-            db.apply_update(update, |_| {})?;
-        }
-        // start background thread to persists the results
+            // Temporary tables of the update are encrypted while the key is active
+            let statistics = encryption::with_key(
+                self.encryption_key.as_ref(),
+                &corpus_dir,
+                || -> Result<_> {
+                    if self.unicode_normalization == UnicodeNormalization::None {
+                        Ok(db.apply_update(update, |_| {})?)
+                    } else {
+                        let mut normalized_update =
+                            update.normalize_unicode(self.unicode_normalization)?;
+                        Ok(db.apply_update(&mut normalized_update, |_| {})?)
+                    }
+                },
+            )?;
+            if statistics.events_per_type.contains_key("DeleteNode") {
+                // the IDs of deleted nodes can be re-used for new nodes
+                renew_node_id_generation(&corpus_dir, self.encryption_key.as_ref())?;
+            }
+            // the summary is calculated again when the changes have been persisted
+            remove_corpus_summary(&corpus_dir);
+            if self.statistics_refresh.is_some() {
+                let number_of_nodes = db
+                    .get_node_annos()
+                    .number_of_annotations_by_name(Some(ANNIS_NS), NODE_NAME);
+                self.add_statistics_drift(
+                    corpus_name,
+                    statistics.number_of_events(),
+                    number_of_nodes,
+                    db_entry.clone(),
+                );
+            }
+            (statistics, db.change_id())
+        };
+        // the persisted document statistics and optimized plans are outdated now
+        docstats::remove(&corpus_dir);
+        self.plan_cache.invalidate(corpus_name, &corpus_dir);
 
-        let active_background_workers = self.active_background_workers.clone();
-        {
-            let &(ref lock, ref _cvar) = &*active_background_workers;
-            let mut nr_active_background_workers = lock.lock().unwrap();
-            *nr_active_background_workers += 1;
-        }
-        thread::spawn(move || {
+        // start background job to persists the results
+        let encryption_key = self.encryption_key.clone();
+        self.spawn_background_worker(move || {
             trace!("Starting background thread to sync WAL updates");
             let lock = db_entry.read().unwrap();
             if let Ok(db) = get_read_or_error(&lock) {
@@ -1365,38 +3809,181 @@ impl CorpusStorage {
                     error!("Can't sync changes in background thread: {:?}", e);
                 } else {
                     trace!("Finished background thread to sync WAL updates");
+                    // If the corpus has been changed again, the summary is calculated by the job of the later update
+                    if db.change_id() == change_id {
+                        save_corpus_summary(&corpus_dir, db, encryption_key.as_ref());
+                    }
                 }
             }
-            let &(ref lock, ref cvar) = &*active_background_workers;
-            let mut nr_active_background_workers = lock.lock().unwrap();
-            *nr_active_background_workers -= 1;
-            cvar.notify_all();
         });
 
+        Ok(statistics)
+    }
+
+    /// Remember the number of changed items for a corpus and start the background job to re-calculate the
+    /// statistics if the configured threshold is exceeded.
+    fn add_statistics_drift(
+        &self,
+        corpus_name: &str,
+        changed_items: usize,
+        number_of_nodes: usize,
+        db_entry: Arc<RwLock<CacheEntry>>,
+    ) {
+        let refresh = if let Some(refresh) = &self.statistics_refresh {
+            refresh
+        } else {
+            return;
+        };
+        if self.abort_background_workers.load(Ordering::SeqCst) {
+            // the corpus storage is shut down
+            return;
+        }
+        {
+            let mut drift_lock = self.statistics_drift.lock().unwrap();
+            let drift = drift_lock.entry(corpus_name.to_string()).or_default();
+            drift.changed_items += changed_items;
+
+            let fraction = drift.changed_items as f64 / (number_of_nodes.max(1) as f64);
+            let throttled = drift
+                .last_refresh
+                .map(|t| t.elapsed() < refresh.min_interval)
+                .unwrap_or(false);
+            if drift.refresh_running || throttled || fraction < refresh.threshold {
+                return;
+            }
+            drift.refresh_running = true;
+            drift.changed_items = 0;
+        }
+
+        let corpus_dir = self.corpus_directory(corpus_name);
+        let corpus_name = corpus_name.to_string();
+        let statistics_drift = self.statistics_drift.clone();
+        let plan_cache = self.plan_cache.clone();
+        let abort = self.abort_background_workers.clone();
+        self.spawn_background_worker(move || {
+            debug!("Re-calculating statistics for corpus {}", corpus_name);
+            if refresh_statistics(&db_entry, &abort) {
+                // plans optimized with the old statistics might not be optimal anymore
+                plan_cache.invalidate(&corpus_name, &corpus_dir);
+                debug!(
+                    "Finished re-calculating statistics for corpus {}",
+                    corpus_name
+                );
+            } else {
+                debug!(
+                    "Aborted re-calculating statistics for corpus {}",
+                    corpus_name
+                );
+            }
+            if let Some(drift) = statistics_drift.lock().unwrap().get_mut(&corpus_name) {
+                drift.refresh_running = false;
+                drift.last_refresh = Some(std::time::Instant::now());
+            }
+        });
+    }
+
+    /// Normalize all annotation values of an existing corpus to the configured Unicode normal form
+    /// and persist the changes.
+    ///
+    /// This can be used to migrate corpora which have been imported without normalization or
+    /// with a different normal form.
+    pub fn renormalize_unicode(&self, corpus_name: &str) -> Result<()> {
+        self.lock.check_valid()?;
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let mut lock = db_entry.write().unwrap();
+        let db: &mut AnnotationGraph = get_write_or_error(&mut lock)?;
+
+        db.normalize_unicode(self.unicode_normalization)?;
+        db.background_sync_wal_updates()?;
+
+        // implementations of the components might have changed
+        save_corpus_summary(
+            &self.corpus_directory(corpus_name),
+            db,
+            self.encryption_key.as_ref(),
+        );
+        self.plan_cache
+            .invalidate(corpus_name, &self.corpus_directory(corpus_name));
+
         Ok(())
     }
 
+    /// Calculate the components which are derived from other components (the `LeftToken` and `RightToken`
+    /// components and the inherited coverage) of an existing corpus again for all nodes and persist the changes.
+    ///
+    /// This can be used to repair corpora with missing or incomplete derived components.
+    /// Returns the number of nodes for which the derived components have been calculated.
+    pub fn rebuild_derived_components(&self, corpus_name: &str) -> Result<usize> {
+        self.lock.check_valid()?;
+        let db_entry = self.get_loaded_entry(corpus_name, true)?;
+        let mut lock = db_entry.write().unwrap();
+        let db: &mut AnnotationGraph = get_write_or_error(&mut lock)?;
+
+        let nodes = rebuild_derived_components(db)?;
+        db.background_sync_wal_updates()?;
+
+        // implementations and statistics of the components might have changed
+        save_corpus_summary(
+            &self.corpus_directory(corpus_name),
+            db,
+            self.encryption_key.as_ref(),
+        );
+        self.plan_cache
+            .invalidate(corpus_name, &self.corpus_directory(corpus_name));
+
+        Ok(nodes)
+    }
+
+    /// Get the derived operators defined in the configuration of the corpus.
+    fn get_derived_operators(
+        &self,
+        corpus_name: &str,
+        query: &str,
+    ) -> Result<Vec<DerivedOperatorDefinition>> {
+        // Avoid reading the corpus configuration from disk for every query that does not use a derived operator.
+        if !query.contains('~') {
+            return Ok(Vec::new());
+        }
+        let derived_operators = self
+            .get_corpus_config(corpus_name)?
+            .map(|config| config.derived_operators)
+            .unwrap_or_default();
+        Ok(derived_operators)
+    }
+
     fn prepare_query<'a, F>(
         &self,
         corpus_name: &str,
         query: &'a str,
         query_language: QueryLanguage,
+        document_filter: &[String],
         additional_components_callback: F,
     ) -> Result<PreparationResult<'a>>
     where
         F: FnOnce(&AnnotationGraph) -> Vec<Component<AnnotationComponentType>>,
     {
         let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let derived_operators = self.get_derived_operators(corpus_name, query)?;
 
         // make sure the database is loaded with all necessary components
-        let (q, missing_components) = {
+        let (mut q, missing_components) = {
             let lock = db_entry.read().unwrap();
             let db = get_read_or_error(&lock)?;
 
-            let q = match query_language {
-                QueryLanguage::AQL => aql::parse(query, false)?,
-                QueryLanguage::AQLQuirksV3 => aql::parse(query, true)?,
+            let quirks_mode = match query_language {
+                QueryLanguage::AQL => false,
+                QueryLanguage::AQLQuirksV3 => true,
             };
+            let mut q = aql::parse_with_derived_operators(
+                query,
+                quirks_mode,
+                self.unicode_normalization,
+                &derived_operators,
+            )?;
+            q.check_limits(&self.query_config.limits)?;
+            if !document_filter.is_empty() {
+                aql::add_document_filter(&mut q, document_filter)?;
+            }
 
             let necessary_components = q.necessary_components(db);
 
@@ -1422,45 +4009,380 @@ impl CorpusStorage {
             {
                 let mut lock = db_entry.write().unwrap();
                 let db = get_write_or_error(&mut lock)?;
-                for c in missing_components {
-                    db.ensure_loaded(&c)?;
-                }
+                db.ensure_loaded_parallel(&missing_components)?;
             }
             self.check_cache_size_and_remove(vec![corpus_name], true);
         };
 
-        Ok(PreparationResult { query: q, db_entry })
+        // re-use the join order if this query has already been optimized for the corpus
+        let corpus_dir = self.corpus_directory(corpus_name);
+        let plan_key = plancache::PlanKey {
+            query: query.to_string(),
+            query_language,
+            document_filter: document_filter.to_vec(),
+            derived_operators: derived_operators_checksum(&derived_operators),
+        };
+        let cached_orders = self
+            .plan_cache
+            .get(
+                corpus_name,
+                &corpus_dir,
+                &plan_key,
+                self.encryption_key.as_ref(),
+            )
+            .filter(|orders| orders.len() == q.alternatives.len());
+        if let Some(cached_orders) = cached_orders {
+            for (alt, order) in q.alternatives.iter_mut().zip(cached_orders) {
+                alt.set_operator_order(order);
+            }
+        } else {
+            let optimized_orders: Option<Vec<Vec<usize>>> = {
+                let lock = db_entry.read().unwrap();
+                let db = get_read_or_error(&lock)?;
+                q.alternatives
+                    .iter()
+                    .map(|alt| alt.optimized_operator_order(db, &self.query_config).ok())
+                    .collect()
+            };
+            // Invalid queries are not cached, the error is reported when the plan is created.
+            if let Some(optimized_orders) = optimized_orders {
+                for (alt, order) in q.alternatives.iter_mut().zip(&optimized_orders) {
+                    alt.set_operator_order(order.clone());
+                }
+                self.plan_cache.insert(
+                    corpus_name,
+                    &corpus_dir,
+                    plan_key,
+                    optimized_orders,
+                    self.encryption_key.as_ref(),
+                );
+            }
+        }
+
+        Ok(PreparationResult {
+            query: q,
+            db_entry,
+            derived_operators,
+        })
     }
 
     /// Preloads all annotation and graph storages from the disk into a main memory cache.
     pub fn preload(&self, corpus_name: &str) -> Result<()> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            for shard in shards {
+                self.preload(&shard)?;
+            }
+            return Ok(());
+        }
         {
             let db_entry = self.get_loaded_entry(corpus_name, false)?;
             let mut lock = db_entry.write().unwrap();
             let db = get_write_or_error(&mut lock)?;
             db.ensure_loaded_all()?;
         }
+        // Creating the corpus information for the fully loaded corpus also persists the corpus summary
+        self.info(corpus_name)?;
         self.check_cache_size_and_remove(vec![corpus_name], true);
         Ok(())
     }
 
+    /// Pins a corpus in the cache, so it is not removed from the cache until the returned handle is dropped.
+    ///
+    /// The corpus is loaded if necessary, but in contrast to [preload(...)](#method.preload), only the
+    /// components that are already loaded or are needed by later queries are held in main memory.
+    /// This gives predictable latencies for a sequence of calls on the same corpus, e.g. in an interactive session.
+    pub fn pin(&self, corpus_name: &str) -> Result<PinnedCorpus> {
+        let entry = self.get_loaded_entry(corpus_name, false)?;
+        *self
+            .pinned_corpora
+            .lock()
+            .unwrap()
+            .entry(corpus_name.to_string())
+            .or_default() += 1;
+        Ok(PinnedCorpus {
+            corpus_name: corpus_name.to_string(),
+            pinned_corpora: self.pinned_corpora.clone(),
+            _entry: entry,
+        })
+    }
+
+    /// Execute a list of named queries on the given corpora and write the results of each query into a separate
+    /// file in `output_dir`.
+    ///
+    /// The file name is the name of the query with the extension of the output format, e.g. `nouns.csv`.
+    /// All corpora are pinned in the cache while the batch is executed, so they are only loaded once.
+    /// A failing query does not abort the batch, instead the error is part of its result.
+    ///
+    /// Returns the outcome for each query in the same order as the `queries`.
+    pub fn execute_batch<S: AsRef<str> + Sync>(
+        &self,
+        corpus_names: &[S],
+        queries: &[BatchQuery],
+        output_dir: &Path,
+        config: &BatchConfig,
+    ) -> Result<Vec<BatchQueryResult>> {
+        std::fs::create_dir_all(output_dir)?;
+        let _pinned: Vec<PinnedCorpus> = corpus_names
+            .iter()
+            .map(|c| self.pin(c.as_ref()))
+            .collect::<Result<_>>()?;
+
+        let mut known_names = HashSet::new();
+        let unique_names: Vec<bool> = queries
+            .iter()
+            .map(|q| known_names.insert(q.name.as_str()))
+            .collect();
+
+        let execute = |(q, is_unique): (&BatchQuery, &bool)| {
+            let start = std::time::Instant::now();
+            let outcome = if *is_unique {
+                self.execute_batch_query(corpus_names, q, output_dir, config)
+            } else {
+                Err(CorpusStorageError::InvalidBatchQueryName(q.name.clone()).into())
+            };
+            let (match_count, output_file, error) = match outcome {
+                Ok((match_count, output_file)) => (Some(match_count), Some(output_file), None),
+                Err(e) => (None, None, Some(e.to_string())),
+            };
+            BatchQueryResult {
+                name: q.name.clone(),
+                match_count,
+                output_file,
+                error,
+                duration: start.elapsed(),
+            }
+        };
+
+        let result = if config.parallel {
+            parallel::install(&self.query_config.thread_pool, || {
+                queries
+                    .par_iter()
+                    .zip(unique_names.par_iter())
+                    .map(execute)
+                    .collect()
+            })
+        } else {
+            queries
+                .iter()
+                .zip(unique_names.iter())
+                .map(execute)
+                .collect()
+        };
+        Ok(result)
+    }
+
+    fn execute_batch_query<S: AsRef<str>>(
+        &self,
+        corpus_names: &[S],
+        query: &BatchQuery,
+        output_dir: &Path,
+        config: &BatchConfig,
+    ) -> Result<(u64, PathBuf)> {
+        let is_valid_name = !query.name.is_empty()
+            && query.name != "."
+            && query.name != ".."
+            && !query.name.contains(['/', '\\']);
+        if !is_valid_name {
+            return Err(CorpusStorageError::InvalidBatchQueryName(query.name.clone()).into());
+        }
+        let search_query = SearchQuery {
+            corpus_names,
+            query: &query.query,
+            query_language: query.query_language,
+            timeout: config.timeout,
+            document_filter: Vec::new(),
+        };
+        let output_file = output_dir.join(format!("{}.{}", query.name, config.format.extension()));
+
+        let match_count = match config.mode {
+            BatchMode::Count => {
+                let count = self.count_extra(search_query)?;
+                match config.format {
+                    BatchOutputFormat::CSV => {
+                        let mut writer = csv::Writer::from_path(&output_file)?;
+                        writer.write_record(["match_count", "document_count"])?;
+                        writer.write_record(&[
+                            count.match_count.to_string(),
+                            count.document_count.to_string(),
+                        ])?;
+                        writer.flush()?;
+                    }
+                    BatchOutputFormat::JSON => {
+                        serde_json::to_writer_pretty(File::create(&output_file)?, &count)?;
+                    }
+                }
+                count.match_count
+            }
+            BatchMode::Find => {
+                let matches = self.find(search_query, 0, None, ResultOrder::Normal)?;
+                match config.format {
+                    BatchOutputFormat::CSV => {
+                        let mut writer = csv::Writer::from_path(&output_file)?;
+                        writer.write_record(["match"])?;
+                        for m in matches.iter() {
+                            writer.write_record([m])?;
+                        }
+                        writer.flush()?;
+                    }
+                    BatchOutputFormat::JSON => {
+                        serde_json::to_writer_pretty(File::create(&output_file)?, &matches)?;
+                    }
+                }
+                matches.len() as u64
+            }
+        };
+        Ok((match_count, output_file))
+    }
+
     /// Unloads a corpus from the cache.
+    ///
+    /// Queries or updates that are still running on this corpus keep their reference to the loaded corpus,
+    /// so the memory is only freed when they are finished.
+    /// Use [try_unload(...)](#method.try_unload) to only unload corpora that are not in use.
     pub fn unload(&self, corpus_name: &str) {
+        for shard in self.shards_of_corpus(corpus_name).unwrap_or_default() {
+            self.unload(&shard);
+        }
         let mut cache_lock = self.corpus_cache.write().unwrap();
         let cache = &mut *cache_lock;
         cache.remove(corpus_name);
     }
 
+    /// Unloads a corpus from the cache, but only if it is not used by any running query or update.
+    ///
+    /// - `corpus_name` - The corpus name to unload.
+    /// - `timeout` - If given, wait at most this amount of time for the running operations to finish.
+    ///   Otherwise, return immediately.
+    ///
+    /// Returns an error if the corpus is still in use, which contains the number of running operations.
+    pub fn try_unload(&self, corpus_name: &str, timeout: Option<Duration>) -> Result<()> {
+        let start = std::time::Instant::now();
+        loop {
+            {
+                let mut cache_lock = self.corpus_cache.write().unwrap();
+                let cache = &mut *cache_lock;
+                // New operations can only get a reference to the entry while holding the cache lock,
+                // so the number of references can't increase until the lock is released.
+                let active_users = cache
+                    .get(corpus_name)
+                    .map(|entry| Arc::strong_count(entry) - 1)
+                    .unwrap_or(0);
+                if active_users == 0 {
+                    cache.remove(corpus_name);
+                    return Ok(());
+                } else if timeout
+                    .map(|timeout| start.elapsed() >= timeout)
+                    .unwrap_or(true)
+                {
+                    return Err(CorpusStorageError::CorpusInUse {
+                        corpus: corpus_name.to_string(),
+                        active_users,
+                    }
+                    .into());
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Reloads a corpus from disk and replaces the cached version of the corpus with it.
+    ///
+    /// The new version is loaded before the old one is replaced, and running queries on this corpus are not
+    /// interrupted. If the corpus is updated while loading it, it is loaded again before replacing the cached version.
+    /// The configured components are preloaded and the configured graph storage implementations are used, like
+    /// when the corpus is loaded into the cache.
+    /// All operations started after this function returns will use the freshly loaded corpus.
+    pub fn force_reload(&self, corpus_name: &str) -> Result<()> {
+        let db_path = self.corpus_directory(corpus_name);
+        if !db_path.is_dir() {
+            return Err(GraphAnnisError::NoSuchCorpus(corpus_name.to_string()));
+        }
+
+        // Changes of previous updates might still be written to disk in the background
+        self.wait_for_background_workers();
+
+        let entry = self.get_entry(corpus_name)?;
+        let change_id_before_load = match &*entry.read().unwrap() {
+            CacheEntry::Loaded(db) => Some(db.change_id()),
+            CacheEntry::NotLoaded => None,
+        };
+
+        let load_start = std::time::Instant::now();
+        let db = self.load_for_reload(corpus_name, &db_path)?;
+        let mut load_time = load_start.elapsed();
+
+        {
+            // Wait for all running operations that hold a lock and swap the graph
+            let mut lock = entry.write().unwrap();
+            let change_id_after_load = match &*lock {
+                CacheEntry::Loaded(db) => Some(db.change_id()),
+                CacheEntry::NotLoaded => None,
+            };
+            let db = if change_id_after_load == change_id_before_load {
+                db
+            } else {
+                // The corpus has been updated while loading it, load it again so these changes are not lost
+                debug!(
+                    "Corpus {} has been changed while reloading it, loading it again",
+                    corpus_name
+                );
+                let load_start = std::time::Instant::now();
+                let db = self.load_for_reload(corpus_name, &db_path)?;
+                load_time = load_start.elapsed();
+                db
+            };
+            *lock = CacheEntry::Loaded(db);
+        }
+        // the corpus on disk might have been changed by another process
+        self.plan_cache.invalidate(corpus_name, &db_path);
+        self.cache_usage
+            .lock()
+            .unwrap()
+            .record_load(corpus_name, load_time);
+        info!("Reloaded corpus {}", corpus_name);
+        self.check_cache_size_and_remove(vec![corpus_name], true);
+        Ok(())
+    }
+
+    /// Load a corpus from disk for [force_reload(...)](#method.force_reload), configured like a corpus that is
+    /// loaded into the cache.
+    fn load_for_reload(&self, corpus_name: &str, db_path: &Path) -> Result<AnnotationGraph> {
+        let mut db = AnnotationGraph::new(false)?;
+        db.set_verify_checksums(self.verify_component_checksums);
+        db.set_encryption_key(self.encryption_key.clone());
+        db.load_from(db_path, false)?;
+        self.preload_configured_components(corpus_name, &mut db)?;
+        Ok(db)
+    }
+
     /// Optimize the node annotation and graph storage implementations of the given corpus.
     /// - `corpus_name` - The corpus name to optimize.
     /// - `disk_based` - If `true`, prefer disk-based annotation and graph storages instead of memory-only ones.
     #[doc(hidden)]
     pub fn reoptimize_implementation(&self, corpus_name: &str, disk_based: bool) -> Result<()> {
+        self.lock.check_valid()?;
         let graph_entry = self.get_loaded_entry(corpus_name, false)?;
         let mut lock = graph_entry.write().unwrap();
         let graph: &mut AnnotationGraph = get_write_or_error(&mut lock)?;
 
+        // load all components, so the configured implementations can be checked against their statistics
+        graph.ensure_loaded_all()?;
+        let config = self.get_corpus_config(corpus_name)?.unwrap_or_default();
+        apply_graph_storage_config(graph, &config, false)?;
         graph.optimize_impl(disk_based)?;
+        save_corpus_summary(
+            &self.corpus_directory(corpus_name),
+            graph,
+            self.encryption_key.as_ref(),
+        );
+        self.plan_cache
+            .invalidate(corpus_name, &self.corpus_directory(corpus_name));
+        docstats::write(
+            &self.corpus_directory(corpus_name),
+            &docstats::calculate(graph),
+            self.encryption_key.as_ref(),
+        )?;
         Ok(())
     }
 
@@ -1477,9 +4399,10 @@ impl CorpusStorage {
         query: &str,
         query_language: QueryLanguage,
     ) -> Result<bool> {
-        for cn in corpus_names {
+        let corpus_names = self.resolve_shards(corpus_names)?;
+        for cn in &corpus_names {
             let prep: PreparationResult =
-                self.prepare_query(cn.as_ref(), query, query_language, |_| vec![])?;
+                self.prepare_query(cn.as_ref(), query, query_language, &[], |_| vec![])?;
             // also get the semantic errors by creating an execution plan on the actual Graph
             let lock = prep.db_entry.read().unwrap();
             let db = get_read_or_error(&lock)?;
@@ -1488,6 +4411,14 @@ impl CorpusStorage {
         Ok(true)
     }
 
+    /// Get the query configuration for executing a query with the given timeout.
+    fn query_config_with_timeout(&self, timeout: TimeoutCheck) -> query::Config {
+        query::Config {
+            timeout: Some(timeout),
+            ..self.query_config.clone()
+        }
+    }
+
     /// Returns a string representation of the execution plan for a `query`.
     ///
     /// - `corpus_names` - The name of the corpora to execute the query on.
@@ -1499,35 +4430,98 @@ impl CorpusStorage {
         query: &str,
         query_language: QueryLanguage,
     ) -> Result<String> {
+        let corpus_names = self.resolve_shards(corpus_names)?;
         let mut all_plans = Vec::with_capacity(corpus_names.len());
-        for cn in corpus_names {
-            let prep = self.prepare_query(cn.as_ref(), query, query_language, |_| vec![])?;
+        for cn in &corpus_names {
+            let prep = self.prepare_query(cn.as_ref(), query, query_language, &[], |_| vec![])?;
 
             // acquire read-only lock and plan
             let lock = prep.db_entry.read().unwrap();
             let db = get_read_or_error(&lock)?;
             let plan = ExecutionPlan::from_disjunction(&prep.query, &db, &self.query_config)?;
 
-            all_plans.push(format!("{}:\n{}", cn.as_ref(), plan));
+            all_plans.push(format!("{}:\n{}", cn, plan));
         }
+        all_plans.push(format!(
+            "join thresholds: {}",
+            self.query_config.join_thresholds
+        ));
         Ok(all_plans.join("\n"))
     }
 
+    /// Returns the execution plan for a `query` as structured data, e.g. to display it graphically.
+    ///
+    /// The plan contains the same information as [plan(...)](#method.plan), like the estimated selectivity and
+    /// output size, the chosen join strategy and the used components of each execution node.
+    ///
+    /// - `corpus_names` - The name of the corpora to execute the query on.
+    /// - `query` - The query as string.
+    /// - `query_language` The query language of the query (e.g. AQL).
+    ///
+    /// Returns the execution plan for each corpus.
+    pub fn plan_structured<S: AsRef<str>>(
+        &self,
+        corpus_names: &[S],
+        query: &str,
+        query_language: QueryLanguage,
+    ) -> Result<Vec<QueryPlan>> {
+        let corpus_names = self.resolve_shards(corpus_names)?;
+        let mut all_plans = Vec::with_capacity(corpus_names.len());
+        for cn in &corpus_names {
+            let prep = self.prepare_query(cn.as_ref(), query, query_language, &[], |_| vec![])?;
+
+            // acquire read-only lock and plan
+            let lock = prep.db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            let plan = ExecutionPlan::from_disjunction(&prep.query, db, &self.query_config)?;
+
+            all_plans.push(QueryPlan {
+                corpus_name: cn.clone(),
+                alternatives: plan.plan_nodes(),
+                estimated_output_size: plan.estimated_output_size(),
+            });
+        }
+        Ok(all_plans)
+    }
+
     /// Count the number of results for a `query`.
     /// - `query` - The search query definition.
     /// Returns the count as number.
     pub fn count<S: AsRef<str>>(&self, query: SearchQuery<S>) -> Result<u64> {
+        self.count_with_warnings(query).map(|(count, _)| count)
+    }
+
+    /// Count the number of results for a `query` and report non-fatal issues of the query.
+    ///
+    /// This works like [count(...)](#method.count), but additionally returns a list of warnings, e.g. when a
+    /// component used by the query does not exist or a slow join implementation has to be used.
+    /// - `query` - The search query definition.
+    pub fn count_with_warnings<S: AsRef<str>>(
+        &self,
+        query: SearchQuery<S>,
+    ) -> Result<(u64, Vec<QueryWarning>)> {
         let timeout = TimeoutCheck::new(query.timeout);
         let mut total_count: u64 = 0;
-
-        for cn in query.corpus_names {
-            let prep =
-                self.prepare_query(cn.as_ref(), query.query, query.query_language, |_| vec![])?;
+        let mut warnings = Vec::new();
+
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |_| vec![],
+            )?;
 
             // acquire read-only lock and execute query
             let lock = prep.db_entry.read().unwrap();
             let db = get_read_or_error(&lock)?;
-            let plan = ExecutionPlan::from_disjunction(&prep.query, &db, &self.query_config)?;
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                &db,
+                &self.query_config_with_timeout(timeout),
+            )?;
+            add_query_warnings(&mut warnings, plan.warnings());
 
             for _ in plan {
                 total_count += 1;
@@ -1539,7 +4533,44 @@ impl CorpusStorage {
             timeout.check()?;
         }
 
-        Ok(total_count)
+        Ok((total_count, warnings))
+    }
+
+    /// Check if there is at least one result for a `query`.
+    ///
+    /// The execution is stopped at the first match and uses joins that produce their output without
+    /// materializing all matches of their input, which is much cheaper than [count(...)](#method.count)
+    /// when only the existence of a phenomenon is relevant.
+    /// - `query` - The search query definition.
+    pub fn exists<S: AsRef<str>>(&self, query: SearchQuery<S>) -> Result<bool> {
+        let timeout = TimeoutCheck::new(query.timeout);
+        let config = query::Config {
+            existence_check: true,
+            ..self.query_config_with_timeout(timeout)
+        };
+
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |_| vec![],
+            )?;
+
+            // acquire read-only lock and execute query
+            let lock = prep.db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            let mut plan = ExecutionPlan::from_disjunction(&prep.query, &db, &config)?;
+
+            if plan.next().is_some() {
+                return Ok(true);
+            }
+
+            timeout.check()?;
+        }
+
+        Ok(false)
     }
 
     /// Count the number of results for a `query` and return both the total number of matches and also the number of documents in the result set.
@@ -1551,14 +4582,23 @@ impl CorpusStorage {
         let mut match_count: u64 = 0;
         let mut document_count: u64 = 0;
 
-        for cn in query.corpus_names {
-            let prep =
-                self.prepare_query(cn.as_ref(), query.query, query.query_language, |_| vec![])?;
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |_| vec![],
+            )?;
 
             // acquire read-only lock and execute query
             let lock = prep.db_entry.read().unwrap();
             let db: &AnnotationGraph = get_read_or_error(&lock)?;
-            let plan = ExecutionPlan::from_disjunction(&prep.query, &db, &self.query_config)?;
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                &db,
+                &self.query_config_with_timeout(timeout),
+            )?;
 
             let mut known_documents: HashSet<SmartString> = HashSet::new();
 
@@ -1597,12 +4637,16 @@ impl CorpusStorage {
         &'b self,
         db: &'b AnnotationGraph,
         query: &'b Disjunction,
+        aql_query: &str,
+        derived_operators: &[DerivedOperatorDefinition],
+        document_filter: &[String],
         offset: usize,
         limit: Option<usize>,
         order: ResultOrder,
         quirks_mode: bool,
-    ) -> Result<(FindIterator<'b>, Option<usize>)> {
-        let mut query_config = self.query_config.clone();
+        timeout: &TimeoutCheck,
+    ) -> Result<(FindIterator<'b>, Option<usize>, Vec<QueryWarning>)> {
+        let mut query_config = self.query_config_with_timeout(*timeout);
         if order == ResultOrder::NotSorted {
             // Do execute query in parallel if the order should not be sorted to have a more stable result ordering.
             // Even if we do not promise to have a stable ordering, it should be the same
@@ -1611,6 +4655,7 @@ impl CorpusStorage {
         }
 
         let plan = ExecutionPlan::from_disjunction(query, &db, &query_config)?;
+        let warnings = plan.warnings().to_vec();
 
         // Try to find the relANNIS version by getting the attribute value which should be attached to the
         // toplevel corpus node.
@@ -1629,6 +4674,20 @@ impl CorpusStorage {
                 }
             }
         }
+        let document_order_it =
+            if order == ResultOrder::Normal && !plan.is_sorted_by_text() && !quirks_mode {
+                self.create_document_order_iterator(
+                    db,
+                    query,
+                    aql_query,
+                    derived_operators,
+                    document_filter,
+                    None,
+                )?
+            } else {
+                None
+            };
+
         let mut expected_size: Option<usize> = None;
         let base_it: FindIterator = if order == ResultOrder::NotSorted
             || (order == ResultOrder::Normal && plan.is_sorted_by_text() && !quirks_mode)
@@ -1636,7 +4695,10 @@ impl CorpusStorage {
             // If the output is already sorted correctly, directly return the iterator.
             // Quirks mode may change the order of the results, thus don't use the shortcut
             // if quirks mode is active.
-            Box::from(plan)
+            Box::from(plan.map(Ok))
+        } else if let Some(document_order_it) = document_order_it {
+            // Only the matches of the current document need to be sorted
+            Box::from(document_order_it)
         } else {
             let estimated_result_size = plan.estimated_output_size();
             // Estimations can be wrong on the upper limit, so limit the maximal reserved vector size
@@ -1654,6 +4716,11 @@ impl CorpusStorage {
                 let mut rng = rand::thread_rng();
                 tmp_results.shuffle(&mut rng);
             } else {
+                let seed = if let ResultOrder::Seeded(seed) = order {
+                    Some(seed)
+                } else {
+                    None
+                };
                 let token_helper = TokenHelper::new(db);
                 let component_order = Component::new(
                     AnnotationComponentType::Ordering,
@@ -1693,7 +4760,7 @@ impl CorpusStorage {
                     }
                 };
 
-                let sort_size = if let Some(limit) = limit {
+                let sort_size = if let (Some(limit), None) = (limit, seed) {
                     // we won't need to sort all items
                     offset + limit
                 } else {
@@ -1702,40 +4769,155 @@ impl CorpusStorage {
                 };
 
                 if self.query_config.use_parallel_joins {
-                    quicksort::sort_first_n_items_parallel(&mut tmp_results, sort_size, order_func);
+                    parallel::install(&self.query_config.thread_pool, || {
+                        quicksort::sort_first_n_items_parallel(
+                            &mut tmp_results,
+                            sort_size,
+                            order_func,
+                            timeout,
+                        )
+                    })?;
                 } else {
-                    quicksort::sort_first_n_items(&mut tmp_results, sort_size, order_func);
+                    quicksort::sort_first_n_items(
+                        &mut tmp_results,
+                        sort_size,
+                        order_func,
+                        timeout,
+                    )?;
+                }
+
+                if let Some(seed) = seed {
+                    // The results are sorted first, so that shuffling the same results
+                    // with the same seed always yields the same order
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    tmp_results.shuffle(&mut rng);
                 }
             }
             expected_size = Some(tmp_results.len());
-            Box::from(tmp_results.into_iter())
+            Box::from(tmp_results.into_iter().map(Ok))
         };
 
-        Ok((base_it, expected_size))
+        Ok((base_it, expected_size, warnings))
+    }
+
+    /// Create an iterator that executes the query for each document separately, if all matches of the query are
+    /// guaranteed to be part of a single document.
+    ///
+    /// If a `start` document is given, all documents sorted before it are omitted and the given number of matches
+    /// of the start document is skipped.
+    ///
+    /// Returns `None` if the query is not document-local or the necessary components are not loaded.
+    fn create_document_order_iterator<'b>(
+        &self,
+        db: &'b AnnotationGraph,
+        query: &Disjunction,
+        aql_query: &str,
+        derived_operators: &[DerivedOperatorDefinition],
+        document_filter: &[String],
+        start: Option<(&str, usize)>,
+    ) -> Result<Option<DocumentOrderIterator<'b>>> {
+        // Parse the query again without the artificial nodes of the document filter, so it can be checked if the
+        // query itself is document-local and the filter can be changed for each document
+        let mut document_query = aql::parse_with_derived_operators(
+            aql_query,
+            false,
+            self.unicode_normalization,
+            derived_operators,
+        )?;
+        if document_query.alternatives.len() != 1
+            || !document_query.alternatives[0].is_document_local(db)
+        {
+            return Ok(None);
+        }
+        let part_of_loaded = db
+            .get_all_components(Some(AnnotationComponentType::PartOf), None)
+            .iter()
+            .all(|c| db.get_graphstorage_as_ref(c).is_some());
+        if !part_of_loaded {
+            return Ok(None);
+        }
+
+        let mut documents: Vec<String> = db
+            .get_node_annos()
+            .exact_anno_search(Some(ANNIS_NS), "doc", ValueSearch::Any)
+            .filter_map(|m| {
+                db.get_node_annos()
+                    .get_value_for_item(&m.node, &NODE_NAME_KEY)
+            })
+            .map(|doc| doc.to_string())
+            .collect();
+        if !document_filter.is_empty() {
+            // The filter can also contain (sub-) corpora, which are not supported
+            let all_documents: HashSet<&String> = documents.iter().collect();
+            if !document_filter.iter().all(|d| all_documents.contains(d)) {
+                return Ok(None);
+            }
+            documents = document_filter.to_vec();
+        }
+        if documents.is_empty() {
+            return Ok(None);
+        }
+        documents.sort_by(|d1, d2| {
+            db::sort_matches::compare_document_path(d1, d2, CollationType::Default, false)
+        });
+        documents.dedup();
+
+        aql::add_document_filter(&mut document_query, &documents[0..1])?;
+
+        if let Some((start_document, _)) = start {
+            documents.retain(|d| {
+                db::sort_matches::compare_document_path(
+                    d,
+                    start_document,
+                    CollationType::Default,
+                    false,
+                ) != std::cmp::Ordering::Less
+            });
+        }
+
+        Ok(Some(DocumentOrderIterator {
+            db,
+            query: document_query,
+            query_config: self.query_config.clone(),
+            documents: documents.into_iter(),
+            output_size: query.alternatives[0].num_of_nodes()
+                - query.alternatives[0].num_of_optional_nodes(),
+            token_helper: TokenHelper::new(db),
+            gs_order: db.get_graphstorage_as_ref(&Component::new(
+                AnnotationComponentType::Ordering,
+                ANNIS_NS.into(),
+                "".into(),
+            )),
+            current_document: Vec::new().into_iter(),
+            current_document_name: None,
+            skip_in_document: start.map(|(document, n)| (document.to_string(), n)),
+        }))
     }
 
     fn find_in_single_corpus<S: AsRef<str>>(
         &self,
         query: &SearchQuery<S>,
         corpus_name: &str,
-        offset: usize,
-        limit: Option<usize>,
-        order: ResultOrder,
+        options: &FindOptions,
         timeout: TimeoutCheck,
-    ) -> Result<(Vec<String>, usize)> {
-        let prep = self.prepare_query(corpus_name, query.query, query.query_language, |db| {
-            let mut additional_components = vec![Component::new(
-                AnnotationComponentType::Ordering,
-                ANNIS_NS.into(),
-                "".into(),
-            )];
-            if order == ResultOrder::Normal || order == ResultOrder::Inverted {
-                for c in token_helper::necessary_components(db) {
-                    additional_components.push(c);
-                }
-            }
-            additional_components
-        })?;
+    ) -> Result<(Vec<MatchWithOffsets>, usize, Vec<QueryWarning>)> {
+        let offset = options.offset;
+        let limit = options.limit;
+        let order = options.order;
+        let with_offsets = options.with_offsets;
+        let projection = options
+            .projection
+            .as_ref()
+            .map(|projection| projection_variables(projection));
+        let projection = projection.as_ref();
+
+        let prep = self.prepare_query(
+            corpus_name,
+            query.query,
+            query.query_language,
+            &query.document_filter,
+            |db| find_additional_components(db, query.query_language, order, with_offsets),
+        )?;
 
         // acquire read-only lock and execute query
         let lock = prep.db_entry.read().unwrap();
@@ -1746,16 +4928,32 @@ impl CorpusStorage {
             QueryLanguage::AQLQuirksV3 => true,
         };
 
-        let (mut base_it, expected_size) = self.create_find_iterator_for_query(
+        if let Some(projection) = projection {
+            // Make sure all projected query nodes exist
+            for var in projection {
+                if prep.query.get_variable_pos(var).is_none() {
+                    return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                        desc: format!("Projected operand '#{}' not found", var),
+                        location: None,
+                    }));
+                }
+            }
+        }
+
+        let (mut base_it, expected_size, warnings) = self.create_find_iterator_for_query(
             db,
             &prep.query,
+            query.query,
+            &prep.derived_operators,
+            &query.document_filter,
             offset,
             limit,
             order,
             quirks_mode,
+            &timeout,
         )?;
 
-        let mut results: Vec<String> = if let Some(expected_size) = expected_size {
+        let mut results: Vec<MatchWithOffsets> = if let Some(expected_size) = expected_size {
             new_vector_with_memory_aligned_capacity(expected_size)
         } else if let Some(limit) = limit {
             new_vector_with_memory_aligned_capacity(limit)
@@ -1765,81 +4963,82 @@ impl CorpusStorage {
 
         // skip the first entries
         let mut skipped = 0;
-        while skipped < offset && base_it.next().is_some() {
+        while skipped < offset {
+            if base_it.next().transpose()?.is_none() {
+                break;
+            }
             skipped += 1;
 
             if skipped % 1_000 == 0 {
                 timeout.check()?;
             }
         }
-        let base_it: Box<dyn Iterator<Item = MatchGroup>> = if let Some(limit) = limit {
+        let base_it: FindIterator = if let Some(limit) = limit {
             Box::new(base_it.take(limit))
         } else {
             Box::new(base_it)
         };
 
+        let token_helper = if with_offsets {
+            TokenHelper::new(db)
+        } else {
+            None
+        };
+        let gs_order = db.get_graphstorage_as_ref(&Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        ));
+
+        // roots of the token chains that have been seen already
+        let mut known_roots = Vec::new();
+
         for (match_nr, m) in base_it.enumerate() {
+            let m = m?;
             let mut match_desc = String::new();
+            let mut offsets = Vec::new();
 
             for (i, singlematch) in m.iter().enumerate() {
-                // check if query node actually should be included in quirks mode
-                let include_in_output = if quirks_mode {
-                    if let Some(var) = prep.query.get_variable_by_pos(i) {
-                        prep.query.is_included_in_output(&var)
-                    } else {
-                        true
-                    }
+                // check if query node actually should be included in the output (e.g. because of quirks mode,
+                // the document filter or the projection)
+                let include_in_output = if let Some(var) = prep.query.get_variable_by_pos(i) {
+                    prep.query.is_included_in_output(&var)
+                        && projection.map(|p| p.contains(&var)).unwrap_or(true)
                 } else {
                     true
                 };
 
                 if include_in_output {
-                    if i > 0 {
+                    if !match_desc.is_empty() {
                         match_desc.push(' ');
                     }
 
-                    let singlematch_anno_key = &singlematch.anno_key;
-                    if singlematch_anno_key.ns != ANNIS_NS || singlematch_anno_key.name != NODE_TYPE
-                    {
-                        if !singlematch_anno_key.ns.is_empty() {
-                            let encoded_anno_ns: Cow<str> =
-                                utf8_percent_encode(&singlematch_anno_key.ns, SALT_URI_ENCODE_SET)
-                                    .into();
-                            match_desc.push_str(&encoded_anno_ns);
-                            match_desc.push_str("::");
-                        }
-                        let encoded_anno_name: Cow<str> =
-                            utf8_percent_encode(&singlematch_anno_key.name, SALT_URI_ENCODE_SET)
-                                .into();
-                        match_desc.push_str(&encoded_anno_name);
-                        match_desc.push_str("::");
+                    append_match_node_id(&mut match_desc, db, singlematch, quirks_mode);
+                    if options.format == MatchIdFormat::NodeNameAndId {
+                        match_desc.push('@');
+                        match_desc.push_str(&singlematch.node.to_string());
                     }
-
-                    if let Some(name) = db
-                        .get_node_annos()
-                        .get_value_for_item(&singlematch.node, &NODE_NAME_KEY)
-                    {
-                        if quirks_mode {
-                            // Unescape and re-escape with quirks-mode compatible character encoding set
-                            let decoded_name =
-                                percent_encoding::percent_decode_str(&name).decode_utf8_lossy();
-                            let re_encoded_name: Cow<str> =
-                                utf8_percent_encode(&decoded_name, QUIRKS_SALT_URI_ENCODE_SET)
-                                    .into();
-                            match_desc.push_str(&re_encoded_name);
-                        } else {
-                            match_desc.push_str(&name);
-                        }
+                    if with_offsets {
+                        offsets.push(text_offset(
+                            singlematch.node,
+                            token_helper.as_ref(),
+                            gs_order,
+                            &mut known_roots,
+                        ));
                     }
                 }
             }
-            results.push(match_desc);
+            results.push(MatchWithOffsets {
+                match_id: match_desc,
+                offsets,
+            });
             if match_nr % 1_000 == 0 {
                 timeout.check()?;
             }
         }
+        timeout.check()?;
 
-        Ok((results, skipped))
+        Ok((results, skipped, warnings))
     }
 
     /// Find all results for a `query` and return the match ID for each result.
@@ -1853,6 +5052,8 @@ impl CorpusStorage {
     ///
     /// Returns a vector of match IDs, where each match ID consists of the matched node annotation identifiers separated by spaces.
     /// You can use the [subgraph(...)](#method.subgraph) method to get the subgraph for a single match described by the node annnotation identifiers.
+    /// Use [find_with_options(...)](#method.find_with_options) for projections, other match ID formats, text offsets
+    /// or to get the warnings of the query.
     pub fn find<S: AsRef<str>>(
         &self,
         query: SearchQuery<S>,
@@ -1860,79 +5061,555 @@ impl CorpusStorage {
         limit: Option<usize>,
         order: ResultOrder,
     ) -> Result<Vec<String>> {
+        let options = FindOptions {
+            offset,
+            limit,
+            order,
+            ..Default::default()
+        };
+        let (result, _) = self.find_with_options(query, options)?;
+        Ok(result.into_iter().map(|m| m.match_id).collect())
+    }
+
+    /// Find all results for a `query` with the given `options`.
+    ///
+    /// This works like [find(...)](#method.find), but the [`FindOptions`] also allow to
+    /// - only include the query nodes of a `projection` in the match IDs,
+    /// - use [`MatchIdFormat::NodeNameAndId`] to add the internal ID of each matched node to the match ID, so
+    ///   follow-up calls using node IDs (e.g. [node_names_from_ids(...)](#method.node_names_from_ids)) don't need to
+    ///   resolve the node names again, and
+    /// - return the index of the left-most and right-most token covered by each included node (`with_offsets`), so
+    ///   highlights can be positioned in a text view without retrieving the subgraph of the match.
+    ///
+    /// Returns the matches and a list of non-fatal issues of the query, e.g. when a component used by the query does
+    /// not exist or a slow join implementation has to be used. The offsets of the matches are empty if they have not
+    /// been requested.
+    /// Returns an error if one of the variables in the projection is not part of the query.
+    pub fn find_with_options<S: AsRef<str>>(
+        &self,
+        query: SearchQuery<S>,
+        options: FindOptions,
+    ) -> Result<(Vec<MatchWithOffsets>, Vec<QueryWarning>)> {
         let timeout = TimeoutCheck::new(query.timeout);
 
         // Sort corpus names
-        let mut corpus_names: Vec<SmartString> = query
-            .corpus_names
+        let mut corpus_names: Vec<SmartString> = self
+            .resolve_shards(query.corpus_names)?
             .iter()
-            .map(|c| c.as_ref().into())
+            .map(|c| c.as_str().into())
             .collect();
 
         match corpus_names.len() {
-            0 => Ok(Vec::new()),
+            0 => Ok((Vec::new(), Vec::new())),
             1 => self
-                .find_in_single_corpus(
-                    &query,
-                    corpus_names[0].as_str(),
-                    offset,
-                    limit,
-                    order,
-                    timeout,
-                )
-                .map(|r| r.0),
+                .find_in_single_corpus(&query, corpus_names[0].as_str(), &options, timeout)
+                .map(|(result, _, warnings)| (result, warnings)),
             _ => {
-                if order == ResultOrder::Randomized {
-                    // This is still oddly ordered, because results from one corpus will always be grouped together.
-                    // But it still better than just output the same corpus first.
-                    let mut rng = rand::thread_rng();
-                    corpus_names.shuffle(&mut rng);
-                } else if order == ResultOrder::Inverted {
-                    corpus_names.sort();
-                    corpus_names.reverse();
-                } else {
-                    corpus_names.sort();
-                }
+                sort_corpus_names(&mut corpus_names, options.order);
 
                 // initialize the limit/offset values for the first corpus
-                let mut offset = offset;
-                let mut limit = limit;
+                let mut single_options = options;
 
                 let mut result = Vec::new();
+                let mut warnings = Vec::new();
                 for cn in corpus_names {
-                    let (single_result, skipped) = self.find_in_single_corpus(
-                        &query,
-                        cn.as_ref(),
-                        offset,
-                        limit,
-                        order,
-                        timeout,
-                    )?;
+                    let (single_result, skipped, single_warnings) =
+                        self.find_in_single_corpus(&query, cn.as_ref(), &single_options, timeout)?;
+
+                    add_query_warnings(&mut warnings, &single_warnings);
 
                     // Adjust limit and offset according to the found matches for the next corpus.
                     let single_result_length = single_result.len();
                     result.extend(single_result.into_iter());
 
-                    if let Some(current_limit) = limit {
+                    if let Some(current_limit) = single_options.limit {
                         if current_limit <= single_result_length {
                             // Searching in this corpus already yielded enough results
                             break;
                         } else {
                             // Adjust the limit for the next corpora to the already found results so-far
-                            limit = Some(current_limit - single_result_length);
+                            single_options.limit = Some(current_limit - single_result_length);
                         }
                     }
-                    if skipped < offset {
-                        offset -= skipped;
+                    if skipped < single_options.offset {
+                        single_options.offset -= skipped;
                     } else {
-                        offset = 0;
+                        single_options.offset = 0;
                     }
 
                     timeout.check()?;
                 }
-                Ok(result)
+                Ok((result, warnings))
+            }
+        }
+    }
+
+    /// Find results for a `query` starting at a position of the result stream, which was returned by a previous call.
+    ///
+    /// In contrast to [find(...)](#method.find) with an offset, the query is not executed again for the documents
+    /// before the cursor when the query is document-local and the normal result order is used.
+    /// This makes paginating deep into large result sets much cheaper.
+    /// For other queries and orders, the query is only executed again on the corpus of the cursor.
+    ///
+    /// - `query` - The search query definition.
+    /// - `cursor` - The cursor returned by the previous call or `None` to start with the first result.
+    /// - `limit` - Return at most `n` matches, where `n` is the limit.
+    /// - `order` - Specify the order of the matches. The randomized order is not supported, since it is not stable.
+    ///
+    /// Returns the match IDs and a cursor pointing after the last returned match, which is `None` if all results
+    /// have been returned.
+    pub fn find_with_cursor<S: AsRef<str>>(
+        &self,
+        query: SearchQuery<S>,
+        cursor: Option<&FindCursor>,
+        limit: usize,
+        order: ResultOrder,
+    ) -> Result<(Vec<String>, Option<FindCursor>)> {
+        if order == ResultOrder::Randomized {
+            return Err(CorpusStorageError::InvalidFindCursor(
+                "the randomized result order is not stable".to_string(),
+            )
+            .into());
+        }
+        let timeout = TimeoutCheck::new(query.timeout);
+
+        let mut corpus_names: Vec<SmartString> = self
+            .resolve_shards(query.corpus_names)?
+            .iter()
+            .map(|c| c.as_str().into())
+            .collect();
+        sort_corpus_names(&mut corpus_names, order);
+        let mut corpora = Vec::with_capacity(corpus_names.len());
+        for cn in &corpus_names {
+            let db_entry = self.get_loaded_entry(cn, false)?;
+            let lock = db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            corpora.push((cn.clone(), db.change_id()));
+        }
+        let fingerprint = FindCursor::fingerprint(&query, &corpora, order);
+
+        let mut start_corpus = 0;
+        let mut document = None;
+        let mut position = 0;
+        if let Some(cursor) = cursor {
+            if cursor.fingerprint != fingerprint {
+                return Err(CorpusStorageError::InvalidFindCursor(
+                    "the cursor was created for a different query".to_string(),
+                )
+                .into());
+            }
+            start_corpus = corpus_names
+                .iter()
+                .position(|cn| cn.as_str() == cursor.corpus)
+                .ok_or_else(|| CorpusStorageError::InvalidFindCursor(cursor.to_string()))?;
+            document = cursor.document.clone();
+            position = cursor.position;
+        }
+
+        let mut result = Vec::new();
+        for cn in &corpus_names[start_corpus..] {
+            let options = FindOptions {
+                offset: position,
+                limit: Some(limit),
+                order,
+                ..Default::default()
+            };
+            let next_position = self.find_in_corpus_from_position(
+                &query,
+                cn,
+                document.take(),
+                &options,
+                timeout,
+                &mut result,
+            )?;
+            if let Some((document, position)) = next_position {
+                let cursor = FindCursor {
+                    fingerprint,
+                    corpus: cn.to_string(),
+                    document,
+                    position,
+                };
+                return Ok((result, Some(cursor)));
+            }
+            position = 0;
+            timeout.check()?;
+        }
+        Ok((result, None))
+    }
+
+    /// Add the results of a single corpus to `result`, starting with the match after the given `document` and the
+    /// position given as offset of the `options`.
+    /// The limit of the `options` applies to the number of all matches in `result`.
+    ///
+    /// Returns the position after the last added match if the limit has been reached.
+    fn find_in_corpus_from_position<S: AsRef<str>>(
+        &self,
+        query: &SearchQuery<S>,
+        corpus_name: &str,
+        document: Option<String>,
+        options: &FindOptions,
+        timeout: TimeoutCheck,
+        result: &mut Vec<String>,
+    ) -> Result<Option<(Option<String>, usize)>> {
+        let position = options.offset;
+        let limit = options.limit.unwrap_or(usize::MAX);
+        let order = options.order;
+        let quirks_mode = match query.query_language {
+            QueryLanguage::AQL => false,
+            QueryLanguage::AQLQuirksV3 => true,
+        };
+        if order == ResultOrder::Normal && !quirks_mode {
+            let prep = self.prepare_query(
+                corpus_name,
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |db| find_additional_components(db, query.query_language, order, false),
+            )?;
+            let lock = prep.db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                db,
+                &self.query_config_with_timeout(timeout),
+            )?;
+            let document_order_it = if plan.is_sorted_by_text() {
+                None
+            } else {
+                self.create_document_order_iterator(
+                    db,
+                    &prep.query,
+                    query.query,
+                    &prep.derived_operators,
+                    &query.document_filter,
+                    document.as_deref().map(|d| (d, position)),
+                )?
+            };
+            if let Some(mut it) = document_order_it {
+                let mut current_document = document;
+                let mut in_document = position;
+                loop {
+                    if result.len() >= limit {
+                        return Ok(Some((current_document, in_document)));
+                    }
+                    let m = match it.next() {
+                        Some(m) => m?,
+                        None => {
+                            timeout.check()?;
+                            return Ok(None);
+                        }
+                    };
+                    if it.current_document_name != current_document {
+                        current_document = it.current_document_name.clone();
+                        in_document = 0;
+                    }
+                    in_document += 1;
+                    result.push(create_match_id(db, &prep.query, &m, quirks_mode));
+                    if result.len() % 1_000 == 0 {
+                        timeout.check()?;
+                    }
+                }
             }
         }
+        if document.is_some() {
+            return Err(CorpusStorageError::InvalidFindCursor(
+                "the query can not be executed document by document anymore".to_string(),
+            )
+            .into());
+        }
+
+        let remaining = limit - result.len();
+        let single_options = FindOptions {
+            limit: Some(remaining),
+            ..options.clone()
+        };
+        let (single_result, _, _) =
+            self.find_in_single_corpus(query, corpus_name, &single_options, timeout)?;
+        let found = single_result.len();
+        result.extend(single_result.into_iter().map(|m| m.match_id));
+        if found >= remaining {
+            Ok(Some((None, position + found)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Resolve a list of node names (e.g. from a match ID) to the internal node IDs of a corpus.
+    ///
+    /// - `corpus_name` - The name of the corpus the nodes belong to.
+    /// - `node_names` - The node annotation identifiers to resolve.
+    ///
+    /// Returns a vector in the same order as the given node names, which contains `None` for
+    /// node names that do not exist in the corpus.
+    pub fn node_ids_from_names<S: AsRef<str>>(
+        &self,
+        corpus_name: &str,
+        node_names: &[S],
+    ) -> Result<Vec<Option<NodeID>>> {
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+
+        Ok(db.node_ids_from_names(node_names))
+    }
+
+    /// Get the generation of the internal node IDs of a corpus.
+    ///
+    /// Node IDs (e.g. returned by [`node_ids_from_names(...)`](#method.node_ids_from_names)) are only valid as long as
+    /// the generation does not change. A new generation is started when the corpus is (re-) imported or nodes are
+    /// deleted by an update, because the IDs of deleted nodes can be re-used.
+    pub fn node_id_generation(&self, corpus_name: &str) -> Result<u64> {
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let corpus_dir = self.corpus_directory(corpus_name);
+        {
+            let _lock = db_entry.read().unwrap();
+            if let Some(generation) =
+                read_node_id_generation(&corpus_dir, self.encryption_key.as_ref())?
+            {
+                return Ok(generation);
+            }
+        }
+        // Corpora created before the generation was introduced or by an update don't have one yet.
+        // Make sure no other caller creates a different generation at the same time.
+        let _lock = db_entry.write().unwrap();
+        if let Some(generation) =
+            read_node_id_generation(&corpus_dir, self.encryption_key.as_ref())?
+        {
+            Ok(generation)
+        } else {
+            renew_node_id_generation(&corpus_dir, self.encryption_key.as_ref())
+        }
+    }
+
+    /// Check that the given node ID generation is still the current one.
+    ///
+    /// This must be called while holding the lock of the corpus entry, so the generation can't be renewed by an
+    /// update before the nodes have been accessed. The generation itself must have been created before acquiring
+    /// the lock, e.g. by calling [`node_id_generation(...)`](#method.node_id_generation).
+    fn check_node_id_generation(&self, corpus_name: &str, generation: u64) -> Result<()> {
+        let current = read_node_id_generation(
+            &self.corpus_directory(corpus_name),
+            self.encryption_key.as_ref(),
+        )?;
+        if current == Some(generation) {
+            Ok(())
+        } else {
+            Err(GraphAnnisError::CorpusStorage(
+                CorpusStorageError::OutdatedNodeIdGeneration {
+                    corpus: corpus_name.to_string(),
+                    given: generation,
+                    current: current.unwrap_or_default(),
+                },
+            ))
+        }
+    }
+
+    /// Get the node names for a list of internal node IDs.
+    ///
+    /// - `corpus_name` - The name of the corpus the nodes belong to.
+    /// - `node_ids` - The internal node IDs.
+    /// - `generation` - The [node ID generation](#method.node_id_generation) the IDs have been retrieved with.
+    ///
+    /// Returns a vector in the same order as the given IDs, which contains `None` for IDs without a node.
+    /// Returns an error if the node ID generation of the corpus has changed.
+    pub fn node_names_from_ids(
+        &self,
+        corpus_name: &str,
+        node_ids: &[NodeID],
+        generation: u64,
+    ) -> Result<Vec<Option<String>>> {
+        // make sure the corpus has a node ID generation
+        self.node_id_generation(corpus_name)?;
+
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        self.check_node_id_generation(corpus_name, generation)?;
+
+        let result = node_ids
+            .iter()
+            .map(|id| {
+                db.get_node_annos()
+                    .get_value_for_item(id, &NODE_NAME_KEY)
+                    .map(|name| name.to_string())
+            })
+            .collect();
+        Ok(result)
+    }
+
+    /// Find all nodes which internal ID is inside the given range.
+    ///
+    /// - `corpus_name` - The name of the corpus to search in.
+    /// - `node_ids` - The range of internal node IDs.
+    /// - `generation` - The [node ID generation](#method.node_id_generation) the range refers to.
+    ///
+    /// Returns the ID and name of each existing node in the range, sorted by the ID.
+    /// Returns an error if the node ID generation of the corpus has changed.
+    pub fn nodes_in_id_range(
+        &self,
+        corpus_name: &str,
+        node_ids: std::ops::Range<NodeID>,
+        generation: u64,
+    ) -> Result<Vec<(NodeID, String)>> {
+        // make sure the corpus has a node ID generation
+        self.node_id_generation(corpus_name)?;
+
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        self.check_node_id_generation(corpus_name, generation)?;
+
+        let node_annos = db.get_node_annos();
+        // there are no nodes after the largest ID
+        let end = if let Some(largest) = node_annos.get_largest_item() {
+            std::cmp::min(node_ids.end, largest.saturating_add(1))
+        } else {
+            node_ids.start
+        };
+        let result = (node_ids.start..end)
+            .filter_map(|id| {
+                node_annos
+                    .get_value_for_item(&id, &NODE_NAME_KEY)
+                    .map(|name| (id, name.to_string()))
+            })
+            .collect();
+        Ok(result)
+    }
+
+    /// Reconstruct the text covered by each of the given nodes from the values of the covered tokens.
+    ///
+    /// - `corpus_name` - The name of the corpus the nodes belong to.
+    /// - `node_names` - The node annotation identifiers to get the covered text for.
+    /// - `whitespace` - Defines if the original spacing of the text (as given by the `annis::tok-whitespace-before` and
+    ///   `annis::tok-whitespace-after` annotations) is reproduced or the tokens are separated by a single space.
+    ///
+    /// Returns a vector in the same order as the given node names, which contains `None` for
+    /// node names that do not exist in the corpus or do not cover any token.
+    pub fn covered_text<S: AsRef<str>>(
+        &self,
+        corpus_name: &str,
+        node_names: &[S],
+        whitespace: WhitespaceHandling,
+    ) -> Result<Vec<Option<String>>> {
+        let component_order = Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        );
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let mut components = {
+            let lock = db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            token_helper::necessary_components(db)
+        };
+        components.insert(component_order.clone());
+        let db_entry =
+            self.get_loaded_entry_with_components(corpus_name, components.into_iter().collect())?;
+
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+
+        let token_helper = TokenHelper::new(db);
+        let gs_order = db.get_graphstorage_as_ref(&component_order);
+        let result = db
+            .node_ids_from_names(node_names)
+            .into_iter()
+            .map(|node| match (node, &token_helper, gs_order) {
+                (Some(node), Some(token_helper), Some(gs_order)) => {
+                    token_helper.covered_text(node, gs_order, whitespace)
+                }
+                _ => None,
+            })
+            .collect();
+        Ok(result)
+    }
+
+    /// Get the matched annotation and the covered tokens for each node of the given matches.
+    ///
+    /// This allows to highlight exactly which annotation of a node was matched by a query without retrieving the
+    /// subgraph of the match.
+    ///
+    /// - `corpus_name` - The name of the corpus the matches belong to.
+    /// - `match_ids` - The match IDs as returned by [find(...)](#method.find).
+    ///
+    /// Returns a vector in the same order as the given match IDs with an entry for each node of the match, which
+    /// is `None` if the node does not exist in the corpus.
+    pub fn matched_nodes<S: AsRef<str>>(
+        &self,
+        corpus_name: &str,
+        match_ids: &[S],
+    ) -> Result<Vec<Vec<Option<MatchedNode>>>> {
+        let component_order = Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        );
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let mut components = {
+            let lock = db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            token_helper::necessary_components(db)
+        };
+        components.insert(component_order.clone());
+        let db_entry =
+            self.get_loaded_entry_with_components(corpus_name, components.into_iter().collect())?;
+
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+
+        let token_helper = TokenHelper::new(db);
+        let gs_order = db.get_graphstorage_as_ref(&component_order);
+
+        // resolve the node names of all matches at once
+        let parsed_matches: Vec<Vec<(Option<AnnoKey>, &str)>> = match_ids
+            .iter()
+            .map(|m| {
+                m.as_ref()
+                    .split_whitespace()
+                    .map(parse_match_node_id)
+                    .collect()
+            })
+            .collect();
+        let node_ids =
+            db.node_ids_from_names(parsed_matches.iter().flatten().map(|(_, name)| *name));
+        let mut node_ids = node_ids.into_iter();
+
+        let mut result = Vec::with_capacity(parsed_matches.len());
+        for match_nodes in parsed_matches {
+            let mut nodes = Vec::with_capacity(match_nodes.len());
+            for (anno_key, node_name) in match_nodes {
+                let node = node_ids.next().flatten();
+                nodes.push(node.map(|node| {
+                    let annotation = anno_key.and_then(|key| {
+                        let val = db.get_node_annos().get_value_for_item(&node, &key)?;
+                        Some(Annotation {
+                            key,
+                            val: val.into(),
+                        })
+                    });
+                    let covered_tokens = match (&token_helper, gs_order) {
+                        (Some(token_helper), Some(gs_order)) => token_helper
+                            .covered_token(node, gs_order)
+                            .into_iter()
+                            .filter_map(|t| {
+                                db.get_node_annos()
+                                    .get_value_for_item(&t, &NODE_NAME_KEY)
+                                    .map(|name| name.to_string())
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    MatchedNode {
+                        node_name: node_name.to_string(),
+                        annotation,
+                        covered_tokens,
+                    }
+                }));
+            }
+            result.push(nodes);
+        }
+        Ok(result)
     }
 
     /// Return the copy of a subgraph which includes the given list of node annotation identifiers,
@@ -1951,87 +5628,115 @@ impl CorpusStorage {
         ctx_right: usize,
         segmentation: Option<String>,
     ) -> Result<AnnotationGraph> {
+        // remove the obsolete "salt:/" prefix
+        let node_names: BTreeSet<String> = node_ids
+            .into_iter()
+            .map(|id| id.strip_prefix("salt:/").map(String::from).unwrap_or(id))
+            .collect();
+
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let mut graphs = Vec::new();
+            for (shard, shard_nodes) in
+                self.route_to_shards(&shards, node_names.into_iter().collect())?
+            {
+                graphs.push(self.subgraph(
+                    &shard,
+                    shard_nodes,
+                    ctx_left,
+                    ctx_right,
+                    segmentation.clone(),
+                )?);
+            }
+            return sharding::merge_graphs(graphs);
+        }
+
         let db_entry = self.get_fully_loaded_entry(corpus_name)?;
 
         let mut query = Disjunction {
             alternatives: vec![],
         };
 
-        // find all nodes covering the same token
-        for source_node_id in node_ids {
-            // remove the obsolete "salt:/" prefix
-            let source_node_id: &str = source_node_id
-                .strip_prefix("salt:/")
-                .unwrap_or(&source_node_id);
-
-            let m = NodeSearchSpec::ExactValue {
+        // Search all given nodes with a single node search, so the number of alternatives does
+        // not depend on the number of nodes.
+        let m = if node_names.len() == 1 {
+            NodeSearchSpec::ExactValue {
                 ns: Some(ANNIS_NS.to_string()),
                 name: NODE_NAME.to_string(),
-                val: Some(source_node_id.to_string()),
+                val: node_names.into_iter().next(),
                 is_meta: false,
-            };
-
-            // nodes overlapping the match: m _o_ node
-            {
-                let mut q = Conjunction::new();
-                let node_idx = q.add_node(NodeSearchSpec::AnyNode, None);
-                let m_idx = q.add_node(m.clone(), None);
-                q.add_operator(
-                    Box::new(operators::OverlapSpec { reflexive: true }),
-                    &m_idx,
-                    &node_idx,
-                    false,
-                )?;
-                query.alternatives.push(q);
             }
-
-            // token left/right and their overlapped nodes
-            if let Some(ref segmentation) = segmentation {
-                add_subgraph_precedence_with_segmentation(
-                    &mut query,
-                    ctx_left,
-                    segmentation,
-                    &m,
-                    true,
-                )?;
-                add_subgraph_precedence_with_segmentation(
-                    &mut query,
-                    ctx_right,
-                    segmentation,
-                    &m,
-                    false,
-                )?;
-            } else {
-                add_subgraph_precedence(&mut query, ctx_left, &m, true)?;
-                add_subgraph_precedence(&mut query, ctx_right, &m, false)?;
+        } else {
+            NodeSearchSpec::ExactValueSet {
+                ns: Some(ANNIS_NS.to_string()),
+                name: NODE_NAME.to_string(),
+                vals: node_names.into_iter().collect(),
+                is_meta: false,
             }
+        };
 
-            // add the textual data sources (which are not part of the corpus graph)
-            {
-                let mut q = Conjunction::new();
-                let datasource_idx = q.add_node(
-                    NodeSearchSpec::ExactValue {
-                        ns: Some(ANNIS_NS.to_string()),
-                        name: NODE_TYPE.to_string(),
-                        val: Some("datasource".to_string()),
-                        is_meta: false,
+        // find all nodes covering the same token
+        // (nodes overlapping the match: m _o_ node)
+        {
+            let mut q = Conjunction::new();
+            let node_idx = q.add_node(NodeSearchSpec::AnyNode, None);
+            let m_idx = q.add_node(m.clone(), None);
+            q.add_operator(
+                Box::new(operators::OverlapSpec { reflexive: true }),
+                &m_idx,
+                &node_idx,
+                false,
+            )?;
+            query.alternatives.push(q);
+        }
+
+        // token left/right and their overlapped nodes
+        if let Some(ref segmentation) = segmentation {
+            add_subgraph_precedence_with_segmentation(
+                &mut query,
+                ctx_left,
+                segmentation,
+                &m,
+                true,
+            )?;
+            add_subgraph_precedence_with_segmentation(
+                &mut query,
+                ctx_right,
+                segmentation,
+                &m,
+                false,
+            )?;
+        } else {
+            add_subgraph_precedence(&mut query, ctx_left, &m, true)?;
+            add_subgraph_precedence(&mut query, ctx_right, &m, false)?;
+        }
+
+        // add the textual data sources (which are not part of the corpus graph)
+        {
+            let mut q = Conjunction::new();
+            let datasource_idx = q.add_node(
+                NodeSearchSpec::ExactValue {
+                    ns: Some(ANNIS_NS.to_string()),
+                    name: NODE_TYPE.to_string(),
+                    val: Some("datasource".to_string()),
+                    is_meta: false,
+                },
+                None,
+            );
+            let m_idx = q.add_node(m.clone(), None);
+            q.add_operator(
+                Box::new(operators::PartOfSubCorpusSpec {
+                    dist: RangeSpec::Bound {
+                        min_dist: 1,
+                        max_dist: 1,
                     },
-                    None,
-                );
-                let m_idx = q.add_node(m.clone(), None);
-                q.add_operator(
-                    Box::new(operators::PartOfSubCorpusSpec {
-                        dist: RangeSpec::Bound {
-                            min_dist: 1,
-                            max_dist: 1,
-                        },
-                    }),
-                    &m_idx,
-                    &datasource_idx,
-                    false,
-                )?;
-                query.alternatives.push(q);
-            }
+                    inverse: false,
+                }),
+                &m_idx,
+                &datasource_idx,
+                false,
+            )?;
+            query.alternatives.push(q);
         }
         extract_subgraph_by_query(&db_entry, &query, &[0], &self.query_config, None)
     }
@@ -2049,7 +5754,22 @@ impl CorpusStorage {
         query_language: QueryLanguage,
         component_type_filter: Option<AnnotationComponentType>,
     ) -> Result<AnnotationGraph> {
-        let prep = self.prepare_query(corpus_name, query, query_language, |g| {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let graphs = shards
+                .iter()
+                .map(|shard| {
+                    self.subgraph_for_query(
+                        shard,
+                        query,
+                        query_language,
+                        component_type_filter.clone(),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            return sharding::merge_graphs(graphs);
+        }
+        let prep = self.prepare_query(corpus_name, query, query_language, &[], |g| {
             g.get_all_components(component_type_filter.clone(), None)
         })?;
 
@@ -2078,78 +5798,29 @@ impl CorpusStorage {
         corpus_name: &str,
         corpus_ids: Vec<String>,
     ) -> Result<AnnotationGraph> {
-        let db_entry = self.get_fully_loaded_entry(corpus_name)?;
-
-        let mut query = Disjunction {
-            alternatives: vec![],
-        };
-        // find all nodes that a connected with the corpus IDs
-        for source_corpus_id in corpus_ids {
-            // remove the obsolete "salt:/" prefix
-            let source_corpus_id: &str = source_corpus_id
-                .strip_prefix("salt:/")
-                .unwrap_or(&source_corpus_id);
-            // All annotation nodes
-            {
-                let mut q = Conjunction::new();
-                let corpus_idx = q.add_node(
-                    NodeSearchSpec::ExactValue {
-                        ns: Some(ANNIS_NS.to_string()),
-                        name: NODE_NAME.to_string(),
-                        val: Some(source_corpus_id.to_string()),
-                        is_meta: false,
-                    },
-                    None,
-                );
-                let any_node_idx = q.add_node(NodeSearchSpec::AnyNode, None);
-                q.add_operator(
-                    Box::new(operators::PartOfSubCorpusSpec {
-                        dist: RangeSpec::Unbound,
-                    }),
-                    &any_node_idx,
-                    &corpus_idx,
-                    true,
-                )?;
-                query.alternatives.push(q);
-            }
-            // All data source nodes
-            {
-                let mut q = Conjunction::new();
-                let corpus_idx = q.add_node(
-                    NodeSearchSpec::ExactValue {
-                        ns: Some(ANNIS_NS.to_string()),
-                        name: NODE_NAME.to_string(),
-                        val: Some(source_corpus_id.to_string()),
-                        is_meta: false,
-                    },
-                    None,
-                );
-                let any_node_idx = q.add_node(
-                    NodeSearchSpec::ExactValue {
-                        ns: Some(ANNIS_NS.to_string()),
-                        name: NODE_TYPE.to_string(),
-                        val: Some("datasource".to_string()),
-                        is_meta: false,
-                    },
-                    None,
-                );
-                q.add_operator(
-                    Box::new(operators::PartOfSubCorpusSpec {
-                        dist: RangeSpec::Unbound,
-                    }),
-                    &any_node_idx,
-                    &corpus_idx,
-                    true,
-                )?;
-                query.alternatives.push(q);
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let mut graphs = Vec::new();
+            for (shard, shard_corpus_ids) in self.route_to_shards(&shards, corpus_ids)? {
+                graphs.push(self.subcorpus_graph(&shard, shard_corpus_ids)?);
             }
+            return sharding::merge_graphs(graphs);
         }
-
+        let db_entry = self.get_fully_loaded_entry(corpus_name)?;
+        let query = subcorpus_query(corpus_ids)?;
         extract_subgraph_by_query(&db_entry, &query, &[1], &self.query_config, None)
     }
 
     /// Return the copy of the graph of the corpus structure given by `corpus_name`.
     pub fn corpus_graph(&self, corpus_name: &str) -> Result<AnnotationGraph> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let graphs = shards
+                .iter()
+                .map(|shard| self.corpus_graph(shard))
+                .collect::<Result<Vec<_>>>()?;
+            return sharding::merge_graphs(graphs);
+        }
         let db_entry = self.get_loaded_entry(corpus_name, false)?;
 
         let subcorpus_components = {
@@ -2191,9 +5862,14 @@ impl CorpusStorage {
 
         let mut tuple_frequency: FxHashMap<Vec<String>, usize> = FxHashMap::default();
 
-        for cn in query.corpus_names {
-            let prep =
-                self.prepare_query(cn.as_ref(), query.query, query.query_language, |_| vec![])?;
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |_| vec![],
+            )?;
 
             // acquire read-only lock and execute query
             let lock = prep.db_entry.read().unwrap();
@@ -2219,7 +5895,11 @@ impl CorpusStorage {
                 }
             }
 
-            let plan = ExecutionPlan::from_disjunction(&prep.query, &db, &self.query_config)?;
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                &db,
+                &self.query_config_with_timeout(timeout),
+            )?;
 
             for mgroup in plan {
                 // for each match, extract the defined annotation (by its key) from the result node
@@ -2244,6 +5924,7 @@ impl CorpusStorage {
                     timeout.check()?;
                 }
             }
+            timeout.check()?;
         }
 
         // output the frequency
@@ -2261,8 +5942,285 @@ impl CorpusStorage {
         Ok(result)
     }
 
+    /// Count how often each value of an annotation occurs in each document, for all matches of a query.
+    ///
+    /// - `query` - The search query definition.
+    /// - `definition` - Defines the query node and the annotation whose values are counted.
+    ///
+    /// The result is a sparse document-term matrix in triplet format, sorted by the document and the value.
+    /// Matches where the query node does not have the annotation are not counted.
+    pub fn document_term_matrix<S: AsRef<str>>(
+        &self,
+        query: SearchQuery<S>,
+        definition: &FrequencyDefEntry,
+    ) -> Result<Vec<DocumentTermCount>> {
+        let mut result = Vec::new();
+        self.for_each_document_term_count(query, definition, |c| {
+            result.push(c);
+            Ok(())
+        })?;
+        Ok(result)
+    }
+
+    /// Write the document-term matrix of [`document_term_matrix`](#method.document_term_matrix) as CSV with the
+    /// columns `document`, `value` and `count` to the given `output`.
+    ///
+    /// The rows of each corpus are written as soon as the query has been executed on it.
+    /// Returns the number of written rows.
+    pub fn export_document_term_matrix<S: AsRef<str>, W: Write>(
+        &self,
+        query: SearchQuery<S>,
+        definition: &FrequencyDefEntry,
+        output: W,
+    ) -> Result<usize> {
+        let mut writer = csv::Writer::from_writer(output);
+        writer.write_record(["document", "value", "count"])?;
+        let mut rows = 0;
+        self.for_each_document_term_count(query, definition, |c| {
+            writer.write_record(&[c.document, c.value, c.count.to_string()])?;
+            rows += 1;
+            Ok(())
+        })?;
+        writer.flush()?;
+        Ok(rows)
+    }
+
+    fn for_each_document_term_count<S, F>(
+        &self,
+        query: SearchQuery<S>,
+        definition: &FrequencyDefEntry,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        S: AsRef<str>,
+        F: FnMut(DocumentTermCount) -> Result<()>,
+    {
+        let timeout = TimeoutCheck::new(query.timeout);
+
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |_| vec![],
+            )?;
+
+            let node_ref = prep
+                .query
+                .get_variable_pos(&definition.node_ref)
+                .ok_or_else(|| CorpusStorageError::UnknownQueryNode(definition.node_ref.clone()))?;
+
+            // acquire read-only lock and execute query
+            let lock = prep.db_entry.read().unwrap();
+            let db: &AnnotationGraph = get_read_or_error(&lock)?;
+
+            let anno_keys = if let Some(ns) = &definition.ns {
+                vec![AnnoKey {
+                    ns: ns.clone().into(),
+                    name: definition.name.clone().into(),
+                }]
+            } else {
+                db.get_node_annos().get_qnames(&definition.name)
+            };
+
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                &db,
+                &self.query_config_with_timeout(timeout),
+            )?;
+
+            // count the values for each document in a single pass over all matches
+            let mut counts: BTreeMap<(SmartString, String), usize> = BTreeMap::new();
+            for (match_nr, mgroup) in plan.enumerate() {
+                if let Some(m) = mgroup.get(node_ref) {
+                    let value = anno_keys
+                        .iter()
+                        .find_map(|k| db.get_node_annos().get_value_for_item(&m.node, k));
+                    let node_name = db
+                        .get_node_annos()
+                        .get_value_for_item(&m.node, &NODE_NAME_KEY);
+                    if let (Some(value), Some(node_name)) = (value, node_name) {
+                        // extract the document path from the node name
+                        let doc_path =
+                            &node_name[0..node_name.rfind('#').unwrap_or_else(|| node_name.len())];
+                        *counts
+                            .entry((doc_path.into(), value.to_string()))
+                            .or_insert(0) += 1;
+                    }
+                }
+                if match_nr % 1_000 == 0 {
+                    timeout.check()?;
+                }
+            }
+            timeout.check()?;
+
+            for ((document, value), count) in counts {
+                callback(DocumentTermCount {
+                    document: document.to_string(),
+                    value,
+                    count,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a plain-text sample of all matches of a query to the given `output`, e.g. as input for annotation tasks.
+    ///
+    /// - `query` - The search query definition.
+    /// - `context` - The number of tokens to include left and right of each match.
+    /// - `whitespace` - How the text between two tokens is reconstructed.
+    /// - `output` - Where to write the sample to.
+    ///
+    /// Each match is written as a line with the tab-separated columns document, left context, matched text and right
+    /// context. The matched text covers all tokens from the left-most to the right-most token of the matched nodes.
+    /// Tabs and line breaks inside the text are replaced by a space. The lines are written while the query is executed
+    /// and are in the order of the execution plan. Returns the number of written lines.
+    pub fn export_match_contexts<S: AsRef<str>, W: Write>(
+        &self,
+        query: SearchQuery<S>,
+        context: usize,
+        whitespace: WhitespaceHandling,
+        mut output: W,
+    ) -> Result<usize> {
+        let timeout = TimeoutCheck::new(query.timeout);
+        let component_order = Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        );
+
+        let mut lines = 0;
+        for cn in self.resolve_shards(query.corpus_names)? {
+            let prep = self.prepare_query(
+                cn.as_ref(),
+                query.query,
+                query.query_language,
+                &query.document_filter,
+                |db| {
+                    let mut components: Vec<_> =
+                        token_helper::necessary_components(db).into_iter().collect();
+                    components.push(component_order.clone());
+                    components
+                },
+            )?;
+
+            // acquire read-only lock and execute query
+            let lock = prep.db_entry.read().unwrap();
+            let db: &AnnotationGraph = get_read_or_error(&lock)?;
+
+            let token_helper = TokenHelper::new(db);
+            let gs_order = db.get_graphstorage_as_ref(&component_order);
+            let (token_helper, gs_order) = match (token_helper, gs_order) {
+                (Some(token_helper), Some(gs_order)) => (token_helper, gs_order),
+                // a corpus without tokens can't have any text to export
+                _ => continue,
+            };
+
+            let plan = ExecutionPlan::from_disjunction(
+                &prep.query,
+                &db,
+                &self.query_config_with_timeout(timeout),
+            )?;
+
+            for (match_nr, mgroup) in plan.enumerate() {
+                if match_nr % 1_000 == 0 {
+                    timeout.check()?;
+                }
+                // find the left-most and right-most token of all matched nodes
+                let mut left: Option<NodeID> = None;
+                let mut right: Option<NodeID> = None;
+                for m in mgroup.iter() {
+                    let (l, r) = token_helper.left_right_token_for(m.node);
+                    if let Some(l) = l {
+                        left = match left {
+                            Some(current) if gs_order.distance(l, current).is_none() => {
+                                Some(current)
+                            }
+                            _ => Some(l),
+                        };
+                    }
+                    if let Some(r) = r {
+                        right = match right {
+                            Some(current) if gs_order.distance(current, r).is_none() => {
+                                Some(current)
+                            }
+                            _ => Some(r),
+                        };
+                    }
+                }
+                let (left, right) = match (left, right) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => continue,
+                };
+                let matched_text = token_helper
+                    .text_between(left, right, gs_order, whitespace)
+                    .unwrap_or_default();
+
+                // walk the context tokens to the left and right of the match
+                let before_match = if context > 0 {
+                    gs_order.get_ingoing_edges(left).next()
+                } else {
+                    None
+                };
+                let mut context_start = before_match;
+                for _ in 1..context {
+                    match context_start.and_then(|t| gs_order.get_ingoing_edges(t).next()) {
+                        Some(t) => context_start = Some(t),
+                        None => break,
+                    }
+                }
+                let after_match = if context > 0 {
+                    gs_order.get_outgoing_edges(right).next()
+                } else {
+                    None
+                };
+                let mut context_end = after_match;
+                for _ in 1..context {
+                    match context_end.and_then(|t| gs_order.get_outgoing_edges(t).next()) {
+                        Some(t) => context_end = Some(t),
+                        None => break,
+                    }
+                }
+                let left_context = match (context_start, before_match) {
+                    (Some(start), Some(end)) => token_helper
+                        .text_between(start, end, gs_order, whitespace)
+                        .unwrap_or_default(),
+                    _ => String::default(),
+                };
+                let right_context = match (after_match, context_end) {
+                    (Some(start), Some(end)) => token_helper
+                        .text_between(start, end, gs_order, whitespace)
+                        .unwrap_or_default(),
+                    _ => String::default(),
+                };
+
+                // extract the document path from the node name
+                let node_name = db
+                    .get_node_annos()
+                    .get_value_for_item(&left, &NODE_NAME_KEY)
+                    .unwrap_or_default();
+                let doc_path =
+                    &node_name[0..node_name.rfind('#').unwrap_or_else(|| node_name.len())];
+
+                let columns: Vec<String> = [doc_path, &left_context, &matched_text, &right_context]
+                    .iter()
+                    .map(|c| c.replace(&['\t', '\n', '\r'][..], " "))
+                    .collect();
+                writeln!(output, "{}", columns.join("\t"))?;
+                lines += 1;
+            }
+            timeout.check()?;
+        }
+        output.flush()?;
+        Ok(lines)
+    }
+
     /// Parses a `query`and return a list of descriptions for its nodes.
     ///
+    /// Derived operators (`~name`) of any corpus are accepted, since they don't change the nodes of the query.
+    ///
     /// - `query` - The query to be analyzed.
     /// - `query_language` - The query language of the query (e.g. AQL).
     pub fn node_descriptions(
@@ -2273,37 +6231,332 @@ impl CorpusStorage {
         let mut result = Vec::new();
         // parse query
         let q: Disjunction = match query_language {
-            QueryLanguage::AQL => aql::parse(query, false)?,
-            QueryLanguage::AQLQuirksV3 => aql::parse(query, true)?,
+            QueryLanguage::AQL => {
+                aql::parse_ignoring_derived_operators(query, false, self.unicode_normalization)?
+            }
+            QueryLanguage::AQLQuirksV3 => {
+                aql::parse_ignoring_derived_operators(query, true, self.unicode_normalization)?
+            }
         };
 
-        for (component_nr, alt) in q.alternatives.iter().enumerate() {
-            for mut n in alt.get_node_descriptions() {
-                n.alternative = component_nr;
-                result.push(n);
+        for (component_nr, alt) in q.alternatives.iter().enumerate() {
+            for mut n in alt.get_node_descriptions() {
+                n.alternative = component_nr;
+                result.push(n);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Parses a `query` in the ANNIS3 compatibility mode ([`QueryLanguage::AQLQuirksV3`]) and returns which rules of
+    /// this mode are applied to it.
+    ///
+    /// This helps to migrate queries from ANNIS3 to the normal AQL, since each of the rules changes the semantics of
+    /// the query compared to [`QueryLanguage::AQL`].
+    pub fn quirks_report(&self, query: &str) -> Result<Vec<QuirksRule>> {
+        aql::quirks_report(query, self.unicode_normalization)
+    }
+
+    /// Returns a list of all components of a corpus given by `corpus_name`.
+    ///
+    /// - `ctype` - Optionally filter by the component type.
+    /// - `name` - Optionally filter by the component name.
+    pub fn list_components(
+        &self,
+        corpus_name: &str,
+        ctype: Option<AnnotationComponentType>,
+        name: Option<&str>,
+    ) -> Vec<Component<AnnotationComponentType>> {
+        let shards = self.shards_of_corpus(corpus_name).unwrap_or_default();
+        if !shards.is_empty() {
+            let mut result: Vec<_> = shards
+                .iter()
+                .flat_map(|shard| self.list_components(shard, ctype.clone(), name))
+                .collect();
+            result.sort();
+            result.dedup();
+            return result;
+        }
+        if let Ok(db_entry) = self.get_loaded_entry(corpus_name, false) {
+            let lock = db_entry.read().unwrap();
+            if let Ok(db) = get_read_or_error(&lock) {
+                return db.get_all_components(ctype, name);
+            }
+        }
+        return vec![];
+    }
+
+    /// Returns a machine-readable schema of the corpus given by `corpus_name`.
+    ///
+    /// The schema contains all node and edge annotation keys with their value counts and example values,
+    /// all components with their implementation and statistics and the available segmentations.
+    /// All components of the corpus are loaded to create the schema.
+    pub fn schema(&self, corpus_name: &str) -> Result<CorpusSchema> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let shard_schemas = shards
+                .iter()
+                .map(|shard| self.schema(shard))
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(sharding::combine_schema(corpus_name, shard_schemas));
+        }
+        let db_entry = self.get_fully_loaded_entry(corpus_name)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+
+        let mut components = Vec::new();
+        let mut segmentations = Vec::new();
+        for c in db.get_all_components(None, None) {
+            if let Some(gs) = db.get_graphstorage(&c) {
+                components.push(ComponentSchema {
+                    component_type: c.get_type(),
+                    layer: c.layer.to_string(),
+                    name: c.name.to_string(),
+                    implementation: gs.serialization_id(),
+                    statistics: gs.get_statistics().cloned(),
+                    edge_annotations: create_annotation_schema(gs.get_anno_storage()),
+                });
+            }
+            if c.get_type() == AnnotationComponentType::Ordering && !c.name.is_empty() {
+                segmentations.push(c.name.to_string());
+            }
+        }
+        segmentations.sort();
+        segmentations.dedup();
+
+        Ok(CorpusSchema {
+            corpus_name: corpus_name.to_string(),
+            node_annotations: create_annotation_schema(db.get_node_annos()),
+            components,
+            segmentations,
+        })
+    }
+
+    /// Infer the segmentations of the corpus given by `corpus_name` and write them into its corpus configuration.
+    ///
+    /// Each `Ordering` component besides the token ordering is a candidate. It is regarded as segmentation if all
+    /// of its nodes are tokens or spans covering tokens and have a common annotation which holds the text of the
+    /// segment. An annotation with the same name as the component is preferred.
+    /// If the tokens of the corpus have no text (e.g. because they only form a timeline for the segmentations) and
+    /// no default segmentation for the context or the base text is configured, the first inferred segmentation is
+    /// configured as default.
+    ///
+    /// Returns the inferred segmentations, sorted by their name.
+    pub fn infer_segmentations(&self, corpus_name: &str) -> Result<Vec<SegmentationDefinition>> {
+        let components = {
+            let db_entry = self.get_loaded_entry(corpus_name, false)?;
+            let lock = db_entry.read().unwrap();
+            let db = get_read_or_error(&lock)?;
+            let mut components =
+                db.get_all_components(Some(AnnotationComponentType::Ordering), None);
+            components.extend(db.get_all_components(Some(AnnotationComponentType::Coverage), None));
+            components
+        };
+        let db_entry = self.get_loaded_entry_with_components(corpus_name, components)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        let node_annos = db.get_node_annos();
+
+        let coverage_gs: Vec<_> = db
+            .get_all_components(Some(AnnotationComponentType::Coverage), None)
+            .into_iter()
+            .filter_map(|c| db.get_graphstorage(&c))
+            .collect();
+
+        let mut segmentations = Vec::new();
+        for c in db.get_all_components(Some(AnnotationComponentType::Ordering), None) {
+            if c.name.is_empty() {
+                continue;
+            }
+            let gs = if let Some(gs) = db.get_graphstorage(&c) {
+                gs
+            } else {
+                continue;
+            };
+            let mut nodes = BTreeSet::new();
+            for source in gs.source_nodes() {
+                nodes.insert(source);
+                nodes.extend(gs.get_outgoing_edges(source));
+            }
+            if nodes.is_empty() {
+                continue;
+            }
+
+            // all segments must be tokens or cover tokens
+            let is_ordered_span = |n: &NodeID| {
+                node_annos.get_value_for_item(n, &TOKEN_KEY).is_some()
+                    || coverage_gs.iter().any(|gs| gs.has_outgoing_edges(*n))
+            };
+            if !nodes.iter().all(is_ordered_span) {
+                continue;
+            }
+
+            // find the annotation keys that are present on all segments
+            let mut key_count: BTreeMap<AnnoKey, usize> = BTreeMap::new();
+            for n in &nodes {
+                for key in node_annos.get_all_keys_for_item(n, None, None) {
+                    if key.ns != ANNIS_NS {
+                        *key_count.entry(key.as_ref().clone()).or_default() += 1;
+                    }
+                }
+            }
+            let common_keys: Vec<AnnoKey> = key_count
+                .into_iter()
+                .filter(|(_, count)| *count == nodes.len())
+                .map(|(key, _)| key)
+                .collect();
+            let segment_key = if let Some(key) = common_keys.iter().find(|k| k.name == c.name) {
+                Some(key.clone())
+            } else if common_keys.len() == 1 {
+                Some(common_keys[0].clone())
+            } else {
+                None
+            };
+            if let Some(key) = segment_key {
+                segmentations.push(SegmentationDefinition {
+                    name: c.name.to_string(),
+                    anno_ns: key.ns.to_string(),
+                    anno_name: key.name.to_string(),
+                });
+            }
+        }
+        segmentations.sort_by(|a, b| a.name.cmp(&b.name));
+        segmentations.dedup_by(|a, b| a.name == b.name);
+
+        let tokens_have_text = node_annos
+            .get_all_values(&TOKEN_KEY, false)
+            .iter()
+            .any(|v| !v.trim().is_empty());
+
+        let mut config = self
+            .get_corpus_config(corpus_name)
+            .map_err(|e| CorpusStorageError::LoadingCorpusConfig {
+                corpus: corpus_name.to_string(),
+                source: Box::new(e),
+            })?
+            .unwrap_or_default();
+        if let Some(first) = segmentations.first() {
+            if !tokens_have_text {
+                if config.context.segmentation.is_none() {
+                    config.context.segmentation = Some(first.name.clone());
+                }
+                if config.view.base_text_segmentation.is_none() {
+                    config.view.base_text_segmentation = Some(first.name.clone());
+                }
+            }
+        }
+        config.segmentations = segmentations.clone();
+        self.write_corpus_config(corpus_name, &config)?;
+
+        Ok(segmentations)
+    }
+
+    /// Returns the number of tokens, segments and annotations for each document of the corpus, sorted by the
+    /// document path.
+    ///
+    /// The statistics are calculated when the corpus is imported and persisted, so they are available without
+    /// loading the corpus. If the corpus has been changed by an update since, they are calculated again and the
+    /// corpus is loaded completely.
+    pub fn document_statistics(&self, corpus_name: &str) -> Result<Vec<DocumentStatistics>> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            let mut documents = Vec::new();
+            for shard in shards {
+                documents.extend(self.document_statistics(&shard)?);
+            }
+            documents.sort_by(|a, b| a.document.cmp(&b.document));
+            return Ok(documents);
+        }
+        let corpus_dir = self.corpus_directory(corpus_name);
+        if let Some(documents) = docstats::read(&corpus_dir, self.encryption_key.as_ref()) {
+            return Ok(documents);
+        }
+
+        let db_entry = self.get_fully_loaded_entry(corpus_name)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        let documents = docstats::calculate(db);
+        docstats::write(&corpus_dir, &documents, self.encryption_key.as_ref())?;
+        Ok(documents)
+    }
+
+    /// Returns the sum of the [document statistics](#method.document_statistics) of the given documents, e.g. to
+    /// normalize frequencies by the number of tokens of a sub-corpus.
+    ///
+    /// - `corpus_name` - The name of the corpus.
+    /// - `documents` - Paths of the documents (including the corpus name) to include. If empty, all documents are
+    ///   included.
+    pub fn aggregated_document_statistics<S: AsRef<str>>(
+        &self,
+        corpus_name: &str,
+        documents: &[S],
+    ) -> Result<DocumentCounts> {
+        let included: HashSet<&str> = documents.iter().map(|d| d.as_ref()).collect();
+        let mut result = DocumentCounts::default();
+        for doc in self.document_statistics(corpus_name)? {
+            if included.is_empty() || included.contains(doc.document.as_str()) {
+                result.add(&doc.counts);
             }
         }
-
         Ok(result)
     }
 
-    /// Returns a list of all components of a corpus given by `corpus_name`.
+    /// Returns the metadata annotations of the top-level corpus node of the corpus given by `corpus_name`.
     ///
-    /// - `ctype` - Optionally filter by the component type.
-    /// - `name` - Optionally filter by the component name.
-    pub fn list_components(
+    /// Annotations of the `annis` namespace (like the node name) are not included.
+    pub fn list_corpus_metadata(&self, corpus_name: &str) -> Result<Vec<Annotation>> {
+        self.list_metadata(corpus_name, corpus_name)
+    }
+
+    /// Returns the metadata annotations of a document or sub-corpus of the corpus given by `corpus_name`,
+    /// without constructing the [sub-corpus graph](#method.subcorpus_graph).
+    ///
+    /// - `document` - The path of the document (including the corpus name), e.g. `GUM/GUM_whow_skittles`.
+    ///
+    /// Annotations of the `annis` namespace (like the node name) are not included.
+    pub fn list_document_metadata(
         &self,
         corpus_name: &str,
-        ctype: Option<AnnotationComponentType>,
-        name: Option<&str>,
-    ) -> Vec<Component<AnnotationComponentType>> {
-        if let Ok(db_entry) = self.get_loaded_entry(corpus_name, false) {
-            let lock = db_entry.read().unwrap();
-            if let Ok(db) = get_read_or_error(&lock) {
-                return db.get_all_components(ctype, name);
-            }
+        document: &str,
+    ) -> Result<Vec<Annotation>> {
+        self.list_metadata(corpus_name, document)
+    }
+
+    fn list_metadata(&self, corpus_name: &str, node_name: &str) -> Result<Vec<Annotation>> {
+        let shards = self.shards_of_corpus(corpus_name)?;
+        if !shards.is_empty() {
+            // Corpora that are part of several shards have the same metadata in each shard
+            let shard = self
+                .route_to_shards(&shards, vec![node_name.to_string()])?
+                .into_iter()
+                .next()
+                .map(|(shard, _)| shard)
+                .ok_or_else(|| GraphAnnisError::NoSuchNodeID(node_name.to_string()))?;
+            return self.list_metadata(&shard, node_name);
         }
-        return vec![];
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        let node_annos = db.get_node_annos();
+
+        let node_id = db
+            .get_node_id_from_name(node_name)
+            .filter(|node_id| {
+                node_annos
+                    .get_value_for_item(node_id, &NODE_TYPE_KEY)
+                    .map(|node_type| node_type == "corpus")
+                    .unwrap_or(false)
+            })
+            .ok_or_else(|| GraphAnnisError::NoSuchNodeID(node_name.to_string()))?;
+
+        let mut result: Vec<Annotation> = node_annos
+            .get_annotations_for_item(&node_id)
+            .into_iter()
+            .filter(|a| a.key.ns != ANNIS_NS)
+            .collect();
+        result.sort();
+        Ok(result)
     }
 
     /// Returns a list of all node annotations of a corpus given by `corpus_name`.
@@ -2316,6 +6569,18 @@ impl CorpusStorage {
         list_values: bool,
         only_most_frequent_values: bool,
     ) -> Vec<Annotation> {
+        let shards = self.shards_of_corpus(corpus_name).unwrap_or_default();
+        if !shards.is_empty() {
+            return sharding::combine_annotations(
+                shards
+                    .iter()
+                    .map(|shard| {
+                        self.list_node_annotations(shard, list_values, only_most_frequent_values)
+                    })
+                    .collect(),
+                list_values && only_most_frequent_values,
+            );
+        }
         let mut result: Vec<Annotation> = Vec::new();
         if let Ok(db_entry) = self.get_loaded_entry(corpus_name, false) {
             let lock = db_entry.read().unwrap();
@@ -2355,6 +6620,123 @@ impl CorpusStorage {
         result
     }
 
+    /// Search the distinct values of a node annotation that match a regular expression, e.g. to let the user pick
+    /// a value for an annotation with a large number of distinct values.
+    ///
+    /// - `corpus_name` - The name of the corpus to search the values in.
+    /// - `ns` - If given, only annotations having this namespace are considered.
+    /// - `name` - The name of the annotation.
+    /// - `pattern` - The regular expression (in AQL syntax) the complete value must match.
+    /// - `limit` - If given, return at most this number of values.
+    ///
+    /// Returns the matching values and how often they occur, sorted by their frequency. In contrast to a query,
+    /// the nodes having these values are never retrieved.
+    pub fn search_node_annotation_values(
+        &self,
+        corpus_name: &str,
+        ns: Option<&str>,
+        name: &str,
+        pattern: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<AnnotationValueCount>> {
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        let node_annos: &dyn AnnotationStorage<NodeID> = db.get_node_annos();
+
+        let keys = if let Some(ns) = ns {
+            vec![AnnoKey {
+                ns: ns.into(),
+                name: name.into(),
+            }]
+        } else {
+            node_annos.get_qnames(name)
+        };
+        let mut result = Vec::new();
+        for key in keys {
+            for (value, count) in node_annos.get_values_matching_regex(&key, pattern)? {
+                result.push(AnnotationValueCount {
+                    key: key.clone(),
+                    value: value.to_string(),
+                    count,
+                });
+            }
+        }
+        result.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.key.cmp(&b.key))
+                .then_with(|| a.value.cmp(&b.value))
+        });
+        if let Some(limit) = limit {
+            result.truncate(limit);
+        }
+        Ok(result)
+    }
+
+    /// Suggest completions for a partial annotation name or value, e.g. for the auto-completion of a query editor.
+    ///
+    /// - `corpus_name` - The name of the corpus to get the suggestions from.
+    /// - `prefix` - The partial annotation name (with or without namespace) or value.
+    /// - `limit` - Maximum number of suggested annotation names and maximum number of suggested values.
+    ///
+    /// Returns the matching node annotation names first and then the matching values, both sorted by how often they
+    /// occur in the corpus. The unique node names are never suggested as values and values are only suggested
+    /// for a non-empty `prefix`.
+    pub fn suggest(
+        &self,
+        corpus_name: &str,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<Suggestion>> {
+        let db_entry = self.get_loaded_entry(corpus_name, false)?;
+        let lock = db_entry.read().unwrap();
+        let db = get_read_or_error(&lock)?;
+        let node_annos: &dyn AnnotationStorage<NodeID> = db.get_node_annos();
+
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+        for key in node_annos.annotation_keys() {
+            let qname = format!("{}:{}", key.ns, key.name);
+            if key.name.starts_with(prefix) || qname.starts_with(prefix) {
+                names.push(Suggestion {
+                    count: node_annos.number_of_annotations_by_name(Some(&key.ns), &key.name),
+                    key: key.clone(),
+                    value: None,
+                });
+            }
+            if prefix.is_empty() || &key == NODE_NAME_KEY.as_ref() {
+                continue;
+            }
+            // Only scan the values starting with the prefix, the scan already counts the annotations of each value
+            let mut candidates = node_annos
+                .get_values_matching_regex(&key, &format!("{}.*", regex::escape(prefix)))?;
+            candidates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            candidates.truncate(limit);
+            for (val, count) in candidates {
+                values.push(Suggestion {
+                    key: key.clone(),
+                    value: Some(val.to_string()),
+                    count,
+                });
+            }
+        }
+
+        let most_frequent_first = |a: &Suggestion, b: &Suggestion| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.key.cmp(&b.key))
+                .then_with(|| a.value.cmp(&b.value))
+        };
+        names.sort_by(most_frequent_first);
+        names.truncate(limit);
+        values.sort_by(most_frequent_first);
+        values.truncate(limit);
+
+        names.extend(values);
+        Ok(names)
+    }
+
     /// Returns a list of all edge annotations of a corpus given by `corpus_name` and the `component`.
     ///
     /// - `list_values` - If true include the possible values in the result.
@@ -2366,6 +6748,23 @@ impl CorpusStorage {
         list_values: bool,
         only_most_frequent_values: bool,
     ) -> Vec<Annotation> {
+        let shards = self.shards_of_corpus(corpus_name).unwrap_or_default();
+        if !shards.is_empty() {
+            return sharding::combine_annotations(
+                shards
+                    .iter()
+                    .map(|shard| {
+                        self.list_edge_annotations(
+                            shard,
+                            component,
+                            list_values,
+                            only_most_frequent_values,
+                        )
+                    })
+                    .collect(),
+                list_values && only_most_frequent_values,
+            );
+        }
         let mut result: Vec<Annotation> = Vec::new();
         if let Ok(db_entry) =
             self.get_loaded_entry_with_components(corpus_name, vec![component.clone()])
@@ -2415,15 +6814,43 @@ impl CorpusStorage {
         check_cache_size_and_remove_with_cache(
             cache,
             &self.cache_strategy,
+            &self.cache_usage,
+            &self.pinned_corpora,
             keep,
             report_cache_status,
         );
     }
 }
 
-impl Drop for CorpusStorage {
-    fn drop(&mut self) {
-        // wait until all background workers are finished
+#[cfg(feature = "corpus-storage")]
+impl CorpusStorage {
+    /// Run the given job in the background, either in the configured thread pool or in a new thread.
+    fn spawn_background_worker<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let active_background_workers = self.active_background_workers.clone();
+        {
+            let &(ref lock, ref _cvar) = &*active_background_workers;
+            let mut nr_active_background_workers = lock.lock().unwrap();
+            *nr_active_background_workers += 1;
+        }
+        let worker = move || {
+            job();
+            let &(ref lock, ref cvar) = &*active_background_workers;
+            let mut nr_active_background_workers = lock.lock().unwrap();
+            *nr_active_background_workers -= 1;
+            cvar.notify_all();
+        };
+        if let Some(pool) = &self.query_config.thread_pool {
+            pool.spawn(worker);
+        } else {
+            thread::spawn(worker);
+        }
+    }
+
+    /// Wait until all background workers are finished.
+    fn wait_for_background_workers(&self) {
         let &(ref lock, ref cvar) = &*self.active_background_workers;
         let mut nr_active_background_workers = lock.lock().unwrap();
         while *nr_active_background_workers > 0 {
@@ -2433,16 +6860,54 @@ impl Drop for CorpusStorage {
             );
             nr_active_background_workers = cvar.wait(nr_active_background_workers).unwrap();
         }
+    }
+}
+
+#[cfg(feature = "corpus-storage")]
+impl Drop for CorpusStorage {
+    fn drop(&mut self) {
+        self.shutdown();
+
+        self.lock.release();
+    }
+}
+
+/// Re-calculate the node annotation and component statistics of a loaded corpus.
+///
+/// The write lock is only held for a single annotation storage or component at a time, so queries can be
+/// executed in between.
+/// Returns `false` if the job was aborted or the corpus is not loaded anymore.
+#[cfg(feature = "corpus-storage")]
+fn refresh_statistics(db_entry: &RwLock<CacheEntry>, abort: &AtomicBool) -> bool {
+    let components = {
+        let mut lock = db_entry.write().unwrap();
+        if let CacheEntry::Loaded(ref mut db) = &mut *lock {
+            db.get_node_annos_mut().calculate_statistics();
+            db.get_all_components(None, None)
+        } else {
+            return false;
+        }
+    };
 
-        // unlock lock file
-        if let Err(e) = self.lock_file.unlock() {
-            warn!("Could not unlock CorpusStorage lock file: {:?}", e);
+    for c in components {
+        if abort.load(Ordering::SeqCst) {
+            return false;
+        }
+        let mut lock = db_entry.write().unwrap();
+        if let CacheEntry::Loaded(ref mut db) = &mut *lock {
+            if db.is_loaded(&c) {
+                if let Err(e) = db.calculate_component_statistics(&c) {
+                    debug!("Skipping statistics of component {}: {:?}", c, e);
+                }
+            }
         } else {
-            trace!("Unlocked CorpusStorage lock file");
+            return false;
         }
     }
+    true
 }
 
+#[cfg(feature = "corpus-storage")]
 fn get_read_or_error<'a>(lock: &'a RwLockReadGuard<CacheEntry>) -> Result<&'a AnnotationGraph> {
     if let CacheEntry::Loaded(ref db) = &**lock {
         Ok(db)
@@ -2453,6 +6918,7 @@ fn get_read_or_error<'a>(lock: &'a RwLockReadGuard<CacheEntry>) -> Result<&'a An
     }
 }
 
+#[cfg(feature = "corpus-storage")]
 fn get_write_or_error<'a>(
     lock: &'a mut RwLockWriteGuard<CacheEntry>,
 ) -> Result<&'a mut AnnotationGraph> {
@@ -2463,6 +6929,7 @@ fn get_write_or_error<'a>(
     }
 }
 
+#[cfg(feature = "corpus-storage")]
 fn get_cache_sizes(
     cache: &LinkedHashMap<String, Arc<RwLock<CacheEntry>>>,
 ) -> LinkedHashMap<String, usize> {
@@ -2479,6 +6946,7 @@ fn get_cache_sizes(
     db_sizes
 }
 
+#[cfg(feature = "corpus-storage")]
 fn get_max_cache_size(cache_strategy: &CacheStrategy, used_cache_size: usize) -> usize {
     match cache_strategy {
         CacheStrategy::FixedMaxMemory(max_size) => *max_size * 1_000_000,
@@ -2499,13 +6967,443 @@ fn get_max_cache_size(cache_strategy: &CacheStrategy, used_cache_size: usize) ->
     }
 }
 
+/// Stable checksum of the derived operator definitions, which is persisted with the optimized plans.
+fn derived_operators_checksum(derived_operators: &[DerivedOperatorDefinition]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    for d in derived_operators {
+        hasher.update(d.name.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(d.definition.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finalize()
+}
+
+/// Read a file of the `corpus_dir` (or of the data directory), which is decrypted if an encryption `key` is given.
+///
+/// Returns `None` if the file does not exist.
+fn read_corpus_file(
+    corpus_dir: &Path,
+    path: &Path,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Result<Option<String>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = encryption::with_key(key, corpus_dir, || encryption::read(path))?;
+    Ok(Some(String::from_utf8(content).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    })?))
+}
+
+/// Write the `content` to a temporary file in the same directory and move it to the given `path`
+/// afterwards, so concurrent readers never see a partially written file.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut tmp_file = tempfile::NamedTempFile::new_in(dir)?;
+    tmp_file.write_all(content)?;
+    tmp_file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Write a file of the `corpus_dir` (or of the data directory), which is encrypted if an encryption `key` is given.
+///
+/// The content is written atomically, see [write_atomically].
+fn write_corpus_file(
+    corpus_dir: &Path,
+    path: &Path,
+    content: &str,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Result<()> {
+    let data = encryption::with_key(key, corpus_dir, || {
+        encryption::encode(path, content.as_bytes())
+    })?;
+    write_atomically(path, &data)
+}
+
+fn read_corpus_summary(
+    corpus_dir: &Path,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Option<CorpusSummary> {
+    let path = corpus_dir.join(CORPUS_SUMMARY_FILE);
+    let file_content = match read_corpus_file(corpus_dir, &path, key) {
+        Ok(file_content) => file_content?,
+        Err(e) => {
+            warn!(
+                "Ignoring unreadable corpus summary {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            return None;
+        }
+    };
+    match toml::from_str(&file_content) {
+        Ok(summary) => Some(summary),
+        Err(e) => {
+            warn!(
+                "Ignoring invalid corpus summary {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// True if the path is relative and does not contain a `..` component, so it can not point outside of the
+/// directory it is joined to.
+fn is_relative_path_without_parent(path: &Path) -> bool {
+    !path.is_absolute()
+        && path.components().all(|c| {
+            !matches!(
+                c,
+                std::path::Component::ParentDir
+                    | std::path::Component::RootDir
+                    | std::path::Component::Prefix(_)
+            )
+        })
+}
+
+/// Set the graph storage implementations of the `graph_storage` section of the corpus configuration
+/// as override for all matching components. Overrides of components that are not configured any longer are removed.
+/// If `optimize` is `true`, the matching components are converted to the configured implementation immediately.
+fn apply_graph_storage_config(
+    db: &mut AnnotationGraph,
+    config: &CorpusConfiguration,
+    optimize: bool,
+) -> Result<()> {
+    db.clear_impl_overrides();
+    for (component, impl_name) in &config.graph_storage {
+        let components = nodesearch::matching_components(db, component).map_err(|reason| {
+            CorpusStorageError::InvalidGraphStorageConfig {
+                component: component.clone(),
+                reason,
+            }
+        })?;
+        for c in components {
+            if optimize {
+                // load the component, so the implementation can be checked against its statistics
+                db.ensure_loaded(&c)?;
+            }
+            db.set_impl_override(c.clone(), Some(impl_name.clone()))?;
+            if optimize {
+                db.optimize_gs_impl(&c)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Get the index of the left-most and right-most token covered by the node, relative to the first token of the text.
+/// Split a single node of a match ID into the matched annotation key and the node name.
+///
+/// The node has the form `ns::name::node_name`, `name::node_name` or `node_name` if no annotation was matched.
+fn parse_match_node_id(node: &str) -> (Option<AnnoKey>, &str) {
+    let node = node.strip_prefix("salt:/").unwrap_or(node);
+    let elements: Vec<&str> = node.splitn(3, "::").collect();
+    let decode = |v: &str| -> SmartString { percent_decode_str(v).decode_utf8_lossy().into() };
+    match elements.as_slice() {
+        [ns, name, node_name] => (
+            Some(AnnoKey {
+                ns: decode(ns),
+                name: decode(name),
+            }),
+            node_name,
+        ),
+        [name, node_name] => (
+            Some(AnnoKey {
+                ns: SmartString::default(),
+                name: decode(name),
+            }),
+            node_name,
+        ),
+        _ => (None, node),
+    }
+}
+
+/// Sort the corpus names in the order their results are returned by a `find` query.
+fn sort_corpus_names(corpus_names: &mut Vec<SmartString>, order: ResultOrder) {
+    if order == ResultOrder::Randomized {
+        // This is still oddly ordered, because results from one corpus will always be grouped together.
+        // But it still better than just output the same corpus first.
+        let mut rng = rand::thread_rng();
+        corpus_names.shuffle(&mut rng);
+    } else if let ResultOrder::Seeded(seed) = order {
+        corpus_names.sort();
+        let mut rng = StdRng::seed_from_u64(seed);
+        corpus_names.shuffle(&mut rng);
+    } else if order == ResultOrder::Inverted {
+        corpus_names.sort();
+        corpus_names.reverse();
+    } else {
+        corpus_names.sort();
+    }
+}
+
+/// Components that need to be loaded to execute a `find` query, in addition to the ones needed by the query itself.
+fn find_additional_components(
+    db: &AnnotationGraph,
+    query_language: QueryLanguage,
+    order: ResultOrder,
+    with_offsets: bool,
+) -> Vec<Component<AnnotationComponentType>> {
+    let mut additional_components = vec![Component::new(
+        AnnotationComponentType::Ordering,
+        ANNIS_NS.into(),
+        "".into(),
+    )];
+    if with_offsets || (order != ResultOrder::NotSorted && order != ResultOrder::Randomized) {
+        for c in token_helper::necessary_components(db) {
+            additional_components.push(c);
+        }
+    }
+    if order == ResultOrder::Normal && matches!(query_language, QueryLanguage::AQL) {
+        // needed to execute document-local queries document by document
+        additional_components
+            .extend(db.get_all_components(Some(AnnotationComponentType::PartOf), None));
+    }
+    additional_components
+}
+
+/// Create the match ID of a match group, which only includes the query nodes that are part of the output.
+pub(crate) fn create_match_id(
+    db: &AnnotationGraph,
+    query: &Disjunction,
+    m: &[Match],
+    quirks_mode: bool,
+) -> String {
+    let mut match_desc = String::new();
+    for (i, singlematch) in m.iter().enumerate() {
+        let include_in_output = if let Some(var) = query.get_variable_by_pos(i) {
+            query.is_included_in_output(&var)
+        } else {
+            true
+        };
+        if include_in_output {
+            if !match_desc.is_empty() {
+                match_desc.push(' ');
+            }
+            append_match_node_id(&mut match_desc, db, singlematch, quirks_mode);
+        }
+    }
+    match_desc
+}
+
+/// Add the warnings of a query on a single corpus, but omit duplicates.
+fn add_query_warnings(warnings: &mut Vec<QueryWarning>, new_warnings: &[QueryWarning]) {
+    for w in new_warnings {
+        if !warnings.contains(w) {
+            warnings.push(w.clone());
+        }
+    }
+}
+
+/// Append the ID of a single matched node (including the matched annotation key, if any) to a match description.
+pub(crate) fn append_match_node_id(
+    match_desc: &mut String,
+    db: &AnnotationGraph,
+    singlematch: &Match,
+    quirks_mode: bool,
+) {
+    let singlematch_anno_key = &singlematch.anno_key;
+    if singlematch_anno_key.ns != ANNIS_NS || singlematch_anno_key.name != NODE_TYPE {
+        if !singlematch_anno_key.ns.is_empty() {
+            let encoded_anno_ns: Cow<str> =
+                utf8_percent_encode(&singlematch_anno_key.ns, SALT_URI_ENCODE_SET).into();
+            match_desc.push_str(&encoded_anno_ns);
+            match_desc.push_str("::");
+        }
+        let encoded_anno_name: Cow<str> =
+            utf8_percent_encode(&singlematch_anno_key.name, SALT_URI_ENCODE_SET).into();
+        match_desc.push_str(&encoded_anno_name);
+        match_desc.push_str("::");
+    }
+
+    if let Some(name) = db
+        .get_node_annos()
+        .get_value_for_item(&singlematch.node, &NODE_NAME_KEY)
+    {
+        if quirks_mode {
+            // Unescape and re-escape with quirks-mode compatible character encoding set
+            let decoded_name = percent_encoding::percent_decode_str(&name).decode_utf8_lossy();
+            let re_encoded_name: Cow<str> =
+                utf8_percent_encode(&decoded_name, QUIRKS_SALT_URI_ENCODE_SET).into();
+            match_desc.push_str(&re_encoded_name);
+        } else {
+            match_desc.push_str(&name);
+        }
+    }
+}
+
+/// Get the variables of a projection, which might be given with the leading `#` of the query.
+fn projection_variables<P: AsRef<str>>(projection: &[P]) -> HashSet<String> {
+    projection
+        .iter()
+        .map(|var| {
+            let var = var.as_ref();
+            var.strip_prefix('#').unwrap_or(var).to_string()
+        })
+        .collect()
+}
+
+fn text_offset(
+    node: NodeID,
+    token_helper: Option<&TokenHelper>,
+    gs_order: Option<&dyn GraphStorage>,
+    known_roots: &mut Vec<NodeID>,
+) -> Option<TextOffset> {
+    let token_helper = token_helper?;
+    let gs_order = gs_order?;
+
+    let (left_token, right_token) = token_helper.left_right_token_for(node);
+    Some(TextOffset {
+        left_token: token_index(left_token?, gs_order, known_roots),
+        right_token: token_index(right_token?, gs_order, known_roots),
+    })
+}
+
+/// Get the index of a token, which is its distance to the first token of the ordering chain.
+///
+/// Matches are usually sorted by their text, so the most recently found root is checked first.
+/// Only if the token does not belong to any of the `known_roots`, the ordering chain is followed backwards
+/// and the newly found root is added to the list.
+fn token_index(token: NodeID, gs_order: &dyn GraphStorage, known_roots: &mut Vec<NodeID>) -> usize {
+    for (i, root) in known_roots.iter().enumerate().rev() {
+        if let Some(index) = gs_order.distance(*root, token) {
+            let root = known_roots.remove(i);
+            known_roots.push(root);
+            return index;
+        }
+    }
+
+    let mut root = token;
+    let mut index = 0;
+    while let Some(previous) = gs_order.get_ingoing_edges(root).next() {
+        root = previous;
+        index += 1;
+    }
+    known_roots.push(root);
+    index
+}
+
+/// Size of a file or 0 if the file does not exist (anymore).
+fn file_size(path: &Path) -> std::io::Result<u64> {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => Ok(metadata.len()),
+        Ok(_) => Ok(0),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+/// Sum of the sizes of all files in a directory or 0 if the directory does not exist (anymore).
+/// Files might be removed by a background task while iterating over the directory, these are ignored.
+fn directory_size(path: &Path, recursive: bool) -> std::io::Result<u64> {
+    let entries = match path.read_dir() {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut result = 0;
+    for entry in entries {
+        let entry = entry?;
+        let entry_path = entry.path();
+        if entry_path.is_dir() {
+            if recursive {
+                result += directory_size(&entry_path, true)?;
+            }
+        } else {
+            result += file_size(&entry_path)?;
+        }
+    }
+    Ok(result)
+}
+
+fn write_corpus_summary(
+    corpus_dir: &Path,
+    summary: &CorpusSummary,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Result<()> {
+    write_corpus_file(
+        corpus_dir,
+        &corpus_dir.join(CORPUS_SUMMARY_FILE),
+        &toml::to_string(summary)?,
+        key,
+    )
+}
+
+fn read_node_id_generation(
+    corpus_dir: &Path,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Result<Option<u64>> {
+    let path = corpus_dir.join(NODE_ID_GENERATION_FILE);
+    match read_corpus_file(corpus_dir, &path, key)? {
+        Some(content) => Ok(Some(content.trim().parse()?)),
+        None => Ok(None),
+    }
+}
+
+/// Start a new generation of node IDs for the corpus and return it.
+fn renew_node_id_generation(corpus_dir: &Path, key: Option<&Arc<EncryptionKey>>) -> Result<u64> {
+    // Use a random value, so a re-imported corpus with the same name never gets the generation of the previous one
+    let generation: u64 = rand::random();
+    write_corpus_file(
+        corpus_dir,
+        &corpus_dir.join(NODE_ID_GENERATION_FILE),
+        &generation.to_string(),
+        key,
+    )?;
+    Ok(generation)
+}
+
+/// Calculate the summary of a corpus that has just been saved and persist it in the corpus directory.
+///
+/// If the summary can't be calculated or written, an outdated summary is removed.
+fn save_corpus_summary(corpus_dir: &Path, db: &AnnotationGraph, key: Option<&Arc<EncryptionKey>>) {
+    let mut mem_ops = MallocSizeOfOps::new(memory_estimation::platform::usable_size, None, None);
+    if let Some(summary) = CorpusSummary::calculate(db, corpus_dir, &mut mem_ops) {
+        if let Err(e) = write_corpus_summary(corpus_dir, &summary, key) {
+            warn!(
+                "Could not write summary for corpus {}: {:?}",
+                corpus_dir.to_string_lossy(),
+                e
+            );
+            remove_corpus_summary(corpus_dir);
+        }
+    } else {
+        remove_corpus_summary(corpus_dir);
+    }
+}
+
+fn remove_corpus_summary(corpus_dir: &Path) {
+    let summary_path = corpus_dir.join(CORPUS_SUMMARY_FILE);
+    if summary_path.is_file() {
+        if let Err(e) = std::fs::remove_file(&summary_path) {
+            warn!(
+                "Could not remove outdated corpus summary {}: {:?}",
+                summary_path.to_string_lossy(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(feature = "corpus-storage")]
 fn check_cache_size_and_remove_with_cache(
     cache: &mut LinkedHashMap<String, Arc<RwLock<CacheEntry>>>,
     cache_strategy: &CacheStrategy,
+    usage: &Mutex<eviction::CacheUsage>,
+    pinned: &Mutex<HashMap<String, usize>>,
     keep: Vec<&str>,
     report_cache_status: bool,
 ) {
-    let keep: HashSet<&str> = keep.into_iter().collect();
+    let pinned = pinned.lock().unwrap();
+    let mut keep: HashSet<&str> = keep.into_iter().collect();
+    // pinned corpora are never removed from the cache
+    keep.extend(pinned.keys().map(String::as_str));
 
     // check size of each corpus and calculate the sum of used memory
     let db_sizes = get_cache_sizes(cache);
@@ -2519,13 +7417,16 @@ fn check_cache_size_and_remove_with_cache(
         (max_cache_size as f64) / 1_000_000.0
     );
 
-    // remove older entries (at the beginning) until cache size requirements are met,
+    // remove entries in the order given by the eviction policy until cache size requirements are met,
     // but never remove the last loaded entry
-    for (corpus_name, corpus_size) in db_sizes.iter() {
+    let mut usage = usage.lock().unwrap();
+    for corpus_name in usage.eviction_order(&db_sizes) {
         if size_sum > max_cache_size {
             if !keep.contains(corpus_name.as_str()) {
-                cache.remove(corpus_name);
+                let corpus_size = db_sizes.get(&corpus_name).copied().unwrap_or_default();
+                cache.remove(&corpus_name);
                 size_sum -= corpus_size;
+                usage.record_eviction(&corpus_name, corpus_size);
                 debug!(
                     "Removing corpus {} from cache. {}",
                     corpus_name,
@@ -2544,6 +7445,7 @@ fn check_cache_size_and_remove_with_cache(
 }
 
 /// Return the current size and loaded corpora as debug string.
+#[cfg(feature = "corpus-storage")]
 fn get_corpus_cache_info_as_string(
     cache: &mut LinkedHashMap<String, Arc<RwLock<CacheEntry>>>,
     max_cache_size: usize,
@@ -2572,6 +7474,81 @@ fn get_corpus_cache_info_as_string(
     }
 }
 
+/// Create a query that finds all nodes and data sources which belong to any of the given sub-corpora/documents.
+///
+/// The sub-corpus/document itself is the first node of each alternative, the found node the second one.
+fn subcorpus_query<'a>(corpus_ids: Vec<String>) -> Result<Disjunction<'a>> {
+    let mut query = Disjunction {
+        alternatives: vec![],
+    };
+    // find all nodes that a connected with the corpus IDs
+    for source_corpus_id in corpus_ids {
+        // remove the obsolete "salt:/" prefix
+        let source_corpus_id: &str = source_corpus_id
+            .strip_prefix("salt:/")
+            .unwrap_or(&source_corpus_id);
+        // All annotation nodes
+        {
+            let mut q = Conjunction::new();
+            let corpus_idx = q.add_node(
+                NodeSearchSpec::ExactValue {
+                    ns: Some(ANNIS_NS.to_string()),
+                    name: NODE_NAME.to_string(),
+                    val: Some(source_corpus_id.to_string()),
+                    is_meta: false,
+                },
+                None,
+            );
+            let any_node_idx = q.add_node(NodeSearchSpec::AnyNode, None);
+            q.add_operator(
+                Box::new(operators::PartOfSubCorpusSpec {
+                    dist: RangeSpec::Unbound,
+                    inverse: false,
+                }),
+                &any_node_idx,
+                &corpus_idx,
+                true,
+            )?;
+            query.alternatives.push(q);
+        }
+        // All data source nodes
+        {
+            let mut q = Conjunction::new();
+            let corpus_idx = q.add_node(
+                NodeSearchSpec::ExactValue {
+                    ns: Some(ANNIS_NS.to_string()),
+                    name: NODE_NAME.to_string(),
+                    val: Some(source_corpus_id.to_string()),
+                    is_meta: false,
+                },
+                None,
+            );
+            let any_node_idx = q.add_node(
+                NodeSearchSpec::ExactValue {
+                    ns: Some(ANNIS_NS.to_string()),
+                    name: NODE_TYPE.to_string(),
+                    val: Some("datasource".to_string()),
+                    is_meta: false,
+                },
+                None,
+            );
+            q.add_operator(
+                Box::new(operators::PartOfSubCorpusSpec {
+                    dist: RangeSpec::Unbound,
+                    inverse: false,
+                }),
+                &any_node_idx,
+                &corpus_idx,
+                true,
+            )?;
+            query.alternatives.push(q);
+        }
+    }
+
+    Ok(query)
+}
+
+#[cfg(feature = "corpus-storage")]
 fn extract_subgraph_by_query(
     db_entry: &Arc<RwLock<CacheEntry>>,
     query: &Disjunction,
@@ -2676,6 +7653,7 @@ fn create_subgraph_edge(
     Ok(())
 }
 
+#[cfg(feature = "corpus-storage")]
 fn create_lockfile_for_directory(db_dir: &Path) -> Result<File> {
     std::fs::create_dir_all(&db_dir).map_err(|e| CorpusStorageError::LockCorpusDirectory {
         path: db_dir.to_string_lossy().to_string(),
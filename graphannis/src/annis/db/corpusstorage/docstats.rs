@@ -0,0 +1,170 @@
+use crate::annis::db::aql::model::{AnnotationComponentType, TOKEN_KEY};
+use crate::annis::errors::*;
+use crate::annis::types::{DocumentCounts, DocumentStatistics};
+use crate::AnnotationGraph;
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::{ANNIS_NS, NODE_NAME_KEY, NODE_TYPE_KEY},
+    types::{Component, NodeID},
+    util::encryption::EncryptionKey,
+};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+const DOCUMENT_STATISTICS_FILE: &str = "document-statistics.toml";
+
+/// Wrapper for the persisted file, since TOML needs a table as root.
+#[derive(Serialize, Deserialize)]
+struct PersistedDocumentStatistics {
+    #[serde(default)]
+    documents: Vec<DocumentStatistics>,
+}
+
+/// Count the tokens, segments and annotations of each document of the (fully loaded) graph.
+///
+/// A node belongs to the documents it is directly part of.
+pub(super) fn calculate(db: &AnnotationGraph) -> Vec<DocumentStatistics> {
+    let node_annos = db.get_node_annos();
+
+    let mut counts: BTreeMap<NodeID, DocumentCounts> = node_annos
+        .exact_anno_search(Some(ANNIS_NS), "doc", ValueSearch::Any)
+        .map(|m| (m.node, DocumentCounts::default()))
+        .collect();
+
+    let part_of_gs = db.get_graphstorage(&Component::new(
+        AnnotationComponentType::PartOf,
+        ANNIS_NS.into(),
+        "".into(),
+    ));
+    let part_of_gs = if let Some(part_of_gs) = part_of_gs {
+        part_of_gs
+    } else {
+        return Vec::new();
+    };
+    let coverage_gs: Vec<_> = db
+        .get_all_components(Some(AnnotationComponentType::Coverage), None)
+        .into_iter()
+        .filter_map(|c| db.get_graphstorage(&c))
+        .collect();
+    let segmentation_gs: Vec<_> = db
+        .get_all_components(Some(AnnotationComponentType::Ordering), None)
+        .into_iter()
+        .filter(|c| !c.name.is_empty())
+        .filter_map(|c| db.get_graphstorage(&c).map(|gs| (c.name.to_string(), gs)))
+        .collect();
+
+    for m in node_annos.exact_anno_search(
+        Some(&NODE_TYPE_KEY.ns),
+        &NODE_TYPE_KEY.name,
+        Some("node").into(),
+    ) {
+        let n = m.node;
+        let is_token = node_annos.has_value_for_item(&n, &TOKEN_KEY)
+            && !coverage_gs.iter().any(|gs| gs.has_outgoing_edges(n));
+        let segments: Vec<&String> = segmentation_gs
+            .iter()
+            .filter(|(_, gs)| gs.has_outgoing_edges(n) || gs.get_ingoing_edges(n).next().is_some())
+            .map(|(name, _)| name)
+            .collect();
+        let annotations: Vec<String> = node_annos
+            .get_all_keys_for_item(&n, None, None)
+            .into_iter()
+            .filter(|key| key.ns != ANNIS_NS)
+            .map(|key| {
+                if key.ns.is_empty() {
+                    key.name.to_string()
+                } else {
+                    format!("{}::{}", key.ns, key.name)
+                }
+            })
+            .collect();
+
+        for doc in part_of_gs.get_outgoing_edges(n) {
+            if let Some(doc_counts) = counts.get_mut(&doc) {
+                if is_token {
+                    doc_counts.tokens += 1;
+                }
+                for name in &segments {
+                    *doc_counts.segments.entry(name.to_string()).or_default() += 1;
+                }
+                for name in &annotations {
+                    *doc_counts.annotations.entry(name.clone()).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    let mut result: Vec<DocumentStatistics> = counts
+        .into_iter()
+        .filter_map(|(doc, counts)| {
+            let document = node_annos.get_value_for_item(&doc, &NODE_NAME_KEY)?;
+            Some(DocumentStatistics {
+                document: document.to_string(),
+                counts,
+            })
+        })
+        .collect();
+    result.sort_by(|a, b| a.document.cmp(&b.document));
+    result
+}
+
+/// Read the persisted document statistics of a corpus, returns `None` if they don't exist or are invalid.
+pub(super) fn read(
+    corpus_dir: &Path,
+    key: Option<&Arc<EncryptionKey>>,
+) -> Option<Vec<DocumentStatistics>> {
+    let path = corpus_dir.join(DOCUMENT_STATISTICS_FILE);
+    let file_content = match super::read_corpus_file(corpus_dir, &path, key) {
+        Ok(file_content) => file_content?,
+        Err(e) => {
+            warn!(
+                "Ignoring unreadable document statistics {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            return None;
+        }
+    };
+    match toml::from_str::<PersistedDocumentStatistics>(&file_content) {
+        Ok(persisted) => Some(persisted.documents),
+        Err(e) => {
+            warn!(
+                "Ignoring invalid document statistics {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            None
+        }
+    }
+}
+
+pub(super) fn write(
+    corpus_dir: &Path,
+    documents: &[DocumentStatistics],
+    key: Option<&Arc<EncryptionKey>>,
+) -> Result<()> {
+    let persisted = PersistedDocumentStatistics {
+        documents: documents.to_vec(),
+    };
+    super::write_corpus_file(
+        corpus_dir,
+        &corpus_dir.join(DOCUMENT_STATISTICS_FILE),
+        &toml::to_string(&persisted)?,
+        key,
+    )
+}
+
+/// Remove the persisted document statistics, which are outdated after the corpus has been changed.
+pub(super) fn remove(corpus_dir: &Path) {
+    let path = corpus_dir.join(DOCUMENT_STATISTICS_FILE);
+    if path.is_file() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!(
+                "Could not remove outdated document statistics {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use super::{CacheMetrics, CorpusCacheMetrics, EvictionPolicy};
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How a single loaded corpus has been used since it was loaded into the cache.
+#[derive(Debug, Clone)]
+struct EntryUsage {
+    /// Value of the logical clock when the corpus has been accessed the last time.
+    last_access: u64,
+    /// Number of accesses since the corpus has been loaded.
+    frequency: u64,
+    /// Time in seconds it took to load the corpus.
+    load_cost: f64,
+    /// The inflation value of the cache when the corpus has been accessed the last time.
+    inflation: f64,
+}
+
+/// Keeps track of the usage of the loaded corpora, decides in which order they are removed from the cache and
+/// collects the cache metrics.
+#[derive(Debug, Default)]
+pub(super) struct CacheUsage {
+    policy: EvictionPolicy,
+    clock: u64,
+    /// The inflation value used by the Greedy-Dual-Size-Frequency policy, which is the priority of the last removed
+    /// corpus. It makes sure that corpora which have been used frequently a long time ago are removed eventually.
+    inflation: f64,
+    entries: HashMap<String, EntryUsage>,
+    metrics: CacheMetrics,
+}
+
+impl CacheUsage {
+    pub(super) fn set_policy(&mut self, policy: EvictionPolicy) {
+        self.policy = policy;
+    }
+
+    pub(super) fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn corpus_metrics(&mut self, corpus_name: &str) -> &mut CorpusCacheMetrics {
+        self.metrics
+            .corpora
+            .entry(corpus_name.to_string())
+            .or_default()
+    }
+
+    /// Record that an already loaded corpus has been accessed.
+    pub(super) fn record_hit(&mut self, corpus_name: &str) {
+        let now = self.tick();
+        let inflation = self.inflation;
+        if let Some(usage) = self.entries.get_mut(corpus_name) {
+            usage.last_access = now;
+            usage.frequency += 1;
+            usage.inflation = inflation;
+        }
+        self.metrics.hits += 1;
+        self.corpus_metrics(corpus_name).hits += 1;
+    }
+
+    /// Record that a corpus has been loaded into the cache, which took the given time.
+    pub(super) fn record_load(&mut self, corpus_name: &str, load_time: Duration) {
+        let now = self.tick();
+        self.entries.insert(
+            corpus_name.to_string(),
+            EntryUsage {
+                last_access: now,
+                frequency: 1,
+                load_cost: load_time.as_secs_f64(),
+                inflation: self.inflation,
+            },
+        );
+        self.metrics.misses += 1;
+        self.metrics.load_time += load_time;
+        let corpus_metrics = self.corpus_metrics(corpus_name);
+        corpus_metrics.loads += 1;
+        corpus_metrics.last_load_time = load_time;
+    }
+
+    /// Record that a corpus has been added to the cache without loading it from disk, e.g. by an import.
+    ///
+    /// Since the time to load the corpus is unknown, it is regarded as cheap to load for the
+    /// [`EvictionPolicy::LoadCostWeighted`] policy.
+    pub(super) fn record_insert(&mut self, corpus_name: &str) {
+        let now = self.tick();
+        self.entries.insert(
+            corpus_name.to_string(),
+            EntryUsage {
+                last_access: now,
+                frequency: 1,
+                load_cost: 0.0,
+                inflation: self.inflation,
+            },
+        );
+    }
+
+    /// Record that a corpus with the given size has been removed from the cache because the cache was too large.
+    pub(super) fn record_eviction(&mut self, corpus_name: &str, size: usize) {
+        if let Some(usage) = self.entries.remove(corpus_name) {
+            if self.policy == EvictionPolicy::LoadCostWeighted {
+                self.inflation = self.inflation.max(gdsf_priority(&usage, size));
+            }
+        }
+        self.metrics.evictions += 1;
+        self.metrics.evicted_bytes += size as u64;
+        self.corpus_metrics(corpus_name).evictions += 1;
+    }
+
+    /// Return the names of the given cached corpora in the order in which they should be removed from the cache.
+    pub(super) fn eviction_order(&self, sizes: &LinkedHashMap<String, usize>) -> Vec<String> {
+        // Corpora without usage information are regarded as the oldest ones.
+        let mut candidates: Vec<(&String, usize, Option<&EntryUsage>)> = sizes
+            .iter()
+            .map(|(corpus_name, size)| (corpus_name, *size, self.entries.get(corpus_name)))
+            .collect();
+        let last_access = |usage: Option<&EntryUsage>| usage.map(|u| u.last_access).unwrap_or(0);
+
+        // All sorts are stable, so corpora with the same priority are removed in the order they have been
+        // added to the cache.
+        match self.policy {
+            EvictionPolicy::LeastRecentlyUsed => {
+                candidates.sort_by_key(|(_, _, usage)| last_access(*usage));
+            }
+            EvictionPolicy::SizeWeighted => {
+                candidates.sort_by(|(_, size1, usage1), (_, size2, usage2)| {
+                    size2
+                        .cmp(size1)
+                        .then_with(|| last_access(*usage1).cmp(&last_access(*usage2)))
+                });
+            }
+            EvictionPolicy::LoadCostWeighted => {
+                let priority = |size: usize, usage: Option<&EntryUsage>| {
+                    usage.map(|u| gdsf_priority(u, size)).unwrap_or(0.0)
+                };
+                candidates.sort_by(|(_, size1, usage1), (_, size2, usage2)| {
+                    priority(*size1, *usage1)
+                        .partial_cmp(&priority(*size2, *usage2))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                        .then_with(|| last_access(*usage1).cmp(&last_access(*usage2)))
+                });
+            }
+        }
+        candidates
+            .into_iter()
+            .map(|(corpus_name, _, _)| corpus_name.clone())
+            .collect()
+    }
+
+    pub(super) fn metrics(&self) -> CacheMetrics {
+        self.metrics.clone()
+    }
+
+    pub(super) fn reset_metrics(&mut self) {
+        self.metrics = CacheMetrics::default();
+    }
+}
+
+/// Priority of a corpus for the Greedy-Dual-Size-Frequency policy. Corpora with a lower priority are removed first.
+fn gdsf_priority(usage: &EntryUsage, size: usize) -> f64 {
+    // use the size in MB, so the load cost does not vanish compared to the size
+    let size = (size as f64 / 1_000_000.0).max(f64::MIN_POSITIVE);
+    usage.inflation + (usage.frequency as f64) * usage.load_cost / size
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(entries: &[(&str, usize)]) -> LinkedHashMap<String, usize> {
+        entries
+            .iter()
+            .map(|(corpus_name, size)| (corpus_name.to_string(), *size))
+            .collect()
+    }
+
+    #[test]
+    fn least_recently_used_order() {
+        let mut usage = CacheUsage::default();
+        usage.record_load("a", Duration::from_secs(1));
+        usage.record_load("b", Duration::from_secs(1));
+        usage.record_load("c", Duration::from_secs(1));
+        usage.record_hit("a");
+
+        usage.record_insert("imported");
+
+        let cached = sizes(&[
+            ("a", 10),
+            ("imported", 10),
+            ("b", 10),
+            ("c", 10),
+            ("unknown", 10),
+        ]);
+        assert_eq!(
+            vec!["unknown", "b", "c", "a", "imported"],
+            usage.eviction_order(&cached)
+        );
+    }
+
+    #[test]
+    fn size_weighted_order() {
+        let mut usage = CacheUsage::default();
+        usage.set_policy(EvictionPolicy::SizeWeighted);
+        usage.record_load("small", Duration::from_secs(1));
+        usage.record_load("huge", Duration::from_secs(1));
+        usage.record_load("medium1", Duration::from_secs(1));
+        usage.record_load("medium2", Duration::from_secs(1));
+        usage.record_hit("medium1");
+
+        let cached = sizes(&[
+            ("small", 1_000),
+            ("huge", 1_000_000),
+            ("medium1", 10_000),
+            ("medium2", 10_000),
+        ]);
+        assert_eq!(
+            vec!["huge", "medium2", "medium1", "small"],
+            usage.eviction_order(&cached)
+        );
+    }
+
+    #[test]
+    fn load_cost_weighted_order() {
+        let mut usage = CacheUsage::default();
+        usage.set_policy(EvictionPolicy::LoadCostWeighted);
+        // expensive to load compared to its size
+        usage.record_load("huge", Duration::from_secs(15));
+        usage.record_load("small", Duration::from_millis(10));
+        usage.record_load("frequent", Duration::from_millis(10));
+        for _ in 0..10 {
+            usage.record_hit("frequent");
+        }
+
+        let cached = sizes(&[
+            ("huge", 1_000_000_000),
+            ("small", 1_000_000),
+            ("frequent", 1_000_000),
+        ]);
+        assert_eq!(
+            vec!["small", "huge", "frequent"],
+            usage.eviction_order(&cached)
+        );
+
+        // removing a corpus raises the priority of the corpora that are accessed afterwards
+        usage.record_eviction("small", 1_000_000);
+        usage.record_load("small", Duration::from_millis(10));
+        assert_eq!(
+            vec!["huge", "small", "frequent"],
+            usage.eviction_order(&cached)
+        );
+    }
+
+    #[test]
+    fn metrics() {
+        let mut usage = CacheUsage::default();
+        usage.record_load("a", Duration::from_secs(2));
+        usage.record_hit("a");
+        usage.record_hit("a");
+        usage.record_eviction("a", 100);
+        usage.record_load("a", Duration::from_secs(1));
+
+        let metrics = usage.metrics();
+        assert_eq!(2, metrics.hits);
+        assert_eq!(2, metrics.misses);
+        assert_eq!(1, metrics.evictions);
+        assert_eq!(100, metrics.evicted_bytes);
+        assert_eq!(Duration::from_secs(3), metrics.load_time);
+        let a = &metrics.corpora["a"];
+        assert_eq!(2, a.hits);
+        assert_eq!(2, a.loads);
+        assert_eq!(1, a.evictions);
+        assert_eq!(Duration::from_secs(1), a.last_load_time);
+
+        usage.reset_metrics();
+        assert_eq!(CacheMetrics::default(), usage.metrics());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::annis::errors::{CorpusStorageError, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LEASE_FILE: &str = "db.lease";
+/// The heartbeat is stored in seconds and renewed three times per timeout, so shorter timeouts are not possible.
+const MIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The content of the lease file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct LeaseInfo {
+    instance_id: String,
+    hostname: String,
+    process_id: u32,
+    /// Seconds since the UNIX epoch when the lease was renewed the last time.
+    heartbeat: u64,
+}
+
+impl LeaseInfo {
+    fn seconds_since_heartbeat(&self) -> u64 {
+        unix_timestamp().saturating_sub(self.heartbeat)
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+fn read_lease(lease_file: &Path) -> Option<LeaseInfo> {
+    let content = std::fs::read_to_string(lease_file).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Create the lease file with the given lease, unless a lease file already exists.
+///
+/// The lease is written to a temporary file first, which is then hard linked as lease file. Creating the hard link
+/// fails atomically if the lease file exists, even on network filesystems, so at most one instance can create
+/// the lease and other instances never read a partially written lease file.
+/// Returns `false` if the lease file already exists.
+fn create_lease(db_dir: &Path, lease: &LeaseInfo) -> std::io::Result<bool> {
+    let tmp_file = db_dir.join(format!("{}.{}.tmp", LEASE_FILE, lease.instance_id));
+    let content = toml::to_string(lease)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(&tmp_file, content)?;
+    let result = std::fs::hard_link(&tmp_file, db_dir.join(LEASE_FILE));
+    std::fs::remove_file(&tmp_file)?;
+    match result {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Renew the lease file with the given lease, but only if the lease file still belongs to this instance.
+///
+/// The renewed lease is written to a temporary file, which then replaces the lease file by renaming it. The lease
+/// file therefore exists at any time and no other instance can create its own lease while the lease is renewed.
+/// Returns `false` if the lease file does not exist anymore or belongs to another instance.
+fn renew_lease(db_dir: &Path, lease: &LeaseInfo) -> std::io::Result<bool> {
+    let lease_file = db_dir.join(LEASE_FILE);
+    let tmp_file = db_dir.join(format!("{}.{}.tmp", LEASE_FILE, lease.instance_id));
+    let content = toml::to_string(lease)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    std::fs::write(&tmp_file, content)?;
+    let owned = read_lease(&lease_file)
+        .map(|current| current.instance_id == lease.instance_id)
+        .unwrap_or(false);
+    if owned {
+        std::fs::rename(&tmp_file, &lease_file)?;
+    } else {
+        std::fs::remove_file(&tmp_file)?;
+    }
+    Ok(owned)
+}
+
+/// Remove the lease file, but only if its content fulfills the `condition`.
+///
+/// The lease file is renamed to a file name which is unique for this instance first, so no other instance can
+/// replace it while it is checked. If the condition does not hold, the lease file is restored.
+/// Returns `true` if the lease file has been removed.
+fn remove_lease_if<F>(db_dir: &Path, instance_id: &str, condition: F) -> std::io::Result<bool>
+where
+    F: FnOnce(&LeaseInfo) -> bool,
+{
+    let lease_file = db_dir.join(LEASE_FILE);
+    let moved_file = db_dir.join(format!("{}.{}.old", LEASE_FILE, instance_id));
+    match std::fs::rename(&lease_file, &moved_file) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    }
+    let fulfilled = read_lease(&moved_file)
+        .map(|l| condition(&l))
+        .unwrap_or(false);
+    if !fulfilled {
+        // Restore the lease file of the other instance, unless a new lease has been created in the meantime
+        if let Err(e) = std::fs::hard_link(&moved_file, &lease_file) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(e);
+            }
+        }
+    }
+    std::fs::remove_file(&moved_file)?;
+    Ok(fulfilled)
+}
+
+fn lease_held_error(db_dir: &Path, current: LeaseInfo) -> CorpusStorageError {
+    CorpusStorageError::LeaseHeld {
+        path: db_dir.to_string_lossy().to_string(),
+        seconds_since_heartbeat: current.seconds_since_heartbeat(),
+        instance_id: current.instance_id,
+        hostname: current.hostname,
+        process_id: current.process_id,
+    }
+}
+
+fn lock_error(db_dir: &Path, source: std::io::Error) -> CorpusStorageError {
+    CorpusStorageError::LockCorpusDirectory {
+        path: db_dir.to_string_lossy().to_string(),
+        source,
+    }
+}
+
+/// A lease on a corpus storage directory, which is renewed by a background thread until it is dropped.
+///
+/// In contrast to file locks, this only relies on reading, writing, renaming and hard linking files, which also
+/// works on network filesystems where file locks are unreliable.
+/// A lease that has not been renewed for longer than the timeout is regarded as abandoned, e.g. because the
+/// process holding it has crashed, and can be taken over by another instance.
+pub(crate) struct Lease {
+    db_dir: PathBuf,
+    instance_id: String,
+    /// Set by the heartbeat thread when the lease could not be renewed because another instance took it over.
+    lost: Arc<AtomicBool>,
+    stop_heartbeat: Option<Sender<()>>,
+    heartbeat_thread: Option<JoinHandle<()>>,
+}
+
+impl Lease {
+    /// Acquire the lease for the directory or return an error if another live instance holds it.
+    pub(crate) fn acquire(db_dir: &Path, timeout: Duration) -> Result<Lease> {
+        if timeout < MIN_TIMEOUT {
+            return Err(CorpusStorageError::LeaseTimeoutTooShort {
+                timeout,
+                minimum: MIN_TIMEOUT,
+            }
+            .into());
+        }
+        std::fs::create_dir_all(db_dir).map_err(|e| lock_error(db_dir, e))?;
+        let lease_file = db_dir.join(LEASE_FILE);
+
+        let lease = LeaseInfo {
+            instance_id: format!("{:016x}", rand::random::<u64>()),
+            hostname: sys_info::hostname().unwrap_or_default(),
+            process_id: std::process::id(),
+            heartbeat: unix_timestamp(),
+        };
+
+        if !create_lease(db_dir, &lease).map_err(|e| lock_error(db_dir, e))? {
+            let existing = read_lease(&lease_file).ok_or_else(|| {
+                lock_error(
+                    db_dir,
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "existing lease file can't be read",
+                    ),
+                )
+            })?;
+            let seconds_since_heartbeat = existing.seconds_since_heartbeat();
+            if seconds_since_heartbeat < timeout.as_secs() {
+                return Err(lease_held_error(db_dir, existing).into());
+            }
+            warn!(
+                "Taking over the abandoned lease of instance {} (host {}, process {}) for {}, which has not been renewed for {} seconds",
+                existing.instance_id,
+                existing.hostname,
+                existing.process_id,
+                db_dir.to_string_lossy(),
+                seconds_since_heartbeat
+            );
+            // Only remove the lease which has been regarded as abandoned and not a lease which another instance
+            // has created in the meantime
+            remove_lease_if(db_dir, &lease.instance_id, |current| current == &existing)
+                .map_err(|e| lock_error(db_dir, e))?;
+            if !create_lease(db_dir, &lease).map_err(|e| lock_error(db_dir, e))? {
+                // Another instance took over the abandoned lease at the same time
+                let current = read_lease(&lease_file).unwrap_or(existing);
+                return Err(lease_held_error(db_dir, current).into());
+            }
+        }
+
+        let instance_id = lease.instance_id.clone();
+        let lost = Arc::new(AtomicBool::new(false));
+        let (stop_heartbeat, stop_receiver) = channel();
+        let heartbeat_dir = db_dir.to_path_buf();
+        let heartbeat_lost = lost.clone();
+        let heartbeat_interval = timeout / 3;
+        let heartbeat_thread = std::thread::spawn(move || {
+            let mut lease = lease;
+            // renew the lease until the sender is dropped
+            while let Err(RecvTimeoutError::Timeout) =
+                stop_receiver.recv_timeout(heartbeat_interval)
+            {
+                lease.heartbeat = unix_timestamp();
+                match renew_lease(&heartbeat_dir, &lease) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        heartbeat_lost.store(true, Ordering::SeqCst);
+                        error!(
+                            "The lease for {} has been taken over by another instance, changes to the corpus storage are refused",
+                            heartbeat_dir.to_string_lossy()
+                        );
+                        return;
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Could not renew the lease for {}: {:?}",
+                            heartbeat_dir.to_string_lossy(),
+                            e
+                        );
+                    }
+                }
+            }
+        });
+
+        Ok(Lease {
+            db_dir: db_dir.to_path_buf(),
+            instance_id,
+            lost,
+            stop_heartbeat: Some(stop_heartbeat),
+            heartbeat_thread: Some(heartbeat_thread),
+        })
+    }
+
+    /// Return an error if the lease has been taken over by another instance.
+    pub(crate) fn check_valid(&self) -> Result<()> {
+        if self.lost.load(Ordering::SeqCst) {
+            Err(CorpusStorageError::LeaseLost(self.db_dir.to_string_lossy().to_string()).into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stop renewing the lease and remove the lease file if it still belongs to this instance.
+    pub(crate) fn release(&mut self) {
+        // dropping the sender stops the heartbeat thread
+        self.stop_heartbeat.take();
+        if let Some(heartbeat_thread) = self.heartbeat_thread.take() {
+            if heartbeat_thread.join().is_err() {
+                warn!("Heartbeat thread for the CorpusStorage lease panicked");
+            }
+        }
+        let instance_id = &self.instance_id;
+        match remove_lease_if(&self.db_dir, instance_id, |current| {
+            &current.instance_id == instance_id
+        }) {
+            Ok(true) => trace!("Released CorpusStorage lease"),
+            Ok(false) => {}
+            Err(e) => warn!("Could not remove CorpusStorage lease file: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease_info(instance_id: &str) -> LeaseInfo {
+        LeaseInfo {
+            instance_id: instance_id.to_string(),
+            hostname: "localhost".to_string(),
+            process_id: 1,
+            heartbeat: unix_timestamp(),
+        }
+    }
+
+    #[test]
+    fn acquire_during_renewal() {
+        let tmp = tempfile::tempdir().unwrap();
+        let db_dir = tmp.path().to_path_buf();
+        let holder = lease_info("0000000000000001");
+        assert!(create_lease(&db_dir, &holder).unwrap());
+
+        let renewal_dir = db_dir.clone();
+        let renewal = std::thread::spawn(move || {
+            let mut holder = holder;
+            for _ in 0..1000 {
+                holder.heartbeat = unix_timestamp();
+                assert!(renew_lease(&renewal_dir, &holder).unwrap());
+            }
+        });
+
+        // Another instance must never be able to create its lease while the lease is renewed
+        let newcomer = lease_info("0000000000000002");
+        while !renewal.is_finished() {
+            assert!(!create_lease(&db_dir, &newcomer).unwrap());
+            assert_eq!(
+                Some("0000000000000001".to_string()),
+                read_lease(&db_dir.join(LEASE_FILE)).map(|l| l.instance_id)
+            );
+        }
+        renewal.join().unwrap();
+
+        // The renewal does not replace a lease of another instance
+        std::fs::remove_file(db_dir.join(LEASE_FILE)).unwrap();
+        assert!(create_lease(&db_dir, &newcomer).unwrap());
+        assert!(!renew_lease(&db_dir, &lease_info("0000000000000001")).unwrap());
+        assert_eq!(Some(newcomer), read_lease(&db_dir.join(LEASE_FILE)));
+    }
+}
@@ -0,0 +1,209 @@
+use super::QueryLanguage;
+use crate::annis::errors::*;
+use graphannis_core::util::encryption::EncryptionKey;
+use linked_hash_map::LinkedHashMap;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const PLAN_CACHE_FILE: &str = "plan-cache.toml";
+
+/// Maximum number of queries for which the optimized plan is remembered for each corpus.
+const MAX_ENTRIES_PER_CORPUS: usize = 100;
+
+/// Identifies a query for which the plan has been optimized.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) struct PlanKey {
+    pub query: String,
+    pub query_language: QueryLanguage,
+    pub document_filter: Vec<String>,
+    /// Checksum of the derived operators of the corpus, which change the operators of the query.
+    pub derived_operators: u32,
+}
+
+/// The optimized operator orders of all alternatives of a query.
+type OperatorOrders = Vec<Vec<usize>>;
+
+#[derive(Serialize, Deserialize)]
+struct CachedPlan {
+    query: String,
+    query_language: QueryLanguage,
+    #[serde(default)]
+    document_filter: Vec<String>,
+    #[serde(default)]
+    derived_operators: u32,
+    /// The optimized order of the binary operators for each alternative of the query.
+    operator_orders: OperatorOrders,
+}
+
+/// Wrapper for the persisted file, since TOML needs a table as root.
+#[derive(Serialize, Deserialize, Default)]
+struct PersistedPlanCache {
+    #[serde(default)]
+    plans: Vec<CachedPlan>,
+}
+
+/// Remembers the optimized join order of queries for each corpus.
+///
+/// Optimizing the join order needs to estimate the costs of many possible execution plans, which is repeated for
+/// each query otherwise (e.g. when paging through the results). The entries are kept in memory and only persisted
+/// in the corpus directory when [PlanCache::persist] is called. They depend on the statistics of the corpus, thus
+/// they must be invalidated when the corpus is changed.
+#[derive(Default)]
+pub(super) struct PlanCache {
+    corpora: Mutex<HashMap<String, CorpusPlans>>,
+}
+
+struct CorpusPlans {
+    plans: LinkedHashMap<PlanKey, OperatorOrders>,
+    corpus_dir: PathBuf,
+    /// True if there are plans which have not been persisted yet.
+    changed: bool,
+}
+
+impl CorpusPlans {
+    fn load(corpus_dir: &Path, encryption_key: Option<&Arc<EncryptionKey>>) -> CorpusPlans {
+        CorpusPlans {
+            plans: read(corpus_dir, encryption_key),
+            corpus_dir: corpus_dir.to_path_buf(),
+            changed: false,
+        }
+    }
+}
+
+impl PlanCache {
+    /// Get the operator orders for all alternatives of the query, if the plan has been optimized before.
+    pub(super) fn get(
+        &self,
+        corpus_name: &str,
+        corpus_dir: &Path,
+        key: &PlanKey,
+        encryption_key: Option<&Arc<EncryptionKey>>,
+    ) -> Option<OperatorOrders> {
+        let mut corpora = self.corpora.lock().unwrap();
+        let corpus = corpora
+            .entry(corpus_name.to_string())
+            .or_insert_with(|| CorpusPlans::load(corpus_dir, encryption_key));
+        corpus.plans.get_refresh(key).cloned()
+    }
+
+    /// Remember the optimized operator orders of all alternatives of the query.
+    pub(super) fn insert(
+        &self,
+        corpus_name: &str,
+        corpus_dir: &Path,
+        key: PlanKey,
+        operator_orders: OperatorOrders,
+        encryption_key: Option<&Arc<EncryptionKey>>,
+    ) {
+        let mut corpora = self.corpora.lock().unwrap();
+        let corpus = corpora
+            .entry(corpus_name.to_string())
+            .or_insert_with(|| CorpusPlans::load(corpus_dir, encryption_key));
+        corpus.plans.insert(key, operator_orders);
+        while corpus.plans.len() > MAX_ENTRIES_PER_CORPUS {
+            corpus.plans.pop_front();
+        }
+        corpus.changed = true;
+    }
+
+    /// Write the plans of all corpora which have changed since they have been loaded or persisted.
+    pub(super) fn persist(&self, encryption_key: Option<&Arc<EncryptionKey>>) {
+        let mut corpora = self.corpora.lock().unwrap();
+        for (corpus_name, corpus) in corpora.iter_mut().filter(|(_, corpus)| corpus.changed) {
+            if let Err(e) = write(&corpus.corpus_dir, &corpus.plans, encryption_key) {
+                warn!(
+                    "Could not persist the plan cache for corpus {}: {:?}",
+                    corpus_name, e
+                );
+            }
+            corpus.changed = false;
+        }
+    }
+
+    /// Forget all plans of the corpus, e.g. because it has been changed.
+    pub(super) fn invalidate(&self, corpus_name: &str, corpus_dir: &Path) {
+        self.corpora.lock().unwrap().remove(corpus_name);
+        remove(corpus_dir);
+    }
+}
+
+fn read(
+    corpus_dir: &Path,
+    encryption_key: Option<&Arc<EncryptionKey>>,
+) -> LinkedHashMap<PlanKey, OperatorOrders> {
+    let path = corpus_dir.join(PLAN_CACHE_FILE);
+    let file_content = match super::read_corpus_file(corpus_dir, &path, encryption_key) {
+        Ok(Some(file_content)) => file_content,
+        Ok(None) => return LinkedHashMap::new(),
+        Err(e) => {
+            warn!(
+                "Ignoring unreadable plan cache {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            return LinkedHashMap::new();
+        }
+    };
+    match toml::from_str::<PersistedPlanCache>(&file_content) {
+        Ok(persisted) => persisted
+            .plans
+            .into_iter()
+            .map(|p| {
+                let key = PlanKey {
+                    query: p.query,
+                    query_language: p.query_language,
+                    document_filter: p.document_filter,
+                    derived_operators: p.derived_operators,
+                };
+                (key, p.operator_orders)
+            })
+            .collect(),
+        Err(e) => {
+            warn!(
+                "Ignoring invalid plan cache {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+            LinkedHashMap::new()
+        }
+    }
+}
+
+fn write(
+    corpus_dir: &Path,
+    plans: &LinkedHashMap<PlanKey, OperatorOrders>,
+    encryption_key: Option<&Arc<EncryptionKey>>,
+) -> Result<()> {
+    let persisted = PersistedPlanCache {
+        plans: plans
+            .iter()
+            .map(|(key, operator_orders)| CachedPlan {
+                query: key.query.clone(),
+                query_language: key.query_language,
+                document_filter: key.document_filter.clone(),
+                derived_operators: key.derived_operators,
+                operator_orders: operator_orders.clone(),
+            })
+            .collect(),
+    };
+    super::write_corpus_file(
+        corpus_dir,
+        &corpus_dir.join(PLAN_CACHE_FILE),
+        &toml::to_string(&persisted)?,
+        encryption_key,
+    )
+}
+
+fn remove(corpus_dir: &Path) {
+    let path = corpus_dir.join(PLAN_CACHE_FILE);
+    if path.is_file() {
+        if let Err(e) = std::fs::remove_file(&path) {
+            warn!(
+                "Could not remove outdated plan cache {}: {:?}",
+                path.to_string_lossy(),
+                e
+            );
+        }
+    }
+}
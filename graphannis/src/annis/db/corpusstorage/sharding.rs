@@ -0,0 +1,283 @@
+//! Helper functions to combine the results of the shards of a sharded corpus.
+
+use super::{AnnotationSchema, ComponentSchema, CorpusInfo, CorpusSchema, DiskUsage, LoadStatus};
+use crate::annis::errors::*;
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::AnnotationGraph;
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::{ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE, NODE_TYPE_KEY},
+    types::{Annotation, Edge},
+};
+use std::collections::{BTreeMap, HashSet};
+
+/// Get the path of the document a node belongs to, which is the part of the node name before the `#`.
+///
+/// Corpus and document nodes have no `#` in their name and the whole name is returned.
+pub(super) fn document_of_node_name(node_name: &str) -> &str {
+    &node_name[0..node_name.rfind('#').unwrap_or(node_name.len())]
+}
+
+/// Combine the graphs extracted from different shards into a single graph.
+///
+/// The node IDs of the shards are independent of each other, so the nodes are identified by their name. Nodes that
+/// exist in several shards (like the top-level corpus) are only included once.
+pub(super) fn merge_graphs(mut graphs: Vec<AnnotationGraph>) -> Result<AnnotationGraph> {
+    if graphs.len() == 1 {
+        if let Some(g) = graphs.pop() {
+            return Ok(g);
+        }
+    }
+
+    let mut update = GraphUpdate::new();
+    let mut added_nodes = HashSet::new();
+    for g in &graphs {
+        let node_annos = g.get_node_annos();
+        for m in node_annos.exact_anno_search(Some(ANNIS_NS), NODE_TYPE, ValueSearch::Any) {
+            let node_name = match node_annos.get_value_for_item(&m.node, &NODE_NAME_KEY) {
+                Some(node_name) => node_name.to_string(),
+                None => continue,
+            };
+            if !added_nodes.insert(node_name.clone()) {
+                continue;
+            }
+            let node_type = node_annos
+                .get_value_for_item(&m.node, &NODE_TYPE_KEY)
+                .unwrap_or_default();
+            update.add_event(UpdateEvent::AddNode {
+                node_name: node_name.clone(),
+                node_type: node_type.to_string(),
+            })?;
+            let mut added_keys = HashSet::new();
+            for a in node_annos.get_annotations_for_item(&m.node) {
+                if a.key.ns == ANNIS_NS && (a.key.name == NODE_NAME || a.key.name == NODE_TYPE) {
+                    continue;
+                }
+                if added_keys.insert(a.key.clone()) {
+                    update.add_event(UpdateEvent::AddNodeLabel {
+                        node_name: node_name.clone(),
+                        anno_ns: a.key.ns.to_string(),
+                        anno_name: a.key.name.to_string(),
+                        anno_value: a.val.to_string(),
+                    })?;
+                } else {
+                    // keep all values of annotations with multiple values
+                    update.add_event(UpdateEvent::AddNodeLabelValue {
+                        node_name: node_name.clone(),
+                        anno_ns: a.key.ns.to_string(),
+                        anno_name: a.key.name.to_string(),
+                        anno_value: a.val.to_string(),
+                    })?;
+                }
+            }
+        }
+    }
+    let mut added_edges = HashSet::new();
+    for g in &graphs {
+        let node_annos = g.get_node_annos();
+        for c in g.get_all_components(None, None) {
+            let gs = match g.get_graphstorage(&c) {
+                Some(gs) => gs,
+                None => continue,
+            };
+            for source in gs.source_nodes() {
+                for target in gs.get_outgoing_edges(source) {
+                    let (source_node, target_node) = match (
+                        node_annos.get_value_for_item(&source, &NODE_NAME_KEY),
+                        node_annos.get_value_for_item(&target, &NODE_NAME_KEY),
+                    ) {
+                        (Some(source_node), Some(target_node)) => {
+                            (source_node.to_string(), target_node.to_string())
+                        }
+                        _ => continue,
+                    };
+                    if !added_edges.insert((c.clone(), source_node.clone(), target_node.clone())) {
+                        continue;
+                    }
+                    update.add_event(UpdateEvent::AddEdge {
+                        source_node: source_node.clone(),
+                        target_node: target_node.clone(),
+                        layer: c.layer.to_string(),
+                        component_type: c.get_type().to_string(),
+                        component_name: c.name.to_string(),
+                    })?;
+                    for a in gs
+                        .get_anno_storage()
+                        .get_annotations_for_item(&Edge { source, target })
+                    {
+                        update.add_event(UpdateEvent::AddEdgeLabel {
+                            source_node: source_node.clone(),
+                            target_node: target_node.clone(),
+                            layer: c.layer.to_string(),
+                            component_type: c.get_type().to_string(),
+                            component_name: c.name.to_string(),
+                            anno_ns: a.key.ns.to_string(),
+                            anno_name: a.key.name.to_string(),
+                            anno_value: a.val.to_string(),
+                        })?;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut result = AnnotationGraph::new(false)?;
+    result.apply_update(&mut update, |_| {})?;
+    Ok(result)
+}
+
+/// Combine the information about all shards to the information about the sharded corpus.
+///
+/// The graph storages and the load report are specific to each shard and are not included.
+pub(super) fn combine_corpus_info(corpus_name: &str, shards: Vec<CorpusInfo>) -> CorpusInfo {
+    let sum = |values: Vec<Option<usize>>| -> Option<usize> { values.into_iter().sum() };
+
+    let mut memory_size = 0;
+    let mut fully_loaded = true;
+    let mut loaded = false;
+    for s in &shards {
+        match s.load_status {
+            LoadStatus::NotLoaded => fully_loaded = false,
+            LoadStatus::PartiallyLoaded(size) => {
+                fully_loaded = false;
+                loaded = true;
+                memory_size += size;
+            }
+            LoadStatus::FullyLoaded(size) => {
+                loaded = true;
+                memory_size += size;
+            }
+        }
+    }
+    let load_status = if !loaded {
+        LoadStatus::NotLoaded
+    } else if fully_loaded {
+        LoadStatus::FullyLoaded(memory_size)
+    } else {
+        LoadStatus::PartiallyLoaded(memory_size)
+    };
+
+    let disk_usage: Option<Vec<DiskUsage>> = shards.iter().map(|s| s.disk_usage.clone()).collect();
+    CorpusInfo {
+        name: corpus_name.to_string(),
+        load_status,
+        node_annos_load_size: sum(shards.iter().map(|s| s.node_annos_load_size).collect()),
+        graphstorages: Vec::new(),
+        config: shards.first().map(|s| s.config.clone()).unwrap_or_default(),
+        number_of_nodes: sum(shards.iter().map(|s| s.number_of_nodes).collect()),
+        estimated_memory_size: sum(shards.iter().map(|s| s.estimated_memory_size).collect()),
+        disk_usage: disk_usage.map(combine_disk_usage),
+        load_report: None,
+        node_annotation_usage: None,
+    }
+}
+
+/// Add the disk usage of all shards.
+pub(super) fn combine_disk_usage(shards: Vec<DiskUsage>) -> DiskUsage {
+    let mut components = BTreeMap::new();
+    let mut result = DiskUsage {
+        node_annotations: 0,
+        components: Vec::new(),
+        update_log: 0,
+        linked_files: 0,
+        backup: 0,
+    };
+    for s in shards {
+        result.node_annotations += s.node_annotations;
+        result.update_log += s.update_log;
+        result.linked_files += s.linked_files;
+        result.backup += s.backup;
+        for (c, size) in s.components {
+            *components.entry(c).or_insert(0) += size;
+        }
+    }
+    result.components = components.into_iter().collect();
+    result
+}
+
+/// Combine the schemas of all shards to the schema of the sharded corpus.
+///
+/// The number of distinct values is the maximum of the shards, since the same value can occur in several shards.
+/// Since each shard has its own graph storages, the implementation of the first shard is used and the
+/// statistics of the components are not included.
+pub(super) fn combine_schema(corpus_name: &str, shards: Vec<CorpusSchema>) -> CorpusSchema {
+    let mut node_annotations = Vec::new();
+    let mut components: BTreeMap<_, (ComponentSchema, Vec<Vec<AnnotationSchema>>)> =
+        BTreeMap::new();
+    let mut segmentations = Vec::new();
+    for s in shards {
+        node_annotations.push(s.node_annotations);
+        for c in s.components {
+            let key = (c.component_type.clone(), c.layer.clone(), c.name.clone());
+            let edge_annotations = c.edge_annotations.clone();
+            components
+                .entry(key)
+                .or_insert_with(|| {
+                    (
+                        ComponentSchema {
+                            statistics: None,
+                            edge_annotations: Vec::new(),
+                            ..c
+                        },
+                        Vec::new(),
+                    )
+                })
+                .1
+                .push(edge_annotations);
+        }
+        segmentations.extend(s.segmentations);
+    }
+    segmentations.sort();
+    segmentations.dedup();
+
+    CorpusSchema {
+        corpus_name: corpus_name.to_string(),
+        node_annotations: combine_annotation_schemas(node_annotations),
+        components: components
+            .into_iter()
+            .map(|(_, (c, edge_annotations))| ComponentSchema {
+                edge_annotations: combine_annotation_schemas(edge_annotations),
+                ..c
+            })
+            .collect(),
+        segmentations,
+    }
+}
+
+fn combine_annotation_schemas(shards: Vec<Vec<AnnotationSchema>>) -> Vec<AnnotationSchema> {
+    let mut result: BTreeMap<(String, String), AnnotationSchema> = BTreeMap::new();
+    for schema in shards.into_iter().flatten() {
+        let key = (schema.ns.clone(), schema.name.clone());
+        if let Some(existing) = result.get_mut(&key) {
+            existing.count += schema.count;
+            existing.distinct_values = existing.distinct_values.max(schema.distinct_values);
+            for v in schema.example_values {
+                if existing.example_values.len() < super::MAX_SCHEMA_EXAMPLE_VALUES
+                    && !existing.example_values.contains(&v)
+                {
+                    existing.example_values.push(v);
+                }
+            }
+        } else {
+            result.insert(key, schema);
+        }
+    }
+    result.into_values().collect()
+}
+
+/// Combine the node or edge annotations listed for each shard.
+///
+/// If `only_most_frequent_values` is set, only the first value of each annotation key is kept.
+pub(super) fn combine_annotations(
+    shards: Vec<Vec<Annotation>>,
+    only_most_frequent_values: bool,
+) -> Vec<Annotation> {
+    let mut result: Vec<Annotation> = shards.into_iter().flatten().collect();
+    if only_most_frequent_values {
+        let mut seen_keys = HashSet::new();
+        result.retain(|a| seen_keys.insert(a.key.clone()));
+    }
+    result.sort();
+    result.dedup();
+    result
+}
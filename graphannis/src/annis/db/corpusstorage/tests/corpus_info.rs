@@ -0,0 +1,793 @@
+use crate::annis::db::{aql::model::AnnotationComponentType, example_generator};
+use crate::corpusstorage::{
+    FrequencyDefEntry, ImportFormat, QueryLanguage, SearchQuery, SegmentationDefinition,
+};
+use crate::errors::{CorpusStorageError, GraphAnnisError};
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+use graphannis_core::{
+    graph::DEFAULT_NS,
+    types::{Annotation, NodeID},
+};
+
+use super::simple_corpus;
+
+#[test]
+fn subgraph_with_segmentation() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    // Add corpus structure
+    example_generator::create_corpus_structure_simple(&mut g);
+    // Use the default tokenization as minimal tokens
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+
+    // Add first segmentation
+    let seg_tokens = vec![
+        "Is this example",
+        "more complicated",
+        "than it appears to be",
+        "?",
+    ];
+    for (i, t) in seg_tokens.iter().enumerate() {
+        let node_name = format!("root/doc1#seg{}", i);
+        example_generator::create_token_node(&mut g, &node_name, t, Some("root/doc1"));
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: node_name,
+            anno_ns: "default_ns".to_string(),
+            anno_name: "seg".to_string(),
+            anno_value: t.to_string(),
+        })
+        .unwrap();
+    }
+    for i in 0..seg_tokens.len() {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: format!("root/doc1#seg{}", i),
+            target_node: format!("root/doc1#seg{}", i + 1),
+            layer: DEFAULT_NS.to_string(),
+            component_type: "Ordering".to_string(),
+            component_name: "seg".to_string(),
+        })
+        .unwrap();
+    }
+    // add coverage for seg
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#seg0",
+        &["root/doc1#tok0", "root/doc1#tok1", "root/doc1#tok2"],
+    );
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#seg1",
+        &["root/doc1#tok3", "root/doc1#tok4"],
+    );
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#seg2",
+        &[
+            "root/doc1#tok5",
+            "root/doc1#tok6",
+            "root/doc1#tok7",
+            "root/doc1#tok8",
+            "root/doc1#tok9",
+        ],
+    );
+    example_generator::make_span(&mut g, "root/doc1#seg3", &["root/doc1#tok10"]);
+
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "node .seg,1,2 node",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    assert_eq!(5, cs.count(query).unwrap());
+
+    // get the subgraph with context 1 on dipl
+    let graph = cs
+        .subgraph(
+            "root",
+            vec!["root/doc1#seg1".to_string()],
+            1,
+            1,
+            Some("seg".to_string()),
+        )
+        .unwrap();
+
+    let cov_components = graph.get_all_components(Some(AnnotationComponentType::Coverage), None);
+    assert_eq!(1, cov_components.len());
+
+    let gs_cov = graph.get_graphstorage(&cov_components[0]).unwrap();
+
+    let segl0_id = graph.get_node_id_from_name("root/doc1#seg0").unwrap();
+    let seg0_out: Vec<NodeID> = gs_cov.get_outgoing_edges(segl0_id).collect();
+    assert_eq!(3, seg0_out.len());
+
+    let seg1_id = graph.get_node_id_from_name("root/doc1#seg1").unwrap();
+    let seg1_out: Vec<NodeID> = gs_cov.get_outgoing_edges(seg1_id).collect();
+    assert_eq!(2, seg1_out.len());
+
+    let seg2_id = graph.get_node_id_from_name("root/doc1#seg2").unwrap();
+    let seg2_out: Vec<NodeID> = gs_cov.get_outgoing_edges(seg2_id).collect();
+    assert_eq!(5, seg2_out.len());
+
+    assert_eq!(None, graph.get_node_id_from_name("root/doc1#seg3"));
+}
+
+#[test]
+fn node_ids_from_names() {
+    let (_tmp, cs) = simple_corpus();
+
+    let ids = cs
+        .node_ids_from_names(
+            "root",
+            &["root/doc1#tok1", "root/doc1#missing", "root/doc1#tok1"],
+        )
+        .unwrap();
+    assert_eq!(3, ids.len());
+    assert!(ids[0].is_some());
+    assert_eq!(None, ids[1]);
+    assert_eq!(ids[0], ids[2]);
+}
+
+#[test]
+fn nodes_by_id() {
+    let (_tmp, cs) = simple_corpus();
+
+    let generation = cs.node_id_generation("root").unwrap();
+    // the generation is stable as long as no node is deleted
+    assert_eq!(generation, cs.node_id_generation("root").unwrap());
+
+    let ids: Vec<NodeID> = cs
+        .node_ids_from_names("root", &["root/doc1#tok1", "root/doc1#tok2"])
+        .unwrap()
+        .into_iter()
+        .map(|id| id.unwrap())
+        .collect();
+    let names = cs
+        .node_names_from_ids("root", &[ids[0], ids[1], 1_000_000], generation)
+        .unwrap();
+    assert_eq!(
+        vec![
+            Some("root/doc1#tok1".to_string()),
+            Some("root/doc1#tok2".to_string()),
+            None
+        ],
+        names
+    );
+    let range = cs
+        .nodes_in_id_range("root", ids[0]..(ids[1] + 1), generation)
+        .unwrap();
+    assert_eq!(
+        vec![
+            (ids[0], "root/doc1#tok1".to_string()),
+            (ids[1], "root/doc1#tok2".to_string())
+        ],
+        range
+    );
+    // corpus, document and 11 token
+    assert_eq!(
+        13,
+        cs.nodes_in_id_range("root", 0..NodeID::max_value(), generation)
+            .unwrap()
+            .len()
+    );
+
+    // deleting a node starts a new generation
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::DeleteNode {
+        node_name: "root/doc1#tok10".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+    assert_ne!(generation, cs.node_id_generation("root").unwrap());
+    assert!(cs
+        .node_names_from_ids("root", &[ids[0]], generation)
+        .is_err());
+}
+
+#[test]
+fn suggest() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (token, pos) in [
+        ("tok2", "NN"),
+        ("tok6", "PPER"),
+        ("tok7", "VVFIN"),
+        ("tok9", "VAINF"),
+    ]
+    .iter()
+    {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#{}", token),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#tok1".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "pos".to_string(),
+        anno_value: "VVFIN".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+
+    let suggestions = cs.suggest("root", "po", 10).unwrap();
+    assert_eq!(1, suggestions.len());
+    assert_eq!("pos", suggestions[0].key.name);
+    assert_eq!(None, suggestions[0].value);
+    assert_eq!(5, suggestions[0].count);
+
+    let suggestions = cs.suggest("root", "default_ns:p", 10).unwrap();
+    assert_eq!(1, suggestions.len());
+    assert_eq!("pos", suggestions[0].key.name);
+
+    // the most frequent value is suggested first and the limit is applied
+    let suggestions = cs.suggest("root", "V", 1).unwrap();
+    assert_eq!(1, suggestions.len());
+    assert_eq!(Some("VVFIN".to_string()), suggestions[0].value);
+    assert_eq!(2, suggestions[0].count);
+    let values: Vec<_> = cs
+        .suggest("root", "V", 10)
+        .unwrap()
+        .into_iter()
+        .filter_map(|s| s.value)
+        .collect();
+    assert_eq!(vec!["VVFIN".to_string(), "VAINF".to_string()], values);
+
+    // node names are not suggested
+    assert!(cs.suggest("root", "root/doc1#", 10).unwrap().is_empty());
+    // an empty prefix only suggests annotation names
+    assert!(cs
+        .suggest("root", "", 100)
+        .unwrap()
+        .iter()
+        .all(|s| s.value.is_none()));
+}
+
+#[test]
+fn corpus_schema() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for i in 0..3 {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: format!("root/doc1#seg{}", i),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#seg{}", i),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "seg".to_string(),
+            anno_value: if i == 2 { "b" } else { "a" }.to_string(),
+        })
+        .unwrap();
+    }
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc1#seg0".to_string(),
+        target_node: "root/doc1#seg1".to_string(),
+        layer: "default_ns".to_string(),
+        component_type: "Ordering".to_string(),
+        component_name: "seg".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+
+    let schema = cs.schema("root").unwrap();
+    assert_eq!("root", schema.corpus_name);
+    assert_eq!(vec!["seg".to_string()], schema.segmentations);
+
+    let seg_anno = schema
+        .node_annotations
+        .iter()
+        .find(|a| a.ns == "default_ns" && a.name == "seg")
+        .unwrap();
+    assert_eq!(3, seg_anno.count);
+    assert_eq!(2, seg_anno.distinct_values);
+    assert_eq!(
+        vec!["a".to_string(), "b".to_string()],
+        seg_anno.example_values
+    );
+
+    let tok_anno = schema
+        .node_annotations
+        .iter()
+        .find(|a| a.ns == "annis" && a.name == "tok")
+        .unwrap();
+    assert_eq!(11, tok_anno.count);
+    assert_eq!(5, tok_anno.example_values.len());
+
+    assert!(schema
+        .components
+        .iter()
+        .any(|c| c.component_type == AnnotationComponentType::Ordering && c.name == "seg"));
+}
+
+#[test]
+fn corpus_schema_estimates_large_annotations() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    for i in 0..1500 {
+        let node_name = format!("root/doc1#n{}", i);
+        g.add_event(UpdateEvent::AddNode {
+            node_name: node_name.clone(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        let pos = match i % 10 {
+            0..=5 => "NN",
+            6..=8 => "DT",
+            _ => "IN",
+        };
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name,
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let schema = cs.schema("root").unwrap();
+    let anno_schema = |ns: &str, name: &str| {
+        schema
+            .node_annotations
+            .iter()
+            .find(|a| a.ns == ns && a.name == name)
+            .unwrap()
+            .clone()
+    };
+
+    let pos_anno = anno_schema("default_ns", "pos");
+    assert_eq!(1500, pos_anno.count);
+    assert_eq!(3, pos_anno.distinct_values);
+    assert_eq!(vec!["NN", "DT", "IN"], pos_anno.example_values);
+
+    // each node has a different name
+    let node_name_anno = anno_schema("annis", "node_name");
+    assert!(node_name_anno.count > 1500);
+    assert_eq!(node_name_anno.count, node_name_anno.distinct_values);
+    assert_eq!(5, node_name_anno.example_values.len());
+}
+
+#[test]
+fn subgraph_for_several_nodes() {
+    let (_tmp, cs) = simple_corpus();
+
+    let graph = cs
+        .subgraph(
+            "root",
+            vec![
+                "root/doc1#tok1".to_string(),
+                "salt:/root/doc1#tok8".to_string(),
+                "root/doc1#tok8".to_string(),
+            ],
+            1,
+            1,
+            None,
+        )
+        .unwrap();
+
+    for included in &[0, 1, 2, 7, 8, 9] {
+        let node_name = format!("root/doc1#tok{}", included);
+        assert!(graph.get_node_id_from_name(&node_name).is_some());
+    }
+    for excluded in &[3, 4, 5, 6, 10] {
+        let node_name = format!("root/doc1#tok{}", excluded);
+        assert_eq!(None, graph.get_node_id_from_name(&node_name));
+    }
+}
+
+#[test]
+fn infer_segmentations() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    // the tokens only form a timeline and have no text
+    for i in 0..4 {
+        example_generator::create_token_node(
+            &mut g,
+            &format!("root/doc1#t{}", i),
+            "",
+            Some("root/doc1"),
+        );
+    }
+    let add_ordering = |g: &mut GraphUpdate, name: &str, nodes: &[&str]| {
+        for pair in nodes.windows(2) {
+            g.add_event(UpdateEvent::AddEdge {
+                source_node: pair[0].to_string(),
+                target_node: pair[1].to_string(),
+                layer: "default_ns".to_string(),
+                component_type: "Ordering".to_string(),
+                component_name: name.to_string(),
+            })
+            .unwrap();
+        }
+    };
+    add_ordering(
+        &mut g,
+        "",
+        &[
+            "root/doc1#t0",
+            "root/doc1#t1",
+            "root/doc1#t2",
+            "root/doc1#t3",
+        ],
+    );
+    let add_span = |g: &mut GraphUpdate, name: &str, annos: &[(&str, &str)], covered: &[&str]| {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: name.to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        for (anno_name, anno_value) in annos {
+            g.add_event(UpdateEvent::AddNodeLabel {
+                node_name: name.to_string(),
+                anno_ns: "default_ns".to_string(),
+                anno_name: anno_name.to_string(),
+                anno_value: anno_value.to_string(),
+            })
+            .unwrap();
+        }
+        example_generator::make_span(g, name, covered);
+    };
+    add_span(
+        &mut g,
+        "root/doc1#dipl0",
+        &[("dipl", "Isz")],
+        &["root/doc1#t0", "root/doc1#t1"],
+    );
+    add_span(
+        &mut g,
+        "root/doc1#dipl1",
+        &[("dipl", "dis")],
+        &["root/doc1#t2", "root/doc1#t3"],
+    );
+    add_ordering(&mut g, "dipl", &["root/doc1#dipl0", "root/doc1#dipl1"]);
+    // the annotation with the name of the segmentation is preferred
+    add_span(
+        &mut g,
+        "root/doc1#norm0",
+        &[("norm", "Is"), ("pos", "VAFIN")],
+        &["root/doc1#t0"],
+    );
+    add_span(
+        &mut g,
+        "root/doc1#norm1",
+        &[("norm", "this"), ("pos", "PDS")],
+        &["root/doc1#t1", "root/doc1#t2", "root/doc1#t3"],
+    );
+    add_ordering(&mut g, "norm", &["root/doc1#norm0", "root/doc1#norm1"]);
+    // spans without a common annotation are no segmentation
+    add_span(&mut g, "root/doc1#other0", &[("a", "1")], &["root/doc1#t0"]);
+    add_span(&mut g, "root/doc1#other1", &[("b", "2")], &["root/doc1#t1"]);
+    add_ordering(&mut g, "other", &["root/doc1#other0", "root/doc1#other1"]);
+    cs.apply_update("root", &mut g).unwrap();
+
+    let segmentations = cs.infer_segmentations("root").unwrap();
+    assert_eq!(
+        vec![
+            SegmentationDefinition {
+                name: "dipl".to_string(),
+                anno_ns: "default_ns".to_string(),
+                anno_name: "dipl".to_string(),
+            },
+            SegmentationDefinition {
+                name: "norm".to_string(),
+                anno_ns: "default_ns".to_string(),
+                anno_name: "norm".to_string(),
+            },
+        ],
+        segmentations
+    );
+
+    let config = cs.info("root").unwrap().config;
+    assert_eq!(segmentations, config.segmentations);
+    assert_eq!(Some("dipl"), config.context.segmentation.as_deref());
+    assert_eq!(Some("dipl"), config.view.base_text_segmentation.as_deref());
+
+    // the segmentation can be used for the context
+    let subgraph = cs
+        .subgraph(
+            "root",
+            vec!["root/doc1#dipl1".to_string()],
+            1,
+            1,
+            config.context.segmentation,
+        )
+        .unwrap();
+    assert!(subgraph.get_node_id_from_name("root/doc1#dipl0").is_some());
+}
+
+#[test]
+fn document_term_matrix() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (i, pos) in ["VBZ", "DT", "NN", "DT"].iter().enumerate() {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#tok{}", i),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = || SearchQuery {
+        corpus_names: &["root"],
+        query: "tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let def: FrequencyDefEntry = "1:pos".parse().unwrap();
+
+    let result = cs.document_term_matrix(query(), &def).unwrap();
+    let result: Vec<(&str, &str, usize)> = result
+        .iter()
+        .map(|c| (c.document.as_str(), c.value.as_str(), c.count))
+        .collect();
+    assert_eq!(
+        vec![
+            ("root/doc1", "DT", 2),
+            ("root/doc1", "NN", 1),
+            ("root/doc1", "VBZ", 1)
+        ],
+        result
+    );
+
+    let mut output = Vec::new();
+    assert_eq!(
+        3,
+        cs.export_document_term_matrix(query(), &def, &mut output)
+            .unwrap()
+    );
+    assert_eq!(
+        "document,value,count\nroot/doc1,DT,2\nroot/doc1,NN,1\nroot/doc1,VBZ,1\n",
+        String::from_utf8(output).unwrap()
+    );
+
+    let def: FrequencyDefEntry = "2:pos".parse().unwrap();
+    assert!(matches!(
+        cs.document_term_matrix(query(), &def),
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::UnknownQueryNode(_)
+        ))
+    ));
+}
+
+#[test]
+fn node_annotation_usage() {
+    let (_tmp, cs) = simple_corpus();
+
+    let info = cs.info("root").unwrap();
+    let usage = info.node_annotation_usage.unwrap();
+    let tok_usage = usage
+        .iter()
+        .find(|u| u.key.ns == "annis" && u.key.name == "tok")
+        .unwrap();
+    assert_eq!(11, tok_usage.count);
+    assert_eq!(11, tok_usage.distinct_values);
+    assert!(tok_usage.bytes > 0);
+    // node names are unique
+    let node_name_usage = usage
+        .iter()
+        .find(|u| u.key.ns == "annis" && u.key.name == "node_name")
+        .unwrap();
+    assert_eq!(node_name_usage.count, node_name_usage.distinct_values);
+}
+
+#[test]
+fn document_statistics() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let input_dir = tempfile::tempdir().unwrap();
+    let corpus_dir = input_dir.path().join("treebank");
+    std::fs::create_dir(&corpus_dir).unwrap();
+    std::fs::write(
+        corpus_dir.join("a.conllu"),
+        "1\tThe\tthe\tDET\t_\t_\t2\tdet\t_\t_\n\
+         2\tdog\tdog\tNOUN\t_\tNumber=Sing\t0\troot\t_\t_\n\
+         \n\
+         1\tDogs\tdog\tNOUN\t_\tNumber=Plur\t0\troot\t_\t_\n\
+         \n",
+    )
+    .unwrap();
+    std::fs::write(
+        corpus_dir.join("b.conllu"),
+        "1\tYes\tyes\tINTJ\t_\t_\t0\troot\t_\t_\n\n",
+    )
+    .unwrap();
+    cs.import_from_fs(
+        &corpus_dir,
+        ImportFormat::CoNLLU,
+        None,
+        false,
+        false,
+        |_| {},
+    )
+    .unwrap();
+
+    // the statistics have been persisted by the import
+    let statistics_file = tmp.path().join("treebank").join("document-statistics.toml");
+    assert!(statistics_file.is_file());
+
+    let documents = cs.document_statistics("treebank").unwrap();
+    assert_eq!(2, documents.len());
+    assert_eq!("treebank/a", documents[0].document);
+    assert_eq!(3, documents[0].counts.tokens);
+    assert_eq!(Some(&3), documents[0].counts.annotations.get("conll::upos"));
+    assert_eq!(
+        Some(&2),
+        documents[0].counts.annotations.get("conll::Number")
+    );
+    assert_eq!(
+        Some(&2),
+        documents[0].counts.annotations.get("conll::sent_id")
+    );
+    assert_eq!("treebank/b", documents[1].document);
+    assert_eq!(1, documents[1].counts.tokens);
+    assert_eq!(None, documents[1].counts.annotations.get("conll::Number"));
+
+    let all = cs
+        .aggregated_document_statistics::<&str>("treebank", &[])
+        .unwrap();
+    assert_eq!(4, all.tokens);
+    assert_eq!(Some(&4), all.annotations.get("conll::lemma"));
+    assert_eq!(Some(250_000.0), all.per_million_tokens(1));
+    let only_b = cs
+        .aggregated_document_statistics("treebank", &["treebank/b"])
+        .unwrap();
+    assert_eq!(1, only_b.tokens);
+
+    // updates invalidate the statistics and they are calculated again on demand
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "treebank/b#t1".to_string(),
+        anno_ns: "".to_string(),
+        anno_name: "sentiment".to_string(),
+        anno_value: "positive".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "treebank/a#t1".to_string(),
+        target_node: "treebank/a#t2".to_string(),
+        layer: "annis".to_string(),
+        component_type: AnnotationComponentType::Ordering.to_string(),
+        component_name: "phrase".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("treebank", &mut g).unwrap();
+    assert!(!statistics_file.is_file());
+
+    let documents = cs.document_statistics("treebank").unwrap();
+    assert!(statistics_file.is_file());
+    assert_eq!(Some(&2), documents[0].counts.segments.get("phrase"));
+    assert_eq!(Some(&1), documents[1].counts.annotations.get("sentiment"));
+}
+
+#[test]
+fn list_corpus_and_document_metadata() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (node_name, anno_name, anno_value) in &[
+        ("root", "license", "CC-BY"),
+        ("root/doc1", "genre", "news"),
+        ("root/doc1", "author", "someone"),
+    ] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: node_name.to_string(),
+            anno_ns: "default_ns".to_string(),
+            anno_name: anno_name.to_string(),
+            anno_value: anno_value.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let as_tuples = |annos: Vec<Annotation>| -> Vec<(String, String)> {
+        annos
+            .into_iter()
+            .map(|a| (a.key.name.to_string(), a.val.to_string()))
+            .collect()
+    };
+
+    assert_eq!(
+        vec![("license".to_string(), "CC-BY".to_string())],
+        as_tuples(cs.list_corpus_metadata("root").unwrap())
+    );
+    assert_eq!(
+        vec![
+            ("author".to_string(), "someone".to_string()),
+            ("genre".to_string(), "news".to_string())
+        ],
+        as_tuples(cs.list_document_metadata("root", "root/doc1").unwrap())
+    );
+    // tokens and unknown nodes are not documents
+    assert!(cs.list_document_metadata("root", "root/doc1#tok0").is_err());
+    assert!(cs.list_document_metadata("root", "root/unknown").is_err());
+    assert!(cs.list_corpus_metadata("unknown").is_err());
+}
+
+#[test]
+fn search_node_annotation_values() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (token, pos) in [
+        ("tok1", "VVFIN"),
+        ("tok2", "NN"),
+        ("tok6", "PPER"),
+        ("tok7", "VVFIN"),
+        ("tok9", "VAINF"),
+    ]
+    .iter()
+    {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#{}", token),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let values = cs
+        .search_node_annotation_values("root", None, "pos", "V.*", None)
+        .unwrap();
+    assert_eq!(2, values.len());
+    assert_eq!("VVFIN", values[0].value);
+    assert_eq!(2, values[0].count);
+    assert_eq!("default_ns", values[0].key.ns);
+    assert_eq!("VAINF", values[1].value);
+    assert_eq!(1, values[1].count);
+
+    let values = cs
+        .search_node_annotation_values("root", Some("default_ns"), "pos", "V.*", Some(1))
+        .unwrap();
+    assert_eq!(1, values.len());
+    assert_eq!("VVFIN", values[0].value);
+
+    assert!(cs
+        .search_node_annotation_values("root", Some("other_ns"), "pos", "V.*", None)
+        .unwrap()
+        .is_empty());
+    assert!(cs
+        .search_node_annotation_values("root", None, "pos", "V(", None)
+        .is_err());
+}
@@ -0,0 +1,431 @@
+use crate::annis::db::example_generator;
+use crate::corpusstorage::{
+    ExportFormat, ImportFormat, NodeNameScheme, NodeNameTemplate, QueryLanguage, ResultOrder,
+    SearchQuery, WhitespaceHandling,
+};
+use crate::errors::{CorpusStorageError, GraphAnnisError};
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+use graphannis_core::types::Annotation;
+use std::sync::Arc;
+
+use super::simple_corpus;
+
+#[test]
+fn two_phase_import() {
+    let (_tmp, cs) = simple_corpus();
+
+    let export_dir = tempfile::tempdir().unwrap();
+    let graphml_file = export_dir.path().join("root.graphml");
+    cs.export_to_fs(&["root"], &graphml_file, ExportFormat::GraphML)
+        .unwrap();
+
+    let pending = cs
+        .analyze_import_from_fs(&graphml_file, ImportFormat::GraphML, false, |_| {})
+        .unwrap();
+    assert_eq!("root", pending.analysis.corpus_name);
+    assert!(pending.analysis.conflicts_with_existing);
+    assert!(pending.analysis.estimated_node_count > 11);
+    assert!(pending.analysis.estimated_edge_count > 0);
+
+    // Nothing has been written to the data directory yet
+    assert_eq!(1, cs.list().unwrap().len());
+
+    let name = cs
+        .commit_import(pending, Some("copy".to_string()), false)
+        .unwrap();
+    assert_eq!("copy", name);
+
+    let mut corpora: Vec<String> = cs.list().unwrap().into_iter().map(|c| c.name).collect();
+    corpora.sort();
+    assert_eq!(vec!["copy", "root"], corpora);
+}
+
+struct WithoutDocument;
+
+impl NodeNameScheme for WithoutDocument {
+    fn node_name(
+        &self,
+        _document: &str,
+        fragment: &str,
+        _annotations: &[Annotation],
+    ) -> Option<String> {
+        Some(fragment.to_string())
+    }
+}
+
+#[test]
+fn import_with_node_name_scheme() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for i in 0..11 {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#tok{}", i),
+            anno_ns: "ext".to_string(),
+            anno_name: "id".to_string(),
+            anno_value: format!("w{}", i + 100),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let export_dir = tempfile::tempdir().unwrap();
+    let graphml_file = export_dir.path().join("root.graphml");
+    cs.export_to_fs(&["root"], &graphml_file, ExportFormat::GraphML)
+        .unwrap();
+
+    // a scheme that creates the same name for all token must fail
+    cs.set_node_name_scheme(Some(Arc::new(
+        NodeNameTemplate::new("{document}#token").unwrap(),
+    )));
+    let result = cs.analyze_import_from_fs(&graphml_file, ImportFormat::GraphML, false, |_| {});
+    assert!(matches!(
+        result,
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::NodeNameCollision { .. }
+        ))
+    ));
+
+    // a scheme that moves the nodes out of their document must fail
+    cs.set_node_name_scheme(Some(Arc::new(WithoutDocument)));
+    let result = cs.analyze_import_from_fs(&graphml_file, ImportFormat::GraphML, false, |_| {});
+    assert!(matches!(
+        result,
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::NodeNameOutsideDocument { .. }
+        ))
+    ));
+
+    cs.set_node_name_scheme(Some(Arc::new(
+        NodeNameTemplate::new("{document}#{ext::id}").unwrap(),
+    )));
+    let pending = cs
+        .analyze_import_from_fs(&graphml_file, ImportFormat::GraphML, false, |_| {})
+        .unwrap();
+    cs.commit_import(pending, Some("renamed".to_string()), false)
+        .unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["renamed"],
+        query: "tok=\"example\" . tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let result = cs.find(query, 0, None, ResultOrder::Normal).unwrap();
+    assert_eq!(vec!["root/doc1#w102 root/doc1#w103"], result);
+}
+
+#[test]
+fn concurrent_import_from_zip() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    for corpus in &["a", "b", "c"] {
+        let mut g = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut g);
+        example_generator::create_tokens(&mut g, Some("root/doc1"));
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+    let export_dir = tempfile::tempdir().unwrap();
+    let zip_file = export_dir.path().join("corpora.zip");
+    cs.export_to_fs(&["a", "b", "c"], &zip_file, ExportFormat::GraphMLZip)
+        .unwrap();
+
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    let messages = std::sync::Mutex::new(Vec::new());
+    let mut corpus_names = cs
+        .import_all_from_zip(
+            std::fs::File::open(&zip_file).unwrap(),
+            false,
+            false,
+            Some(2),
+            |status| messages.lock().unwrap().push(status.to_string()),
+        )
+        .unwrap();
+    corpus_names.sort();
+    assert_eq!(vec!["a", "b", "c"], corpus_names);
+    let messages = messages.into_inner().unwrap();
+    assert!(messages.iter().any(|m| m.ends_with("(3 of 3)")));
+
+    for corpus in &["a", "b", "c"] {
+        let query = SearchQuery {
+            corpus_names: &[corpus],
+            query: "tok",
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        };
+        assert_eq!(11, cs.count(query).unwrap());
+    }
+}
+
+#[test]
+fn export_matched_documents() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    for doc in &["root/doc2", "root/doc3"] {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: doc.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: doc.to_string(),
+            target_node: "root".to_string(),
+            layer: "annis".to_string(),
+            component_type: "PartOf".to_string(),
+            component_name: "".to_string(),
+        })
+        .unwrap();
+    }
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    example_generator::create_tokens(&mut g, Some("root/doc3"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    let export_dir = tempfile::tempdir().unwrap();
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok=\"example\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let exported = cs
+        .export_matched_documents(query, export_dir.path())
+        .unwrap();
+    assert_eq!(vec!["root/doc1", "root/doc3"], exported);
+    assert!(export_dir.path().join("root/doc1.graphml").is_file());
+    assert!(!export_dir.path().join("root/doc2.graphml").exists());
+
+    // The exported document contains all tokens
+    cs.import_from_fs(
+        &export_dir.path().join("root/doc3.graphml"),
+        ImportFormat::GraphML,
+        Some("doc3".to_string()),
+        false,
+        false,
+        |_| {},
+    )
+    .unwrap();
+    let query = SearchQuery {
+        corpus_names: &["doc3"],
+        query: "tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(11, cs.count(query).unwrap());
+
+    // document paths must not point outside the export directory
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "evil".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "evil/../../doc".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "evil/../../doc".to_string(),
+        target_node: "evil".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    example_generator::create_tokens(&mut g, Some("evil/../../doc"));
+    cs.apply_update("evil", &mut g).unwrap();
+    let export_dir = tempfile::tempdir().unwrap();
+    let result = cs.export_matched_documents(
+        SearchQuery {
+            corpus_names: &["evil"],
+            query: "tok=\"example\"",
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        },
+        &export_dir.path().join("nested"),
+    );
+    assert!(matches!(
+        result,
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::InvalidDocumentExportPath(_)
+        ))
+    ));
+    assert!(!export_dir.path().join("doc.graphml").exists());
+}
+
+#[test]
+fn export_match_contexts() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let mut output = Vec::new();
+    assert_eq!(
+        2,
+        cs.export_match_contexts(
+            query("tok=/(this|it)/"),
+            2,
+            WhitespaceHandling::SingleSpace,
+            &mut output
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        "root/doc1\tIs\tthis\texample more\nroot/doc1\tcomplicated than\tit\tappears to\n",
+        String::from_utf8(output).unwrap()
+    );
+
+    // the matched text spans all matched nodes and the context is cut at the borders of the text
+    let mut output = Vec::new();
+    assert_eq!(
+        1,
+        cs.export_match_contexts(
+            query("tok=\"to\" & tok=\"appears\" & #2 . #1"),
+            3,
+            WhitespaceHandling::SingleSpace,
+            &mut output
+        )
+        .unwrap()
+    );
+    assert_eq!(
+        "root/doc1\tcomplicated than it\tappears to\tbe ?\n",
+        String::from_utf8(output).unwrap()
+    );
+
+    // without context, the left and right context columns are empty
+    let mut output = Vec::new();
+    assert_eq!(
+        1,
+        cs.export_match_contexts(
+            query("tok=\"Is\""),
+            0,
+            WhitespaceHandling::SingleSpace,
+            &mut output
+        )
+        .unwrap()
+    );
+    assert_eq!("root/doc1\t\tIs\t\n", String::from_utf8(output).unwrap());
+}
+
+#[test]
+fn import_conllu() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let input_dir = tempfile::tempdir().unwrap();
+    let input_file = input_dir.path().join("ud-example.conllu");
+    std::fs::write(
+        &input_file,
+        "# sent_id = s1\n\
+         # text = The dog barks.\n\
+         1\tThe\tthe\tDET\tDT\tDefinite=Def|PronType=Art\t2\tdet\t_\t_\n\
+         2\tdog\tdog\tNOUN\tNN\tNumber=Sing\t3\tnsubj\t_\t_\n\
+         3\tbarks\tbark\tVERB\tVBZ\tMood=Ind|Number=Sing\t0\troot\t_\tSpaceAfter=No\n\
+         4\t.\t.\tPUNCT\t.\t_\t3\tpunct\t_\t_\n\
+         \n\
+         # sent_id = s2\n\
+         1-2\tIt's\t_\t_\t_\t_\t_\t_\t_\t_\n\
+         1\tIt\tit\tPRON\tPRP\tCase=Nom\t2\tnsubj\t_\t_\n\
+         2\t's\tbe\tAUX\tVBZ\t_\t0\troot\t_\t_\n\
+         \n",
+    )
+    .unwrap();
+
+    let corpus_name = cs
+        .import_from_fs(
+            &input_file,
+            ImportFormat::CoNLLU,
+            None,
+            false,
+            false,
+            |_| {},
+        )
+        .unwrap();
+    assert_eq!("ud-example", corpus_name);
+
+    let count = |aql: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["ud-example"],
+            query: aql,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    };
+    assert_eq!(6, count("tok"));
+    assert_eq!(1, count("upos=\"NOUN\""));
+    assert_eq!(1, count("lemma=\"be\""));
+    assert_eq!(2, count("Number=\"Sing\""));
+    assert_eq!(1, count("Case=\"Nom\""));
+    assert_eq!(2, count("tok ->dep[deprel=\"nsubj\"] tok"));
+    assert_eq!(1, count("upos=\"VERB\" ->dep upos=\"NOUN\""));
+    assert_eq!(
+        3,
+        count("tok ->dep tok=\".\" | tok ->dep tok=\"dog\" | tok ->dep tok=\"The\"")
+    );
+    assert_eq!(4, count("sent_id=\"s1\" _i_ tok"));
+    assert_eq!(1, count("text=\"The dog barks.\""));
+    assert_eq!(1, count("tok=\"barks\" . tok=\".\""));
+    assert_eq!(1, count("tok=\".\" . tok=\"It\""));
+    assert_eq!(6, count("annis:doc=\"ud-example\" @^ tok"));
+}
+
+#[test]
+fn throttled_import_progress() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let input_dir = tempfile::tempdir().unwrap();
+    let input_file = input_dir.path().join("progress.conllu");
+    std::fs::write(
+        &input_file,
+        "1\tA\ta\tDET\tDT\t_\t2\tdet\t_\t_\n\
+         2\tdog\tdog\tNOUN\tNN\t_\t0\troot\t_\t_\n\
+         \n",
+    )
+    .unwrap();
+
+    let import = |cs: &CorpusStorage| {
+        let messages = std::cell::RefCell::new(Vec::new());
+        cs.import_from_fs(
+            &input_file,
+            ImportFormat::CoNLLU,
+            None,
+            false,
+            true,
+            |status| messages.borrow_mut().push(status.to_string()),
+        )
+        .unwrap();
+        messages.into_inner()
+    };
+
+    let all_messages = import(&cs);
+    assert!(all_messages.len() > 2);
+
+    // only the first and the last message are reported if the interval is long enough
+    cs.set_progress_interval(std::time::Duration::from_secs(3600));
+    let throttled_messages = import(&cs);
+    assert_eq!(2, throttled_messages.len());
+    assert_eq!(all_messages.first(), throttled_messages.first());
+    assert_eq!(all_messages.last(), throttled_messages.last());
+}
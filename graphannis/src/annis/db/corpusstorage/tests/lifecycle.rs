@@ -0,0 +1,858 @@
+use crate::annis::db::{aql::model::AnnotationComponentType, example_generator};
+use crate::corpusstorage::{
+    CacheStrategy, EvictionPolicy, LoadStatus, LockingStrategy, QueryLanguage, ResultOrder,
+    SearchQuery, StatisticsRefresh,
+};
+use crate::errors::{CorpusStorageError, GraphAnnisError};
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::{AnnotationGraph, CorpusStorage};
+use graphannis_core::util::encryption::{self, EncryptionKey};
+use graphannis_core::{
+    annostorage::ValueSearch,
+    errors::GraphAnnisCoreError,
+    graph::{ANNIS_NS, NODE_TYPE},
+};
+use std::sync::Arc;
+
+use super::{count, simple_corpus};
+
+#[test]
+fn delete() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    // fully load a corpus
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "test".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+
+    cs.apply_update("testcorpus", &mut g).unwrap();
+    cs.preload("testcorpus").unwrap();
+    cs.delete("testcorpus").unwrap();
+}
+
+#[test]
+fn load_cs_twice() {
+    let tmp = tempfile::tempdir().unwrap();
+    {
+        let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+        let mut g = GraphUpdate::new();
+        g.add_event(UpdateEvent::AddNode {
+            node_name: "test".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+
+        cs.apply_update("testcorpus", &mut g).unwrap();
+    }
+
+    {
+        let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+        let mut g = GraphUpdate::new();
+        g.add_event(UpdateEvent::AddNode {
+            node_name: "test".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+
+        cs.apply_update("testcorpus", &mut g).unwrap();
+    }
+}
+
+#[test]
+fn try_unload_corpus_in_use() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    cs.apply_update("root", &mut g).unwrap();
+    cs.preload("root").unwrap();
+
+    // Simulate a running query by holding a reference to the cache entry
+    let entry = cs.get_entry("root").unwrap();
+    assert!(cs
+        .try_unload("root", Some(std::time::Duration::from_millis(50)))
+        .is_err());
+    assert!(cs.corpus_cache.read().unwrap().contains_key("root"));
+
+    drop(entry);
+    cs.try_unload("root", None).unwrap();
+    assert!(!cs.corpus_cache.read().unwrap().contains_key("root"));
+}
+
+#[test]
+fn force_reload() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(11, cs.count(query.clone()).unwrap());
+
+    cs.force_reload("root").unwrap();
+    assert_eq!(11, cs.count(query).unwrap());
+
+    assert!(cs.force_reload("nonexisting").is_err());
+}
+
+#[test]
+fn corpus_summary_persisted() {
+    let tmp = tempfile::tempdir().unwrap();
+    {
+        let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+        let mut g = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut g);
+        example_generator::create_tokens(&mut g, Some("root/doc1"));
+        cs.apply_update("root", &mut g).unwrap();
+    }
+    assert!(tmp
+        .path()
+        .join("root")
+        .join("corpus-summary.toml")
+        .is_file());
+
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    let corpora = cs.list().unwrap();
+    assert_eq!(1, corpora.len());
+    let info = &corpora[0];
+    assert_eq!(LoadStatus::NotLoaded, info.load_status);
+    assert_eq!(Some(13), info.number_of_nodes);
+    assert!(info.estimated_memory_size.is_some());
+    assert!(!info.graphstorages.is_empty());
+    for gs in &info.graphstorages {
+        assert_eq!(LoadStatus::NotLoaded, gs.load_status);
+        assert!(!gs.implementation.is_empty());
+    }
+
+    // Updating the corpus replaces the outdated summary
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#newnode".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+    drop(cs);
+
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    let info = cs.info("root").unwrap();
+    assert_eq!(LoadStatus::NotLoaded, info.load_status);
+    assert_eq!(Some(14), info.number_of_nodes);
+    assert!(info.disk_usage.is_some());
+}
+
+#[test]
+fn background_statistics_refresh() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_statistics_refresh(Some(StatisticsRefresh {
+        threshold: 0.5,
+        min_interval: std::time::Duration::from_secs(0),
+    }));
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+    cs.wait_for_background_workers();
+    {
+        let drift = cs.statistics_drift.lock().unwrap();
+        let drift = drift.get("root").unwrap();
+        assert_eq!(0, drift.changed_items);
+        assert!(drift.last_refresh.is_some());
+    }
+
+    // a small update does not exceed the threshold
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#tok0".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "pos".to_string(),
+        anno_value: "VBZ".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+    cs.wait_for_background_workers();
+    assert_eq!(1, cs.statistics_drift.lock().unwrap()["root"].changed_items);
+}
+
+#[test]
+fn pinned_corpus_stays_in_cache() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs =
+        CorpusStorage::with_cache_strategy(tmp.path(), CacheStrategy::FixedMaxMemory(0), false)
+            .unwrap();
+    for corpus in &["a", "b"] {
+        let mut g = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut g);
+        example_generator::create_tokens(&mut g, Some("root/doc1"));
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+    cs.wait_for_background_workers();
+    cs.unload("a");
+    cs.unload("b");
+
+    let pinned = cs.pin("a").unwrap();
+    assert_eq!("a", pinned.corpus_name());
+    // loading another corpus would remove "a" from the cache if it was not pinned
+    cs.preload("b").unwrap();
+    assert!(cs.corpus_cache.read().unwrap().contains_key("a"));
+
+    drop(pinned);
+    cs.preload("b").unwrap();
+    assert!(!cs.corpus_cache.read().unwrap().contains_key("a"));
+}
+
+#[test]
+fn cache_metrics() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs =
+        CorpusStorage::with_cache_strategy(tmp.path(), CacheStrategy::FixedMaxMemory(0), false)
+            .unwrap();
+    cs.set_eviction_policy(EvictionPolicy::SizeWeighted);
+    assert_eq!(EvictionPolicy::SizeWeighted, cs.eviction_policy());
+    for corpus in &["a", "b"] {
+        let mut g = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut g);
+        example_generator::create_tokens(&mut g, Some("root/doc1"));
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+    cs.wait_for_background_workers();
+    cs.unload("a");
+    cs.unload("b");
+    cs.reset_cache_metrics();
+
+    cs.preload("a").unwrap();
+    cs.preload("a").unwrap();
+    // the cache has no space left, so loading "b" removes "a"
+    cs.preload("b").unwrap();
+
+    let metrics = cs.cache_metrics();
+    assert_eq!(2, metrics.misses);
+    assert_eq!(1, metrics.evictions);
+    assert!(metrics.evicted_bytes > 0);
+    assert!(metrics.hits >= 1);
+    assert_eq!(1, metrics.corpora["a"].loads);
+    assert_eq!(1, metrics.corpora["a"].evictions);
+    assert_eq!(1, metrics.corpora["b"].loads);
+    assert_eq!(0, metrics.corpora["b"].evictions);
+}
+
+#[test]
+fn disk_usage() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    assert!(cs.disk_usage("root").is_err());
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+    // wait for the background thread that persists the update
+    std::mem::drop(cs);
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let usage = cs.disk_usage("root").unwrap();
+    assert!(usage.node_annotations > 0);
+    assert!(usage
+        .components
+        .iter()
+        .any(|(c, size)| c.get_type() == AnnotationComponentType::Ordering && *size > 0));
+    assert_eq!(0, usage.linked_files);
+    assert_eq!(
+        usage.total(),
+        usage.node_annotations
+            + usage.components.iter().map(|(_, size)| size).sum::<u64>()
+            + usage.update_log
+            + usage.linked_files
+            + usage.backup
+    );
+
+    let info = cs.info("root").unwrap();
+    assert!(info.disk_usage.is_some());
+}
+
+#[test]
+fn preload_configured_components() {
+    let tmp = tempfile::tempdir().unwrap();
+    {
+        let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+        let mut g = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut g);
+        example_generator::create_tokens(&mut g, Some("root/doc1"));
+        example_generator::make_span(&mut g, "root/doc1#span1", &["root/doc1#tok0"]);
+        cs.apply_update("root", &mut g).unwrap();
+    }
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        "preload_components = [\"Ordering/annis\", \"NotAComponentType\"]\n",
+    )
+    .unwrap();
+
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    // trigger loading the corpus without loading any specific component
+    assert!(!cs.list_components("root", None, None).is_empty());
+
+    let is_loaded = |ctype: AnnotationComponentType| {
+        let info = cs.info("root").unwrap();
+        assert_eq!(2, info.config.preload_components.len());
+        info.graphstorages
+            .iter()
+            .find(|gs| gs.component.get_type() == ctype && gs.component.layer == "annis")
+            .map(|gs| match gs.load_status {
+                LoadStatus::NotLoaded => false,
+                _ => true,
+            })
+            .unwrap()
+    };
+    assert!(is_loaded(AnnotationComponentType::Ordering));
+    assert!(!is_loaded(AnnotationComponentType::Coverage));
+
+    // reloading the corpus also preloads the configured components
+    cs.force_reload("root").unwrap();
+    assert!(is_loaded(AnnotationComponentType::Ordering));
+    assert!(!is_loaded(AnnotationComponentType::Coverage));
+}
+
+#[test]
+fn corpus_groups() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    for corpus in &["a", "b"] {
+        let mut g = GraphUpdate::new();
+        g.add_event(UpdateEvent::AddNode {
+            node_name: corpus.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+
+    cs.add_to_corpus_group("news", "a").unwrap();
+    cs.add_to_corpus_group("news", "b").unwrap();
+    cs.add_to_corpus_group("learner", "b").unwrap();
+    assert!(cs.add_to_corpus_group("news", "unknown").is_err());
+
+    assert_eq!(vec!["learner", "news"], cs.list_corpus_groups().unwrap());
+    assert_eq!(vec!["a", "b"], cs.corpora_in_group("news").unwrap());
+    assert_eq!(vec!["learner", "news"], cs.groups_of_corpus("b").unwrap());
+    assert!(cs.corpora_in_group("unknown").unwrap().is_empty());
+
+    // groups are persisted in the corpus storage directory
+    drop(cs);
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    assert_eq!(vec!["a", "b"], cs.corpora_in_group("news").unwrap());
+
+    assert!(cs.remove_from_corpus_group("learner", "b").unwrap());
+    assert!(!cs.remove_from_corpus_group("learner", "b").unwrap());
+    assert_eq!(vec!["news"], cs.list_corpus_groups().unwrap());
+
+    // deleted corpora are removed from their groups
+    cs.preload("a").unwrap();
+    assert!(cs.delete("a").unwrap());
+    assert_eq!(vec!["b"], cs.corpora_in_group("news").unwrap());
+
+    assert!(cs.delete_corpus_group("news").unwrap());
+    assert!(!cs.delete_corpus_group("news").unwrap());
+    assert!(cs.list_corpus_groups().unwrap().is_empty());
+    assert_eq!(1, cs.list().unwrap().len());
+}
+
+#[test]
+fn sharded_corpus() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    for doc in &["root/doc1", "root/doc2", "root/doc3", "root/doc4"] {
+        if *doc != "root/doc1" {
+            g.add_event(UpdateEvent::AddNode {
+                node_name: doc.to_string(),
+                node_type: "corpus".to_string(),
+            })
+            .unwrap();
+            g.add_event(UpdateEvent::AddEdge {
+                source_node: doc.to_string(),
+                target_node: "root".to_string(),
+                layer: "annis".to_string(),
+                component_type: "PartOf".to_string(),
+                component_name: "".to_string(),
+            })
+            .unwrap();
+        }
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "annis".to_string(),
+            anno_name: "doc".to_string(),
+            anno_value: doc["root/".len()..].to_string(),
+        })
+        .unwrap();
+        example_generator::create_tokens(&mut g, Some(doc));
+    }
+    cs.apply_update("root", &mut g).unwrap();
+    cs.add_to_corpus_group("all", "root").unwrap();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let queries = [
+        "tok",
+        "tok=\"example\"",
+        "tok . tok",
+        "tok @* annis:node_name=\"root/doc3\"",
+    ];
+    let expected_counts: Vec<u64> = queries
+        .iter()
+        .map(|aql| cs.count(query(aql)).unwrap())
+        .collect();
+    let expected_matches = cs
+        .find(query("tok=\"example\""), 0, None, ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(4, expected_matches.len());
+    let number_of_nodes = |g: &AnnotationGraph| {
+        g.get_node_annos()
+            .exact_anno_search(Some(ANNIS_NS), NODE_TYPE, ValueSearch::Any)
+            .count()
+    };
+    let subgraph_nodes = number_of_nodes(
+        &cs.subgraph(
+            "root",
+            vec!["root/doc1#tok1".to_string(), "root/doc4#tok1".to_string()],
+            1,
+            1,
+            None,
+        )
+        .unwrap(),
+    );
+    let corpus_graph_nodes = number_of_nodes(&cs.corpus_graph("root").unwrap());
+    let subcorpus_graph_nodes = number_of_nodes(
+        &cs.subcorpus_graph(
+            "root",
+            vec!["root/doc1".to_string(), "root/doc4".to_string()],
+        )
+        .unwrap(),
+    );
+    let document_statistics = cs.document_statistics("root").unwrap();
+    let document_metadata = cs.list_document_metadata("root", "root/doc4").unwrap();
+
+    assert!(cs.split_into_shards("root", 0).is_err());
+    let shards = cs.split_into_shards("root", 3).unwrap();
+    assert_eq!(vec!["root-shard1", "root-shard2"], shards);
+    assert_eq!(shards, cs.shards_of_corpus("root").unwrap());
+    assert_eq!(vec!["root"], cs.list_sharded_corpora().unwrap());
+    assert_eq!(vec!["all"], cs.groups_of_corpus("root").unwrap());
+    assert_eq!(
+        vec!["root-shard1", "root-shard2", "root"],
+        cs.list()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!("root", cs.info("root").unwrap().name);
+
+    // the matches of the sharded corpus can be displayed
+    assert_eq!(
+        subgraph_nodes,
+        number_of_nodes(
+            &cs.subgraph(
+                "root",
+                vec!["root/doc1#tok1".to_string(), "root/doc4#tok1".to_string()],
+                1,
+                1,
+                None,
+            )
+            .unwrap()
+        )
+    );
+    assert_eq!(
+        corpus_graph_nodes,
+        number_of_nodes(&cs.corpus_graph("root").unwrap())
+    );
+    assert_eq!(
+        subcorpus_graph_nodes,
+        number_of_nodes(
+            &cs.subcorpus_graph(
+                "root",
+                vec!["root/doc1".to_string(), "root/doc4".to_string()],
+            )
+            .unwrap()
+        )
+    );
+    assert_eq!(document_statistics, cs.document_statistics("root").unwrap());
+    assert_eq!(
+        document_metadata,
+        cs.list_document_metadata("root", "root/doc4").unwrap()
+    );
+    assert_eq!(
+        2,
+        cs.plan_structured(&["root"], "tok", QueryLanguage::AQL)
+            .unwrap()
+            .len()
+    );
+    assert!(!cs.schema("root").unwrap().node_annotations.is_empty());
+
+    // queries on the sharded corpus have the same result as on the original corpus
+    for (aql, expected) in queries.iter().zip(expected_counts) {
+        assert_eq!(expected, cs.count(query(aql)).unwrap(), "{}", aql);
+    }
+    assert_eq!(
+        expected_matches,
+        cs.find(query("tok=\"example\""), 0, None, ResultOrder::Normal)
+            .unwrap()
+    );
+    assert_eq!(
+        &expected_matches[1..3],
+        cs.find(query("tok=\"example\""), 1, Some(2), ResultOrder::Normal)
+            .unwrap()
+            .as_slice()
+    );
+    assert_eq!(
+        4,
+        cs.count_extra(query("tok=\"example\""))
+            .unwrap()
+            .document_count
+    );
+
+    // deleting the sharded corpus deletes all shards
+    assert!(cs.delete("root").unwrap());
+    assert!(cs.list().unwrap().is_empty());
+    assert!(cs.list_sharded_corpora().unwrap().is_empty());
+
+    // existing corpora can be combined to a sharded corpus
+    for corpus in &["a", "b"] {
+        let mut g = GraphUpdate::new();
+        g.add_event(UpdateEvent::AddNode {
+            node_name: corpus.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+    assert!(cs.create_sharded_corpus("a", &["b"]).is_err());
+    assert!(cs.create_sharded_corpus("ab", &["a", "unknown"]).is_err());
+    cs.create_sharded_corpus("ab", &["a", "b"]).unwrap();
+    assert_eq!(
+        2,
+        cs.count(SearchQuery {
+            corpus_names: &["ab"],
+            query: "annis:node_type=\"corpus\"",
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    );
+    // a shard that is deleted is removed from the sharded corpus
+    assert!(cs.delete("a").unwrap());
+    assert_eq!(vec!["b"], cs.shards_of_corpus("ab").unwrap());
+}
+
+#[test]
+fn configured_graph_storage_implementation() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    let ordering_impl = |cs: &CorpusStorage| {
+        cs.info("root")
+            .unwrap()
+            .graphstorages
+            .into_iter()
+            .find(|gs| {
+                gs.component.get_type() == AnnotationComponentType::Ordering
+                    && gs.component.layer == "annis"
+            })
+            .map(|gs| gs.implementation)
+            .unwrap()
+    };
+
+    // without configuration the heuristic chooses the dense ordering, since most nodes are tokens
+    cs.reoptimize_implementation("root", false).unwrap();
+    assert_eq!("DenseOrderingV1", ordering_impl(&cs));
+
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        "[graph_storage]\n\"Ordering/annis\" = \"AdjacencyListV1\"\n",
+    )
+    .unwrap();
+    cs.reoptimize_implementation("root", false).unwrap();
+    assert_eq!("AdjacencyListV1", ordering_impl(&cs));
+
+    // the configured implementation is also used after reloading the corpus
+    cs.force_reload("root").unwrap();
+    cs.reoptimize_implementation("root", false).unwrap();
+    assert_eq!("AdjacencyListV1", ordering_impl(&cs));
+
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        "[graph_storage]\n\"Ordering/annis\" = \"NoSuchImplementation\"\n",
+    )
+    .unwrap();
+    assert!(cs.reoptimize_implementation("root", false).is_err());
+
+    // the ordering has no inverse edges and can not be represented by the symmetric adjacency list
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        "[graph_storage]\n\"Ordering/annis\" = \"SymmetricAdjacencyListV1\"\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        cs.reoptimize_implementation("root", false),
+        Err(GraphAnnisError::Core(
+            GraphAnnisCoreError::GraphStorageImplNotApplicable { .. }
+        ))
+    ));
+
+    // removing the configuration also removes the override
+    std::fs::write(tmp.path().join("root").join("corpus-config.toml"), "").unwrap();
+    cs.reoptimize_implementation("root", false).unwrap();
+    assert_eq!("DenseOrderingV1", ordering_impl(&cs));
+}
+
+#[test]
+fn lease_based_locking() {
+    let tmp = tempfile::tempdir().unwrap();
+    let locking_strategy = LockingStrategy::Lease {
+        timeout: std::time::Duration::from_secs(60),
+    };
+    let open = || {
+        CorpusStorage::with_locking_strategy(
+            tmp.path(),
+            CacheStrategy::FixedMaxMemory(100),
+            false,
+            locking_strategy,
+        )
+    };
+
+    let cs = open().unwrap();
+    assert!(tmp.path().join("db.lease").is_file());
+    // the lease is held by a live instance
+    assert!(matches!(
+        open(),
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::LeaseHeld { .. }
+        ))
+    ));
+
+    // the lease is released when the instance is dropped
+    std::mem::drop(cs);
+    assert!(!tmp.path().join("db.lease").exists());
+    let cs = open().unwrap();
+    std::mem::drop(cs);
+
+    // an abandoned lease is taken over
+    std::fs::write(
+        tmp.path().join("db.lease"),
+        "instance_id = \"0000000000000001\"\nhostname = \"other\"\nprocess_id = 1\nheartbeat = 0\n",
+    )
+    .unwrap();
+    let cs = open().unwrap();
+    std::mem::drop(cs);
+    assert!(!tmp.path().join("db.lease").exists());
+
+    // timeouts shorter than the heartbeat resolution are rejected
+    assert!(matches!(
+        CorpusStorage::with_locking_strategy(
+            tmp.path(),
+            CacheStrategy::FixedMaxMemory(100),
+            false,
+            LockingStrategy::Lease {
+                timeout: std::time::Duration::from_secs(0),
+            },
+        ),
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::LeaseTimeoutTooShort { .. }
+        ))
+    ));
+}
+
+#[test]
+fn lease_taken_over_refuses_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_locking_strategy(
+        tmp.path(),
+        CacheStrategy::FixedMaxMemory(100),
+        false,
+        LockingStrategy::Lease {
+            timeout: std::time::Duration::from_secs(3),
+        },
+    )
+    .unwrap();
+
+    // simulate another instance which regarded the lease as abandoned and took it over
+    let other_lease = format!(
+        "instance_id = \"0000000000000001\"\nhostname = \"other\"\nprocess_id = 1\nheartbeat = {}\n",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    );
+    std::fs::write(tmp.path().join("db.lease"), &other_lease).unwrap();
+    // wait for the next heartbeat
+    std::thread::sleep(std::time::Duration::from_millis(1500));
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    assert!(matches!(
+        cs.apply_update("root", &mut g),
+        Err(GraphAnnisError::CorpusStorage(
+            CorpusStorageError::LeaseLost(_)
+        ))
+    ));
+    // the lease of the other instance is not removed
+    std::mem::drop(cs);
+    assert_eq!(
+        other_lease,
+        std::fs::read_to_string(tmp.path().join("db.lease")).unwrap()
+    );
+}
+
+#[test]
+fn custom_thread_pool() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs =
+        CorpusStorage::with_cache_strategy(tmp.path(), CacheStrategy::FixedMaxMemory(1024), true)
+            .unwrap();
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|idx| format!("custom-worker-{}", idx))
+        .build()
+        .unwrap();
+    cs.set_thread_pool(Some(Arc::new(thread_pool)));
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(10, count(&cs, "tok . tok"));
+    assert_eq!(55, count(&cs, "tok .* tok"));
+
+    // the changes of the update have been written to disk by the background job
+    cs.shutdown();
+    assert!(tmp.path().join("root").join("current").is_dir());
+    assert_eq!(10, count(&cs, "tok . tok"));
+}
+
+#[test]
+fn encrypted_corpus() {
+    let tmp = tempfile::tempdir().unwrap();
+    let key = || {
+        EncryptionKey::from_hex("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+            .unwrap()
+    };
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_encryption_key(Some(key()));
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "\"complicated\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(1, cs.count(query.clone()).unwrap());
+    // also write the document statistics
+    cs.document_statistics("root").unwrap();
+    drop(cs);
+
+    // the annotation values and node names are not stored as plain text in any file
+    let mut data_files = 0;
+    let mut dirs = vec![tmp.path().join("root")];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                data_files += 1;
+                let content = std::fs::read(&path).unwrap();
+                assert!(
+                    !content.windows(11).any(|w| w == b"complicated"),
+                    "{:?} contains an annotation value",
+                    path
+                );
+                assert!(
+                    !content.windows(4).any(|w| w == b"doc1"),
+                    "{:?} contains a node name",
+                    path
+                );
+            }
+        }
+    }
+    assert!(data_files > 0);
+
+    // the corpus can only be loaded with the key
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_encryption_key(Some(key()));
+    assert_eq!(1, cs.count(query.clone()).unwrap());
+    drop(cs);
+
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    assert!(cs.count(query).is_err());
+}
+
+#[test]
+fn no_unencrypted_files_with_key() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_encryption_key(Some(EncryptionKey::new([3; 32])));
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+    // write all other files of the corpus and the data directory
+    cs.infer_segmentations("root").unwrap();
+    cs.node_id_generation("root").unwrap();
+    cs.document_statistics("root").unwrap();
+    cs.add_to_corpus_group("group", "root").unwrap();
+    assert_eq!(11, count(&cs, "tok"));
+    drop(cs);
+
+    // Only these files contain no corpus data and are not encrypted
+    let unencrypted_files = ["db.lock", "impl.cfg", "component.crc32", "change_id"];
+    let mut checked_files = 0;
+    let mut dirs = vec![tmp.path().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if !unencrypted_files.contains(&file_name.as_str()) {
+                checked_files += 1;
+                let content = std::fs::read(&path).unwrap();
+                assert!(
+                    encryption::is_encrypted(&content) || content.starts_with(b"GANNWAL3"),
+                    "{:?} is not encrypted",
+                    path
+                );
+            }
+        }
+    }
+    assert!(checked_files > 0);
+}
@@ -0,0 +1,41 @@
+extern crate log;
+extern crate tempfile;
+
+use crate::annis::db::example_generator;
+use crate::corpusstorage::{QueryLanguage, SearchQuery};
+use crate::update::GraphUpdate;
+use crate::CorpusStorage;
+use tempfile::TempDir;
+
+mod corpus_info;
+mod import_export;
+mod lifecycle;
+mod operators;
+mod plan;
+mod search;
+mod update;
+
+/// Create a corpus storage in a temporary directory with the corpus "root", which contains the
+/// example tokens in the single document "root/doc1".
+fn simple_corpus() -> (TempDir, CorpusStorage) {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+    (tmp, cs)
+}
+
+/// Count the matches of the AQL query `aql` in the corpus "root".
+fn count(cs: &CorpusStorage, aql: &str) -> u64 {
+    cs.count(SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    })
+    .unwrap()
+}
@@ -0,0 +1,800 @@
+use crate::annis::db::{aql::model::AnnotationComponentType, example_generator};
+use crate::corpusstorage::{JoinThresholds, QueryLanguage, ResultOrder, SearchQuery};
+use crate::errors::GraphAnnisError;
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+
+use super::count;
+
+fn add_dominance_edge(g: &mut GraphUpdate, source_node: &str, target_node: &str) {
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: source_node.to_string(),
+        target_node: target_node.to_string(),
+        layer: "".to_string(),
+        component_type: "Dominance".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+}
+
+#[test]
+fn merge_join_for_document_local_operators() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc2".to_string(),
+        target_node: "root".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    for doc in &["root/doc1", "root/doc2"] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "annis".to_string(),
+            anno_name: "doc".to_string(),
+            anno_value: doc.to_string(),
+        })
+        .unwrap();
+        example_generator::create_tokens(&mut g, Some(doc));
+        // a dominance edge is needed to have statistics for the arity operator
+        add_dominance_edge(&mut g, &format!("{}#tok0", doc), &format!("{}#tok1", doc));
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    // the unary operators prevent using an index join for the node searches
+    let aql = "tok & tok & #1 . #2 & #1:arity=0,1 & #2:arity=0,1";
+    let plan = cs.plan(&["root"], aql, QueryLanguage::AQL).unwrap();
+    assert!(plan.contains("mergejoin"));
+    // almost all nodes are token, so checking all nodes in document order is cheaper than sorting the matches
+    assert!(plan.contains("document-ordered-search"), "{}", plan);
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    // each document has 11 token, which results in 10 pairs of adjacent token
+    assert_eq!(20, cs.count(query.clone()).unwrap());
+
+    // use a nested loop instead if there are not enough documents
+    let mut cs = cs;
+    cs.set_join_thresholds(JoinThresholds {
+        min_documents_for_merge_join: 3,
+        ..Default::default()
+    });
+    let plan = cs.plan(&["root"], aql, QueryLanguage::AQL).unwrap();
+    assert!(!plan.contains("mergejoin"));
+    assert!(plan.contains("nestedloop"));
+    assert!(plan.contains("join thresholds: min_documents_for_merge_join=3,"));
+    assert_eq!(20, cs.count(query.clone()).unwrap());
+
+    // empty batches are not allowed
+    cs.set_join_thresholds(JoinThresholds {
+        parallel_index_join_batch_size: 0,
+        parallel_nested_loop_batch_size: 0,
+        ..Default::default()
+    });
+    assert_eq!(1, cs.join_thresholds().parallel_index_join_batch_size);
+    assert_eq!(1, cs.join_thresholds().parallel_nested_loop_batch_size);
+    assert_eq!(20, cs.count(query).unwrap());
+}
+
+#[test]
+fn merge_join_with_custom_node_names() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc2".to_string(),
+        target_node: "root".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    // the token names do not contain the document name, so the document can only be found by the PartOf edges
+    for (doc_nr, doc) in ["root/doc1", "root/doc2"].iter().enumerate() {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "annis".to_string(),
+            anno_name: "doc".to_string(),
+            anno_value: doc.to_string(),
+        })
+        .unwrap();
+        for i in 0..11 {
+            example_generator::create_token_node(
+                &mut g,
+                &format!("t{}_{}", doc_nr, i),
+                "example",
+                Some(doc),
+            );
+        }
+        for i in 0..10 {
+            g.add_event(UpdateEvent::AddEdge {
+                source_node: format!("t{}_{}", doc_nr, i),
+                target_node: format!("t{}_{}", doc_nr, i + 1),
+                layer: "annis".to_string(),
+                component_type: "Ordering".to_string(),
+                component_name: "".to_string(),
+            })
+            .unwrap();
+        }
+        add_dominance_edge(&mut g, &format!("t{}_0", doc_nr), &format!("t{}_1", doc_nr));
+    }
+    // token which are not part of any document are still found
+    example_generator::create_token_node(&mut g, "loose0", "example", None);
+    example_generator::create_token_node(&mut g, "loose1", "example", None);
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "loose0".to_string(),
+        target_node: "loose1".to_string(),
+        layer: "annis".to_string(),
+        component_type: "Ordering".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+
+    let aql = "tok & tok & #1 . #2 & #1:arity=0,1 & #2:arity=0,1";
+    let plan = cs.plan(&["root"], aql, QueryLanguage::AQL).unwrap();
+    assert!(plan.contains("mergejoin"));
+    assert!(plan.contains("document-ordered-search"), "{}", plan);
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(21, cs.count(query).unwrap());
+}
+
+#[test]
+fn edge_anno_wildcards() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (source, target, anno) in &[
+        (1, 0, Some(("default_ns", "func", "nsubj"))),
+        (2, 3, Some(("default_ns", "func", "det"))),
+        (4, 5, Some(("other", "rel", "nsubj"))),
+        (6, 7, None),
+    ] {
+        let source_node = format!("root/doc1#tok{}", source);
+        let target_node = format!("root/doc1#tok{}", target);
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: source_node.clone(),
+            target_node: target_node.clone(),
+            layer: "".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+        if let Some((anno_ns, anno_name, anno_value)) = anno {
+            g.add_event(UpdateEvent::AddEdgeLabel {
+                source_node,
+                target_node,
+                layer: "".to_string(),
+                component_type: "Pointing".to_string(),
+                component_name: "dep".to_string(),
+                anno_ns: anno_ns.to_string(),
+                anno_name: anno_name.to_string(),
+                anno_value: anno_value.to_string(),
+            })
+            .unwrap();
+        }
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |aql| count(&cs, aql);
+    assert_eq!(4, count("tok ->dep tok"));
+    assert_eq!(2, count("tok ->dep[func=*] tok"));
+    assert_eq!(1, count("tok ->dep[other:rel=*] tok"));
+    assert_eq!(2, count("tok ->dep[*=\"nsubj\"] tok"));
+    assert_eq!(3, count("tok ->dep[*=/(nsubj|det)/] tok"));
+    assert_eq!(0, count("tok ->dep[*=\"obj\"] tok"));
+}
+
+#[test]
+fn operator_components_by_layer() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (source, target, component_type, layer) in &[
+        (0, 1, "Dominance", "syntax"),
+        (2, 3, "Dominance", "other"),
+        (4, 5, "Pointing", "syntax"),
+        (6, 7, "Pointing", "other"),
+    ] {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: format!("root/doc1#tok{}", source),
+            target_node: format!("root/doc1#tok{}", target),
+            layer: layer.to_string(),
+            component_type: component_type.to_string(),
+            component_name: "edge".to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(2, cs.count(query("tok >edge tok")).unwrap());
+    assert_eq!(1, cs.count(query("tok >syntax::edge tok")).unwrap());
+    assert_eq!(1, cs.count(query("tok >other::edge tok")).unwrap());
+    assert_eq!(2, cs.count(query("tok ->edge tok")).unwrap());
+    assert_eq!(1, cs.count(query("tok ->syntax::edge tok")).unwrap());
+
+    let result = cs.count(query("tok >missing::edge tok"));
+    if let Err(GraphAnnisError::AQLSemanticError(e)) = result {
+        assert!(e.desc.contains("available layers: other, syntax"));
+        assert!(e.location.is_some());
+    } else {
+        panic!("Expected semantic error, got {:?}", result);
+    }
+}
+
+#[test]
+fn segmentation_ordering_has_positions() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for i in 0..5 {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: format!("root/doc1#seg{}", i),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#seg{}", i),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "seg".to_string(),
+            anno_value: format!("s{}", i),
+        })
+        .unwrap();
+        example_generator::make_span(
+            &mut g,
+            &format!("root/doc1#seg{}", i),
+            &[&format!("root/doc1#tok{}", i * 2)],
+        );
+        if i > 0 {
+            g.add_event(UpdateEvent::AddEdge {
+                source_node: format!("root/doc1#seg{}", i - 1),
+                target_node: format!("root/doc1#seg{}", i),
+                layer: "default_ns".to_string(),
+                component_type: "Ordering".to_string(),
+                component_name: "seg".to_string(),
+            })
+            .unwrap();
+        }
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    // the ordering of the segmentation is stored with the position of each node
+    let schema = cs.schema("root").unwrap();
+    let seg_ordering = schema
+        .components
+        .iter()
+        .find(|c| c.component_type == AnnotationComponentType::Ordering && c.name == "seg")
+        .unwrap();
+    assert!(seg_ordering.implementation.starts_with("LinearO"));
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "seg=\"s0\" .seg,3,4 seg",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(2, cs.count(query).unwrap());
+}
+
+#[test]
+fn same_segment_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    // two sentences covering the tokens 0-3 and 4-10
+    for (i, tokens) in [0..4, 4..11].iter().enumerate() {
+        let sentence = format!("root/doc1#sent{}", i);
+        g.add_event(UpdateEvent::AddNode {
+            node_name: sentence.clone(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: sentence.clone(),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "sentence".to_string(),
+            anno_value: "s".to_string(),
+        })
+        .unwrap();
+        let token_names: Vec<String> = tokens
+            .clone()
+            .map(|t| format!("root/doc1#tok{}", t))
+            .collect();
+        let token_names: Vec<&str> = token_names.iter().map(String::as_str).collect();
+        example_generator::make_span(&mut g, &sentence, &token_names);
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |query: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    };
+
+    // ordered pairs of different token in the same sentence: 4*3 + 7*6
+    assert_eq!(54, count("tok _=sentence_ tok"));
+    assert_eq!(
+        count("tok & tok & sentence & #3 _i_ #1 & #3 _i_ #2"),
+        count("tok _=sentence_ tok")
+    );
+    assert_eq!(3, count("tok=\"this\" _=sentence_ tok"));
+    assert_eq!(0, count("tok _=unknown_ tok"));
+}
+
+#[test]
+fn outdegree_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (source, target) in &[(0, 1), (0, 2), (0, 3), (4, 5)] {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: format!("root/doc1#tok{}", source),
+            target_node: format!("root/doc1#tok{}", target),
+            layer: "dep".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(1, count(&cs, "tok & #1:outdegree(dep)>=3"));
+    assert_eq!(2, count(&cs, "tok & #1:outdegree(dep) >= 1"));
+    assert_eq!(1, count(&cs, "tok & #1:outdegree(dep::dep)=1"));
+    assert_eq!(2, count(&cs, "tok & #1:outdegree(dep)=1,3"));
+    assert_eq!(10, count(&cs, "tok & #1:outdegree(dep)<=1"));
+    assert_eq!(0, count(&cs, "tok & #1:outdegree(other::dep)>=1"));
+    assert_eq!(0, count(&cs, "tok & #1:outdegree(dep)>=4"));
+
+    // numbers that are too large are a syntax error
+    assert!(matches!(
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query: "tok & #1:outdegree(dep)>=99999999999999999999999",
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        }),
+        Err(GraphAnnisError::AQLSyntaxError(_))
+    ));
+}
+
+#[test]
+fn length_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#span".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#span".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "phrase".to_string(),
+        anno_value: "NP".to_string(),
+    })
+    .unwrap();
+    // "this example more"
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#span",
+        &["root/doc1#tok1", "root/doc1#tok2", "root/doc1#tok3"],
+    );
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(3, count(&cs, "tok & #1:len>=7"));
+    assert_eq!(4, count(&cs, "tok & #1:len=2"));
+    assert_eq!(5, count(&cs, "tok & #1:len <= 2"));
+    assert_eq!(5, count(&cs, "tok & #1:len=4,7"));
+    assert_eq!(11, count(&cs, "tok & #1:tokenlen=1"));
+    // the whitespace between the tokens is not counted
+    assert_eq!(1, count(&cs, "phrase & #1:len>=15"));
+    assert_eq!(0, count(&cs, "phrase & #1:len>=16"));
+    assert_eq!(1, count(&cs, "phrase & #1:tokenlen=3"));
+    assert_eq!(0, count(&cs, "phrase & #1:tokenlen<=2"));
+}
+
+#[test]
+fn same_text_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc2".to_string(),
+        target_node: "root".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    example_generator::create_tokens(&mut g, Some("root/doc2"));
+    // "this example more" in both documents and "example more complicated" in the second one
+    for (span, covered) in &[
+        (
+            "root/doc1#span1",
+            ["root/doc1#tok1", "root/doc1#tok2", "root/doc1#tok3"],
+        ),
+        (
+            "root/doc2#span1",
+            ["root/doc2#tok1", "root/doc2#tok2", "root/doc2#tok3"],
+        ),
+        (
+            "root/doc2#span2",
+            ["root/doc2#tok2", "root/doc2#tok3", "root/doc2#tok4"],
+        ),
+    ] {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: span.to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: span.to_string(),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "phrase".to_string(),
+            anno_value: "NP".to_string(),
+        })
+        .unwrap();
+        example_generator::make_span(&mut g, span, covered);
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(2, count(&cs, "phrase & phrase & #1 _sametext_ #2"));
+    assert_eq!(2, count(&cs, "phrase & node & #1 _sametext_ #2"));
+    assert_eq!(2, count(&cs, "tok=\"example\" & tok & #1 _sametext_ #2"));
+    assert_eq!(0, count(&cs, "phrase & tok & #1 _sametext_ #2"));
+    // the same node or nodes with an identical coverage are not reported
+    assert_eq!(
+        0,
+        count(&cs, "phrase & phrase & #1 _sametext_ #2 & #1 _=_ #2")
+    );
+}
+
+#[test]
+fn different_document_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc2".to_string(),
+        target_node: "root".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    example_generator::create_tokens(&mut g, Some("root/doc2"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(
+        2,
+        count(&cs, "tok=\"example\" & tok=\"example\" & #1 _diffdoc_ #2")
+    );
+    assert_eq!(22, count(&cs, "tok=\"example\" _diffdoc_ tok"));
+    assert_eq!(22, count(&cs, "tok _diffdoc_ tok=\"example\""));
+    assert_eq!(242, count(&cs, "tok & tok & #1 _diffdoc_ #2"));
+    assert_eq!(0, count(&cs, "tok . tok & #1 _diffdoc_ #2"));
+}
+
+#[test]
+fn inverse_part_of_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (node_name, level) in &[("root", "top"), ("root/doc1", "document")] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: node_name.to_string(),
+            anno_ns: "".to_string(),
+            anno_name: "level".to_string(),
+            anno_value: level.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(11, count(&cs, "level=\"document\" @^ tok"));
+    assert_eq!(0, count(&cs, "level=\"top\" @^ tok"));
+    assert_eq!(11, count(&cs, "level=\"top\" @^2 tok"));
+    assert_eq!(11, count(&cs, "level=\"top\" @^* tok"));
+    assert_eq!(22, count(&cs, "level @^1,2 tok"));
+    assert_eq!(1, count(&cs, "level=\"top\" @^ level"));
+    assert_eq!(0, count(&cs, "tok @^* level"));
+    // the operator can be used with the corpus node in either argument position
+    assert_eq!(
+        count(&cs, "tok @* level=\"top\""),
+        count(&cs, "tok & level=\"top\" & #2 @^* #1")
+    );
+}
+
+#[test]
+fn nested_loop_with_edge_operator() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_join_thresholds(JoinThresholds {
+        min_documents_for_merge_join: 3,
+        ..Default::default()
+    });
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (source, target, func) in &[(0, 1, "det"), (0, 2, "obj"), (3, 4, "det")] {
+        let source_node = format!("root/doc1#tok{}", source);
+        let target_node = format!("root/doc1#tok{}", target);
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: source_node.clone(),
+            target_node: target_node.clone(),
+            layer: "default_ns".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddEdgeLabel {
+            source_node,
+            target_node,
+            layer: "default_ns".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "func".to_string(),
+            anno_value: func.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    for (aql, expected) in &[
+        ("tok & tok & #1 ->dep #2 & #1:arity=0,3 & #2:arity=0,3", 3),
+        (
+            "tok & tok & #1 ->dep[func=\"det\"] #2 & #1:arity=0,3 & #2:arity=0,3",
+            2,
+        ),
+        ("tok & tok & #2 ->dep #1 & #1:arity=0,3 & #2:arity=0,3", 3),
+    ] {
+        let plan = cs.plan(&["root"], aql, QueryLanguage::AQL).unwrap();
+        assert!(plan.contains("nestedloop"), "{}", plan);
+        let query = SearchQuery {
+            corpus_names: &["root"],
+            query: aql,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        };
+        assert_eq!(*expected, cs.count(query).unwrap(), "{}", aql);
+    }
+}
+
+#[test]
+fn negated_binary_operators() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for target in &["root/doc1#tok1", "root/doc1#tok3"] {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: "root/doc1#tok2".to_string(),
+            target_node: target.to_string(),
+            layer: "default_ns".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |query: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+    };
+
+    assert_eq!(3, count("tok=\"Is\" .1,3 tok").unwrap());
+    assert_eq!(2, count("tok=\"Is\" .1,3 tok & #1 !. #2").unwrap());
+    assert_eq!(1, count("tok=\"example\" .1,2 tok & #1 !->dep #2").unwrap());
+    assert_eq!(
+        1,
+        count("tok=\"example\" & tok & #1 !->dep #2 & #1 .1,2 #2").unwrap()
+    );
+    assert_eq!(3, count("tok=\"Is\" .1,3 tok & #1 !> #2").unwrap());
+
+    // the operands of a negated operator must be bound by other operators
+    assert!(matches!(
+        count("tok=\"Is\" !. tok"),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+}
+
+#[test]
+fn derived_operators() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for target in &["root/doc1#tok1", "root/doc1#tok3", "root/doc1#tok6"] {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: "root/doc1#tok2".to_string(),
+            target_node: target.to_string(),
+            layer: "default_ns".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        r#"
+[[derived_operators]]
+name = "governs-near"
+definition = "->dep & ^1,2"
+
+[[derived_operators]]
+name = "governs-next"
+definition = "->dep & .1"
+description = "Dependency to the following token"
+"#,
+    )
+    .unwrap();
+
+    let query = |query| SearchQuery {
+        corpus_names: &["root"],
+        query,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let count = |aql| cs.count(query(aql));
+
+    assert_eq!(3, count("tok & tok & #1 ->dep #2").unwrap());
+    assert_eq!(2, count("tok & tok & #1 ~governs-near #2").unwrap());
+    assert_eq!(1, count("tok & tok & #1 ~governs-next #2").unwrap());
+    // derived operators can be combined with other operators
+    assert_eq!(
+        1,
+        count("tok & tok & #1 ~governs-near #2 & #1 . #2").unwrap()
+    );
+
+    let result = cs
+        .find(
+            query("tok & tok & #1 ~governs-near #2"),
+            0,
+            None,
+            ResultOrder::Normal,
+        )
+        .unwrap();
+    assert_eq!(
+        vec![
+            "annis::tok::root/doc1#tok2 root/doc1#tok1",
+            "annis::tok::root/doc1#tok2 root/doc1#tok3"
+        ],
+        result
+    );
+
+    assert!(matches!(
+        count("tok & tok & #1 ~unknown #2"),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+
+    // the nodes of queries with derived operators can be described without a corpus
+    let descriptions = cs
+        .node_descriptions("tok & pos=\"NN\" & #1 ~governs-near #2", QueryLanguage::AQL)
+        .unwrap();
+    assert_eq!(2, descriptions.len());
+    assert!(cs.quirks_report("tok & tok & #1 ~governs-near #2").is_ok());
+
+    // changing the definition of a derived operator must not re-use the plan of the old definition
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        r#"
+[[derived_operators]]
+name = "governs-near"
+definition = "->dep & .1,2"
+"#,
+    )
+    .unwrap();
+    assert_eq!(1, count("tok & tok & #1 ~governs-near #2").unwrap());
+
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        r#"
+[[derived_operators]]
+name = "invalid"
+definition = "->dep &"
+"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        count("tok & tok & #1 ~invalid #2"),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+}
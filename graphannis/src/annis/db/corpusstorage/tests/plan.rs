@@ -0,0 +1,141 @@
+use crate::annis::db::example_generator;
+use crate::corpusstorage::{QueryLanguage, ResultOrder, SearchQuery};
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+
+use super::simple_corpus;
+
+#[test]
+fn plan_describes_operator_components() {
+    let (_tmp, cs) = simple_corpus();
+
+    let plan = cs
+        .plan(&["root"], "tok=\"this\" . tok", QueryLanguage::AQL)
+        .unwrap();
+    assert!(plan.contains("~ seed: #1, selectivity: "));
+    assert!(plan.contains("~ Ordering/annis/ [nodes=11"));
+}
+
+#[test]
+fn plan_describes_switched_operands() {
+    let (_tmp, cs) = simple_corpus();
+
+    // the more specific right operand is used as seed
+    let plan = cs
+        .plan(&["root"], "tok . tok=\"this\"", QueryLanguage::AQL)
+        .unwrap();
+    assert!(plan.contains("operands switched: inverse"));
+    let plan = cs
+        .plan(&["root"], "tok ^ tok=\"this\"", QueryLanguage::AQL)
+        .unwrap();
+    assert!(plan.contains("operands switched: commutative"));
+    let plan = cs
+        .plan(&["root"], "tok=\"this\" . tok", QueryLanguage::AQL)
+        .unwrap();
+    assert!(!plan.contains("operands switched"));
+}
+
+#[test]
+fn plan_structured() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    let plans = cs
+        .plan_structured(&["root"], "tok=\"this\" . tok", QueryLanguage::AQL)
+        .unwrap();
+    assert_eq!(1, plans.len());
+    assert_eq!("root", plans[0].corpus_name);
+    assert_eq!(1, plans[0].alternatives.len());
+
+    let join = plans[0].alternatives[0].as_ref().unwrap();
+    assert_eq!(vec![1, 2], join.query_nodes);
+    assert_eq!("#1 . #2", join.query_fragment);
+    assert!(!join.implementation.is_empty());
+    assert_eq!(
+        Some(plans[0].estimated_output_size),
+        join.cost.as_ref().map(|cost| cost.output)
+    );
+    let operator = join.operator.as_ref().unwrap();
+    assert!(operator.selectivity.is_some());
+    assert_eq!(None, operator.operand_switch);
+    let ordering = operator
+        .components
+        .iter()
+        .find(|c| c.component.to_string() == "Ordering/annis/")
+        .unwrap();
+    assert!(ordering.loaded);
+    assert_eq!(11, ordering.statistics.as_ref().unwrap().nodes);
+
+    // the seed is the first input of the join
+    assert_eq!(2, join.children.len());
+    assert_eq!(vec![1], join.children[0].query_nodes);
+    assert!(join.children[0].children.is_empty());
+
+    // the plan can be serialized, e.g. to be sent by the web service
+    let json = serde_json::to_string(&plans).unwrap();
+    assert!(json.contains("\"implementation\""));
+
+    // each alternative of a disjunction has its own plan
+    let plans = cs
+        .plan_structured(
+            &["root"],
+            "tok=\"this\" | tok=\"example\"",
+            QueryLanguage::AQL,
+        )
+        .unwrap();
+    assert_eq!(2, plans[0].alternatives.len());
+}
+
+#[test]
+fn plan_cache() {
+    let (tmp, cs) = simple_corpus();
+
+    let plan_cache_file = tmp.path().join("root").join("plan-cache.toml");
+    assert!(!plan_cache_file.exists());
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok & tok & tok & #1 . #2 & #2 . #3",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let first = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    // the plans are only persisted when the corpus storage is shut down
+    assert!(!plan_cache_file.exists());
+    // the cached plan gives the same results
+    let second = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(first, second);
+    assert_eq!(9, cs.count(query.clone()).unwrap());
+
+    // the cached plans are loaded from disk by a new corpus storage
+    drop(cs);
+    assert!(plan_cache_file.is_file());
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    assert_eq!(
+        first,
+        cs.find(query.clone(), 0, None, ResultOrder::Normal)
+            .unwrap()
+    );
+
+    // updating the corpus invalidates the cached plans
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::DeleteNode {
+        node_name: "root/doc1#tok10".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+    assert!(!plan_cache_file.exists());
+    assert_eq!(8, cs.count(query.clone()).unwrap());
+    cs.shutdown();
+    assert!(plan_cache_file.is_file());
+}
@@ -0,0 +1,1150 @@
+use crate::annis::db::example_generator;
+use crate::corpusstorage::{
+    BatchConfig, BatchMode, BatchOutputFormat, BatchQuery, FindCursor, FindOptions, MatchIdFormat,
+    QueryLanguage, QueryLimits, QueryWarning, QuirksRule, ResultOrder, SearchQuery, TextOffset,
+};
+use crate::errors::GraphAnnisError;
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+use graphannis_core::types::NodeID;
+
+use super::simple_corpus;
+
+#[test]
+fn find_with_seeded_order() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let normal = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    let seeded_1 = cs
+        .find(query.clone(), 0, None, ResultOrder::Seeded(42))
+        .unwrap();
+    let seeded_2 = cs
+        .find(query.clone(), 0, None, ResultOrder::Seeded(42))
+        .unwrap();
+    let other_seed = cs
+        .find(query.clone(), 0, None, ResultOrder::Seeded(43))
+        .unwrap();
+
+    assert_eq!(11, seeded_1.len());
+    assert_eq!(seeded_1, seeded_2);
+    assert_ne!(normal, seeded_1);
+    assert_ne!(seeded_1, other_seed);
+
+    // The seeded order must contain the same matches as the normal order
+    let mut sorted_seeded = seeded_1.clone();
+    sorted_seeded.sort();
+    let mut sorted_normal = normal.clone();
+    sorted_normal.sort();
+    assert_eq!(sorted_normal, sorted_seeded);
+
+    // Pagination must be consistent with the complete result
+    let page = cs.find(query, 3, Some(4), ResultOrder::Seeded(42)).unwrap();
+    assert_eq!(&seeded_1[3..7], page.as_slice());
+}
+
+#[test]
+fn find_with_projection() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok & second#tok & #1 . #second",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let all = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    let projected = |var: &str| -> Vec<String> {
+        let options = FindOptions {
+            projection: Some(vec![var.to_string()]),
+            ..Default::default()
+        };
+        let (result, _) = cs.find_with_options(query.clone(), options).unwrap();
+        result.into_iter().map(|m| m.match_id).collect()
+    };
+    let first = projected("1");
+    let second = projected("#second");
+
+    assert_eq!(10, all.len());
+    assert_eq!(all.len(), first.len());
+    assert_eq!(all.len(), second.len());
+    for i in 0..all.len() {
+        let nodes: Vec<&str> = all[i].split(' ').collect();
+        assert_eq!(2, nodes.len());
+        assert_eq!(nodes[0], first[i]);
+        assert_eq!(nodes[1], second[i]);
+    }
+
+    // Projecting a non-existing query node is an error
+    let options = FindOptions {
+        projection: Some(vec!["3".to_string()]),
+        ..Default::default()
+    };
+    assert!(cs.find_with_options(query, options).is_err());
+}
+
+#[test]
+fn find_with_node_ids() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok & tok & #1 . #2",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let names = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    let find_with_format = |format: MatchIdFormat| -> Vec<String> {
+        let options = FindOptions {
+            format,
+            ..Default::default()
+        };
+        let (result, _) = cs.find_with_options(query.clone(), options).unwrap();
+        result.into_iter().map(|m| m.match_id).collect()
+    };
+    // the default format is the same as for find()
+    assert_eq!(names, find_with_format(MatchIdFormat::default()));
+
+    let with_ids = find_with_format(MatchIdFormat::NodeNameAndId);
+    assert_eq!(10, with_ids.len());
+    let generation = cs.node_id_generation("root").unwrap();
+    for (match_names, match_with_ids) in names.iter().zip(with_ids.iter()) {
+        let nodes: Vec<&str> = match_names.split(' ').collect();
+        let nodes_with_ids: Vec<&str> = match_with_ids.split(' ').collect();
+        assert_eq!(2, nodes_with_ids.len());
+        for (node, node_with_id) in nodes.iter().zip(nodes_with_ids.iter()) {
+            let (name, id) = node_with_id.rsplit_once('@').unwrap();
+            assert_eq!(*node, name);
+            let id: NodeID = id.parse().unwrap();
+            let resolved = cs.node_names_from_ids("root", &[id], generation).unwrap();
+            // the match ID contains the matched annotation key before the node name
+            assert!(name.ends_with(resolved[0].as_deref().unwrap()));
+        }
+    }
+}
+
+#[test]
+fn query_limits() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    cs.set_query_limits(QueryLimits {
+        max_nodes: Some(3),
+        max_alternatives: Some(2),
+        max_unbounded_operators: Some(1),
+    });
+    let count = |cs: &CorpusStorage, aql: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query: aql,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+    };
+    let is_too_complex = |result: Result<u64, GraphAnnisError>| {
+        matches!(result, Err(GraphAnnisError::QueryTooComplex(_)))
+    };
+
+    assert_eq!(55, count(&cs, "tok & tok & #1 .* #2").unwrap());
+    assert_eq!(
+        9,
+        count(&cs, "tok & tok & tok & #1 . #2 & #2 . #3").unwrap()
+    );
+    assert!(is_too_complex(count(
+        &cs,
+        "tok & tok & tok & tok & #1 . #2 & #2 . #3 & #3 . #4"
+    )));
+    assert!(is_too_complex(count(
+        &cs,
+        "tok & tok & tok & #1 .* #2 & #2 .* #3"
+    )));
+    assert!(is_too_complex(count(
+        &cs,
+        "\"Is\" | \"this\" | \"example\""
+    )));
+    // the limits are checked for each alternative
+    assert_eq!(
+        19,
+        count(
+            &cs,
+            "(tok=\"Is\" & tok & #1 .* #2) | (tok=\"this\" & tok & #3 .* #4)"
+        )
+        .unwrap()
+    );
+    // validating the query also checks the limits
+    assert!(is_too_complex(
+        cs.validate_query(
+            &["root"],
+            "tok & tok & tok & #1 .* #2 & #2 .* #3",
+            QueryLanguage::AQL
+        )
+        .map(|_| 0)
+    ));
+
+    // without limits, all queries are allowed
+    cs.set_query_limits(QueryLimits::default());
+    assert_eq!(
+        8,
+        count(&cs, "tok & tok & tok & tok & #1 . #2 & #2 . #3 & #3 . #4").unwrap()
+    );
+}
+
+#[test]
+fn find_with_document_filter() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc2".to_string(),
+        target_node: "root".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    example_generator::create_tokens(&mut g, Some("root/doc2"));
+    cs.apply_update("root", &mut g).unwrap();
+
+    let mut query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(22, cs.count(query.clone()).unwrap());
+
+    query.document_filter = vec!["root/doc2".to_string()];
+    assert_eq!(11, cs.count(query.clone()).unwrap());
+    let matches = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(11, matches.len());
+    for m in matches {
+        assert!(m.starts_with("root/doc2#"));
+        assert!(!m.contains(' '));
+    }
+
+    query.document_filter = vec!["root/doc1".to_string(), "root/doc2".to_string()];
+    assert_eq!(22, cs.count(query.clone()).unwrap());
+
+    query.document_filter = vec!["root/doc3".to_string()];
+    assert_eq!(0, cs.count(query.clone()).unwrap());
+
+    // all nodes of a match must be part of the filtered documents or the document itself, not only the first one
+    query.query = "tok & annis:node_type=\"corpus\" & #1 @* #2";
+    query.document_filter = Vec::new();
+    assert_eq!(44, cs.count(query.clone()).unwrap());
+    query.document_filter = vec!["root/doc2".to_string()];
+    let matches = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(11, matches.len());
+    for m in matches {
+        assert!(m.starts_with("root/doc2#"));
+        assert!(m.ends_with(" root/doc2"));
+    }
+}
+
+#[test]
+fn find_document_local_query_in_document_order() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    for doc in &["root/doc2", "root/doc10"] {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: doc.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: doc.to_string(),
+            target_node: "root".to_string(),
+            layer: "annis".to_string(),
+            component_type: "PartOf".to_string(),
+            component_name: "".to_string(),
+        })
+        .unwrap();
+    }
+    for doc in &["root/doc2", "root/doc1", "root/doc10"] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "annis".to_string(),
+            anno_name: "doc".to_string(),
+            anno_value: doc.to_string(),
+        })
+        .unwrap();
+        example_generator::create_tokens(&mut g, Some(doc));
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "tok . tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let result = cs
+        .find(query.clone(), 0, None, ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(30, result.len());
+    assert_eq!("root/doc1#tok0 root/doc1#tok1", result[0]);
+    assert_eq!("root/doc10#tok9 root/doc10#tok10", result[19]);
+    assert_eq!("root/doc2#tok0 root/doc2#tok1", result[20]);
+
+    // the matches must have the same order as when sorting all matches at once
+    let mut inverted = cs
+        .find(query.clone(), 0, None, ResultOrder::Inverted)
+        .unwrap();
+    inverted.reverse();
+    assert_eq!(inverted, result);
+
+    let limited = cs
+        .find(query.clone(), 9, Some(2), ResultOrder::Normal)
+        .unwrap();
+    assert_eq!(&result[9..11], &limited[..]);
+
+    let filtered = cs
+        .find(
+            SearchQuery {
+                document_filter: vec!["root/doc2".to_string()],
+                ..query
+            },
+            0,
+            None,
+            ResultOrder::Normal,
+        )
+        .unwrap();
+    assert_eq!(&result[20..], &filtered[..]);
+}
+
+#[test]
+fn exists() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert!(cs
+        .exists(query("tok & tok & #1 . #2 & #1:arity=0,1 & #2:arity=0,1"))
+        .unwrap());
+    assert!(cs.exists(query("tok=\"example\" . tok=\"more\"")).unwrap());
+    assert!(!cs.exists(query("tok=\"more\" . tok=\"example\"")).unwrap());
+    assert!(!cs.exists(query("tok=\"not-in-corpus\"")).unwrap());
+}
+
+#[test]
+fn exact_value_set_search() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (i, pos) in ["NN", "NE", "VVFIN", "NN", "ART"].iter().enumerate() {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#tok{}", i),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |query: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    };
+
+    assert_eq!(3, count("pos=\"NN\" | pos=\"NE\""));
+    assert_eq!(3, count("pos in (\"NN\", \"NE\")"));
+    assert_eq!(3, count("default_ns:pos in (\"NN\",\"NE\",\"unknown\")"));
+    assert_eq!(1, count("pos in (\"ART\")"));
+    assert_eq!(0, count("pos in (\"unknown\")"));
+    assert_eq!(
+        2,
+        count("pos in (\"NN\", \"NE\") . pos in (\"VVFIN\", \"NE\")")
+    );
+}
+
+#[test]
+fn component_scoped_node_search() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    // "it" refers to "example" and "this" to "Is"
+    for (source, target, name) in [(6, 2, "anaphor"), (1, 0, "dummy")].iter() {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: format!("root/doc1#tok{}", source),
+            target_node: format!("root/doc1#tok{}", target),
+            layer: "coref".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: name.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |query: &str| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+    };
+
+    assert_eq!(4, count("node @component=\"Pointing\"").unwrap());
+    assert_eq!(4, count("tok @component=\"Pointing/coref\"").unwrap());
+    assert_eq!(
+        2,
+        count("node @component=\"Pointing/coref/anaphor\"").unwrap()
+    );
+    assert_eq!(0, count("node @component=\"Pointing/other\"").unwrap());
+    assert_eq!(
+        1,
+        count("tok=\"example\" @component=\"Pointing/coref/anaphor\"").unwrap()
+    );
+    assert_eq!(
+        0,
+        count("tok=\"this\" @component=\"Pointing/coref/anaphor\"").unwrap()
+    );
+    // the restriction is also applied when the node is the target of a join
+    assert_eq!(
+        1,
+        count("tok=\"this\" . node @component=\"Pointing/coref\"").unwrap()
+    );
+    assert!(count("node @component=\"NoSuchType\"").is_err());
+}
+
+#[test]
+fn find_with_offsets() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#span1".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#span1".to_string(),
+        anno_ns: "".to_string(),
+        anno_name: "phrase".to_string(),
+        anno_value: "np".to_string(),
+    })
+    .unwrap();
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#span1",
+        &["root/doc1#tok2", "root/doc1#tok3", "root/doc1#tok4"],
+    );
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "phrase=\"np\" _i_ tok=\"more\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let options = FindOptions {
+        with_offsets: true,
+        ..Default::default()
+    };
+    let (result, _) = cs.find_with_options(query, options).unwrap();
+    assert_eq!(1, result.len());
+    assert_eq!("phrase::root/doc1#span1 root/doc1#tok3", result[0].match_id);
+    assert_eq!(
+        vec![
+            Some(TextOffset {
+                left_token: 2,
+                right_token: 4
+            }),
+            Some(TextOffset {
+                left_token: 3,
+                right_token: 3
+            })
+        ],
+        result[0].offsets
+    );
+
+    // only the offsets of the projected nodes are returned
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "phrase=\"np\" _i_ tok=\"more\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let options = FindOptions {
+        projection: Some(vec!["#2".to_string()]),
+        with_offsets: true,
+        ..Default::default()
+    };
+    let (result, _) = cs.find_with_options(query, options).unwrap();
+    assert_eq!(1, result.len());
+    assert_eq!("root/doc1#tok3", result[0].match_id);
+    assert_eq!(
+        vec![Some(TextOffset {
+            left_token: 3,
+            right_token: 3
+        })],
+        result[0].offsets
+    );
+}
+
+#[test]
+fn execute_batch() {
+    let (tmp, cs) = simple_corpus();
+
+    let queries = vec![
+        BatchQuery {
+            name: "tokens".to_string(),
+            query: "tok".to_string(),
+            query_language: QueryLanguage::AQL,
+        },
+        BatchQuery {
+            name: "this".to_string(),
+            query: "tok=\"this\"".to_string(),
+            query_language: QueryLanguage::AQL,
+        },
+        BatchQuery {
+            name: "invalid".to_string(),
+            query: "tok &".to_string(),
+            query_language: QueryLanguage::AQL,
+        },
+        BatchQuery {
+            name: "tokens".to_string(),
+            query: "tok".to_string(),
+            query_language: QueryLanguage::AQL,
+        },
+        BatchQuery {
+            name: "../outside".to_string(),
+            query: "tok".to_string(),
+            query_language: QueryLanguage::AQL,
+        },
+    ];
+
+    let out_dir = tmp.path().join("batch");
+    let results = cs
+        .execute_batch(&["root"], &queries, &out_dir, &BatchConfig::default())
+        .unwrap();
+    assert_eq!(5, results.len());
+
+    assert_eq!(Some(11), results[0].match_count);
+    assert_eq!(Some(out_dir.join("tokens.csv")), results[0].output_file);
+    assert_eq!(
+        "match_count,document_count\n11,1\n",
+        std::fs::read_to_string(out_dir.join("tokens.csv")).unwrap()
+    );
+    assert_eq!(Some(1), results[1].match_count);
+    assert!(results[2].error.is_some());
+    assert!(results[2].output_file.is_none());
+    // duplicate and invalid names are reported as error
+    assert!(results[3].error.is_some());
+    assert!(results[4].error.is_some());
+    assert!(!tmp.path().join("outside.csv").exists());
+
+    let config = BatchConfig {
+        mode: BatchMode::Find,
+        format: BatchOutputFormat::JSON,
+        parallel: true,
+        timeout: None,
+    };
+    let results = cs
+        .execute_batch(&["root"], &queries[1..2], &out_dir, &config)
+        .unwrap();
+    assert_eq!(Some(1), results[0].match_count);
+    let matches: Vec<String> =
+        serde_json::from_reader(std::fs::File::open(out_dir.join("this.json")).unwrap()).unwrap();
+    assert_eq!(vec!["root/doc1#tok1".to_string()], matches);
+}
+
+#[test]
+fn regex_flags() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc1#tok0".to_string(),
+        target_node: "root/doc1#tok1".to_string(),
+        layer: "default_ns".to_string(),
+        component_type: "Pointing".to_string(),
+        component_name: "dep".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdgeLabel {
+        source_node: "root/doc1#tok0".to_string(),
+        target_node: "root/doc1#tok1".to_string(),
+        layer: "default_ns".to_string(),
+        component_type: "Pointing".to_string(),
+        component_name: "dep".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "func".to_string(),
+        anno_value: "SUBJ".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(0, cs.count(query("tok=/is/")).unwrap());
+    assert_eq!(1, cs.count(query("tok=/(?i)is/")).unwrap());
+    assert_eq!(1, cs.count(query("tok=/is/i")).unwrap());
+    assert_eq!(3, cs.count(query("tok=/T.*/i")).unwrap());
+    assert_eq!(8, cs.count(query("tok!=/T.*/i")).unwrap());
+
+    assert_eq!(0, cs.count(query("tok ->dep[func=/subj/] tok")).unwrap());
+    assert_eq!(1, cs.count(query("tok ->dep[func=/subj/i] tok")).unwrap());
+    assert_eq!(1, cs.count(query("tok ->dep[func=/(?i)s.*/] tok")).unwrap());
+
+    // invalid flags and patterns are reported for node and edge annotation searches
+    for aql in &[
+        "tok=/(?y)is/",
+        "tok ->dep[func=/(?y)subj/] tok",
+        "tok ->dep[func=/(/] tok",
+    ] {
+        let result = cs.count(query(aql));
+        if let Err(GraphAnnisError::AQLSemanticError(e)) = result {
+            assert!(e.location.is_some());
+        } else {
+            panic!("Expected semantic error for {}, got {:?}", aql, result);
+        }
+    }
+}
+
+#[test]
+fn matched_nodes_of_matches() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#span".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#span".to_string(),
+        anno_ns: "syntax".to_string(),
+        anno_name: "cat".to_string(),
+        anno_value: "NP".to_string(),
+    })
+    .unwrap();
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#span",
+        &["root/doc1#tok2", "root/doc1#tok4"],
+    );
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "cat=\"NP\" _l_ tok=\"example\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let mut match_ids = cs.find(query, 0, None, ResultOrder::Normal).unwrap();
+    assert_eq!(1, match_ids.len());
+    match_ids.push("root/doc1#tok0 root/doc1#missing".to_string());
+
+    let result = cs.matched_nodes("root", &match_ids).unwrap();
+    assert_eq!(2, result.len());
+
+    let span = result[0][0].as_ref().unwrap();
+    assert_eq!("root/doc1#span", span.node_name);
+    let span_anno = span.annotation.as_ref().unwrap();
+    assert_eq!("syntax", span_anno.key.ns);
+    assert_eq!("cat", span_anno.key.name);
+    assert_eq!("NP", span_anno.val);
+    // the span does not cover the token in the middle
+    assert_eq!(
+        vec!["root/doc1#tok2", "root/doc1#tok4"],
+        span.covered_tokens
+    );
+
+    let token = result[0][1].as_ref().unwrap();
+    assert_eq!("root/doc1#tok2", token.node_name);
+    // token matches are not qualified with an annotation
+    assert!(token.annotation.is_none());
+    assert_eq!(vec!["root/doc1#tok2"], token.covered_tokens);
+
+    let token = result[1][0].as_ref().unwrap();
+    assert!(token.annotation.is_none());
+    assert_eq!(vec!["root/doc1#tok0"], token.covered_tokens);
+    assert!(result[1][1].is_none());
+}
+
+#[test]
+fn negated_meta_constraint() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    for doc in &["root/doc2", "root/doc3"] {
+        g.add_event(UpdateEvent::AddNode {
+            node_name: doc.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: doc.to_string(),
+            target_node: "root".to_string(),
+            layer: "annis".to_string(),
+            component_type: "PartOf".to_string(),
+            component_name: "".to_string(),
+        })
+        .unwrap();
+    }
+    for doc in &["root/doc1", "root/doc2", "root/doc3"] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "annis".to_string(),
+            anno_name: "doc".to_string(),
+            anno_value: doc.to_string(),
+        })
+        .unwrap();
+        example_generator::create_tokens(&mut g, Some(doc));
+    }
+    // doc3 has no genre at all
+    for (doc, genre) in &[("root/doc1", "fiction"), ("root/doc2", "news")] {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: doc.to_string(),
+            anno_ns: "".to_string(),
+            anno_name: "genre".to_string(),
+            anno_value: genre.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count = |aql: &str, query_language: QueryLanguage| {
+        cs.count(SearchQuery {
+            corpus_names: &["root"],
+            query: aql,
+            query_language,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    };
+
+    // each document has 11 token
+    assert_eq!(33, count("tok", QueryLanguage::AQL));
+    // documents without the annotation fulfill the negated constraint
+    assert_eq!(
+        22,
+        count("tok & !meta::genre=\"fiction\"", QueryLanguage::AQL)
+    );
+    assert_eq!(
+        11,
+        count("tok & !meta::genre=/fic.*|news/", QueryLanguage::AQL)
+    );
+    assert_eq!(
+        33,
+        count("tok & !meta::genre=\"poetry\"", QueryLanguage::AQL)
+    );
+    // only documents with a genre different from "fiction" are excluded
+    assert_eq!(
+        22,
+        count("tok & !meta::genre!=\"fiction\"", QueryLanguage::AQL)
+    );
+    // the legacy (non-negated) constraint requires the annotation
+    assert_eq!(
+        11,
+        count("tok & meta::genre!=\"fiction\"", QueryLanguage::AQLQuirksV3)
+    );
+    assert_eq!(
+        22,
+        count("tok & !meta::genre=\"fiction\"", QueryLanguage::AQLQuirksV3)
+    );
+
+    let result = cs.validate_query(&["root"], "!meta::genre=\"fiction\"", QueryLanguage::AQL);
+    assert!(result.is_err());
+}
+
+#[test]
+fn query_warnings() {
+    let (_tmp, cs) = simple_corpus();
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["root"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let (count, warnings) = cs.count_with_warnings(query("tok")).unwrap();
+    assert_eq!(11, count);
+    assert!(warnings.is_empty());
+
+    // there is no dependency component, which would otherwise silently produce an empty result
+    let (count, warnings) = cs.count_with_warnings(query("tok ->dep tok")).unwrap();
+    assert_eq!(0, count);
+    assert_eq!(
+        vec![QueryWarning::ComponentNotLoaded {
+            component: "Pointing/dep".to_string()
+        }],
+        warnings
+    );
+
+    // the alternative with the unknown segmentation is ignored, but the other one is still executed
+    let (matches, warnings) = cs
+        .find_with_options(query("tok=\"Is\" | (tok .seg tok)"), FindOptions::default())
+        .unwrap();
+    let matches: Vec<String> = matches.into_iter().map(|m| m.match_id).collect();
+    assert_eq!(vec!["root/doc1#tok0"], matches);
+    assert_eq!(1, warnings.len());
+    assert!(matches!(
+        warnings[0],
+        QueryWarning::AlternativeIgnored { alternative: 2, .. }
+    ));
+}
+
+#[test]
+fn find_with_cursor() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    for corpus in &["corpus1", "corpus2"] {
+        let mut g = GraphUpdate::new();
+        g.add_event(UpdateEvent::AddNode {
+            node_name: corpus.to_string(),
+            node_type: "corpus".to_string(),
+        })
+        .unwrap();
+        for doc in &["doc1", "doc2", "doc3"] {
+            let doc = format!("{}/{}", corpus, doc);
+            g.add_event(UpdateEvent::AddNode {
+                node_name: doc.clone(),
+                node_type: "corpus".to_string(),
+            })
+            .unwrap();
+            g.add_event(UpdateEvent::AddNodeLabel {
+                node_name: doc.clone(),
+                anno_ns: "annis".to_string(),
+                anno_name: "doc".to_string(),
+                anno_value: doc.clone(),
+            })
+            .unwrap();
+            g.add_event(UpdateEvent::AddEdge {
+                source_node: doc.clone(),
+                target_node: corpus.to_string(),
+                layer: "annis".to_string(),
+                component_type: "PartOf".to_string(),
+                component_name: "".to_string(),
+            })
+            .unwrap();
+            example_generator::create_tokens(&mut g, Some(&doc));
+        }
+        cs.apply_update(corpus, &mut g).unwrap();
+    }
+
+    let query = |aql| SearchQuery {
+        corpus_names: &["corpus2", "corpus1"],
+        query: aql,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    for aql in &["tok", "tok . tok", "tok=/.*s/ ->dep tok"] {
+        for order in &[
+            ResultOrder::Normal,
+            ResultOrder::Inverted,
+            ResultOrder::NotSorted,
+            ResultOrder::Seeded(42),
+        ] {
+            let expected = cs.find(query(aql), 0, None, *order).unwrap();
+
+            let mut all_pages = Vec::new();
+            let mut cursor: Option<FindCursor> = None;
+            loop {
+                let (page, next_cursor) = cs
+                    .find_with_cursor(query(aql), cursor.as_ref(), 7, *order)
+                    .unwrap();
+                assert!(page.len() <= 7);
+                all_pages.extend(page);
+                match next_cursor {
+                    Some(next_cursor) => {
+                        // the cursor can be passed on as string
+                        let parsed: FindCursor = next_cursor.to_string().parse().unwrap();
+                        assert_eq!(next_cursor, parsed);
+                        cursor = Some(parsed);
+                    }
+                    None => break,
+                }
+            }
+            assert_eq!(expected, all_pages, "query {} with order {:?}", aql, order);
+        }
+    }
+
+    // a cursor can't be used for a different query
+    let (_, cursor) = cs
+        .find_with_cursor(query("tok"), None, 5, ResultOrder::Normal)
+        .unwrap();
+    assert!(cs
+        .find_with_cursor(query("tok . tok"), cursor.as_ref(), 5, ResultOrder::Normal)
+        .is_err());
+    assert!(cs
+        .find_with_cursor(query("tok"), None, 5, ResultOrder::Randomized)
+        .is_err());
+
+    // the cursor is invalid after the corpus has been changed
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "corpus1/doc1#tok0".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "pos".to_string(),
+        anno_value: "VBZ".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("corpus1", &mut g).unwrap();
+    assert!(cs
+        .find_with_cursor(query("tok"), cursor.as_ref(), 5, ResultOrder::Normal)
+        .is_err());
+}
+
+#[test]
+fn contradictory_constraints() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for (i, pos) in ["VBZ", "DT", "NN"].iter().enumerate() {
+        g.add_event(UpdateEvent::AddNodeLabel {
+            node_name: format!("root/doc1#tok{}", i),
+            anno_ns: "default_ns".to_string(),
+            anno_name: "pos".to_string(),
+            anno_value: pos.to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let count_query = |aql| {
+        cs.count_with_warnings(SearchQuery {
+            corpus_names: &["root"],
+            query: aql,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        })
+        .unwrap()
+    };
+    let is_contradiction = |w: &QueryWarning| matches!(w, QueryWarning::Contradiction { .. });
+
+    for aql in &[
+        "tok=\"this\" & tok=\"example\" & #1 _ident_ #2",
+        "tok=\"this\" & tok!=\"this\" & #1 _ident_ #2",
+        "pos=\"NN\" & pos=\"DT\" & tok & #1 _ident_ #3 & #3 _ident_ #2",
+        "pos=\"NN\" & default_ns:pos=\"VBZ\" & #1 _ident_ #2",
+        "tok .3,1 tok",
+        "tok ^5,2 tok",
+    ] {
+        let (count, warnings) = count_query(aql);
+        assert_eq!(0, count, "{}", aql);
+        assert!(warnings.iter().any(is_contradiction), "{}", aql);
+    }
+
+    // an alternative without a contradiction is still executed
+    let (count, warnings) =
+        count_query("(tok=\"this\" & tok=\"example\" & #1 _ident_ #2) | tok=\"this\"");
+    assert_eq!(1, count);
+    assert!(matches!(
+        warnings[0],
+        QueryWarning::Contradiction { alternative: 1, .. }
+    ));
+
+    for aql in &[
+        "tok=\"this\" & tok=/th.*/ & #1 _ident_ #2",
+        "pos=\"DT\" & pos!=\"NN\" & #1 _ident_ #2",
+        "tok=\"this\" . tok=\"example\"",
+        "tok .1,3 tok",
+    ] {
+        let (count, warnings) = count_query(aql);
+        assert!(count > 0, "{}", aql);
+        assert!(!warnings.iter().any(is_contradiction), "{}", aql);
+    }
+}
+
+#[test]
+fn quirks_report() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    assert_eq!(
+        vec![QuirksRule::DocumentNameOrder, QuirksRule::MatchIdEscaping],
+        cs.quirks_report("tok . tok").unwrap()
+    );
+
+    let report = cs.quirks_report("tok .* tok | tok ^* tok").unwrap();
+    assert!(matches!(
+        report[0],
+        QuirksRule::PrecedenceLimited { location: Some(_) }
+    ));
+    assert!(matches!(
+        report[1],
+        QuirksRule::NearLimited { location: Some(_) }
+    ));
+
+    let report = cs
+        .quirks_report("cat & cat & cat & #1 > #2 & #1 > #3")
+        .unwrap();
+    assert!(report.contains(&QuirksRule::DistributedJoins {
+        variable: "1".to_string(),
+        joins: 2,
+    }));
+    assert!(report.contains(&QuirksRule::LocalReflexivity { alternative: 1 }));
+
+    let report = cs.quirks_report("tok & meta::doc=\"doc1\"").unwrap();
+    assert!(matches!(
+        report[0],
+        QuirksRule::LegacyMetaSearch { location: Some(_) }
+    ));
+
+    // invalid queries are reported as error
+    assert!(cs.quirks_report("tok &").is_err());
+}
+
+#[test]
+fn optional_nodes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    for target in &["root/doc1#tok1", "root/doc1#tok3"] {
+        g.add_event(UpdateEvent::AddEdge {
+            source_node: "root/doc1#tok2".to_string(),
+            target_node: target.to_string(),
+            layer: "default_ns".to_string(),
+            component_type: "Pointing".to_string(),
+            component_name: "dep".to_string(),
+        })
+        .unwrap();
+    }
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = |query, query_language| SearchQuery {
+        corpus_names: &["root"],
+        query,
+        query_language,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let count = |aql| cs.count(query(aql, QueryLanguage::AQL));
+
+    // tokens that are not followed by "example"
+    assert_eq!(10, count("tok & tok=\"example\"? & #1 !. #2").unwrap());
+    assert_eq!(10, count("tok=\"example\"? & tok & #2 !. #1").unwrap());
+    // tokens without an outgoing or incoming pointing relation
+    assert_eq!(10, count("tok & tok? & #1 !->dep #2").unwrap());
+    assert_eq!(9, count("tok? & tok & #1 !->dep #2").unwrap());
+    // tokens that are not followed by two other tokens
+    assert_eq!(2, count("tok & tok? & tok? & #1 !. #2 & #2 !. #3").unwrap());
+    // unary operators restrict the optional node
+    assert_eq!(8, count("tok & tok? & #1 !. #2 & #2:len=2").unwrap());
+
+    // the optional node is not part of the match
+    let result = cs
+        .find(
+            query(
+                "tok=\"complicated\"? & tok=\"example\" & #2 !. #1",
+                QueryLanguage::AQL,
+            ),
+            0,
+            None,
+            ResultOrder::Normal,
+        )
+        .unwrap();
+    assert_eq!(vec!["root/doc1#tok2"], result);
+    assert_eq!(
+        0,
+        count("tok=\"more\"? & tok=\"example\" & #2 !. #1").unwrap()
+    );
+
+    // optional nodes must be connected with negated operators
+    assert!(matches!(
+        count("tok & tok? & #1 . #2"),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+    assert!(matches!(
+        count("tok & tok?"),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+    assert!(matches!(
+        cs.count(query("tok & tok? & #1 !. #2", QueryLanguage::AQLQuirksV3)),
+        Err(GraphAnnisError::AQLSemanticError(_))
+    ));
+}
@@ -0,0 +1,326 @@
+use crate::annis::db::example_generator;
+use crate::corpusstorage::{
+    DerivedComponentsPolicy, ExportFormat, ImportFormat, QueryLanguage, SearchQuery,
+    UnicodeNormalization,
+};
+use crate::errors::{CorpusStorageError, GraphAnnisError};
+use crate::update::{GraphUpdate, UpdateEvent};
+use crate::CorpusStorage;
+
+use super::simple_corpus;
+
+#[test]
+fn apply_update_add_and_delete_nodes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/subCorpus1/doc1"));
+    example_generator::create_tokens(&mut g, Some("root/subCorpus1/doc2"));
+
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/subCorpus1/doc1#tok1".to_owned(),
+        target_node: "root/subCorpus1/doc1#tok2".to_owned(),
+        layer: "dep".to_owned(),
+        component_type: "Pointing".to_owned(),
+        component_name: "dep".to_owned(),
+    })
+    .unwrap();
+
+    cs.apply_update("root", &mut g).unwrap();
+
+    let node_query = SearchQuery {
+        corpus_names: &["root"],
+        query: "node",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+
+    let node_count = cs.count(node_query.clone()).unwrap();
+    assert_eq!(22, node_count);
+
+    let dep_query = SearchQuery {
+        corpus_names: &["root"],
+        query: "node ->dep node",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    let edge_count = cs.count(dep_query.clone()).unwrap();
+    assert_eq!(1, edge_count);
+
+    // delete one of the tokens
+    let mut g = GraphUpdate::new();
+    g.add_event(UpdateEvent::DeleteNode {
+        node_name: "root/subCorpus1/doc1#tok2".to_string(),
+    })
+    .unwrap();
+    cs.apply_update("root", &mut g).unwrap();
+
+    let node_count = cs.count(node_query).unwrap();
+    assert_eq!(21, node_count);
+    let edge_count = cs.count(dep_query).unwrap();
+    assert_eq!(0, edge_count);
+}
+
+fn add_token_with_value(g: &mut GraphUpdate, value: &str) {
+    example_generator::create_corpus_structure_simple(g);
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#tok1".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#tok1".to_string(),
+        anno_ns: "annis".to_string(),
+        anno_name: "tok".to_string(),
+        anno_value: value.to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddEdge {
+        source_node: "root/doc1#tok1".to_string(),
+        target_node: "root/doc1".to_string(),
+        layer: "annis".to_string(),
+        component_type: "PartOf".to_string(),
+        component_name: "".to_string(),
+    })
+    .unwrap();
+}
+
+#[test]
+fn unicode_normalization() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    // "Café" with a combining accent (NFD)
+    let mut g = GraphUpdate::new();
+    add_token_with_value(&mut g, "Cafe\u{0301}");
+    cs.apply_update("root", &mut g).unwrap();
+
+    // query with the precomposed (NFC) and decomposed (NFD) form
+    for query in &["\"Caf\u{00E9}\"", "\"Cafe\u{0301}\"", "/Caf\u{00E9}/"] {
+        let query = SearchQuery {
+            corpus_names: &["root"],
+            query,
+            query_language: QueryLanguage::AQL,
+            timeout: None,
+            document_filter: Vec::new(),
+        };
+        assert_eq!(1, cs.count(query).unwrap());
+    }
+
+    // only the literals are normalized, error locations (in bytes) still refer to the original query
+    let error_column = |query| match cs.count(SearchQuery {
+        corpus_names: &["root"],
+        query,
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    }) {
+        Err(GraphAnnisError::AQLSemanticError(err)) => err.location.unwrap().start.column,
+        other => panic!("expected a semantic error, got {:?}", other),
+    };
+    assert_eq!(
+        error_column("tok=\"Cafexx\" & #1 . #2"),
+        error_column("tok=\"Cafe\u{0301}\" & #1 . #2")
+    );
+}
+
+#[test]
+fn renormalize_unicode() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    cs.set_unicode_normalization(UnicodeNormalization::None);
+
+    let mut g = GraphUpdate::new();
+    add_token_with_value(&mut g, "Cafe\u{0301}");
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = SearchQuery {
+        corpus_names: &["root"],
+        query: "\"Caf\u{00E9}\"",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(0, cs.count(query.clone()).unwrap());
+
+    cs.set_unicode_normalization(UnicodeNormalization::NFC);
+    cs.renormalize_unicode("root").unwrap();
+    assert_eq!(1, cs.count(query.clone()).unwrap());
+    // a corpus is not created by renormalizing it
+    assert!(cs.renormalize_unicode("unknown").is_err());
+    assert!(!tmp.path().join("unknown").exists());
+
+    // the normalized values must have been persisted
+    std::mem::drop(cs);
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+    assert_eq!(1, cs.count(query).unwrap());
+}
+
+#[test]
+fn apply_update_statistics() {
+    let tmp = tempfile::tempdir().unwrap();
+    let cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+
+    let statistics = cs.apply_update("root", &mut g).unwrap();
+
+    assert_eq!(Some(&13), statistics.events_per_type.get("AddNode"));
+    assert_eq!(Some(&23), statistics.events_per_type.get("AddEdge"));
+    assert_eq!(None, statistics.events_per_type.get("DeleteNode"));
+    assert_eq!(47, statistics.number_of_events());
+    // all new nodes need to be indexed
+    assert_eq!(13, statistics.reindexed_nodes);
+}
+
+#[test]
+fn apply_metadata_update_with_schema() {
+    let (tmp, cs) = simple_corpus();
+
+    std::fs::write(
+        tmp.path().join("root").join("corpus-config.toml"),
+        r#"
+[[metadata]]
+name = "genre"
+values = ["news", "fiction"]
+
+[[metadata]]
+ns = "meta"
+name = "year"
+type = "integer"
+"#,
+    )
+    .unwrap();
+
+    let label = |node_name: &str, anno_ns: &str, anno_name: &str, anno_value: &str| {
+        UpdateEvent::AddNodeLabel {
+            node_name: node_name.to_string(),
+            anno_ns: anno_ns.to_string(),
+            anno_name: anno_name.to_string(),
+            anno_value: anno_value.to_string(),
+        }
+    };
+    let is_invalid = |events: Vec<UpdateEvent>| {
+        let mut u = GraphUpdate::new();
+        for e in events {
+            u.add_event(e).unwrap();
+        }
+        matches!(
+            cs.apply_metadata_update("root", &mut u),
+            Err(GraphAnnisError::CorpusStorage(
+                CorpusStorageError::InvalidMetadataUpdate { .. }
+            ))
+        )
+    };
+
+    // not a corpus or document node
+    assert!(is_invalid(vec![label(
+        "root/doc1#tok0",
+        "",
+        "genre",
+        "news"
+    )]));
+    // the node does not exist
+    assert!(is_invalid(vec![label("root/doc2", "", "genre", "news")]));
+    // reserved namespace
+    assert!(is_invalid(vec![label("root/doc1", "annis", "doc", "news")]));
+    // not declared
+    assert!(is_invalid(vec![label(
+        "root/doc1",
+        "",
+        "author",
+        "someone"
+    )]));
+    // invalid values
+    assert!(is_invalid(vec![label("root/doc1", "", "genre", "poetry")]));
+    assert!(is_invalid(vec![
+        label("root/doc1", "", "genre", "news"),
+        label("root/doc1", "meta", "year", "last year"),
+    ]));
+    // only labels can be changed
+    assert!(is_invalid(vec![UpdateEvent::AddNode {
+        node_name: "root/doc2".to_string(),
+        node_type: "corpus".to_string(),
+    }]));
+
+    let mut u = GraphUpdate::new();
+    u.add_event(label("root/doc1", "", "genre", "fiction"))
+        .unwrap();
+    u.add_event(label("root", "meta", "year", "1997")).unwrap();
+    cs.apply_metadata_update("root", &mut u).unwrap();
+
+    let corpus_graph = cs.corpus_graph("root").unwrap();
+    let doc1 = corpus_graph.get_node_id_from_name("root/doc1").unwrap();
+    let genre: Vec<_> = corpus_graph
+        .get_node_annos()
+        .get_annotations_for_item(&doc1)
+        .into_iter()
+        .filter(|a| a.key.name == "genre")
+        .map(|a| a.val.to_string())
+        .collect();
+    // the value of the rejected update has not been applied
+    assert_eq!(vec!["fiction"], genre);
+}
+
+#[test]
+fn rebuild_derived_components() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut cs = CorpusStorage::with_auto_cache_size(tmp.path(), false).unwrap();
+
+    let mut g = GraphUpdate::new();
+    example_generator::create_corpus_structure_simple(&mut g);
+    example_generator::create_tokens(&mut g, Some("root/doc1"));
+    g.add_event(UpdateEvent::AddNode {
+        node_name: "root/doc1#span".to_string(),
+        node_type: "node".to_string(),
+    })
+    .unwrap();
+    g.add_event(UpdateEvent::AddNodeLabel {
+        node_name: "root/doc1#span".to_string(),
+        anno_ns: "default_ns".to_string(),
+        anno_name: "phrase".to_string(),
+        anno_value: "NP".to_string(),
+    })
+    .unwrap();
+    example_generator::make_span(
+        &mut g,
+        "root/doc1#span",
+        &["root/doc1#tok1", "root/doc1#tok2"],
+    );
+    cs.apply_update("root", &mut g).unwrap();
+
+    let query = |corpus_names| SearchQuery {
+        corpus_names,
+        query: "phrase _l_ tok & #1 _r_ tok",
+        query_language: QueryLanguage::AQL,
+        timeout: None,
+        document_filter: Vec::new(),
+    };
+    assert_eq!(1, cs.count(query(&["root"])).unwrap());
+
+    assert!(cs.rebuild_derived_components("root").unwrap() > 11);
+    assert_eq!(1, cs.count(query(&["root"])).unwrap());
+
+    // import the exported corpus and rebuild the derived components after the import
+    let export_dir = tempfile::tempdir().unwrap();
+    let graphml_file = export_dir.path().join("root.graphml");
+    cs.export_to_fs(&["root"], &graphml_file, ExportFormat::GraphML)
+        .unwrap();
+    cs.set_derived_components_policy(DerivedComponentsPolicy::Rebuild);
+    cs.import_from_fs(
+        &graphml_file,
+        ImportFormat::GraphML,
+        Some("copy".to_string()),
+        false,
+        false,
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(1, cs.count(query(&["copy"])).unwrap());
+}
@@ -27,7 +27,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus1".to_string(),
             target_node: "root".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -43,7 +43,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus2".to_string(),
             target_node: "root".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -59,7 +59,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus1/doc1".to_string(),
             target_node: "root/subCorpus1".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -75,7 +75,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus1/doc2".to_string(),
             target_node: "root/subCorpus1".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -91,7 +91,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus2/doc3".to_string(),
             target_node: "root/subCorpus2".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -107,7 +107,7 @@ pub fn create_corpus_structure(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/subCorpus2/doc4".to_string(),
             target_node: "root/subCorpus2".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -140,7 +140,7 @@ pub fn create_corpus_structure_simple(update: &mut GraphUpdate) {
         .add_event(UpdateEvent::AddEdge {
             source_node: "root/doc1".to_string(),
             target_node: "root".to_string(),
-            layer: "".to_string(),
+            layer: "annis".to_string(),
             component_type: "PartOf".to_string(),
             component_name: "".to_string(),
         })
@@ -226,9 +226,9 @@ pub fn create_token_node(
         // add the token node to the document
         update
             .add_event(UpdateEvent::AddEdge {
-                source_node: parent_document.to_string(),
-                target_node: node_name.to_string(),
-                layer: "".to_string(),
+                source_node: node_name.to_string(),
+                target_node: parent_document.to_string(),
+                layer: "annis".to_string(),
                 component_type: "PartOf".to_string(),
                 component_name: "".to_string(),
             })
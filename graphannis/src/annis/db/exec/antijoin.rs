@@ -0,0 +1,245 @@
+use super::{CostEstimate, Desc, ExecutionNode, NodeSearchDesc};
+use crate::annis::db::AnnotationStorage;
+use crate::{
+    annis::operator::{BinaryOperator, UnaryOperator},
+    graph::Match,
+};
+use graphannis_core::{annostorage::MatchGroup, types::NodeID};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Refers to a node that is used as operand of an operator of the anti-join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntiJoinOperand {
+    /// The node at the given position of the outer match.
+    Outer(usize),
+    /// The optional node with the given index.
+    Optional(usize),
+}
+
+/// An optional node of the anti-join, which must not exist for a match of the outer execution node.
+pub struct OptionalNode<'a> {
+    pub node_search_desc: Arc<NodeSearchDesc>,
+    /// All matches of the node search, used if the candidates can't be retrieved by an operator.
+    pub all_matches: Option<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>>,
+    pub unary_operators: Vec<Box<dyn UnaryOperator + 'a>>,
+}
+
+/// An operator of the anti-join, where at least one operand is an optional node.
+pub struct AntiJoinOperator<'a> {
+    pub op: Box<dyn BinaryOperator + 'a>,
+    /// The inverse operator, which allows to retrieve the LHS from the RHS.
+    pub inverse_op: Option<Box<dyn BinaryOperator + 'a>>,
+    pub lhs: AntiJoinOperand,
+    pub rhs: AntiJoinOperand,
+}
+
+/// How the candidates for an optional node are found.
+enum CandidateSource {
+    /// Use the operator with the given index to retrieve the matches reachable from the other operand.
+    Operator { op_idx: usize, inverse: bool },
+    /// Check all matches of the node search, which are collected when the first outer match is processed.
+    NodeSearch(Option<Vec<Match>>),
+}
+
+/// Only outputs the matches of the outer execution node for which the optional nodes do not exist.
+///
+/// For each outer match, the optional nodes are searched one after another. The outer match is discarded as soon as
+/// there is a combination of matches for all optional nodes that fulfills all operators of the anti-join.
+pub struct AntiJoin<'a> {
+    outer: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+    optional_nodes: Vec<OptionalNode<'a>>,
+    operators: Vec<AntiJoinOperator<'a>>,
+    candidate_sources: Vec<CandidateSource>,
+    node_annos: &'a dyn AnnotationStorage<NodeID>,
+    desc: Option<Desc>,
+}
+
+impl<'a> AntiJoin<'a> {
+    pub fn new(
+        outer: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+        optional_nodes: Vec<OptionalNode<'a>>,
+        operators: Vec<AntiJoinOperator<'a>>,
+        node_annos: &'a dyn AnnotationStorage<NodeID>,
+        query_fragment: &str,
+    ) -> AntiJoin<'a> {
+        let desc = outer.get_desc().map(|outer_desc| {
+            let cost = outer_desc.cost.as_ref().map(|outer_cost| CostEstimate {
+                output: outer_cost.output,
+                processed_in_step: outer_cost.output,
+                intermediate_sum: outer_cost.intermediate_sum + outer_cost.output,
+            });
+            Desc {
+                component_nr: outer_desc.component_nr,
+                lhs: Some(Box::new(outer_desc.clone())),
+                rhs: None,
+                node_pos: outer_desc.node_pos.clone(),
+                impl_description: String::from("antijoin"),
+                query_fragment: query_fragment.to_string(),
+                cost,
+                operator: None,
+            }
+        });
+
+        let mut candidate_sources = Vec::with_capacity(optional_nodes.len());
+        for idx in 0..optional_nodes.len() {
+            // the candidates can be retrieved from any operand which is already bound
+            let is_bound = |operand: AntiJoinOperand| match operand {
+                AntiJoinOperand::Outer(_) => true,
+                AntiJoinOperand::Optional(other) => other < idx,
+            };
+            let seed = operators.iter().enumerate().find_map(|(op_idx, op_entry)| {
+                if op_entry.rhs == AntiJoinOperand::Optional(idx) && is_bound(op_entry.lhs) {
+                    Some(CandidateSource::Operator {
+                        op_idx,
+                        inverse: false,
+                    })
+                } else if op_entry.lhs == AntiJoinOperand::Optional(idx)
+                    && is_bound(op_entry.rhs)
+                    && op_entry.inverse_op.is_some()
+                {
+                    Some(CandidateSource::Operator {
+                        op_idx,
+                        inverse: true,
+                    })
+                } else {
+                    None
+                }
+            });
+            candidate_sources.push(seed.unwrap_or(CandidateSource::NodeSearch(None)));
+        }
+
+        AntiJoin {
+            outer,
+            optional_nodes,
+            operators,
+            candidate_sources,
+            node_annos,
+            desc,
+        }
+    }
+
+    fn get_match<'b>(
+        operand: AntiJoinOperand,
+        outer: &'b [Match],
+        bound: &'b [Match],
+    ) -> Option<&'b Match> {
+        match operand {
+            AntiJoinOperand::Outer(pos) => outer.get(pos),
+            AntiJoinOperand::Optional(idx) => bound.get(idx),
+        }
+    }
+
+    fn candidates(&self, idx: usize, outer: &[Match], bound: &[Match]) -> Cow<[Match]> {
+        match &self.candidate_sources[idx] {
+            CandidateSource::Operator { op_idx, inverse } => {
+                let op_entry = &self.operators[*op_idx];
+                let (op, source) = if *inverse {
+                    (op_entry.inverse_op.as_deref(), op_entry.rhs)
+                } else {
+                    (Some(op_entry.op.as_ref()), op_entry.lhs)
+                };
+                if let (Some(op), Some(source)) = (op, Self::get_match(source, outer, bound)) {
+                    let it_nodes = Box::new(op.retrieve_matches(source).map(|m| m.node).fuse());
+                    let desc = &self.optional_nodes[idx].node_search_desc;
+                    let candidates = self.node_annos.get_keys_for_iterator(
+                        desc.qname.0.as_deref(),
+                        desc.qname.1.as_deref(),
+                        it_nodes,
+                    );
+                    Cow::Owned(candidates.into_vec())
+                } else {
+                    Cow::Borrowed(&[])
+                }
+            }
+            CandidateSource::NodeSearch(all_matches) => {
+                Cow::Borrowed(all_matches.as_deref().unwrap_or_default())
+            }
+        }
+    }
+
+    /// Check if the optional node with the given index and all following ones exist for the outer match.
+    fn optional_nodes_exist(&self, idx: usize, outer: &[Match], bound: &mut Vec<Match>) -> bool {
+        if idx >= self.optional_nodes.len() {
+            return true;
+        }
+        let node = &self.optional_nodes[idx];
+        for candidate in self.candidates(idx, outer, bound).iter() {
+            let conditions_met = node
+                .node_search_desc
+                .cond
+                .iter()
+                .all(|f| (f)(candidate, self.node_annos))
+                && node
+                    .unary_operators
+                    .iter()
+                    .all(|op| op.filter_match(candidate));
+            if !conditions_met {
+                continue;
+            }
+            bound.push(candidate.clone());
+            // check all operators where both operands are bound now
+            let operators_met = self.operators.iter().all(|op_entry| {
+                match (
+                    Self::get_match(op_entry.lhs, outer, bound),
+                    Self::get_match(op_entry.rhs, outer, bound),
+                ) {
+                    (Some(lhs), Some(rhs)) => {
+                        (op_entry.op.is_reflexive() || lhs.different_to(rhs))
+                            && op_entry.op.filter_match(lhs, rhs)
+                    }
+                    _ => true,
+                }
+            });
+            if operators_met && self.optional_nodes_exist(idx + 1, outer, bound) {
+                return true;
+            }
+            bound.pop();
+        }
+        false
+    }
+}
+
+impl<'a> ExecutionNode for AntiJoin<'a> {
+    fn as_iter(&mut self) -> &mut dyn Iterator<Item = MatchGroup> {
+        self
+    }
+
+    fn get_desc(&self) -> Option<&Desc> {
+        self.desc.as_ref()
+    }
+
+    fn is_sorted_by_text(&self) -> bool {
+        self.outer.is_sorted_by_text()
+    }
+
+    fn is_sorted_by_document(&self) -> bool {
+        self.outer.is_sorted_by_document()
+    }
+}
+
+impl<'a> Iterator for AntiJoin<'a> {
+    type Item = MatchGroup;
+
+    fn next(&mut self) -> Option<MatchGroup> {
+        for (idx, source) in self.candidate_sources.iter_mut().enumerate() {
+            if let CandidateSource::NodeSearch(all_matches @ None) = source {
+                let node_search = self.optional_nodes[idx].all_matches.take();
+                *all_matches = Some(
+                    node_search
+                        .map(|it| it.filter_map(|mut m| m.pop()).collect())
+                        .unwrap_or_default(),
+                );
+            }
+        }
+
+        let mut bound = Vec::with_capacity(self.optional_nodes.len());
+        while let Some(m) = self.outer.next() {
+            bound.clear();
+            if !self.optional_nodes_exist(0, &m, &mut bound) {
+                return Some(m);
+            }
+        }
+        None
+    }
+}
@@ -1,12 +1,13 @@
 use graphannis_core::annostorage::MatchGroup;
 
-use super::{CostEstimate, Desc, ExecutionNode};
+use super::{CostEstimate, Desc, ExecutionNode, OperatorDesc};
 use crate::annis::db::query::conjunction::{BinaryOperatorEntry, UnaryOperatorEntry};
 use crate::annis::operator::{BinaryOperator, EstimationType, UnaryOperator};
 
 pub struct Filter<'a> {
     it: Box<dyn Iterator<Item = MatchGroup> + 'a>,
     desc: Option<Desc>,
+    sorted_by_document: bool,
 }
 
 fn calculate_binary_outputsize(op: &dyn BinaryOperator, num_tuples: usize) -> usize {
@@ -66,22 +67,25 @@ impl<'a> Filter<'a> {
                 cost: cost_est,
                 lhs: Some(Box::new(orig_desc.clone())),
                 rhs: None,
+                operator: Some(OperatorDesc::new(&op_entry)),
             })
         } else {
             None
         };
+        let sorted_by_document = exec.is_sorted_by_document();
         let it =
             exec.filter(move |tuple| op_entry.op.filter_match(&tuple[lhs_idx], &tuple[rhs_idx]));
         Filter {
             desc,
             it: Box::new(it),
+            sorted_by_document,
         }
     }
 
     pub fn new_unary(
         exec: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
         idx: usize,
-        op_entry: UnaryOperatorEntry,
+        op_entry: UnaryOperatorEntry<'a>,
     ) -> Filter<'a> {
         let desc = if let Some(orig_desc) = exec.get_desc() {
             let cost_est = if let Some(ref orig_cost) = orig_desc.cost {
@@ -102,14 +106,17 @@ impl<'a> Filter<'a> {
                 cost: cost_est,
                 lhs: Some(Box::new(orig_desc.clone())),
                 rhs: None,
+                operator: None,
             })
         } else {
             None
         };
+        let sorted_by_document = exec.is_sorted_by_document();
         let it = exec.filter(move |tuple| op_entry.op.filter_match(&tuple[idx]));
         Filter {
             desc,
             it: Box::new(it),
+            sorted_by_document,
         }
     }
 }
@@ -122,6 +129,11 @@ impl<'a> ExecutionNode for Filter<'a> {
     fn get_desc(&self) -> Option<&Desc> {
         self.desc.as_ref()
     }
+
+    fn is_sorted_by_document(&self) -> bool {
+        // filtering does not change the order of the matches
+        self.sorted_by_document
+    }
 }
 
 impl<'a> Iterator for Filter<'a> {
@@ -67,7 +67,7 @@ impl<'a> IndexJoin<'a> {
 
         IndexJoin {
             desc: Desc::join(
-                op_entry.op.as_ref(),
+                &op_entry,
                 lhs_desc.as_ref(),
                 rhs_desc,
                 "indexjoin",
@@ -0,0 +1,353 @@
+use super::{Desc, ExecutionNode};
+use crate::annis::db::aql::model::AnnotationComponentType;
+use crate::annis::db::query::conjunction::BinaryOperatorEntry;
+use crate::annis::db::AnnotationStorage;
+use crate::annis::operator::BinaryOperator;
+use crate::annis::util::TimeoutCheck;
+use crate::AnnotationGraph;
+use graphannis_core::{
+    annostorage::{MatchGroup, ValueSearch},
+    graph::{storage::GraphStorage, NODE_TYPE_KEY},
+    types::NodeID,
+};
+use std::sync::Arc;
+
+/// Finds the document a node belongs to by following the `PartOf` edges of the corpus structure.
+#[derive(Clone)]
+pub struct DocumentLookup<'a> {
+    node_annos: &'a dyn AnnotationStorage<NodeID>,
+    part_of: Vec<Arc<dyn GraphStorage>>,
+}
+
+impl<'a> DocumentLookup<'a> {
+    pub fn new(db: &'a AnnotationGraph) -> DocumentLookup<'a> {
+        let part_of = db
+            .get_all_components(Some(AnnotationComponentType::PartOf), None)
+            .into_iter()
+            .filter_map(|c| db.get_graphstorage(&c))
+            .collect();
+        DocumentLookup {
+            node_annos: db.get_node_annos(),
+            part_of,
+        }
+    }
+
+    /// Get the document of the node, which is the node itself for corpus and document nodes and the target of the
+    /// `PartOf` edge for all other nodes.
+    ///
+    /// Returns `None` if the node is not part of any document.
+    pub fn document(&self, node: NodeID) -> Option<NodeID> {
+        let is_corpus_node = self
+            .node_annos
+            .get_value_for_item(&node, &NODE_TYPE_KEY)
+            .map(|node_type| node_type == "corpus")
+            .unwrap_or(false);
+        if is_corpus_node {
+            return Some(node);
+        }
+        self.part_of
+            .iter()
+            .flat_map(|gs| gs.get_outgoing_edges(node))
+            .min()
+    }
+
+    /// Get all nodes of the graph in the order in which the matches are sorted by the merge join: first the nodes
+    /// that are not part of any document and then the nodes of each document, with the documents sorted by their
+    /// node ID.
+    pub fn nodes_by_document(&self) -> impl Iterator<Item = NodeID> + 'a {
+        let lookup = self.clone();
+        let without_document = self
+            .node_annos
+            .exact_anno_search(
+                Some(&NODE_TYPE_KEY.ns),
+                &NODE_TYPE_KEY.name,
+                ValueSearch::Any,
+            )
+            .map(|m| m.node)
+            .filter(move |node| lookup.document(*node).is_none());
+
+        let mut documents: Vec<NodeID> = self
+            .node_annos
+            .exact_anno_search(
+                Some(&NODE_TYPE_KEY.ns),
+                &NODE_TYPE_KEY.name,
+                Some("corpus").into(),
+            )
+            .map(|m| m.node)
+            .collect();
+        documents.sort_unstable();
+        documents.dedup();
+
+        let lookup = self.clone();
+        let with_document = documents.into_iter().flat_map(move |document| {
+            // the corpus or document node itself and all nodes that belong to it
+            let mut nodes: Vec<NodeID> = lookup
+                .part_of
+                .iter()
+                .flat_map(|gs| gs.get_ingoing_edges(document))
+                .filter(|node| lookup.document(*node) == Some(document))
+                .collect();
+            nodes.push(document);
+            nodes.sort_unstable();
+            nodes.dedup();
+            nodes
+        });
+
+        without_document.chain(with_document)
+    }
+}
+
+type DocumentStream<'a> = Box<dyn Iterator<Item = (Option<NodeID>, MatchGroup)> + 'a>;
+
+/// Create a stream of the matches of an execution node together with their document.
+///
+/// If the execution node does not already produce its output in document order, all matches are collected and
+/// sorted. Collecting the matches is stopped when the timeout is exceeded.
+fn stream_by_document<'a>(
+    exec: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+    idx: usize,
+    documents: DocumentLookup<'a>,
+    timeout: Option<TimeoutCheck>,
+) -> DocumentStream<'a> {
+    if exec.is_sorted_by_document() {
+        return Box::new(exec.map(move |m| (documents.document(m[idx].node), m)));
+    }
+    let mut result = Vec::new();
+    for (match_nr, m) in exec.enumerate() {
+        if match_nr % 1_000 == 0 && timeout.map(|t| t.check().is_err()).unwrap_or(false) {
+            return Box::new(std::iter::empty());
+        }
+        result.push((documents.document(m[idx].node), m));
+    }
+    // use a stable sort to keep the original order inside a document
+    result.sort_by_key(|(document, _)| *document);
+    Box::new(result.into_iter())
+}
+
+/// Estimate the number of processed items when reading an input of the merge join with the given output size.
+///
+/// Inputs that are not sorted by document have to be sorted first.
+pub fn sort_cost(out: usize, sorted: bool) -> usize {
+    if sorted {
+        out
+    } else {
+        out.saturating_add((out as f64 * (out as f64).log2().max(1.0)).round() as usize)
+    }
+}
+
+/// Estimate the number of processed items of the merge join.
+///
+/// Inputs that are not sorted by document have to be sorted first and only the matches of the same document are
+/// compared with each other.
+pub fn estimated_cost(
+    out_lhs: usize,
+    lhs_sorted: bool,
+    out_rhs: usize,
+    rhs_sorted: bool,
+    number_of_documents: usize,
+) -> usize {
+    sort_cost(out_lhs, lhs_sorted)
+        .saturating_add(sort_cost(out_rhs, rhs_sorted))
+        .saturating_add(out_lhs.saturating_mul(out_rhs) / std::cmp::max(number_of_documents, 1))
+}
+
+/// A join for operators that can only be true for nodes of the same document.
+///
+/// Both sides are ordered by their document and only the matches of the same document are compared with each other.
+/// Inputs that are already in document order are streamed, so only the RHS matches of the current document are
+/// held in memory.
+/// The output of this join is in document order too, so sorting can be skipped by joins that use it as input.
+pub struct MergeJoin<'a> {
+    lhs: Option<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>>,
+    rhs: Option<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>>,
+    lhs_idx: usize,
+    rhs_idx: usize,
+    op: Box<dyn BinaryOperator + 'a>,
+    documents: DocumentLookup<'a>,
+    timeout: Option<TimeoutCheck>,
+    desc: Desc,
+    global_reflexivity: bool,
+
+    lhs_stream: Option<DocumentStream<'a>>,
+    rhs_stream: Option<DocumentStream<'a>>,
+    current_lhs: Option<(Option<NodeID>, MatchGroup)>,
+    /// The next RHS match which has not been added to the group of the current document yet.
+    next_rhs: Option<(Option<NodeID>, MatchGroup)>,
+    rhs_group_document: Option<Option<NodeID>>,
+    rhs_group: Vec<MatchGroup>,
+    pos_rhs: usize,
+    processed: usize,
+}
+
+impl<'a> MergeJoin<'a> {
+    /// Create a new `MergeJoin`
+    /// # Arguments
+    ///
+    /// * `op_entry` - The operator that connects the LHS and RHS, it must only be true for nodes of the same document
+    /// * `lhs` - An iterator for a left-hand-side
+    /// * `rhs` - An iterator for a right-hand-side
+    /// * `lhs_idx` - The index of the element in the LHS that should be used as operand
+    /// * `rhs_idx` - The index of the element in the RHS that should be used as operand
+    /// * `db` - The graph used to get the document of a node
+    /// * `number_of_documents` - The number of documents in the corpus, used for the cost estimation
+    /// * `timeout` - If given, the join stops producing output when the timeout is exceeded
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        op_entry: BinaryOperatorEntry<'a>,
+        lhs: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+        rhs: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+        lhs_idx: usize,
+        rhs_idx: usize,
+        db: &'a AnnotationGraph,
+        number_of_documents: usize,
+        timeout: Option<TimeoutCheck>,
+    ) -> MergeJoin<'a> {
+        let lhs_sorted = lhs.is_sorted_by_document();
+        let rhs_sorted = rhs.is_sorted_by_document();
+
+        let processed_func = |_, out_lhs: usize, out_rhs: usize| {
+            estimated_cost(
+                out_lhs,
+                lhs_sorted,
+                out_rhs,
+                rhs_sorted,
+                number_of_documents,
+            )
+        };
+
+        let desc = Desc::join(
+            &op_entry,
+            lhs.get_desc(),
+            rhs.get_desc(),
+            "mergejoin (document)",
+            &format!(
+                "#{} {} #{}",
+                op_entry.node_nr_left, op_entry.op, op_entry.node_nr_right
+            ),
+            &processed_func,
+        );
+
+        MergeJoin {
+            lhs: Some(lhs),
+            rhs: Some(rhs),
+            lhs_idx,
+            rhs_idx,
+            op: op_entry.op,
+            documents: DocumentLookup::new(db),
+            timeout,
+            desc,
+            global_reflexivity: op_entry.global_reflexivity,
+            lhs_stream: None,
+            rhs_stream: None,
+            current_lhs: None,
+            next_rhs: None,
+            rhs_group_document: None,
+            rhs_group: Vec::new(),
+            pos_rhs: 0,
+            processed: 0,
+        }
+    }
+
+    /// Create the streams of both sides on first use.
+    fn init(&mut self) {
+        if let (Some(lhs), Some(rhs)) = (self.lhs.take(), self.rhs.take()) {
+            let mut lhs_stream =
+                stream_by_document(lhs, self.lhs_idx, self.documents.clone(), self.timeout);
+            let mut rhs_stream =
+                stream_by_document(rhs, self.rhs_idx, self.documents.clone(), self.timeout);
+            self.current_lhs = lhs_stream.next();
+            self.next_rhs = rhs_stream.next();
+            self.lhs_stream = Some(lhs_stream);
+            self.rhs_stream = Some(rhs_stream);
+            self.find_rhs_group();
+        }
+    }
+
+    /// Collect the RHS matches that have the same document as the current LHS match.
+    fn find_rhs_group(&mut self) {
+        self.pos_rhs = 0;
+        let lhs_document = match &self.current_lhs {
+            Some((document, _)) => *document,
+            None => return,
+        };
+        if self.rhs_group_document == Some(lhs_document) {
+            // the group of the previous LHS match can be reused
+            return;
+        }
+        self.rhs_group.clear();
+        self.rhs_group_document = Some(lhs_document);
+        if let Some(rhs_stream) = self.rhs_stream.as_mut() {
+            // both sides are sorted, so we never have to go back
+            while let Some((rhs_document, m)) = self.next_rhs.take() {
+                if rhs_document < lhs_document {
+                    self.next_rhs = rhs_stream.next();
+                } else if rhs_document == lhs_document {
+                    self.rhs_group.push(m);
+                    self.next_rhs = rhs_stream.next();
+                } else {
+                    self.next_rhs = Some((rhs_document, m));
+                    break;
+                }
+            }
+        }
+    }
+
+    fn is_timed_out(&self) -> bool {
+        self.timeout.map(|t| t.check().is_err()).unwrap_or(false)
+    }
+}
+
+impl<'a> ExecutionNode for MergeJoin<'a> {
+    fn as_iter(&mut self) -> &mut dyn Iterator<Item = MatchGroup> {
+        self
+    }
+
+    fn get_desc(&self) -> Option<&Desc> {
+        Some(&self.desc)
+    }
+
+    fn is_sorted_by_document(&self) -> bool {
+        true
+    }
+}
+
+impl<'a> Iterator for MergeJoin<'a> {
+    type Item = MatchGroup;
+
+    fn next(&mut self) -> Option<MatchGroup> {
+        self.init();
+
+        loop {
+            let (_, m_lhs) = self.current_lhs.as_ref()?;
+            while self.pos_rhs < self.rhs_group.len() {
+                let m_rhs = &self.rhs_group[self.pos_rhs];
+                self.pos_rhs += 1;
+
+                self.processed += 1;
+                if self.processed % 1_000 == 0 && self.is_timed_out() {
+                    // the caller reports the timeout when checking it after the iteration
+                    self.current_lhs = None;
+                    return None;
+                }
+
+                let lhs = &m_lhs[self.lhs_idx];
+                let rhs = &m_rhs[self.rhs_idx];
+                // filter by reflexivity if necessary
+                if self.op.filter_match(lhs, rhs)
+                    && (self.op.is_reflexive()
+                        || (self.global_reflexivity
+                            && lhs.different_to_all(m_rhs)
+                            && rhs.different_to_all(m_lhs))
+                        || (!self.global_reflexivity && lhs.different_to(rhs)))
+                {
+                    let mut result = m_lhs.clone();
+                    result.extend(m_rhs.iter().cloned());
+                    return Some(result);
+                }
+            }
+            // all RHS candidates for this LHS have been checked
+            self.current_lhs = self.lhs_stream.as_mut().and_then(|s| s.next());
+            self.find_rhs_group();
+        }
+    }
+}
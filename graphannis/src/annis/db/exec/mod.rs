@@ -1,12 +1,16 @@
 use self::nodesearch::NodeSearch;
-use crate::annis::db::AnnotationStorage;
+use crate::annis::db::query::conjunction::BinaryOperatorEntry;
+use crate::annis::db::{aql::model::AnnotationComponentType, AnnotationStorage};
+use crate::annis::types::{OperandSwitch, PlanComponent, PlanCost, PlanNode, PlanOperator};
 use crate::{
     annis::operator::{BinaryOperator, EstimationType},
     graph::Match,
+    AnnotationGraph,
 };
 use graphannis_core::{
     annostorage::MatchGroup,
-    types::{AnnoKey, NodeID},
+    graph::storage::GraphStatistic,
+    types::{AnnoKey, Component, NodeID},
 };
 
 use std::collections::BTreeMap;
@@ -19,9 +23,69 @@ pub struct CostEstimate {
     pub processed_in_step: usize,
 }
 
+/// The state of a component used by an operator at the time the execution plan was created.
+#[derive(Debug, Clone)]
+pub struct ComponentDesc {
+    pub component: Component<AnnotationComponentType>,
+    /// True if the graph storage for the component was loaded.
+    pub loaded: bool,
+    /// The statistics of the graph storage, if it was loaded and statistics are available.
+    pub statistics: Option<GraphStatistic>,
+}
+
+impl ComponentDesc {
+    pub fn new(
+        component: Component<AnnotationComponentType>,
+        db: &AnnotationGraph,
+    ) -> ComponentDesc {
+        let gs = db.get_graphstorage_as_ref(&component);
+        ComponentDesc {
+            loaded: gs.is_some(),
+            statistics: gs.and_then(|gs| gs.get_statistics().cloned()),
+            component,
+        }
+    }
+}
+
+/// Describes the binary operator of a join or filter and the estimations which were used to plan its execution.
+#[derive(Debug, Clone)]
+pub struct OperatorDesc {
+    /// Estimated selectivity of the operator or `None` if the output is estimated as the minimum of both inputs.
+    pub selectivity: Option<f64>,
+    /// Estimated selectivity of the edge annotation condition of the operator.
+    pub edge_anno_selectivity: Option<f64>,
+    /// The components used by the operator.
+    pub components: Vec<ComponentDesc>,
+    /// Set if the operands of the operator are switched compared to the query.
+    pub operand_switch: Option<OperandSwitch>,
+}
+
+impl OperatorDesc {
+    pub fn new(op_entry: &BinaryOperatorEntry) -> OperatorDesc {
+        let selectivity = match op_entry.op.estimation_type() {
+            EstimationType::SELECTIVITY(selectivity) => Some(selectivity),
+            EstimationType::MIN => None,
+        };
+        let operand_switch = if !op_entry.operands_switched {
+            None
+        } else if op_entry.op.is_commutative() {
+            Some(OperandSwitch::Commutative)
+        } else {
+            Some(OperandSwitch::Inverse)
+        };
+        OperatorDesc {
+            selectivity,
+            edge_anno_selectivity: op_entry.op.edge_anno_selectivity(),
+            components: op_entry.components.clone(),
+            operand_switch,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Desc {
     pub component_nr: usize,
+    /// For joins, this is the side that is iterated over and used as seed for the other side.
     pub lhs: Option<Box<Desc>>,
     pub rhs: Option<Box<Desc>>,
     /// Maps the index of the node in the actual result to the index in the internal execution plan intermediate result.
@@ -29,6 +93,8 @@ pub struct Desc {
     pub impl_description: String,
     pub query_fragment: String,
     pub cost: Option<CostEstimate>,
+    /// The operator of a join or binary filter.
+    pub operator: Option<OperatorDesc>,
 }
 
 fn calculate_outputsize(
@@ -79,17 +145,19 @@ impl Desc {
             impl_description: String::from(""),
             query_fragment: node_desc_arg.query_fragment,
             cost,
+            operator: None,
         }
     }
 
     pub fn join(
-        op: &dyn BinaryOperator,
+        op_entry: &BinaryOperatorEntry,
         lhs: Option<&Desc>,
         rhs: Option<&Desc>,
         impl_description: &str,
         query_fragment: &str,
         processed_func: &dyn Fn(EstimationType, usize, usize) -> usize,
     ) -> Desc {
+        let op = op_entry.op.as_ref();
         let component_nr = if let Some(d) = lhs {
             d.component_nr
         } else if let Some(d) = rhs {
@@ -143,6 +211,7 @@ impl Desc {
             impl_description: String::from(impl_description),
             query_fragment: String::from(query_fragment),
             cost,
+            operator: Some(OperatorDesc::new(op_entry)),
         }
     }
 
@@ -176,6 +245,9 @@ impl Desc {
             ));
 
             let new_indention = format!("{}    ", indention);
+            if let Some(ref operator) = self.operator {
+                result.push_str(&operator.debug_string(self.lhs.as_deref(), &new_indention));
+            }
             if let Some(ref lhs) = self.lhs {
                 result.push_str(&lhs.debug_string(&new_indention));
             }
@@ -185,6 +257,88 @@ impl Desc {
         }
         result
     }
+
+    /// Convert this description and its inputs to a node of the public execution plan tree.
+    pub fn to_plan_node(&self) -> PlanNode {
+        let children = self
+            .lhs
+            .iter()
+            .chain(self.rhs.iter())
+            .map(|d| d.to_plan_node())
+            .collect();
+        PlanNode {
+            implementation: self.impl_description.clone(),
+            query_fragment: self.query_fragment.clone(),
+            query_nodes: self.node_pos.keys().map(|node_nr| node_nr + 1).collect(),
+            cost: self.cost.as_ref().map(|cost| PlanCost {
+                output: cost.output,
+                intermediate_sum: cost.intermediate_sum,
+                processed_in_step: cost.processed_in_step,
+            }),
+            operator: self.operator.as_ref().map(|operator| PlanOperator {
+                selectivity: operator.selectivity,
+                edge_anno_selectivity: operator.edge_anno_selectivity,
+                operand_switch: operator.operand_switch,
+                components: operator
+                    .components
+                    .iter()
+                    .map(|c| PlanComponent {
+                        component: c.component.clone(),
+                        loaded: c.loaded,
+                        statistics: c.statistics.clone(),
+                    })
+                    .collect(),
+            }),
+            children,
+        }
+    }
+}
+
+impl OperatorDesc {
+    fn debug_string(&self, seed: Option<&Desc>, indention: &str) -> String {
+        let mut result = String::default();
+
+        let mut infos = Vec::new();
+        if let Some(seed) = seed {
+            let seed_nodes: Vec<String> = seed
+                .node_pos
+                .keys()
+                .map(|node_nr| format!("#{}", node_nr + 1))
+                .collect();
+            infos.push(format!("seed: {}", seed_nodes.join(",")));
+        }
+        if let Some(selectivity) = self.selectivity {
+            infos.push(format!("selectivity: {:.4}", selectivity));
+        } else {
+            infos.push("selectivity: min".to_string());
+        }
+        if let Some(edge_anno_selectivity) = self.edge_anno_selectivity {
+            infos.push(format!(
+                "edge anno selectivity: {:.4}",
+                edge_anno_selectivity
+            ));
+        }
+        match self.operand_switch {
+            Some(OperandSwitch::Inverse) => infos.push("operands switched: inverse".to_string()),
+            Some(OperandSwitch::Commutative) => {
+                infos.push("operands switched: commutative".to_string())
+            }
+            None => {}
+        }
+        result.push_str(&format!("{}~ {}\n", indention, infos.join(", ")));
+
+        for c in &self.components {
+            let state = if !c.loaded {
+                "not loaded".to_string()
+            } else if let Some(ref stats) = c.statistics {
+                stats.to_string()
+            } else {
+                "no statistics".to_string()
+            };
+            result.push_str(&format!("{}~ {} [{}]\n", indention, c.component, state));
+        }
+        result
+    }
 }
 
 pub type MatchFilterFunc =
@@ -209,6 +363,11 @@ pub trait ExecutionNode: Iterator {
     fn is_sorted_by_text(&self) -> bool {
         false
     }
+
+    /// Returns `true` if the output is grouped by the document of the matches and the documents are sorted by their node ID.
+    fn is_sorted_by_document(&self) -> bool {
+        false
+    }
 }
 
 pub struct EmptyResultSet;
@@ -234,8 +393,10 @@ impl ExecutionNode for EmptyResultSet {
     }
 }
 
+pub mod antijoin;
 pub mod filter;
 pub mod indexjoin;
+pub mod mergejoin;
 pub mod nestedloop;
 pub mod nodesearch;
 pub mod parallel;
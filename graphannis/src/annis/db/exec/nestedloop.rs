@@ -1,4 +1,4 @@
-use graphannis_core::annostorage::MatchGroup;
+use graphannis_core::annostorage::{Match, MatchGroup};
 
 use super::{Desc, ExecutionNode};
 use crate::annis::db::query::conjunction::BinaryOperatorEntry;
@@ -13,6 +13,10 @@ pub struct NestedLoop<'a> {
     outer_idx: usize,
     inner_cache: Vec<MatchGroup>,
     pos_inner_cache: Option<usize>,
+    /// The inner matches from the cache that are used as operand.
+    inner_candidates: Vec<Match>,
+    /// Result of the operator for the current outer match and each of the inner candidates.
+    filter_result: Option<Vec<bool>>,
 
     left_is_outer: bool,
     desc: Desc,
@@ -50,7 +54,7 @@ impl<'a> NestedLoop<'a> {
         if left_is_outer {
             NestedLoop {
                 desc: Desc::join(
-                    op_entry.op.as_ref(),
+                    &op_entry,
                     lhs.get_desc(),
                     rhs.get_desc(),
                     "nestedloop L-R",
@@ -68,13 +72,15 @@ impl<'a> NestedLoop<'a> {
                 inner_idx: rhs_idx,
                 inner_cache: Vec::new(),
                 pos_inner_cache: None,
+                inner_candidates: Vec::new(),
+                filter_result: None,
                 left_is_outer,
                 global_reflexivity: op_entry.global_reflexivity,
             }
         } else {
             NestedLoop {
                 desc: Desc::join(
-                    op_entry.op.as_ref(),
+                    &op_entry,
                     rhs.get_desc(),
                     lhs.get_desc(),
                     "nestedloop R-L",
@@ -92,6 +98,8 @@ impl<'a> NestedLoop<'a> {
                 inner_idx: lhs_idx,
                 inner_cache: Vec::new(),
                 pos_inner_cache: None,
+                inner_candidates: Vec::new(),
+                filter_result: None,
                 left_is_outer,
                 global_reflexivity: op_entry.global_reflexivity,
             }
@@ -118,17 +126,35 @@ impl<'a> Iterator for NestedLoop<'a> {
                 if self.pos_inner_cache.is_some() {
                     let mut cache_pos = self.pos_inner_cache.unwrap();
 
+                    if self.left_is_outer && self.filter_result.is_none() {
+                        // check all cached inner matches for the current outer match at once
+                        if self.inner_candidates.len() != self.inner_cache.len() {
+                            let inner_idx = self.inner_idx;
+                            self.inner_candidates = self
+                                .inner_cache
+                                .iter()
+                                .map(|m_inner| m_inner[inner_idx].clone())
+                                .collect();
+                        }
+                        self.filter_result = Some(
+                            self.op
+                                .filter_matches(&m_outer[self.outer_idx], &self.inner_candidates),
+                        );
+                    }
+
                     while cache_pos < self.inner_cache.len() {
                         let m_inner = &self.inner_cache[cache_pos];
-                        cache_pos += 1;
-                        self.pos_inner_cache = Some(cache_pos);
-                        let filter_true = if self.left_is_outer {
+                        let filter_true = if let Some(filter_result) = &self.filter_result {
+                            filter_result[cache_pos]
+                        } else if self.left_is_outer {
                             self.op
                                 .filter_match(&m_outer[self.outer_idx], &m_inner[self.inner_idx])
                         } else {
                             self.op
                                 .filter_match(&m_inner[self.inner_idx], &m_outer[self.outer_idx])
                         };
+                        cache_pos += 1;
+                        self.pos_inner_cache = Some(cache_pos);
                         // filter by reflexivity if necessary
                         if filter_true
                             && (self.op.is_reflexive()
@@ -174,6 +200,7 @@ impl<'a> Iterator for NestedLoop<'a> {
 
             // consume next outer
             self.outer.next()?;
+            self.filter_result = None;
         }
     }
 }
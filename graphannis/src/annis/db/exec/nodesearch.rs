@@ -1,5 +1,6 @@
 use super::MatchFilterFunc;
 use super::{Desc, ExecutionNode, NodeSearchDesc};
+use crate::annis::db::exec::mergejoin::DocumentLookup;
 use crate::annis::db::exec::tokensearch;
 use crate::annis::db::exec::tokensearch::AnyTokenSearch;
 use crate::annis::db::{aql::model::AnnotationComponentType, AnnotationStorage};
@@ -14,12 +15,13 @@ use crate::{
 use graphannis_core::{
     annostorage::{MatchGroup, ValueSearch},
     graph::{storage::GraphStorage, NODE_TYPE_KEY},
-    types::{Component, Edge, NodeID},
+    types::{AnnoKey, Component, Edge, NodeID},
 };
 use itertools::Itertools;
 use smallvec::smallvec;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt;
+use std::str::FromStr;
 use std::sync::Arc;
 
 /// An [ExecutionNode](#impl-ExecutionNode) which wraps base node (annotation) searches.
@@ -30,6 +32,7 @@ pub struct NodeSearch<'a> {
     desc: Option<Desc>,
     node_search_desc: Arc<NodeSearchDesc>,
     is_sorted: bool,
+    is_sorted_by_document: bool,
 }
 #[derive(Clone, Debug, PartialOrd, Ord, Hash, PartialEq, Eq)]
 pub enum NodeSearchSpec {
@@ -45,6 +48,13 @@ pub enum NodeSearchSpec {
         val: String,
         is_meta: bool,
     },
+    /// Matches annotations having one of the given values, e.g. `pos in ("NN", "NE")`.
+    ExactValueSet {
+        ns: Option<String>,
+        name: String,
+        vals: Vec<String>,
+        is_meta: bool,
+    },
     RegexValue {
         ns: Option<String>,
         name: String,
@@ -73,6 +83,12 @@ pub enum NodeSearchSpec {
     },
     AnyToken,
     AnyNode,
+    /// Restricts the `base` search to nodes that are the source or target of an edge in a component.
+    /// The `component` is given as `ctype[/layer[/name]]`, e.g. `Pointing/coref/anaphor` or `Pointing`.
+    InComponent {
+        base: Box<NodeSearchSpec>,
+        component: String,
+    },
 }
 
 impl NodeSearchSpec {
@@ -94,13 +110,129 @@ impl NodeSearchSpec {
         &self,
         db: &AnnotationGraph,
     ) -> HashSet<Component<AnnotationComponentType>> {
-        if let NodeSearchSpec::AnyToken = self {
-            return tokensearch::AnyTokenSearch::necessary_components(db);
+        match self {
+            NodeSearchSpec::AnyToken => tokensearch::AnyTokenSearch::necessary_components(db),
+            NodeSearchSpec::InComponent { base, component } => {
+                let mut result = base.necessary_components(db);
+                if let Ok(components) = matching_components(db, component) {
+                    result.extend(components);
+                }
+                result
+            }
+            _ => HashSet::default(),
+        }
+    }
+
+    /// Get the annotation key this specification restricts and the restriction of its value.
+    ///
+    /// Annotation names without namespace are only resolved if there is a single annotation key with
+    /// this name in the graph, since otherwise the specification can refer to different annotations.
+    fn value_constraint(&self, db: &AnnotationGraph) -> Option<(AnnoKey, ValueConstraint)> {
+        let resolve_key = |ns: &Option<String>, name: &str| -> Option<AnnoKey> {
+            if let Some(ns) = ns {
+                Some(AnnoKey {
+                    ns: ns.into(),
+                    name: name.into(),
+                })
+            } else {
+                let mut qnames = db.get_node_annos().get_qnames(name);
+                if qnames.len() == 1 {
+                    qnames.pop()
+                } else {
+                    None
+                }
+            }
+        };
+
+        match self {
+            NodeSearchSpec::ExactValue {
+                ns,
+                name,
+                val: Some(val),
+                is_meta: false,
+            } => Some((
+                resolve_key(ns, name)?,
+                ValueConstraint::OneOf(std::iter::once(val.clone()).collect()),
+            )),
+            NodeSearchSpec::ExactValueSet {
+                ns,
+                name,
+                vals,
+                is_meta: false,
+            } => Some((
+                resolve_key(ns, name)?,
+                ValueConstraint::OneOf(vals.iter().cloned().collect()),
+            )),
+            NodeSearchSpec::NotExactValue {
+                ns,
+                name,
+                val,
+                is_meta: false,
+            } => Some((resolve_key(ns, name)?, ValueConstraint::Not(val.clone()))),
+            NodeSearchSpec::ExactTokenValue { val, .. } => Some((
+                TOKEN_KEY.as_ref().clone(),
+                ValueConstraint::OneOf(std::iter::once(val.clone()).collect()),
+            )),
+            NodeSearchSpec::NotExactTokenValue { val } => Some((
+                TOKEN_KEY.as_ref().clone(),
+                ValueConstraint::Not(val.clone()),
+            )),
+            NodeSearchSpec::InComponent { base, .. } => base.value_constraint(db),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if no node can match both this and the `other` specification, e.g. because they
+    /// require different values for the same annotation.
+    pub fn contradicts(&self, other: &NodeSearchSpec, db: &AnnotationGraph) -> bool {
+        if let (Some((key, constraint)), Some((other_key, other_constraint))) =
+            (self.value_constraint(db), other.value_constraint(db))
+        {
+            if key != other_key {
+                return false;
+            }
+            match (constraint, other_constraint) {
+                (ValueConstraint::OneOf(vals), ValueConstraint::OneOf(other_vals)) => {
+                    vals.is_disjoint(&other_vals)
+                }
+                (ValueConstraint::OneOf(vals), ValueConstraint::Not(excluded))
+                | (ValueConstraint::Not(excluded), ValueConstraint::OneOf(vals)) => {
+                    vals.iter().all(|v| v == &excluded)
+                }
+                (ValueConstraint::Not(_), ValueConstraint::Not(_)) => false,
+            }
+        } else {
+            false
         }
-        HashSet::default()
     }
 }
 
+/// Restriction of the value of a single annotation, used to detect contradicting node search specifications.
+enum ValueConstraint {
+    OneOf(BTreeSet<String>),
+    Not(String),
+}
+
+/// Get all components of the graph that match a component description in the format `ctype[/layer[/name]]`.
+pub(crate) fn matching_components(
+    db: &AnnotationGraph,
+    component: &str,
+) -> std::result::Result<Vec<Component<AnnotationComponentType>>, String> {
+    let mut parts = component.splitn(3, '/');
+    let ctype = parts.next().unwrap_or_default();
+    let ctype = AnnotationComponentType::from_str(ctype)
+        .map_err(|_| format!("unknown component type \"{}\"", ctype))?;
+    let layer = parts.next();
+    let name = parts.next();
+
+    let result = db
+        .get_all_components(Some(ctype), name)
+        .into_iter()
+        .filter(|c| layer.is_none() || layer == Some(c.layer.as_str()))
+        .collect();
+    Ok(result)
+}
+
 impl fmt::Display for NodeSearchSpec {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -138,6 +270,18 @@ impl fmt::Display for NodeSearchSpec {
                     write!(f, "{}!=\"{}\"", name, &val)
                 }
             }
+            NodeSearchSpec::ExactValueSet {
+                ref ns,
+                ref name,
+                ref vals,
+                ..
+            } => {
+                if let Some(ref ns) = ns {
+                    write!(f, "{}:", ns)?;
+                }
+                let vals = vals.iter().map(|v| format!("\"{}\"", v)).join(", ");
+                write!(f, "{} in ({})", name, vals)
+            }
             NodeSearchSpec::RegexValue {
                 ref ns,
                 ref name,
@@ -186,6 +330,10 @@ impl fmt::Display for NodeSearchSpec {
             NodeSearchSpec::NotRegexTokenValue { ref val } => write!(f, "tok!=/{}/", val),
             NodeSearchSpec::AnyToken => write!(f, "tok"),
             NodeSearchSpec::AnyNode => write!(f, "node"),
+            NodeSearchSpec::InComponent {
+                ref base,
+                ref component,
+            } => write!(f, "{} @component=\"{}\"", base, component),
         }
     }
 }
@@ -226,6 +374,19 @@ impl<'a> NodeSearch<'a> {
                 &query_fragment,
                 node_nr,
             ),
+            NodeSearchSpec::ExactValueSet {
+                ns,
+                name,
+                vals,
+                is_meta,
+            } => NodeSearch::new_annosearch_exact(
+                db,
+                (ns, name),
+                ValueSearch::AnyOf(vals),
+                is_meta,
+                &query_fragment,
+                node_nr,
+            ),
             NodeSearchSpec::RegexValue {
                 ns,
                 name,
@@ -329,6 +490,16 @@ impl<'a> NodeSearch<'a> {
             NodeSearchSpec::AnyToken => {
                 NodeSearch::new_anytoken_search(db, &query_fragment, node_nr)
             }
+            NodeSearchSpec::InComponent { base, component } => NodeSearch::new_component_search(
+                db,
+                *base,
+                &component,
+                super::NodeDescArg {
+                    query_fragment,
+                    node_nr,
+                },
+                location_in_query,
+            ),
             NodeSearchSpec::AnyNode => {
                 let it = db
                     .get_node_annos()
@@ -375,11 +546,80 @@ impl<'a> NodeSearch<'a> {
                         const_output: Some(NODE_TYPE_KEY.clone()),
                     }),
                     is_sorted: false,
+                    is_sorted_by_document: false,
                 })
             }
         }
     }
 
+    fn new_component_search(
+        db: &'a AnnotationGraph,
+        base: NodeSearchSpec,
+        component: &str,
+        node_desc_arg: super::NodeDescArg,
+        location_in_query: Option<LineColumnRange>,
+    ) -> Result<NodeSearch<'a>> {
+        let components = matching_components(db, component).map_err(|desc| {
+            GraphAnnisError::AQLSemanticError(AQLError {
+                desc,
+                location: location_in_query.clone(),
+            })
+        })?;
+
+        // collect all nodes that are source or target of an edge in any of the components
+        let mut component_nodes: BTreeSet<NodeID> = BTreeSet::new();
+        for c in components.iter() {
+            if let Some(gs) = db.get_graphstorage_as_ref(c) {
+                for source in gs.source_nodes() {
+                    component_nodes.insert(source);
+                    component_nodes.extend(gs.get_outgoing_edges(source));
+                }
+            }
+        }
+        let component_nodes = Arc::new(component_nodes);
+
+        let mut search = if let NodeSearchSpec::AnyNode = base {
+            // iterate over the nodes of the component instead of all nodes
+            let base_nodes: Vec<NodeID> = component_nodes.iter().copied().collect();
+            let it = base_nodes.into_iter().map(|node| {
+                smallvec![Match {
+                    node,
+                    anno_key: NODE_TYPE_KEY.clone(),
+                }]
+            });
+            let mut search =
+                NodeSearch::from_spec(base, node_desc_arg.node_nr, db, location_in_query)?;
+            search.it = Box::new(it);
+            search.is_sorted = true;
+            search
+        } else {
+            let mut search =
+                NodeSearch::from_spec(base, node_desc_arg.node_nr, db, location_in_query)?;
+            let filter_nodes = component_nodes.clone();
+            let it = search
+                .it
+                .filter(move |m| m.iter().all(|m| filter_nodes.contains(&m.node)));
+            search.it = Box::new(it);
+            search
+        };
+
+        let filter_nodes = component_nodes.clone();
+        if let Some(node_search_desc) = Arc::get_mut(&mut search.node_search_desc) {
+            node_search_desc
+                .cond
+                .push(Box::new(move |m, _| filter_nodes.contains(&m.node)));
+        }
+
+        if let Some(desc) = search.desc.as_mut() {
+            desc.query_fragment = node_desc_arg.query_fragment;
+            if let Some(cost) = desc.cost.as_mut() {
+                cost.output = std::cmp::max(1, std::cmp::min(cost.output, component_nodes.len()));
+            }
+        }
+
+        Ok(search)
+    }
+
     fn new_annosearch_exact(
         db: &'a AnnotationGraph,
         qname: (Option<String>, String),
@@ -437,6 +677,14 @@ impl<'a> NodeSearch<'a> {
                     - db.get_node_annos()
                         .guess_max_count(qname.0.as_deref(), &qname.1, &val, &val)
             }
+            ValueSearch::AnyOf(ref vals) => vals
+                .iter()
+                .unique()
+                .map(|val| {
+                    db.get_node_annos()
+                        .guess_max_count(qname.0.as_deref(), &qname.1, val, val)
+                })
+                .sum(),
             ValueSearch::Any => db
                 .get_node_annos()
                 .number_of_annotations_by_name(qname.0.as_deref(), &qname.1),
@@ -453,20 +701,27 @@ impl<'a> NodeSearch<'a> {
             ValueSearch::Any => {}
             ValueSearch::Some(val) => {
                 filters.push(Box::new(move |m, node_annos| {
-                    if let Some(anno_val) = node_annos.get_value_for_item(&m.node, &m.anno_key) {
-                        anno_val == val.as_str()
-                    } else {
-                        false
-                    }
+                    node_annos
+                        .get_values_for_item(&m.node, &m.anno_key)
+                        .iter()
+                        .any(|anno_val| anno_val == val.as_str())
                 }));
             }
             ValueSearch::NotSome(val) => {
                 filters.push(Box::new(move |m, node_annos| {
-                    if let Some(anno_val) = node_annos.get_value_for_item(&m.node, &m.anno_key) {
-                        anno_val != val.as_str()
-                    } else {
-                        false
-                    }
+                    node_annos
+                        .get_values_for_item(&m.node, &m.anno_key)
+                        .iter()
+                        .any(|anno_val| anno_val != val.as_str())
+                }));
+            }
+            ValueSearch::AnyOf(vals) => {
+                let vals: HashSet<String> = vals.into_iter().collect();
+                filters.push(Box::new(move |m, node_annos| {
+                    node_annos
+                        .get_values_for_item(&m.node, &m.anno_key)
+                        .iter()
+                        .any(|anno_val| vals.contains(anno_val.as_ref()))
                 }));
             }
         }
@@ -485,6 +740,7 @@ impl<'a> NodeSearch<'a> {
                 const_output,
             }),
             is_sorted: false,
+            is_sorted_by_document: false,
         })
     }
 
@@ -555,23 +811,12 @@ impl<'a> NodeSearch<'a> {
         let re = regex::Regex::new(&full_match_pattern);
         match re {
             Ok(re) => {
-                if negated {
-                    filters.push(Box::new(move |m, node_annos| {
-                        if let Some(val) = node_annos.get_value_for_item(&m.node, &m.anno_key) {
-                            !re.is_match(&val)
-                        } else {
-                            false
-                        }
-                    }));
-                } else {
-                    filters.push(Box::new(move |m, node_annos| {
-                        if let Some(val) = node_annos.get_value_for_item(&m.node, &m.anno_key) {
-                            re.is_match(&val)
-                        } else {
-                            false
-                        }
-                    }));
-                }
+                filters.push(Box::new(move |m, node_annos| {
+                    node_annos
+                        .get_values_for_item(&m.node, &m.anno_key)
+                        .iter()
+                        .any(|val| re.is_match(val) != negated)
+                }));
             }
             Err(e) => {
                 return Err(GraphAnnisError::AQLSemanticError(AQLError {
@@ -590,6 +835,7 @@ impl<'a> NodeSearch<'a> {
                 const_output,
             }),
             is_sorted: false,
+            is_sorted_by_document: false,
         })
     }
 
@@ -645,6 +891,15 @@ impl<'a> NodeSearch<'a> {
                 };
                 Box::new(it)
             }
+            ValueSearch::AnyOf(ref vals) => {
+                // value sets are always compared exactly
+                let it = db.get_node_annos().exact_anno_search(
+                    Some(&TOKEN_KEY.ns),
+                    &TOKEN_KEY.name,
+                    ValueSearch::AnyOf(vals.iter().map(String::as_str).collect()),
+                );
+                Box::new(it)
+            }
         };
 
         let it_base = if leafs_only {
@@ -653,7 +908,7 @@ impl<'a> NodeSearch<'a> {
                 .into_iter()
                 .filter_map(|c| db.get_graphstorage(&c))
                 .filter(|gs| {
-                    if let Some(stats) = gs.get_statistics() {
+                    if let Some(stats) = gs.get_statistics_or_estimate() {
                         stats.nodes > 0
                     } else {
                         true
@@ -746,6 +1001,16 @@ impl<'a> NodeSearch<'a> {
                     }));
                 };
             }
+            ValueSearch::AnyOf(ref vals) => {
+                let vals: HashSet<String> = vals.iter().cloned().collect();
+                filters.push(Box::new(move |m, node_annos| {
+                    if let Some(anno_val) = node_annos.get_value_for_item(&m.node, &m.anno_key) {
+                        vals.contains(anno_val.as_ref())
+                    } else {
+                        false
+                    }
+                }));
+            }
             ValueSearch::Any => {}
         };
 
@@ -755,7 +1020,7 @@ impl<'a> NodeSearch<'a> {
                 .into_iter()
                 .filter_map(|c| db.get_graphstorage(&c))
                 .filter(|gs| {
-                    if let Some(stats) = gs.get_statistics() {
+                    if let Some(stats) = gs.get_statistics_or_estimate() {
                         stats.nodes > 0
                     } else {
                         true
@@ -814,6 +1079,18 @@ impl<'a> NodeSearch<'a> {
                 };
                 total_count - positive_count
             }
+            ValueSearch::AnyOf(vals) => vals
+                .iter()
+                .unique()
+                .map(|val| {
+                    db.get_node_annos().guess_max_count(
+                        Some(&TOKEN_KEY.ns),
+                        &TOKEN_KEY.name,
+                        val,
+                        val,
+                    )
+                })
+                .sum(),
             ValueSearch::Any => db
                 .get_node_annos()
                 .number_of_annotations_by_name(Some(&TOKEN_KEY.ns), &TOKEN_KEY.name),
@@ -839,6 +1116,7 @@ impl<'a> NodeSearch<'a> {
                 const_output: Some(NODE_TYPE_KEY.clone()),
             }),
             is_sorted: false,
+            is_sorted_by_document: false,
         })
     }
 
@@ -856,7 +1134,7 @@ impl<'a> NodeSearch<'a> {
             .into_iter()
             .filter_map(|c| db.get_graphstorage(&c))
             .filter(|gs| {
-                if let Some(stats) = gs.get_statistics() {
+                if let Some(stats) = gs.get_statistics_or_estimate() {
                     stats.nodes > 0
                 } else {
                     true
@@ -898,6 +1176,7 @@ impl<'a> NodeSearch<'a> {
                 const_output: Some(NODE_TYPE_KEY.clone()),
             }),
             is_sorted: true,
+            is_sorted_by_document: false,
         })
     }
 
@@ -972,9 +1251,66 @@ impl<'a> NodeSearch<'a> {
             desc: new_desc,
             node_search_desc,
             is_sorted: false,
+            is_sorted_by_document: false,
         })
     }
 
+    /// Create a search for the same nodes as the search described by `node_search_desc`, but which returns its
+    /// matches grouped and sorted by their document.
+    ///
+    /// Instead of using an annotation index, all nodes are checked document by document, so this is only cheaper
+    /// than sorting the matches of the original search if the original search is not very selective.
+    pub fn new_document_ordered_search(
+        db: &'a AnnotationGraph,
+        node_search_desc: Arc<NodeSearchDesc>,
+        desc: Option<&Desc>,
+    ) -> NodeSearch<'a> {
+        let node_search_desc_1 = node_search_desc.clone();
+        let it = DocumentLookup::new(db)
+            .nodes_by_document()
+            .flat_map(move |node| {
+                // fetch annotation candidates for the node and only include the ones that fullfill all original node
+                // search predicates
+                let node_search_desc = node_search_desc_1.clone();
+                let mut matches: Vec<MatchGroup> = db
+                    .get_node_annos()
+                    .get_all_keys_for_item(
+                        &node,
+                        node_search_desc.qname.0.as_deref(),
+                        node_search_desc.qname.1.as_deref(),
+                    )
+                    .into_iter()
+                    .map(|anno_key| Match { node, anno_key })
+                    .filter(|m| {
+                        node_search_desc
+                            .cond
+                            .iter()
+                            .all(|cond| cond(m, db.get_node_annos()))
+                    })
+                    .map(|m| smallvec![m])
+                    .collect();
+                if let Some(ref const_anno) = node_search_desc.const_output {
+                    // only return the one unique constant annotation for this node
+                    matches.truncate(1);
+                    for m in matches.iter_mut() {
+                        m[0].anno_key = const_anno.clone();
+                    }
+                }
+                matches
+            });
+        let mut new_desc = desc.cloned();
+        if let Some(ref mut new_desc) = new_desc {
+            new_desc.impl_description = String::from("document-ordered-search");
+        }
+        NodeSearch {
+            it: Box::new(it),
+            desc: new_desc,
+            node_search_desc,
+            is_sorted: false,
+            is_sorted_by_document: true,
+        }
+    }
+
     pub fn set_desc(&mut self, desc: Option<Desc>) {
         self.desc = desc;
     }
@@ -1000,6 +1336,10 @@ impl<'a> ExecutionNode for NodeSearch<'a> {
     fn is_sorted_by_text(&self) -> bool {
         self.is_sorted
     }
+
+    fn is_sorted_by_document(&self) -> bool {
+        self.is_sorted_by_document
+    }
 }
 
 impl<'a> Iterator for NodeSearch<'a> {
@@ -7,12 +7,11 @@ use crate::{
 };
 use graphannis_core::{annostorage::MatchGroup, types::NodeID};
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::iter::Peekable;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 
-const MAX_BUFFER_SIZE: usize = 512;
-
 /// A join that takes any iterator as left-hand-side (LHS) and an annotation condition as right-hand-side (RHS).
 /// It then retrieves all matches as defined by the operator for each LHS element and checks
 /// if the annotation condition is true.
@@ -25,6 +24,8 @@ pub struct IndexJoin<'a> {
     node_annos: &'a dyn AnnotationStorage<NodeID>,
     desc: Desc,
     global_reflexivity: bool,
+    batch_size: usize,
+    thread_pool: Option<Arc<ThreadPool>>,
 }
 
 impl<'a> IndexJoin<'a> {
@@ -36,6 +37,8 @@ impl<'a> IndexJoin<'a> {
     /// * `op_entry` - The operator that connects the LHS and RHS (with description)
     /// * `anno_qname` A pair of the annotation namespace and name (both optional) to define which annotations to fetch
     /// * `anno_cond` - A filter function to determine if a RHS candidate is included
+    /// * `batch_size` - Number of LHS matches that are processed in parallel
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         lhs: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
         lhs_idx: usize,
@@ -43,6 +46,8 @@ impl<'a> IndexJoin<'a> {
         node_search_desc: Arc<NodeSearchDesc>,
         node_annos: &'a dyn AnnotationStorage<NodeID>,
         rhs_desc: Option<&Desc>,
+        batch_size: usize,
+        thread_pool: Option<Arc<ThreadPool>>,
     ) -> IndexJoin<'a> {
         let lhs_desc = lhs.get_desc().cloned();
         // TODO, we
@@ -71,7 +76,7 @@ impl<'a> IndexJoin<'a> {
 
         IndexJoin {
             desc: Desc::join(
-                op_entry.op.as_ref(),
+                &op_entry,
                 lhs_desc.as_ref(),
                 rhs_desc,
                 "indexjoin (parallel)",
@@ -88,6 +93,8 @@ impl<'a> IndexJoin<'a> {
             node_annos,
             match_receiver: None,
             global_reflexivity: op_entry.global_reflexivity,
+            batch_size,
+            thread_pool,
         }
     }
 
@@ -96,8 +103,8 @@ impl<'a> IndexJoin<'a> {
         tx: &Sender<MatchGroup>,
     ) -> Vec<(MatchGroup, Sender<MatchGroup>)> {
         let mut lhs_buffer: Vec<(MatchGroup, Sender<MatchGroup>)> =
-            Vec::with_capacity(MAX_BUFFER_SIZE);
-        while lhs_buffer.len() < MAX_BUFFER_SIZE {
+            Vec::with_capacity(self.batch_size);
+        while lhs_buffer.len() < self.batch_size {
             if let Some(lhs) = self.lhs.next() {
                 lhs_buffer.push((lhs, tx.clone()));
             } else {
@@ -124,57 +131,59 @@ impl<'a> IndexJoin<'a> {
         let global_reflexivity = self.global_reflexivity;
 
         // find all RHS in parallel
-        lhs_buffer.par_iter_mut().for_each(|(m_lhs, tx)| {
-            let mut rhs_candidate =
-                next_candidates(m_lhs, op, lhs_idx, node_annos, &node_search_desc)
-                    .into_iter()
-                    .peekable();
-            while let Some(mut m_rhs) = rhs_candidate.next() {
-                // check if all filters are true
-                let mut filter_result = true;
-                for f in &node_search_desc.cond {
-                    if !(f)(&m_rhs, node_annos) {
-                        filter_result = false;
-                        break;
+        super::install(&self.thread_pool, || {
+            lhs_buffer.par_iter_mut().for_each(|(m_lhs, tx)| {
+                let mut rhs_candidate =
+                    next_candidates(m_lhs, op, lhs_idx, node_annos, &node_search_desc)
+                        .into_iter()
+                        .peekable();
+                while let Some(mut m_rhs) = rhs_candidate.next() {
+                    // check if all filters are true
+                    let mut filter_result = true;
+                    for f in &node_search_desc.cond {
+                        if !(f)(&m_rhs, node_annos) {
+                            filter_result = false;
+                            break;
+                        }
                     }
-                }
 
-                if filter_result {
-                    // replace the annotation with a constant value if needed
-                    if let Some(ref const_anno) = node_search_desc.const_output {
-                        m_rhs = (m_rhs.node, const_anno.clone()).into();
-                    }
+                    if filter_result {
+                        // replace the annotation with a constant value if needed
+                        if let Some(ref const_anno) = node_search_desc.const_output {
+                            m_rhs = (m_rhs.node, const_anno.clone()).into();
+                        }
 
-                    // check if lhs and rhs are equal and if this is allowed in this query
-                    if op.is_reflexive()
-                        || (global_reflexivity && m_rhs.different_to_all(&m_lhs)
-                            || (!global_reflexivity && m_rhs.different_to(&m_lhs[lhs_idx])))
-                    {
-                        // filters have been checked, return the result
-                        let mut result = m_lhs.clone();
-                        let matched_node = m_rhs.node;
-                        result.push(m_rhs);
-                        if node_search_desc.const_output.is_some() {
-                            // only return the one unique constAnno for this node and no duplicates
-                            // skip all RHS candidates that have the same node ID
-                            #[allow(clippy::while_let_loop)]
-                            loop {
-                                if let Some(next_match) = rhs_candidate.peek() {
-                                    if next_match.node != matched_node {
+                        // check if lhs and rhs are equal and if this is allowed in this query
+                        if op.is_reflexive()
+                            || (global_reflexivity && m_rhs.different_to_all(&m_lhs)
+                                || (!global_reflexivity && m_rhs.different_to(&m_lhs[lhs_idx])))
+                        {
+                            // filters have been checked, return the result
+                            let mut result = m_lhs.clone();
+                            let matched_node = m_rhs.node;
+                            result.push(m_rhs);
+                            if node_search_desc.const_output.is_some() {
+                                // only return the one unique constAnno for this node and no duplicates
+                                // skip all RHS candidates that have the same node ID
+                                #[allow(clippy::while_let_loop)]
+                                loop {
+                                    if let Some(next_match) = rhs_candidate.peek() {
+                                        if next_match.node != matched_node {
+                                            break;
+                                        }
+                                    } else {
                                         break;
                                     }
-                                } else {
-                                    break;
+                                    rhs_candidate.next();
                                 }
-                                rhs_candidate.next();
                             }
-                        }
-                        if tx.send(result).is_err() {
-                            return;
+                            if tx.send(result).is_err() {
+                                return;
+                            }
                         }
                     }
                 }
-            }
+            });
         });
         Some(rx)
     }
@@ -1,2 +1,18 @@
+use rayon::ThreadPool;
+use std::sync::Arc;
+
 pub mod indexjoin;
 pub mod nestedloop;
+
+/// Execute the given parallel operation in the configured thread pool or the global one if no pool is configured.
+pub(crate) fn install<OP, R>(thread_pool: &Option<Arc<ThreadPool>>, op: OP) -> R
+where
+    OP: FnOnce() -> R + Send,
+    R: Send,
+{
+    if let Some(thread_pool) = thread_pool {
+        thread_pool.install(op)
+    } else {
+        op()
+    }
+}
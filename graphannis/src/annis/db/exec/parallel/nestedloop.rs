@@ -3,11 +3,10 @@ use crate::annis::db::query::conjunction::BinaryOperatorEntry;
 use crate::annis::operator::BinaryOperator;
 use graphannis_core::annostorage::MatchGroup;
 use rayon::prelude::*;
+use rayon::ThreadPool;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 
-const MAX_BUFFER_SIZE: usize = 1024;
-
 pub struct NestedLoop<'a> {
     outer: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
     inner: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
@@ -25,6 +24,8 @@ pub struct NestedLoop<'a> {
     desc: Desc,
 
     global_reflexivity: bool,
+    batch_size: usize,
+    thread_pool: Option<Arc<ThreadPool>>,
 }
 
 type MatchCandidate = (Arc<MatchGroup>, Arc<MatchGroup>, Sender<MatchGroup>);
@@ -36,6 +37,8 @@ impl<'a> NestedLoop<'a> {
         rhs: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
         lhs_idx: usize,
         rhs_idx: usize,
+        batch_size: usize,
+        thread_pool: Option<Arc<ThreadPool>>,
     ) -> NestedLoop<'a> {
         let mut left_is_outer = true;
         if let (Some(ref desc_lhs), Some(ref desc_rhs)) = (lhs.get_desc(), rhs.get_desc()) {
@@ -59,7 +62,7 @@ impl<'a> NestedLoop<'a> {
         if left_is_outer {
             NestedLoop {
                 desc: Desc::join(
-                    op_entry.op.as_ref(),
+                    &op_entry,
                     lhs.get_desc(),
                     rhs.get_desc(),
                     "nestedloop (parallel) L-R",
@@ -80,13 +83,15 @@ impl<'a> NestedLoop<'a> {
                 pos_inner_cache: None,
                 left_is_outer,
                 global_reflexivity: op_entry.global_reflexivity,
-                match_candidate_buffer: Vec::with_capacity(MAX_BUFFER_SIZE),
+                match_candidate_buffer: Vec::with_capacity(batch_size),
+                batch_size,
                 current_outer: None,
+                thread_pool,
             }
         } else {
             NestedLoop {
                 desc: Desc::join(
-                    op_entry.op.as_ref(),
+                    &op_entry,
                     rhs.get_desc(),
                     lhs.get_desc(),
                     "nestedloop (parallel) R-L",
@@ -107,8 +112,10 @@ impl<'a> NestedLoop<'a> {
                 pos_inner_cache: None,
                 left_is_outer,
                 global_reflexivity: op_entry.global_reflexivity,
-                match_candidate_buffer: Vec::with_capacity(MAX_BUFFER_SIZE),
+                match_candidate_buffer: Vec::with_capacity(batch_size),
+                batch_size,
                 current_outer: None,
+                thread_pool,
             }
         }
     }
@@ -132,7 +139,7 @@ impl<'a> NestedLoop<'a> {
     fn next_match_buffer(&mut self, tx: &Sender<MatchGroup>) {
         self.match_candidate_buffer.clear();
 
-        while self.match_candidate_buffer.len() < MAX_BUFFER_SIZE {
+        while self.match_candidate_buffer.len() < self.batch_size {
             if let Some(m_outer) = self.peek_outer() {
                 if self.pos_inner_cache.is_some() {
                     let mut cache_pos = self.pos_inner_cache.unwrap();
@@ -148,7 +155,7 @@ impl<'a> NestedLoop<'a> {
                             tx.clone(),
                         ));
 
-                        if self.match_candidate_buffer.len() >= MAX_BUFFER_SIZE {
+                        if self.match_candidate_buffer.len() >= self.batch_size {
                             return;
                         }
                     }
@@ -161,7 +168,7 @@ impl<'a> NestedLoop<'a> {
                         self.match_candidate_buffer
                             .push((m_outer.clone(), m_inner, tx.clone()));
 
-                        if self.match_candidate_buffer.len() >= MAX_BUFFER_SIZE {
+                        if self.match_candidate_buffer.len() >= self.batch_size {
                             return;
                         }
                     }
@@ -195,33 +202,36 @@ impl<'a> NestedLoop<'a> {
         let op: &dyn BinaryOperator = op.as_ref();
         let global_reflexivity = self.global_reflexivity;
 
-        self.match_candidate_buffer
-            .par_iter_mut()
-            .for_each(|(m_outer, m_inner, tx)| {
-                let filter_true = if left_is_outer {
-                    op.filter_match(&m_outer[outer_idx], &m_inner[inner_idx])
-                } else {
-                    op.filter_match(&m_inner[inner_idx], &m_outer[outer_idx])
-                };
-                // filter by reflexivity if necessary
-
-                if filter_true
-                    && (op.is_reflexive()
-                        || (global_reflexivity
-                            && m_outer[outer_idx].different_to_all(&m_inner)
-                            && m_inner[inner_idx].different_to_all(&m_outer))
-                        || (!global_reflexivity
-                            && m_outer[outer_idx].different_to(&m_inner[inner_idx])))
-                {
-                    let mut result = MatchGroup::new();
-                    result.extend(m_outer.iter().cloned());
-                    result.extend(m_inner.iter().cloned());
-
-                    if tx.send(result).is_err() {
-                        return;
+        let match_candidate_buffer = &mut self.match_candidate_buffer;
+        super::install(&self.thread_pool, || {
+            match_candidate_buffer
+                .par_iter_mut()
+                .for_each(|(m_outer, m_inner, tx)| {
+                    let filter_true = if left_is_outer {
+                        op.filter_match(&m_outer[outer_idx], &m_inner[inner_idx])
+                    } else {
+                        op.filter_match(&m_inner[inner_idx], &m_outer[outer_idx])
+                    };
+                    // filter by reflexivity if necessary
+
+                    if filter_true
+                        && (op.is_reflexive()
+                            || (global_reflexivity
+                                && m_outer[outer_idx].different_to_all(&m_inner)
+                                && m_inner[inner_idx].different_to_all(&m_outer))
+                            || (!global_reflexivity
+                                && m_outer[outer_idx].different_to(&m_inner[inner_idx])))
+                    {
+                        let mut result = MatchGroup::new();
+                        result.extend(m_outer.iter().cloned());
+                        result.extend(m_inner.iter().cloned());
+
+                        if tx.send(result).is_err() {
+                            return;
+                        }
                     }
-                }
-            });
+                });
+        });
         self.match_candidate_buffer.clear();
 
         Some(rx)
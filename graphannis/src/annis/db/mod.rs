@@ -1,11 +1,16 @@
 pub mod aql;
+#[cfg(feature = "arrow")]
+pub mod arrow_export;
+pub mod conllu;
 pub mod corpusstorage;
 #[cfg(test)]
 pub mod example_generator;
 pub mod exec;
+pub mod node_name_scheme;
 mod plan;
 pub mod query;
 pub mod relannis;
+pub mod single_graph;
 pub mod sort_matches;
 pub mod token_helper;
 
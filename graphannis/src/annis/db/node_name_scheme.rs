@@ -0,0 +1,296 @@
+//! User-defined node names for imported corpora.
+//!
+//! The importers create node names in the form `corpus/doc#fragment`, where the fragment depends on the
+//! import format. A [`NodeNameScheme`] allows to replace the fragment, e.g. with a stable ID of an external
+//! system that is part of the node annotations, so cross-references to these systems stay valid after the import.
+
+use crate::{
+    annis::errors::{CorpusStorageError, Result},
+    AnnotationGraph,
+};
+use graphannis_core::{
+    annostorage::ValueSearch,
+    graph::{ANNIS_NS, NODE_NAME, NODE_NAME_KEY, NODE_TYPE_KEY},
+    types::{Annotation, NodeID},
+};
+use std::collections::HashMap;
+
+/// Defines how the names of the annotation nodes of an imported corpus are created.
+///
+/// Only nodes which are part of a document (their name has the form `corpus/doc#fragment`) are renamed,
+/// the names of the corpus, document and data source nodes are never changed.
+/// The new name must keep the `corpus/doc#` prefix, because the document of a node is derived from its name.
+pub trait NodeNameScheme: Send + Sync {
+    /// Create the name for an annotation node.
+    ///
+    /// - `document` - The path of the document the node belongs to, e.g. `corpus/doc`.
+    /// - `fragment` - The fragment of the node name that has been created by the importer.
+    /// - `annotations` - All annotations of the node.
+    ///
+    /// Return `None` to keep the name created by the importer.
+    fn node_name(
+        &self,
+        document: &str,
+        fragment: &str,
+        annotations: &[Annotation],
+    ) -> Option<String>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TemplatePart {
+    Text(String),
+    Document,
+    Fragment,
+    Annotation { ns: Option<String>, name: String },
+}
+
+/// A [`NodeNameScheme`] that creates the node names from a template.
+///
+/// The template can contain the following placeholders:
+/// - `{document}` - the path of the document, e.g. `corpus/doc`
+/// - `{fragment}` - the fragment of the node name that has been created by the importer
+/// - `{ns::name}` or `{name}` - the value of the annotation with the given (qualified) name
+///
+/// The template must start with `{document}#`, e.g. `{document}#{ext::id}` uses the value of the `ext::id`
+/// annotation as fragment.
+/// The name of nodes without one of the referenced annotations is not changed.
+/// Braces can be escaped by doubling them (`{{` and `}}`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeNameTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+impl NodeNameTemplate {
+    /// Parse the given template, fails if a placeholder is not closed or empty or if the template does not start
+    /// with `{document}#`.
+    pub fn new(template: &str) -> Result<NodeNameTemplate> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut closed = false;
+                    for c in &mut chars {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    if !closed || placeholder.is_empty() {
+                        return Err(CorpusStorageError::InvalidNodeNameTemplate(
+                            template.to_string(),
+                        )
+                        .into());
+                    }
+                    if !text.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+                    }
+                    let part = match placeholder.as_str() {
+                        "document" => TemplatePart::Document,
+                        "fragment" => TemplatePart::Fragment,
+                        qname => {
+                            if let Some(sep) = qname.find("::") {
+                                TemplatePart::Annotation {
+                                    ns: Some(qname[..sep].to_string()),
+                                    name: qname[sep + 2..].to_string(),
+                                }
+                            } else {
+                                TemplatePart::Annotation {
+                                    ns: None,
+                                    name: qname.to_string(),
+                                }
+                            }
+                        }
+                    };
+                    parts.push(part);
+                }
+                '}' => {
+                    return Err(
+                        CorpusStorageError::InvalidNodeNameTemplate(template.to_string()).into(),
+                    );
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+        let has_document_prefix = match (parts.first(), parts.get(1)) {
+            (Some(TemplatePart::Document), Some(TemplatePart::Text(text))) => text.starts_with('#'),
+            _ => false,
+        };
+        if !has_document_prefix {
+            return Err(CorpusStorageError::InvalidNodeNameTemplate(template.to_string()).into());
+        }
+        Ok(NodeNameTemplate { parts })
+    }
+}
+
+impl NodeNameScheme for NodeNameTemplate {
+    fn node_name(
+        &self,
+        document: &str,
+        fragment: &str,
+        annotations: &[Annotation],
+    ) -> Option<String> {
+        let mut result = String::new();
+        for p in &self.parts {
+            match p {
+                TemplatePart::Text(text) => result.push_str(text),
+                TemplatePart::Document => result.push_str(document),
+                TemplatePart::Fragment => result.push_str(fragment),
+                TemplatePart::Annotation { ns, name } => {
+                    let anno = annotations.iter().find(|a| {
+                        a.key.name == name.as_str()
+                            && ns
+                                .as_ref()
+                                .map(|ns| a.key.ns == ns.as_str())
+                                .unwrap_or(true)
+                    })?;
+                    result.push_str(&anno.val);
+                }
+            }
+        }
+        Some(result)
+    }
+}
+
+/// Rename all annotation nodes of the graph with the given scheme.
+///
+/// All names are checked for collisions and for the `corpus/doc#` prefix before any node is renamed, so the graph is
+/// unchanged if an error is returned.
+/// Returns the number of renamed nodes.
+pub(crate) fn apply_node_name_scheme(
+    graph: &mut AnnotationGraph,
+    scheme: &dyn NodeNameScheme,
+) -> Result<usize> {
+    let node_annos = graph.get_node_annos();
+
+    let nodes: Vec<NodeID> = node_annos
+        .exact_anno_search(Some(ANNIS_NS), NODE_NAME, ValueSearch::Any)
+        .map(|m| m.node)
+        .collect();
+
+    // map the new node names to the old ones to detect collisions
+    let mut new_names: HashMap<String, String> = HashMap::with_capacity(nodes.len());
+    let mut renamed: Vec<(NodeID, String)> = Vec::new();
+    for n in nodes {
+        let old_name = if let Some(old_name) = node_annos.get_value_for_item(&n, &NODE_NAME_KEY) {
+            old_name.to_string()
+        } else {
+            continue;
+        };
+        let is_annotation_node = node_annos
+            .get_value_for_item(&n, &NODE_TYPE_KEY)
+            .map(|t| t == "node")
+            .unwrap_or(false);
+
+        let mut new_name = None;
+        if is_annotation_node {
+            if let Some(hash_pos) = old_name.find('#') {
+                let document = &old_name[..hash_pos];
+                let annotations = node_annos.get_annotations_for_item(&n);
+                new_name = scheme.node_name(document, &old_name[hash_pos + 1..], &annotations);
+                if let Some(new_name) = &new_name {
+                    let keeps_document = new_name.len() > hash_pos
+                        && new_name.starts_with(&old_name[..=hash_pos])
+                        && !new_name[hash_pos + 1..].contains('#');
+                    if !keeps_document {
+                        return Err(CorpusStorageError::NodeNameOutsideDocument {
+                            node_name: new_name.clone(),
+                            old_name: old_name.clone(),
+                            document: document.to_string(),
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+        let final_name = new_name.clone().unwrap_or_else(|| old_name.clone());
+        if let Some(other) = new_names.insert(final_name.clone(), old_name.clone()) {
+            return Err(CorpusStorageError::NodeNameCollision {
+                node_name: final_name,
+                first: other,
+                second: old_name,
+            }
+            .into());
+        }
+        if let Some(new_name) = new_name {
+            if new_name != old_name {
+                renamed.push((n, new_name));
+            }
+        }
+    }
+
+    let result = renamed.len();
+    let node_annos = graph.get_node_annos_mut();
+    for (n, new_name) in renamed {
+        node_annos.insert(
+            n,
+            Annotation {
+                key: NODE_NAME_KEY.as_ref().clone(),
+                val: new_name.into(),
+            },
+        )?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphannis_core::types::AnnoKey;
+
+    #[test]
+    fn parse_and_apply_template() {
+        let annotations = vec![
+            Annotation {
+                key: AnnoKey {
+                    ns: "ext".into(),
+                    name: "id".into(),
+                },
+                val: "A-1".into(),
+            },
+            Annotation {
+                key: AnnoKey {
+                    ns: "default_ns".into(),
+                    name: "pos".into(),
+                },
+                val: "NN".into(),
+            },
+        ];
+
+        let template = NodeNameTemplate::new("{document}#{ext::id}").unwrap();
+        assert_eq!(
+            Some("root/doc1#A-1".to_string()),
+            template.node_name("root/doc1", "tok1", &annotations)
+        );
+        let template = NodeNameTemplate::new("{document}#{pos}_{fragment}{{x}}").unwrap();
+        assert_eq!(
+            Some("root/doc1#NN_tok1{x}".to_string()),
+            template.node_name("root/doc1", "tok1", &annotations)
+        );
+        // missing annotations keep the original name
+        let template = NodeNameTemplate::new("{document}#{other::id}").unwrap();
+        assert_eq!(None, template.node_name("root/doc1", "tok1", &annotations));
+
+        assert!(NodeNameTemplate::new("{document").is_err());
+        assert!(NodeNameTemplate::new("{}").is_err());
+        assert!(NodeNameTemplate::new("doc}").is_err());
+        // the document prefix is required
+        assert!(NodeNameTemplate::new("{ext::id}").is_err());
+        assert!(NodeNameTemplate::new("{document}_{ext::id}").is_err());
+        assert!(NodeNameTemplate::new("x{document}#{ext::id}").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::annis::db::exec::{Desc, EmptyResultSet, ExecutionNode};
 use crate::annis::db::query::disjunction::Disjunction;
 use crate::annis::db::query::Config;
+use crate::annis::types::{PlanNode, QueryWarning};
 use crate::AnnotationGraph;
 use crate::{annis::errors::*, graph::Match};
 use graphannis_core::{
@@ -19,6 +20,50 @@ pub struct ExecutionPlan<'a> {
     inverse_node_pos: Vec<Option<Vec<usize>>>,
     proxy_mode: bool,
     unique_result_set: HashSet<Vec<(NodeID, Arc<AnnoKey>)>>,
+    warnings: Vec<QueryWarning>,
+}
+
+fn add_warning(warnings: &mut Vec<QueryWarning>, w: QueryWarning) {
+    if !warnings.contains(&w) {
+        warnings.push(w);
+    }
+}
+
+/// Collect the warnings for all operators of an execution plan description.
+fn collect_warnings(desc: &Desc, warnings: &mut Vec<QueryWarning>) {
+    if desc.impl_description.starts_with("nestedloop") {
+        add_warning(
+            warnings,
+            QueryWarning::NestedLoopJoin {
+                query_fragment: desc.query_fragment.clone(),
+            },
+        );
+    }
+    if let Some(ref operator) = desc.operator {
+        for c in &operator.components {
+            if !c.loaded {
+                add_warning(
+                    warnings,
+                    QueryWarning::ComponentNotLoaded {
+                        component: c.component.to_string(),
+                    },
+                );
+            } else if c.statistics.is_none() {
+                add_warning(
+                    warnings,
+                    QueryWarning::StatisticsMissing {
+                        component: c.component.to_string(),
+                    },
+                );
+            }
+        }
+    }
+    if let Some(ref lhs) = desc.lhs {
+        collect_warnings(lhs, warnings);
+    }
+    if let Some(ref rhs) = desc.rhs {
+        collect_warnings(rhs, warnings);
+    }
 }
 
 impl<'a> ExecutionPlan<'a> {
@@ -30,9 +75,27 @@ impl<'a> ExecutionPlan<'a> {
         let mut plans: Vec<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>> = Vec::new();
         let mut descriptions = Vec::new();
         let mut inverse_node_pos = Vec::new();
-        for alt in &query.alternatives {
+        let mut warnings = Vec::new();
+        for (alt_idx, alt) in query.alternatives.iter().enumerate() {
+            for component in alt.missing_components(db) {
+                add_warning(
+                    &mut warnings,
+                    QueryWarning::ComponentNotLoaded { component },
+                );
+            }
             let p = alt.make_exec_node(db, &config);
             if let Ok(p) = p {
+                if let Some(description) = alt.find_contradiction(db) {
+                    // don't execute an alternative that can't have any result
+                    warnings.push(QueryWarning::Contradiction {
+                        alternative: alt_idx + 1,
+                        description,
+                    });
+                    continue;
+                }
+                if let Some(desc) = p.get_desc() {
+                    collect_warnings(desc, &mut warnings);
+                }
                 descriptions.push(p.get_desc().cloned());
 
                 if let Some(ref desc) = p.get_desc() {
@@ -66,6 +129,10 @@ impl<'a> ExecutionPlan<'a> {
                 if let GraphAnnisError::AQLSemanticError(_) = &e {
                     return Err(e);
                 }
+                warnings.push(QueryWarning::AlternativeIgnored {
+                    alternative: alt_idx + 1,
+                    reason: e.to_string(),
+                });
             }
         }
 
@@ -82,9 +149,15 @@ impl<'a> ExecutionPlan<'a> {
             proxy_mode: plans.len() == 1,
             plans,
             unique_result_set: HashSet::new(),
+            warnings,
         })
     }
 
+    /// Non-fatal issues that were detected when creating the execution plan.
+    pub fn warnings(&self) -> &[QueryWarning] {
+        &self.warnings
+    }
+
     fn reorder_match(&self, tmp: MatchGroup) -> MatchGroup {
         if tmp.len() <= 1 {
             // nothing to reorder
@@ -104,6 +177,14 @@ impl<'a> ExecutionPlan<'a> {
         }
     }
 
+    /// The execution plan tree of each alternative or `None` if the alternative is not executed.
+    pub fn plan_nodes(&self) -> Vec<Option<PlanNode>> {
+        self.descriptions
+            .iter()
+            .map(|d| d.as_ref().map(|d| d.to_plan_node()))
+            .collect()
+    }
+
     pub fn estimated_output_size(&self) -> usize {
         let mut estimation = 0;
         for desc in &self.descriptions {
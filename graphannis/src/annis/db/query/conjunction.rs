@@ -1,15 +1,17 @@
 use super::disjunction::Disjunction;
 use super::Config;
+use crate::annis::db::exec::antijoin::{AntiJoin, AntiJoinOperand, AntiJoinOperator, OptionalNode};
 use crate::annis::db::exec::filter::Filter;
 use crate::annis::db::exec::indexjoin::IndexJoin;
+use crate::annis::db::exec::mergejoin::{self, MergeJoin};
 use crate::annis::db::exec::nestedloop::NestedLoop;
 use crate::annis::db::exec::nodesearch::{NodeSearch, NodeSearchSpec};
 use crate::annis::db::exec::parallel;
-use crate::annis::db::exec::{CostEstimate, Desc, ExecutionNode, NodeSearchDesc};
+use crate::annis::db::exec::{ComponentDesc, CostEstimate, Desc, ExecutionNode, NodeSearchDesc};
 use crate::annis::db::{aql::model::AnnotationComponentType, AnnotationStorage};
 use crate::annis::errors::*;
 use crate::annis::operator::{
-    BinaryOperator, BinaryOperatorSpec, UnaryOperator, UnaryOperatorSpec,
+    BinaryOperator, BinaryOperatorSpec, ReachabilityCache, UnaryOperator, UnaryOperatorSpec,
 };
 use crate::AnnotationGraph;
 use crate::{
@@ -18,7 +20,7 @@ use crate::{
 };
 use graphannis_core::{
     annostorage::MatchGroup,
-    graph::storage::GraphStatistic,
+    graph::{storage::GraphStatistic, ANNIS_NS, NODE_TYPE},
     types::{Component, Edge},
 };
 use rand::distributions::Distribution;
@@ -34,6 +36,7 @@ struct BinaryOperatorSpecEntry<'a> {
     idx_left: usize,
     idx_right: usize,
     global_reflexivity: bool,
+    location: Option<LineColumnRange>,
 }
 
 #[derive(Debug)]
@@ -47,10 +50,14 @@ pub struct BinaryOperatorEntry<'a> {
     pub node_nr_left: usize,
     pub node_nr_right: usize,
     pub global_reflexivity: bool,
+    /// The components used by the operator, as they were when the plan was created.
+    pub components: Vec<ComponentDesc>,
+    /// True if the operands are switched compared to the query, i.e. the operator is the inverse one.
+    pub operands_switched: bool,
 }
 
-pub struct UnaryOperatorEntry {
-    pub op: Box<dyn UnaryOperator>,
+pub struct UnaryOperatorEntry<'a> {
+    pub op: Box<dyn UnaryOperator + 'a>,
     pub node_nr: usize,
 }
 
@@ -63,6 +70,10 @@ pub struct Conjunction<'a> {
     location_in_query: HashMap<String, LineColumnRange>,
     include_in_output: HashSet<String>,
     var_idx_offset: usize,
+    /// The number of optional nodes, which are always the last nodes of the conjunction.
+    num_optional_nodes: usize,
+    /// A previously optimized order of the binary operators, which is used instead of optimizing the order again.
+    operator_order: Option<Vec<usize>>,
 }
 
 fn update_components_for_nodes(
@@ -117,9 +128,23 @@ fn create_join<'b>(
     idx_left: usize,
     idx_right: usize,
 ) -> Box<dyn ExecutionNode<Item = MatchGroup> + 'b> {
+    let thresholds = &config.join_thresholds;
+    // parallel joins process their input in batches, which is wasted work when only the first match is needed
+    // or the input is too small
+    let use_parallel_joins = |lhs: &dyn ExecutionNode<Item = MatchGroup>| {
+        let estimated_input = lhs
+            .get_desc()
+            .and_then(|d| d.cost.as_ref())
+            .map(|c| c.output)
+            .unwrap_or(usize::max_value());
+        config.use_parallel_joins
+            && !config.existence_check
+            && estimated_input >= thresholds.min_parallel_join_input
+    };
+
     if exec_right.as_nodesearch().is_some() {
         // use index join
-        if config.use_parallel_joins {
+        if use_parallel_joins(exec_left.as_ref()) {
             let join = parallel::indexjoin::IndexJoin::new(
                 exec_left,
                 idx_left,
@@ -127,6 +152,8 @@ fn create_join<'b>(
                 exec_right.as_nodesearch().unwrap().get_node_search_desc(),
                 db.get_node_annos(),
                 exec_right.get_desc(),
+                thresholds.parallel_index_join_batch_size,
+                config.thread_pool.clone(),
             );
             return Box::new(join);
         } else {
@@ -143,7 +170,7 @@ fn create_join<'b>(
     } else if exec_left.as_nodesearch().is_some() {
         // avoid a nested loop join by switching the operand and using and index join
         if let Some(inverse_op) = op_entry.op.get_inverse_operator(db) {
-            if config.use_parallel_joins {
+            if use_parallel_joins(exec_right.as_ref()) {
                 let join = parallel::indexjoin::IndexJoin::new(
                     exec_right,
                     idx_right,
@@ -152,10 +179,14 @@ fn create_join<'b>(
                         node_nr_right: op_entry.node_nr_left,
                         op: inverse_op,
                         global_reflexivity: op_entry.global_reflexivity,
+                        components: op_entry.components.clone(),
+                        operands_switched: !op_entry.operands_switched,
                     },
                     exec_left.as_nodesearch().unwrap().get_node_search_desc(),
                     db.get_node_annos(),
                     exec_left.get_desc(),
+                    thresholds.parallel_index_join_batch_size,
+                    config.thread_pool.clone(),
                 );
                 return Box::new(join);
             } else {
@@ -167,6 +198,8 @@ fn create_join<'b>(
                         node_nr_right: op_entry.node_nr_left,
                         op: inverse_op,
                         global_reflexivity: op_entry.global_reflexivity,
+                        components: op_entry.components.clone(),
+                        operands_switched: !op_entry.operands_switched,
                     },
                     exec_left.as_nodesearch().unwrap().get_node_search_desc(),
                     db.get_node_annos(),
@@ -177,10 +210,60 @@ fn create_join<'b>(
         }
     }
 
+    // only compare matches of the same document if the operator allows it and there is more than one document,
+    // but not for existence checks since both sides would have to be collected before the first match
+    if op_entry.op.is_document_local() && !config.existence_check {
+        let number_of_documents = db
+            .get_node_annos()
+            .number_of_annotations_by_name(Some(ANNIS_NS), "doc");
+        let estimated_output = |exec: &dyn ExecutionNode<Item = MatchGroup>| {
+            exec.get_desc()
+                .and_then(|d| d.cost.as_ref())
+                .map(|c| c.output)
+        };
+        // compare the costs with the nested loop, which compares each match with all matches of the other side
+        let merge_join_is_cheaper = match (
+            estimated_output(exec_left.as_ref()),
+            estimated_output(exec_right.as_ref()),
+        ) {
+            (Some(out_lhs), Some(out_rhs)) => {
+                let merge_cost = mergejoin::estimated_cost(
+                    out_lhs,
+                    exec_left.is_sorted_by_document(),
+                    out_rhs,
+                    exec_right.is_sorted_by_document(),
+                    number_of_documents,
+                );
+                let nested_loop_cost = out_lhs.min(out_rhs) + out_lhs.saturating_mul(out_rhs);
+                merge_cost < nested_loop_cost
+            }
+            _ => true,
+        };
+        if number_of_documents >= thresholds.min_documents_for_merge_join && merge_join_is_cheaper {
+            let join = MergeJoin::new(
+                op_entry,
+                exec_left,
+                exec_right,
+                idx_left,
+                idx_right,
+                db,
+                number_of_documents,
+                config.timeout,
+            );
+            return Box::new(join);
+        }
+    }
+
     // use nested loop as "fallback"
-    if config.use_parallel_joins {
+    if use_parallel_joins(exec_left.as_ref()) {
         let join = parallel::nestedloop::NestedLoop::new(
-            op_entry, exec_left, exec_right, idx_left, idx_right,
+            op_entry,
+            exec_left,
+            exec_right,
+            idx_left,
+            idx_right,
+            thresholds.parallel_nested_loop_batch_size,
+            config.thread_pool.clone(),
         );
         Box::new(join)
     } else {
@@ -199,6 +282,8 @@ impl<'a> Conjunction<'a> {
             location_in_query: HashMap::default(),
             include_in_output: HashSet::default(),
             var_idx_offset: 0,
+            num_optional_nodes: 0,
+            operator_order: None,
         }
     }
 
@@ -211,6 +296,8 @@ impl<'a> Conjunction<'a> {
             location_in_query: HashMap::default(),
             include_in_output: HashSet::default(),
             var_idx_offset,
+            num_optional_nodes: 0,
+            operator_order: None,
         }
     }
 
@@ -223,6 +310,7 @@ impl<'a> Conjunction<'a> {
         for (var, spec) in &self.nodes {
             let anno_name = match spec {
                 NodeSearchSpec::ExactValue { name, .. } => Some(name.clone()),
+                NodeSearchSpec::ExactValueSet { name, .. } => Some(name.clone()),
                 NodeSearchSpec::RegexValue { name, .. } => Some(name.clone()),
                 _ => None,
             };
@@ -248,13 +336,33 @@ impl<'a> Conjunction<'a> {
         location: Option<LineColumnRange>,
         included_in_output: bool,
     ) -> String {
-        let idx = self.var_idx_offset + self.nodes.len();
         let variable = if let Some(variable) = variable {
             variable.to_string()
         } else {
-            (idx + 1).to_string()
+            (self.var_idx_offset + self.nodes.len() + 1).to_string()
         };
-        self.nodes.push((variable.clone(), node));
+        // insert the node before the optional nodes and move these to the next position
+        let pos = self.nodes.len() - self.num_optional_nodes;
+        let idx = self.var_idx_offset + pos;
+        for v in self.variables.values_mut() {
+            if *v >= idx {
+                *v += 1;
+            }
+        }
+        for op_entry in self.binary_operators.iter_mut() {
+            if op_entry.idx_left >= idx {
+                op_entry.idx_left += 1;
+            }
+            if op_entry.idx_right >= idx {
+                op_entry.idx_right += 1;
+            }
+        }
+        for op_entry in self.unary_operators.iter_mut() {
+            if op_entry.idx >= idx {
+                op_entry.idx += 1;
+            }
+        }
+        self.nodes.insert(pos, (variable.clone(), node));
         self.variables.insert(variable.clone(), idx);
         if included_in_output {
             self.include_in_output.insert(variable.clone());
@@ -265,6 +373,50 @@ impl<'a> Conjunction<'a> {
         variable
     }
 
+    /// Add an optional node, which is not part of the output and must not exist.
+    ///
+    /// All operators that have an optional node as operand form a condition, which must not be fulfilled by any
+    /// combination of nodes for the optional nodes. Matches of the other nodes for which such a combination exists
+    /// are discarded.
+    pub fn add_optional_node_from_query(
+        &mut self,
+        node: NodeSearchSpec,
+        variable: Option<&str>,
+        location: Option<LineColumnRange>,
+    ) -> String {
+        let idx = self.var_idx_offset + self.nodes.len();
+        let variable = if let Some(variable) = variable {
+            variable.to_string()
+        } else {
+            (idx + 1).to_string()
+        };
+        self.nodes.push((variable.clone(), node));
+        self.variables.insert(variable.clone(), idx);
+        self.num_optional_nodes += 1;
+        if let Some(location) = location {
+            self.location_in_query.insert(variable.clone(), location);
+        }
+        variable
+    }
+
+    /// Returns `true` if the node with the given variable is an optional node.
+    pub fn is_optional_node(&self, variable: &str) -> bool {
+        self.variables
+            .get(variable)
+            .map(|idx| self.is_optional(*idx))
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if the node at the given index (including the offset) is an optional node.
+    fn is_optional(&self, idx: usize) -> bool {
+        idx - self.var_idx_offset >= self.nodes.len() - self.num_optional_nodes
+    }
+
+    /// Returns `true` if an operand of the operator is an optional node.
+    fn has_optional_operand(&self, op_entry: &BinaryOperatorSpecEntry) -> bool {
+        self.is_optional(op_entry.idx_left) || self.is_optional(op_entry.idx_right)
+    }
+
     pub fn add_unary_operator_from_query(
         &mut self,
         op: Box<dyn UnaryOperatorSpec>,
@@ -303,13 +455,14 @@ impl<'a> Conjunction<'a> {
     ) -> Result<()> {
         //let original_order = self.operators.len();
         let idx_left = self.resolve_variable_pos(var_left, location.clone())?;
-        let idx_right = self.resolve_variable_pos(var_right, location)?;
+        let idx_right = self.resolve_variable_pos(var_right, location.clone())?;
 
         self.binary_operators.push(BinaryOperatorSpecEntry {
             op,
             idx_left,
             idx_right,
             global_reflexivity,
+            location,
         });
         Ok(())
     }
@@ -318,6 +471,39 @@ impl<'a> Conjunction<'a> {
         self.nodes.len()
     }
 
+    /// Return the number of optional nodes, which are not part of the matches.
+    pub fn num_of_optional_nodes(&self) -> usize {
+        self.num_optional_nodes
+    }
+
+    /// Use the given order of the binary operators when creating the execution plan, e.g. because it has been
+    /// optimized for an earlier execution of the same query.
+    ///
+    /// Returns `false` and ignores the order if it is not a permutation of all binary operators.
+    pub fn set_operator_order(&mut self, operator_order: Vec<usize>) -> bool {
+        if self.is_valid_operator_order(&operator_order) {
+            self.operator_order = Some(operator_order);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_valid_operator_order(&self, operator_order: &[usize]) -> bool {
+        let mut sorted = operator_order.to_vec();
+        sorted.sort_unstable();
+        sorted.len() == self.binary_operators.len()
+            && sorted.iter().enumerate().all(|(i, o)| i == *o)
+    }
+
+    /// Return the number of binary operators that don't have an upper bound for the distance.
+    pub fn num_of_unbounded_operators(&self) -> usize {
+        self.binary_operators
+            .iter()
+            .filter(|op_entry| op_entry.op.is_unbounded())
+            .count()
+    }
+
     pub fn resolve_variable_pos(
         &self,
         variable: &str,
@@ -361,6 +547,41 @@ impl<'a> Conjunction<'a> {
         }))
     }
 
+    /// Replace the search specification of the node with the given variable.
+    pub fn replace_node(&mut self, variable: &str, node: NodeSearchSpec) -> Result<()> {
+        let idx = self.resolve_variable_pos(variable, None)?;
+        if let Some(pos) = idx.checked_sub(self.var_idx_offset) {
+            if pos < self.nodes.len() {
+                self.nodes[pos].1 = node;
+                return Ok(());
+            }
+        }
+        Err(GraphAnnisError::AQLSemanticError(AQLError {
+            desc: format!("Operand '#{}' not found", variable),
+            location: None,
+        }))
+    }
+
+    /// Returns `true` if all nodes of a match for this conjunction belong to the same document.
+    ///
+    /// This is the case if the nodes are connected by at least one operator and all operators are
+    /// document-local. Operators of optional nodes are not considered, since these nodes are not part of the match.
+    pub fn is_document_local(&self, db: &AnnotationGraph) -> bool {
+        let mut operators = self
+            .binary_operators
+            .iter()
+            .filter(|op_entry| !self.has_optional_operand(op_entry))
+            .peekable();
+        operators.peek().is_some()
+            && operators.all(|op_entry| {
+                op_entry
+                    .op
+                    .create_operator(db)
+                    .map(|op| op.is_document_local())
+                    .unwrap_or(false)
+            })
+    }
+
     pub fn necessary_components(
         &self,
         db: &AnnotationGraph,
@@ -478,12 +699,69 @@ impl<'a> Conjunction<'a> {
         Ok(best_operator_order)
     }
 
+    /// Replace the node search for an operand of a document-local operator with a search that returns its matches
+    /// in document order, if checking all nodes is cheaper than sorting the matches for a merge join.
+    ///
+    /// This is only done for node searches which are filtered by a unary operator, because a filtered node search
+    /// can't be used for an index join anyway.
+    fn search_in_document_order(
+        &self,
+        exec: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+        idx: usize,
+        db: &'a AnnotationGraph,
+        config: &Config,
+    ) -> Box<dyn ExecutionNode<Item = MatchGroup> + 'a> {
+        let node_search = match exec.as_nodesearch() {
+            Some(node_search) if !config.existence_check && !exec.is_sorted_by_document() => {
+                node_search
+            }
+            _ => return exec,
+        };
+        let number_of_documents = db
+            .get_node_annos()
+            .number_of_annotations_by_name(Some(ANNIS_NS), "doc");
+        if number_of_documents < config.join_thresholds.min_documents_for_merge_join {
+            return exec;
+        }
+        let is_document_local_operand = self
+            .binary_operators
+            .iter()
+            .filter(|op_entry| !self.has_optional_operand(op_entry))
+            .filter(|op_entry| op_entry.idx_left == idx || op_entry.idx_right == idx)
+            .any(|op_entry| {
+                op_entry
+                    .op
+                    .create_operator(db)
+                    .map(|op| op.is_document_local())
+                    .unwrap_or(false)
+            });
+        let estimated_output = exec
+            .get_desc()
+            .and_then(|d| d.cost.as_ref())
+            .map(|c| c.output);
+        if let (true, Some(estimated_output)) = (is_document_local_operand, estimated_output) {
+            // all nodes are checked once for not being part of a document and once when iterating the documents
+            let number_of_nodes = db
+                .get_node_annos()
+                .number_of_annotations_by_name(Some(ANNIS_NS), NODE_TYPE);
+            if number_of_nodes.saturating_mul(2) < mergejoin::sort_cost(estimated_output, false) {
+                return Box::new(NodeSearch::new_document_ordered_search(
+                    db,
+                    node_search.get_node_search_desc(),
+                    node_search.get_desc(),
+                ));
+            }
+        }
+        exec
+    }
+
     fn optimize_node_search_by_operator(
         &'a self,
         node_search_desc: Arc<NodeSearchDesc>,
         desc: Option<&Desc>,
         op_spec_entries: Box<dyn Iterator<Item = &'a BinaryOperatorSpecEntry> + 'a>,
         db: &'a AnnotationGraph,
+        config: &Config,
     ) -> Option<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>> {
         let desc = desc?;
         // check if we can replace this node search with a generic "all nodes from either of these components" search
@@ -491,7 +769,7 @@ impl<'a> Conjunction<'a> {
 
         for e in op_spec_entries {
             let op_spec = &e.op;
-            if e.idx_left == desc.component_nr {
+            if e.idx_left == desc.component_nr && !op_spec.is_filter_only() {
                 // get the necessary components and count the number of nodes in these components
                 let components = op_spec.necessary_components(db);
                 if !components.is_empty() {
@@ -507,15 +785,17 @@ impl<'a> Conjunction<'a> {
                                 let edge_anno_est = edge_anno_spec.guess_max_count(anno_storage);
                                 estimated_component_search += edge_anno_est;
                                 estimation_valid = true;
-                            } else if let Some(stats) = gs.get_statistics() {
-                                let stats: &GraphStatistic = stats;
+                            } else if let Some(stats) = gs.get_statistics_or_estimate() {
+                                let stats: GraphStatistic = stats;
                                 estimated_component_search += stats.nodes;
                                 estimation_valid = true;
                             }
                         }
                     }
 
-                    if estimation_valid && node_search_cost.output > estimated_component_search {
+                    let max_output = (estimated_component_search as f64)
+                        * config.join_thresholds.component_search_factor;
+                    if estimation_valid && (node_search_cost.output as f64) > max_output {
                         let poc_search = NodeSearch::new_partofcomponentsearch(
                             db,
                             node_search_desc,
@@ -557,7 +837,8 @@ impl<'a> Conjunction<'a> {
             BTreeMap::new();
         let mut node2cost: BTreeMap<usize, CostEstimate> = BTreeMap::new();
 
-        for node_nr in 0..self.nodes.len() {
+        let num_bound_nodes = self.nodes.len() - self.num_optional_nodes;
+        for node_nr in 0..num_bound_nodes {
             let n_spec = &self.nodes[node_nr].1;
             let n_var = &self.nodes[node_nr].0;
 
@@ -596,14 +877,20 @@ impl<'a> Conjunction<'a> {
                         impl_description: orig_impl_desc,
                         query_fragment: orig_query_frag,
                         cost,
+                        operator: None,
                     };
                     node_search.set_desc(Some(new_desc));
 
                     let node_by_component_search = self.optimize_node_search_by_operator(
                         node_search.get_node_search_desc(),
                         node_search.get_desc(),
-                        Box::new(self.binary_operators.iter()),
+                        Box::new(
+                            self.binary_operators
+                                .iter()
+                                .filter(move |e| !self.has_optional_operand(e)),
+                        ),
                         db,
+                        config,
                     );
 
                     // move to map
@@ -619,11 +906,17 @@ impl<'a> Conjunction<'a> {
 
         // 2. add unary operators as filter to the existing node search
         for op_spec_entry in self.unary_operators.iter() {
+            if self.is_optional(op_spec_entry.idx) {
+                // applied when searching for the optional node
+                continue;
+            }
             let child_exec: Box<dyn ExecutionNode<Item = MatchGroup> + 'a> = component2exec
                 .remove(&op_spec_entry.idx)
                 .ok_or(GraphAnnisError::NoExecutionNode(op_spec_entry.idx))?;
+            let child_exec =
+                self.search_in_document_order(child_exec, op_spec_entry.idx, db, config);
 
-            let op: Box<dyn UnaryOperator> =
+            let op: Box<dyn UnaryOperator + 'a> =
                 op_spec_entry.op.create_operator(db).ok_or_else(|| {
                     GraphAnnisError::ImpossibleSearch(format!(
                         "could not create operator {:?}",
@@ -640,11 +933,25 @@ impl<'a> Conjunction<'a> {
         }
 
         // 3. add the joins which produce the results in operand order
+
+        // All operators of this plan share the results of their reachability checks
+        let reachability_cache = Arc::new(ReachabilityCache::default());
+
+        // Filter-only operators are applied when their operands have been bound by the other operators
+        // Operators of optional nodes are applied by the anti-join
+        let (mut operator_order, filter_only): (Vec<usize>, Vec<usize>) = operator_order
+            .into_iter()
+            .filter(|i| !self.has_optional_operand(&self.binary_operators[*i]))
+            .partition(|i| !self.binary_operators[*i].op.is_filter_only());
+        operator_order.extend(filter_only);
+
         for i in operator_order {
             let op_spec_entry: &BinaryOperatorSpecEntry<'a> = &self.binary_operators[i];
 
-            let mut op: Box<dyn BinaryOperator + 'a> =
-                op_spec_entry.op.create_operator(db).ok_or_else(|| {
+            let mut op: Box<dyn BinaryOperator + 'a> = op_spec_entry
+                .op
+                .create_operator_with_cache(db, &reachability_cache)
+                .ok_or_else(|| {
                     GraphAnnisError::ImpossibleSearch(format!(
                         "could not create operator {:?}",
                         op_spec_entry
@@ -654,9 +961,11 @@ impl<'a> Conjunction<'a> {
             let mut spec_idx_left = op_spec_entry.idx_left;
             let mut spec_idx_right = op_spec_entry.idx_right;
 
+            let mut operands_switched = false;
             let inverse_op = op.get_inverse_operator(db);
             if let Some(inverse_op) = inverse_op {
                 if should_switch_operand_order(op_spec_entry, &node2cost) {
+                    operands_switched = true;
                     spec_idx_left = op_spec_entry.idx_right;
                     spec_idx_right = op_spec_entry.idx_left;
 
@@ -668,11 +977,24 @@ impl<'a> Conjunction<'a> {
             spec_idx_left -= self.var_idx_offset;
             spec_idx_right -= self.var_idx_offset;
 
+            let mut components: Vec<_> = op_spec_entry
+                .op
+                .necessary_components(db)
+                .into_iter()
+                .collect();
+            components.sort();
+            let components = components
+                .into_iter()
+                .map(|c| ComponentDesc::new(c, db))
+                .collect();
+
             let op_entry = BinaryOperatorEntry {
                 op,
                 node_nr_left: spec_idx_left + 1,
                 node_nr_right: spec_idx_right + 1,
                 global_reflexivity: op_spec_entry.global_reflexivity,
+                components,
+                operands_switched,
             };
 
             let component_left: usize = *(node2component
@@ -718,9 +1040,16 @@ impl<'a> Conjunction<'a> {
                         .get(&spec_idx_right)
                         .ok_or(GraphAnnisError::RHSOperandNotFound)?);
 
-                    create_join(
-                        db, config, op_entry, exec_left, exec_right, idx_left, idx_right,
-                    )
+                    if op_spec_entry.op.is_filter_only() {
+                        // the operator can't retrieve matches, compare all combinations of both operands
+                        Box::new(NestedLoop::new(
+                            op_entry, exec_left, exec_right, idx_left, idx_right,
+                        ))
+                    } else {
+                        create_join(
+                            db, config, op_entry, exec_left, exec_right, idx_left, idx_right,
+                        )
+                    }
                 };
 
             let new_component_nr = new_exec
@@ -738,7 +1067,7 @@ impl<'a> Conjunction<'a> {
         }
 
         // it must be checked before that all components are connected
-        component2exec
+        let exec = component2exec
             .into_iter()
             .map(|(_cid, exec)| exec)
             .next()
@@ -746,16 +1075,134 @@ impl<'a> Conjunction<'a> {
                 GraphAnnisError::ImpossibleSearch(String::from(
                     "could not find execution node for query component",
                 ))
-            })
+            })?;
+
+        if self.num_optional_nodes > 0 {
+            self.make_anti_join(exec, db, &reachability_cache)
+        } else {
+            Ok(exec)
+        }
+    }
+
+    /// Discard all matches of the given execution node for which the optional nodes exist.
+    fn make_anti_join(
+        &'a self,
+        exec: Box<dyn ExecutionNode<Item = MatchGroup> + 'a>,
+        db: &'a AnnotationGraph,
+        reachability_cache: &Arc<ReachabilityCache>,
+    ) -> Result<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>> {
+        let num_bound_nodes = self.nodes.len() - self.num_optional_nodes;
+
+        let mut optional_nodes = Vec::with_capacity(self.num_optional_nodes);
+        for node_nr in num_bound_nodes..self.nodes.len() {
+            let (n_var, n_spec) = &self.nodes[node_nr];
+            let node_search = NodeSearch::from_spec(
+                n_spec.clone(),
+                node_nr,
+                db,
+                self.location_in_query.get(n_var).cloned(),
+            )?;
+            let mut unary_operators = Vec::new();
+            for op_spec_entry in self.unary_operators.iter() {
+                if op_spec_entry.idx - self.var_idx_offset == node_nr {
+                    let op = op_spec_entry.op.create_operator(db).ok_or_else(|| {
+                        GraphAnnisError::ImpossibleSearch(format!(
+                            "could not create operator {:?}",
+                            op_spec_entry
+                        ))
+                    })?;
+                    unary_operators.push(op);
+                }
+            }
+            optional_nodes.push(OptionalNode {
+                node_search_desc: node_search.get_node_search_desc(),
+                all_matches: Some(Box::new(node_search)),
+                unary_operators,
+            });
+        }
+
+        let node_pos = &exec
+            .get_desc()
+            .ok_or(GraphAnnisError::PlanDescriptionMissing)?
+            .node_pos;
+        let get_operand = |idx: usize| -> Result<AntiJoinOperand> {
+            let node_nr = idx - self.var_idx_offset;
+            if node_nr >= num_bound_nodes {
+                Ok(AntiJoinOperand::Optional(node_nr - num_bound_nodes))
+            } else {
+                let pos = node_pos
+                    .get(&node_nr)
+                    .ok_or(GraphAnnisError::NoComponentForNode(node_nr + 1))?;
+                Ok(AntiJoinOperand::Outer(*pos))
+            }
+        };
+
+        let mut operators = Vec::new();
+        let mut query_fragments = Vec::new();
+        for op_spec_entry in self.binary_operators.iter() {
+            if !self.has_optional_operand(op_spec_entry) {
+                continue;
+            }
+            let op = op_spec_entry
+                .op
+                .create_operator_with_cache(db, reachability_cache)
+                .ok_or_else(|| {
+                    GraphAnnisError::ImpossibleSearch(format!(
+                        "could not create operator {:?}",
+                        op_spec_entry
+                    ))
+                })?;
+            query_fragments.push(format!(
+                "#{} {} #{}",
+                self.nodes[op_spec_entry.idx_left - self.var_idx_offset].0,
+                op,
+                self.nodes[op_spec_entry.idx_right - self.var_idx_offset].0
+            ));
+            operators.push(AntiJoinOperator {
+                inverse_op: op.get_inverse_operator(db),
+                op,
+                lhs: get_operand(op_spec_entry.idx_left)?,
+                rhs: get_operand(op_spec_entry.idx_right)?,
+            });
+        }
+
+        let anti_join = AntiJoin::new(
+            exec,
+            optional_nodes,
+            operators,
+            db.get_node_annos(),
+            &format!("!({})", query_fragments.join(" & ")),
+        );
+        Ok(Box::new(anti_join))
     }
 
     fn check_components_connected(&self) -> Result<()> {
         let mut node2component: BTreeMap<usize, usize> = BTreeMap::new();
+        let num_bound_nodes = self.nodes.len() - self.num_optional_nodes;
         node2component
-            .extend((self.var_idx_offset..self.nodes.len() + self.var_idx_offset).map(|i| (i, i)));
+            .extend((self.var_idx_offset..num_bound_nodes + self.var_idx_offset).map(|i| (i, i)));
+
+        // optional nodes must be used by at least one operator
+        for optional_idx in num_bound_nodes..self.nodes.len() {
+            let idx = optional_idx + self.var_idx_offset;
+            if !self
+                .binary_operators
+                .iter()
+                .any(|op_entry| op_entry.idx_left == idx || op_entry.idx_right == idx)
+            {
+                let n_var = &self.nodes[optional_idx].0;
+                return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                    desc: format!(
+                        "Optional variable \"{}\" not bound (use negated linguistic operators)",
+                        n_var
+                    ),
+                    location: self.location_in_query.get(n_var).cloned(),
+                }));
+            }
+        }
 
         for op_entry in self.binary_operators.iter() {
-            if op_entry.op.is_binding() {
+            if op_entry.op.is_binding() && !self.has_optional_operand(op_entry) {
                 // merge both operands to the same component
                 if let (Some(component_left), Some(component_right)) = (
                     node2component.get(&op_entry.idx_left),
@@ -803,14 +1250,99 @@ impl<'a> Conjunction<'a> {
         Ok(())
     }
 
+    /// Check if the constraints of this conjunction contradict each other, so it can't have any result.
+    ///
+    /// Returns a description of the first found contradiction.
+    pub fn find_contradiction(&self, db: &AnnotationGraph) -> Option<String> {
+        let offset = self.var_idx_offset;
+        // a contradiction between optional nodes only means that they never exist
+        let binary_operators = || {
+            self.binary_operators
+                .iter()
+                .filter(|op_entry| !self.has_optional_operand(op_entry))
+        };
+        for op_entry in binary_operators() {
+            if let Some(reason) = op_entry.op.contradiction() {
+                return Some(format!(
+                    "operator between #{} and #{}: {}",
+                    self.nodes[op_entry.idx_left - offset].0,
+                    self.nodes[op_entry.idx_right - offset].0,
+                    reason
+                ));
+            }
+        }
+
+        // group all nodes that are bound to be the same node
+        let mut node2group: BTreeMap<usize, usize> =
+            (0..self.nodes.len()).map(|i| (i, i)).collect();
+        for op_entry in binary_operators() {
+            if op_entry.op.is_identity() {
+                let group_left = node2group[&(op_entry.idx_left - offset)];
+                let group_right = node2group[&(op_entry.idx_right - offset)];
+                update_components_for_nodes(&mut node2group, group_right, group_left);
+            }
+        }
+        for i in 0..self.nodes.len() {
+            for j in (i + 1)..self.nodes.len() {
+                if node2group[&i] == node2group[&j]
+                    && self.nodes[i].1.contradicts(&self.nodes[j].1, db)
+                {
+                    return Some(format!(
+                        "#{} ({}) and #{} ({}) are the same node, but their values contradict each other",
+                        self.nodes[i].0, self.nodes[i].1, self.nodes[j].0, self.nodes[j].1
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    /// Return the names of all components referenced by the operators of this conjunction, which do not exist.
+    pub fn missing_components(&self, db: &AnnotationGraph) -> Vec<String> {
+        self.binary_operators
+            .iter()
+            .flat_map(|op_entry| op_entry.op.missing_components(db))
+            .collect()
+    }
+
+    fn validate(&self, db: &AnnotationGraph) -> Result<()> {
+        self.check_components_connected()?;
+
+        for op_entry in &self.binary_operators {
+            if let Err(desc) = op_entry.op.validate(db) {
+                return Err(GraphAnnisError::AQLSemanticError(AQLError {
+                    desc,
+                    location: op_entry.location.clone(),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the order of the binary operators that is used for the execution plan.
+    ///
+    /// This is either the order given by [set_operator_order(...)](#method.set_operator_order) or the order with
+    /// the lowest estimated cost.
+    pub fn optimized_operator_order(
+        &self,
+        db: &'a AnnotationGraph,
+        config: &Config,
+    ) -> Result<Vec<usize>> {
+        self.validate(db)?;
+        match &self.operator_order {
+            Some(operator_order) if self.is_valid_operator_order(operator_order) => {
+                Ok(operator_order.clone())
+            }
+            _ => self.optimize_join_order_heuristics(db, config),
+        }
+    }
+
     pub fn make_exec_node(
         &'a self,
         db: &'a AnnotationGraph,
         config: &Config,
     ) -> Result<Box<dyn ExecutionNode<Item = MatchGroup> + 'a>> {
-        self.check_components_connected()?;
-
-        let operator_order = self.optimize_join_order_heuristics(db, config)?;
+        let operator_order = self.optimized_operator_order(db, config)?;
         self.make_exec_plan_with_order(db, config, operator_order)
     }
 }
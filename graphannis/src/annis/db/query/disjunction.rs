@@ -1,4 +1,6 @@
 use super::conjunction::Conjunction;
+use super::QueryLimits;
+use crate::annis::errors::{GraphAnnisError, Result};
 use crate::{annis::db::aql::model::AnnotationComponentType, AnnotationGraph};
 use graphannis_core::types::Component;
 use std::collections::HashSet;
@@ -52,4 +54,41 @@ impl<'a> Disjunction<'a> {
         }
         false
     }
+
+    /// Return an error describing the first exceeded limit if the query is too complex.
+    pub fn check_limits(&self, limits: &QueryLimits) -> Result<()> {
+        if let Some(max_alternatives) = limits.max_alternatives {
+            if self.alternatives.len() > max_alternatives {
+                return Err(GraphAnnisError::QueryTooComplex(format!(
+                    "the query has {} alternatives, but only {} are allowed",
+                    self.alternatives.len(),
+                    max_alternatives
+                )));
+            }
+        }
+        for (alt_nr, alt) in self.alternatives.iter().enumerate() {
+            if let Some(max_nodes) = limits.max_nodes {
+                if alt.num_of_nodes() > max_nodes {
+                    return Err(GraphAnnisError::QueryTooComplex(format!(
+                        "alternative {} of the query has {} nodes, but only {} are allowed",
+                        alt_nr + 1,
+                        alt.num_of_nodes(),
+                        max_nodes
+                    )));
+                }
+            }
+            if let Some(max_unbounded) = limits.max_unbounded_operators {
+                let unbounded = alt.num_of_unbounded_operators();
+                if unbounded > max_unbounded {
+                    return Err(GraphAnnisError::QueryTooComplex(format!(
+                        "alternative {} of the query has {} operators without an upper bound for the distance, but only {} are allowed",
+                        alt_nr + 1,
+                        unbounded,
+                        max_unbounded
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
 }
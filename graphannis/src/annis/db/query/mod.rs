@@ -1,6 +1,90 @@
+use crate::annis::util::TimeoutCheck;
+use rayon::ThreadPool;
+use std::fmt;
+use std::sync::Arc;
+
 #[derive(Clone, Default, Debug)]
 pub struct Config {
     pub use_parallel_joins: bool,
+    /// Only the existence of a match is relevant and the execution is stopped at the first match.
+    /// Joins that need to materialize their input before producing the first output are avoided.
+    pub existence_check: bool,
+    pub join_thresholds: JoinThresholds,
+    pub limits: QueryLimits,
+    /// Thread pool for the parallel joins, the global thread pool is used if `None`.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// Joins that have to process a large part of their input before producing output stop early when this
+    /// timeout is exceeded. The timeout must be checked again after iterating over the results to report it.
+    pub timeout: Option<TimeoutCheck>,
+}
+
+/// Thresholds which are used by the query planner to select the implementation of a join.
+///
+/// The defaults work well for most corpora, but corpora with an unusual shape (e.g. a huge number of
+/// very small documents) can profit from different values.
+/// The effective values are included in the output of [`CorpusStorage::plan`](crate::CorpusStorage::plan).
+#[derive(Clone, Debug, PartialEq)]
+pub struct JoinThresholds {
+    /// A merge join can be used for document-local operators if the corpus has at least this number of documents.
+    /// It is only chosen if its estimated cost is lower than the one of a nested loop join.
+    /// Defaults to `2`.
+    pub min_documents_for_merge_join: usize,
+    /// Parallel joins are only used if the estimated output of the left-hand side has at least this size.
+    /// Defaults to `0`, which means parallel joins are always used when they are enabled.
+    pub min_parallel_join_input: usize,
+    /// Number of left-hand side matches that are processed in one batch by the parallel index join.
+    /// Must be at least `1` and defaults to `512`.
+    pub parallel_index_join_batch_size: usize,
+    /// Number of match candidates that are processed in one batch by the parallel nested loop join.
+    /// Must be at least `1` and defaults to `1024`.
+    pub parallel_nested_loop_batch_size: usize,
+    /// A node search is replaced by a search for all nodes of the components of an operator, if its estimated
+    /// output is larger than the number of nodes in the components multiplied by this factor.
+    /// Defaults to `1.0`.
+    pub component_search_factor: f64,
+}
+
+impl Default for JoinThresholds {
+    fn default() -> Self {
+        JoinThresholds {
+            min_documents_for_merge_join: 2,
+            min_parallel_join_input: 0,
+            parallel_index_join_batch_size: 512,
+            parallel_nested_loop_batch_size: 1024,
+            component_search_factor: 1.0,
+        }
+    }
+}
+
+impl fmt::Display for JoinThresholds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "min_documents_for_merge_join={}, min_parallel_join_input={}, parallel_index_join_batch_size={}, \
+             parallel_nested_loop_batch_size={}, component_search_factor={}",
+            self.min_documents_for_merge_join,
+            self.min_parallel_join_input,
+            self.parallel_index_join_batch_size,
+            self.parallel_nested_loop_batch_size,
+            self.component_search_factor
+        )
+    }
+}
+
+/// Limits for the complexity of a query, which are checked before the query is executed.
+///
+/// Shared servers can use these limits to reject queries which would be very expensive to execute, e.g. a query
+/// with many unbounded dominance operators. The number of nodes and unbounded operators are limited for each
+/// alternative of the query. All limits are disabled by default.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Maximal number of nodes in a single alternative of the query.
+    pub max_nodes: Option<usize>,
+    /// Maximal number of alternatives of the query.
+    pub max_alternatives: Option<usize>,
+    /// Maximal number of operators without an upper bound for the distance (e.g. `>*` or `.*`) in a single
+    /// alternative of the query.
+    pub max_unbounded_operators: Option<usize>,
 }
 
 pub mod conjunction;
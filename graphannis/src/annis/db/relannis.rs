@@ -11,7 +11,7 @@ use crate::{
             VisualizerVisibility,
         },
     },
-    corpusstorage::QueryLanguage,
+    corpusstorage::{DuplicateNodeName, DuplicateNodeNamePolicy, QueryLanguage},
     AnnotationGraph,
 };
 use graphannis_core::{
@@ -22,12 +22,13 @@ use graphannis_core::{
 };
 use percent_encoding::utf8_percent_encode;
 use smartstring::alias::String;
+use std::cell::Cell;
 use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fs::File;
 use std::io::prelude::*;
-use std::ops::Bound::Included;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::{borrow::Cow, collections::HashMap};
 
 lazy_static! {
@@ -303,6 +304,7 @@ struct LoadNodeAndCorpusResult {
     toplevel_corpus_name: String,
     id_to_node_name: DiskMap<NodeID, String>,
     textpos_table: TextPosTable,
+    duplicate_node_names: Vec<DuplicateNodeName>,
 }
 
 struct NodeTabParseResult {
@@ -310,25 +312,109 @@ struct NodeTabParseResult {
     missing_seg_span: DiskMap<NodeID, String>,
     id_to_node_name: DiskMap<NodeID, String>,
     textpos_table: TextPosTable,
+    duplicate_node_names: Vec<DuplicateNodeName>,
 }
 
 struct LoadNodeResult {
     nodes_by_text: DiskMap<NodeByTextEntry, bool>,
     id_to_node_name: DiskMap<NodeID, String>,
     textpos_table: TextPosTable,
+    duplicate_node_names: Vec<DuplicateNodeName>,
+}
+
+/// Number of rows of a table after which the progress is reported.
+const PROGRESS_REPORT_ROWS: usize = 100_000;
+
+/// State of a single relANNIS import that is shared by all functions loading the tables.
+///
+/// Each import uses its own context, so several imports can run at the same time. The context reports the
+/// progress for each table and checks the cancellation flag for every row, so that long running imports can be
+/// observed and aborted.
+pub struct ImportContext<'a> {
+    progress_callback: &'a dyn Fn(&str),
+    cancel_flag: Option<&'a AtomicBool>,
+    table_rows: Cell<usize>,
+    total_rows: Cell<usize>,
+}
+
+impl<'a> ImportContext<'a> {
+    pub fn new(progress_callback: &'a dyn Fn(&str)) -> ImportContext<'a> {
+        ImportContext {
+            progress_callback,
+            cancel_flag: None,
+            table_rows: Cell::new(0),
+            total_rows: Cell::new(0),
+        }
+    }
+
+    /// Abort the import with [GraphAnnisError::ImportCancelled] as soon as the given flag is set.
+    pub fn with_cancel_flag(mut self, cancel_flag: &'a AtomicBool) -> ImportContext<'a> {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// The number of rows that have been loaded from all tables so far.
+    pub fn total_rows(&self) -> usize {
+        self.total_rows.get()
+    }
+
+    fn progress(&self, status: &str) {
+        (self.progress_callback)(status);
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        if let Some(cancel_flag) = self.cancel_flag {
+            if cancel_flag.load(Ordering::Relaxed) {
+                return Err(GraphAnnisError::ImportCancelled);
+            }
+        }
+        Ok(())
+    }
+
+    /// Report that loading the given table file starts.
+    fn start_table(&self, table_path: &Path) {
+        self.table_rows.set(0);
+        self.progress(&format!(
+            "loading {}",
+            table_path.to_str().unwrap_or_default()
+        ));
+    }
+
+    /// Count a loaded row of the current table, report the progress regularly and stop if the import has been
+    /// cancelled.
+    fn row_loaded(&self, table_path: &Path) -> Result<()> {
+        let table_rows = self.table_rows.get() + 1;
+        self.table_rows.set(table_rows);
+        self.total_rows.set(self.total_rows.get() + 1);
+        if table_rows % PROGRESS_REPORT_ROWS == 0 {
+            self.progress(&format!(
+                "loaded {} lines from {}",
+                table_rows,
+                table_path.to_str().unwrap_or_default()
+            ));
+        }
+        self.check_cancelled()
+    }
 }
 
 /// Load a c corpus in the legacy relANNIS format from the specified `path`.
 ///
-/// Returns a tuple consisting of the corpus name and the extracted annotation graph.
-pub fn load<F>(
+/// Returns a tuple consisting of the corpus name, the extracted annotation graph, the corpus configuration
+/// and the node names that are used by more than one node.
+/// How these duplicate node names are handled is defined by the `duplicate_policy`.
+///
+/// The `ctx` is used to report the progress and to check if the import has been cancelled.
+pub fn load(
     path: &Path,
     disk_based: bool,
-    progress_callback: F,
-) -> Result<(String, AnnotationGraph, CorpusConfiguration)>
-where
-    F: Fn(&str),
-{
+    duplicate_policy: DuplicateNodeNamePolicy,
+    ctx: &ImportContext,
+) -> Result<(
+    String,
+    AnnotationGraph,
+    CorpusConfiguration,
+    Vec<DuplicateNodeName>,
+)> {
     // convert to path
     let path = PathBuf::from(path);
     if path.is_dir() && path.exists() {
@@ -347,65 +433,71 @@ where
         let mut db = AnnotationGraph::with_default_graphstorages(disk_based)?;
         let mut config = CorpusConfiguration::default();
         let mut updates = GraphUpdate::new();
-        let load_node_and_corpus_result =
-            load_node_and_corpus_tables(&path, &mut updates, is_annis_33, &progress_callback)?;
+        let mut load_node_and_corpus_result =
+            load_node_and_corpus_tables(&path, &mut updates, is_annis_33, duplicate_policy, ctx)?;
         {
             let text_coverage_edges = load_edge_tables(
                 &path,
                 &mut updates,
                 is_annis_33,
                 &load_node_and_corpus_result.id_to_node_name,
-                &progress_callback,
+                ctx,
             )?;
 
             calculate_automatic_coverage_edges(
                 &mut updates,
                 &load_node_and_corpus_result,
                 &text_coverage_edges,
-                &progress_callback,
+                ctx,
             )?;
         }
 
-        load_resolver_vis_map(&path, &mut config, is_annis_33, &progress_callback)?;
-        load_example_queries(&path, &mut config, is_annis_33, &progress_callback)?;
-        load_corpus_properties(&path, &mut config, &progress_callback)?;
+        load_resolver_vis_map(&path, &mut config, is_annis_33, ctx)?;
+        load_example_queries(&path, &mut config, is_annis_33, ctx)?;
+        load_corpus_properties(&path, &mut config, ctx)?;
 
         // TODO: implement handling the "virtual_tokenization_from_namespace" and "virtual_tokenization_mapping" corpus properties
 
-        db.apply_update(&mut updates, &progress_callback)?;
+        db.apply_update(&mut updates, |status| ctx.progress(status))?;
 
-        progress_callback("calculating node statistics");
+        ctx.progress("calculating node statistics");
         db.get_node_annos_mut().calculate_statistics();
 
         for c in db.get_all_components(None, None) {
-            progress_callback(&format!("calculating statistics for component {}", c));
+            ctx.check_cancelled()?;
+            ctx.progress(&format!("calculating statistics for component {}", c));
             db.calculate_component_statistics(&c)?;
             db.optimize_gs_impl(&c)?;
         }
 
-        progress_callback(&format!(
+        ctx.progress(&format!(
             "finished loading relANNIS from {}",
             path.to_string_lossy()
         ));
 
-        return Ok((load_node_and_corpus_result.toplevel_corpus_name, db, config));
+        let duplicate_node_names =
+            std::mem::take(&mut load_node_and_corpus_result.duplicate_node_names);
+        return Ok((
+            load_node_and_corpus_result.toplevel_corpus_name,
+            db,
+            config,
+            duplicate_node_names,
+        ));
     }
 
     Err(RelAnnisError::DirectoryNotFound(path.to_string_lossy().to_string()).into())
 }
 
-fn load_node_and_corpus_tables<F>(
+fn load_node_and_corpus_tables(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<LoadNodeAndCorpusResult>
-where
-    F: Fn(&str),
-{
-    let corpus_table = parse_corpus_tab(&path, is_annis_33, &progress_callback)?;
-    let mut texts = parse_text_tab(&path, is_annis_33, &progress_callback)?;
-    let corpus_id_to_annos = load_corpus_annotation(&path, is_annis_33, &progress_callback)?;
+    duplicate_policy: DuplicateNodeNamePolicy,
+    ctx: &ImportContext,
+) -> Result<LoadNodeAndCorpusResult> {
+    let corpus_table = parse_corpus_tab(&path, is_annis_33, ctx)?;
+    let mut texts = parse_text_tab(&path, is_annis_33, ctx)?;
+    let corpus_id_to_annos = load_corpus_annotation(&path, is_annis_33, ctx)?;
 
     let load_nodes_result = load_nodes(
         path,
@@ -413,7 +505,8 @@ where
         &mut texts,
         &corpus_table,
         is_annis_33,
-        progress_callback,
+        duplicate_policy,
+        ctx,
     )?;
 
     add_subcorpora(
@@ -431,28 +524,26 @@ where
         &load_nodes_result.textpos_table,
         &mut texts,
         &load_nodes_result.id_to_node_name,
-        progress_callback,
+        ctx,
     )?;
 
     Ok(LoadNodeAndCorpusResult {
         toplevel_corpus_name: corpus_table.toplevel_corpus_name,
         id_to_node_name: load_nodes_result.id_to_node_name,
         textpos_table: load_nodes_result.textpos_table,
+        duplicate_node_names: load_nodes_result.duplicate_node_names,
     })
 }
 
-fn load_edge_tables<F>(
+fn load_edge_tables(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     is_annis_33: bool,
     id_to_node_name: &DiskMap<NodeID, String>,
-    progress_callback: &F,
-) -> Result<LoadRankResult>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<LoadRankResult> {
     let load_rank_result = {
-        let component_by_id = load_component_tab(path, is_annis_33, progress_callback)?;
+        let component_by_id = load_component_tab(path, is_annis_33, ctx)?;
 
         load_rank_tab(
             path,
@@ -460,7 +551,7 @@ where
             &component_by_id,
             id_to_node_name,
             is_annis_33,
-            progress_callback,
+            ctx,
         )?
     };
 
@@ -470,21 +561,18 @@ where
         &load_rank_result,
         id_to_node_name,
         is_annis_33,
-        progress_callback,
+        ctx,
     )?;
 
     Ok(load_rank_result)
 }
 
-fn load_resolver_vis_map<F>(
+fn load_resolver_vis_map(
     path: &Path,
     config: &mut CorpusConfiguration,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     let mut resolver_tab_path = PathBuf::from(path);
     resolver_tab_path.push(if is_annis_33 {
         "resolver_vis_map.annis"
@@ -497,16 +585,14 @@ where
         return Ok(());
     }
 
-    progress_callback(&format!(
-        "loading {}",
-        resolver_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&resolver_tab_path);
 
     let mut resolver_tab_csv = postgresql_import_reader(resolver_tab_path.as_path())?;
 
     let mut rules_by_order: Vec<(i64, bool, VisualizerRule)> = DEFAULT_VISUALIZER_RULES.clone();
 
     for result in resolver_tab_csv.records() {
+        ctx.row_loaded(&resolver_tab_path)?;
         let line = result?;
 
         let layer = get_field(&line, 2, "namespace", &resolver_tab_path)?;
@@ -588,15 +674,12 @@ where
     Ok(())
 }
 
-fn load_example_queries<F>(
+fn load_example_queries(
     path: &Path,
     config: &mut CorpusConfiguration,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     let mut example_queries_path = PathBuf::from(path);
     example_queries_path.push(if is_annis_33 {
         "example_queries.annis"
@@ -609,14 +692,12 @@ where
         return Ok(());
     }
 
-    progress_callback(&format!(
-        "loading {}",
-        example_queries_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&example_queries_path);
 
     let mut example_queries_csv = postgresql_import_reader(example_queries_path.as_path())?;
 
     for result in example_queries_csv.records() {
+        ctx.row_loaded(&example_queries_path)?;
         let line = result?;
 
         if let (Some(query), Some(description)) = (
@@ -633,14 +714,11 @@ where
     Ok(())
 }
 
-fn load_corpus_properties<F>(
+fn load_corpus_properties(
     path: &Path,
     config: &mut CorpusConfiguration,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     let corpus_config_path = path.join("ExtData").join("corpus.properties");
 
     if !corpus_config_path.is_file() {
@@ -648,7 +726,7 @@ where
         return Ok(());
     }
 
-    progress_callback(&format!(
+    ctx.progress(&format!(
         "loading {}",
         corpus_config_path.to_str().unwrap_or_default()
     ));
@@ -819,14 +897,11 @@ fn get_field_not_null(
     Ok(result)
 }
 
-fn parse_corpus_tab<F>(
+fn parse_corpus_tab(
     path: &PathBuf,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<ParsedCorpusTable>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<ParsedCorpusTable> {
     let mut corpus_tab_path = PathBuf::from(path);
     corpus_tab_path.push(if is_annis_33 {
         "corpus.annis"
@@ -834,10 +909,7 @@ where
         "corpus.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        corpus_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&corpus_tab_path);
 
     let mut corpus_by_preorder = BTreeMap::new();
     let mut corpus_by_id = BTreeMap::new();
@@ -847,6 +919,7 @@ where
     let mut document_names: HashMap<String, usize> = HashMap::new();
 
     for result in corpus_tab_csv.records() {
+        ctx.row_loaded(&corpus_tab_path)?;
         let line = result?;
 
         let id = get_field_not_null(&line, 0, "id", &corpus_tab_path)?.parse::<u32>()?;
@@ -902,14 +975,11 @@ where
     })
 }
 
-fn parse_text_tab<F>(
+fn parse_text_tab(
     path: &PathBuf,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<DiskMap<TextKey, Text>>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<DiskMap<TextKey, Text>> {
     let mut text_tab_path = PathBuf::from(path);
     text_tab_path.push(if is_annis_33 {
         "text.annis"
@@ -917,16 +987,14 @@ where
         "text.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        text_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&text_tab_path);
 
     let mut texts: DiskMap<TextKey, Text> = DiskMap::default();
 
     let mut text_tab_csv = postgresql_import_reader(text_tab_path.as_path())?;
 
     for result in text_tab_csv.records() {
+        ctx.row_loaded(&text_tab_path)?;
         let line = result?;
 
         let id = get_field_not_null(&line, if is_annis_33 { 1 } else { 0 }, "id", &text_tab_path)?
@@ -963,25 +1031,23 @@ where
     Ok(texts)
 }
 
-fn calculate_automatic_token_order<F>(
+fn calculate_automatic_token_order(
     updates: &mut GraphUpdate,
     token_by_index: &DiskMap<TextProperty, NodeID>,
     id_to_node_name: &DiskMap<NodeID, String>,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     // iterate over all token by their order, find the nodes with the same
     // text coverage (either left or right) and add explicit Ordering edge
 
     let msg = "calculating the automatically generated Ordering edges";
-    progress_callback(msg);
+    ctx.progress(msg);
 
     let mut last_textprop: Option<TextProperty> = None;
     let mut last_token: Option<NodeID> = None;
 
     for (current_textprop, current_token) in token_by_index.try_iter()? {
+        ctx.check_cancelled()?;
         // if the last token/text value is valid and we are still in the same text
         if let (Some(last_token), Some(last_textprop)) = (last_token, last_textprop) {
             if last_textprop.corpus_id == current_textprop.corpus_id
@@ -1083,19 +1149,9 @@ fn add_automatic_cov_edge_for_node(
                 .text_coverage_edges
                 .try_contains_key(&edge)?
             {
-                let nodes_with_same_source = (
-                    Included(Edge {
-                        source: n,
-                        target: NodeID::min_value(),
-                    }),
-                    Included(Edge {
-                        source: n,
-                        target: NodeID::max_value(),
-                    }),
-                );
                 let has_outgoing_text_coverage_edge = load_rank_result
                     .text_coverage_edges
-                    .range(nodes_with_same_source)
+                    .prefix(&n.create_key())
                     .next()
                     .is_some();
                 let (component_layer, component_name) = if has_outgoing_text_coverage_edge {
@@ -1134,23 +1190,21 @@ fn add_automatic_cov_edge_for_node(
     Ok(())
 }
 
-fn calculate_automatic_coverage_edges<F>(
+fn calculate_automatic_coverage_edges(
     updates: &mut GraphUpdate,
     load_node_and_corpus_result: &LoadNodeAndCorpusResult,
     load_rank_result: &LoadRankResult,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     // add explicit coverage edges for each node in the special annis namespace coverage component
-    progress_callback("calculating the automatically generated Coverage edges");
+    ctx.progress("calculating the automatically generated Coverage edges");
 
     for (n, textprop) in load_node_and_corpus_result
         .textpos_table
         .node_to_left
         .try_iter()?
     {
+        ctx.check_cancelled()?;
         if textprop.segmentation.is_empty()
             && !load_node_and_corpus_result
                 .textpos_table
@@ -1195,21 +1249,19 @@ where
     Ok(())
 }
 
-fn add_white_space_token_labels<F>(
+fn add_white_space_token_labels(
     updates: &mut GraphUpdate,
     textpos_table: &TextPosTable,
     texts: &mut DiskMap<TextKey, Text>,
     id_to_node_name: &DiskMap<NodeID, String>,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
-    progress_callback("adding non-tokenized primary text segments as white-space label to tokens");
+    ctx: &ImportContext,
+) -> Result<()> {
+    ctx.progress("adding non-tokenized primary text segments as white-space label to tokens");
     let mut added_whitespace_label_count = 0;
 
     // Iterate over all texts of the graph separately
     for (text_key, text) in texts.try_iter()? {
+        ctx.check_cancelled()?;
         let mut text_char_it = text.val.chars();
         let mut current_text_offset = 0;
 
@@ -1329,7 +1381,7 @@ where
             previous_token_id = Some(current_token_id);
         }
     }
-    progress_callback(&format!(
+    ctx.progress(&format!(
         "added {} non-tokenized primary text segments as white-space labels to the existing tokens",
         added_whitespace_label_count
     ));
@@ -1337,20 +1389,21 @@ where
     Ok(())
 }
 
-fn load_node_tab<F>(
+fn load_node_tab(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     texts: &mut DiskMap<TextKey, Text>,
     corpus_table: &ParsedCorpusTable,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<NodeTabParseResult>
-where
-    F: Fn(&str),
-{
+    duplicate_policy: DuplicateNodeNamePolicy,
+    ctx: &ImportContext,
+) -> Result<NodeTabParseResult> {
     let mut nodes_by_text: DiskMap<NodeByTextEntry, bool> = DiskMap::default();
     let mut missing_seg_span: DiskMap<NodeID, String> = DiskMap::default();
     let mut id_to_node_name: DiskMap<NodeID, String> = DiskMap::default();
+    // used to detect node names that are used by more than one node
+    let mut node_name_to_id: DiskMap<std::string::String, NodeID> = DiskMap::default();
+    let mut duplicate_node_names = Vec::new();
 
     let mut node_tab_path = PathBuf::from(path);
     node_tab_path.push(if is_annis_33 {
@@ -1359,10 +1412,7 @@ where
         "node.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        node_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&node_tab_path);
 
     // maps a character position to it's token
     let mut textpos_table = TextPosTable {
@@ -1380,7 +1430,8 @@ where
     {
         let mut node_tab_csv = postgresql_import_reader(node_tab_path.as_path())?;
 
-        for (line_nr, result) in node_tab_csv.records().enumerate() {
+        for result in node_tab_csv.records() {
+            ctx.row_loaded(&node_tab_path)?;
             let line = result?;
 
             let node_nr = get_field_not_null(&line, 0, "id", &node_tab_path)?.parse::<NodeID>()?;
@@ -1417,12 +1468,46 @@ where
                 }
             }
 
-            let node_path = format!(
+            let mut node_path = format!(
                 "{}#{}",
                 get_corpus_path(corpus_id, corpus_table)?,
                 // fragments don't need escaping
                 node_name
             );
+            if let Some(first) = node_name_to_id.try_get(&node_path)? {
+                let renamed_to = match duplicate_policy {
+                    DuplicateNodeNamePolicy::Error => {
+                        return Err(RelAnnisError::DuplicateNodeName {
+                            node_name: node_path,
+                            first,
+                            second: node_nr,
+                        }
+                        .into());
+                    }
+                    DuplicateNodeNamePolicy::Rename => {
+                        let mut suffix = 2;
+                        let mut new_name = format!("{}_{}", node_path, suffix);
+                        while node_name_to_id.try_contains_key(&new_name)? {
+                            suffix += 1;
+                            new_name = format!("{}_{}", node_path, suffix);
+                        }
+                        Some(new_name)
+                    }
+                    DuplicateNodeNamePolicy::Merge => None,
+                };
+                warn!(
+                    "node name {} is used by the nodes with ID {} and {}",
+                    node_path, first, node_nr
+                );
+                duplicate_node_names.push(DuplicateNodeName {
+                    node_name: node_path.clone(),
+                    renamed_to: renamed_to.clone(),
+                });
+                if let Some(renamed_to) = renamed_to {
+                    node_path = renamed_to;
+                }
+            }
+            node_name_to_id.insert(node_path.clone(), node_nr)?;
             updates.add_event(UpdateEvent::AddNode {
                 node_name: node_path.clone(),
                 node_type: "node".to_owned(),
@@ -1562,14 +1647,6 @@ where
                     textpos_table.token_by_index.insert(index, node_nr)?;
                 } // end if node has segmentation info
             } // endif if check segmentations
-
-            if (line_nr + 1) % 100_000 == 0 {
-                progress_callback(&format!(
-                    "loaded {} lines from {}",
-                    line_nr + 1,
-                    node_tab_path.to_str().unwrap_or_default()
-                ));
-            }
         }
     } // end "scan all lines" visibility block
 
@@ -1594,7 +1671,7 @@ where
             updates,
             &textpos_table.token_by_index,
             &id_to_node_name,
-            progress_callback,
+            ctx,
         )?;
     } // end if token_by_index not empty
 
@@ -1603,20 +1680,18 @@ where
         missing_seg_span,
         id_to_node_name,
         textpos_table,
+        duplicate_node_names,
     })
 }
 
-fn load_node_anno_tab<F>(
+fn load_node_anno_tab(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     missing_seg_span: &DiskMap<NodeID, String>,
     id_to_node_name: &DiskMap<NodeID, String>,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     let mut node_anno_tab_path = PathBuf::from(path);
     node_anno_tab_path.push(if is_annis_33 {
         "node_annotation.annis"
@@ -1624,14 +1699,12 @@ where
         "node_annotation.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        node_anno_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&node_anno_tab_path);
 
     let mut node_anno_tab_csv = postgresql_import_reader(node_anno_tab_path.as_path())?;
 
-    for (line_nr, result) in node_anno_tab_csv.records().enumerate() {
+    for result in node_anno_tab_csv.records() {
+        ctx.row_loaded(&node_anno_tab_path)?;
         let line = result?;
 
         let col_id = get_field_not_null(&line, 0, "id", &node_anno_tab_path)?;
@@ -1667,27 +1740,16 @@ where
                 anno_value: anno_val,
             })?;
         }
-
-        if (line_nr + 1) % 100_000 == 0 {
-            progress_callback(&format!(
-                "loaded {} lines from {}",
-                line_nr + 1,
-                node_anno_tab_path.to_str().unwrap_or_default()
-            ));
-        }
     }
 
     Ok(())
 }
 
-fn load_component_tab<F>(
+fn load_component_tab(
     path: &PathBuf,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<BTreeMap<u32, Component<AnnotationComponentType>>>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<BTreeMap<u32, Component<AnnotationComponentType>>> {
     let mut component_tab_path = PathBuf::from(path);
     component_tab_path.push(if is_annis_33 {
         "component.annis"
@@ -1695,15 +1757,13 @@ where
         "component.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        component_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&component_tab_path);
 
     let mut component_by_id: BTreeMap<u32, Component<AnnotationComponentType>> = BTreeMap::new();
 
     let mut component_tab_csv = postgresql_import_reader(component_tab_path.as_path())?;
     for result in component_tab_csv.records() {
+        ctx.row_loaded(&component_tab_path)?;
         let line = result?;
 
         let cid: u32 = get_field_not_null(&line, 0, "id", &component_tab_path)?.parse()?;
@@ -1717,24 +1777,23 @@ where
     Ok(component_by_id)
 }
 
-fn load_nodes<F>(
+fn load_nodes(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     texts: &mut DiskMap<TextKey, Text>,
     corpus_table: &ParsedCorpusTable,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<LoadNodeResult>
-where
-    F: Fn(&str),
-{
+    duplicate_policy: DuplicateNodeNamePolicy,
+    ctx: &ImportContext,
+) -> Result<LoadNodeResult> {
     let node_tab_parse_result = load_node_tab(
         path,
         updates,
         texts,
         corpus_table,
         is_annis_33,
-        progress_callback,
+        duplicate_policy,
+        ctx,
     )?;
 
     load_node_anno_tab(
@@ -1743,27 +1802,25 @@ where
         &node_tab_parse_result.missing_seg_span,
         &node_tab_parse_result.id_to_node_name,
         is_annis_33,
-        progress_callback,
+        ctx,
     )?;
 
     Ok(LoadNodeResult {
         nodes_by_text: node_tab_parse_result.nodes_by_text,
         id_to_node_name: node_tab_parse_result.id_to_node_name,
         textpos_table: node_tab_parse_result.textpos_table,
+        duplicate_node_names: node_tab_parse_result.duplicate_node_names,
     })
 }
 
-fn load_rank_tab<F>(
+fn load_rank_tab(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     component_by_id: &BTreeMap<u32, Component<AnnotationComponentType>>,
     id_to_node_name: &DiskMap<NodeID, String>,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<LoadRankResult>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<LoadRankResult> {
     let mut rank_tab_path = PathBuf::from(path);
     rank_tab_path.push(if is_annis_33 {
         "rank.annis"
@@ -1771,10 +1828,7 @@ where
         "rank.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        rank_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&rank_tab_path);
 
     let mut load_rank_result = LoadRankResult {
         components_by_pre: DiskMap::default(),
@@ -1792,6 +1846,7 @@ where
     // first run: collect all pre-order values for a node
     let mut pre_to_node_id: DiskMap<u32, NodeID> = DiskMap::default();
     for result in rank_tab_csv.records() {
+        ctx.row_loaded(&rank_tab_path)?;
         let line = result?;
         let pre: u32 = get_field_not_null(&line, 0, "pre", &rank_tab_path)?.parse()?;
         let node_id: NodeID =
@@ -1800,9 +1855,11 @@ where
     }
 
     // second run: get the actual edges
+    ctx.start_table(&rank_tab_path);
     let mut rank_tab_csv = postgresql_import_reader(rank_tab_path.as_path())?;
 
     for result in rank_tab_csv.records() {
+        ctx.row_loaded(&rank_tab_path)?;
         let line = result?;
 
         let component_ref: u32 =
@@ -1867,17 +1924,14 @@ where
     Ok(load_rank_result)
 }
 
-fn load_edge_annotation<F>(
+fn load_edge_annotation(
     path: &PathBuf,
     updates: &mut GraphUpdate,
     rank_result: &LoadRankResult,
     id_to_node_name: &DiskMap<NodeID, String>,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<()>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<()> {
     let mut edge_anno_tab_path = PathBuf::from(path);
     edge_anno_tab_path.push(if is_annis_33 {
         "edge_annotation.annis"
@@ -1885,14 +1939,12 @@ where
         "edge_annotation.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        edge_anno_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&edge_anno_tab_path);
 
     let mut edge_anno_tab_csv = postgresql_import_reader(edge_anno_tab_path.as_path())?;
 
     for result in edge_anno_tab_csv.records() {
+        ctx.row_loaded(&edge_anno_tab_path)?;
         let line = result?;
 
         let pre = get_field_not_null(&line, 0, "pre", &edge_anno_tab_path)?.parse::<u32>()?;
@@ -1927,14 +1979,11 @@ where
     Ok(())
 }
 
-fn load_corpus_annotation<F>(
+fn load_corpus_annotation(
     path: &PathBuf,
     is_annis_33: bool,
-    progress_callback: &F,
-) -> Result<BTreeMap<(u32, AnnoKey), std::string::String>>
-where
-    F: Fn(&str),
-{
+    ctx: &ImportContext,
+) -> Result<BTreeMap<(u32, AnnoKey), std::string::String>> {
     let mut corpus_id_to_anno = BTreeMap::new();
 
     let mut corpus_anno_tab_path = PathBuf::from(path);
@@ -1944,14 +1993,12 @@ where
         "corpus_annotation.tab"
     });
 
-    progress_callback(&format!(
-        "loading {}",
-        corpus_anno_tab_path.to_str().unwrap_or_default()
-    ));
+    ctx.start_table(&corpus_anno_tab_path);
 
     let mut corpus_anno_tab_csv = postgresql_import_reader(corpus_anno_tab_path.as_path())?;
 
     for result in corpus_anno_tab_csv.records() {
+        ctx.row_loaded(&corpus_anno_tab_path)?;
         let line = result?;
 
         let id = get_field_not_null(&line, 0, "id", &corpus_anno_tab_path)?.parse::<u32>()?;
@@ -2173,3 +2220,121 @@ fn component_type_from_short_name(short_type: &str) -> Result<AnnotationComponen
         _ => Err(RelAnnisError::InvalidComponentShortName(short_type.to_string()).into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a minimal relANNIS 3.3 corpus where both token have the same node name.
+    fn create_corpus_with_duplicate_node_names(path: &Path) {
+        let files = [
+            ("annis.version", "3.3"),
+            (
+                "corpus.annis",
+                "0\tcorpus\tCORPUS\tNULL\t0\t3\tTRUE\tNULL\n\
+                 1\tdoc\tDOCUMENT\tNULL\t1\t2\tFALSE\tNULL\n",
+            ),
+            ("text.annis", "1\t0\ttext\ta b\n"),
+            ("corpus_annotation.annis", ""),
+            (
+                "node.annis",
+                "1\t0\t1\tNULL\ttok\t0\t1\t0\t0\t0\tNULL\tNULL\ta\tFALSE\n\
+                 2\t0\t1\tNULL\ttok\t2\t3\t1\t1\t1\tNULL\tNULL\tb\tFALSE\n",
+            ),
+            ("node_annotation.annis", ""),
+            ("component.annis", ""),
+            ("rank.annis", ""),
+            ("edge_annotation.annis", ""),
+        ];
+        for (file_name, content) in &files {
+            std::fs::write(path.join(file_name), content).unwrap();
+        }
+    }
+
+    #[test]
+    fn duplicate_node_name_policy() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_corpus_with_duplicate_node_names(tmp.path());
+
+        let (_, g, _, duplicates) = load(
+            tmp.path(),
+            false,
+            DuplicateNodeNamePolicy::Merge,
+            &ImportContext::new(&|_| {}),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![DuplicateNodeName {
+                node_name: "corpus/doc#tok".into(),
+                renamed_to: None,
+            }],
+            duplicates
+        );
+        assert!(g.get_node_id_from_name("corpus/doc#tok").is_some());
+        assert!(g.get_node_id_from_name("corpus/doc#tok_2").is_none());
+
+        let (_, g, _, duplicates) = load(
+            tmp.path(),
+            false,
+            DuplicateNodeNamePolicy::Rename,
+            &ImportContext::new(&|_| {}),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![DuplicateNodeName {
+                node_name: "corpus/doc#tok".into(),
+                renamed_to: Some("corpus/doc#tok_2".into()),
+            }],
+            duplicates
+        );
+        let tok2 = g.get_node_id_from_name("corpus/doc#tok_2").unwrap();
+        assert_eq!(
+            "b",
+            g.get_node_annos()
+                .get_value_for_item(
+                    &tok2,
+                    &AnnoKey {
+                        ns: ANNIS_NS.into(),
+                        name: TOK.into()
+                    }
+                )
+                .unwrap()
+        );
+
+        let result = load(
+            tmp.path(),
+            false,
+            DuplicateNodeNamePolicy::Error,
+            &ImportContext::new(&|_| {}),
+        );
+        assert!(matches!(
+            result,
+            Err(GraphAnnisError::RelAnnisImportError(
+                RelAnnisError::DuplicateNodeName {
+                    first: 1,
+                    second: 2,
+                    ..
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn cancel_import() {
+        let tmp = tempfile::tempdir().unwrap();
+        create_corpus_with_duplicate_node_names(tmp.path());
+
+        let cancel_flag = AtomicBool::new(false);
+        let ctx = ImportContext::new(&|_| {}).with_cancel_flag(&cancel_flag);
+        load(tmp.path(), false, DuplicateNodeNamePolicy::Merge, &ctx).unwrap();
+        // corpus, text and node tables
+        assert_eq!(5, ctx.total_rows());
+
+        cancel_flag.store(true, Ordering::SeqCst);
+        let ctx = ImportContext::new(&|_| {}).with_cancel_flag(&cancel_flag);
+        let result = load(tmp.path(), false, DuplicateNodeNamePolicy::Merge, &ctx);
+        assert!(matches!(result, Err(GraphAnnisError::ImportCancelled)));
+        // the import is aborted at the first row
+        assert_eq!(1, ctx.total_rows());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::annis::db::aql;
+use crate::annis::db::corpusstorage::{create_match_id, QueryLanguage};
+use crate::annis::db::plan::ExecutionPlan;
+use crate::annis::db::query;
+use crate::annis::db::query::disjunction::Disjunction;
+use crate::annis::db::sort_matches::{self, CollationType};
+use crate::annis::db::token_helper::TokenHelper;
+use crate::annis::errors::Result;
+use crate::{graph::MatchGroup, model::AnnotationComponentType, AnnotationGraph};
+use graphannis_core::{
+    graph::{serialization::graphml, update::GraphUpdate, ANNIS_NS},
+    types::Component,
+    util::UnicodeNormalization,
+};
+use std::io::Read;
+
+/// Executes queries on a single annotation graph that is held in main memory.
+///
+/// In contrast to the [`CorpusStorage`](crate::CorpusStorage), no data directory, lock file or corpus cache
+/// is involved. This is useful for tests or processing pipelines that create a transient graph and
+/// only need to search it.
+pub struct SingleGraphSearch {
+    graph: AnnotationGraph,
+    query_config: query::Config,
+    unicode_normalization: UnicodeNormalization,
+}
+
+impl SingleGraphSearch {
+    /// Create a new search facade for an existing graph.
+    ///
+    /// All components of the graph are loaded and its annotation values are normalized to the
+    /// default Unicode normal form, the same way a corpus is prepared on import.
+    pub fn from_graph(mut graph: AnnotationGraph) -> Result<SingleGraphSearch> {
+        let unicode_normalization = UnicodeNormalization::default();
+        graph.ensure_loaded_all()?;
+        graph.normalize_unicode(unicode_normalization)?;
+        Ok(SingleGraphSearch {
+            graph,
+            query_config: query::Config::default(),
+            unicode_normalization,
+        })
+    }
+
+    /// Create a new graph by applying the given update to an empty graph.
+    pub fn from_update(update: &mut GraphUpdate) -> Result<SingleGraphSearch> {
+        let mut graph = AnnotationGraph::with_default_graphstorages(false)?;
+        graph.apply_update(update, |_| {})?;
+        SingleGraphSearch::from_graph(graph)
+    }
+
+    /// Create a new graph by importing a GraphML file from the given `input`.
+    pub fn from_graphml<R: Read>(input: R) -> Result<SingleGraphSearch> {
+        let (graph, _config) = graphml::import(input, false, |_| {})?;
+        SingleGraphSearch::from_graph(graph)
+    }
+
+    /// If `true`, parallel joins are used, using all available cores. Defaults to `false`.
+    pub fn set_parallel_joins(&mut self, use_parallel_joins: bool) {
+        self.query_config.use_parallel_joins = use_parallel_joins;
+    }
+
+    /// Return a reference to the searched graph.
+    pub fn graph(&self) -> &AnnotationGraph {
+        &self.graph
+    }
+
+    /// Consume the search facade and return the graph.
+    pub fn into_graph(self) -> AnnotationGraph {
+        self.graph
+    }
+
+    fn parse(&self, query: &str, query_language: QueryLanguage) -> Result<Disjunction> {
+        let quirks_mode = matches!(query_language, QueryLanguage::AQLQuirksV3);
+        aql::parse(query, quirks_mode, self.unicode_normalization)
+    }
+
+    /// Count the number of results for a `query`.
+    pub fn count(&self, query: &str, query_language: QueryLanguage) -> Result<u64> {
+        let q = self.parse(query, query_language)?;
+        let plan = ExecutionPlan::from_disjunction(&q, &self.graph, &self.query_config)?;
+        Ok(plan.count() as u64)
+    }
+
+    /// Find all results for a `query` and return the match ID for each result.
+    ///
+    /// The results are sorted by their position in the text, in the same way as the
+    /// [`CorpusStorage::find`](crate::CorpusStorage::find) function with the normal result order.
+    /// - `offset` - Skip the `n` first results, where `n` is the offset.
+    /// - `limit` - Return at most `n` matches, where `n` is the limit.  Use `None` to allow unlimited result sizes.
+    pub fn find(
+        &self,
+        query: &str,
+        query_language: QueryLanguage,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Vec<String>> {
+        let quirks_mode = matches!(query_language, QueryLanguage::AQLQuirksV3);
+        let q = self.parse(query, query_language)?;
+        let plan = ExecutionPlan::from_disjunction(&q, &self.graph, &self.query_config)?;
+
+        let mut matches: Vec<MatchGroup> = plan.collect();
+        let token_helper = TokenHelper::new(&self.graph);
+        let gs_order = self.graph.get_graphstorage_as_ref(&Component::new(
+            AnnotationComponentType::Ordering,
+            ANNIS_NS.into(),
+            "".into(),
+        ));
+        matches.sort_by(|m1, m2| {
+            sort_matches::compare_matchgroup_by_text_pos(
+                m1,
+                m2,
+                self.graph.get_node_annos(),
+                token_helper.as_ref(),
+                gs_order,
+                CollationType::Default,
+                quirks_mode,
+            )
+        });
+
+        let matches = matches.into_iter().skip(offset);
+        let matches: Box<dyn Iterator<Item = MatchGroup>> = if let Some(limit) = limit {
+            Box::new(matches.take(limit))
+        } else {
+            Box::new(matches)
+        };
+
+        Ok(matches
+            .map(|m| create_match_id(&self.graph, &q, &m, quirks_mode))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annis::db::example_generator;
+
+    fn create_search() -> SingleGraphSearch {
+        let mut u = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut u);
+        example_generator::create_tokens(&mut u, Some("root/doc1"));
+        SingleGraphSearch::from_update(&mut u).unwrap()
+    }
+
+    #[test]
+    fn count_and_find_in_single_graph() {
+        let search = create_search();
+
+        assert_eq!(3, search.count("tok=/.*s/", QueryLanguage::AQL).unwrap());
+
+        let result = search
+            .find("tok=/.*s/ . tok", QueryLanguage::AQL, 0, None)
+            .unwrap();
+        assert_eq!(
+            vec![
+                "root/doc1#tok0 root/doc1#tok1",
+                "root/doc1#tok1 root/doc1#tok2",
+                "root/doc1#tok7 root/doc1#tok8",
+            ],
+            result
+        );
+
+        let result = search
+            .find("tok=/.*s/ . tok", QueryLanguage::AQL, 1, Some(1))
+            .unwrap();
+        assert_eq!(vec!["root/doc1#tok1 root/doc1#tok2"], result);
+    }
+
+    #[test]
+    fn graphml_roundtrip_search() {
+        let search = create_search();
+        let mut output = Vec::new();
+        graphml::export(search.graph(), None, &mut output, |_| {}).unwrap();
+
+        let search = SingleGraphSearch::from_graphml(output.as_slice()).unwrap();
+        assert_eq!(11, search.count("tok", QueryLanguage::AQL).unwrap());
+    }
+}
@@ -55,7 +55,8 @@ fn split_path_and_nodename(full_node_name: &str) -> (&str, &str) {
     }
 }
 
-fn compare_document_path(
+/// Compare two document paths by their elements, shorter paths are sorted before longer ones with the same prefix.
+pub fn compare_document_path(
     p1: &str,
     p2: &str,
     collation: CollationType,
@@ -1,6 +1,8 @@
 use crate::{
     annis::db::{
-        aql::model::{AnnotationComponentType, TOKEN_KEY},
+        aql::model::{
+            AnnotationComponentType, TOKEN_KEY, TOK_WHITESPACE_AFTER_KEY, TOK_WHITESPACE_BEFORE_KEY,
+        },
         AnnotationStorage,
     },
     graph::GraphStorage,
@@ -14,6 +16,29 @@ use graphannis_core::{
 use std::collections::HashSet;
 use std::sync::Arc;
 
+/// Defines how the text between two tokens is reconstructed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[repr(C)]
+pub enum WhitespaceHandling {
+    /// Use the `annis::tok-whitespace-after` and `annis::tok-whitespace-before` annotations of the tokens
+    /// to reproduce the original spacing of the text. Tokens without any of these annotations are separated
+    /// by a single space.
+    Original,
+    /// Always separate the tokens by a single space.
+    SingleSpace,
+}
+
+impl Default for WhitespaceHandling {
+    fn default() -> Self {
+        WhitespaceHandling::Original
+    }
+}
+
+/// Resolves the tokens of nodes in an annotation graph.
+///
+/// This follows the conventions of the annotation graph model: tokens are nodes with an `annis::tok` annotation
+/// and without outgoing edges in any coverage component, and each node is connected to its left-most and
+/// right-most covered token with edges in the `LeftToken` and `RightToken` components.
 #[derive(Clone)]
 pub struct TokenHelper<'a> {
     node_annos: &'a dyn AnnotationStorage<NodeID>,
@@ -39,6 +64,7 @@ lazy_static! {
     };
 }
 
+/// Returns all components that need to be loaded before a [`TokenHelper`](struct.TokenHelper.html) can be created.
 pub fn necessary_components(db: &AnnotationGraph) -> HashSet<Component<AnnotationComponentType>> {
     let mut result = HashSet::default();
     result.insert(COMPONENT_LEFT.clone());
@@ -53,6 +79,10 @@ pub fn necessary_components(db: &AnnotationGraph) -> HashSet<Component<Annotatio
 }
 
 impl<'a> TokenHelper<'a> {
+    /// Create a new helper for the given graph.
+    ///
+    /// Returns `None` if the `LeftToken` or `RightToken` component does not exist or is not loaded.
+    /// Use [`necessary_components`](fn.necessary_components.html) to get the components that need to be loaded.
     pub fn new(graph: &'a AnnotationGraph) -> Option<TokenHelper<'a>> {
         let cov_edges: Vec<Arc<dyn GraphStorage>> = graph
             .get_all_components(Some(AnnotationComponentType::Coverage), None)
@@ -74,18 +104,20 @@ impl<'a> TokenHelper<'a> {
             cov_edges,
         })
     }
-    pub fn get_gs_coverage(&self) -> &Vec<Arc<dyn GraphStorage>> {
+
+    pub(crate) fn get_gs_coverage(&self) -> &Vec<Arc<dyn GraphStorage>> {
         &self.cov_edges
     }
 
-    pub fn get_gs_left_token(&self) -> &dyn GraphStorage {
+    pub(crate) fn get_gs_left_token(&self) -> &dyn GraphStorage {
         self.left_edges.as_ref()
     }
 
-    pub fn get_gs_right_token_(&self) -> &dyn GraphStorage {
+    pub(crate) fn get_gs_right_token(&self) -> &dyn GraphStorage {
         self.right_edges.as_ref()
     }
 
+    /// Returns `true` if the node is a token.
     pub fn is_token(&self, id: NodeID) -> bool {
         if self.node_annos.has_value_for_item(&id, &TOKEN_KEY) {
             // check if there is no outgoing edge in any of the coverage components
@@ -95,6 +127,7 @@ impl<'a> TokenHelper<'a> {
         }
     }
 
+    /// Returns `true` if the node covers any other node.
     pub fn has_outgoing_coverage_edges(&self, id: NodeID) -> bool {
         for c in self.cov_edges.iter() {
             if c.has_outgoing_edges(id) {
@@ -104,6 +137,7 @@ impl<'a> TokenHelper<'a> {
         false
     }
 
+    /// Get the right-most token covered by the node or the node itself if it is a token.
     pub fn right_token_for(&self, n: NodeID) -> Option<NodeID> {
         if self.is_token(n) {
             Some(n)
@@ -113,6 +147,7 @@ impl<'a> TokenHelper<'a> {
         }
     }
 
+    /// Get the left-most token covered by the node or the node itself if it is a token.
     pub fn left_token_for(&self, n: NodeID) -> Option<NodeID> {
         if self.is_token(n) {
             Some(n)
@@ -122,6 +157,7 @@ impl<'a> TokenHelper<'a> {
         }
     }
 
+    /// Get both the left-most and right-most token covered by the node.
     pub fn left_right_token_for(&self, n: NodeID) -> (Option<NodeID>, Option<NodeID>) {
         if self.is_token(n) {
             (Some(n), Some(n))
@@ -132,4 +168,193 @@ impl<'a> TokenHelper<'a> {
             (out_left.next(), out_right.next())
         }
     }
+
+    /// Get all tokens covered by the node in the order of the text or the node itself if it is a token.
+    ///
+    /// Tokens between the left-most and right-most covered token that are not covered by the node (e.g. for
+    /// discontinuous spans) are not included.
+    pub fn covered_token(&self, node: NodeID, gs_order: &dyn GraphStorage) -> Vec<NodeID> {
+        if self.is_token(node) {
+            return vec![node];
+        }
+        let covered: HashSet<NodeID> = self
+            .cov_edges
+            .iter()
+            .flat_map(|gs| gs.find_connected(node, 1, std::ops::Bound::Unbounded))
+            .collect();
+
+        let mut result = Vec::new();
+        if let (Some(mut current), Some(right_token)) = self.left_right_token_for(node) {
+            loop {
+                if covered.contains(&current) {
+                    result.push(current);
+                }
+                if current == right_token {
+                    break;
+                }
+                if let Some(next) = gs_order.get_outgoing_edges(current).next() {
+                    current = next;
+                } else {
+                    break;
+                }
+            }
+        }
+        result
+    }
+
+    /// Get the text covered by the node by joining the values of all tokens from the left-most to the right-most
+    /// covered token.
+    ///
+    /// - `node` - The node to get the covered text for.
+    /// - `gs_order` - The `Ordering` component that defines the sequence of the tokens.
+    /// - `whitespace` - How the text between two tokens should be reconstructed.
+    ///
+    /// Returns `None` if the node does not cover any token or the right-most token can't be reached from the left-most token.
+    pub fn covered_text(
+        &self,
+        node: NodeID,
+        gs_order: &dyn GraphStorage,
+        whitespace: WhitespaceHandling,
+    ) -> Option<String> {
+        let (left_token, right_token) = self.left_right_token_for(node);
+        self.text_between(left_token?, right_token?, gs_order, whitespace)
+    }
+
+    /// Get the text of all tokens from `left_token` to `right_token` (both included).
+    ///
+    /// Returns `None` if the `right_token` can't be reached from the `left_token`.
+    pub fn text_between(
+        &self,
+        left_token: NodeID,
+        right_token: NodeID,
+        gs_order: &dyn GraphStorage,
+        whitespace: WhitespaceHandling,
+    ) -> Option<String> {
+        let mut current = left_token;
+
+        let mut result = String::new();
+        loop {
+            if let Some(val) = self.node_annos.get_value_for_item(&current, &TOKEN_KEY) {
+                result.push_str(&val);
+            }
+            if current == right_token {
+                return Some(result);
+            }
+            let next = gs_order.get_outgoing_edges(current).next()?;
+            match whitespace {
+                WhitespaceHandling::Original => {
+                    let after = self
+                        .node_annos
+                        .get_value_for_item(&current, &TOK_WHITESPACE_AFTER_KEY);
+                    let before = self
+                        .node_annos
+                        .get_value_for_item(&next, &TOK_WHITESPACE_BEFORE_KEY);
+                    if after.is_none() && before.is_none() {
+                        result.push(' ');
+                    } else {
+                        result.push_str(after.as_deref().unwrap_or_default());
+                        result.push_str(before.as_deref().unwrap_or_default());
+                    }
+                }
+                WhitespaceHandling::SingleSpace => result.push(' '),
+            }
+            current = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annis::db::example_generator;
+    use graphannis_core::graph::update::{GraphUpdate, UpdateEvent};
+
+    #[test]
+    fn resolve_tokens_of_span() {
+        let mut u = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut u);
+        example_generator::create_tokens(&mut u, Some("root/doc1"));
+        u.add_event(UpdateEvent::AddNode {
+            node_name: "root/doc1#span".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        example_generator::make_span(
+            &mut u,
+            "root/doc1#span",
+            &["root/doc1#tok2", "root/doc1#tok3", "root/doc1#tok4"],
+        );
+
+        let mut g = AnnotationGraph::with_default_graphstorages(false).unwrap();
+        g.apply_update(&mut u, |_| {}).unwrap();
+
+        let span = g.get_node_id_from_name("root/doc1#span").unwrap();
+        let tok2 = g.get_node_id_from_name("root/doc1#tok2").unwrap();
+        let tok4 = g.get_node_id_from_name("root/doc1#tok4").unwrap();
+
+        let token_helper = TokenHelper::new(&g).unwrap();
+        assert!(!token_helper.is_token(span));
+        assert!(token_helper.is_token(tok2));
+        assert!(token_helper.has_outgoing_coverage_edges(span));
+        assert_eq!(Some(tok2), token_helper.left_token_for(span));
+        assert_eq!(Some(tok4), token_helper.right_token_for(span));
+        assert_eq!(
+            (Some(tok4), Some(tok4)),
+            token_helper.left_right_token_for(tok4)
+        );
+    }
+
+    #[test]
+    fn covered_text_with_whitespace() {
+        let mut u = GraphUpdate::new();
+        example_generator::create_corpus_structure_simple(&mut u);
+        example_generator::create_tokens(&mut u, Some("root/doc1"));
+        u.add_event(UpdateEvent::AddNode {
+            node_name: "root/doc1#span".to_string(),
+            node_type: "node".to_string(),
+        })
+        .unwrap();
+        example_generator::make_span(
+            &mut u,
+            "root/doc1#span",
+            &["root/doc1#tok8", "root/doc1#tok9", "root/doc1#tok10"],
+        );
+        // "to be?" without a space before the question mark
+        for (token, value) in [("root/doc1#tok8", "  "), ("root/doc1#tok9", "")].iter() {
+            u.add_event(UpdateEvent::AddNodeLabel {
+                node_name: token.to_string(),
+                anno_ns: ANNIS_NS.to_string(),
+                anno_name: "tok-whitespace-after".to_string(),
+                anno_value: value.to_string(),
+            })
+            .unwrap();
+        }
+
+        let mut g = AnnotationGraph::with_default_graphstorages(false).unwrap();
+        g.apply_update(&mut u, |_| {}).unwrap();
+
+        let span = g.get_node_id_from_name("root/doc1#span").unwrap();
+        let tok7 = g.get_node_id_from_name("root/doc1#tok7").unwrap();
+        let gs_order = g
+            .get_graphstorage_as_ref(&Component::new(
+                AnnotationComponentType::Ordering,
+                ANNIS_NS.into(),
+                "".into(),
+            ))
+            .unwrap();
+
+        let token_helper = TokenHelper::new(&g).unwrap();
+        assert_eq!(
+            Some("to  be?".to_string()),
+            token_helper.covered_text(span, gs_order, WhitespaceHandling::Original)
+        );
+        assert_eq!(
+            Some("to be ?".to_string()),
+            token_helper.covered_text(span, gs_order, WhitespaceHandling::SingleSpace)
+        );
+        assert_eq!(
+            Some("appears".to_string()),
+            token_helper.covered_text(tok7, gs_order, WhitespaceHandling::Original)
+        );
+    }
 }
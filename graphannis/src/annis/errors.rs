@@ -19,8 +19,12 @@ pub enum GraphAnnisError {
     AQLSemanticError(AQLError),
     #[error("impossible search expression detected: {0}")]
     ImpossibleSearch(String),
+    #[error("query is too complex: {0}")]
+    QueryTooComplex(String),
     #[error("timeout")]
     Timeout,
+    #[error("import has been cancelled")]
+    ImportCancelled,
     #[error("could not load graph {name} from disk")]
     LoadingGraphFailed { name: String },
     #[error("corpus {0} not found")]
@@ -51,6 +55,8 @@ pub enum GraphAnnisError {
     #[error(transparent)]
     RelAnnisImportError(#[from] RelAnnisError),
     #[error(transparent)]
+    ConllUImportError(#[from] ConllUError),
+    #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     TomlDeserializer(#[from] toml::de::Error),
@@ -63,7 +69,17 @@ pub enum GraphAnnisError {
     #[error(transparent)]
     Csv(#[from] csv::Error),
     #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
+    #[error(transparent)]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
 }
 
 #[derive(Error, Debug)]
@@ -106,8 +122,76 @@ pub enum CorpusStorageError {
         path: String,
         source: std::io::Error,
     },
+    #[error("corpus directory {path} is used by instance {instance_id} (host {hostname}, process {process_id}), which renewed its lease {seconds_since_heartbeat} seconds ago")]
+    LeaseHeld {
+        path: String,
+        instance_id: String,
+        hostname: String,
+        process_id: u32,
+        seconds_since_heartbeat: u64,
+    },
+    #[error("the lease for corpus directory {0} has been taken over by another instance")]
+    LeaseLost(String),
+    #[error("lease timeout {timeout:?} is shorter than the minimum of {minimum:?}")]
+    LeaseTimeoutTooShort {
+        timeout: std::time::Duration,
+        minimum: std::time::Duration,
+    },
     #[error("the corpus cache entry is not loaded")]
     CorpusCacheEntryNotLoaded,
+    #[error("corpus {corpus} is still used by {active_users} query or update operation(s)")]
+    CorpusInUse { corpus: String, active_users: usize },
+    #[error("node IDs of generation {given} are outdated, the current node ID generation of corpus {corpus} is {current}")]
+    OutdatedNodeIdGeneration {
+        corpus: String,
+        given: u64,
+        current: u64,
+    },
+    #[error("invalid node name template \"{0}\"")]
+    InvalidNodeNameTemplate(String),
+    #[error(
+        "the node name scheme creates the same name {node_name} for the nodes {first} and {second}"
+    )]
+    NodeNameCollision {
+        node_name: String,
+        first: String,
+        second: String,
+    },
+    #[error("the node name scheme creates the name {node_name} for node {old_name}, but it must start with \"{document}#\"")]
+    NodeNameOutsideDocument {
+        node_name: String,
+        old_name: String,
+        document: String,
+    },
+    #[error(
+        "invalid name \"{0}\" for a query of a batch, names must be unique and valid file names"
+    )]
+    InvalidBatchQueryName(String),
+    #[error("invalid meta data update for node {node_name}: {reason}")]
+    InvalidMetadataUpdate { node_name: String, reason: String },
+    #[error("invalid graph storage configuration for component {component}: {reason}")]
+    InvalidGraphStorageConfig { component: String, reason: String },
+    #[error("invalid find cursor: {0}")]
+    InvalidFindCursor(String),
+    #[error("query node {0} does not exist")]
+    UnknownQueryNode(String),
+    #[error("invalid sharding of corpus {corpus}: {reason}")]
+    InvalidSharding { corpus: String, reason: String },
+    #[error("invalid name {0} for a linked file, it must be a relative path inside the corpus")]
+    InvalidLinkedFileName(String),
+    #[error("can not export document {0}, its path must be relative and stay inside the export directory")]
+    InvalidDocumentExportPath(String),
+}
+
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ConllUError {
+    #[error("invalid line {line} in file {file}: {reason}")]
+    InvalidLine {
+        file: String,
+        line: usize,
+        reason: String,
+    },
 }
 
 #[derive(Error, Debug)]
@@ -145,6 +229,12 @@ pub enum RelAnnisError {
     NoRightPositionForNode(NodeID),
     #[error("invalid component type short name '{0}'")]
     InvalidComponentShortName(String),
+    #[error("node name {node_name} is used by the nodes with ID {first} and {second}")]
+    DuplicateNodeName {
+        node_name: String,
+        first: NodeID,
+        second: NodeID,
+    },
 }
 
 #[derive(Debug, Serialize, Clone)]
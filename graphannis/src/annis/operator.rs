@@ -1,7 +1,16 @@
 use super::db::aql::model::AnnotationComponentType;
-use crate::{annis::db::AnnotationStorage, graph::Match, AnnotationGraph};
-use graphannis_core::types::{Component, Edge};
+use crate::{
+    annis::db::AnnotationStorage,
+    graph::{GraphStorage, Match},
+    AnnotationGraph,
+};
+use graphannis_core::types::{Component, Edge, NodeID};
+use linked_hash_map::LinkedHashMap;
+use rustc_hash::FxHasher;
 use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
 
 #[derive(Clone, Debug, PartialOrd, Ord, Hash, PartialEq, Eq)]
 pub enum EdgeAnnoSearchSpec {
@@ -25,6 +34,10 @@ pub enum EdgeAnnoSearchSpec {
         name: String,
         val: String,
     },
+    /// An annotation with any name that has exactly the given value.
+    ExactValueAnyName { val: String },
+    /// An annotation with any name whose value matches the given regular expression.
+    RegexValueAnyName { val: String },
 }
 
 impl std::fmt::Display for EdgeAnnoSearchSpec {
@@ -86,11 +99,23 @@ impl std::fmt::Display for EdgeAnnoSearchSpec {
 
                 write!(f, "{}!=/{}/", qname, val)
             }
+            EdgeAnnoSearchSpec::ExactValueAnyName { ref val } => write!(f, "*=\"{}\"", val),
+            EdgeAnnoSearchSpec::RegexValueAnyName { ref val } => write!(f, "*=/{}/", val),
         }
     }
 }
 
 impl EdgeAnnoSearchSpec {
+    /// Returns the pattern if this searches for values matching a regular expression.
+    pub fn regex_pattern(&self) -> Option<&str> {
+        match self {
+            EdgeAnnoSearchSpec::RegexValue { ref val, .. }
+            | EdgeAnnoSearchSpec::NotRegexValue { ref val, .. }
+            | EdgeAnnoSearchSpec::RegexValueAnyName { ref val } => Some(val),
+            _ => None,
+        }
+    }
+
     pub fn guess_max_count(&self, anno_storage: &dyn AnnotationStorage<Edge>) -> usize {
         match self {
             EdgeAnnoSearchSpec::ExactValue {
@@ -130,6 +155,16 @@ impl EdgeAnnoSearchSpec {
                 total
                     - anno_storage.guess_max_count_regex(ns.as_ref().map(String::as_str), name, val)
             }
+            EdgeAnnoSearchSpec::ExactValueAnyName { ref val } => anno_storage
+                .annotation_keys()
+                .iter()
+                .map(|key| anno_storage.guess_max_count(Some(&key.ns), &key.name, val, val))
+                .sum(),
+            EdgeAnnoSearchSpec::RegexValueAnyName { ref val } => anno_storage
+                .annotation_keys()
+                .iter()
+                .map(|key| anno_storage.guess_max_count_regex(Some(&key.ns), &key.name, val))
+                .sum(),
         }
     }
 }
@@ -144,6 +179,14 @@ pub trait BinaryOperator: std::fmt::Display + Send + Sync {
 
     fn filter_match(&self, lhs: &Match, rhs: &Match) -> bool;
 
+    /// Check for each of the `rhs` candidates if the operator is true for the `lhs` match.
+    ///
+    /// The result has the same order as the candidates and is the same as calling
+    /// [filter_match(...)](#tymethod.filter_match) for each candidate.
+    fn filter_matches(&self, lhs: &Match, rhs: &[Match]) -> Vec<bool> {
+        rhs.iter().map(|m| self.filter_match(lhs, m)).collect()
+    }
+
     fn is_reflexive(&self) -> bool {
         true
     }
@@ -155,6 +198,12 @@ pub trait BinaryOperator: std::fmt::Display + Send + Sync {
         None
     }
 
+    /// Returns `true` if the operator is symmetric, so its inverse operator is the operator itself and switching
+    /// the operands does not change the result.
+    fn is_commutative(&self) -> bool {
+        false
+    }
+
     fn estimation_type(&self) -> EstimationType {
         EstimationType::SELECTIVITY(0.1)
     }
@@ -162,6 +211,159 @@ pub trait BinaryOperator: std::fmt::Display + Send + Sync {
     fn edge_anno_selectivity(&self) -> Option<f64> {
         None
     }
+
+    /// Returns `true` if the operator can only be true if both operands belong to the same document.
+    ///
+    /// For these operators, a merge join that only compares the matches of the same document can be used.
+    fn is_document_local(&self) -> bool {
+        false
+    }
+}
+
+/// Default number of entries a [`ReachabilityCache`] can hold before the least recently used ones are removed.
+pub const DEFAULT_REACHABILITY_CACHE_SIZE: usize = 100_000;
+
+/// Number of independently locked parts of a [`ReachabilityCache`].
+const REACHABILITY_CACHE_SHARDS: usize = 16;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct ReachabilityKey {
+    gs: usize,
+    source: NodeID,
+    target: NodeID,
+    min_distance: usize,
+    max_distance: Bound<usize>,
+}
+
+/// Memoizes the result of reachability checks on graph storages for the execution of a single query.
+///
+/// Queries with many constraints on the same node (e.g. `#1 > #2 & #1 > #3 & #1 > #4`) often check the
+/// same pair of nodes for the same component several times. The cache is shared by all operators of a query,
+/// which might be executed in parallel. To avoid contention, the entries are distributed over several shards
+/// that are locked independently. Each shard is bounded: when it is full, its least recently used entry is removed.
+pub struct ReachabilityCache {
+    shards: Vec<Mutex<LinkedHashMap<ReachabilityKey, bool>>>,
+    shard_capacity: usize,
+}
+
+impl Default for ReachabilityCache {
+    fn default() -> Self {
+        ReachabilityCache::with_capacity(DEFAULT_REACHABILITY_CACHE_SIZE)
+    }
+}
+
+impl ReachabilityCache {
+    pub fn with_capacity(capacity: usize) -> ReachabilityCache {
+        let number_of_shards = capacity.clamp(1, REACHABILITY_CACHE_SHARDS);
+        ReachabilityCache {
+            shards: (0..number_of_shards)
+                .map(|_| Mutex::new(LinkedHashMap::new()))
+                .collect(),
+            shard_capacity: capacity / number_of_shards,
+        }
+    }
+
+    fn shard(&self, key: &ReachabilityKey) -> &Mutex<LinkedHashMap<ReachabilityKey, bool>> {
+        let mut hasher = FxHasher::default();
+        key.hash(&mut hasher);
+        &self.shards[(hasher.finish() as usize) % self.shards.len()]
+    }
+
+    fn get(&self, key: &ReachabilityKey) -> Option<bool> {
+        let mut entries = self.shard(key).lock().ok()?;
+        entries.get_refresh(key).copied()
+    }
+
+    fn insert(&self, key: ReachabilityKey, connected: bool) {
+        if let Ok(mut entries) = self.shard(&key).lock() {
+            entries.insert(key, connected);
+            while entries.len() > self.shard_capacity {
+                entries.pop_front();
+            }
+        }
+    }
+
+    /// Same as [`GraphStorage::is_connected`], but returns the cached result if the same check has been
+    /// executed before on this graph storage.
+    pub fn is_connected(
+        &self,
+        gs: &Arc<dyn GraphStorage>,
+        source: NodeID,
+        target: NodeID,
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> bool {
+        if self.shard_capacity == 0 {
+            return gs.is_connected(source, target, min_distance, max_distance);
+        }
+        // The graph storages are kept alive by the operators during the query execution,
+        // so their address is a stable identifier.
+        let key = ReachabilityKey {
+            gs: Arc::as_ptr(gs) as *const () as usize,
+            source,
+            target,
+            min_distance,
+            max_distance,
+        };
+        if let Some(result) = self.get(&key) {
+            return result;
+        }
+        // Don't hold the lock while traversing the graph
+        let result = gs.is_connected(source, target, min_distance, max_distance);
+        self.insert(key, result);
+        result
+    }
+
+    /// Same as [`GraphStorage::are_connected`], but only checks the targets on the graph storage which have not
+    /// been checked before for the same source.
+    pub fn are_connected(
+        &self,
+        gs: &Arc<dyn GraphStorage>,
+        source: NodeID,
+        targets: &[NodeID],
+        min_distance: usize,
+        max_distance: Bound<usize>,
+    ) -> Vec<bool> {
+        if self.shard_capacity == 0 {
+            return gs.are_connected(source, targets, min_distance, max_distance);
+        }
+        let key = |target: NodeID| ReachabilityKey {
+            gs: Arc::as_ptr(gs) as *const () as usize,
+            source,
+            target,
+            min_distance,
+            max_distance,
+        };
+
+        let mut result: Vec<Option<bool>> = targets.iter().map(|t| self.get(&key(*t))).collect();
+        let (uncached_pos, uncached_targets): (Vec<usize>, Vec<NodeID>) = result
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_none())
+            .map(|(i, _)| (i, targets[i]))
+            .unzip();
+        if !uncached_targets.is_empty() {
+            // Don't hold the lock while traversing the graph
+            let connected = gs.are_connected(source, &uncached_targets, min_distance, max_distance);
+            for ((i, target), c) in uncached_pos
+                .into_iter()
+                .zip(uncached_targets)
+                .zip(connected)
+            {
+                result[i] = Some(c);
+                self.insert(key(target), c);
+            }
+        }
+        result.into_iter().map(|r| r.unwrap_or_default()).collect()
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| s.lock().map(|e| e.len()).unwrap_or_default())
+            .sum()
+    }
 }
 
 pub trait BinaryOperatorSpec: std::fmt::Debug {
@@ -172,6 +374,17 @@ pub trait BinaryOperatorSpec: std::fmt::Debug {
 
     fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn BinaryOperator + 'a>>;
 
+    /// Create an operator that can use the given cache, which is shared with the other operators of the query.
+    ///
+    /// Operators that don't benefit from the cache don't need to implement this and ignore the cache.
+    fn create_operator_with_cache<'a>(
+        &self,
+        db: &'a AnnotationGraph,
+        _cache: &Arc<ReachabilityCache>,
+    ) -> Option<Box<dyn BinaryOperator + 'a>> {
+        self.create_operator(db)
+    }
+
     fn get_edge_anno_spec(&self) -> Option<EdgeAnnoSearchSpec> {
         None
     }
@@ -179,6 +392,41 @@ pub trait BinaryOperatorSpec: std::fmt::Debug {
     fn is_binding(&self) -> bool {
         true
     }
+
+    /// Returns `true` if the operator can't retrieve the matches for its right-hand side and can only be used
+    /// to filter matches of operands that are already bound by other operators.
+    ///
+    /// These operators are applied after all other operators of a query.
+    fn is_filter_only(&self) -> bool {
+        false
+    }
+
+    /// Check if the operator can be applied to the given graph and return a description of the problem if not,
+    /// e.g. when a component referenced by the operator does not exist.
+    fn validate(&self, _db: &AnnotationGraph) -> std::result::Result<(), String> {
+        Ok(())
+    }
+
+    /// Return the names of the components the operator refers to, but which do not exist in the graph.
+    /// The operator can't have any result in this case.
+    fn missing_components(&self, _db: &AnnotationGraph) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Describe why the operator can never be fulfilled, e.g. because of an empty distance range.
+    fn contradiction(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns `true` if the operator requires both operands to be the same node.
+    fn is_identity(&self) -> bool {
+        false
+    }
+
+    /// Returns `true` if the operator has no upper bound for the distance between the operands, e.g. `>*`.
+    fn is_unbounded(&self) -> bool {
+        false
+    }
 }
 
 pub trait UnaryOperatorSpec: std::fmt::Debug {
@@ -187,7 +435,7 @@ pub trait UnaryOperatorSpec: std::fmt::Debug {
         db: &AnnotationGraph,
     ) -> HashSet<Component<AnnotationComponentType>>;
 
-    fn create_operator(&self, db: &AnnotationGraph) -> Option<Box<dyn UnaryOperator>>;
+    fn create_operator<'a>(&self, db: &'a AnnotationGraph) -> Option<Box<dyn UnaryOperator + 'a>>;
 }
 
 pub trait UnaryOperator: std::fmt::Display + Send + Sync {
@@ -197,3 +445,65 @@ pub trait UnaryOperator: std::fmt::Display + Send + Sync {
         EstimationType::SELECTIVITY(0.1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphannis_core::graph::storage::{
+        adjacencylist::AdjacencyListStorage, WriteableGraphStorage,
+    };
+
+    #[test]
+    fn reachability_cache_is_bounded() {
+        let mut gs = AdjacencyListStorage::new();
+        for i in 0..4 {
+            gs.add_edge(Edge {
+                source: i,
+                target: i + 1,
+            })
+            .unwrap();
+        }
+        let gs: Arc<dyn GraphStorage> = Arc::new(gs);
+
+        let cache = ReachabilityCache::with_capacity(2);
+        assert!(cache.is_connected(&gs, 0, 4, 1, Bound::Unbounded));
+        assert!(!cache.is_connected(&gs, 4, 0, 1, Bound::Unbounded));
+        assert!(cache.len() <= 2);
+        // same check, different distance
+        assert!(!cache.is_connected(&gs, 0, 4, 1, Bound::Included(2)));
+        assert!(cache.len() <= 2);
+        // repeated checks return the same result, regardless if they are still cached
+        assert!(!cache.is_connected(&gs, 0, 4, 1, Bound::Included(2)));
+        assert!(cache.is_connected(&gs, 0, 4, 1, Bound::Unbounded));
+        assert!(cache.len() <= 2);
+        for i in 0..4 {
+            assert!(cache.is_connected(&gs, i, i + 1, 1, Bound::Included(1)));
+        }
+        assert!(cache.len() <= 2);
+
+        let disabled = ReachabilityCache::with_capacity(0);
+        assert!(disabled.is_connected(&gs, 0, 1, 1, Bound::Included(1)));
+        assert_eq!(0, disabled.len());
+    }
+
+    #[test]
+    fn reachability_cache_batch() {
+        let mut gs = AdjacencyListStorage::new();
+        for i in 0..4 {
+            gs.add_edge(Edge {
+                source: i,
+                target: i + 1,
+            })
+            .unwrap();
+        }
+        let gs: Arc<dyn GraphStorage> = Arc::new(gs);
+
+        let cache = ReachabilityCache::with_capacity(1_000);
+        assert!(cache.is_connected(&gs, 0, 2, 1, Bound::Included(2)));
+        assert_eq!(
+            vec![true, true, false, false],
+            cache.are_connected(&gs, 0, &[1, 2, 3, 0], 1, Bound::Included(2))
+        );
+        assert_eq!(4, cache.len());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::annis::db::aql::model::AnnotationComponentType;
 use crate::corpusstorage::QueryLanguage;
+use graphannis_core::graph::storage::GraphStatistic;
+use graphannis_core::types::{AnnoKey, Annotation, Component};
 use std::collections::BTreeMap;
 
 /// A struct that contains the extended results of the count query.
@@ -11,6 +14,256 @@ pub struct CountExtra {
     pub document_count: u64,
 }
 
+/// A non-fatal issue of a query, which might explain why the results are incomplete or the query is slow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryWarning {
+    /// An alternative of the query could not be executed and does not contribute any results.
+    AlternativeIgnored { alternative: usize, reason: String },
+    /// A component needed by an operator does not exist or is not loaded and is treated as empty.
+    ComponentNotLoaded { component: String },
+    /// There are no statistics for a component needed by an operator, so the execution plan is based on rough estimates.
+    StatisticsMissing { component: String },
+    /// The operator of this query fragment is executed with a nested loop join, which compares all pairs of matches.
+    NestedLoopJoin { query_fragment: String },
+    /// The constraints of an alternative of the query contradict each other, so it is not executed.
+    Contradiction {
+        alternative: usize,
+        description: String,
+    },
+}
+
+impl std::fmt::Display for QueryWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            QueryWarning::AlternativeIgnored {
+                alternative,
+                reason,
+            } => write!(
+                f,
+                "alternative {} of the query is ignored: {}",
+                alternative, reason
+            ),
+            QueryWarning::ComponentNotLoaded { component } => write!(
+                f,
+                "component {} does not exist or is not loaded and is treated as empty",
+                component
+            ),
+            QueryWarning::StatisticsMissing { component } => write!(
+                f,
+                "no statistics available for component {}, the execution plan might be inefficient",
+                component
+            ),
+            QueryWarning::NestedLoopJoin { query_fragment } => write!(
+                f,
+                "{} is executed with a nested loop join, which can be slow",
+                query_fragment
+            ),
+            QueryWarning::Contradiction {
+                alternative,
+                description,
+            } => write!(
+                f,
+                "alternative {} of the query can't have any result: {}",
+                alternative, description
+            ),
+        }
+    }
+}
+
+/// The execution plan of a query for a single corpus, as returned by
+/// [`CorpusStorage::plan_structured`](crate::CorpusStorage::plan_structured).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryPlan {
+    pub corpus_name: String,
+    /// The execution plan of each alternative of the query or `None` if the alternative is not executed.
+    pub alternatives: Vec<Option<PlanNode>>,
+    /// Estimated number of results of all alternatives.
+    pub estimated_output_size: usize,
+}
+
+/// A node of the execution plan tree, e.g. a node search, a join or a filter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanNode {
+    /// The chosen implementation of this node, e.g. the join strategy like `indexjoin` or `nestedloop L-R`.
+    pub implementation: String,
+    /// The part of the query that is executed by this node.
+    pub query_fragment: String,
+    /// The number of the query nodes (starting with 1) that are part of the output of this node.
+    pub query_nodes: Vec<usize>,
+    /// The estimated costs or `None` if there are no estimations for this node.
+    pub cost: Option<PlanCost>,
+    /// The binary operator of a join or filter.
+    pub operator: Option<PlanOperator>,
+    /// The inputs of this node. For joins, the first input is the seed which is iterated over.
+    pub children: Vec<PlanNode>,
+}
+
+/// The cost estimations of a node of the execution plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanCost {
+    /// Estimated number of results of the node.
+    pub output: usize,
+    /// Estimated number of intermediate results processed by this node and all its inputs.
+    pub intermediate_sum: usize,
+    /// Estimated number of intermediate results processed by this node only.
+    pub processed_in_step: usize,
+}
+
+/// The binary operator of a join or filter and the estimations which were used to plan its execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanOperator {
+    /// Estimated selectivity of the operator or `None` if the output is estimated as the minimum of both inputs.
+    pub selectivity: Option<f64>,
+    /// Estimated selectivity of the edge annotation condition of the operator.
+    pub edge_anno_selectivity: Option<f64>,
+    /// Set if the operands of the operator are switched compared to the query.
+    pub operand_switch: Option<OperandSwitch>,
+    /// The components used by the operator.
+    pub components: Vec<PlanComponent>,
+}
+
+/// Describes how the planner changed the direction of a binary operator compared to the query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperandSwitch {
+    /// The operands are switched and the inverse operator is used, e.g. to use the smaller operand as seed.
+    Inverse,
+    /// The operands of a commutative operator are switched, which does not change the operator.
+    Commutative,
+}
+
+/// A component used by an operator of the execution plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanComponent {
+    pub component: Component<AnnotationComponentType>,
+    /// True if the graph storage for the component was loaded when the plan was created.
+    pub loaded: bool,
+    /// The statistics of the graph storage, if it was loaded and statistics are available.
+    pub statistics: Option<GraphStatistic>,
+}
+
+/// A rule of the ANNIS3 compatibility mode ([`QueryLanguage::AQLQuirksV3`]) that changes the semantics of a query
+/// compared to the normal AQL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum QuirksRule {
+    /// The unbound precedence operator `.*` is limited to a distance of at most 50 tokens.
+    PrecedenceLimited { location: Option<LineColumnRange> },
+    /// The unbound near-by operator `^*` is limited to a distance of at most 50 tokens.
+    NearLimited { location: Option<LineColumnRange> },
+    /// The node is the operand of several dominance or pointing relation operators and each of these
+    /// operators is applied to a separate copy of the node.
+    /// Thus, the node is only required to be different to the other operand of each single operator.
+    DistributedJoins { variable: String, joins: usize },
+    /// A legacy meta data search with `meta::` is applied to all alternatives of the query.
+    LegacyMetaSearch { location: Option<LineColumnRange> },
+    /// Operators only require their own operands to be different nodes, other nodes of the alternative
+    /// can still match the same node.
+    LocalReflexivity { alternative: usize },
+    /// Results are only sorted by the name of their document and not by the complete corpus path.
+    DocumentNameOrder,
+    /// Node names in match IDs are escaped in the same way as in ANNIS3.
+    MatchIdEscaping,
+}
+
+fn fmt_location(location: &Option<LineColumnRange>) -> String {
+    if let Some(location) = location {
+        format!(" at {}", location)
+    } else {
+        String::default()
+    }
+}
+
+impl std::fmt::Display for QuirksRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            QuirksRule::PrecedenceLimited { location } => write!(
+                f,
+                "unbound precedence operator{} is limited to a distance of 50",
+                fmt_location(location)
+            ),
+            QuirksRule::NearLimited { location } => write!(
+                f,
+                "unbound near-by operator{} is limited to a distance of 50",
+                fmt_location(location)
+            ),
+            QuirksRule::DistributedJoins { variable, joins } => write!(
+                f,
+                "the {} dominance or pointing relation operators of node #{} are applied to separate copies of the node",
+                joins, variable
+            ),
+            QuirksRule::LegacyMetaSearch { location } => write!(
+                f,
+                "legacy meta data search{} is applied to all alternatives",
+                fmt_location(location)
+            ),
+            QuirksRule::LocalReflexivity { alternative } => write!(
+                f,
+                "operators of alternative {} only require their own operands to be different nodes",
+                alternative
+            ),
+            QuirksRule::DocumentNameOrder => {
+                write!(f, "results are only sorted by the document name")
+            }
+            QuirksRule::MatchIdEscaping => {
+                write!(f, "node names in match IDs are escaped as in ANNIS3")
+            }
+        }
+    }
+}
+
+/// Position of a matched node relative to the text it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextOffset {
+    /// Index of the left-most token covered by the node, the first token of the text has the index 0.
+    pub left_token: usize,
+    /// Index of the right-most token covered by the node.
+    pub right_token: usize,
+}
+
+/// A match ID together with the position of each of its nodes in the text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchWithOffsets {
+    /// The match ID, as it is returned by the `find` function.
+    pub match_id: String,
+    /// The text offsets of the nodes in the same order as they appear in the match ID.
+    /// Nodes which do not cover any token (e.g. documents) have no offset.
+    pub offsets: Vec<Option<TextOffset>>,
+}
+
+/// A node of a match together with the information needed to highlight it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchedNode {
+    /// The name of the matched node.
+    pub node_name: String,
+    /// The annotation of the node that was matched by the query or `None` if the node itself was matched (e.g. by
+    /// `node` or `tok`).
+    pub annotation: Option<Annotation>,
+    /// Names of the tokens covered by the node in the order of the text. A token only covers itself.
+    pub covered_tokens: Vec<String>,
+}
+
+/// A completion for a partial annotation name or value, as returned by `CorpusStorage::suggest`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suggestion {
+    /// The qualified annotation name.
+    pub key: AnnoKey,
+    /// The suggested value or `None` if the annotation name itself is the suggestion.
+    pub value: Option<String>,
+    /// Number of nodes with this annotation (and value).
+    pub count: usize,
+}
+
+/// A distinct annotation value and how often it occurs, as returned by
+/// `CorpusStorage::search_node_annotation_values`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationValueCount {
+    /// The qualified annotation name.
+    pub key: AnnoKey,
+    /// The annotation value.
+    pub value: String,
+    /// Number of nodes with this annotation value.
+    pub count: usize,
+}
+
 /// Definition of the result of a `frequency` query.
 pub type FrequencyTable<T> = Vec<FrequencyTableRow<T>>;
 
@@ -23,6 +276,66 @@ pub struct FrequencyTableRow<T> {
     pub count: usize,
 }
 
+/// A single non-zero cell of a document-term matrix, as created by
+/// [`CorpusStorage::document_term_matrix`](crate::CorpusStorage::document_term_matrix).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentTermCount {
+    /// The path of the document, including the corpus name.
+    pub document: String,
+    /// The annotation value.
+    pub value: String,
+    /// Number of matches in this document where the query node has this annotation value.
+    pub count: usize,
+}
+
+/// Number of tokens, segments and annotations, either of a single document or aggregated over several documents.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentCounts {
+    /// Number of tokens.
+    pub tokens: usize,
+    /// Number of nodes for each segmentation, given by the name of its ordering component.
+    #[serde(default)]
+    pub segments: BTreeMap<String, usize>,
+    /// Number of nodes with an annotation for each qualified annotation name (e.g. `tiger::pos`).
+    /// Annotations of the internal `annis` namespace are not included.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, usize>,
+}
+
+impl DocumentCounts {
+    /// Add the counts of another document.
+    pub fn add(&mut self, other: &DocumentCounts) {
+        self.tokens += other.tokens;
+        for (name, count) in &other.segments {
+            *self.segments.entry(name.clone()).or_default() += count;
+        }
+        for (name, count) in &other.annotations {
+            *self.annotations.entry(name.clone()).or_default() += count;
+        }
+    }
+
+    /// Normalize an absolute frequency (e.g. a match count) to a rate per one million tokens.
+    ///
+    /// Returns `None` if there are no tokens.
+    pub fn per_million_tokens(&self, frequency: u64) -> Option<f64> {
+        if self.tokens == 0 {
+            None
+        } else {
+            Some(frequency as f64 * 1_000_000.0 / self.tokens as f64)
+        }
+    }
+}
+
+/// Pre-computed counts for a single document, as returned by
+/// [`CorpusStorage::document_statistics`](crate::CorpusStorage::document_statistics).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentStatistics {
+    /// The path of the document, including the corpus name.
+    pub document: String,
+    /// The counts for this document.
+    pub counts: DocumentCounts,
+}
+
 /// Description of an attribute of a query.
 #[derive(Serialize)]
 pub struct QueryAttributeDescription {
@@ -82,6 +395,124 @@ pub struct CorpusConfiguration {
     pub example_queries: Vec<ExampleQuery>,
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub visualizers: Vec<VisualizerRule>,
+    /// Components which are loaded together with the corpus, given in the format `type[/layer[/name]]`
+    /// (e.g. `Ordering` or `Coverage/annis`). All other components are only loaded when needed by a query.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub preload_components: Vec<String>,
+    /// Declares which meta data annotations are allowed on corpus and document nodes.
+    /// If not empty, [`CorpusStorage::apply_metadata_update`](crate::CorpusStorage::apply_metadata_update)
+    /// rejects all annotations that are not declared here.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metadata: Vec<MetadataDefinition>,
+    /// Graph storage implementations to use for specific components instead of choosing one by heuristic,
+    /// given as map from the component in the format `type[/layer[/name]]` to the name of the implementation
+    /// (e.g. `"Pointing/dep" = "AdjacencyListV1"`).
+    /// This is respected when importing and when re-optimizing the corpus.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub graph_storage: BTreeMap<String, String>,
+    /// The segmentations of the corpus and the annotations which hold the text of their segments.
+    /// Can be created from the corpus with [`CorpusStorage::infer_segmentations`](crate::CorpusStorage::infer_segmentations).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segmentations: Vec<SegmentationDefinition>,
+    /// Named operators of this corpus, which can be used in AQL queries as shorthand for a combination of
+    /// existing operators.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived_operators: Vec<DerivedOperatorDefinition>,
+}
+
+/// Definition of a named operator, which is expanded to one or more existing AQL binary operators when a query is
+/// parsed.
+///
+/// E.g. the definition `->dep[func!="punct"] & .1,10` for the name `governs` allows to write `#1 ~governs #2`
+/// instead of `#1 ->dep[func!="punct"] #2 & #1 .1,10 #2`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DerivedOperatorDefinition {
+    /// Name of the operator, which is used with the `~` prefix in queries.
+    pub name: String,
+    /// One or more AQL binary operators joined with `&`, which must all be true for the operands.
+    pub definition: String,
+    /// Describes the relation expressed by the operator for the users of the corpus.
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Definition of a segmentation layer of a corpus.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SegmentationDefinition {
+    /// Name of the segmentation, which is also the name of its `Ordering` component.
+    pub name: String,
+    /// Namespace of the annotation that holds the text of the segments.
+    #[serde(default)]
+    pub anno_ns: String,
+    /// Name of the annotation that holds the text of the segments.
+    pub anno_name: String,
+}
+
+/// Definition of a meta data annotation for corpus and document nodes.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct MetadataDefinition {
+    /// Namespace of the annotation. If not given, the annotation can have any namespace.
+    pub ns: Option<String>,
+    /// Name of the annotation.
+    pub name: String,
+    /// Type of the annotation value.
+    #[serde(default, rename = "type")]
+    pub value_type: MetadataValueType,
+    /// If not empty, the annotation value must be one of these values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+impl MetadataDefinition {
+    /// Returns `true` if this definition applies to the given annotation key.
+    pub fn matches_key(&self, key: &AnnoKey) -> bool {
+        self.name == key.name.as_str()
+            && self
+                .ns
+                .as_ref()
+                .map(|ns| ns == key.ns.as_str())
+                .unwrap_or(true)
+    }
+
+    /// Returns `true` if the value has the declared type and is one of the allowed values.
+    pub fn is_valid_value(&self, value: &str) -> bool {
+        self.value_type.is_valid_value(value)
+            && (self.values.is_empty() || self.values.iter().any(|v| v == value))
+    }
+}
+
+/// Type of a meta data annotation value.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub enum MetadataValueType {
+    /// Any text
+    #[serde(rename = "text")]
+    Text,
+    /// A signed integer number, e.g. `1997`
+    #[serde(rename = "integer")]
+    Integer,
+    /// A decimal number, e.g. `1.5`
+    #[serde(rename = "number")]
+    Number,
+    /// Either `true` or `false`
+    #[serde(rename = "boolean")]
+    Boolean,
+}
+
+impl Default for MetadataValueType {
+    fn default() -> Self {
+        MetadataValueType::Text
+    }
+}
+
+impl MetadataValueType {
+    pub fn is_valid_value(&self, value: &str) -> bool {
+        match self {
+            MetadataValueType::Text => true,
+            MetadataValueType::Integer => value.parse::<i64>().is_ok(),
+            MetadataValueType::Number => value.parse::<f64>().is_ok(),
+            MetadataValueType::Boolean => value == "true" || value == "false",
+        }
+    }
 }
 
 /// Configuration for configuring context in subgraph queries.
@@ -97,7 +97,7 @@ pub fn node_names_from_match(match_line: &str) -> Vec<String> {
     result
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct TimeoutCheck {
     start_time: Instant,
     timeout: Option<Duration>,
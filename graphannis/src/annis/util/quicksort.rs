@@ -1,17 +1,30 @@
+use super::TimeoutCheck;
+use crate::errors::Result;
 use rand::Rng;
+
+/// Number of comparisons in a partition loop after which the timeout is checked.
+const TIMEOUT_CHECK_INTERVAL: usize = 1_000;
+
 /// Make sure that the first `n` items of the complete vector are sorted by the given comparision function.
 ///
 /// This returns the original items and it is guaranteed that the items (0..n) are
 /// sorted and that all of these items are smaller or equal to the n-th item.
-pub fn sort_first_n_items<T, F>(items: &mut Vec<T>, n: usize, order_func: F)
+/// Returns a timeout error if the given `timeout` is exceeded while sorting.
+pub fn sort_first_n_items<T, F>(
+    items: &mut [T],
+    n: usize,
+    order_func: F,
+    timeout: &TimeoutCheck,
+) -> Result<()>
 where
     T: Send,
     F: Fn(&T, &T) -> std::cmp::Ordering + Sync,
 {
     let item_len = items.len();
     if item_len > 0 {
-        quicksort(items, n, &order_func);
+        quicksort(items, n, &order_func, timeout)?;
     }
+    Ok(())
 }
 
 /// Classic implementation of a quicksort algorithm, see Cormen et al. 2009 "Introduction to Algorithms" p. 170ff
@@ -21,35 +34,48 @@ where
 /// if at least `max_size` items at the beginning of the vector have been sorted.
 ///
 /// The algorithm used a randomized pivot element and is executed in parallel.
-fn quicksort<T, F>(items: &mut [T], max_size: usize, order_func: &F)
+fn quicksort<T, F>(
+    items: &mut [T],
+    max_size: usize,
+    order_func: &F,
+    timeout: &TimeoutCheck,
+) -> Result<()>
 where
     F: Fn(&T, &T) -> std::cmp::Ordering,
 {
     if items.len() > 1 {
-        let q = randomized_partition(items, order_func);
+        let q = randomized_partition(items, order_func, timeout)?;
         let (lo, hi) = items.split_at_mut(q);
 
-        quicksort(lo, max_size, order_func);
+        quicksort(lo, max_size, order_func, timeout)?;
         if q < max_size {
             // only sort right partition if the left partition is not large enough
-            quicksort(hi, max_size, order_func);
+            quicksort(hi, max_size, order_func, timeout)?;
         }
     }
+    Ok(())
 }
 
 /// Make sure that the first `n` items of the complete vector are sorted by the given comparision function.
 ///
 /// This returns the original items and it is guaranteed that the items (0..n) are
 /// sorted and that all of these items are smaller or equal to the n-th item.
-pub fn sort_first_n_items_parallel<T, F>(items: &mut Vec<T>, n: usize, order_func: F)
+/// Returns a timeout error if the given `timeout` is exceeded while sorting.
+pub fn sort_first_n_items_parallel<T, F>(
+    items: &mut [T],
+    n: usize,
+    order_func: F,
+    timeout: &TimeoutCheck,
+) -> Result<()>
 where
     T: Send,
     F: Fn(&T, &T) -> std::cmp::Ordering + Sync,
 {
     let item_len = items.len();
     if item_len > 0 {
-        quicksort_parallel(items, n, &order_func);
+        quicksort_parallel(items, n, &order_func, timeout)?;
     }
+    Ok(())
 }
 
 /// Classic implementation of a quicksort algorithm, see Cormen et al. 2009 "Introduction to Algorithms" p. 170ff
@@ -59,43 +85,57 @@ where
 /// if at least `max_size` items at the beginning of the vector have been sorted.
 ///
 /// The algorithm used a randomized pivot element and is executed in parallel.
-fn quicksort_parallel<T, F>(items: &mut [T], max_size: usize, order_func: &F)
+fn quicksort_parallel<T, F>(
+    items: &mut [T],
+    max_size: usize,
+    order_func: &F,
+    timeout: &TimeoutCheck,
+) -> Result<()>
 where
     T: Send,
     F: Fn(&T, &T) -> std::cmp::Ordering + Sync,
 {
     if items.len() > 1 {
-        let q = randomized_partition(items, order_func);
+        let q = randomized_partition(items, order_func, timeout)?;
         let (lo, hi) = items.split_at_mut(q);
 
-        rayon::join(
-            || quicksort_parallel(lo, max_size, order_func),
+        let (result_lo, result_hi) = rayon::join(
+            || quicksort_parallel(lo, max_size, order_func, timeout),
             || {
                 if q < max_size {
                     // only sort right partition if the left partition is not large enough
-                    quicksort_parallel(hi, max_size, order_func);
+                    quicksort_parallel(hi, max_size, order_func, timeout)
+                } else {
+                    Ok(())
                 }
             },
         );
+        result_lo?;
+        result_hi?;
     }
+    Ok(())
 }
 
-fn randomized_partition<T, F>(items: &mut [T], order_func: &F) -> usize
+fn randomized_partition<T, F>(
+    items: &mut [T],
+    order_func: &F,
+    timeout: &TimeoutCheck,
+) -> Result<usize>
 where
     F: Fn(&T, &T) -> std::cmp::Ordering,
 {
     let items_len = items.len();
     if items_len == 0 {
-        0
+        Ok(0)
     } else {
         let mut rng = rand::thread_rng();
         let i = rng.gen_range(0, items_len);
         items.swap(items_len - 1, i);
-        partition(items, order_func)
+        partition(items, order_func, timeout)
     }
 }
 
-fn partition<T, F>(items: &mut [T], order_func: &F) -> usize
+fn partition<T, F>(items: &mut [T], order_func: &F, timeout: &TimeoutCheck) -> Result<usize>
 where
     F: Fn(&T, &T) -> std::cmp::Ordering,
 {
@@ -104,6 +144,9 @@ where
     let mut i = 0;
 
     for j in 0..(items.len() - 1) {
+        if j % TIMEOUT_CHECK_INTERVAL == 0 {
+            timeout.check()?;
+        }
         let comparision = order_func(&items[j], &items[r]);
         match comparision {
             std::cmp::Ordering::Less | std::cmp::Ordering::Equal => {
@@ -116,76 +159,81 @@ where
 
     items.swap(i, r);
 
-    i
+    Ok(i)
 }
 
 #[cfg(test)]
 mod test {
 
+    use super::TimeoutCheck;
     use rand;
     use rand::distributions::Distribution;
     use rand::Rng;
 
     #[test]
     fn canary_sort_test() {
+        let no_timeout = TimeoutCheck::new(None);
         let mut items = vec![4, 10, 100, 4, 5];
         let num_items = items.len();
-        super::sort_first_n_items(&mut items, num_items, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, num_items, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![4, 4, 5, 10, 100], items);
 
         let mut items: Vec<usize> = vec![];
-        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         let empty_items: Vec<usize> = vec![];
         assert_eq!(empty_items, items);
 
         let mut items: Vec<usize> = vec![1];
-        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1], items);
 
         let mut items: Vec<usize> = vec![1, 2];
-        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2], items);
 
         let mut items: Vec<usize> = vec![2, 1];
-        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2], items);
 
         let mut items: Vec<usize> = vec![1, 2, 3, 4, 5];
-        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2, 3, 4, 5], items);
     }
 
     #[test]
     fn canary_sort_test_parallel() {
+        let no_timeout = TimeoutCheck::new(None);
         let mut items = vec![4, 10, 100, 4, 5];
         let num_items = items.len();
-        super::sort_first_n_items_parallel(&mut items, num_items, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, num_items, |x, y| x.cmp(y), &no_timeout)
+            .unwrap();
         assert_eq!(vec![4, 4, 5, 10, 100], items);
 
         let mut items: Vec<usize> = vec![];
-        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         let empty_items: Vec<usize> = vec![];
         assert_eq!(empty_items, items);
 
         let mut items: Vec<usize> = vec![1];
-        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1], items);
 
         let mut items: Vec<usize> = vec![1, 2];
-        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2], items);
 
         let mut items: Vec<usize> = vec![2, 1];
-        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2], items);
 
         let mut items: Vec<usize> = vec![1, 2, 3, 4, 5];
-        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y));
+        super::sort_first_n_items_parallel(&mut items, 0, |x, y| x.cmp(y), &no_timeout).unwrap();
         assert_eq!(vec![1, 2, 3, 4, 5], items);
     }
 
     #[test]
     fn random_sort_test() {
+        let no_timeout = TimeoutCheck::new(None);
         // compare 100 random arrays against the standard library sort
         let mut rng = rand::thread_rng();
         let random_item_gen = rand::distributions::Uniform::from(1..100);
@@ -200,13 +248,15 @@ mod test {
 
             let mut sorted_by_stdlib = items.clone();
             sorted_by_stdlib.sort();
-            super::sort_first_n_items(&mut items, items_size, |x, y| x.cmp(y));
+            super::sort_first_n_items(&mut items, items_size, |x, y| x.cmp(y), &no_timeout)
+                .unwrap();
             assert_eq!(items, sorted_by_stdlib);
         }
     }
 
     #[test]
     fn random_sort_test_parallel() {
+        let no_timeout = TimeoutCheck::new(None);
         // compare 100 random arrays against the standard library sort
         let mut rng = rand::thread_rng();
         let random_item_gen = rand::distributions::Uniform::from(1..100);
@@ -221,8 +271,35 @@ mod test {
 
             let mut sorted_by_stdlib = items.clone();
             sorted_by_stdlib.sort_unstable();
-            super::sort_first_n_items_parallel(&mut items, items_size, |x, y| x.cmp(y));
+            super::sort_first_n_items_parallel(
+                &mut items,
+                items_size,
+                |x, y| x.cmp(y),
+                &no_timeout,
+            )
+            .unwrap();
             assert_eq!(items, sorted_by_stdlib);
         }
     }
+
+    #[test]
+    fn sort_aborts_on_timeout() {
+        let timeout = TimeoutCheck::new(Some(std::time::Duration::from_millis(0)));
+        std::thread::sleep(std::time::Duration::from_millis(1));
+
+        let mut items: Vec<usize> = (0..10_000).rev().collect();
+        let num_items = items.len();
+        assert!(
+            super::sort_first_n_items(&mut items, num_items, |x, y| x.cmp(y), &timeout).is_err()
+        );
+
+        let mut items: Vec<usize> = (0..10_000).rev().collect();
+        assert!(super::sort_first_n_items_parallel(
+            &mut items,
+            num_items,
+            |x, y| x.cmp(y),
+            &timeout
+        )
+        .is_err());
+    }
 }
@@ -3,6 +3,8 @@
 
 // `error_chain!` can recurse deeply
 #![recursion_limit = "1024"]
+// Without the `CorpusStorage`, the importers and parts of the query execution are not used
+#![cfg_attr(not(feature = "corpus-storage"), allow(dead_code, unused_imports))]
 
 extern crate graphannis_malloc_size_of as malloc_size_of;
 #[macro_use]
@@ -29,18 +31,36 @@ static GLOBAL: System = System;
 
 mod annis;
 
+#[cfg(feature = "corpus-storage")]
 pub use crate::annis::db::corpusstorage::CorpusStorage;
+pub use crate::annis::db::single_graph::SingleGraphSearch;
 
 /// Types that are used by the `CorpusStorage` API.
 pub mod corpusstorage {
+    #[cfg(feature = "corpus-storage")]
+    pub use crate::annis::db::corpusstorage::PinnedCorpus;
     pub use crate::annis::db::corpusstorage::SearchQuery;
     pub use crate::annis::db::corpusstorage::{
-        CacheStrategy, CorpusInfo, ExportFormat, FrequencyDefEntry, GraphStorageInfo, ImportFormat,
-        LoadStatus, QueryLanguage, ResultOrder,
+        AnnotationSchema, BatchConfig, BatchMode, BatchOutputFormat, BatchQuery, BatchQueryResult,
+        CacheMetrics, CacheStrategy, ComponentSchema, CorpusCacheMetrics, CorpusInfo, CorpusSchema,
+        DerivedComponentsPolicy, DiskUsage, DuplicateNodeName, DuplicateNodeNamePolicy,
+        EvictionPolicy, ExportFormat, FindCursor, FindOptions, FrequencyDefEntry, GraphStorageInfo,
+        ImportAnalysis, ImportFormat, LoadStatus, LockingStrategy, MatchIdFormat, PendingImport,
+        QueryLanguage, ResultOrder, StatisticsRefresh,
     };
+    pub use crate::annis::db::node_name_scheme::{NodeNameScheme, NodeNameTemplate};
+    pub use crate::annis::db::query::{JoinThresholds, QueryLimits};
+    pub use crate::annis::db::token_helper::WhitespaceHandling;
     pub use crate::annis::types::{
-        CountExtra, FrequencyTable, FrequencyTableRow, QueryAttributeDescription,
+        AnnotationValueCount, CountExtra, DerivedOperatorDefinition, DocumentCounts,
+        DocumentStatistics, DocumentTermCount, FrequencyTable, FrequencyTableRow, MatchWithOffsets,
+        MatchedNode, MetadataDefinition, MetadataValueType, OperandSwitch, PlanComponent, PlanCost,
+        PlanNode, PlanOperator, QueryAttributeDescription, QueryPlan, QueryWarning, QuirksRule,
+        SegmentationDefinition, Suggestion, TextOffset,
     };
+    pub use graphannis_core::graph::{LoadReport, LoadedState, UpdateLogRecovery};
+    pub use graphannis_core::util::encryption::EncryptionKey;
+    pub use graphannis_core::util::UnicodeNormalization;
 }
 
 pub use graphannis_core::graph::update;
@@ -53,6 +73,7 @@ pub type AnnotationGraph =
 
 /// Types that are used by the `Graph` API.
 pub mod graph {
+    pub use graphannis_core::annostorage::AnnoKeyUsage;
     pub use graphannis_core::annostorage::AnnotationStorage;
     pub use graphannis_core::annostorage::Match;
     pub use graphannis_core::annostorage::MatchGroup;
@@ -61,6 +82,13 @@ pub mod graph {
     pub use graphannis_core::types::{AnnoKey, Annotation, Component, Edge, NodeID};
 }
 
+/// Helper functions to resolve the tokens of nodes in an [`AnnotationGraph`](type.AnnotationGraph.html).
+pub mod token_helper {
+    pub use crate::annis::db::token_helper::{
+        necessary_components, TokenHelper, WhitespaceHandling,
+    };
+}
+
 /// Types that define the annotation graph model.
 pub mod model {
     pub use crate::annis::db::aql::model::AnnotationComponentType;
@@ -68,11 +96,24 @@ pub mod model {
         graphannis_core::types::Component<crate::model::AnnotationComponentType>;
 }
 
+/// The syntax tree of AQL queries, e.g. for editor tooling.
+pub mod aql {
+    pub use crate::annis::db::aql::syntax::*;
+}
+
 /// Contains the graphANNIS-specific error types.
 pub mod errors {
     pub use crate::annis::errors::*;
 }
 
+/// Export of query results and token streams to Apache Arrow IPC or Parquet files.
+#[cfg(feature = "arrow")]
+pub mod arrow_export {
+    pub use crate::annis::db::arrow_export::{
+        write_frequency_table, write_matches, write_tokens, ArrowFormat,
+    };
+}
+
 /// Utility functions.
 pub mod util {
     pub use crate::annis::util::get_queries_from_csv;
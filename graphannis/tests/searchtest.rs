@@ -49,6 +49,7 @@ fn non_reflexivity_nodes() {
                         query: "node",
                         query_language: QueryLanguage::AQL,
                         timeout: None,
+                        document_filter: Vec::new(),
                     };
                     cs.count(query).unwrap_or(0)
                 };
@@ -66,6 +67,7 @@ fn non_reflexivity_nodes() {
                             query: "node {} node",
                             query_language: QueryLanguage::AQL,
                             timeout: None,
+                            document_filter: Vec::new(),
                         };
                         cs.count(query).unwrap_or(0)
                     };
@@ -99,6 +101,7 @@ fn non_reflexivity_tokens() {
                         query: "tok",
                         query_language: QueryLanguage::AQL,
                         timeout: None,
+                        document_filter: Vec::new(),
                     };
                     cs.count(query).unwrap_or(0)
                 };
@@ -115,6 +118,7 @@ fn non_reflexivity_tokens() {
                             query: &format!("tok {} tok", o),
                             query_language: QueryLanguage::AQL,
                             timeout: None,
+                            document_filter: Vec::new(),
                         };
                         cs.count(query).unwrap_or(0)
                     };
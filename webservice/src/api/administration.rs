@@ -141,6 +141,7 @@ pub async fn import_corpus(
             tmp,
             settings.database.disk_based,
             params.override_existing,
+            None,
             |status| {
                 info!("Job {} update: {}", &id_as_string, status);
                 // Add status report to background job messages
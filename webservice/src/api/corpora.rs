@@ -2,10 +2,14 @@ use super::{check_corpora_authorized, check_is_admin};
 use crate::{
     actions, errors::ServiceError, extractors::ClaimsFromAuth, settings::Settings, DbPool,
 };
-use actix_files::NamedFile;
-use actix_web::web::{self, HttpResponse};
+use actix_files::{file_extension_to_mime, NamedFile};
+use actix_web::{
+    web::{self, HttpResponse},
+    Either,
+};
 use graphannis::{
-    corpusstorage::QueryLanguage, graph, model::AnnotationComponentType, CorpusStorage,
+    corpusstorage::QueryLanguage, errors::GraphAnnisError, graph, model::AnnotationComponentType,
+    CorpusStorage,
 };
 use std::path::PathBuf;
 
@@ -115,6 +119,19 @@ pub async fn configuration(
     Ok(HttpResponse::Ok().json(corpus_info.config))
 }
 
+pub async fn schema(
+    corpus: web::Path<String>,
+    cs: web::Data<CorpusStorage>,
+    claims: ClaimsFromAuth,
+    db_pool: web::Data<DbPool>,
+) -> Result<HttpResponse, ServiceError> {
+    check_corpora_authorized(vec![corpus.clone()], claims.0, &db_pool).await?;
+
+    let schema = cs.schema(corpus.as_str())?;
+
+    Ok(HttpResponse::Ok().json(schema))
+}
+
 #[derive(Deserialize, Clone)]
 pub struct ListComponentsParameters {
     #[serde(rename = "type")]
@@ -184,6 +201,61 @@ pub async fn node_annotations(
 
     Ok(HttpResponse::Ok().json(annos))
 }
+
+#[derive(Deserialize)]
+pub struct AnnotationValuesParameters {
+    ns: Option<String>,
+    name: String,
+    pattern: String,
+    limit: Option<usize>,
+}
+
+pub async fn node_annotation_values(
+    corpus: web::Path<String>,
+    params: web::Query<AnnotationValuesParameters>,
+    cs: web::Data<CorpusStorage>,
+    claims: ClaimsFromAuth,
+    db_pool: web::Data<DbPool>,
+) -> Result<HttpResponse, ServiceError> {
+    check_corpora_authorized(vec![corpus.clone()], claims.0, &db_pool).await?;
+
+    let values = cs.search_node_annotation_values(
+        corpus.as_str(),
+        params.ns.as_deref(),
+        &params.name,
+        &params.pattern,
+        params.limit,
+    )?;
+
+    Ok(HttpResponse::Ok().json(values))
+}
+
+#[derive(Deserialize)]
+pub struct MetadataParameters {
+    document: Option<String>,
+}
+
+pub async fn metadata(
+    corpus: web::Path<String>,
+    params: web::Query<MetadataParameters>,
+    cs: web::Data<CorpusStorage>,
+    claims: ClaimsFromAuth,
+    db_pool: web::Data<DbPool>,
+) -> Result<HttpResponse, ServiceError> {
+    check_corpora_authorized(vec![corpus.clone()], claims.0, &db_pool).await?;
+
+    let annos = if let Some(document) = &params.document {
+        cs.list_document_metadata(corpus.as_str(), document)
+    } else {
+        cs.list_corpus_metadata(corpus.as_str())
+    };
+    match annos {
+        Ok(annos) => Ok(HttpResponse::Ok().json(annos)),
+        Err(GraphAnnisError::NoSuchNodeID(_)) => Err(ServiceError::NotFound),
+        Err(e) => Err(e.into()),
+    }
+}
+
 pub async fn edge_annotations(
     path: web::Path<(String, AnnotationComponentType, String, String)>,
     params: web::Query<AnnotationParameters>,
@@ -269,9 +341,10 @@ pub async fn list_files(
 pub async fn file_content(
     web::Path((corpus, name)): web::Path<(String, String)>,
     claims: ClaimsFromAuth,
+    cs: web::Data<CorpusStorage>,
     db_pool: web::Data<DbPool>,
     settings: web::Data<Settings>,
-) -> Result<NamedFile, ServiceError> {
+) -> Result<Either<NamedFile, HttpResponse>, ServiceError> {
     let name = percent_encoding::percent_decode_str(&name).decode_utf8_lossy();
 
     check_corpora_authorized(vec![corpus.clone()], claims.0, &db_pool).await?;
@@ -294,7 +367,24 @@ pub async fn file_content(
         .join("files")
         .join(&file_path);
 
-    Ok(NamedFile::open(path)?)
+    if settings.database.encryption_key.is_some() {
+        // Encrypted files must be decrypted by the corpus storage and can't be streamed from disk
+        if !path.is_file() {
+            return Err(ServiceError::NotFound);
+        }
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = cs.linked_file_content(&corpus, file_path)?;
+        Ok(Either::B(
+            HttpResponse::Ok()
+                .content_type(file_extension_to_mime(&ext).to_string())
+                .body(content),
+        ))
+    } else {
+        Ok(Either::A(NamedFile::open(path)?))
+    }
 }
 pub async fn delete(
     path: web::Path<String>,
@@ -5,7 +5,7 @@ use crate::{errors::ServiceError, extractors::ClaimsFromAuth, settings::Settings
 use actix_web::web::{self, Bytes, HttpResponse};
 use futures::stream::iter;
 use graphannis::{
-    corpusstorage::{FrequencyDefEntry, QueryLanguage, ResultOrder, SearchQuery},
+    corpusstorage::{FindOptions, FrequencyDefEntry, QueryLanguage, ResultOrder, SearchQuery},
     CorpusStorage,
 };
 use serde::Deserialize;
@@ -31,6 +31,7 @@ pub async fn count(
         query: &params.query,
         query_language: params.query_language,
         timeout: settings.database.query_timeout.map(Duration::from_secs),
+        document_filter: Vec::new(),
     };
     let count = cs.count_extra(query)?;
     Ok(HttpResponse::Ok().json(count))
@@ -63,6 +64,10 @@ pub struct FindQuery {
     offset: usize,
     #[serde(default)]
     order: ResultOrder,
+    #[serde(default)]
+    projection: Option<Vec<String>>,
+    #[serde(default)]
+    with_offsets: bool,
 }
 
 pub async fn find(
@@ -78,8 +83,22 @@ pub async fn find(
         query: &params.query,
         query_language: params.query_language,
         timeout: settings.database.query_timeout.map(Duration::from_secs),
+        document_filter: Vec::new(),
     };
-    let matches = cs.find(query, params.offset, params.limit, params.order)?;
+    let options = FindOptions {
+        offset: params.offset,
+        limit: params.limit,
+        order: params.order,
+        projection: params.projection.clone(),
+        with_offsets: params.with_offsets,
+        ..Default::default()
+    };
+    let (matches, _) = cs.find_with_options(query, options)?;
+    if params.with_offsets {
+        return Ok(HttpResponse::Ok().json(matches));
+    }
+
+    let matches: Vec<String> = matches.into_iter().map(|m| m.match_id).collect();
 
     let body = iter(
         matches
@@ -116,6 +135,7 @@ pub async fn frequency(
         query: &params.query,
         query_language: params.query_language,
         timeout: settings.database.query_timeout.map(Duration::from_secs),
+        document_filter: Vec::new(),
     };
     let result = cs.frequency(query, params.definition.clone())?;
 
@@ -31,8 +31,10 @@ enum BadRequestError {
     AQLSyntaxError(AQLError),
     AQLSemanticError(AQLError),
     ImpossibleSearch(String),
+    QueryTooComplex(String),
     UUID(String),
     IllegalNodePath(String),
+    InvalidRegex(String),
 }
 
 impl ResponseError for ServiceError {
@@ -63,6 +65,11 @@ impl ResponseError for ServiceError {
                     .json(BadRequestError::AQLSyntaxError(aql_error.clone())),
                 GraphAnnisError::ImpossibleSearch(aql_error) => HttpResponse::BadRequest()
                     .json(BadRequestError::ImpossibleSearch(aql_error.clone())),
+                GraphAnnisError::QueryTooComplex(reason) => HttpResponse::BadRequest()
+                    .json(BadRequestError::QueryTooComplex(reason.clone())),
+                GraphAnnisError::Core(GraphAnnisCoreError::Regex(e)) => {
+                    HttpResponse::BadRequest().json(BadRequestError::InvalidRegex(e.to_string()))
+                }
                 _ => HttpResponse::InternalServerError().json(err.to_string()),
             },
             ServiceError::NotFound => HttpResponse::NotFound().finish(),
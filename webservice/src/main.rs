@@ -18,10 +18,13 @@ use api::administration;
 use clap::Arg;
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
+use graphannis::corpusstorage::{EncryptionKey, QueryLimits};
 use simplelog::{LevelFilter, SimpleLogger, TermLogger};
 use std::{
     io::{Error, ErrorKind, Result},
     path::PathBuf,
+    sync::Arc,
+    time::Duration,
 };
 
 mod actions;
@@ -85,11 +88,31 @@ fn init_app() -> anyhow::Result<(graphannis::CorpusStorage, settings::Settings,
 
     // Create a graphANNIS corpus storage as shared state
     let data_dir = std::path::PathBuf::from(&settings.database.graphannis);
-    let cs = graphannis::CorpusStorage::with_cache_strategy(
+    let mut cs = graphannis::CorpusStorage::with_cache_strategy(
         &data_dir,
         settings.database.cache.clone(),
         true,
     )?;
+    cs.set_eviction_policy(settings.database.eviction_policy);
+    if let Some(worker_threads) = settings.database.worker_threads {
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(worker_threads)
+            .thread_name(|idx| format!("graphannis-worker-{}", idx))
+            .build()?;
+        cs.set_thread_pool(Some(Arc::new(thread_pool)));
+    }
+    cs.set_progress_interval(Duration::from_millis(settings.database.progress_interval));
+    cs.set_query_limits(QueryLimits {
+        max_nodes: settings.database.max_query_nodes,
+        max_alternatives: settings.database.max_query_alternatives,
+        max_unbounded_operators: settings.database.max_unbounded_operators,
+    });
+    if let Some(encryption_key) = &settings.database.encryption_key {
+        cs.set_encryption_key(Some(
+            EncryptionKey::from_hex(encryption_key)?
+                .allow_unencrypted(settings.database.allow_unencrypted),
+        ));
+    }
 
     // Add a connection pool to the SQLite database
 
@@ -140,6 +163,9 @@ async fn main() -> Result<()> {
 
     let api_version = format!("/v{}", env!("CARGO_PKG_VERSION_MAJOR"),);
 
+    // Keep a reference to the corpus storage to shut it down when the server has stopped
+    let shutdown_cs = cs.clone();
+
     // Run server
     HttpServer::new(move || {
         let logger = if settings.logging.debug {
@@ -192,10 +218,16 @@ async fn main() -> Result<()> {
                                 "/{corpus}/configuration",
                                 web::get().to(api::corpora::configuration),
                             )
+                            .route("/{corpus}/schema", web::get().to(api::corpora::schema))
                             .route(
                                 "/{corpus}/node-annotations",
                                 web::get().to(api::corpora::node_annotations),
                             )
+                            .route("/{corpus}/metadata", web::get().to(api::corpora::metadata))
+                            .route(
+                                "/{corpus}/node-annotation-values",
+                                web::get().to(api::corpora::node_annotation_values),
+                            )
                             .route(
                                 "/{corpus}/components",
                                 web::get().to(api::corpora::list_components),
@@ -225,5 +257,9 @@ async fn main() -> Result<()> {
     })
     .bind(bind_address)?
     .run()
-    .await
+    .await?;
+
+    // make sure all changes are written to disk before the service exits
+    shutdown_cs.shutdown();
+    Ok(())
 }
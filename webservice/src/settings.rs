@@ -1,6 +1,6 @@
 use anyhow::Result;
 use config::ConfigError;
-use graphannis::corpusstorage::CacheStrategy;
+use graphannis::corpusstorage::{CacheStrategy, EvictionPolicy};
 use jsonwebtoken::DecodingKey;
 use std::ops::Deref;
 
@@ -22,8 +22,30 @@ pub struct Database {
     pub disk_based: bool,
     #[serde(default)]
     pub cache: CacheStrategy,
+    #[serde(default)]
+    pub eviction_policy: EvictionPolicy,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub query_timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_query_nodes: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_query_alternatives: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unbounded_operators: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worker_threads: Option<usize>,
+    #[serde(default = "default_progress_interval")]
+    pub progress_interval: u64,
+    /// Hexadecimal representation of the key which is used to encrypt the corpus data on disk.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption_key: Option<String>,
+    /// Allow reading unencrypted files with the encryption key, which is needed to migrate existing corpora.
+    #[serde(default)]
+    pub allow_unencrypted: bool,
+}
+
+fn default_progress_interval() -> u64 {
+    500
 }
 
 #[derive(Debug, Deserialize)]